@@ -80,12 +80,12 @@ async fn main() {
             .expect("Failed to parse json-rpc alike payload on a single line");
 
         let seq = sequencer
-            .call(args.request.clone().into(), args.block_hash)
+            .call(args.request.clone().into(), args.block_hash.into())
             .map_ok(|x| x.result)
             .map_err(Error::from);
 
         let local = handle
-            .call(args.request, args.block_hash)
+            .call(args.request, args.block_hash.into(), None)
             .map_err(Error::from);
 
         let (local, seq) = tokio::join!(local, seq);
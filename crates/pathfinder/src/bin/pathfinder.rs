@@ -20,6 +20,20 @@ async fn healthz(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
     Ok(Response::new(Body::from("ok")))
 }
 
+/// Serves the RPC metrics on `/metrics`, and the health check on any other path.
+async fn health_and_metrics(
+    req: Request<Body>,
+    fallbacks: Arc<rpc::metrics::SequencerFallbacks>,
+    node_cache: Arc<state::node_cache::NodeCache>,
+) -> Result<Response<Body>, Infallible> {
+    match req.uri().path() {
+        "/metrics" => Ok(Response::new(Body::from(
+            fallbacks.render() + &node_cache.render(),
+        ))),
+        _ => healthz(req).await,
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     if std::env::var_os("RUST_LOG").is_none() {
@@ -43,17 +57,45 @@ async fn main() -> anyhow::Result<()> {
         .chain()
         .await
         .context("Determining Ethereum chain")?;
+    let network_chain =
+        ethereum::Chain::verify(config.chain, network_chain).context("Verifying Ethereum chain")?;
 
     let database_path = config.data_directory.join(match network_chain {
         ethereum::Chain::Mainnet => "mainnet.sqlite",
         ethereum::Chain::Goerli => "goerli.sqlite",
     });
-    let storage = Storage::migrate(database_path.clone()).unwrap();
+    let storage = Storage::migrate(database_path.clone())
+        .with_context(|| format!("Opening database at {}", database_path.display()))?;
     info!(location=?database_path, "Database migrated.");
 
-    let sequencer = sequencer::Client::new(network_chain).unwrap();
+    if config.check_database_integrity {
+        info!("Checking database integrity, this may take a while.");
+        let problems = storage
+            .integrity_check()
+            .context("Checking database integrity")?;
+        for problem in &problems {
+            tracing::error!(%problem, "Database integrity problem.");
+        }
+        anyhow::ensure!(
+            problems.is_empty(),
+            "Database integrity check found {} problem(s)",
+            problems.len()
+        );
+        info!("Database integrity check passed.");
+    }
+
+    if let Some(options) = config.optimize_database {
+        info!(?options, "Optimizing database.");
+        storage.optimize(options).context("Optimizing database")?;
+        info!("Database optimized.");
+    }
+
+    let sequencer = sequencer::Client::with_timeout(network_chain, config.sequencer_timeout)
+        .context("Creating sequencer client")?
+        .with_max_in_flight(config.sequencer_max_in_flight);
     let sync_state = Arc::new(state::SyncState::default());
 
+    let retain_raw_blocks = config.retain_raw_blocks;
     let sync_handle = tokio::spawn(state::sync(
         storage.clone(),
         eth_transport,
@@ -61,9 +103,16 @@ async fn main() -> anyhow::Result<()> {
         sequencer.clone(),
         sync_state.clone(),
         state::l1::sync,
-        state::l2::sync,
+        move |tx_event, sequencer, head, chain| {
+            state::l2::sync(tx_event, sequencer, head, chain, retain_raw_blocks)
+        },
     ));
 
+    cairo::ext_py::self_test()
+        .await
+        .context("Running the call self-test. Have you setup our Python dependencies?")?;
+    info!("Call self-test passed.");
+
     // TODO: the error could be recovered, but currently it's required for startup. There should
     // not be other reason for the start to fail than python script not firing up.
     let (call_handle, cairo_handle) = cairo::ext_py::start(
@@ -76,25 +125,73 @@ async fn main() -> anyhow::Result<()> {
         "Creating python process for call handling. Have you setup our Python dependencies?",
     )?;
 
-    let api = rpc::api::RpcApi::new(storage, sequencer, network_chain, sync_state)
-        .with_call_handling(call_handle);
+    let api = rpc::api::RpcApi::new(
+        storage.clone(),
+        sequencer,
+        network_chain,
+        sync_state.clone(),
+    )
+    .with_call_handling(call_handle)
+    .with_sequencer_code_fallback(config.sequencer_code_fallback)
+    .with_partial_blocks(config.partial_block_responses)
+    .with_pending_disabled(config.disable_pending)
+    .with_event_query_log_threshold(config.event_query_log_threshold)
+    .with_events_cache(config.events_cache_ttl, config.events_cache_capacity)
+    .with_default_event_page_size(config.events_page_size)
+    .with_max_concurrent_event_queries(config.events_max_concurrent_queries)
+    .with_event_soft_deadline(config.events_soft_deadline)
+    .with_raw_blocks_retained(config.retain_raw_blocks);
+
+    let ws_rpc_handle = match config.ws_rpc_addr {
+        Some(addr) => {
+            let (handle, local_addr) =
+                rpc::run_ws_server(addr, storage, sync_state, api.max_event_filter_keys())
+                    .await
+                    .context("Starting the WebSocket-RPC server")?;
+            info!("📡 WebSocket-RPC server started on: {}", local_addr);
+            Some(handle)
+        }
+        None => None,
+    };
+
+    let sequencer_fallbacks = api.sequencer_fallbacks();
+    let node_cache = api.node_cache();
 
+    let listener = rpc::listener::ListenerConfig {
+        max_connections: config.http_rpc_max_connections,
+        max_concurrent_calls: config.http_rpc_max_concurrent_calls,
+        backlog: config.http_rpc_backlog,
+        gzip: config
+            .http_rpc_gzip
+            .then(rpc::compression::GzipConfig::default),
+    };
+    let methods = rpc::MethodFilter {
+        allowed: config.http_rpc_allowed_methods,
+        denied: config.http_rpc_denied_methods,
+    };
     let (rpc_handle, local_addr) =
-        rpc::run_server(config.http_rpc_addr, api).context("Starting the RPC server")?;
+        rpc::run_server_with(config.http_rpc_addr, api, listener, methods)
+            .context("Starting the RPC server")?;
     info!("📡 HTTP-RPC server started on: {}", local_addr);
 
     let addr2 = SocketAddr::from(([0, 0, 0, 0], 8080));
 
-    let make_svc = make_service_fn(|_conn| {
+    let make_svc = make_service_fn(move |_conn| {
+        let fallbacks = sequencer_fallbacks.clone();
+        let node_cache = node_cache.clone();
         // This is the `Service` that will handle the connection.
         // `service_fn` is a helper to convert a function that
         // returns a Response into a `Service`.
-        async { Ok::<_, Infallible>(service_fn(healthz)) }
+        async {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                health_and_metrics(req, fallbacks.clone(), node_cache.clone())
+            }))
+        }
     });
 
     let srv2 = Server::bind(&addr2).serve(make_svc);
 
-    info!("📡 Health server started on: {}", addr2);
+    info!("📡 Health and metrics server started on: {}", addr2);
 
     tokio::spawn(srv2);
 
@@ -118,6 +215,14 @@ async fn main() -> anyhow::Result<()> {
             // This handle returns () so its not very useful.
             tracing::error!("RPC server process ended unexpected");
         }
+        _result = async {
+            match ws_rpc_handle {
+                Some(handle) => handle.await,
+                None => std::future::pending().await,
+            }
+        } => {
+            tracing::error!("WebSocket-RPC server process ended unexpected");
+        }
         result = update_handle => {
             match result {
                 Ok(_) => tracing::error!("Release monitoring process ended unexpectedly"),
@@ -6,17 +6,19 @@
 //! should not cause issues in WAL mode.
 //!
 //! Use of the call functionality happens through [`Handle::call`], which hands out futures in
-//! exchange for [`Call`] and [`BlockHashOrTag`], former selects the contract and method to call,
-//! latter selectes "when" to call it on the history. None of the block or tags are resolved over
+//! exchange for [`Call`] and [`BlockId`], former selects the contract and method to call,
+//! latter selectes "when" to call it on the history. [`Handle::simulate_calls`] executes many
+//! calls in order on the same state, and [`Handle::trace_call`] executes a call as an invoke
+//! transaction and returns its call tree. None of the block or tags are resolved over
 //! at rust side, because transactions cannot carry over between processes.
 //!
-//! While the python script does attempt to resolve "latest", it probably needs fixing. To make it
-//! support "pending", a feature needs to be added which flushes the "open" pending to a
-//! global_state, and after that, calls can be made to it's `block_hash` for which we probably need
-//! to add an alternative way to use a hash directly rather as a root than assume it's a block hash.
+//! While the python script does attempt to resolve "latest", it probably needs fixing. It does not
+//! support "pending" at all, instead calls on the pending block are executed on "latest" with the
+//! pending block's [`StateDiff`] applied on top of it.
 
-use crate::core::CallResultValue;
-use crate::rpc::types::{request::Call, BlockHashOrTag};
+use crate::core::{CallResultValue, TransactionSignatureElem};
+use crate::rpc::types::{reply::CallTrace, request::Call, BlockId};
+use crate::sequencer::reply::state_update::StateDiff;
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot, Mutex};
 
@@ -29,6 +31,9 @@ mod sub_process;
 mod service;
 pub use service::start;
 
+mod self_test;
+pub use self_test::self_test;
+
 /// Handle to the python executors work queue. Cloneable and shareable.
 #[derive(Clone)]
 pub struct Handle {
@@ -37,18 +42,75 @@ pub struct Handle {
 
 impl Handle {
     /// Execute the given call on the python cairo-lang executors.
+    ///
+    /// If given, `pending_state_diff` is applied on top of the state at `at_block` before the
+    /// call is executed.
     pub async fn call(
         &self,
         call: Call,
-        at_block: BlockHashOrTag,
+        at_block: BlockId,
+        pending_state_diff: Option<StateDiff>,
     ) -> Result<Vec<CallResultValue>, CallFailure> {
+        let mut outputs = self
+            .simulate_calls(vec![call], at_block, pending_state_diff)
+            .await?;
+
+        outputs
+            .pop()
+            .ok_or(CallFailure::Internal("Missing call output"))
+    }
+
+    /// Execute the given calls in order on the python cairo-lang executors. Each call sees the
+    /// state changes made by the calls before it, but the changes are not persisted.
+    ///
+    /// Returns the output of each call. If a call fails, the rest are not executed and
+    /// [`CallFailure::ExecutionFailed`] carries the index of the failed call.
+    pub async fn simulate_calls(
+        &self,
+        calls: Vec<Call>,
+        at_block: BlockId,
+        pending_state_diff: Option<StateDiff>,
+    ) -> Result<Vec<Vec<CallResultValue>>, CallFailure> {
+        self.execute(calls, at_block, pending_state_diff, None)
+            .await
+            .map(|(outputs, _)| outputs)
+    }
+
+    /// Execute the given call as an invoke transaction with the given `signature` on the python
+    /// cairo-lang executors, and return the trace of the call and the calls it made in turn.
+    pub async fn trace_call(
+        &self,
+        call: Call,
+        signature: Vec<TransactionSignatureElem>,
+        at_block: BlockId,
+    ) -> Result<CallTrace, CallFailure> {
+        let (_, mut traces) = self
+            .execute(vec![call], at_block, None, Some(signature))
+            .await?;
+
+        traces
+            .pop()
+            .ok_or(CallFailure::Internal("Missing call trace"))
+    }
+
+    /// Sends the calls to the executors. The calls are traced if a `signature` is given.
+    async fn execute(
+        &self,
+        calls: Vec<Call>,
+        at_block: BlockId,
+        pending_state_diff: Option<StateDiff>,
+        signature: Option<Vec<TransactionSignatureElem>>,
+    ) -> Result<(Vec<Vec<CallResultValue>>, Vec<CallTrace>), CallFailure> {
         use tracing::field::Empty;
         let (tx, rx) = oneshot::channel();
 
         let continued_span = tracing::info_span!("ext_py_call", pid = Empty);
 
         self.command_tx
-            .send(((call, at_block, tx), continued_span))
+            .send((
+                (calls, at_block, pending_state_diff, signature, tx),
+                continued_span,
+            ))
             .await
             .map_err(|_| CallFailure::Shutdown)?;
 
@@ -66,8 +128,16 @@ pub enum CallFailure {
     NoSuchBlock,
     /// The called top-level contract could not be found.
     NoSuchContract,
-    /// `cairo-lang` failed the call, string has the exception name.
-    ExecutionFailed(String),
+    /// `cairo-lang` failed the call.
+    ExecutionFailed {
+        /// The exception name.
+        exception: String,
+        /// The message of the exception, for example the reason the call reverted.
+        revert_reason: Option<String>,
+        /// Index of the failed call among the executed calls, if the failure happened while
+        /// executing one.
+        call_index: Option<usize>,
+    },
     /// Internal, opaque-ish failure reason, none of them signal an issue with the call.
     Internal(&'static str),
     /// Channel related issue or shutting down.
@@ -90,11 +160,14 @@ impl From<ErrorKind> for CallFailure {
 /// to be.
 type SharedReceiver<T> = Arc<Mutex<mpsc::Receiver<T>>>;
 
-/// Alias for the type used to transfer commands over to executors.
+/// Alias for the type used to transfer commands over to executors. The calls are traced and
+/// executed as a transaction with the signature, if one is given.
 type Command = (
-    Call,
-    BlockHashOrTag,
-    oneshot::Sender<Result<Vec<CallResultValue>, CallFailure>>,
+    Vec<Call>,
+    BlockId,
+    Option<StateDiff>,
+    Option<Vec<TransactionSignatureElem>>,
+    oneshot::Sender<Result<(Vec<Vec<CallResultValue>>, Vec<CallTrace>), CallFailure>>,
 );
 
 /// Informational events from python process executors.
@@ -137,6 +210,245 @@ mod tests {
     use std::path::PathBuf;
     use tokio::sync::oneshot;
 
+    #[test]
+    fn command_with_caller_address() {
+        use crate::core::{ContractAddress, EntryPoint};
+        use crate::rpc::types::{BlockId, Tag};
+
+        let contract_address = ContractAddress(StarkHash::from_hex_str("0x1").unwrap());
+        let caller_address = ContractAddress(StarkHash::from_hex_str("0x1234").unwrap());
+        let entry_point_selector = EntryPoint::hashed(&b"get_value"[..]);
+        let at_block = BlockId::Tag(Tag::Latest);
+
+        let mut cmd = super::ser::ChildCommand {
+            calls: vec![super::ser::ChildCall {
+                contract_address: &contract_address,
+                calldata: &[],
+                entry_point_selector: &entry_point_selector,
+                caller_address: Some(&caller_address),
+                signature: None,
+            }],
+            at_block: &at_block,
+            pending_state_diff: None,
+            trace: false,
+        };
+        let json = serde_json::to_value(&cmd).unwrap();
+        assert_eq!(json["calls"][0]["caller_address"], "0x1234");
+
+        // Python uses the zero address as caller if it's missing.
+        cmd.calls[0].caller_address = None;
+        let json = serde_json::to_value(&cmd).unwrap();
+        assert!(json["calls"][0].get("caller_address").is_none());
+    }
+
+    #[test]
+    fn command_at_block() {
+        use crate::core::{ContractAddress, EntryPoint, StarknetBlockHash, StarknetBlockNumber};
+        use crate::rpc::types::{BlockId, Tag};
+
+        let contract_address = ContractAddress(StarkHash::from_hex_str("0x1").unwrap());
+        let entry_point_selector = EntryPoint::hashed(&b"get_value"[..]);
+
+        // The python side accepts the hash, the number or the tag of a block.
+        for (at_block, expected) in [
+            (
+                BlockId::Hash(StarknetBlockHash(StarkHash::from_hex_str("0x12").unwrap())),
+                serde_json::json!("0x12"),
+            ),
+            (
+                BlockId::Number(StarknetBlockNumber(3)),
+                serde_json::json!(3),
+            ),
+            (BlockId::Tag(Tag::Latest), serde_json::json!("latest")),
+        ] {
+            let cmd = super::ser::ChildCommand {
+                calls: vec![super::ser::ChildCall {
+                    contract_address: &contract_address,
+                    calldata: &[],
+                    entry_point_selector: &entry_point_selector,
+                    caller_address: None,
+                    signature: None,
+                }],
+                at_block: &at_block,
+                pending_state_diff: None,
+                trace: false,
+            };
+            let json = serde_json::to_value(&cmd).unwrap();
+            assert_eq!(json["at_block"], expected);
+        }
+    }
+
+    #[test]
+    fn command_with_pending_state_diff() {
+        use crate::core::{
+            ContractAddress, ContractHash, EntryPoint, StorageAddress, StorageValue,
+        };
+        use crate::rpc::types::{BlockId, Tag};
+        use crate::sequencer::reply::state_update::{Contract, StateDiff, StorageDiff};
+
+        let contract_address = ContractAddress(StarkHash::from_hex_str("0x1").unwrap());
+        let entry_point_selector = EntryPoint::hashed(&b"get_value"[..]);
+        let at_block = BlockId::Tag(Tag::Latest);
+        let pending_state_diff = StateDiff {
+            storage_diffs: std::collections::BTreeMap::from([(
+                contract_address,
+                vec![StorageDiff {
+                    key: StorageAddress(StarkHash::from_hex_str("0x84").unwrap()),
+                    value: StorageValue(StarkHash::from_hex_str("0x5").unwrap()),
+                }],
+            )]),
+            deployed_contracts: vec![Contract {
+                address: ContractAddress(StarkHash::from_hex_str("0x2").unwrap()),
+                contract_hash: ContractHash(StarkHash::from_hex_str("0x3").unwrap()),
+            }],
+            nonces: std::collections::BTreeMap::new(),
+        };
+
+        let mut cmd = super::ser::ChildCommand {
+            calls: vec![super::ser::ChildCall {
+                contract_address: &contract_address,
+                calldata: &[],
+                entry_point_selector: &entry_point_selector,
+                caller_address: None,
+                signature: None,
+            }],
+            at_block: &at_block,
+            pending_state_diff: Some(&pending_state_diff),
+            trace: false,
+        };
+        let json = serde_json::to_value(&cmd).unwrap();
+        assert_eq!(
+            json["pending_state_diff"],
+            serde_json::json!({
+                "storage_diffs": { "0x1": [{ "key": "0x84", "value": "0x5" }] },
+                "deployed_contracts": [{ "address": "0x2", "contract_hash": "0x3" }],
+            })
+        );
+
+        cmd.pending_state_diff = None;
+        let json = serde_json::to_value(&cmd).unwrap();
+        assert!(json.get("pending_state_diff").is_none());
+    }
+
+    #[test]
+    fn command_with_many_calls() {
+        use crate::core::{CallParam, ContractAddress, EntryPoint};
+        use crate::rpc::types::{BlockId, Tag};
+
+        let contract_address = ContractAddress(StarkHash::from_hex_str("0x1").unwrap());
+        let increase_value = EntryPoint::hashed(&b"increase_value"[..]);
+        let get_value = EntryPoint::hashed(&b"get_value"[..]);
+        let calldata = [CallParam(StarkHash::from_hex_str("0x84").unwrap())];
+        let at_block = BlockId::Tag(Tag::Latest);
+
+        let cmd = super::ser::ChildCommand {
+            calls: vec![
+                super::ser::ChildCall {
+                    contract_address: &contract_address,
+                    calldata: &calldata,
+                    entry_point_selector: &increase_value,
+                    caller_address: None,
+                    signature: None,
+                },
+                super::ser::ChildCall {
+                    contract_address: &contract_address,
+                    calldata: &calldata,
+                    entry_point_selector: &get_value,
+                    caller_address: None,
+                    signature: None,
+                },
+            ],
+            at_block: &at_block,
+            pending_state_diff: None,
+            trace: false,
+        };
+        let json = serde_json::to_value(&cmd).unwrap();
+
+        // The calls are executed in the given order.
+        let calls = json["calls"].as_array().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            calls[0]["entry_point_selector"],
+            serde_json::to_value(increase_value).unwrap()
+        );
+        assert_eq!(
+            calls[1]["entry_point_selector"],
+            serde_json::to_value(get_value).unwrap()
+        );
+    }
+
+    #[test]
+    fn command_with_trace() {
+        use crate::core::{ContractAddress, EntryPoint, TransactionSignatureElem};
+        use crate::rpc::types::{BlockId, Tag};
+
+        let contract_address = ContractAddress(StarkHash::from_hex_str("0x1").unwrap());
+        let entry_point_selector = EntryPoint::hashed(&b"get_value"[..]);
+        let signature = [TransactionSignatureElem(
+            StarkHash::from_hex_str("0x5").unwrap(),
+        )];
+        let at_block = BlockId::Tag(Tag::Latest);
+
+        let mut cmd = super::ser::ChildCommand {
+            calls: vec![super::ser::ChildCall {
+                contract_address: &contract_address,
+                calldata: &[],
+                entry_point_selector: &entry_point_selector,
+                caller_address: None,
+                signature: Some(&signature),
+            }],
+            at_block: &at_block,
+            pending_state_diff: None,
+            trace: true,
+        };
+        let json = serde_json::to_value(&cmd).unwrap();
+        assert_eq!(json["trace"], true);
+        assert_eq!(json["calls"][0]["signature"], serde_json::json!(["0x5"]));
+
+        cmd.trace = false;
+        cmd.calls[0].signature = None;
+        let json = serde_json::to_value(&cmd).unwrap();
+        assert!(json.get("trace").is_none());
+        assert!(json["calls"][0].get("signature").is_none());
+    }
+
+    #[test]
+    fn traces_are_parsed() {
+        let json = r#"{"status":"ok","output":[["0x3"]],"traces":[{"contract_address":"0x1","caller_address":"0x0","entry_point_selector":"0x2","calldata":["0x84"],"result":["0x3"],"internal_calls":[]}],"timings":{}}"#;
+        let response = serde_json::from_str::<super::de::ChildResponse<'_>>(json)
+            .unwrap()
+            .refine()
+            .unwrap_or_else(|_| panic!("Response should be valid"));
+
+        let (_, status, result) = response.into_messages();
+        assert_eq!(status, super::Status::Ok);
+        let (outputs, traces) = result.unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].result, outputs[0]);
+        assert!(traces[0].internal_calls.is_empty());
+    }
+
+    #[test]
+    fn failed_call_index_is_parsed() {
+        let json = r#"{"status":"failed","exception":"TRANSACTION_FAILED","revert_reason":"nope","failed_call_index":1,"timings":{}}"#;
+        let response = serde_json::from_str::<super::de::ChildResponse<'_>>(json)
+            .unwrap()
+            .refine()
+            .unwrap_or_else(|_| panic!("Response should be valid"));
+
+        let (_, status, result) = response.into_messages();
+        assert_eq!(status, super::Status::Failed);
+        assert_matches::assert_matches!(
+            result,
+            Err(super::CallFailure::ExecutionFailed {
+                call_index: Some(1),
+                revert_reason: Some(reason),
+                ..
+            }) => assert_eq!(reason, "nope")
+        );
+    }
+
     #[test_log::test(tokio::test)]
     #[ignore]
     async fn start_with_wrong_database_schema_fails() {
@@ -179,7 +491,7 @@ mod tests {
 
         let tx = conn.transaction().unwrap();
 
-        fill_example_state(&tx);
+        super::self_test::fill_example_state(&tx).unwrap();
 
         tx.commit().unwrap();
 
@@ -214,10 +526,12 @@ mod tests {
                                     StarkHash::from_hex_str("84").unwrap(),
                                 )],
                                 entry_point_selector: crate::core::EntryPoint::hashed(&b"get_value"[..]),
+                                sender_address: None,
                             },
-                            super::BlockHashOrTag::Hash(crate::core::StarknetBlockHash(
+                            super::BlockId::Hash(crate::core::StarknetBlockHash(
                                 StarkHash::from_be_slice(&b"some blockhash somewhere"[..]).unwrap(),
-                            ))
+                            )),
+                            None,
                         ).await.unwrap();
                     }
                 })
@@ -233,98 +547,86 @@ mod tests {
         jh.await.unwrap();
     }
 
-    fn fill_example_state(tx: &rusqlite::Transaction) {
-        let contract_definition = zstd::decode_all(std::io::Cursor::new(include_bytes!(
-            "../../fixtures/contract_definition.json.zst"
-        )))
-        .unwrap();
+    #[test_log::test(tokio::test)]
+    #[ignore] // these tests require that you've entered into python venv
+    async fn trace_like_in_python() {
+        use crate::core::{CallParam, ContractAddress, EntryPoint};
 
-        let address = StarkHash::from_hex_str(
-            "057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374",
-        )
-        .unwrap();
-        let expected_hash = StarkHash::from_hex_str(
-            "050b2148c0d782914e0b12a1a32abe5e398930b7e914f82c65cb7afce0a0ab9b",
-        )
-        .unwrap();
+        let db_file = tempfile::NamedTempFile::new().unwrap();
 
-        let (abi, bytecode, hash) =
-            crate::state::contract_hash::extract_abi_code_hash(&*contract_definition).unwrap();
+        let s = crate::storage::Storage::migrate(PathBuf::from(db_file.path())).unwrap();
 
-        assert_eq!(hash.0, expected_hash);
+        let mut conn = s.connection().unwrap();
+        conn.execute("PRAGMA foreign_keys = off", []).unwrap();
 
-        crate::storage::ContractCodeTable::insert(tx, hash, &abi, &bytecode, &contract_definition)
-            .unwrap();
+        let tx = conn.transaction().unwrap();
 
-        crate::storage::ContractsTable::upsert(tx, crate::core::ContractAddress(address), hash)
-            .unwrap();
+        super::self_test::fill_example_state(&tx).unwrap();
 
-        // this will create the table, not created by migration
-        crate::state::state_tree::ContractsStateTree::load(
-            tx,
-            crate::core::ContractRoot(StarkHash::ZERO),
-        )
-        .unwrap();
+        tx.commit().unwrap();
 
-        crate::state::state_tree::GlobalStateTree::load(
-            tx,
-            crate::core::GlobalRoot(StarkHash::ZERO),
-        )
-        .unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
 
-        tx.execute("insert into tree_contracts (hash, data, ref_count) values (?1, ?2, 1)",
-            rusqlite::params![
-                &hex::decode("04fb440e8ca9b74fc12a22ebffe0bc0658206337897226117b985434c239c028").unwrap()[..],
-                &hex::decode("00000000000000000000000000000000000000000000000000000000000000030000000000000000000000000000000000000000000000000000000000000084fb").unwrap()[..],
-            ]).unwrap();
-
-        tx.execute(
-            "insert into contract_states (state_hash, hash, root) values (?1, ?2, ?3)",
-            rusqlite::params![
-                &hex::decode("002e9723e54711aec56e3fb6ad1bb8272f64ec92e0a43a20feed943b1d4f73c5")
-                    .unwrap()[..],
-                &hex::decode("050b2148c0d782914e0b12a1a32abe5e398930b7e914f82c65cb7afce0a0ab9b")
-                    .unwrap()[..],
-                &hex::decode("04fb440e8ca9b74fc12a22ebffe0bc0658206337897226117b985434c239c028")
-                    .unwrap()[..],
-            ],
+        let (handle, jh) = super::start(
+            PathBuf::from(db_file.path()),
+            std::num::NonZeroUsize::new(1).unwrap(),
+            async move {
+                let _ = shutdown_rx.await;
+            },
         )
+        .await
         .unwrap();
 
-        tx.execute(
-            "insert into tree_global (hash, data, ref_count) values (?1, ?2, 1)",
-            rusqlite::params![
-                &hex::decode("0704dfcbc470377c68e6f5ffb83970ebd0d7c48d5b8d2f4ed61a24e795e034bd").unwrap()[..],
-                &hex::decode("002e9723e54711aec56e3fb6ad1bb8272f64ec92e0a43a20feed943b1d4f73c5057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374fb").unwrap()[..],
-            ],
-        )
-        .unwrap();
+        let address = ContractAddress(
+            StarkHash::from_hex_str(
+                "057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374",
+            )
+            .unwrap(),
+        );
+        let calldata = vec![
+            CallParam(address.0),
+            CallParam(StarkHash::from_hex_str("84").unwrap()),
+            CallParam(StarkHash::from_hex_str("4").unwrap()),
+        ];
+
+        let trace = handle
+            .trace_call(
+                super::Call {
+                    contract_address: address,
+                    calldata: calldata.clone(),
+                    entry_point_selector: EntryPoint::hashed(&b"call_increase_value"[..]),
+                    sender_address: None,
+                },
+                vec![],
+                super::BlockId::Number(crate::core::StarknetBlockNumber(1)),
+            )
+            .await
+            .unwrap();
 
-        tx.execute(
-            "insert into starknet_blocks (hash, number, timestamp, root) values (?1, 1, 1, ?)",
-            rusqlite::params![
-                &StarkHash::from_be_slice(&b"some blockhash somewhere"[..])
-                    .unwrap()
-                    .to_be_bytes()[..],
-                &hex::decode("0704dfcbc470377c68e6f5ffb83970ebd0d7c48d5b8d2f4ed61a24e795e034bd")
-                    .unwrap()[..],
-            ],
-        )
-        .unwrap();
+        assert_eq!(trace.contract_address, address);
+        assert_eq!(trace.calldata, calldata);
+        assert!(trace.result.is_empty());
+
+        // call_increase_value calls increase_value on the given contract, here itself
+        assert_eq!(trace.internal_calls.len(), 1);
+        let inner = &trace.internal_calls[0];
+        assert_eq!(inner.contract_address, address);
+        assert_eq!(inner.caller_address, address);
+        assert_eq!(
+            inner.entry_point_selector,
+            Some(EntryPoint::hashed(&b"increase_value"[..]))
+        );
+        assert_eq!(inner.calldata, calldata[1..]);
+        assert!(inner.internal_calls.is_empty());
 
-        if false {
-            let mut stmt = tx
-                .prepare("select starknet_block_hash from global_state")
-                .unwrap();
-            let mut rows = stmt.query([]).unwrap();
-            while let Some(row) = rows.next().unwrap() {
-                let first = row.get_ref(0).expect("get column");
+        shutdown_tx.send(()).unwrap();
 
-                println!("{:?}", first);
+        jh.await.unwrap();
+    }
 
-                let first = first.as_blob().expect("cannot read it as a blob");
-                println!("{}", hex::encode(first));
-            }
-        }
+    #[test_log::test(tokio::test)]
+    #[ignore] // these tests require that you've entered into python venv
+    async fn self_test_succeeds() {
+        super::self_test().await.unwrap();
     }
 }
@@ -2,6 +2,7 @@
 
 use super::{CallFailure, SubprocessError};
 use crate::core::CallResultValue;
+use crate::rpc::types::reply::CallTrace;
 
 /// The python loop currently responds with these four possibilities. An enum would be more
 /// appropriate.
@@ -16,21 +17,32 @@ pub(crate) struct ChildResponse<'a> {
     /// longer. Probably okay to give as a hint in the internal error message.
     #[serde(borrow)]
     exception: Option<std::borrow::Cow<'a, str>>,
+    /// The full message of a known cairo-lang failure, such as the reason a call reverted.
+    /// Only present alongside `exception`.
+    #[serde(borrow, default)]
+    revert_reason: Option<std::borrow::Cow<'a, str>>,
+    /// Index of the call which failed, present alongside `exception` if the failure happened
+    /// while executing a call.
+    #[serde(default)]
+    failed_call_index: Option<usize>,
     /// Enumeration of "known errors", present when `status` is [`Status::Error`].
     kind: Option<ErrorKind>,
     /// Timing information, possibly available.
     #[serde(default)]
     timings: Timings,
-    /// The real output from the contract when `status` is [`Status::Ok`].
+    /// The real output from each of the calls when `status` is [`Status::Ok`].
+    #[serde(default)]
+    output: Vec<Vec<CallResultValue>>,
+    /// The trace of each of the calls when `status` is [`Status::Ok`] and tracing was requested.
     #[serde(default)]
-    output: Vec<CallResultValue>,
+    traces: Vec<CallTrace>,
 }
 
 impl<'a> ChildResponse<'a> {
     pub(super) fn refine(mut self) -> Result<RefinedChildResponse<'a>, SubprocessError> {
         match (&self.status, &mut self.kind, &mut self.exception) {
             (Status::Ok, None, None) => Ok(RefinedChildResponse {
-                status: RefinedStatus::Ok(self.output),
+                status: RefinedStatus::Ok(self.output, self.traces),
                 timings: self.timings,
             }),
             (Status::Error, x @ Some(_), None) => Ok(RefinedChildResponse {
@@ -38,7 +50,11 @@ impl<'a> ChildResponse<'a> {
                 timings: self.timings,
             }),
             (Status::Failed, None, s @ &mut Some(_)) => Ok(RefinedChildResponse {
-                status: RefinedStatus::Failed(s.take().unwrap()),
+                status: RefinedStatus::Failed(
+                    s.take().unwrap(),
+                    self.revert_reason.take(),
+                    self.failed_call_index,
+                ),
                 timings: self.timings,
             }),
             // these should not happen, so turn them into similar as serde_json errors
@@ -53,24 +69,28 @@ impl RefinedChildResponse<'_> {
     ) -> (
         Option<Timings>,
         Status,
-        Result<Vec<CallResultValue>, CallFailure>,
+        Result<(Vec<Vec<CallResultValue>>, Vec<CallTrace>), CallFailure>,
     ) {
         match self {
             RefinedChildResponse {
                 timings,
-                status: RefinedStatus::Ok(x),
-            } => (Some(timings), Status::Ok, Ok(x)),
+                status: RefinedStatus::Ok(outputs, traces),
+            } => (Some(timings), Status::Ok, Ok((outputs, traces))),
             RefinedChildResponse {
                 timings,
                 status: RefinedStatus::Error(e),
             } => (Some(timings), Status::Error, Err(CallFailure::from(e))),
             RefinedChildResponse {
                 timings,
-                status: RefinedStatus::Failed(s),
+                status: RefinedStatus::Failed(s, revert_reason, call_index),
             } => (
                 Some(timings),
                 Status::Failed,
-                Err(CallFailure::ExecutionFailed(s.to_string())),
+                Err(CallFailure::ExecutionFailed {
+                    exception: s.to_string(),
+                    revert_reason: revert_reason.map(|r| r.into_owned()),
+                    call_index,
+                }),
             ),
         }
     }
@@ -121,7 +141,13 @@ pub struct RefinedChildResponse<'a> {
 
 /// More sensible alternative to [`Status`].
 pub enum RefinedStatus<'a> {
-    Ok(Vec<CallResultValue>),
+    /// The output of each call and, if requested, the trace of each call.
+    Ok(Vec<Vec<CallResultValue>>, Vec<CallTrace>),
     Error(ErrorKind),
-    Failed(std::borrow::Cow<'a, str>),
+    /// The exception, the revert reason if one was given and the index of the failed call.
+    Failed(
+        std::borrow::Cow<'a, str>,
+        Option<std::borrow::Cow<'a, str>>,
+        Option<usize>,
+    ),
 }
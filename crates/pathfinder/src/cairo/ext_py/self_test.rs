@@ -0,0 +1,141 @@
+//! Checks that calls can be executed at all, by calling a known contract in a throwaway database.
+
+use crate::core::{
+    CallParam, CallResultValue, ContractAddress, ContractRoot, EntryPoint, GlobalRoot,
+    StarknetBlockNumber,
+};
+use crate::rpc::types::{request::Call, BlockId};
+use crate::state::state_tree::{ContractsStateTree, GlobalStateTree};
+use crate::storage::{ContractCodeTable, ContractsTable, Storage};
+use anyhow::Context;
+use pedersen::StarkHash;
+use tokio::sync::oneshot;
+
+/// Address of the example contract deployed by [fill_example_state].
+const CONTRACT_ADDRESS: &str = "057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374";
+
+/// Starts a single python executor on a temporary database containing the contract of
+/// `fixtures/contract_definition.json.zst`, and checks that calling its `get_value` entry point
+/// returns the known value.
+///
+/// Meant to be called at startup, so that a broken python environment is detected before any
+/// calls are served.
+pub async fn self_test() -> anyhow::Result<()> {
+    let db_file = tempfile::NamedTempFile::new().context("Creating temporary database file")?;
+    let storage = Storage::migrate(db_file.path().into()).context("Creating database")?;
+    {
+        let mut connection = storage
+            .connection()
+            .context("Opening database connection")?;
+        // The example state does not contain the rows the foreign keys refer to.
+        connection
+            .execute("PRAGMA foreign_keys = off", [])
+            .context("Disabling foreign keys")?;
+        let transaction = connection
+            .transaction()
+            .context("Creating database transaction")?;
+        fill_example_state(&transaction).context("Filling example state")?;
+        transaction.commit().context("Committing example state")?;
+    }
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let (handle, jh) = super::start(
+        db_file.path().into(),
+        std::num::NonZeroUsize::new(1).unwrap(),
+        async move {
+            let _ = shutdown_rx.await;
+        },
+    )
+    .await
+    .context("Starting python executor")?;
+
+    let result = handle
+        .call(
+            Call {
+                contract_address: ContractAddress(StarkHash::from_hex_str(CONTRACT_ADDRESS)?),
+                calldata: vec![CallParam(StarkHash::from_hex_str("0x84")?)],
+                entry_point_selector: EntryPoint::hashed(&b"get_value"[..]),
+                sender_address: None,
+            },
+            BlockId::Number(StarknetBlockNumber(1)),
+            None,
+        )
+        .await;
+
+    let _ = shutdown_tx.send(());
+    jh.await.context("Waiting for python executor to stop")?;
+
+    let result = result.map_err(|e| anyhow::anyhow!("Call failed: {:?}", e))?;
+    let expected = vec![CallResultValue(StarkHash::from_hex_str("0x3")?)];
+    anyhow::ensure!(
+        result == expected,
+        "Call returned {:?} instead of {:?}",
+        result,
+        expected
+    );
+
+    Ok(())
+}
+
+/// Deploys the contract of `fixtures/contract_definition.json.zst` at [CONTRACT_ADDRESS] in
+/// block 1, with the value 3 stored at key 0x84.
+pub(super) fn fill_example_state(tx: &rusqlite::Transaction<'_>) -> anyhow::Result<()> {
+    let contract_definition = zstd::decode_all(std::io::Cursor::new(include_bytes!(
+        "../../../fixtures/contract_definition.json.zst"
+    )))
+    .context("Decompressing contract definition")?;
+
+    let address = StarkHash::from_hex_str(CONTRACT_ADDRESS)?;
+
+    let (abi, bytecode, hash) =
+        crate::state::contract_hash::extract_abi_code_hash(&contract_definition)
+            .context("Computing contract hash")?;
+
+    ContractCodeTable::insert(tx, hash, &abi, &bytecode, &contract_definition)
+        .context("Inserting contract code")?;
+    ContractsTable::upsert(tx, ContractAddress(address), hash).context("Inserting contract")?;
+
+    // this will create the tables, not created by migration
+    ContractsStateTree::load(tx, ContractRoot(StarkHash::ZERO))
+        .context("Creating contracts state tree")?;
+    GlobalStateTree::load(tx, GlobalRoot(StarkHash::ZERO)).context("Creating global state tree")?;
+
+    tx.execute(
+        "insert into tree_contracts (hash, data, ref_count) values (?1, ?2, 1)",
+        rusqlite::params![
+            &hex::decode("04fb440e8ca9b74fc12a22ebffe0bc0658206337897226117b985434c239c028")?[..],
+            &hex::decode("00000000000000000000000000000000000000000000000000000000000000030000000000000000000000000000000000000000000000000000000000000084fb")?[..],
+        ],
+    )
+    .context("Inserting contract storage")?;
+
+    tx.execute(
+        "insert into contract_states (state_hash, hash, root) values (?1, ?2, ?3)",
+        rusqlite::params![
+            &hex::decode("002e9723e54711aec56e3fb6ad1bb8272f64ec92e0a43a20feed943b1d4f73c5")?[..],
+            &hash.0.to_be_bytes()[..],
+            &hex::decode("04fb440e8ca9b74fc12a22ebffe0bc0658206337897226117b985434c239c028")?[..],
+        ],
+    )
+    .context("Inserting contract state")?;
+
+    tx.execute(
+        "insert into tree_global (hash, data, ref_count) values (?1, ?2, 1)",
+        rusqlite::params![
+            &hex::decode("0704dfcbc470377c68e6f5ffb83970ebd0d7c48d5b8d2f4ed61a24e795e034bd")?[..],
+            &hex::decode("002e9723e54711aec56e3fb6ad1bb8272f64ec92e0a43a20feed943b1d4f73c5057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374fb")?[..],
+        ],
+    )
+    .context("Inserting global state")?;
+
+    tx.execute(
+        "insert into starknet_blocks (hash, number, timestamp, root) values (?1, 1, 1, ?)",
+        rusqlite::params![
+            &StarkHash::from_be_slice(&b"some blockhash somewhere"[..])?.to_be_bytes()[..],
+            &hex::decode("0704dfcbc470377c68e6f5ffb83970ebd0d7c48d5b8d2f4ed61a24e795e034bd")?[..],
+        ],
+    )
+    .context("Inserting block")?;
+
+    Ok(())
+}
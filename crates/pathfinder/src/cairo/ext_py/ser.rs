@@ -1,13 +1,33 @@
 //! The json serializable types
 
-use crate::core::{CallParam, ContractAddress, EntryPoint};
-use crate::rpc::types::BlockHashOrTag;
+use crate::core::{CallParam, ContractAddress, EntryPoint, TransactionSignatureElem};
+use crate::rpc::types::BlockId;
+use crate::sequencer::reply::state_update::StateDiff;
 
 /// The command we send to the python loop.
 #[derive(serde::Serialize, Debug)]
 pub struct ChildCommand<'a> {
+    /// The calls to execute in order; each call sees the state changes of the previous ones.
+    pub calls: Vec<ChildCall<'a>>,
+    pub at_block: &'a BlockId,
+    /// Storage updates and deployed contracts which are applied on top of the state at
+    /// `at_block` before executing the calls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_state_diff: Option<&'a StateDiff>,
+    /// Requests the trace of each call in addition to its output.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub trace: bool,
+}
+
+/// A single call within [`ChildCommand`].
+#[derive(serde::Serialize, Debug)]
+pub struct ChildCall<'a> {
     pub contract_address: &'a ContractAddress,
     pub calldata: &'a [CallParam],
     pub entry_point_selector: &'a EntryPoint,
-    pub at_block: &'a BlockHashOrTag,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caller_address: Option<&'a ContractAddress>,
+    /// The signature of the transaction the call is executed as, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<&'a [TransactionSignatureElem]>,
 }
@@ -2,7 +2,7 @@
 
 use super::{
     de::{ChildResponse, RefinedChildResponse, Status, Timings},
-    ser::ChildCommand,
+    ser::{ChildCall, ChildCommand},
     CallFailure, Command, SharedReceiver, SubProcessEvent, SubprocessError, SubprocessExitReason,
 };
 use anyhow::Context;
@@ -75,7 +75,7 @@ pub(super) async fn launch_python(
             },
         };
 
-        if command.2.is_closed() {
+        if command.4.is_closed() {
             // quickly loadshed, as the caller has already left.
             continue;
         }
@@ -259,14 +259,23 @@ async fn process(
     stdout: &mut BufReader<ChildStdout>,
     buffer: &mut String,
 ) -> Result<(Option<Timings>, Status), Option<SubprocessExitReason>> {
-    let (call, at_block, mut response) = command;
+    let (calls, at_block, pending_state_diff, signature, mut response) = command;
     command_buffer.clear();
 
     let cmd = ChildCommand {
-        contract_address: &call.contract_address,
-        calldata: &call.calldata,
-        entry_point_selector: &call.entry_point_selector,
+        calls: calls
+            .iter()
+            .map(|call| ChildCall {
+                contract_address: &call.contract_address,
+                calldata: &call.calldata,
+                entry_point_selector: &call.entry_point_selector,
+                caller_address: call.sender_address.as_ref(),
+                signature: signature.as_deref(),
+            })
+            .collect(),
         at_block: &at_block,
+        pending_state_diff: pending_state_diff.as_ref(),
+        trace: signature.is_some(),
     };
 
     let mut cursor = std::io::Cursor::new(command_buffer);
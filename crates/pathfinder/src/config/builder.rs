@@ -36,7 +36,12 @@ impl ConfigBuilder {
     /// and parsing as required by [Configuration] types. Also ensures that all
     /// required options are set.
     pub fn try_build(mut self) -> std::io::Result<Configuration> {
-        use super::DEFAULT_HTTP_RPC_ADDR;
+        use super::{
+            DEFAULT_EVENTS_CACHE_CAPACITY, DEFAULT_EVENTS_CACHE_TTL,
+            DEFAULT_EVENTS_MAX_CONCURRENT_QUERIES, DEFAULT_EVENTS_PAGE_SIZE,
+            DEFAULT_EVENT_QUERY_LOG_THRESHOLD, DEFAULT_HTTP_RPC_ADDR, DEFAULT_HTTP_RPC_BACKLOG,
+            DEFAULT_SEQUENCER_MAX_IN_FLIGHT, DEFAULT_SEQUENCER_TIMEOUT,
+        };
 
         // Required parameters.
         let eth_url = self.take_required(ConfigOption::EthereumHttpUrl)?;
@@ -52,6 +57,14 @@ impl ConfigBuilder {
         // Optional parameters.
         let eth_user_agent = self.take(ConfigOption::EthereumUserAgent);
         let eth_password = self.take(ConfigOption::EthereumPassword);
+        let eth_auth_header = self.take(ConfigOption::EthereumAuthHeader);
+        let http_rpc_max_connections = self.take(ConfigOption::HttpRpcMaxConnections);
+        let http_rpc_max_concurrent_calls = self.take(ConfigOption::HttpRpcMaxConcurrentCalls);
+        let chain = self.take(ConfigOption::Chain);
+        let http_rpc_allowed_methods = self.take(ConfigOption::HttpRpcAllowedMethods);
+        let http_rpc_denied_methods = self.take(ConfigOption::HttpRpcDeniedMethods);
+        let ws_rpc_addr = self.take(ConfigOption::WsRpcAddress);
+        let events_soft_deadline = self.take(ConfigOption::EventsSoftDeadline);
 
         // Optional parameters with defaults.
         let data_directory = self
@@ -62,6 +75,55 @@ impl ConfigBuilder {
             .take(ConfigOption::HttpRpcAddress)
             .unwrap_or_else(|| DEFAULT_HTTP_RPC_ADDR.to_owned());
 
+        let sequencer_code_fallback = self
+            .take(ConfigOption::SequencerCodeFallback)
+            .unwrap_or_else(|| "false".to_owned());
+        let http_rpc_backlog = self
+            .take(ConfigOption::HttpRpcBacklog)
+            .unwrap_or_else(|| DEFAULT_HTTP_RPC_BACKLOG.to_owned());
+        let sequencer_timeout = self
+            .take(ConfigOption::SequencerTimeout)
+            .unwrap_or_else(|| DEFAULT_SEQUENCER_TIMEOUT.to_owned());
+        let partial_block_responses = self
+            .take(ConfigOption::PartialBlockResponses)
+            .unwrap_or_else(|| "false".to_owned());
+        let disable_pending = self
+            .take(ConfigOption::DisablePending)
+            .unwrap_or_else(|| "false".to_owned());
+        let http_rpc_gzip = self
+            .take(ConfigOption::HttpRpcGzip)
+            .unwrap_or_else(|| "false".to_owned());
+        let retain_raw_blocks = self
+            .take(ConfigOption::RetainRawBlocks)
+            .unwrap_or_else(|| "false".to_owned());
+        let optimize_database = self
+            .take(ConfigOption::OptimizeDatabase)
+            .unwrap_or_else(|| "false".to_owned());
+        let vacuum_database = self
+            .take(ConfigOption::VacuumDatabase)
+            .unwrap_or_else(|| "false".to_owned());
+        let check_database_integrity = self
+            .take(ConfigOption::CheckDatabaseIntegrity)
+            .unwrap_or_else(|| "false".to_owned());
+        let event_query_log_threshold = self
+            .take(ConfigOption::EventQueryLogThreshold)
+            .unwrap_or_else(|| DEFAULT_EVENT_QUERY_LOG_THRESHOLD.to_owned());
+        let sequencer_max_in_flight = self
+            .take(ConfigOption::SequencerMaxInFlight)
+            .unwrap_or_else(|| DEFAULT_SEQUENCER_MAX_IN_FLIGHT.to_owned());
+        let events_cache_ttl = self
+            .take(ConfigOption::EventsCacheTtl)
+            .unwrap_or_else(|| DEFAULT_EVENTS_CACHE_TTL.to_owned());
+        let events_cache_capacity = self
+            .take(ConfigOption::EventsCacheCapacity)
+            .unwrap_or_else(|| DEFAULT_EVENTS_CACHE_CAPACITY.to_owned());
+        let events_page_size = self
+            .take(ConfigOption::EventsPageSize)
+            .unwrap_or_else(|| DEFAULT_EVENTS_PAGE_SIZE.to_owned());
+        let events_max_concurrent_queries = self
+            .take(ConfigOption::EventsMaxConcurrentQueries)
+            .unwrap_or_else(|| DEFAULT_EVENTS_MAX_CONCURRENT_QUERIES.to_owned());
+
         // Parse the HTTP-RPC listening address and port.
         let http_rpc_addr = http_rpc_addr.parse::<SocketAddr>().map_err(|err| {
             std::io::Error::new(
@@ -73,14 +135,316 @@ impl ConfigBuilder {
             )
         })?;
 
+        let ws_rpc_addr = ws_rpc_addr
+            .map(|addr| {
+                addr.parse::<SocketAddr>().map_err(|err| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!(
+                            "Invalid WebSocket-RPC listening interface and port ({}): {}",
+                            addr, err
+                        ),
+                    )
+                })
+            })
+            .transpose()?;
+
+        let sequencer_code_fallback = sequencer_code_fallback.parse::<bool>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid sequencer code fallback setting ({}): {}",
+                    sequencer_code_fallback, err
+                ),
+            )
+        })?;
+
+        let partial_block_responses = partial_block_responses.parse::<bool>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid partial block responses setting ({}): {}",
+                    partial_block_responses, err
+                ),
+            )
+        })?;
+
+        let disable_pending = disable_pending.parse::<bool>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid disable pending setting ({}): {}",
+                    disable_pending, err
+                ),
+            )
+        })?;
+
+        let http_rpc_gzip = http_rpc_gzip.parse::<bool>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid HTTP-RPC gzip compression setting ({}): {}",
+                    http_rpc_gzip, err
+                ),
+            )
+        })?;
+
+        let retain_raw_blocks = retain_raw_blocks.parse::<bool>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid retain raw blocks setting ({}): {}",
+                    retain_raw_blocks, err
+                ),
+            )
+        })?;
+
+        let optimize_database = optimize_database.parse::<bool>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid optimize database setting ({}): {}",
+                    optimize_database, err
+                ),
+            )
+        })?;
+
+        let vacuum_database = vacuum_database.parse::<bool>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid vacuum database setting ({}): {}",
+                    vacuum_database, err
+                ),
+            )
+        })?;
+
+        let check_database_integrity = check_database_integrity.parse::<bool>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid check database integrity setting ({}): {}",
+                    check_database_integrity, err
+                ),
+            )
+        })?;
+
+        let optimize_database =
+            (optimize_database || vacuum_database).then(|| crate::storage::OptimizeOptions {
+                analyze: optimize_database,
+                vacuum: vacuum_database,
+            });
+
+        let http_rpc_max_connections = http_rpc_max_connections
+            .map(|max| {
+                max.parse::<usize>().map_err(|err| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("Invalid HTTP-RPC maximum connections ({}): {}", max, err),
+                    )
+                })
+            })
+            .transpose()?;
+
+        let http_rpc_max_concurrent_calls = http_rpc_max_concurrent_calls
+            .map(|max| {
+                max.parse::<usize>().map_err(|err| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!(
+                            "Invalid HTTP-RPC maximum concurrent calls ({}): {}",
+                            max, err
+                        ),
+                    )
+                })
+            })
+            .transpose()?;
+
+        let chain = chain
+            .map(|chain| {
+                chain.parse::<crate::ethereum::Chain>().map_err(|err| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("Invalid Ethereum chain ({}): {}", chain, err),
+                    )
+                })
+            })
+            .transpose()?;
+
+        let http_rpc_backlog = http_rpc_backlog.parse::<u32>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid HTTP-RPC accept backlog ({}): {}",
+                    http_rpc_backlog, err
+                ),
+            )
+        })?;
+
+        let sequencer_timeout = sequencer_timeout
+            .parse::<u64>()
+            .map(std::time::Duration::from_secs)
+            .map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid sequencer request timeout ({}): {}",
+                        sequencer_timeout, err
+                    ),
+                )
+            })?;
+
+        let event_query_log_threshold = event_query_log_threshold
+            .parse::<u64>()
+            .map(std::time::Duration::from_millis)
+            .map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid event query log threshold ({}): {}",
+                        event_query_log_threshold, err
+                    ),
+                )
+            })?;
+
+        let sequencer_max_in_flight = sequencer_max_in_flight
+            .parse::<std::num::NonZeroUsize>()
+            .map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid sequencer maximum in-flight requests ({}): {}",
+                        sequencer_max_in_flight, err
+                    ),
+                )
+            })?;
+
+        let events_cache_ttl = events_cache_ttl
+            .parse::<u64>()
+            .map(std::time::Duration::from_millis)
+            .map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid events cache TTL ({}): {}", events_cache_ttl, err),
+                )
+            })?;
+
+        let events_cache_capacity = events_cache_capacity.parse::<usize>().map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid events cache capacity ({}): {}",
+                    events_cache_capacity, err
+                ),
+            )
+        })?;
+
+        let max_page_size = crate::storage::StarknetEventsTable::PAGE_SIZE_LIMIT;
+        let events_page_size = events_page_size
+            .parse::<usize>()
+            .map_err(|err| err.to_string())
+            .and_then(|page_size| {
+                if (1..=max_page_size).contains(&page_size) {
+                    Ok(page_size)
+                } else {
+                    Err(format!("must be between 1 and {}", max_page_size))
+                }
+            })
+            .map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid events default page size ({}): {}",
+                        events_page_size, err
+                    ),
+                )
+            })?;
+
+        let events_max_concurrent_queries = events_max_concurrent_queries
+            .parse::<std::num::NonZeroUsize>()
+            .map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid events maximum concurrent queries ({}): {}",
+                        events_max_concurrent_queries, err
+                    ),
+                )
+            })?;
+
+        let events_soft_deadline = events_soft_deadline
+            .map(|deadline| {
+                deadline
+                    .parse::<u64>()
+                    .map(std::time::Duration::from_millis)
+                    .map_err(|err| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            format!("Invalid events soft deadline ({}): {}", deadline, err),
+                        )
+                    })
+            })
+            .transpose()?;
+
+        let eth_auth_header = eth_auth_header
+            .map(|header| {
+                parse_header(&header).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "Invalid Ethereum auth header: expected `Name: value`",
+                    )
+                })
+            })
+            .transpose()?;
+
+        let http_rpc_allowed_methods = http_rpc_allowed_methods
+            .map(|methods| {
+                let methods = parse_method_names(&methods);
+                // Allowing no methods at all is most likely a mistake.
+                if methods.is_empty() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "HTTP-RPC allowed methods must name at least one method",
+                    ));
+                }
+                Ok(methods)
+            })
+            .transpose()?;
+        let http_rpc_denied_methods = http_rpc_denied_methods
+            .map(|methods| parse_method_names(&methods))
+            .unwrap_or_default();
+
         Ok(Configuration {
             ethereum: EthereumConfig {
                 url: eth_url,
                 user_agent: eth_user_agent,
                 password: eth_password,
+                auth_header: eth_auth_header,
             },
             http_rpc_addr,
             data_directory,
+            sequencer_code_fallback,
+            http_rpc_max_connections,
+            http_rpc_max_concurrent_calls,
+            http_rpc_backlog,
+            sequencer_timeout,
+            partial_block_responses,
+            disable_pending,
+            http_rpc_gzip,
+            event_query_log_threshold,
+            chain,
+            sequencer_max_in_flight,
+            http_rpc_allowed_methods,
+            http_rpc_denied_methods,
+            ws_rpc_addr,
+            events_cache_ttl,
+            events_cache_capacity,
+            events_page_size,
+            events_max_concurrent_queries,
+            events_soft_deadline,
+            retain_raw_blocks,
+            optimize_database,
+            check_database_integrity,
         })
     }
 
@@ -100,6 +464,27 @@ impl ConfigBuilder {
     }
 }
 
+/// Splits a comma separated list of method names, ignoring whitespace and empty names.
+fn parse_method_names(methods: &str) -> Vec<String> {
+    methods
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Splits a `Name: value` HTTP header at the first colon, trimming whitespace around both
+/// parts. Returns [None] if either part is empty.
+fn parse_header(header: &str) -> Option<(String, String)> {
+    let (name, value) = header.split_once(':')?;
+    let (name, value) = (name.trim(), value.trim());
+    if name.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some((name.to_owned(), value.to_owned()))
+}
+
 #[cfg(test)]
 mod tests {
     use enum_iterator::IntoEnumIterator;
@@ -229,6 +614,215 @@ mod tests {
             assert!(builder.try_build().is_ok());
         }
 
+        #[test]
+        fn chain() {
+            use crate::ethereum::Chain;
+
+            for (value, expected) in [("mainnet", Chain::Mainnet), ("goerli", Chain::Goerli)] {
+                let config = builder_with_all_required()
+                    .with(ConfigOption::Chain, Some(value.to_owned()))
+                    .try_build()
+                    .unwrap();
+                assert_eq!(config.chain, Some(expected));
+            }
+
+            let result = builder_with_all_required()
+                .with(ConfigOption::Chain, Some("ropsten".to_owned()))
+                .try_build();
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn ethereum_auth_header() {
+            let config = builder_with_all_required()
+                .with(
+                    ConfigOption::EthereumAuthHeader,
+                    Some("Authorization: Bearer secret: token ".to_owned()),
+                )
+                .try_build()
+                .unwrap();
+            assert_eq!(
+                config.ethereum.auth_header,
+                Some((
+                    "Authorization".to_owned(),
+                    "Bearer secret: token".to_owned()
+                ))
+            );
+
+            for invalid in ["Authorization", ": Bearer secret", "Authorization: "] {
+                let result = builder_with_all_required()
+                    .with(ConfigOption::EthereumAuthHeader, Some(invalid.to_owned()))
+                    .try_build();
+                assert!(result.is_err(), "{invalid}");
+            }
+        }
+
+        #[test]
+        fn optimize_database() {
+            use crate::storage::OptimizeOptions;
+
+            for (optimize, vacuum, expected) in [
+                ("true", "false", (true, false)),
+                ("false", "true", (false, true)),
+                ("true", "true", (true, true)),
+            ] {
+                let config = builder_with_all_required()
+                    .with(ConfigOption::OptimizeDatabase, Some(optimize.to_owned()))
+                    .with(ConfigOption::VacuumDatabase, Some(vacuum.to_owned()))
+                    .try_build()
+                    .unwrap();
+                assert_eq!(
+                    config.optimize_database,
+                    Some(OptimizeOptions {
+                        analyze: expected.0,
+                        vacuum: expected.1,
+                    })
+                );
+            }
+
+            let result = builder_with_all_required()
+                .with(ConfigOption::VacuumDatabase, Some("yes".to_owned()))
+                .try_build();
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn sequencer_max_in_flight() {
+            let config = builder_with_all_required()
+                .with(ConfigOption::SequencerMaxInFlight, Some("8".to_owned()))
+                .try_build()
+                .unwrap();
+            assert_eq!(config.sequencer_max_in_flight.get(), 8);
+
+            // Zero would block all requests to the sequencer.
+            let result = builder_with_all_required()
+                .with(ConfigOption::SequencerMaxInFlight, Some("0".to_owned()))
+                .try_build();
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn http_rpc_methods() {
+            let config = builder_with_all_required()
+                .with(
+                    ConfigOption::HttpRpcAllowedMethods,
+                    Some("starknet_chainId, starknet_call,".to_owned()),
+                )
+                .with(
+                    ConfigOption::HttpRpcDeniedMethods,
+                    Some("starknet_call".to_owned()),
+                )
+                .try_build()
+                .unwrap();
+            assert_eq!(
+                config.http_rpc_allowed_methods,
+                Some(vec![
+                    "starknet_chainId".to_owned(),
+                    "starknet_call".to_owned()
+                ])
+            );
+            assert_eq!(config.http_rpc_denied_methods, vec!["starknet_call"]);
+
+            let result = builder_with_all_required()
+                .with(ConfigOption::HttpRpcAllowedMethods, Some(" , ".to_owned()))
+                .try_build();
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn ws_rpc_addr() {
+            let config = builder_with_all_required()
+                .with(
+                    ConfigOption::WsRpcAddress,
+                    Some("127.0.0.1:9546".to_owned()),
+                )
+                .try_build()
+                .unwrap();
+            assert_eq!(
+                config.ws_rpc_addr,
+                Some("127.0.0.1:9546".parse::<SocketAddr>().unwrap())
+            );
+
+            let result = builder_with_all_required()
+                .with(ConfigOption::WsRpcAddress, Some("localhost".to_owned()))
+                .try_build();
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn events_cache() {
+            let config = builder_with_all_required()
+                .with(ConfigOption::EventsCacheTtl, Some("500".to_owned()))
+                .with(ConfigOption::EventsCacheCapacity, Some("0".to_owned()))
+                .try_build()
+                .unwrap();
+            assert_eq!(
+                config.events_cache_ttl,
+                std::time::Duration::from_millis(500)
+            );
+            assert_eq!(config.events_cache_capacity, 0);
+
+            let result = builder_with_all_required()
+                .with(ConfigOption::EventsCacheCapacity, Some("-1".to_owned()))
+                .try_build();
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn events_page_size() {
+            let config = builder_with_all_required()
+                .with(ConfigOption::EventsPageSize, Some("10".to_owned()))
+                .try_build()
+                .unwrap();
+            assert_eq!(config.events_page_size, 10);
+
+            let max_page_size = crate::storage::StarknetEventsTable::PAGE_SIZE_LIMIT;
+            for invalid in ["0".to_owned(), (max_page_size + 1).to_string()] {
+                let result = builder_with_all_required()
+                    .with(ConfigOption::EventsPageSize, Some(invalid))
+                    .try_build();
+                assert!(result.is_err());
+            }
+        }
+
+        #[test]
+        fn events_max_concurrent_queries() {
+            let config = builder_with_all_required()
+                .with(
+                    ConfigOption::EventsMaxConcurrentQueries,
+                    Some("2".to_owned()),
+                )
+                .try_build()
+                .unwrap();
+            assert_eq!(config.events_max_concurrent_queries.get(), 2);
+
+            // Zero would block all event queries.
+            let result = builder_with_all_required()
+                .with(
+                    ConfigOption::EventsMaxConcurrentQueries,
+                    Some("0".to_owned()),
+                )
+                .try_build();
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn events_soft_deadline() {
+            let config = builder_with_all_required()
+                .with(ConfigOption::EventsSoftDeadline, Some("250".to_owned()))
+                .try_build()
+                .unwrap();
+            assert_eq!(
+                config.events_soft_deadline,
+                Some(std::time::Duration::from_millis(250))
+            );
+
+            let result = builder_with_all_required()
+                .with(ConfigOption::EventsSoftDeadline, Some("soon".to_owned()))
+                .try_build();
+            assert!(result.is_err());
+        }
+
         #[test]
         fn with_required_missing_should_error() {
             // Any missing required field should fail to build.
@@ -261,6 +855,161 @@ mod tests {
                 let config = builder_with_all_required().try_build().unwrap();
                 assert_eq!(config.http_rpc_addr, expected);
             }
+
+            #[test]
+            fn sequencer_code_fallback() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert!(!config.sequencer_code_fallback);
+            }
+
+            #[test]
+            fn http_rpc_max_connections() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.http_rpc_max_connections, None);
+            }
+
+            #[test]
+            fn http_rpc_max_concurrent_calls() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.http_rpc_max_concurrent_calls, None);
+            }
+
+            #[test]
+            fn chain() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.chain, None);
+            }
+
+            #[test]
+            fn http_rpc_methods() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.http_rpc_allowed_methods, None);
+                assert!(config.http_rpc_denied_methods.is_empty());
+            }
+
+            #[test]
+            fn ws_rpc_addr() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.ws_rpc_addr, None);
+            }
+
+            #[test]
+            fn http_rpc_backlog() {
+                use crate::config::DEFAULT_HTTP_RPC_BACKLOG;
+
+                let expected = DEFAULT_HTTP_RPC_BACKLOG.parse::<u32>().unwrap();
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.http_rpc_backlog, expected);
+            }
+
+            #[test]
+            fn partial_block_responses() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert!(!config.partial_block_responses);
+            }
+
+            #[test]
+            fn disable_pending() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert!(!config.disable_pending);
+            }
+
+            #[test]
+            fn http_rpc_gzip() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert!(!config.http_rpc_gzip);
+            }
+
+            #[test]
+            fn retain_raw_blocks() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert!(!config.retain_raw_blocks);
+            }
+
+            #[test]
+            fn optimize_database() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.optimize_database, None);
+            }
+
+            #[test]
+            fn check_database_integrity() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert!(!config.check_database_integrity);
+            }
+
+            #[test]
+            fn sequencer_timeout() {
+                use crate::config::DEFAULT_SEQUENCER_TIMEOUT;
+
+                let expected = DEFAULT_SEQUENCER_TIMEOUT.parse::<u64>().unwrap();
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(
+                    config.sequencer_timeout,
+                    std::time::Duration::from_secs(expected)
+                );
+            }
+
+            #[test]
+            fn sequencer_max_in_flight() {
+                use crate::config::DEFAULT_SEQUENCER_MAX_IN_FLIGHT;
+
+                let expected = DEFAULT_SEQUENCER_MAX_IN_FLIGHT.parse::<usize>().unwrap();
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.sequencer_max_in_flight.get(), expected);
+            }
+
+            #[test]
+            fn event_query_log_threshold() {
+                use crate::config::DEFAULT_EVENT_QUERY_LOG_THRESHOLD;
+
+                let expected = DEFAULT_EVENT_QUERY_LOG_THRESHOLD.parse::<u64>().unwrap();
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(
+                    config.event_query_log_threshold,
+                    std::time::Duration::from_millis(expected)
+                );
+            }
+
+            #[test]
+            fn events_cache() {
+                use crate::config::{DEFAULT_EVENTS_CACHE_CAPACITY, DEFAULT_EVENTS_CACHE_TTL};
+
+                let expected_ttl = DEFAULT_EVENTS_CACHE_TTL.parse::<u64>().unwrap();
+                let expected_capacity = DEFAULT_EVENTS_CACHE_CAPACITY.parse::<usize>().unwrap();
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(
+                    config.events_cache_ttl,
+                    std::time::Duration::from_millis(expected_ttl)
+                );
+                assert_eq!(config.events_cache_capacity, expected_capacity);
+            }
+
+            #[test]
+            fn events_page_size() {
+                use crate::config::DEFAULT_EVENTS_PAGE_SIZE;
+
+                let expected = DEFAULT_EVENTS_PAGE_SIZE.parse::<usize>().unwrap();
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.events_page_size, expected);
+            }
+
+            #[test]
+            fn events_max_concurrent_queries() {
+                use crate::config::DEFAULT_EVENTS_MAX_CONCURRENT_QUERIES;
+
+                let expected = DEFAULT_EVENTS_MAX_CONCURRENT_QUERIES
+                    .parse::<usize>()
+                    .unwrap();
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.events_max_concurrent_queries.get(), expected);
+            }
+
+            #[test]
+            fn events_soft_deadline() {
+                let config = builder_with_all_required().try_build().unwrap();
+                assert_eq!(config.events_soft_deadline, None);
+            }
         }
     }
 }
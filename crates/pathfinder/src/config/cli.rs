@@ -11,7 +11,31 @@ const DATA_DIR_KEY: &str = "data-directory";
 const ETH_URL_KEY: &str = "ethereum.url";
 const ETH_USER_AGENT_KEY: &str = "ethereum.user-agent";
 const ETH_PASS_KEY: &str = "ethereum.password";
+const ETH_AUTH_HEADER_KEY: &str = "ethereum.auth-header";
 const HTTP_RPC_ADDR_KEY: &str = "http-rpc";
+const SEQ_CODE_FALLBACK_KEY: &str = "sequencer-code-fallback";
+const HTTP_RPC_MAX_CONNECTIONS_KEY: &str = "http-rpc-max-connections";
+const HTTP_RPC_MAX_CONCURRENT_CALLS_KEY: &str = "http-rpc-max-concurrent-calls";
+const HTTP_RPC_BACKLOG_KEY: &str = "http-rpc-backlog";
+const SEQ_TIMEOUT_KEY: &str = "sequencer-timeout";
+const PARTIAL_BLOCK_RESPONSES_KEY: &str = "partial-block-responses";
+const DISABLE_PENDING_KEY: &str = "disable-pending";
+const HTTP_RPC_GZIP_KEY: &str = "http-rpc-gzip";
+const EVENT_QUERY_LOG_THRESHOLD_KEY: &str = "event-query-log-threshold";
+const CHAIN_KEY: &str = "chain";
+const SEQ_MAX_IN_FLIGHT_KEY: &str = "sequencer-max-in-flight";
+const HTTP_RPC_ALLOWED_METHODS_KEY: &str = "http-rpc-allowed-methods";
+const HTTP_RPC_DENIED_METHODS_KEY: &str = "http-rpc-denied-methods";
+const WS_RPC_ADDR_KEY: &str = "ws-rpc";
+const EVENTS_CACHE_TTL_KEY: &str = "events-cache-ttl";
+const EVENTS_CACHE_CAPACITY_KEY: &str = "events-cache-capacity";
+const EVENTS_PAGE_SIZE_KEY: &str = "events-page-size";
+const EVENTS_MAX_CONCURRENT_QUERIES_KEY: &str = "events-max-concurrent-queries";
+const EVENTS_SOFT_DEADLINE_KEY: &str = "events-soft-deadline";
+const RETAIN_RAW_BLOCKS_KEY: &str = "retain-raw-blocks";
+const OPTIMIZE_DATABASE_KEY: &str = "optimize-database";
+const VACUUM_DATABASE_KEY: &str = "vacuum-database";
+const CHECK_DATABASE_INTEGRITY_KEY: &str = "check-database-integrity";
 
 /// Parses the cmd line arguments and returns the optional
 /// configuration file's path and the specified configuration options.
@@ -41,14 +65,100 @@ where
     let ethereum_url = args.value_of(ETH_URL_KEY).map(|s| s.to_owned());
     let ethereum_user_agent = args.value_of(ETH_USER_AGENT_KEY).map(|s| s.to_owned());
     let ethereum_password = args.value_of(ETH_PASS_KEY).map(|s| s.to_owned());
+    let ethereum_auth_header = args.value_of(ETH_AUTH_HEADER_KEY).map(|s| s.to_owned());
     let http_rpc_addr = args.value_of(HTTP_RPC_ADDR_KEY).map(|s| s.to_owned());
+    let sequencer_code_fallback = args.value_of(SEQ_CODE_FALLBACK_KEY).map(|s| s.to_owned());
+    let http_rpc_max_connections = args
+        .value_of(HTTP_RPC_MAX_CONNECTIONS_KEY)
+        .map(|s| s.to_owned());
+    let http_rpc_max_concurrent_calls = args
+        .value_of(HTTP_RPC_MAX_CONCURRENT_CALLS_KEY)
+        .map(|s| s.to_owned());
+    let http_rpc_backlog = args.value_of(HTTP_RPC_BACKLOG_KEY).map(|s| s.to_owned());
+    let sequencer_timeout = args.value_of(SEQ_TIMEOUT_KEY).map(|s| s.to_owned());
+    let partial_block_responses = args
+        .value_of(PARTIAL_BLOCK_RESPONSES_KEY)
+        .map(|s| s.to_owned());
+    let disable_pending = args.value_of(DISABLE_PENDING_KEY).map(|s| s.to_owned());
+    let http_rpc_gzip = args.value_of(HTTP_RPC_GZIP_KEY).map(|s| s.to_owned());
+    let event_query_log_threshold = args
+        .value_of(EVENT_QUERY_LOG_THRESHOLD_KEY)
+        .map(|s| s.to_owned());
+    let chain = args.value_of(CHAIN_KEY).map(|s| s.to_owned());
+    let sequencer_max_in_flight = args.value_of(SEQ_MAX_IN_FLIGHT_KEY).map(|s| s.to_owned());
+    let http_rpc_allowed_methods = args
+        .value_of(HTTP_RPC_ALLOWED_METHODS_KEY)
+        .map(|s| s.to_owned());
+    let http_rpc_denied_methods = args
+        .value_of(HTTP_RPC_DENIED_METHODS_KEY)
+        .map(|s| s.to_owned());
+    let ws_rpc_addr = args.value_of(WS_RPC_ADDR_KEY).map(|s| s.to_owned());
+    let events_cache_ttl = args.value_of(EVENTS_CACHE_TTL_KEY).map(|s| s.to_owned());
+    let events_cache_capacity = args
+        .value_of(EVENTS_CACHE_CAPACITY_KEY)
+        .map(|s| s.to_owned());
+    let events_page_size = args.value_of(EVENTS_PAGE_SIZE_KEY).map(|s| s.to_owned());
+    let events_max_concurrent_queries = args
+        .value_of(EVENTS_MAX_CONCURRENT_QUERIES_KEY)
+        .map(|s| s.to_owned());
+    let events_soft_deadline = args
+        .value_of(EVENTS_SOFT_DEADLINE_KEY)
+        .map(|s| s.to_owned());
+    let retain_raw_blocks = args.value_of(RETAIN_RAW_BLOCKS_KEY).map(|s| s.to_owned());
+    let optimize_database = args.value_of(OPTIMIZE_DATABASE_KEY).map(|s| s.to_owned());
+    let vacuum_database = args.value_of(VACUUM_DATABASE_KEY).map(|s| s.to_owned());
+    let check_database_integrity = args
+        .value_of(CHECK_DATABASE_INTEGRITY_KEY)
+        .map(|s| s.to_owned());
 
     let cfg = ConfigBuilder::default()
         .with(ConfigOption::EthereumHttpUrl, ethereum_url)
         .with(ConfigOption::EthereumUserAgent, ethereum_user_agent)
         .with(ConfigOption::EthereumPassword, ethereum_password)
+        .with(ConfigOption::EthereumAuthHeader, ethereum_auth_header)
         .with(ConfigOption::HttpRpcAddress, http_rpc_addr)
-        .with(ConfigOption::DataDirectory, data_directory);
+        .with(ConfigOption::DataDirectory, data_directory)
+        .with(ConfigOption::SequencerCodeFallback, sequencer_code_fallback)
+        .with(
+            ConfigOption::HttpRpcMaxConnections,
+            http_rpc_max_connections,
+        )
+        .with(
+            ConfigOption::HttpRpcMaxConcurrentCalls,
+            http_rpc_max_concurrent_calls,
+        )
+        .with(ConfigOption::HttpRpcBacklog, http_rpc_backlog)
+        .with(ConfigOption::SequencerTimeout, sequencer_timeout)
+        .with(ConfigOption::PartialBlockResponses, partial_block_responses)
+        .with(ConfigOption::DisablePending, disable_pending)
+        .with(ConfigOption::HttpRpcGzip, http_rpc_gzip)
+        .with(
+            ConfigOption::EventQueryLogThreshold,
+            event_query_log_threshold,
+        )
+        .with(ConfigOption::Chain, chain)
+        .with(ConfigOption::SequencerMaxInFlight, sequencer_max_in_flight)
+        .with(
+            ConfigOption::HttpRpcAllowedMethods,
+            http_rpc_allowed_methods,
+        )
+        .with(ConfigOption::HttpRpcDeniedMethods, http_rpc_denied_methods)
+        .with(ConfigOption::WsRpcAddress, ws_rpc_addr)
+        .with(ConfigOption::EventsCacheTtl, events_cache_ttl)
+        .with(ConfigOption::EventsCacheCapacity, events_cache_capacity)
+        .with(ConfigOption::EventsPageSize, events_page_size)
+        .with(
+            ConfigOption::EventsMaxConcurrentQueries,
+            events_max_concurrent_queries,
+        )
+        .with(ConfigOption::EventsSoftDeadline, events_soft_deadline)
+        .with(ConfigOption::RetainRawBlocks, retain_raw_blocks)
+        .with(ConfigOption::OptimizeDatabase, optimize_database)
+        .with(ConfigOption::VacuumDatabase, vacuum_database)
+        .with(
+            ConfigOption::CheckDatabaseIntegrity,
+            check_database_integrity,
+        );
 
     Ok((config_filepath, cfg))
 }
@@ -91,6 +201,15 @@ fn clap_app() -> clap::Command<'static> {
                 .env("PATHFINDER_ETHEREUM_API_PASSWORD")
                 .long_help("The optional password to use for the Ethereum API"),
         )
+        .arg(
+            Arg::new(ETH_AUTH_HEADER_KEY)
+                .long(ETH_AUTH_HEADER_KEY)
+                .help("Ethereum API auth header")
+                .takes_value(true)
+                .value_name("NAME: VALUE")
+                .env("PATHFINDER_ETHEREUM_API_AUTH_HEADER")
+                .long_help("An optional HTTP header attached to every Ethereum API request, i.e. `Authorization: Bearer <token>`. Takes precedence over the password.")
+        )
         .arg(
             Arg::new(ETH_URL_KEY)
                 .long(ETH_URL_KEY)
@@ -118,6 +237,213 @@ Examples:
                 .value_name("IP:PORT")
                 .env("PATHFINDER_DATA_DIRECTORY")
         )
+        .arg(
+            Arg::new(SEQ_CODE_FALLBACK_KEY)
+                .long(SEQ_CODE_FALLBACK_KEY)
+                .help("Query the sequencer for contract code missing from local storage [default: false]")
+                .takes_value(true)
+                .value_name("BOOL")
+                .env("PATHFINDER_SEQUENCER_CODE_FALLBACK")
+                .long_help("When enabled, starknet_getCode queries the sequencer for contracts which are not (yet) synced. The code is not stored locally.")
+        )
+        .arg(
+            Arg::new(HTTP_RPC_MAX_CONNECTIONS_KEY)
+                .long(HTTP_RPC_MAX_CONNECTIONS_KEY)
+                .help("Maximum number of concurrent HTTP-RPC connections [default: unlimited]")
+                .takes_value(true)
+                .value_name("COUNT")
+                .env("PATHFINDER_HTTP_RPC_MAX_CONNECTIONS")
+                .long_help("Connections exceeding this limit are refused immediately instead of waiting to be served.")
+        )
+        .arg(
+            Arg::new(HTTP_RPC_MAX_CONCURRENT_CALLS_KEY)
+                .long(HTTP_RPC_MAX_CONCURRENT_CALLS_KEY)
+                .help("Maximum number of HTTP-RPC calls handled concurrently [default: unlimited]")
+                .takes_value(true)
+                .value_name("COUNT")
+                .env("PATHFINDER_HTTP_RPC_MAX_CONCURRENT_CALLS")
+                .long_help("Applies across all connections. Calls exceeding this limit fail immediately with a server busy error (-32005).")
+        )
+        .arg(
+            Arg::new(HTTP_RPC_BACKLOG_KEY)
+                .long(HTTP_RPC_BACKLOG_KEY)
+                .help("Accept backlog of the HTTP-RPC listening socket [default: 1024]")
+                .takes_value(true)
+                .value_name("COUNT")
+                .env("PATHFINDER_HTTP_RPC_BACKLOG")
+                .long_help("The number of pending connections the operating system queues before new connections are dropped.")
+        )
+        .arg(
+            Arg::new(SEQ_TIMEOUT_KEY)
+                .long(SEQ_TIMEOUT_KEY)
+                .help("Timeout of requests to the sequencer in seconds [default: 120]")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .env("PATHFINDER_SEQUENCER_TIMEOUT")
+                .long_help("Requests to the sequencer which take longer than this are aborted and retried.")
+        )
+        .arg(
+            Arg::new(PARTIAL_BLOCK_RESPONSES_KEY)
+                .long(PARTIAL_BLOCK_RESPONSES_KEY)
+                .help("Leave unreadable transactions out of block responses [default: false]")
+                .takes_value(true)
+                .value_name("BOOL")
+                .env("PATHFINDER_PARTIAL_BLOCK_RESPONSES")
+                .long_help("When enabled, transactions whose stored data cannot be read are left out of starknet_getBlockByHash and starknet_getBlockByNumber responses and listed under skipped_transactions, instead of failing the request.")
+        )
+        .arg(
+            Arg::new(DISABLE_PENDING_KEY)
+                .long(DISABLE_PENDING_KEY)
+                .help("Reject requests for the pending block [default: false]")
+                .takes_value(true)
+                .value_name("BOOL")
+                .env("PATHFINDER_DISABLE_PENDING")
+                .long_help("When enabled, requests using the pending tag are rejected with an error instead of being served with data from the sequencer.")
+        )
+        .arg(
+            Arg::new(HTTP_RPC_GZIP_KEY)
+                .long(HTTP_RPC_GZIP_KEY)
+                .help("Gzip compress HTTP-RPC responses [default: false]")
+                .takes_value(true)
+                .value_name("BOOL")
+                .env("PATHFINDER_HTTP_RPC_GZIP")
+                .long_help("When enabled, responses of at least 1KiB are gzip compressed for clients which accept gzip encoding.")
+        )
+        .arg(
+            Arg::new(EVENT_QUERY_LOG_THRESHOLD_KEY)
+                .long(EVENT_QUERY_LOG_THRESHOLD_KEY)
+                .help("Log event queries slower than this in milliseconds [default: 1000]")
+                .takes_value(true)
+                .value_name("MILLISECONDS")
+                .env("PATHFINDER_EVENT_QUERY_LOG_THRESHOLD")
+                .long_help("Event queries of starknet_getEvents which take longer than this are logged as warnings, along with the shape of their filter.")
+        )
+        .arg(
+            Arg::new(CHAIN_KEY)
+                .long(CHAIN_KEY)
+                .help("The expected Ethereum chain, mainnet or goerli [default: detected]")
+                .takes_value(true)
+                .value_name("CHAIN")
+                .env("PATHFINDER_CHAIN")
+                .long_help("The node refuses to start if the Ethereum endpoint is on another chain. When not set, the chain is detected from the Ethereum endpoint.")
+        )
+        .arg(
+            Arg::new(SEQ_MAX_IN_FLIGHT_KEY)
+                .long(SEQ_MAX_IN_FLIGHT_KEY)
+                .help("Maximum number of concurrent requests to the sequencer [default: 64]")
+                .takes_value(true)
+                .value_name("COUNT")
+                .env("PATHFINDER_SEQUENCER_MAX_IN_FLIGHT")
+                .long_help("Requests to the sequencer above this limit wait until an earlier request completes.")
+        )
+        .arg(
+            Arg::new(HTTP_RPC_ALLOWED_METHODS_KEY)
+                .long(HTTP_RPC_ALLOWED_METHODS_KEY)
+                .help("Comma separated HTTP-RPC methods which are the only ones offered [default: all]")
+                .takes_value(true)
+                .value_name("METHODS")
+                .env("PATHFINDER_HTTP_RPC_ALLOWED_METHODS")
+                .long_help("Requests for any other method fail with 'Method not found'.")
+        )
+        .arg(
+            Arg::new(HTTP_RPC_DENIED_METHODS_KEY)
+                .long(HTTP_RPC_DENIED_METHODS_KEY)
+                .help("Comma separated HTTP-RPC methods which are not offered [default: none]")
+                .takes_value(true)
+                .value_name("METHODS")
+                .env("PATHFINDER_HTTP_RPC_DENIED_METHODS")
+                .long_help("Requests for these methods fail with 'Method not found', i.e. to disable starknet_call on a public endpoint. Takes precedence over the allowed methods.")
+        )
+        .arg(
+            Arg::new(WS_RPC_ADDR_KEY)
+                .long(WS_RPC_ADDR_KEY)
+                .help("WebSocket-RPC listening address [default: disabled]")
+                .takes_value(true)
+                .value_name("IP:PORT")
+                .env("PATHFINDER_WS_RPC_ADDRESS")
+                .long_help("The WebSocket-RPC server offers subscriptions, i.e. starknet_subscribeNewHeads and starknet_subscribeEvents. It is only started if an address is set.")
+        )
+        .arg(
+            Arg::new(EVENTS_CACHE_TTL_KEY)
+                .long(EVENTS_CACHE_TTL_KEY)
+                .help("Serve repeated starknet_getEvents filters from a cache for this many milliseconds [default: 2000]")
+                .takes_value(true)
+                .value_name("MILLISECONDS")
+                .env("PATHFINDER_EVENTS_CACHE_TTL")
+                .long_help("Results of starknet_getEvents are cached, so that clients polling the same filter do not query the database every time. All cached results are dropped once a new block has been stored. Filters including the pending block are never cached.")
+        )
+        .arg(
+            Arg::new(EVENTS_CACHE_CAPACITY_KEY)
+                .long(EVENTS_CACHE_CAPACITY_KEY)
+                .help("Maximum number of cached starknet_getEvents results [default: 64]")
+                .takes_value(true)
+                .value_name("COUNT")
+                .env("PATHFINDER_EVENTS_CACHE_CAPACITY")
+                .long_help("Once full, the oldest cached result is evicted. Zero disables the cache.")
+        )
+        .arg(
+            Arg::new(EVENTS_PAGE_SIZE_KEY)
+                .long(EVENTS_PAGE_SIZE_KEY)
+                .help("Page size of starknet_getEvents filters which do not specify one [default: 64]")
+                .takes_value(true)
+                .value_name("COUNT")
+                .env("PATHFINDER_EVENTS_PAGE_SIZE")
+                .long_help("Must be between 1 and the maximum page size of 1024.")
+        )
+        .arg(
+            Arg::new(EVENTS_MAX_CONCURRENT_QUERIES_KEY)
+                .long(EVENTS_MAX_CONCURRENT_QUERIES_KEY)
+                .help("Maximum number of starknet_getEvents queries executed concurrently [default: 8]")
+                .takes_value(true)
+                .value_name("COUNT")
+                .env("PATHFINDER_EVENTS_MAX_CONCURRENT_QUERIES")
+                .long_help("Event queries above this limit wait until an earlier query completes, so that a burst of event queries does not hold up other methods.")
+        )
+        .arg(
+            Arg::new(EVENTS_SOFT_DEADLINE_KEY)
+                .long(EVENTS_SOFT_DEADLINE_KEY)
+                .help("Milliseconds after which starknet_getEvents returns the events gathered so far")
+                .takes_value(true)
+                .value_name("MILLISECONDS")
+                .env("PATHFINDER_EVENTS_SOFT_DEADLINE")
+                .long_help("Queries running past this deadline return a partial page together with a continuation token, which resumes the query where it left off. Disabled by default.")
+        )
+        .arg(
+            Arg::new(RETAIN_RAW_BLOCKS_KEY)
+                .long(RETAIN_RAW_BLOCKS_KEY)
+                .help("Store blocks exactly as received from the sequencer [default: false]")
+                .takes_value(true)
+                .value_name("BOOL")
+                .env("PATHFINDER_RETAIN_RAW_BLOCKS")
+                .long_help("When enabled, the JSON of every synced block is stored as received from the sequencer and served by starknet_getRawBlock, i.e. for debugging. Only blocks synced while enabled are available.")
+        )
+        .arg(
+            Arg::new(OPTIMIZE_DATABASE_KEY)
+                .long(OPTIMIZE_DATABASE_KEY)
+                .help("Refresh the database statistics on startup [default: false]")
+                .takes_value(true)
+                .value_name("BOOL")
+                .env("PATHFINDER_OPTIMIZE_DATABASE")
+                .long_help("When enabled, ANALYZE and PRAGMA optimize are run on the database before syncing starts, which helps the query planner after large amounts of data were synced.")
+        )
+        .arg(
+            Arg::new(VACUUM_DATABASE_KEY)
+                .long(VACUUM_DATABASE_KEY)
+                .help("Rebuild the database file on startup [default: false]")
+                .takes_value(true)
+                .value_name("BOOL")
+                .env("PATHFINDER_VACUUM_DATABASE")
+                .long_help("When enabled, VACUUM is run on the database before syncing starts, reclaiming unused pages. This rewrites the entire database file and can take a long time on large databases.")
+        )
+        .arg(
+            Arg::new(CHECK_DATABASE_INTEGRITY_KEY)
+                .long(CHECK_DATABASE_INTEGRITY_KEY)
+                .help("Check the database for corruption on startup [default: false]")
+                .takes_value(true)
+                .value_name("BOOL")
+                .env("PATHFINDER_CHECK_DATABASE_INTEGRITY")
+                .long_help("When enabled, PRAGMA integrity_check is run on the database before syncing starts, and the node refuses to start if any problems are found. This reads the entire database and can take a long time on large databases.")
+        )
 }
 
 #[cfg(test)]
@@ -135,9 +461,33 @@ mod tests {
     fn clear_environment() {
         env::remove_var("PATHFINDER_ETHEREUM_API_USER_AGENT");
         env::remove_var("PATHFINDER_ETHEREUM_API_PASSWORD");
+        env::remove_var("PATHFINDER_ETHEREUM_API_AUTH_HEADER");
         env::remove_var("PATHFINDER_ETHEREUM_API_URL");
         env::remove_var("PATHFINDER_HTTP_RPC_ADDRESS");
         env::remove_var("PATHFINDER_DATA_DIRECTORY");
+        env::remove_var("PATHFINDER_SEQUENCER_CODE_FALLBACK");
+        env::remove_var("PATHFINDER_HTTP_RPC_MAX_CONNECTIONS");
+        env::remove_var("PATHFINDER_HTTP_RPC_MAX_CONCURRENT_CALLS");
+        env::remove_var("PATHFINDER_HTTP_RPC_BACKLOG");
+        env::remove_var("PATHFINDER_SEQUENCER_TIMEOUT");
+        env::remove_var("PATHFINDER_PARTIAL_BLOCK_RESPONSES");
+        env::remove_var("PATHFINDER_DISABLE_PENDING");
+        env::remove_var("PATHFINDER_HTTP_RPC_GZIP");
+        env::remove_var("PATHFINDER_EVENT_QUERY_LOG_THRESHOLD");
+        env::remove_var("PATHFINDER_CHAIN");
+        env::remove_var("PATHFINDER_SEQUENCER_MAX_IN_FLIGHT");
+        env::remove_var("PATHFINDER_HTTP_RPC_ALLOWED_METHODS");
+        env::remove_var("PATHFINDER_HTTP_RPC_DENIED_METHODS");
+        env::remove_var("PATHFINDER_WS_RPC_ADDRESS");
+        env::remove_var("PATHFINDER_EVENTS_CACHE_TTL");
+        env::remove_var("PATHFINDER_EVENTS_CACHE_CAPACITY");
+        env::remove_var("PATHFINDER_EVENTS_PAGE_SIZE");
+        env::remove_var("PATHFINDER_EVENTS_MAX_CONCURRENT_QUERIES");
+        env::remove_var("PATHFINDER_EVENTS_SOFT_DEADLINE");
+        env::remove_var("PATHFINDER_RETAIN_RAW_BLOCKS");
+        env::remove_var("PATHFINDER_OPTIMIZE_DATABASE");
+        env::remove_var("PATHFINDER_VACUUM_DATABASE");
+        env::remove_var("PATHFINDER_CHECK_DATABASE_INTEGRITY");
     }
 
     #[test]
@@ -203,6 +553,27 @@ mod tests {
         assert_eq!(cfg.take(ConfigOption::EthereumPassword), Some(value));
     }
 
+    #[test]
+    fn ethereum_auth_header_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--ethereum.auth-header", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EthereumAuthHeader), Some(value));
+    }
+
+    #[test]
+    fn ethereum_auth_header_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_ETHEREUM_API_AUTH_HEADER", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EthereumAuthHeader), Some(value));
+    }
+
     #[test]
     fn config_filepath_short() {
         let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
@@ -265,6 +636,511 @@ mod tests {
         assert_eq!(cfg.take(ConfigOption::DataDirectory), Some(value));
     }
 
+    #[test]
+    fn sequencer_code_fallback_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--sequencer-code-fallback", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerCodeFallback), Some(value));
+    }
+
+    #[test]
+    fn sequencer_code_fallback_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_SEQUENCER_CODE_FALLBACK", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerCodeFallback), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_max_connections_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--http-rpc-max-connections", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcMaxConnections), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_max_connections_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_HTTP_RPC_MAX_CONNECTIONS", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcMaxConnections), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_max_concurrent_calls_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--http-rpc-max-concurrent-calls", &value]).unwrap();
+        assert_eq!(
+            cfg.take(ConfigOption::HttpRpcMaxConcurrentCalls),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn http_rpc_max_concurrent_calls_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_HTTP_RPC_MAX_CONCURRENT_CALLS", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(
+            cfg.take(ConfigOption::HttpRpcMaxConcurrentCalls),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn http_rpc_backlog_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--http-rpc-backlog", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcBacklog), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_backlog_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_HTTP_RPC_BACKLOG", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcBacklog), Some(value));
+    }
+
+    #[test]
+    fn sequencer_timeout_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--sequencer-timeout", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerTimeout), Some(value));
+    }
+
+    #[test]
+    fn sequencer_timeout_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_SEQUENCER_TIMEOUT", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerTimeout), Some(value));
+    }
+
+    #[test]
+    fn partial_block_responses_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--partial-block-responses", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::PartialBlockResponses), Some(value));
+    }
+
+    #[test]
+    fn partial_block_responses_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_PARTIAL_BLOCK_RESPONSES", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::PartialBlockResponses), Some(value));
+    }
+
+    #[test]
+    fn disable_pending_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--disable-pending", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::DisablePending), Some(value));
+    }
+
+    #[test]
+    fn disable_pending_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_DISABLE_PENDING", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::DisablePending), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_gzip_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--http-rpc-gzip", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcGzip), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_gzip_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_HTTP_RPC_GZIP", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcGzip), Some(value));
+    }
+
+    #[test]
+    fn event_query_log_threshold_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--event-query-log-threshold", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventQueryLogThreshold), Some(value));
+    }
+
+    #[test]
+    fn event_query_log_threshold_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_EVENT_QUERY_LOG_THRESHOLD", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventQueryLogThreshold), Some(value));
+    }
+
+    #[test]
+    fn chain_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--chain", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::Chain), Some(value));
+    }
+
+    #[test]
+    fn chain_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_CHAIN", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::Chain), Some(value));
+    }
+
+    #[test]
+    fn sequencer_max_in_flight_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--sequencer-max-in-flight", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerMaxInFlight), Some(value));
+    }
+
+    #[test]
+    fn sequencer_max_in_flight_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_SEQUENCER_MAX_IN_FLIGHT", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerMaxInFlight), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_allowed_methods_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--http-rpc-allowed-methods", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcAllowedMethods), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_allowed_methods_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_HTTP_RPC_ALLOWED_METHODS", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcAllowedMethods), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_denied_methods_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--http-rpc-denied-methods", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcDeniedMethods), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_denied_methods_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_HTTP_RPC_DENIED_METHODS", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcDeniedMethods), Some(value));
+    }
+
+    #[test]
+    fn ws_rpc_address_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--ws-rpc", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::WsRpcAddress), Some(value));
+    }
+
+    #[test]
+    fn ws_rpc_address_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_WS_RPC_ADDRESS", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::WsRpcAddress), Some(value));
+    }
+
+    #[test]
+    fn events_cache_ttl_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--events-cache-ttl", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsCacheTtl), Some(value));
+    }
+
+    #[test]
+    fn events_cache_ttl_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_EVENTS_CACHE_TTL", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsCacheTtl), Some(value));
+    }
+
+    #[test]
+    fn events_cache_capacity_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--events-cache-capacity", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsCacheCapacity), Some(value));
+    }
+
+    #[test]
+    fn events_cache_capacity_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_EVENTS_CACHE_CAPACITY", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsCacheCapacity), Some(value));
+    }
+
+    #[test]
+    fn events_page_size_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--events-page-size", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsPageSize), Some(value));
+    }
+
+    #[test]
+    fn events_page_size_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_EVENTS_PAGE_SIZE", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsPageSize), Some(value));
+    }
+
+    #[test]
+    fn events_max_concurrent_queries_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--events-max-concurrent-queries", &value]).unwrap();
+        assert_eq!(
+            cfg.take(ConfigOption::EventsMaxConcurrentQueries),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn events_max_concurrent_queries_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_EVENTS_MAX_CONCURRENT_QUERIES", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(
+            cfg.take(ConfigOption::EventsMaxConcurrentQueries),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn events_soft_deadline_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--events-soft-deadline", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsSoftDeadline), Some(value));
+    }
+
+    #[test]
+    fn events_soft_deadline_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_EVENTS_SOFT_DEADLINE", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsSoftDeadline), Some(value));
+    }
+
+    #[test]
+    fn retain_raw_blocks_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--retain-raw-blocks", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::RetainRawBlocks), Some(value));
+    }
+
+    #[test]
+    fn retain_raw_blocks_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_RETAIN_RAW_BLOCKS", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::RetainRawBlocks), Some(value));
+    }
+
+    #[test]
+    fn optimize_database_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--optimize-database", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::OptimizeDatabase), Some(value));
+    }
+
+    #[test]
+    fn optimize_database_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_OPTIMIZE_DATABASE", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::OptimizeDatabase), Some(value));
+    }
+
+    #[test]
+    fn vacuum_database_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) = parse_args(vec!["bin name", "--vacuum-database", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::VacuumDatabase), Some(value));
+    }
+
+    #[test]
+    fn vacuum_database_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_VACUUM_DATABASE", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::VacuumDatabase), Some(value));
+    }
+
+    #[test]
+    fn check_database_integrity_long() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        let (_, mut cfg) =
+            parse_args(vec!["bin name", "--check-database-integrity", &value]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::CheckDatabaseIntegrity), Some(value));
+    }
+
+    #[test]
+    fn check_database_integrity_environment_variable() {
+        let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        clear_environment();
+
+        let value = "value".to_owned();
+        env::set_var("PATHFINDER_CHECK_DATABASE_INTEGRITY", &value);
+        let (_, mut cfg) = parse_args(vec!["bin name"]).unwrap();
+        assert_eq!(cfg.take(ConfigOption::CheckDatabaseIntegrity), Some(value));
+    }
+
     #[test]
     fn empty_config() {
         let _env_guard = ENV_VAR_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
@@ -9,6 +9,8 @@ struct EthereumConfig {
     #[serde(rename = "user-agent")]
     user_agent: Option<String>,
     password: Option<String>,
+    #[serde(rename = "auth-header")]
+    auth_header: Option<String>,
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
@@ -18,6 +20,51 @@ struct FileConfig {
     http_rpc: Option<String>,
     #[serde(rename = "data-directory")]
     data_directory: Option<String>,
+    #[serde(rename = "sequencer-code-fallback")]
+    sequencer_code_fallback: Option<String>,
+    #[serde(rename = "http-rpc-max-connections")]
+    http_rpc_max_connections: Option<String>,
+    #[serde(rename = "http-rpc-max-concurrent-calls")]
+    http_rpc_max_concurrent_calls: Option<String>,
+    #[serde(rename = "http-rpc-backlog")]
+    http_rpc_backlog: Option<String>,
+    #[serde(rename = "sequencer-timeout")]
+    sequencer_timeout: Option<String>,
+    #[serde(rename = "partial-block-responses")]
+    partial_block_responses: Option<String>,
+    #[serde(rename = "disable-pending")]
+    disable_pending: Option<String>,
+    #[serde(rename = "http-rpc-gzip")]
+    http_rpc_gzip: Option<String>,
+    #[serde(rename = "event-query-log-threshold")]
+    event_query_log_threshold: Option<String>,
+    chain: Option<String>,
+    #[serde(rename = "sequencer-max-in-flight")]
+    sequencer_max_in_flight: Option<String>,
+    #[serde(rename = "http-rpc-allowed-methods")]
+    http_rpc_allowed_methods: Option<String>,
+    #[serde(rename = "http-rpc-denied-methods")]
+    http_rpc_denied_methods: Option<String>,
+    #[serde(rename = "ws-rpc")]
+    ws_rpc: Option<String>,
+    #[serde(rename = "events-cache-ttl")]
+    events_cache_ttl: Option<String>,
+    #[serde(rename = "events-cache-capacity")]
+    events_cache_capacity: Option<String>,
+    #[serde(rename = "events-page-size")]
+    events_page_size: Option<String>,
+    #[serde(rename = "events-max-concurrent-queries")]
+    events_max_concurrent_queries: Option<String>,
+    #[serde(rename = "events-soft-deadline")]
+    events_soft_deadline: Option<String>,
+    #[serde(rename = "retain-raw-blocks")]
+    retain_raw_blocks: Option<String>,
+    #[serde(rename = "optimize-database")]
+    optimize_database: Option<String>,
+    #[serde(rename = "vacuum-database")]
+    vacuum_database: Option<String>,
+    #[serde(rename = "check-database-integrity")]
+    check_database_integrity: Option<String>,
 }
 
 impl FileConfig {
@@ -27,11 +74,68 @@ impl FileConfig {
             Some(eth) => ConfigBuilder::default()
                 .with(ConfigOption::EthereumHttpUrl, eth.url)
                 .with(ConfigOption::EthereumUserAgent, eth.user_agent)
-                .with(ConfigOption::EthereumPassword, eth.password),
+                .with(ConfigOption::EthereumPassword, eth.password)
+                .with(ConfigOption::EthereumAuthHeader, eth.auth_header),
             None => ConfigBuilder::default(),
         }
         .with(ConfigOption::DataDirectory, self.data_directory)
         .with(ConfigOption::HttpRpcAddress, self.http_rpc)
+        .with(
+            ConfigOption::SequencerCodeFallback,
+            self.sequencer_code_fallback,
+        )
+        .with(
+            ConfigOption::HttpRpcMaxConnections,
+            self.http_rpc_max_connections,
+        )
+        .with(
+            ConfigOption::HttpRpcMaxConcurrentCalls,
+            self.http_rpc_max_concurrent_calls,
+        )
+        .with(ConfigOption::HttpRpcBacklog, self.http_rpc_backlog)
+        .with(ConfigOption::SequencerTimeout, self.sequencer_timeout)
+        .with(
+            ConfigOption::PartialBlockResponses,
+            self.partial_block_responses,
+        )
+        .with(ConfigOption::DisablePending, self.disable_pending)
+        .with(ConfigOption::HttpRpcGzip, self.http_rpc_gzip)
+        .with(
+            ConfigOption::EventQueryLogThreshold,
+            self.event_query_log_threshold,
+        )
+        .with(ConfigOption::Chain, self.chain)
+        .with(
+            ConfigOption::SequencerMaxInFlight,
+            self.sequencer_max_in_flight,
+        )
+        .with(
+            ConfigOption::HttpRpcAllowedMethods,
+            self.http_rpc_allowed_methods,
+        )
+        .with(
+            ConfigOption::HttpRpcDeniedMethods,
+            self.http_rpc_denied_methods,
+        )
+        .with(ConfigOption::WsRpcAddress, self.ws_rpc)
+        .with(ConfigOption::EventsCacheTtl, self.events_cache_ttl)
+        .with(
+            ConfigOption::EventsCacheCapacity,
+            self.events_cache_capacity,
+        )
+        .with(ConfigOption::EventsPageSize, self.events_page_size)
+        .with(
+            ConfigOption::EventsMaxConcurrentQueries,
+            self.events_max_concurrent_queries,
+        )
+        .with(ConfigOption::EventsSoftDeadline, self.events_soft_deadline)
+        .with(ConfigOption::RetainRawBlocks, self.retain_raw_blocks)
+        .with(ConfigOption::OptimizeDatabase, self.optimize_database)
+        .with(ConfigOption::VacuumDatabase, self.vacuum_database)
+        .with(
+            ConfigOption::CheckDatabaseIntegrity,
+            self.check_database_integrity,
+        )
     }
 }
 
@@ -76,24 +180,38 @@ mod tests {
         assert_eq!(cfg.take(ConfigOption::EthereumPassword), Some(value));
     }
 
+    #[test]
+    fn ethereum_auth_header() {
+        let value = "value".to_owned();
+        let toml = format!(r#"ethereum.auth-header = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EthereumAuthHeader), Some(value));
+    }
+
     #[test]
     fn ethereum_section() {
         let user_agent = "user_agent".to_owned();
         let url = "url".to_owned();
         let password = "password".to_owned();
+        let auth_header = "auth_header".to_owned();
 
         let toml = format!(
             r#"[ethereum]
 user-agent = "{}"
 url = "{}"
-password = "{}""#,
-            user_agent, url, password
+password = "{}"
+auth-header = "{}""#,
+            user_agent, url, password, auth_header
         );
 
         let mut cfg = config_from_str(&toml).unwrap();
         assert_eq!(cfg.take(ConfigOption::EthereumUserAgent), Some(user_agent));
         assert_eq!(cfg.take(ConfigOption::EthereumHttpUrl), Some(url));
         assert_eq!(cfg.take(ConfigOption::EthereumPassword), Some(password));
+        assert_eq!(
+            cfg.take(ConfigOption::EthereumAuthHeader),
+            Some(auth_header)
+        );
     }
 
     #[test]
@@ -112,6 +230,196 @@ password = "{}""#,
         assert_eq!(cfg.take(ConfigOption::DataDirectory), Some(value));
     }
 
+    #[test]
+    fn sequencer_code_fallback() {
+        let value = "value".to_owned();
+        let toml = format!(r#"sequencer-code-fallback = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerCodeFallback), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_max_connections() {
+        let value = "value".to_owned();
+        let toml = format!(r#"http-rpc-max-connections = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcMaxConnections), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_max_concurrent_calls() {
+        let value = "value".to_owned();
+        let toml = format!(r#"http-rpc-max-concurrent-calls = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(
+            cfg.take(ConfigOption::HttpRpcMaxConcurrentCalls),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn http_rpc_backlog() {
+        let value = "value".to_owned();
+        let toml = format!(r#"http-rpc-backlog = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcBacklog), Some(value));
+    }
+
+    #[test]
+    fn sequencer_timeout() {
+        let value = "value".to_owned();
+        let toml = format!(r#"sequencer-timeout = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerTimeout), Some(value));
+    }
+
+    #[test]
+    fn partial_block_responses() {
+        let value = "value".to_owned();
+        let toml = format!(r#"partial-block-responses = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::PartialBlockResponses), Some(value));
+    }
+
+    #[test]
+    fn disable_pending() {
+        let value = "value".to_owned();
+        let toml = format!(r#"disable-pending = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::DisablePending), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_gzip() {
+        let value = "value".to_owned();
+        let toml = format!(r#"http-rpc-gzip = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcGzip), Some(value));
+    }
+
+    #[test]
+    fn event_query_log_threshold() {
+        let value = "value".to_owned();
+        let toml = format!(r#"event-query-log-threshold = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventQueryLogThreshold), Some(value));
+    }
+
+    #[test]
+    fn chain() {
+        let value = "value".to_owned();
+        let toml = format!(r#"chain = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::Chain), Some(value));
+    }
+
+    #[test]
+    fn sequencer_max_in_flight() {
+        let value = "value".to_owned();
+        let toml = format!(r#"sequencer-max-in-flight = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::SequencerMaxInFlight), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_allowed_methods() {
+        let value = "value".to_owned();
+        let toml = format!(r#"http-rpc-allowed-methods = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcAllowedMethods), Some(value));
+    }
+
+    #[test]
+    fn http_rpc_denied_methods() {
+        let value = "value".to_owned();
+        let toml = format!(r#"http-rpc-denied-methods = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::HttpRpcDeniedMethods), Some(value));
+    }
+
+    #[test]
+    fn ws_rpc() {
+        let value = "value".to_owned();
+        let toml = format!(r#"ws-rpc = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::WsRpcAddress), Some(value));
+    }
+
+    #[test]
+    fn events_cache_ttl() {
+        let value = "value".to_owned();
+        let toml = format!(r#"events-cache-ttl = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsCacheTtl), Some(value));
+    }
+
+    #[test]
+    fn events_cache_capacity() {
+        let value = "value".to_owned();
+        let toml = format!(r#"events-cache-capacity = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsCacheCapacity), Some(value));
+    }
+
+    #[test]
+    fn events_page_size() {
+        let value = "value".to_owned();
+        let toml = format!(r#"events-page-size = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsPageSize), Some(value));
+    }
+
+    #[test]
+    fn events_max_concurrent_queries() {
+        let value = "value".to_owned();
+        let toml = format!(r#"events-max-concurrent-queries = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(
+            cfg.take(ConfigOption::EventsMaxConcurrentQueries),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn events_soft_deadline() {
+        let value = "value".to_owned();
+        let toml = format!(r#"events-soft-deadline = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::EventsSoftDeadline), Some(value));
+    }
+
+    #[test]
+    fn retain_raw_blocks() {
+        let value = "value".to_owned();
+        let toml = format!(r#"retain-raw-blocks = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::RetainRawBlocks), Some(value));
+    }
+
+    #[test]
+    fn optimize_database() {
+        let value = "value".to_owned();
+        let toml = format!(r#"optimize-database = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::OptimizeDatabase), Some(value));
+    }
+
+    #[test]
+    fn vacuum_database() {
+        let value = "value".to_owned();
+        let toml = format!(r#"vacuum-database = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::VacuumDatabase), Some(value));
+    }
+
+    #[test]
+    fn check_database_integrity() {
+        let value = "value".to_owned();
+        let toml = format!(r#"check-database-integrity = "{}""#, value);
+        let mut cfg = config_from_str(&toml).unwrap();
+        assert_eq!(cfg.take(ConfigOption::CheckDatabaseIntegrity), Some(value));
+    }
+
     #[test]
     fn empty_config() {
         let cfg = config_from_str("").unwrap();
@@ -92,6 +92,10 @@ pub struct StorageAddress(pub StarkHash);
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, PartialOrd, Ord)]
 pub struct StorageValue(pub StarkHash);
 
+/// The nonce of a StarkNet contract.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, PartialOrd, Ord)]
+pub struct ContractNonce(pub StarkHash);
+
 /// A commitment root of the global StarkNet state. This is the entry-point
 /// for the global state at a specific point in time via the global state tree.
 #[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
@@ -105,8 +109,8 @@ pub struct StarknetBlockHash(pub StarkHash);
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct StarknetBlockNumber(pub u64);
 
-/// The timestamp of a Starknet block.
-#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+/// The timestamp of a Starknet block, in seconds since the Unix epoch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct StarknetBlockTimestamp(pub u64);
 
 /// A StarkNet transaction hash.
@@ -138,7 +142,7 @@ pub struct L2ToL1MessagePayloadElem(pub StarkHash);
 pub struct EventData(pub StarkHash);
 
 /// StarkNet transaction event key.
-#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct EventKey(pub StarkHash);
 
 /// StarkNet sequencer address.
@@ -245,6 +249,28 @@ impl From<StarknetBlockHash> for crate::rpc::types::BlockHashOrTag {
 #[error("expected slice length of 16 or less, got {0}")]
 pub struct FromSliceError(usize);
 
+impl ContractAddress {
+    /// Shows up as the contract address of some transactions, but no contract is ever deployed
+    /// at it.
+    pub const ZERO: ContractAddress = ContractAddress(StarkHash::ZERO);
+}
+
+impl StarknetBlockTimestamp {
+    /// The last second of the year 9999. Timestamps in milliseconds of any date since 1978 are
+    /// larger, so that they can be told apart from timestamps in seconds.
+    pub const MAX: StarknetBlockTimestamp = StarknetBlockTimestamp(253_402_300_799);
+
+    /// Constructs a [StarknetBlockTimestamp] from seconds since the Unix epoch.
+    pub const fn from_unix_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    /// Returns the number of seconds since the Unix epoch.
+    pub const fn as_secs(&self) -> u64 {
+        self.0
+    }
+}
+
 impl GasPrice {
     pub const ZERO: GasPrice = GasPrice(0u128);
 
@@ -276,3 +302,140 @@ impl From<u64> for GasPrice {
         Self(u128::from(src))
     }
 }
+
+impl Fee {
+    pub const ZERO: Fee = Fee(H128([0u8; 16]));
+    pub const MAX: Fee = Fee(H128([u8::MAX; 16]));
+
+    /// Returns the amount of this [Fee]. Fees are at most 128 bits, so this never truncates.
+    pub fn to_u128(&self) -> u128 {
+        u128::from_be_bytes(self.0.to_fixed_bytes())
+    }
+
+    /// Constructs [Fee] from a slice of bytes. Big endian byte order is assumed.
+    ///
+    /// Fails instead of truncating if the slice is longer than 16 bytes.
+    pub fn from_be_slice(src: &[u8]) -> Result<Self, FromSliceError> {
+        if src.len() > 16 {
+            return Err(FromSliceError(src.len()));
+        }
+
+        let mut buf = [0u8; 16];
+        buf[16 - src.len()..].copy_from_slice(src);
+
+        Ok(Self(H128(buf)))
+    }
+
+    /// Adds two fees, returning [None] on overflow.
+    pub fn checked_add(self, rhs: Fee) -> Option<Fee> {
+        self.to_u128().checked_add(rhs.to_u128()).map(Fee::from)
+    }
+
+    /// Sums up fees, i.e. of all transactions in a block, returning [None] on overflow.
+    pub fn checked_sum(fees: impl IntoIterator<Item = Fee>) -> Option<Fee> {
+        fees.into_iter()
+            .try_fold(Fee::ZERO, |total, fee| total.checked_add(fee))
+    }
+
+    /// The fee of consuming `gas` at `gas_price`, returning [None] on overflow.
+    pub fn from_gas(gas: u128, gas_price: GasPrice) -> Option<Fee> {
+        gas.checked_mul(gas_price.0).map(Fee::from)
+    }
+}
+
+impl From<u128> for Fee {
+    fn from(amount: u128) -> Self {
+        Self(H128(amount.to_be_bytes()))
+    }
+}
+
+impl From<Fee> for u128 {
+    fn from(fee: Fee) -> Self {
+        fee.to_u128()
+    }
+}
+
+/// Formats the amount in decimal.
+impl std::fmt::Display for Fee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.to_u128(), f)
+    }
+}
+
+/// Formats the amount in hex, without leading zeros. Use `{:#x}` for a `0x` prefix.
+impl std::fmt::LowerHex for Fee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::LowerHex::fmt(&self.to_u128(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod starknet_block_timestamp {
+        use super::super::*;
+
+        #[test]
+        fn round_trips_through_secs() {
+            for secs in [0, 1_654_000_000, StarknetBlockTimestamp::MAX.as_secs()] {
+                let timestamp = StarknetBlockTimestamp::from_unix_secs(secs);
+                assert_eq!(timestamp.as_secs(), secs);
+                assert_eq!(timestamp, StarknetBlockTimestamp(secs));
+            }
+        }
+
+        #[test]
+        fn millis_exceed_max() {
+            let millis = 1_654_000_000_000;
+            assert!(StarknetBlockTimestamp::from_unix_secs(millis) > StarknetBlockTimestamp::MAX);
+        }
+    }
+
+    mod fee {
+        use super::super::*;
+
+        #[test]
+        fn round_trips_through_u128() {
+            for amount in [0, 1, u128::MAX] {
+                assert_eq!(Fee::from(amount).to_u128(), amount);
+                assert_eq!(u128::from(Fee::from(amount)), amount);
+            }
+            assert_eq!(Fee::from(0), Fee::ZERO);
+            assert_eq!(Fee::from(u128::MAX), Fee::MAX);
+            assert_eq!(Fee(H128::zero()), Fee::ZERO);
+        }
+
+        #[test]
+        fn from_be_slice() {
+            assert_eq!(Fee::from_be_slice(&[]).unwrap(), Fee::ZERO);
+            assert_eq!(Fee::from_be_slice(&[1, 0]).unwrap(), Fee::from(256));
+            assert_eq!(Fee::from_be_slice(&[u8::MAX; 16]).unwrap(), Fee::MAX);
+            Fee::from_be_slice(&[0u8; 17]).unwrap_err();
+        }
+
+        #[test]
+        fn overflow_is_detected() {
+            assert_eq!(Fee::MAX.checked_add(Fee::ZERO), Some(Fee::MAX));
+            assert_eq!(Fee::MAX.checked_add(Fee::from(1)), None);
+
+            assert_eq!(
+                Fee::checked_sum([Fee::from(1), Fee::from(2), Fee::from(3)]),
+                Some(Fee::from(6))
+            );
+            assert_eq!(Fee::checked_sum(Vec::new()), Some(Fee::ZERO));
+            assert_eq!(Fee::checked_sum([Fee::MAX, Fee::from(1)]), None);
+
+            assert_eq!(Fee::from_gas(10, GasPrice(7)), Some(Fee::from(70)));
+            assert_eq!(Fee::from_gas(u128::MAX, GasPrice(2)), None);
+        }
+
+        #[test]
+        fn display() {
+            assert_eq!(Fee::ZERO.to_string(), "0");
+            assert_eq!(format!("{:#x}", Fee::ZERO), "0x0");
+            assert_eq!(Fee::from(255).to_string(), "255");
+            assert_eq!(format!("{:#x}", Fee::from(255)), "0xff");
+            assert_eq!(Fee::MAX.to_string(), u128::MAX.to_string());
+            assert_eq!(format!("{:x}", Fee::MAX), "f".repeat(32));
+        }
+    }
+}
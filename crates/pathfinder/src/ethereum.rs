@@ -21,6 +21,33 @@ pub enum Chain {
     Goerli,
 }
 
+impl std::str::FromStr for Chain {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mainnet" => Ok(Chain::Mainnet),
+            "goerli" => Ok(Chain::Goerli),
+            other => anyhow::bail!("Unknown chain: {}, expected mainnet or goerli", other),
+        }
+    }
+}
+
+impl Chain {
+    /// Returns the `detected` chain of the Ethereum endpoint, failing if it is not the
+    /// `configured` chain.
+    pub fn verify(configured: Option<Chain>, detected: Chain) -> Result<Chain> {
+        match configured {
+            Some(configured) if configured != detected => anyhow::bail!(
+                "Ethereum endpoint is on {:?} but {:?} was configured",
+                detected,
+                configured
+            ),
+            _ => Ok(detected),
+        }
+    }
+}
+
 /// List of semi-official Ethereum RPC errors taken from [EIP-1474] (which is stagnant).
 ///
 /// The issue of standardizing the Ethereum RPC seems to now be taking
@@ -148,6 +175,24 @@ mod tests {
 
     mod chain {
         use super::*;
+
+        #[test]
+        fn verify() {
+            assert_eq!(Chain::verify(None, Chain::Goerli).unwrap(), Chain::Goerli);
+            assert_eq!(
+                Chain::verify(Some(Chain::Mainnet), Chain::Mainnet).unwrap(),
+                Chain::Mainnet
+            );
+        }
+
+        #[test]
+        fn verify_mismatch() {
+            let error = Chain::verify(Some(Chain::Mainnet), Chain::Goerli).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "Ethereum endpoint is on Goerli but Mainnet was configured"
+            );
+        }
         use crate::ethereum::transport::{EthereumTransport, HttpTransport};
 
         #[tokio::test]
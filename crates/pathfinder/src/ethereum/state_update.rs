@@ -6,7 +6,7 @@ use pedersen::StarkHash;
 use retrieve::*;
 
 use crate::{
-    core::{ContractAddress, ContractHash, StorageAddress, StorageValue},
+    core::{ContractAddress, ContractHash, ContractNonce, StorageAddress, StorageValue},
     ethereum::{
         log::StateUpdateLog,
         state_update::{parse::StateUpdateParser, retrieve::retrieve_transition_fact},
@@ -37,13 +37,23 @@ pub struct StorageUpdate {
     pub value: StorageValue,
 }
 
+/// A StarkNet contract's nonce update.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NonceUpdate {
+    pub address: ContractAddress,
+    pub nonce: ContractNonce,
+}
+
 /// The set of state updates of a StarkNet [StateUpdate].
 ///
-/// Contains new [DeployedContracts](DeployedContract) as well as [ContractUpdates](ContractUpdate).
+/// Contains new [DeployedContracts](DeployedContract) as well as [ContractUpdates](ContractUpdate)
+/// and [NonceUpdates](NonceUpdate). Nonces are not part of the state updates published on L1,
+/// so they are only present in updates received from L2.
 #[derive(Debug, Clone, PartialEq)]
 pub struct StateUpdate {
     pub deployed_contracts: Vec<DeployedContract>,
     pub contract_updates: Vec<ContractUpdate>,
+    pub nonce_updates: Vec<NonceUpdate>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -148,6 +158,7 @@ mod tests {
             .unwrap();
 
         let expected = StateUpdate {
+            nonce_updates: vec![],
             deployed_contracts: vec![DeployedContract {
                 address: ContractAddress(StarkHash::from_hex_str("6CF1C6DCA6DE4CE15DB3EB7AEE1C6191537C82E2F2DE22FE4426199EE50E9A").unwrap()),
                 hash: ContractHash(StarkHash::from_hex_str("484DE75F165C844F9D8C5B07A7D1A650A476815DC7A061126FD41BB998C043D").unwrap()),
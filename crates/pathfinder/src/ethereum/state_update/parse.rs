@@ -43,6 +43,7 @@ impl StateUpdateParser {
         Ok(StateUpdate {
             deployed_contracts,
             contract_updates,
+            nonce_updates: Vec::new(),
         })
     }
 
@@ -461,6 +462,7 @@ mod tests {
             let fact = StateUpdate {
                 deployed_contracts: vec![deployed_contract(), deployed_contract()],
                 contract_updates: vec![contract_update(), contract_update()],
+                nonce_updates: Vec::new(),
             };
 
             let data: Vec<U256> = fact.clone().into();
@@ -474,6 +476,7 @@ mod tests {
             let fact = StateUpdate {
                 deployed_contracts: Vec::new(),
                 contract_updates: vec![contract_update(), contract_update()],
+                nonce_updates: Vec::new(),
             };
 
             let data: Vec<U256> = fact.clone().into();
@@ -487,6 +490,7 @@ mod tests {
             let fact = StateUpdate {
                 deployed_contracts: vec![deployed_contract(), deployed_contract()],
                 contract_updates: Vec::new(),
+                nonce_updates: Vec::new(),
             };
 
             let data: Vec<U256> = fact.clone().into();
@@ -500,6 +504,7 @@ mod tests {
             let fact = StateUpdate {
                 deployed_contracts: Vec::new(),
                 contract_updates: Vec::new(),
+                nonce_updates: Vec::new(),
             };
 
             let data: Vec<U256> = fact.clone().into();
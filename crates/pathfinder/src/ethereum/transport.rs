@@ -49,9 +49,16 @@ pub trait EthereumTransport {
 /// `backoff [secs] = min((2 ^ N) * 15, 3600) [secs]`
 ///
 /// where `N` is the consecutive retry iteration number `{1, 2, ...}`.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct HttpTransport(Web3<Http>);
 
+impl std::fmt::Debug for HttpTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The underlying transport's URL may contain the Ethereum password.
+        f.write_str("HttpTransport(..)")
+    }
+}
+
 impl HttpTransport {
     /// Creates new [`HttpTransport`] from [`Web3<Http>`]
     pub fn new(http: Web3<Http>) -> Self {
@@ -64,17 +71,36 @@ impl HttpTransport {
     /// - the [Url](reqwest::Url)
     /// - the user-agent (if provided)
     /// - the password (if provided)
+    /// - the custom auth header (if provided)
+    ///
+    /// The custom auth header takes precedence over the password: if both are provided,
+    /// the password is __not__ set on the URL. This prevents the basic authentication
+    /// derived from the URL from clobbering an `Authorization` header.
     pub fn from_config(config: EthereumConfig) -> anyhow::Result<Self> {
-        let client = reqwest::Client::builder();
+        use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
-        let client = client
-            .user_agent(crate::consts::USER_AGENT)
-            .build()
-            .context("Creating HTTP client")?;
+        let mut client = reqwest::Client::builder().user_agent(crate::consts::USER_AGENT);
 
         let mut url = config.url;
-        url.set_password(config.password.as_deref())
-            .map_err(|_| anyhow::anyhow!("Setting password"))?;
+        match config.auth_header {
+            Some((name, value)) => {
+                let name =
+                    HeaderName::from_bytes(name.as_bytes()).context("Parsing auth header name")?;
+                let mut value =
+                    HeaderValue::from_str(&value).context("Parsing auth header value")?;
+                value.set_sensitive(true);
+
+                let mut headers = HeaderMap::new();
+                headers.insert(name, value);
+                client = client.default_headers(headers);
+            }
+            None => {
+                url.set_password(config.password.as_deref())
+                    .map_err(|_| anyhow::anyhow!("Setting password"))?;
+            }
+        }
+
+        let client = client.build().context("Creating HTTP client")?;
 
         let client = Http::with_client(client, url);
 
@@ -253,6 +279,70 @@ impl std::ops::Deref for HttpTransport {
 
 #[cfg(test)]
 mod tests {
+    #[tokio::test]
+    async fn auth_header() {
+        use super::{EthereumTransport, HttpTransport};
+        use crate::config::EthereumConfig;
+        use warp::Filter;
+
+        // Record the header instead of asserting in the handler, as a failing
+        // request would otherwise be retried indefinitely.
+        let (header_tx, mut header_rx) = tokio::sync::mpsc::unbounded_channel();
+        let filter = warp::header::optional::<String>("authorization")
+            .and(warp::body::json())
+            .map(move |auth: Option<String>, request: serde_json::Value| {
+                header_tx.send(auth).unwrap();
+
+                warp::reply::json(&serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": "0x10",
+                }))
+            });
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+        let (addr, run_srv) =
+            warp::serve(filter).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async {
+                shutdown_rx.await.ok();
+            });
+        let server_handle = tokio::spawn(run_srv);
+
+        let config = EthereumConfig {
+            url: format!("http://{}", addr).parse().unwrap(),
+            user_agent: None,
+            password: Some("ignored".to_owned()),
+            auth_header: Some(("Authorization".to_owned(), "Bearer secret".to_owned())),
+        };
+        let transport = HttpTransport::from_config(config).unwrap();
+
+        let block_number = transport.block_number().await.unwrap();
+        assert_eq!(block_number, 16);
+        assert_eq!(
+            header_rx.recv().await.unwrap().as_deref(),
+            Some("Bearer secret")
+        );
+
+        shutdown_tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[test]
+    fn debug_masks_password() {
+        use super::HttpTransport;
+        use crate::config::EthereumConfig;
+
+        let config = EthereumConfig {
+            url: "https://localhost:8545".parse().unwrap(),
+            user_agent: None,
+            password: Some("secret".to_owned()),
+            auth_header: None,
+        };
+        let transport = HttpTransport::from_config(config).unwrap();
+
+        let debug = format!("{:?}", transport);
+        assert!(!debug.contains("secret"), "{}", debug);
+    }
+
     mod logs {
         use crate::ethereum::{
             transport::{EthereumTransport, HttpTransport, LogsError},
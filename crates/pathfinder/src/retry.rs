@@ -196,6 +196,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn backoff_sequence() {
+        use super::{MaybeLimited, Strategy};
+
+        // Delays are in milliseconds in tests.
+        let strategy = Strategy {
+            base_secs: NonZeroU64::new(2).unwrap(),
+            factor: NonZeroU64::new(10).unwrap(),
+            max_delay: Some(Duration::from_millis(100)),
+            max_num_retries: Some(NonZeroUsize::new(5).unwrap()),
+        };
+
+        let delays = MaybeLimited::from(strategy)
+            .map(|d| d.as_millis())
+            .collect::<Vec<_>>();
+        // 2^N * 10, saturating at 100
+        assert_eq!(delays, vec![20, 40, 80, 100, 100]);
+    }
+
     mod unconditional {
         use super::*;
 
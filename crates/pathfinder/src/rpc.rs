@@ -1,48 +1,152 @@
 //! StarkNet node JSON-RPC related modules.
 pub mod api;
+pub mod compression;
+pub mod events_cache;
+pub mod latest_block;
+pub mod listener;
+pub mod metrics;
 pub mod serde;
 pub mod types;
 
 use crate::{
     core::{
-        CallSignatureElem, ConstructorParam, ContractAddress, ContractAddressSalt, Fee,
-        StarknetTransactionHash, StarknetTransactionIndex, TransactionVersion,
+        CallSignatureElem, ConstructorParam, ContractAddress, ContractAddressSalt, ContractHash,
+        Fee, GlobalRoot, StarknetBlockHash, StarknetBlockNumber, StarknetTransactionIndex,
+        TransactionVersion,
     },
     rpc::{
         api::RpcApi,
+        listener::ListenerConfig,
         serde::{CallSignatureElemAsDecimalStr, FeeAsHexStr, TransactionVersionAsHexStr},
         types::{
-            request::OverflowingStorageAddress,
-            request::{BlockResponseScope, Call, EventFilter},
-            BlockHashOrTag, BlockNumberOrTag,
+            reply::EmittedEvent,
+            request::{BlockResponseScope, Call, EventFilter, EventSubscriptionFilter},
+            request::{OverflowingStorageAddress, UncheckedTransactionHash},
+            BlockHashOrTag, BlockId, BlockNumberOrTag, BlockNumberTagOrOffset,
         },
     },
     sequencer::request::add_transaction::ContractDefinition,
+    state::SyncState,
+    storage::{StarknetEventFilter, StarknetEventsTable, Storage},
 };
 use ::serde::Deserialize;
+use anyhow::Context;
 use jsonrpsee::{
     http_server::{HttpServerBuilder, HttpServerHandle, RpcModule},
     types::Error,
+    ws_server::{WsServerBuilder, WsServerHandle},
 };
 
-use std::{net::SocketAddr, result::Result};
+use std::{net::SocketAddr, result::Result, sync::Arc};
+
+/// Restricts the methods offered by the HTTP-RPC server.
+///
+/// Methods which are not offered are not registered at all, so requests for them fail with
+/// `Method not found` (-32601).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MethodFilter {
+    /// Only these methods are offered, or all of them if [None].
+    pub allowed: Option<Vec<String>>,
+    /// These methods are not offered, even if they are [allowed](MethodFilter::allowed).
+    pub denied: Vec<String>,
+}
+
+impl MethodFilter {
+    /// Whether the method with `method_name` is offered.
+    pub fn allows(&self, method_name: &str) -> bool {
+        let allowed = match &self.allowed {
+            Some(allowed) => allowed.iter().any(|name| name == method_name),
+            None => true,
+        };
+
+        allowed && !self.denied.iter().any(|name| name == method_name)
+    }
+}
+
+/// The error code of calls rejected because the server is already handling the
+/// [maximum number of concurrent calls](ListenerConfig::max_concurrent_calls).
+pub const SERVER_BUSY_CODE: i32 = -32005;
+
+/// The error returned for calls exceeding the concurrent call limit.
+fn server_busy() -> Error {
+    Error::Call(jsonrpsee::types::CallError::Custom {
+        code: SERVER_BUSY_CODE,
+        message: "Server is busy, try again later".to_owned(),
+        data: None,
+    })
+}
+
+/// The context of the methods registered through [RpcModuleWrapper]: the context of the method
+/// implementations, along with the concurrent call limit shared by all methods.
+struct LimitedContext<Context> {
+    inner: Arc<Context>,
+    /// Each call holds a permit until it completes. Unlimited if [None].
+    concurrent_calls: Option<tokio::sync::Semaphore>,
+}
+
+impl<Context> LimitedContext<Context> {
+    /// Acquires a permit for a call, failing with [server_busy] if the limit is reached.
+    fn acquire(&self) -> Result<Option<tokio::sync::SemaphorePermit<'_>>, Error> {
+        match &self.concurrent_calls {
+            Some(semaphore) => match semaphore.try_acquire() {
+                Ok(permit) => Ok(Some(permit)),
+                Err(_) => {
+                    tracing::debug!("Rejecting call above the concurrent call limit");
+                    Err(server_busy())
+                }
+            },
+            None => Ok(None),
+        }
+    }
+}
 
-/// Helper wrapper for attaching spans to rpc method implementations
-struct RpcModuleWrapper<Context>(jsonrpsee::RpcModule<Context>);
+/// Helper wrapper for attaching spans to rpc method implementations, for leaving out the
+/// methods which are not allowed by the [MethodFilter], and for limiting the number of calls
+/// handled concurrently.
+struct RpcModuleWrapper<Context> {
+    module: jsonrpsee::RpcModule<LimitedContext<Context>>,
+    filter: MethodFilter,
+    /// Names of the methods left out because of the filter.
+    disabled: Vec<&'static str>,
+}
 
 impl<Context: Send + Sync + 'static> RpcModuleWrapper<Context> {
+    fn new(context: Context, filter: MethodFilter, max_concurrent_calls: Option<usize>) -> Self {
+        let context = LimitedContext {
+            inner: Arc::new(context),
+            concurrent_calls: max_concurrent_calls.map(tokio::sync::Semaphore::new),
+        };
+        Self {
+            module: RpcModule::new(context),
+            filter,
+            disabled: Vec::new(),
+        }
+    }
+
+    /// Returns `false` and logs that the method is disabled, if the filter does not allow it.
+    fn allows(&mut self, method_name: &'static str) -> bool {
+        if self.filter.allows(method_name) {
+            return true;
+        }
+
+        tracing::info!(method = %method_name, "HTTP-RPC method disabled");
+        self.disabled.push(method_name);
+        false
+    }
+
     /// This wrapper helper adds a tracing span around all rpc methods with name = method_name.
     ///
-    /// It could do more, for example trace the outputs, durations.
+    /// It could do more, for example trace the outputs, durations. Methods which the filter does
+    /// not allow are skipped. Calls above the concurrent call limit fail with [server_busy]
+    /// without being handled.
     ///
-    /// This is the only one method provided at the moment, because it's the only one used. If you
-    /// need to use some other `register_*` method from [`jsonrpsee::RpcModule`], just add it to
-    /// this wrapper.
+    /// Only the `register_*` methods in use are provided. If you need to use some other
+    /// `register_*` method from [`jsonrpsee::RpcModule`], just add it to this wrapper.
     fn register_async_method<R, Fun, Fut>(
         &mut self,
         method_name: &'static str,
         callback: Fun,
-    ) -> Result<jsonrpsee::utils::server::rpc_module::MethodResourcesBuilder, jsonrpsee::types::Error>
+    ) -> Result<(), jsonrpsee::types::Error>
     where
         R: ::serde::Serialize + Send + Sync + 'static,
         Fut: std::future::Future<Output = Result<R, Error>> + Send,
@@ -54,23 +158,126 @@ impl<Context: Send + Sync + 'static> RpcModuleWrapper<Context> {
     {
         use tracing::Instrument;
 
-        self.0.register_async_method(method_name, move |p, c| {
-            // why info here? it's the same used in warp tracing filter for example.
-            let span = tracing::info_span!("rpc_method", name = method_name);
-            callback(p, c).instrument(span)
-        })
+        if !self.allows(method_name) {
+            return Ok(());
+        }
+
+        self.module
+            .register_async_method(method_name, move |p, c| {
+                // why info here? it's the same used in warp tracing filter for example.
+                let span = tracing::info_span!("rpc_method", name = method_name);
+                async move {
+                    let _permit = c.acquire()?;
+                    callback(p, c.inner.clone()).await
+                }
+                .instrument(span)
+            })
+            .map(|_| ())
+    }
+
+    /// Same as [`Self::register_async_method`] but for synchronous methods.
+    fn register_method<R, Fun>(
+        &mut self,
+        method_name: &'static str,
+        callback: Fun,
+    ) -> Result<(), jsonrpsee::types::Error>
+    where
+        R: ::serde::Serialize + 'static,
+        Fun: Fn(jsonrpsee::types::v2::Params<'_>, &Context) -> Result<R, Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        if !self.allows(method_name) {
+            return Ok(());
+        }
+
+        self.module
+            .register_method(method_name, move |p, c| {
+                let _span = tracing::info_span!("rpc_method", name = method_name).entered();
+                let _permit = c.acquire()?;
+                callback(p, &c.inner)
+            })
+            .map(|_| ())
+    }
+
+    /// Names of all registered methods.
+    fn method_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.module.method_names()
+    }
+
+    /// Logs the names in the filter which match none of the methods, i.e. because of a typo.
+    fn warn_unknown_filter_names(&self) {
+        let filter = &self.filter;
+        for name in filter.allowed.iter().flatten().chain(&filter.denied) {
+            let known = self.method_names().any(|method| method == name)
+                || self.disabled.iter().any(|method| method == name);
+            if !known {
+                tracing::warn!(method = %name, "Unknown HTTP-RPC method in the method filter");
+            }
+        }
     }
 
-    fn into_inner(self) -> jsonrpsee::RpcModule<Context> {
-        self.0
+    fn into_inner(self) -> jsonrpsee::RpcModule<LimitedContext<Context>> {
+        self.module
     }
 }
 
 /// Starts the HTTP-RPC server.
-pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, SocketAddr), Error> {
-    let server = HttpServerBuilder::default().build(addr)?;
-    let local_addr = server.local_addr()?;
-    let mut module = RpcModuleWrapper(RpcModule::new(api));
+///
+/// Requests which do not conform to the JSON-RPC 2.0 envelope, i.e. because the `"jsonrpc": "2.0"`
+/// member is missing or has a different version, are rejected by [jsonrpsee] with an
+/// `Invalid Request` (-32600) error.
+///
+/// Requests must have a JSON content type, `application/json` optionally followed by a
+/// `charset=utf-8` parameter, matched case-insensitively. Requests with any other content type
+/// are rejected with `415 Unsupported Media Type`.
+pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(ServerHandle, SocketAddr), Error> {
+    run_server_with(
+        addr,
+        api,
+        ListenerConfig::default(),
+        MethodFilter::default(),
+    )
+}
+
+/// Starts the HTTP-RPC server like [run_server], listening on `addr` given as a `host:port` string,
+/// i.e. `localhost:9545` or `[::1]:9545`. Hostnames are resolved, listening on the first address
+/// they resolve to.
+pub fn run_server_on(addr: &str, api: RpcApi) -> Result<(ServerHandle, SocketAddr), Error> {
+    run_server(resolve_listen_addr(addr)?, api)
+}
+
+/// Resolves a `host:port` string to the socket address to listen on.
+fn resolve_listen_addr(addr: &str) -> Result<SocketAddr, Error> {
+    use std::net::ToSocketAddrs;
+
+    addr.to_socket_addrs()
+        .map_err(|err| Error::Custom(format!("Invalid listening address ({}): {}", addr, err)))?
+        .next()
+        .ok_or_else(|| {
+            Error::Custom(format!(
+                "Listening address ({}) does not resolve to any address",
+                addr
+            ))
+        })
+}
+
+/// Starts the HTTP-RPC server, listening on a socket configured according to [ListenerConfig] and
+/// offering the methods allowed by the [MethodFilter].
+pub fn run_server_with(
+    addr: SocketAddr,
+    api: RpcApi,
+    listener: ListenerConfig,
+    methods: MethodFilter,
+) -> Result<(ServerHandle, SocketAddr), Error> {
+    let socket = listener::bind(addr, listener.backlog)?;
+    let local_addr = socket.local_addr()?;
+    // jsonrpsee binds its listening socket itself and accepts connections without limit, so it
+    // only listens on the loopback interface behind a front server serving `socket`.
+    let server = HttpServerBuilder::default().build(listener::loopback(addr))?;
+    let front = listener::serve(socket, server.local_addr()?, &listener)?;
+    let mut module = RpcModuleWrapper::new(api, methods, listener.max_concurrent_calls);
     module.register_async_method("starknet_getBlockByHash", |params, context| async move {
         #[derive(Debug, Deserialize)]
         pub struct NamedArgs {
@@ -86,7 +293,7 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
     module.register_async_method("starknet_getBlockByNumber", |params, context| async move {
         #[derive(Debug, Deserialize)]
         pub struct NamedArgs {
-            pub block_number: BlockNumberOrTag,
+            pub block_number: BlockNumberTagOrOffset,
             #[serde(default)]
             pub requested_scope: Option<BlockResponseScope>,
         }
@@ -95,6 +302,14 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
             .get_block_by_number(params.block_number, params.requested_scope)
             .await
     })?;
+    module.register_async_method("starknet_getRawBlock", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub block_hash: StarknetBlockHash,
+        }
+        let params = params.parse::<NamedArgs>()?;
+        context.get_raw_block(params.block_hash).await
+    })?;
     // module.register_async_method(
     //     "starknet_getStateUpdateByHash",
     //     |params, context| async move {
@@ -116,23 +331,98 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
             pub contract_address: ContractAddress,
             // Accept overflowing type here to report INVALID_STORAGE_KEY properly
             pub key: OverflowingStorageAddress,
-            pub block_hash: BlockHashOrTag,
+            pub block_hash: BlockId,
         }
         let params = params.parse::<NamedArgs>()?;
         context
             .get_storage_at(params.contract_address, params.key, params.block_hash)
             .await
     })?;
+    module.register_async_method("starknet_getStorageAtRoot", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub contract_address: ContractAddress,
+            // Accept overflowing type here to report INVALID_STORAGE_KEY properly
+            pub key: OverflowingStorageAddress,
+            pub root: GlobalRoot,
+        }
+        let params = params.parse::<NamedArgs>()?;
+        context
+            .get_storage_at_root(params.contract_address, params.key, params.root)
+            .await
+    })?;
+    module.register_async_method("starknet_getStorageAtKeys", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub contract_address: ContractAddress,
+            // Accept overflowing type here to report invalid keys individually
+            pub keys: Vec<OverflowingStorageAddress>,
+            pub block_hash: BlockHashOrTag,
+        }
+        let params = params.parse::<NamedArgs>()?;
+        context
+            .get_storage_at_keys(params.contract_address, params.keys, params.block_hash)
+            .await
+    })?;
+    module.register_async_method("starknet_getStorageDiff", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub contract_address: ContractAddress,
+            pub from_block: BlockId,
+            pub to_block: BlockId,
+        }
+        let params = params.parse::<NamedArgs>()?;
+        context
+            .get_storage_diff(params.contract_address, params.from_block, params.to_block)
+            .await
+    })?;
+    module.register_async_method("starknet_getNonce", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub contract_address: ContractAddress,
+            pub block_id: BlockId,
+        }
+        let params = params.parse::<NamedArgs>()?;
+        context
+            .get_nonce(params.contract_address, params.block_id)
+            .await
+    })?;
+    module.register_async_method("starknet_getContractCount", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub block_id: BlockId,
+        }
+        context
+            .get_contract_count(params.parse::<NamedArgs>()?.block_id)
+            .await
+    })?;
     module.register_async_method(
         "starknet_getTransactionByHash",
         |params, context| async move {
             #[derive(Debug, Deserialize)]
             pub struct NamedArgs {
-                pub transaction_hash: StarknetTransactionHash,
+                // Accept unchecked type here to report INVALID_TX_HASH for malformed hashes
+                pub transaction_hash: UncheckedTransactionHash,
             }
-            context
-                .get_transaction_by_hash(params.parse::<NamedArgs>()?.transaction_hash)
-                .await
+            let transaction_hash = params.parse::<NamedArgs>()?.transaction_hash.parse()?;
+            context.get_transaction_by_hash(transaction_hash).await
+        },
+    )?;
+    module.register_async_method(
+        "starknet_getTransactionsByHashes",
+        |params, context| async move {
+            #[derive(Debug, Deserialize)]
+            pub struct NamedArgs {
+                // Accept unchecked type here to report INVALID_TX_HASH for malformed hashes
+                pub hashes: Vec<UncheckedTransactionHash>,
+            }
+            let hashes = params
+                .parse::<NamedArgs>()?
+                .hashes
+                .iter()
+                .map(UncheckedTransactionHash::parse)
+                .collect::<Result<Vec<_>, _>>()?;
+            context.get_transactions_by_hashes(hashes).await
         },
     )?;
     module.register_async_method(
@@ -168,20 +458,43 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
         |params, context| async move {
             #[derive(Debug, Deserialize)]
             pub struct NamedArgs {
-                pub transaction_hash: StarknetTransactionHash,
+                // Accept unchecked type here to report INVALID_TX_HASH for malformed hashes
+                pub transaction_hash: UncheckedTransactionHash,
             }
-            context
-                .get_transaction_receipt(params.parse::<NamedArgs>()?.transaction_hash)
-                .await
+            let transaction_hash = params.parse::<NamedArgs>()?.transaction_hash.parse()?;
+            context.get_transaction_receipt(transaction_hash).await
         },
     )?;
     module.register_async_method("starknet_getCode", |params, context| async move {
         #[derive(Debug, Deserialize)]
         pub struct NamedArgs {
             pub contract_address: ContractAddress,
+            #[serde(default)]
+            pub include_events: bool,
+        }
+        let params = params.parse::<NamedArgs>()?;
+        context
+            .get_code(params.contract_address, params.include_events)
+            .await
+    })?;
+    module.register_async_method("starknet_getAbi", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub contract_address: ContractAddress,
+            pub block_hash: BlockHashOrTag,
+        }
+        let params = params.parse::<NamedArgs>()?;
+        context
+            .get_abi(params.contract_address, params.block_hash)
+            .await
+    })?;
+    module.register_async_method("starknet_classExists", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub class_hash: ContractHash,
         }
         context
-            .get_code(params.parse::<NamedArgs>()?.contract_address)
+            .class_exists(params.parse::<NamedArgs>()?.class_hash)
             .await
     })?;
     module.register_async_method(
@@ -189,7 +502,7 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
         |params, context| async move {
             #[derive(Debug, Deserialize)]
             pub struct NamedArgs {
-                pub block_hash: BlockHashOrTag,
+                pub block_hash: BlockId,
             }
             context
                 .get_block_transaction_count_by_hash(params.parse::<NamedArgs>()?.block_hash)
@@ -201,7 +514,7 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
         |params, context| async move {
             #[derive(Debug, Deserialize)]
             pub struct NamedArgs {
-                pub block_number: BlockNumberOrTag,
+                pub block_number: BlockId,
             }
             context
                 .get_block_transaction_count_by_number(params.parse::<NamedArgs>()?.block_number)
@@ -212,14 +525,44 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
         #[derive(Debug, Deserialize)]
         pub struct NamedArgs {
             pub request: Call,
-            pub block_hash: BlockHashOrTag,
+            pub block_hash: BlockId,
         }
         let params = params.parse::<NamedArgs>()?;
         context.call(params.request, params.block_hash).await
     })?;
+    module.register_async_method("starknet_simulateCalls", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub calls: Vec<Call>,
+            pub block_id: BlockId,
+        }
+        let params = params.parse::<NamedArgs>()?;
+        context.simulate_calls(params.calls, params.block_id).await
+    })?;
+    module.register_async_method("starknet_traceTransaction", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            // Accept unchecked type here to report INVALID_TX_HASH for malformed hashes
+            pub transaction_hash: UncheckedTransactionHash,
+        }
+        let transaction_hash = params.parse::<NamedArgs>()?.transaction_hash.parse()?;
+        context.trace_transaction(transaction_hash).await
+    })?;
     module.register_async_method("starknet_blockNumber", |_, context| async move {
         context.block_number().await
     })?;
+    module.register_async_method(
+        "starknet_getRecentBlockHashes",
+        |params, context| async move {
+            #[derive(Debug, Deserialize)]
+            pub struct NamedArgs {
+                pub n: usize,
+            }
+            context
+                .get_recent_block_hashes(params.parse::<NamedArgs>()?.n)
+                .await
+        },
+    )?;
     module.register_async_method("starknet_chainId", |_, context| async move {
         context.chain_id().await
     })?;
@@ -232,6 +575,57 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
     module.register_async_method("starknet_syncing", |_, context| async move {
         context.syncing().await
     })?;
+    module.register_async_method("starknet_syncStats", |_, context| async move {
+        context.sync_stats().await
+    })?;
+    module.register_async_method("starknet_l1ConfirmedBlock", |_, context| async move {
+        context.l1_confirmed_block().await
+    })?;
+    module.register_async_method("starknet_getEventKeyStats", |params, context| async move {
+        #[derive(Debug, Deserialize)]
+        pub struct NamedArgs {
+            pub block_number: BlockNumberOrTag,
+        }
+        context
+            .get_event_key_stats(params.parse::<NamedArgs>()?.block_number)
+            .await
+    })?;
+    module.register_async_method(
+        "starknet_getTransactionEvents",
+        |params, context| async move {
+            #[derive(Debug, Deserialize)]
+            pub struct NamedArgs {
+                // Accept unchecked type here to report INVALID_TX_HASH for malformed hashes
+                pub transaction_hash: UncheckedTransactionHash,
+            }
+            let transaction_hash = params.parse::<NamedArgs>()?.transaction_hash.parse()?;
+            context.get_transaction_events(transaction_hash).await
+        },
+    )?;
+    module.register_async_method(
+        "starknet_getTransactionIndex",
+        |params, context| async move {
+            #[derive(Debug, Deserialize)]
+            pub struct NamedArgs {
+                // Accept unchecked type here to report INVALID_TX_HASH for malformed hashes
+                pub transaction_hash: UncheckedTransactionHash,
+            }
+            let transaction_hash = params.parse::<NamedArgs>()?.transaction_hash.parse()?;
+            context.get_transaction_index(transaction_hash).await
+        },
+    )?;
+    module.register_async_method(
+        "starknet_getMessagesByTransaction",
+        |params, context| async move {
+            #[derive(Debug, Deserialize)]
+            pub struct NamedArgs {
+                // Accept unchecked type here to report INVALID_TX_HASH for malformed hashes
+                pub transaction_hash: UncheckedTransactionHash,
+            }
+            let transaction_hash = params.parse::<NamedArgs>()?.transaction_hash.parse()?;
+            context.get_messages_by_transaction(transaction_hash).await
+        },
+    )?;
     module.register_async_method("starknet_getEvents", |params, context| async move {
         #[derive(Debug, Deserialize)]
         struct NamedArgs {
@@ -253,6 +647,8 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
                 pub max_fee: Fee,
                 #[serde_as(as = "TransactionVersionAsHexStr")]
                 pub version: TransactionVersion,
+                #[serde(default)]
+                pub dry_run: bool,
             }
             let params = params.parse::<NamedArgs>()?;
             context
@@ -261,6 +657,7 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
                     params.signature,
                     params.max_fee,
                     params.version,
+                    params.dry_run,
                 )
                 .await
         },
@@ -273,6 +670,8 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
                 pub contract_address_salt: ContractAddressSalt,
                 pub constructor_calldata: Vec<ConstructorParam>,
                 pub contract_definition: ContractDefinition,
+                #[serde(default)]
+                pub dry_run: bool,
             }
             let params = params.parse::<NamedArgs>()?;
             context
@@ -280,15 +679,246 @@ pub fn run_server(addr: SocketAddr, api: RpcApi) -> Result<(HttpServerHandle, So
                     params.contract_address_salt,
                     params.constructor_calldata,
                     params.contract_definition,
+                    params.dry_run,
+                )
+                .await
+        },
+    )?;
+    module.register_async_method(
+        "starknet_computeContractAddress",
+        |params, context| async move {
+            #[derive(Debug, Deserialize)]
+            pub struct NamedArgs {
+                pub salt: ContractAddressSalt,
+                pub class_hash: ContractHash,
+                pub constructor_calldata: Vec<ConstructorParam>,
+                #[serde(default)]
+                pub deployer_address: Option<ContractAddress>,
+            }
+            let params = params.parse::<NamedArgs>()?;
+            context
+                .compute_contract_address(
+                    params.salt,
+                    params.class_hash,
+                    params.constructor_calldata,
+                    params.deployer_address,
                 )
                 .await
         },
     )?;
 
+    // Lists all the methods registered above, so clients can detect which of them are supported.
+    let mut method_names = module
+        .method_names()
+        .chain(std::iter::once("rpc_methods"))
+        .filter(|name| module.filter.allows(name))
+        .collect::<Vec<_>>();
+    method_names.sort_unstable();
+    module.register_method("rpc_methods", move |_, _| Ok(method_names.clone()))?;
+    module.warn_unknown_filter_names();
+
     let module = module.into_inner();
+    server.start(module).map(|server| {
+        (
+            ServerHandle {
+                server,
+                _front: front,
+            },
+            local_addr,
+        )
+    })
+}
+
+/// Handle of a running HTTP-RPC server, which completes when the server stops.
+///
+/// Dropping the handle stops the [front server](listener) serving the listening socket.
+pub struct ServerHandle {
+    server: HttpServerHandle,
+    _front: tokio::sync::oneshot::Sender<()>,
+}
+
+impl std::future::Future for ServerHandle {
+    type Output = ();
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        std::pin::Pin::new(&mut self.get_mut().server).poll(cx)
+    }
+}
+
+/// State shared by the WebSocket-RPC subscriptions.
+struct SubscriptionContext {
+    storage: Storage,
+    sync_state: Arc<SyncState>,
+    max_event_filter_keys: usize,
+}
+
+/// Starts the WebSocket-RPC server, which offers subscriptions to the progress of sync:
+///
+/// - `starknet_subscribeNewHeads` pushes the [header](crate::rpc::types::reply::BlockHeader) of
+///   each block as sync stores it, until `starknet_unsubscribeNewHeads` is called with the
+///   subscription id or the client disconnects.
+/// - `starknet_subscribeEvents` pushes the events of each stored block which match its
+///   [filter](EventSubscriptionFilter), until `starknet_unsubscribeEvents` is called. Subscribers
+///   falling too far behind are closed instead of missing events. Filters with more than
+///   `max_event_filter_keys` keys are rejected, like in `starknet_getEvents`.
+pub async fn run_ws_server(
+    addr: SocketAddr,
+    storage: Storage,
+    sync_state: Arc<SyncState>,
+    max_event_filter_keys: usize,
+) -> Result<(WsServerHandle, SocketAddr), Error> {
+    let server = WsServerBuilder::default().build(addr).await?;
+    let local_addr = server.local_addr()?;
+
+    let mut module = RpcModule::new(SubscriptionContext {
+        storage,
+        sync_state,
+        max_event_filter_keys,
+    });
+    module.register_subscription(
+        "starknet_subscribeNewHeads",
+        "starknet_subscribeNewHeads",
+        "starknet_unsubscribeNewHeads",
+        |_, mut sink, context| {
+            use tokio::sync::broadcast::error::RecvError;
+
+            let mut new_heads = context.sync_state.new_heads.subscribe();
+            tokio::spawn(async move {
+                loop {
+                    match new_heads.recv().await {
+                        Ok(header) => {
+                            // Fails once the subscription has been closed, which is only noticed
+                            // here when the next header arrives.
+                            if sink.send(&header).is_err() {
+                                break;
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::debug!(%skipped, "New heads subscriber lagging behind");
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+            Ok(())
+        },
+    )?;
+    module.register_subscription(
+        "starknet_subscribeEvents",
+        "starknet_subscribeEvents",
+        "starknet_unsubscribeEvents",
+        |params, mut sink, context| {
+            use tokio::sync::broadcast::error::RecvError;
+
+            #[derive(Debug, Deserialize)]
+            pub struct NamedArgs {
+                pub filter: EventSubscriptionFilter,
+            }
+            // The subscription id has already been sent at this point, so invalid parameters
+            // close the subscription.
+            let filter = match params.parse::<NamedArgs>() {
+                Ok(params) => params.filter,
+                Err(e) => {
+                    sink.close(&format!("Invalid params: {}", e));
+                    return Ok(());
+                }
+            };
+            if filter.keys.len() > context.max_event_filter_keys {
+                sink.close(&format!(
+                    "Too many keys in filter, at most {} are allowed",
+                    context.max_event_filter_keys
+                ));
+                return Ok(());
+            }
+
+            let storage = context.storage.clone();
+            let mut new_heads = context.sync_state.new_heads.subscribe();
+            tokio::spawn(async move {
+                loop {
+                    let block = match new_heads.recv().await {
+                        Ok(header) => header.block_number,
+                        Err(RecvError::Lagged(skipped)) => {
+                            // The events of the skipped blocks are not buffered, so the
+                            // subscriber would silently miss them.
+                            sink.close(&format!(
+                                "Subscriber too slow, missed the events of {} block(s)",
+                                skipped
+                            ));
+                            break;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+
+                    let storage = storage.clone();
+                    let filter = filter.clone();
+                    let events = tokio::task::spawn_blocking(move || {
+                        block_events(&storage, &filter, block)
+                    })
+                    .await
+                    .context("Database read panic or shutting down")
+                    .and_then(|x| x);
+                    let events = match events {
+                        Ok(events) => events,
+                        Err(e) => {
+                            tracing::error!(block=%block.0, reason=?e, "Reading subscribed events failed");
+                            sink.close("Internal error");
+                            break;
+                        }
+                    };
+
+                    for event in events {
+                        // Fails once the subscription has been closed.
+                        if sink.send(&event).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+            Ok(())
+        },
+    )?;
+
     server.start(module).map(|handle| (handle, local_addr))
 }
 
+/// Reads the events of `block` which match the subscription's `filter`, in the order they
+/// were emitted.
+fn block_events(
+    storage: &Storage,
+    filter: &EventSubscriptionFilter,
+    block: StarknetBlockNumber,
+) -> anyhow::Result<Vec<EmittedEvent>> {
+    let connection = storage
+        .read_connection()
+        .context("Opening database connection")?;
+
+    let mut filter = StarknetEventFilter {
+        from_block: Some(block),
+        to_block: Some(block),
+        from_timestamp: None,
+        to_timestamp: None,
+        contract_addresses: filter.address.map(|address| vec![address]),
+        exclude_address: filter.exclude_address,
+        keys: filter.keys.clone(),
+        key_match: filter.key_match,
+        page_size: StarknetEventsTable::PAGE_SIZE_LIMIT,
+        page_number: 0,
+        continuation_token: None,
+    };
+    let mut events = Vec::new();
+    loop {
+        let page = StarknetEventsTable::get_events(&connection, &filter)?;
+        events.extend(page.events.into_iter().map(EmittedEvent::from));
+        if page.is_last_page {
+            return Ok(events);
+        }
+        filter.page_number += 1;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,10 +926,10 @@ mod tests {
         core::{
             ContractAddress, ContractHash, EventData, EventKey, GasPrice, GlobalRoot,
             SequencerAddress, StarknetBlockHash, StarknetBlockNumber, StarknetBlockTimestamp,
-            StarknetProtocolVersion, StorageAddress,
+            StarknetProtocolVersion, StarknetTransactionHash, StorageAddress,
         },
         ethereum::Chain,
-        rpc::run_server,
+        rpc::{run_server, types::request::KeyMatchMode},
         sequencer::{
             reply::transaction::{
                 execution_resources::{BuiltinInstanceCounter, EmptyBuiltinInstanceCounter},
@@ -368,6 +998,27 @@ mod tests {
         )
     }
 
+    /// Spawns a mock sequencer which fails every request with a StarkNet error, so tests
+    /// which must not reach the actual sequencer do not depend on it.
+    fn failing_sequencer() -> SeqClient {
+        use warp::Filter;
+
+        let any = warp::any().map(|| {
+            warp::reply::with_status(
+                warp::reply::json(&json!({
+                    "code": "StarkErrorCode.MALFORMED_REQUEST",
+                    "message": "Mock sequencer failure",
+                })),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            )
+        });
+        let (addr, run_srv) = warp::serve(any).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(run_srv);
+
+        let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+        SeqClient::with_url(url).unwrap()
+    }
+
     // Local test helper
     fn setup_storage() -> Storage {
         use crate::{
@@ -415,6 +1066,7 @@ mod tests {
             bytecode: zstd_magic.clone(),
             definition: zstd_magic,
             hash: contract0_hash,
+            dictionary_id: None,
         };
         let mut contract1_code = contract0_code.clone();
         contract1_code.hash = contract1_hash;
@@ -513,6 +1165,7 @@ mod tests {
             l2_to_l1_messages: vec![],
             transaction_hash: txn0_hash,
             transaction_index: StarknetTransactionIndex(0),
+            transaction_failure_reason: None,
         };
         let txn1_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 1").unwrap());
         let txn2_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 2").unwrap());
@@ -565,20 +1218,123 @@ mod tests {
         storage
     }
 
-    mod get_block_by_hash {
+    mod pending_fallback {
+        //! Without a pending block, the pending tag resolves to the latest accepted block.
         use super::*;
-        use crate::core::{StarknetBlockHash, StarknetBlockNumber};
+        use crate::core::StorageValue;
         use crate::rpc::types::{
             reply::{Block, Transactions},
-            request::BlockResponseScope,
-            BlockHashOrTag, Tag,
+            BlockHashOrTag, BlockNumberOrTag, Tag,
         };
-        use pedersen::StarkHash;
         use pretty_assertions::assert_eq;
 
-        #[tokio::test]
-        async fn genesis() {
-            let storage = setup_storage();
+        /// Spawns a mock sequencer which has no pending block,
+        /// i.e. it always replies with an accepted block.
+        pub(super) fn sequencer_without_pending() -> SeqClient {
+            use crate::sequencer::reply::{Block, Status};
+            use warp::Filter;
+
+            let any = warp::any().map(|| {
+                warp::reply::json(&Block {
+                    block_hash: Some(StarknetBlockHash(
+                        StarkHash::from_be_slice(b"sequencer latest").unwrap(),
+                    )),
+                    block_number: Some(StarknetBlockNumber(100)),
+                    gas_price: None,
+                    parent_block_hash: StarknetBlockHash(StarkHash::ZERO),
+                    sequencer_address: None,
+                    state_root: None,
+                    status: Status::AcceptedOnL2,
+                    timestamp: StarknetBlockTimestamp(0),
+                    transaction_receipts: vec![],
+                    transactions: vec![],
+                })
+            });
+            let (addr, run_srv) = warp::serve(any).bind_ephemeral(([127, 0, 0, 1], 0));
+            tokio::spawn(run_srv);
+
+            let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+            SeqClient::with_url(url).unwrap()
+        }
+
+        #[tokio::test]
+        async fn get_block_by_hash() {
+            let storage = setup_storage();
+            let sequencer = sequencer_without_pending();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let fallbacks = api.sequencer_fallbacks();
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let latest_hash = StarknetBlockHash(StarkHash::from_be_slice(b"latest").unwrap());
+            let params = rpc_params!(BlockHashOrTag::Tag(Tag::Pending));
+            let block = client(addr)
+                .request::<Block>("starknet_getBlockByHash", params)
+                .await
+                .unwrap();
+            assert_eq!(block.block_hash, Some(latest_hash));
+            assert_eq!(block.block_number, Some(StarknetBlockNumber(2)));
+            assert_matches!(
+                block.transactions,
+                Transactions::HashesOnly(t) => assert_eq!(t.len(), 3)
+            );
+            assert_eq!(fallbacks.get("starknet_getBlockByHash"), 1);
+            assert_eq!(fallbacks.get("starknet_getBlockByNumber"), 0);
+        }
+
+        #[tokio::test]
+        async fn get_block_by_number() {
+            let storage = setup_storage();
+            let sequencer = sequencer_without_pending();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let latest_hash = StarknetBlockHash(StarkHash::from_be_slice(b"latest").unwrap());
+            let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Pending));
+            let block = client(addr)
+                .request::<Block>("starknet_getBlockByNumber", params)
+                .await
+                .unwrap();
+            assert_eq!(block.block_hash, Some(latest_hash));
+            assert_eq!(block.block_number, Some(StarknetBlockNumber(2)));
+        }
+
+        #[tokio::test]
+        async fn get_storage_at() {
+            let storage = setup_storage();
+            let sequencer = sequencer_without_pending();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                BlockHashOrTag::Tag(Tag::Pending)
+            );
+            let value = client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params)
+                .await
+                .unwrap();
+            assert_eq!(
+                value,
+                StorageValue(StarkHash::from_be_slice(b"storage value 2").unwrap())
+            );
+        }
+    }
+
+    mod get_block_by_hash {
+        use super::*;
+        use crate::core::{StarknetBlockHash, StarknetBlockNumber};
+        use crate::rpc::types::{
+            reply::{Block, Transactions},
+            request::BlockResponseScope,
+            BlockHashOrTag, Tag,
+        };
+        use pedersen::StarkHash;
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn genesis() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
@@ -708,8 +1464,9 @@ mod tests {
 
         #[tokio::test]
         async fn pending() {
+            // Without a pending block nor any accepted block there is no block to return.
             let storage = Storage::in_memory().unwrap();
-            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sequencer = super::pending_fallback::sequencer_without_pending();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
@@ -717,13 +1474,13 @@ mod tests {
                 BlockHashOrTag::Tag(Tag::Pending),
                 BlockResponseScope::FullTransactions
             );
-            let block = client(addr)
+            let error = client(addr)
                 .request::<Block>("starknet_getBlockByHash", params)
                 .await
-                .unwrap();
+                .unwrap_err();
             assert_matches!(
-                block.transactions,
-                Transactions::Full(_) => ()
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
             );
         }
 
@@ -744,6 +1501,145 @@ mod tests {
                 Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
             );
         }
+
+        #[tokio::test]
+        async fn pending_disabled() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                .with_pending_disabled(true);
+            let fallbacks = api.sequencer_fallbacks();
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(BlockHashOrTag::Tag(Tag::Pending));
+            let error = client(addr)
+                .request::<Block>("starknet_getBlockByHash", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(
+                    get_err(&s),
+                    (-32602, "The pending tag is disabled on this node".to_owned())
+                )
+            );
+            // The sequencer was not queried for the pending block.
+            assert_eq!(fallbacks.get("starknet_getBlockByHash"), 0);
+
+            // Other tags are still served.
+            let params = rpc_params!(BlockHashOrTag::Tag(Tag::Latest));
+            let block = client(addr)
+                .request::<Block>("starknet_getBlockByHash", params)
+                .await
+                .unwrap();
+            assert_eq!(
+                block.block_hash,
+                Some(StarknetBlockHash(
+                    StarkHash::from_be_slice(b"latest").unwrap()
+                ))
+            );
+        }
+
+        #[tokio::test]
+        async fn pending_disabled_when_resolving_block() {
+            use crate::rpc::types::BlockId;
+
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                .with_pending_disabled(true);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            // Methods which resolve the pending tag to the latest block reject it as well.
+            let contract = ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap());
+            let genesis = BlockId::Number(StarknetBlockNumber(0));
+            let pending = BlockId::Tag(Tag::Pending);
+            for (method, params) in [
+                ("starknet_getContractCount", rpc_params!(pending)),
+                (
+                    "starknet_getStorageDiff",
+                    rpc_params!(contract, genesis, pending),
+                ),
+            ] {
+                let error = client(addr)
+                    .request::<serde_json::Value>(method, params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(
+                        get_err(&s),
+                        (-32602, "The pending tag is disabled on this node".to_owned()),
+                        "{}",
+                        method
+                    )
+                );
+            }
+        }
+
+        mod unreadable_transaction {
+            use super::*;
+            use crate::core::StarknetTransactionHash;
+            use pretty_assertions::assert_eq;
+
+            /// Replaces the stored data of `txn 4 ` in the latest block with garbage.
+            fn setup_storage_with_unreadable_transaction() -> Storage {
+                let storage = setup_storage();
+                let connection = storage.connection().unwrap();
+                let garbage = zstd::encode_all(&b"not a transaction"[..], 0).unwrap();
+                let hash = StarkHash::from_be_slice(b"txn 4 ").unwrap();
+                connection
+                    .execute(
+                        "UPDATE starknet_transactions SET tx = ? WHERE hash = ?",
+                        rusqlite::params![garbage, hash.as_be_bytes()],
+                    )
+                    .unwrap();
+                storage
+            }
+
+            fn params() -> Option<ParamsSer<'static>> {
+                let latest_hash = StarknetBlockHash(StarkHash::from_be_slice(b"latest").unwrap());
+                rpc_params!(latest_hash, BlockResponseScope::FullTransactions)
+            }
+
+            #[tokio::test]
+            async fn is_skipped_in_partial_mode() {
+                let storage = setup_storage_with_unreadable_transaction();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                    .with_partial_blocks(true);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let block = client(addr)
+                    .request::<Block>("starknet_getBlockByHash", params())
+                    .await
+                    .unwrap();
+
+                let hash = |s: &[u8]| StarknetTransactionHash(StarkHash::from_be_slice(s).unwrap());
+                assert_matches!(
+                    block.transactions,
+                    Transactions::Full(t) => assert_eq!(
+                        t.into_iter().map(|t| t.txn_hash).collect::<Vec<_>>(),
+                        vec![hash(b"txn 3"), hash(b"txn 5")]
+                    )
+                );
+                assert_eq!(block.skipped_transactions, vec![hash(b"txn 4 ")]);
+            }
+
+            #[tokio::test]
+            async fn fails_the_block_by_default() {
+                let storage = setup_storage_with_unreadable_transaction();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                client(addr)
+                    .request::<Block>("starknet_getBlockByHash", params())
+                    .await
+                    .unwrap_err();
+            }
+        }
     }
 
     mod get_block_by_number {
@@ -921,6 +1817,127 @@ mod tests {
                 Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
             );
         }
+
+        mod latest_offset {
+            use super::*;
+            use crate::rpc::types::LatestOffset;
+            use pretty_assertions::assert_eq;
+            use serde_json::json;
+
+            #[tokio::test]
+            async fn zero_is_latest() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = rpc_params!(LatestOffset { latest_offset: 0 });
+                let block = client(addr)
+                    .request::<Block>("starknet_getBlockByNumber", params)
+                    .await
+                    .unwrap();
+                assert_eq!(block.block_number, Some(StarknetBlockNumber(2)));
+            }
+
+            #[tokio::test]
+            async fn positional_and_named() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(LatestOffset { latest_offset: 2 });
+                let block = client(addr)
+                    .request::<Block>("starknet_getBlockByNumber", params)
+                    .await
+                    .unwrap();
+                assert_eq!(block.block_number, Some(StarknetBlockNumber(0)));
+
+                let params = by_name([("block_number", json!({ "latest_offset": 1 }))]);
+                let block = client(addr)
+                    .request::<Block>("starknet_getBlockByNumber", params)
+                    .await
+                    .unwrap();
+                assert_eq!(block.block_number, Some(StarknetBlockNumber(1)));
+            }
+
+            #[tokio::test]
+            async fn out_of_range() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = rpc_params!(LatestOffset { latest_offset: 3 });
+                let error = client(addr)
+                    .request::<Block>("starknet_getBlockByNumber", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
+                );
+            }
+        }
+
+        #[test]
+        fn latest_is_pinned_during_concurrent_insert() {
+            use crate::rpc::{api::read_raw_block, types::reply::RpcError};
+            use crate::storage::StarknetBlocksBlockId;
+
+            let storage = setup_storage();
+            let mut connection = storage.connection().unwrap();
+
+            // A request reads the latest block within a single database transaction.
+            let transaction = connection.transaction().unwrap();
+            let latest = read_raw_block(
+                &transaction,
+                StarknetBlocksBlockId::Latest,
+                RpcError::InvalidBlockNumber,
+            )
+            .unwrap();
+            assert_eq!(latest.number, StarknetBlockNumber(2));
+
+            // Meanwhile the sync process inserts a new block. The request's transaction either
+            // keeps the insert from going through, or hides it from the request.
+            let writer = storage.connection().unwrap();
+            let block = StarknetBlock {
+                number: StarknetBlockNumber(3),
+                hash: StarknetBlockHash(StarkHash::from_be_slice(b"newest").unwrap()),
+                root: GlobalRoot(StarkHash::from_be_slice(b"newest root").unwrap()),
+                timestamp: StarknetBlockTimestamp(3),
+                gas_price: GasPrice::ZERO,
+                sequencer_address: SequencerAddress(StarkHash::ZERO),
+            };
+            let inserted = StarknetBlocksTable::insert(&writer, &block).is_ok();
+
+            // The rest of the request still sees the same latest block.
+            let still_latest = read_raw_block(
+                &transaction,
+                StarknetBlocksBlockId::Latest,
+                RpcError::InvalidBlockNumber,
+            )
+            .unwrap();
+            assert_eq!(still_latest.number, latest.number);
+            assert_eq!(still_latest.hash, latest.hash);
+            drop(transaction);
+
+            // Once the request is done, the insert goes through.
+            if !inserted {
+                StarknetBlocksTable::insert(&writer, &block).unwrap();
+            }
+
+            // Subsequent requests see the new block.
+            let transaction = connection.transaction().unwrap();
+            let latest = read_raw_block(
+                &transaction,
+                StarknetBlocksBlockId::Latest,
+                RpcError::InvalidBlockNumber,
+            )
+            .unwrap();
+            assert_eq!(latest.number, StarknetBlockNumber(3));
+        }
     }
 
     mod get_state_update_by_hash {
@@ -973,60 +1990,147 @@ mod tests {
         }
     }
 
-    mod get_storage_at {
+    mod get_raw_block {
         use super::*;
-        use crate::{
-            core::StorageValue,
-            rpc::types::{BlockHashOrTag, Tag},
-        };
+        use crate::core::StarknetBlockHash;
+        use crate::storage::StarknetRawBlocksTable;
         use pretty_assertions::assert_eq;
 
+        /// A block as the sequencer might send it, including fields which are not part of
+        /// the sequencer reply types.
+        fn raw_genesis() -> serde_json::Value {
+            serde_json::json!({
+                "block_hash": "0x67656e65736973",
+                "block_number": 0,
+                "unknown_field": ["kept", "as", "is"],
+                "transactions": [],
+                "transaction_receipts": [],
+            })
+        }
+
+        fn genesis_hash() -> StarknetBlockHash {
+            StarknetBlockHash(StarkHash::from_be_slice(b"genesis").unwrap())
+        }
+
+        fn setup_storage_with_raw_genesis() -> Storage {
+            let storage = setup_storage();
+            let connection = storage.connection().unwrap();
+            let raw = serde_json::to_vec(&raw_genesis()).unwrap();
+            StarknetRawBlocksTable::insert(&connection, StarknetBlockNumber(0), &raw).unwrap();
+            storage
+        }
+
         #[tokio::test]
-        async fn key_is_field_modulus() {
-            use std::str::FromStr;
+        async fn round_trip() {
+            let storage = setup_storage_with_raw_genesis();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                .with_raw_blocks_retained(true);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(genesis_hash());
+            let block = client(addr)
+                .request::<serde_json::Value>("starknet_getRawBlock", params)
+                .await
+                .unwrap();
+            assert_eq!(block, raw_genesis());
+        }
 
+        #[tokio::test]
+        async fn not_retained() {
             let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
-            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                .with_raw_blocks_retained(true);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap()),
-                web3::types::H256::from_str(
-                    "0x0800000000000011000000000000000000000000000000000000000000000001"
-                )
-                .unwrap(),
-                BlockHashOrTag::Tag(Tag::Latest)
-            );
+            let params = rpc_params!(genesis_hash());
             let error = client(addr)
-                .request::<StorageValue>("starknet_getStorageAt", params)
+                .request::<serde_json::Value>("starknet_getRawBlock", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_KEY)
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
             );
         }
 
         #[tokio::test]
-        async fn key_is_less_than_modulus_but_252_bits() {
-            use std::str::FromStr;
-
-            let storage = setup_storage();
+        async fn retention_disabled() {
+            let storage = setup_storage_with_raw_genesis();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap()),
-                web3::types::H256::from_str(
-                    "0x0800000000000000000000000000000000000000000000000000000000000000"
-                )
-                .unwrap(),
-                BlockHashOrTag::Tag(Tag::Latest)
-            );
+            let params = rpc_params!(genesis_hash());
             let error = client(addr)
-                .request::<StorageValue>("starknet_getStorageAt", params)
+                .request::<serde_json::Value>("starknet_getRawBlock", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(
+                    get_err(&s),
+                    (-32602, "Raw block retention is disabled on this node".to_owned())
+                )
+            );
+        }
+    }
+
+    mod get_storage_at {
+        use super::*;
+        use crate::{
+            core::StorageValue,
+            rpc::types::{BlockHashOrTag, Tag},
+        };
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn key_is_field_modulus() {
+            use std::str::FromStr;
+
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap()),
+                web3::types::H256::from_str(
+                    "0x0800000000000011000000000000000000000000000000000000000000000001"
+                )
+                .unwrap(),
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_KEY)
+            );
+        }
+
+        #[tokio::test]
+        async fn key_is_less_than_modulus_but_252_bits() {
+            use std::str::FromStr;
+
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap()),
+                web3::types::H256::from_str(
+                    "0x0800000000000000000000000000000000000000000000000000000000000000"
+                )
+                .unwrap(),
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params)
                 .await
                 .unwrap_err();
             assert_matches!(
@@ -1057,6 +2161,29 @@ mod tests {
             );
         }
 
+        #[tokio::test]
+        async fn zero_contract_address() {
+            // The zero address shows up in transactions of the fixture, but is never deployed.
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                ContractAddress::ZERO,
+                StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::CONTRACT_NOT_FOUND)
+            );
+        }
+
         #[tokio::test]
         async fn pre_deploy_block_hash() {
             let storage = setup_storage();
@@ -1129,6 +2256,142 @@ mod tests {
             );
         }
 
+        #[tokio::test]
+        async fn deployment_block_number() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                StarknetBlockNumber(1)
+            );
+            let value = client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params)
+                .await
+                .unwrap();
+            assert_eq!(
+                value.0,
+                StarkHash::from_be_slice(b"storage value 1").unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn second_read_hits_node_cache() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let node_cache = api.node_cache();
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = || {
+                rpc_params!(
+                    ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                    StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                    BlockHashOrTag::Tag(Tag::Latest)
+                )
+            };
+
+            client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params())
+                .await
+                .unwrap();
+            let (hits, misses) = (node_cache.hits(), node_cache.misses());
+            assert!(misses > 0);
+
+            let value = client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params())
+                .await
+                .unwrap();
+            assert_eq!(
+                value.0,
+                StarkHash::from_be_slice(b"storage value 2").unwrap()
+            );
+            // Every node of the second read is served from the cache.
+            assert_eq!(node_cache.misses(), misses);
+            assert_eq!(node_cache.hits() - hits, hits + misses);
+        }
+
+        #[tokio::test]
+        async fn non_existent_block_number() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                StarknetBlockNumber(123)
+            );
+            let error = client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
+            );
+        }
+
+        #[tokio::test]
+        async fn resolved_block_is_logged() {
+            /// Collects the formatted log output.
+            #[derive(Clone, Default)]
+            struct LogBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+            impl std::io::Write for LogBuffer {
+                fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                    self.0.lock().unwrap().extend_from_slice(buf);
+                    Ok(buf.len())
+                }
+
+                fn flush(&mut self) -> std::io::Result<()> {
+                    Ok(())
+                }
+            }
+
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+
+            let buffer = LogBuffer::default();
+            let writer = buffer.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_writer(move || writer.clone())
+                .with_max_level(tracing::Level::DEBUG)
+                .with_ansi(false)
+                .finish();
+            // The test runtime is single threaded, so the subscriber sees everything logged
+            // outside of blocking tasks.
+            let _guard = tracing::subscriber::set_default(subscriber);
+
+            api.get_storage_at(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                OverflowingStorageAddress(web3::types::H256(
+                    StarkHash::from_be_slice(b"storage addr 0")
+                        .unwrap()
+                        .to_be_bytes(),
+                )),
+                BlockId::Tag(Tag::Latest),
+            )
+            .await
+            .unwrap();
+
+            let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+            assert!(logs.contains("Resolved block"), "{}", logs);
+            assert!(
+                logs.contains("method=\"starknet_getStorageAt\""),
+                "{}",
+                logs
+            );
+            assert!(logs.contains("requested=Tag(Latest)"), "{}", logs);
+            assert!(logs.contains("block=2"), "{}", logs);
+        }
+
         mod latest_block {
             use super::*;
             use pretty_assertions::assert_eq;
@@ -1184,1772 +2447,6125 @@ mod tests {
             }
         }
 
-        #[tokio::test]
-        async fn pending_block() {
-            let storage = Storage::in_memory().unwrap();
-            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-            let sync_state = Arc::new(SyncState::default());
-            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                *VALID_CONTRACT_ADDR,
-                *VALID_KEY,
-                BlockHashOrTag::Tag(Tag::Pending)
-            );
-            client(addr)
-                .request::<StorageValue>("starknet_getStorageAt", params)
-                .await
-                .unwrap();
-        }
-    }
-
-    mod get_transaction_by_hash {
-        use super::*;
-        use crate::rpc::types::reply::Transaction;
-        use pretty_assertions::assert_eq;
-
-        mod accepted {
+        pub(super) mod pending {
             use super::*;
+            use crate::sequencer::reply::state_update::{Contract, StateDiff, StorageDiff};
             use pretty_assertions::assert_eq;
 
-            #[tokio::test]
-            async fn positional_args() {
+            /// Spawns a mock sequencer with a pending block, whose state update contains
+            /// the given diff.
+            pub(crate) fn sequencer_with_pending(state_diff: StateDiff) -> SeqClient {
+                use crate::sequencer::reply::{Block, Status};
+                use warp::Filter;
+
+                let block = warp::path!("feeder_gateway" / "get_block").map(|| {
+                    warp::reply::json(&Block {
+                        block_hash: None,
+                        block_number: None,
+                        gas_price: None,
+                        parent_block_hash: StarknetBlockHash(
+                            StarkHash::from_be_slice(b"latest").unwrap(),
+                        ),
+                        sequencer_address: None,
+                        state_root: None,
+                        status: Status::Pending,
+                        timestamp: StarknetBlockTimestamp(0),
+                        transaction_receipts: vec![],
+                        transactions: vec![],
+                    })
+                });
+                let state_update = json!({
+                    "new_root": GlobalRoot(StarkHash::ZERO),
+                    "old_root": GlobalRoot(StarkHash::ZERO),
+                    "state_diff": state_diff,
+                });
+                let state_update = warp::path!("feeder_gateway" / "get_state_update")
+                    .map(move || warp::reply::json(&state_update));
+                let (addr, run_srv) =
+                    warp::serve(block.or(state_update)).bind_ephemeral(([127, 0, 0, 1], 0));
+                tokio::spawn(run_srv);
+
+                let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+                SeqClient::with_url(url).unwrap()
+            }
+
+            async fn get_pending(
+                state_diff: StateDiff,
+                contract_address: ContractAddress,
+            ) -> StorageValue {
                 let storage = setup_storage();
-                let hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sequencer = sequencer_with_pending(state_diff);
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let fallbacks = api.sequencer_fallbacks();
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = rpc_params!(hash);
-                let transaction = client(addr)
-                    .request::<Transaction>("starknet_getTransactionByHash", params)
+                let params = rpc_params!(
+                    contract_address,
+                    StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                    BlockHashOrTag::Tag(Tag::Pending)
+                );
+                let value = client(addr)
+                    .request::<StorageValue>("starknet_getStorageAt", params)
                     .await
                     .unwrap();
-                assert_eq!(transaction.txn_hash, hash);
+                // Fetching both the pending block and its state update is a single fallback.
+                assert_eq!(fallbacks.get("starknet_getStorageAt"), 1);
+                value
             }
 
             #[tokio::test]
-            async fn named_args() {
-                let storage = setup_storage();
-                let hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = by_name([("transaction_hash", json!(hash))]);
-                let transaction = client(addr)
-                    .request::<Transaction>("starknet_getTransactionByHash", params)
-                    .await
-                    .unwrap();
-                assert_eq!(transaction.txn_hash, hash);
+            async fn changed_in_pending_block() {
+                let contract1 = ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap());
+                let pending_value =
+                    StorageValue(StarkHash::from_be_slice(b"pending value").unwrap());
+                let state_diff = StateDiff {
+                    storage_diffs: [(
+                        contract1,
+                        vec![StorageDiff {
+                            key: StorageAddress(
+                                StarkHash::from_be_slice(b"storage addr 0").unwrap(),
+                            ),
+                            value: pending_value,
+                        }],
+                    )]
+                    .into_iter()
+                    .collect(),
+                    deployed_contracts: vec![],
+                    nonces: Default::default(),
+                };
+
+                let value = get_pending(state_diff, contract1).await;
+                assert_eq!(value, pending_value);
+            }
+
+            #[tokio::test]
+            async fn unchanged_in_pending_block() {
+                let contract0 = ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap());
+                let contract1 = ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap());
+                let state_diff = StateDiff {
+                    storage_diffs: [(
+                        contract0,
+                        vec![StorageDiff {
+                            key: StorageAddress(
+                                StarkHash::from_be_slice(b"storage addr 0").unwrap(),
+                            ),
+                            value: StorageValue(
+                                StarkHash::from_be_slice(b"pending value").unwrap(),
+                            ),
+                        }],
+                    )]
+                    .into_iter()
+                    .collect(),
+                    deployed_contracts: vec![],
+                    nonces: Default::default(),
+                };
+
+                // Falls back to the latest block.
+                let value = get_pending(state_diff, contract1).await;
+                assert_eq!(
+                    value.0,
+                    StarkHash::from_be_slice(b"storage value 2").unwrap()
+                );
             }
+
+            #[tokio::test]
+            async fn deployed_in_pending_block() {
+                let pending_contract =
+                    ContractAddress(StarkHash::from_be_slice(b"pending contract").unwrap());
+                let state_diff = StateDiff {
+                    storage_diffs: Default::default(),
+                    deployed_contracts: vec![Contract {
+                        address: pending_contract,
+                        contract_hash: ContractHash(
+                            StarkHash::from_be_slice(b"contract 0 hash").unwrap(),
+                        ),
+                    }],
+                    nonces: Default::default(),
+                };
+
+                let value = get_pending(state_diff, pending_contract).await;
+                assert_eq!(value, StorageValue(StarkHash::ZERO));
+            }
+        }
+    }
+
+    mod get_storage_at_root {
+        use super::*;
+        use crate::core::StorageValue;
+        use crate::storage::{StarknetBlocksBlockId, StarknetBlocksTable};
+        use pretty_assertions::assert_eq;
+
+        /// The root of the latest block, which is `global_root2` of [setup_storage].
+        fn latest_root(storage: &Storage) -> GlobalRoot {
+            let connection = storage.connection().unwrap();
+            StarknetBlocksTable::get(&connection, StarknetBlocksBlockId::Latest)
+                .unwrap()
+                .unwrap()
+                .root
         }
 
         #[tokio::test]
-        async fn invalid_hash() {
+        async fn known_value() {
             let storage = setup_storage();
+            let root = latest_root(&storage);
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(*INVALID_TX_HASH);
-            let error = client(addr)
-                .request::<Transaction>("starknet_getTransactionByHash", params)
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                root
+            );
+            let value = client(addr)
+                .request::<StorageValue>("starknet_getStorageAtRoot", params)
+                .await
+                .unwrap();
+            assert_eq!(
+                value.0,
+                StarkHash::from_be_slice(b"storage value 2").unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn unknown_root() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                GlobalRoot(StarkHash::from_be_slice(b"unknown root").unwrap())
+            );
+            let error = client(addr)
+                .request::<StorageValue>("starknet_getStorageAtRoot", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+                Error::Request(s) => assert_eq!(get_err(&s).0, -32602)
             );
         }
-    }
-
-    mod get_transaction_by_block_hash_and_index {
-        use super::*;
-        use crate::rpc::types::{reply::Transaction, BlockHashOrTag, Tag};
-        use pretty_assertions::assert_eq;
 
         #[tokio::test]
-        async fn genesis() {
+        async fn non_existent_contract_address() {
             let storage = setup_storage();
+            let root = latest_root(&storage);
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let genesis_hash = StarknetBlockHash(StarkHash::from_be_slice(b"genesis").unwrap());
-            let params = rpc_params!(genesis_hash, 0);
-            let txn = client(addr)
-                .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"nonexistent").unwrap()),
+                StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                root
+            );
+            let error = client(addr)
+                .request::<StorageValue>("starknet_getStorageAtRoot", params)
                 .await
-                .unwrap();
-            assert_eq!(
-                txn.txn_hash,
-                StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap())
-            )
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::CONTRACT_NOT_FOUND)
+            );
         }
+    }
 
-        mod latest {
-            use super::*;
-            use pretty_assertions::assert_eq;
-
-            #[tokio::test]
-            async fn positional_args() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = rpc_params!(BlockHashOrTag::Tag(Tag::Latest), 0);
-                let txn = client(addr)
-                    .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
-                    .await
-                    .unwrap();
-                assert_eq!(
-                    txn.txn_hash,
-                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap())
-                );
-            }
-
-            #[tokio::test]
-            async fn named_args() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = by_name([("block_hash", json!("latest")), ("index", json!(0))]);
-                let txn = client(addr)
-                    .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
-                    .await
-                    .unwrap();
-                assert_eq!(
-                    txn.txn_hash,
-                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap())
-                );
-            }
-        }
+    mod get_storage_at_keys {
+        use super::*;
+        use crate::{
+            core::StorageValue,
+            rpc::types::{
+                reply::{InvalidStorageKey, StorageAtKey, StorageAtKeyResult},
+                BlockHashOrTag, Tag,
+            },
+        };
+        use pretty_assertions::assert_eq;
+        use web3::types::H256;
 
         #[tokio::test]
-        async fn pending() {
-            let storage = Storage::in_memory().unwrap();
+        async fn valid_and_invalid_keys() {
+            use std::str::FromStr;
+
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(BlockHashOrTag::Tag(Tag::Pending), 0);
-            client(addr)
-                .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
+
+            let valid = OverflowingStorageAddress(H256(
+                StarkHash::from_be_slice(b"storage addr 0")
+                    .unwrap()
+                    .to_be_bytes(),
+            ));
+            let field_modulus = OverflowingStorageAddress(
+                H256::from_str(
+                    "0x0800000000000011000000000000000000000000000000000000000000000001",
+                )
+                .unwrap(),
+            );
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                vec![valid, field_modulus],
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let values = client(addr)
+                .request::<Vec<StorageAtKey>>("starknet_getStorageAtKeys", params)
                 .await
                 .unwrap();
+            assert_eq!(
+                values,
+                vec![
+                    StorageAtKey {
+                        key: valid,
+                        result: StorageAtKeyResult::Value(StorageValue(
+                            StarkHash::from_be_slice(b"storage value 2").unwrap()
+                        )),
+                    },
+                    StorageAtKey {
+                        key: field_modulus,
+                        result: StorageAtKeyResult::InvalidKey(
+                            InvalidStorageKey::ExceedsFieldModulus
+                        ),
+                    },
+                ]
+            );
         }
 
         #[tokio::test]
-        async fn invalid_block() {
+        async fn pending() {
+            use crate::sequencer::reply::state_update::{StateDiff, StorageDiff};
+
+            let contract1 = ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap());
+            let key0 = StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap());
+            let key1 = StorageAddress(StarkHash::from_be_slice(b"storage addr 1").unwrap());
+            let pending_value = StorageValue(StarkHash::from_be_slice(b"pending value").unwrap());
+            let state_diff = StateDiff {
+                storage_diffs: [(
+                    contract1,
+                    vec![StorageDiff {
+                        key: key1,
+                        value: pending_value,
+                    }],
+                )]
+                .into_iter()
+                .collect(),
+                deployed_contracts: vec![],
+                nonces: Default::default(),
+            };
+
             let storage = setup_storage();
-            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sequencer = super::get_storage_at::pending::sequencer_with_pending(state_diff);
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(StarknetBlockHash(StarkHash::ZERO), 0);
-            let error = client(addr)
-                .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
+
+            let keys = [key0, key1].map(|key| OverflowingStorageAddress(H256(key.0.to_be_bytes())));
+            let params = rpc_params!(contract1, keys, BlockHashOrTag::Tag(Tag::Pending));
+            let values = client(addr)
+                .request::<Vec<StorageAtKey>>("starknet_getStorageAtKeys", params)
                 .await
-                .unwrap_err();
-            assert_matches!(
-                error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
+                .unwrap();
+            // The key unchanged in the pending block is read from the latest block.
+            assert_eq!(
+                values,
+                vec![
+                    StorageAtKey {
+                        key: keys[0],
+                        result: StorageAtKeyResult::Value(StorageValue(
+                            StarkHash::from_be_slice(b"storage value 2").unwrap()
+                        )),
+                    },
+                    StorageAtKey {
+                        key: keys[1],
+                        result: StorageAtKeyResult::Value(pending_value),
+                    },
+                ]
             );
         }
 
         #[tokio::test]
-        async fn invalid_transaction_index() {
+        async fn too_many_keys() {
             let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
-            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let api =
+                RpcApi::new(storage, sequencer, Chain::Goerli, sync_state).with_max_storage_keys(1);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let genesis_hash = StarknetBlockHash(StarkHash::from_be_slice(b"genesis").unwrap());
-            let params = rpc_params!(genesis_hash, 123);
+
+            let key = OverflowingStorageAddress(H256(
+                StarkHash::from_be_slice(b"storage addr 0")
+                    .unwrap()
+                    .to_be_bytes(),
+            ));
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                vec![key, key],
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
             let error = client(addr)
-                .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
+                .request::<Vec<StorageAtKey>>("starknet_getStorageAtKeys", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_INDEX)
+                Error::Request(s) => assert_eq!(get_err(&s).0, -32602)
             );
         }
     }
 
-    mod get_transaction_by_block_number_and_index {
+    mod get_storage_diff {
         use super::*;
-        use crate::rpc::types::{reply::Transaction, BlockNumberOrTag, Tag};
+        use crate::{
+            core::StorageValue,
+            rpc::types::{reply::StorageDiffEntry, BlockId, Tag},
+        };
         use pretty_assertions::assert_eq;
 
         #[tokio::test]
-        async fn genesis() {
+        async fn changed_value() {
             let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(0, 0);
-            let txn = client(addr)
-                .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                BlockId::Number(StarknetBlockNumber(1)),
+                BlockId::Number(StarknetBlockNumber(2))
+            );
+            let diff = client(addr)
+                .request::<Vec<StorageDiffEntry>>("starknet_getStorageDiff", params)
                 .await
                 .unwrap();
             assert_eq!(
-                txn.txn_hash,
-                StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap())
+                diff,
+                vec![StorageDiffEntry {
+                    key: StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                    old_value: StorageValue(StarkHash::from_be_slice(b"storage value 1").unwrap()),
+                    new_value: StorageValue(StarkHash::from_be_slice(b"storage value 2").unwrap()),
+                }]
             );
         }
 
-        mod latest {
-            use super::*;
-            use pretty_assertions::assert_eq;
-
-            #[tokio::test]
-            async fn positional_args() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Latest), 0);
-                let txn = client(addr)
-                    .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
-                    .await
-                    .unwrap();
-                assert_eq!(
-                    txn.txn_hash,
-                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap())
-                );
-            }
-
-            #[tokio::test]
-            async fn named_args() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = by_name([("block_number", json!("latest")), ("index", json!(0))]);
-                let txn = client(addr)
-                    .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
-                    .await
-                    .unwrap();
-                assert_eq!(
-                    txn.txn_hash,
-                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap())
-                );
-            }
-        }
-
         #[tokio::test]
-        async fn pending() {
-            let storage = Storage::in_memory().unwrap();
+        async fn same_block() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Pending), 0);
-            client(addr)
-                .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
+            let params = by_name([
+                (
+                    "contract_address",
+                    json!(ContractAddress(
+                        StarkHash::from_be_slice(b"contract 1").unwrap()
+                    )),
+                ),
+                (
+                    "from_block",
+                    json!(StarknetBlockHash(
+                        StarkHash::from_be_slice(b"latest").unwrap()
+                    )),
+                ),
+                ("to_block", json!(BlockId::Tag(Tag::Latest))),
+            ]);
+            let diff = client(addr)
+                .request::<Vec<StorageDiffEntry>>("starknet_getStorageDiff", params)
                 .await
                 .unwrap();
+            assert_eq!(diff, vec![]);
         }
 
         #[tokio::test]
-        async fn invalid_block() {
+        async fn contract_not_deployed_at_from_block() {
             let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(123, 0);
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                BlockId::Number(StarknetBlockNumber(0)),
+                BlockId::Number(StarknetBlockNumber(2))
+            );
             let error = client(addr)
-                .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
+                .request::<Vec<StorageDiffEntry>>("starknet_getStorageDiff", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
+                Error::Request(s) => assert_eq!(get_err(&s), *error::CONTRACT_NOT_FOUND)
             );
         }
 
         #[tokio::test]
-        async fn invalid_transaction_index() {
+        async fn invalid_block() {
             let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(0, 123);
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                BlockId::Number(StarknetBlockNumber(1)),
+                BlockId::Number(StarknetBlockNumber(123))
+            );
             let error = client(addr)
-                .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
+                .request::<Vec<StorageDiffEntry>>("starknet_getStorageDiff", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_INDEX)
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
             );
         }
     }
 
-    mod get_transaction_receipt {
+    mod get_nonce {
         use super::*;
-        use crate::rpc::types::reply::TransactionReceipt;
+        use crate::{
+            core::ContractNonce,
+            rpc::types::{BlockId, Tag},
+            storage::ContractNoncesTable,
+        };
         use pretty_assertions::assert_eq;
 
-        mod accepted {
-            use super::*;
-            use pretty_assertions::assert_eq;
+        /// Sets the nonce of `contract 0` to 1 in block 1 and to 2 in block 2.
+        fn setup_storage_with_nonces() -> Storage {
+            let storage = setup_storage();
+            let connection = storage.connection().unwrap();
+            let address = ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap());
+            for (block, nonce) in [(1, "0x1"), (2, "0x2")] {
+                ContractNoncesTable::insert(
+                    &connection,
+                    address,
+                    StarknetBlockNumber(block),
+                    ContractNonce(StarkHash::from_hex_str(nonce).unwrap()),
+                )
+                .unwrap();
+            }
+            storage
+        }
 
-            #[tokio::test]
-            async fn positional_args() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
-                let params = rpc_params!(txn_hash);
-                let receipt = client(addr)
-                    .request::<TransactionReceipt>("starknet_getTransactionReceipt", params)
-                    .await
-                    .unwrap();
-                assert_eq!(receipt.txn_hash, txn_hash);
-                assert_eq!(
-                    receipt.events[0].keys[0],
-                    EventKey(StarkHash::from_be_slice(b"event 0 key").unwrap())
-                );
-            }
+        #[tokio::test]
+        async fn differs_between_blocks() {
+            let storage = setup_storage_with_nonces();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let client = client(addr);
 
-            #[tokio::test]
-            async fn named_args() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
-                let params = by_name([("transaction_hash", json!(txn_hash))]);
-                let receipt = client(addr)
-                    .request::<TransactionReceipt>("starknet_getTransactionReceipt", params)
+            let address = ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap());
+            let mut nonces = Vec::new();
+            for block in [
+                BlockId::Number(StarknetBlockNumber(0)),
+                BlockId::Number(StarknetBlockNumber(1)),
+                BlockId::Number(StarknetBlockNumber(2)),
+                BlockId::Tag(Tag::Latest),
+            ] {
+                let params = rpc_params!(address, block);
+                let nonce = client
+                    .request::<ContractNonce>("starknet_getNonce", params)
                     .await
                     .unwrap();
-                assert_eq!(receipt.txn_hash, txn_hash);
-                assert_eq!(
-                    receipt.events[0].keys[0],
-                    EventKey(StarkHash::from_be_slice(b"event 0 key").unwrap())
-                );
+                nonces.push(nonce.0);
             }
+
+            assert_eq!(
+                nonces,
+                vec![
+                    StarkHash::ZERO,
+                    StarkHash::from_hex_str("0x1").unwrap(),
+                    StarkHash::from_hex_str("0x2").unwrap(),
+                    StarkHash::from_hex_str("0x2").unwrap(),
+                ]
+            );
         }
 
         #[tokio::test]
-        async fn invalid() {
-            let storage = setup_storage();
+        async fn named_args() {
+            let storage = setup_storage_with_nonces();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"not found").unwrap());
-            let params = rpc_params!(txn_hash);
+            let params = by_name([
+                (
+                    "contract_address",
+                    json!(ContractAddress(
+                        StarkHash::from_be_slice(b"contract 0").unwrap()
+                    )),
+                ),
+                ("block_id", json!(BlockId::Number(StarknetBlockNumber(1)))),
+            ]);
+            let nonce = client(addr)
+                .request::<ContractNonce>("starknet_getNonce", params)
+                .await
+                .unwrap();
+            assert_eq!(nonce.0, StarkHash::from_hex_str("0x1").unwrap());
+        }
+
+        #[tokio::test]
+        async fn invalid_contract() {
+            let storage = setup_storage_with_nonces();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"invalid").unwrap()),
+                BlockId::Tag(Tag::Latest)
+            );
             let error = client(addr)
-                .request::<TransactionReceipt>("starknet_getTransactionReceipt", params)
+                .request::<ContractNonce>("starknet_getNonce", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+                Error::Request(s) => assert_eq!(get_err(&s), *error::CONTRACT_NOT_FOUND)
             );
         }
     }
 
-    mod get_code {
+    mod get_contract_count {
         use super::*;
-        use crate::core::ContractCode;
-        use crate::rpc::types::reply::ErrorCode;
+        use crate::rpc::types::{BlockId, Tag};
         use pretty_assertions::assert_eq;
 
         #[tokio::test]
-        async fn invalid_contract_address() {
-            let storage = Storage::in_memory().unwrap();
+        async fn counts_deployed_contracts() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(*INVALID_CONTRACT_ADDR);
-            let error = client(addr)
-                .request::<ContractCode>("starknet_getCode", params)
-                .await
-                .unwrap_err();
-            assert_eq!(ErrorCode::ContractNotFound, error);
+
+            let mut counts = Vec::new();
+            for block in [
+                BlockId::Number(StarknetBlockNumber(0)),
+                BlockId::Number(StarknetBlockNumber(1)),
+                BlockId::Number(StarknetBlockNumber(2)),
+                BlockId::Tag(Tag::Latest),
+            ] {
+                let count = client(addr)
+                    .request::<u64>("starknet_getContractCount", rpc_params!(block))
+                    .await
+                    .unwrap();
+                counts.push(count);
+            }
+
+            // Contract 0 is deployed in the genesis block, contract 1 in block 1.
+            assert_eq!(counts, vec![1, 2, 2, 2]);
         }
 
         #[tokio::test]
-        async fn returns_not_found_if_we_dont_know_about_the_contract() {
-            let storage = Storage::in_memory().unwrap();
+        async fn named_args() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-
-            let not_found = client(addr)
-                .request::<ContractCode>(
-                    "starknet_getCode",
-                    rpc_params!(
-                        "0x4ae0618c330c59559a59a27d143dd1c07cd74cf4e5e5a7cd85d53c6bf0e89dc"
-                    ),
-                )
+            let params = by_name([("block_id", json!(BlockId::Number(StarknetBlockNumber(2))))]);
+            let count = client(addr)
+                .request::<u64>("starknet_getContractCount", params)
                 .await
-                .unwrap_err();
-
-            assert_eq!(ErrorCode::ContractNotFound, not_found);
+                .unwrap();
+            assert_eq!(count, 2);
         }
 
         #[tokio::test]
-        async fn returns_abi_and_code_for_known() {
-            use crate::core::ContractCode;
-            use anyhow::Context;
-            use bytes::Bytes;
-            use futures::stream::TryStreamExt;
-            use pedersen::StarkHash;
-
-            let storage = Storage::in_memory().unwrap();
-
-            let contract_definition = include_bytes!("../fixtures/contract_definition.json.zst");
-            let buffer = zstd::decode_all(std::io::Cursor::new(contract_definition)).unwrap();
-            let contract_definition = Bytes::from(buffer);
-
-            {
-                let mut conn = storage.connection().unwrap();
-                let tx = conn.transaction().unwrap();
-
-                let address = StarkHash::from_hex_str(
-                    "057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374",
-                )
-                .unwrap();
-                let expected_hash = StarkHash::from_hex_str(
-                    "050b2148c0d782914e0b12a1a32abe5e398930b7e914f82c65cb7afce0a0ab9b",
-                )
-                .unwrap();
-
-                let (abi, bytecode, hash) =
-                    crate::state::contract_hash::extract_abi_code_hash(&*contract_definition)
-                        .unwrap();
-
-                assert_eq!(hash.0, expected_hash);
-
-                crate::storage::ContractCodeTable::insert(
-                    &tx,
-                    hash,
-                    &abi,
-                    &bytecode,
-                    &contract_definition,
-                )
-                .context("Deploy testing contract")
-                .unwrap();
-
-                crate::storage::ContractsTable::upsert(
-                    &tx,
-                    crate::core::ContractAddress(address),
-                    hash,
-                )
-                .unwrap();
-
-                tx.commit().unwrap();
-            }
-
+        async fn invalid_block() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-
-            let client = client(addr);
-
-            // both parameters, these used to be separate tests
-            let rets = [
-                rpc_params!("0x057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374"),
-                by_name([(
-                    "contract_address",
-                    json!("0x057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374"),
-                )]),
-            ]
-            .into_iter()
-            .map(|arg| client.request::<ContractCode>("starknet_getCode", arg))
-            .collect::<futures::stream::FuturesOrdered<_>>()
-            .try_collect::<Vec<_>>()
-            .await
-            .unwrap();
-
-            assert_eq!(rets.len(), 2);
-
-            assert_eq!(rets[0], rets[1]);
-            let abi = rets[0].abi.to_string();
-            assert_eq!(
-                abi,
-                // this should not have the quotes because that'd be in json:
-                // `"abi":"\"[{....}]\""`
-                r#"[{"inputs":[{"name":"address","type":"felt"},{"name":"value","type":"felt"}],"name":"increase_value","outputs":[],"type":"function"},{"inputs":[{"name":"contract_address","type":"felt"},{"name":"address","type":"felt"},{"name":"value","type":"felt"}],"name":"call_increase_value","outputs":[],"type":"function"},{"inputs":[{"name":"address","type":"felt"}],"name":"get_value","outputs":[{"name":"res","type":"felt"}],"type":"function"}]"#
+            let params = rpc_params!(BlockId::Number(StarknetBlockNumber(123)));
+            let error = client(addr)
+                .request::<u64>("starknet_getContractCount", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
             );
-            assert_eq!(rets[0].bytecode.len(), 132);
         }
     }
 
-    mod get_block_transaction_count_by_hash {
+    mod get_transaction_by_hash {
         use super::*;
-        use crate::rpc::types::{BlockHashOrTag, Tag};
+        use crate::rpc::types::reply::Transaction;
         use pretty_assertions::assert_eq;
 
-        #[tokio::test]
-        async fn genesis() {
-            let storage = setup_storage();
-            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-            let sync_state = Arc::new(SyncState::default());
-            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(StarknetBlockHash(
-                StarkHash::from_be_slice(b"genesis").unwrap()
-            ));
-            let count = client(addr)
-                .request::<u64>("starknet_getBlockTransactionCountByHash", params)
-                .await
-                .unwrap();
-            assert_eq!(count, 1);
-        }
-
-        mod latest {
+        mod accepted {
             use super::*;
             use pretty_assertions::assert_eq;
 
             #[tokio::test]
             async fn positional_args() {
                 let storage = setup_storage();
+                let hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
                 let sequencer = SeqClient::new(Chain::Goerli).unwrap();
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = rpc_params!(BlockHashOrTag::Tag(Tag::Latest));
-                let count = client(addr)
-                    .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                let params = rpc_params!(hash);
+                let transaction = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByHash", params)
                     .await
                     .unwrap();
-                assert_eq!(count, 3);
+                assert_eq!(transaction.txn_hash, hash);
             }
 
             #[tokio::test]
             async fn named_args() {
                 let storage = setup_storage();
+                let hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
                 let sequencer = SeqClient::new(Chain::Goerli).unwrap();
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = by_name([("block_hash", json!("latest"))]);
-                let count = client(addr)
-                    .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                let params = by_name([("transaction_hash", json!(hash))]);
+                let transaction = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByHash", params)
                     .await
                     .unwrap();
-                assert_eq!(count, 3);
+                assert_eq!(transaction.txn_hash, hash);
             }
         }
 
         #[tokio::test]
-        async fn pending() {
+        async fn invalid_hash() {
             let storage = setup_storage();
-            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-            let sync_state = Arc::new(SyncState::default());
-            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(BlockHashOrTag::Tag(Tag::Pending));
-            client(addr)
-                .request::<u64>("starknet_getBlockTransactionCountByHash", params)
-                .await
-                .unwrap();
-        }
-
-        #[tokio::test]
-        async fn invalid() {
-            let storage = Storage::in_memory().unwrap();
-            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sequencer = failing_sequencer();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(StarknetBlockHash(StarkHash::ZERO));
+            let params = rpc_params!(*INVALID_TX_HASH);
             let error = client(addr)
-                .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                .request::<Transaction>("starknet_getTransactionByHash", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
             );
         }
-    }
-
-    mod get_block_transaction_count_by_number {
-        use super::*;
-        use crate::rpc::types::{BlockNumberOrTag, Tag};
-        use pretty_assertions::assert_eq;
 
         #[tokio::test]
-        async fn genesis() {
+        async fn malformed_hash() {
             let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(0);
-            let count = client(addr)
-                .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
-                .await
-                .unwrap();
-            assert_eq!(count, 1);
-        }
 
-        mod latest {
-            use super::*;
-            use pretty_assertions::assert_eq;
+            let too_long = format!("0x{}", "1".repeat(300));
+            for hash in [too_long.as_str(), "0xnothex"] {
+                for method in [
+                    "starknet_getTransactionByHash",
+                    "starknet_getTransactionReceipt",
+                    "starknet_traceTransaction",
+                ] {
+                    let error = client(addr)
+                        .request::<serde_json::Value>(method, rpc_params!(hash))
+                        .await
+                        .unwrap_err();
+                    assert_matches!(
+                        error,
+                        Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH, "{}", method)
+                    );
+                }
 
-            #[tokio::test]
-            async fn positional_args() {
+                let error = client(addr)
+                    .request::<serde_json::Value>(
+                        "starknet_getTransactionsByHashes",
+                        rpc_params!(vec![hash]),
+                    )
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+                );
+            }
+        }
+
+        mod pending {
+            use super::*;
+            use crate::rpc::types::reply::TransactionStatus;
+            use pretty_assertions::assert_eq;
+
+            /// Spawns a mock sequencer whose pending block contains a single transaction.
+            fn sequencer_with_pending(transaction_hash: StarknetTransactionHash) -> SeqClient {
+                use crate::sequencer::reply::{
+                    transaction::{Transaction, Type},
+                    Block, Status,
+                };
+                use warp::Filter;
+
+                let any = warp::any().map(move || {
+                    warp::reply::json(&Block {
+                        block_hash: None,
+                        block_number: None,
+                        gas_price: None,
+                        parent_block_hash: StarknetBlockHash(
+                            StarkHash::from_be_slice(b"latest").unwrap(),
+                        ),
+                        sequencer_address: None,
+                        state_root: None,
+                        status: Status::Pending,
+                        timestamp: StarknetBlockTimestamp(0),
+                        transaction_receipts: vec![],
+                        transactions: vec![Transaction {
+                            calldata: Some(vec![]),
+                            class_hash: None,
+                            constructor_calldata: None,
+                            contract_address: ContractAddress(
+                                StarkHash::from_be_slice(b"pending contract").unwrap(),
+                            ),
+                            contract_address_salt: None,
+                            entry_point_type: None,
+                            entry_point_selector: None,
+                            max_fee: None,
+                            signature: None,
+                            transaction_hash,
+                            r#type: Type::InvokeFunction,
+                        }],
+                    })
+                });
+                let (addr, run_srv) = warp::serve(any).bind_ephemeral(([127, 0, 0, 1], 0));
+                tokio::spawn(run_srv);
+
+                let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+                SeqClient::with_url(url).unwrap()
+            }
+
+            #[tokio::test]
+            async fn found_in_pending_block() {
                 let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let hash =
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"pending txn").unwrap());
+                let sequencer = sequencer_with_pending(hash);
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Latest));
-                let count = client(addr)
-                    .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                let params = rpc_params!(hash);
+                let transaction = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByHash", params)
                     .await
                     .unwrap();
-                assert_eq!(count, 3);
+                assert_eq!(transaction.txn_hash, hash);
+                assert_eq!(transaction.status, Some(TransactionStatus::Pending));
             }
 
             #[tokio::test]
-            async fn named_args() {
+            async fn accepted_has_no_status() {
                 let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
+                let sequencer = sequencer_with_pending(*INVALID_TX_HASH);
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = by_name([("block_number", json!("latest"))]);
-                let count = client(addr)
-                    .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                let params = rpc_params!(hash);
+                let transaction = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByHash", params)
                     .await
                     .unwrap();
-                assert_eq!(count, 3);
+                assert_eq!(transaction.txn_hash, hash);
+                assert_eq!(transaction.status, None);
+            }
+
+            #[tokio::test]
+            async fn not_in_pending_block() {
+                let storage = setup_storage();
+                let hash =
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"pending txn").unwrap());
+                let sequencer = sequencer_with_pending(hash);
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = rpc_params!(*INVALID_TX_HASH);
+                let error = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByHash", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+                );
             }
         }
+    }
+
+    mod get_transactions_by_hashes {
+        use super::*;
+        use crate::rpc::types::reply::Transaction;
+        use pretty_assertions::assert_eq;
 
         #[tokio::test]
-        async fn pending() {
-            let storage = Storage::in_memory().unwrap();
-            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+        async fn known_and_unknown() {
+            let storage = setup_storage();
+            let sequencer = failing_sequencer();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Pending));
-            client(addr)
-                .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
-                .await
-                .unwrap();
+            let hash0 = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
+            let hash3 = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap());
+            let hashes = vec![hash3, *INVALID_TX_HASH, hash0];
+
+            for params in [
+                rpc_params!(hashes.clone()),
+                by_name([("hashes", json!(hashes.clone()))]),
+            ] {
+                let transactions = client(addr)
+                    .request::<Vec<Option<Transaction>>>("starknet_getTransactionsByHashes", params)
+                    .await
+                    .unwrap();
+                let transaction_hashes = transactions
+                    .into_iter()
+                    .map(|tx| tx.map(|tx| tx.txn_hash))
+                    .collect::<Vec<_>>();
+                assert_eq!(transaction_hashes, vec![Some(hash3), None, Some(hash0)]);
+            }
         }
 
         #[tokio::test]
-        async fn invalid() {
-            let storage = Storage::in_memory().unwrap();
+        async fn too_many_hashes() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
-            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                .with_max_transaction_hashes(1);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(123);
+            let hash0 = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
+            let hash1 = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 1").unwrap());
+            let params = rpc_params!(vec![hash0, hash1]);
             let error = client(addr)
-                .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                .request::<Vec<Option<Transaction>>>("starknet_getTransactionsByHashes", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
+                Error::Request(s) => assert_eq!(get_err(&s).0, -32602)
             );
         }
     }
 
-    mod call {
+    mod get_transaction_by_block_hash_and_index {
         use super::*;
-        use crate::{
-            core::{CallParam, CallResultValue},
-            rpc::types::{request::Call, BlockHashOrTag, Tag},
-        };
+        use crate::rpc::types::{reply::Transaction, BlockHashOrTag, Tag};
         use pretty_assertions::assert_eq;
 
-        lazy_static::lazy_static! {
-            static ref CALL_DATA: Vec<CallParam> = vec![CallParam::from_hex_str("1234").unwrap()];
-        }
-
         #[tokio::test]
-        async fn latest_invoked_block() {
-            let storage = Storage::in_memory().unwrap();
+        async fn genesis() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                Call {
-                    calldata: CALL_DATA.clone(),
-                    contract_address: *VALID_CONTRACT_ADDR,
-                    entry_point_selector: *VALID_ENTRY_POINT,
-                },
-                *INVOKE_CONTRACT_BLOCK_HASH
-            );
-            client(addr)
-                .request::<Vec<CallResultValue>>("starknet_call", params)
+            let genesis_hash = StarknetBlockHash(StarkHash::from_be_slice(b"genesis").unwrap());
+            let params = rpc_params!(genesis_hash, 0);
+            let txn = client(addr)
+                .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
                 .await
                 .unwrap();
+            assert_eq!(
+                txn.txn_hash,
+                StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap())
+            )
         }
 
-        mod latest_block {
+        mod latest {
             use super::*;
+            use pretty_assertions::assert_eq;
 
             #[tokio::test]
             async fn positional_args() {
-                let storage = Storage::in_memory().unwrap();
+                let storage = setup_storage();
                 let sequencer = SeqClient::new(Chain::Goerli).unwrap();
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = rpc_params!(
-                    Call {
-                        calldata: CALL_DATA.clone(),
-                        contract_address: *VALID_CONTRACT_ADDR,
-                        entry_point_selector: *VALID_ENTRY_POINT,
-                    },
-                    BlockHashOrTag::Tag(Tag::Latest)
-                );
-                client(addr)
-                    .request::<Vec<CallResultValue>>("starknet_call", params)
+                let params = rpc_params!(BlockHashOrTag::Tag(Tag::Latest), 0);
+                let txn = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
                     .await
                     .unwrap();
+                assert_eq!(
+                    txn.txn_hash,
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap())
+                );
             }
 
             #[tokio::test]
             async fn named_args() {
-                let storage = Storage::in_memory().unwrap();
+                let storage = setup_storage();
                 let sequencer = SeqClient::new(Chain::Goerli).unwrap();
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                let params = by_name([
-                    (
-                        "request",
-                        json!({
-                            "calldata": CALL_DATA.clone(),
-                            "contract_address": *VALID_CONTRACT_ADDR,
-                            "entry_point_selector": *VALID_ENTRY_POINT,
-                        }),
-                    ),
-                    ("block_hash", json!("latest")),
-                ]);
-                client(addr)
-                    .request::<Vec<CallResultValue>>("starknet_call", params)
+                let params = by_name([("block_hash", json!("latest")), ("index", json!(0))]);
+                let txn = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
                     .await
                     .unwrap();
+                assert_eq!(
+                    txn.txn_hash,
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap())
+                );
             }
         }
 
         #[tokio::test]
-        async fn pending_block() {
+        async fn pending() {
             let storage = Storage::in_memory().unwrap();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                Call {
-                    calldata: CALL_DATA.clone(),
-                    contract_address: *VALID_CONTRACT_ADDR,
-                    entry_point_selector: *VALID_ENTRY_POINT,
-                },
-                BlockHashOrTag::Tag(Tag::Pending)
-            );
+            let params = rpc_params!(BlockHashOrTag::Tag(Tag::Pending), 0);
             client(addr)
-                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
                 .await
                 .unwrap();
         }
 
         #[tokio::test]
-        async fn invalid_entry_point() {
-            let storage = Storage::in_memory().unwrap();
+        async fn invalid_block() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                Call {
-                    calldata: CALL_DATA.clone(),
-                    contract_address: *VALID_CONTRACT_ADDR,
-                    entry_point_selector: *INVALID_ENTRY_POINT,
-                },
-                BlockHashOrTag::Tag(Tag::Latest)
-            );
+            let params = rpc_params!(StarknetBlockHash(StarkHash::ZERO), 0);
             let error = client(addr)
-                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_SELECTOR)
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
             );
         }
 
         #[tokio::test]
-        async fn invalid_contract_address() {
-            let storage = Storage::in_memory().unwrap();
+        async fn invalid_transaction_index() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                Call {
-                    calldata: CALL_DATA.clone(),
-                    contract_address: *INVALID_CONTRACT_ADDR,
-                    entry_point_selector: *VALID_ENTRY_POINT,
-                },
-                BlockHashOrTag::Tag(Tag::Latest)
-            );
+            let genesis_hash = StarknetBlockHash(StarkHash::from_be_slice(b"genesis").unwrap());
+            let params = rpc_params!(genesis_hash, 123);
             let error = client(addr)
-                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .request::<Transaction>("starknet_getTransactionByBlockHashAndIndex", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::CONTRACT_NOT_FOUND)
+                Error::Request(s) => {
+                    assert_eq!(get_err(&s), *error::INVALID_TX_INDEX);
+                    // The genesis block contains a single transaction.
+                    let error = serde_json::from_str::<serde_json::Value>(&s).unwrap();
+                    assert_eq!(error["error"]["data"], json!({ "transaction_count": 1 }));
+                }
             );
         }
+    }
+
+    mod get_transaction_by_block_number_and_index {
+        use super::*;
+        use crate::rpc::types::{reply::Transaction, BlockNumberOrTag, Tag};
+        use pretty_assertions::assert_eq;
 
         #[tokio::test]
-        async fn invalid_call_data() {
-            let storage = Storage::in_memory().unwrap();
+        async fn genesis() {
+            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                Call {
-                    calldata: vec![],
-                    contract_address: *VALID_CONTRACT_ADDR,
-                    entry_point_selector: *VALID_ENTRY_POINT,
-                },
-                BlockHashOrTag::Tag(Tag::Latest)
-            );
-            let error = client(addr)
-                .request::<Vec<CallResultValue>>("starknet_call", params)
+            let params = rpc_params!(0, 0);
+            let txn = client(addr)
+                .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
                 .await
-                .unwrap_err();
-            assert_matches!(
-                error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_CALL_DATA)
+                .unwrap();
+            assert_eq!(
+                txn.txn_hash,
+                StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap())
             );
         }
 
-        #[tokio::test]
-        async fn uninitialized_contract() {
-            let storage = Storage::in_memory().unwrap();
-            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-            let sync_state = Arc::new(SyncState::default());
-            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                Call {
-                    calldata: CALL_DATA.clone(),
-                    contract_address: *VALID_CONTRACT_ADDR,
-                    entry_point_selector: *VALID_ENTRY_POINT,
-                },
-                *PRE_DEPLOY_CONTRACT_BLOCK_HASH
-            );
-            let error = client(addr)
-                .request::<Vec<CallResultValue>>("starknet_call", params)
-                .await
-                .unwrap_err();
-            assert_matches!(
-                error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::CONTRACT_NOT_FOUND)
-            );
+        mod latest {
+            use super::*;
+            use pretty_assertions::assert_eq;
+
+            #[tokio::test]
+            async fn positional_args() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Latest), 0);
+                let txn = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    txn.txn_hash,
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap())
+                );
+            }
+
+            #[tokio::test]
+            async fn named_args() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = by_name([("block_number", json!("latest")), ("index", json!(0))]);
+                let txn = client(addr)
+                    .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    txn.txn_hash,
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap())
+                );
+            }
         }
 
         #[tokio::test]
-        async fn invalid_block_hash() {
+        async fn pending() {
             let storage = Storage::in_memory().unwrap();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let params = rpc_params!(
-                Call {
-                    calldata: CALL_DATA.clone(),
-                    contract_address: *VALID_CONTRACT_ADDR,
-                    entry_point_selector: *VALID_ENTRY_POINT,
-                },
-                *INVALID_BLOCK_HASH
+            let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Pending), 0);
+            client(addr)
+                .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
+                .await
+                .unwrap();
+        }
+
+        #[tokio::test]
+        async fn invalid_block() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(123, 0);
+            let error = client(addr)
+                .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
             );
+        }
+
+        #[tokio::test]
+        async fn invalid_transaction_index() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(0, 123);
             let error = client(addr)
-                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .request::<Transaction>("starknet_getTransactionByBlockNumberAndIndex", params)
                 .await
                 .unwrap_err();
             assert_matches!(
                 error,
-                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
+                Error::Request(s) => {
+                    assert_eq!(get_err(&s), *error::INVALID_TX_INDEX);
+                    // The genesis block contains a single transaction.
+                    let error = serde_json::from_str::<serde_json::Value>(&s).unwrap();
+                    assert_eq!(error["error"]["data"], json!({ "transaction_count": 1 }));
+                }
             );
         }
     }
 
-    #[tokio::test]
-    async fn block_number() {
-        let storage = setup_storage();
-        let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-        let sync_state = Arc::new(SyncState::default());
-        let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-        let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-        let number = client(addr)
-            .request::<u64>("starknet_blockNumber", rpc_params!())
-            .await
-            .unwrap();
-        assert_eq!(number, 2);
-    }
+    mod get_transaction_receipt {
+        use super::*;
+        use crate::rpc::types::reply::TransactionReceipt;
+        use pretty_assertions::assert_eq;
 
-    #[tokio::test]
-    async fn chain_id() {
-        use futures::stream::StreamExt;
+        mod accepted {
+            use super::*;
+            use pretty_assertions::assert_eq;
 
-        assert_eq!(
-            [Chain::Goerli, Chain::Mainnet]
+            #[tokio::test]
+            async fn positional_args() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
+                let params = rpc_params!(txn_hash);
+                let receipt = client(addr)
+                    .request::<TransactionReceipt>("starknet_getTransactionReceipt", params)
+                    .await
+                    .unwrap();
+                assert_eq!(receipt.txn_hash, txn_hash);
+                assert_eq!(
+                    receipt.events[0].keys[0],
+                    EventKey(StarkHash::from_be_slice(b"event 0 key").unwrap())
+                );
+            }
+
+            #[tokio::test]
+            async fn named_args() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
+                let params = by_name([("transaction_hash", json!(txn_hash))]);
+                let receipt = client(addr)
+                    .request::<TransactionReceipt>("starknet_getTransactionReceipt", params)
+                    .await
+                    .unwrap();
+                assert_eq!(receipt.txn_hash, txn_hash);
+                assert_eq!(
+                    receipt.events[0].keys[0],
+                    EventKey(StarkHash::from_be_slice(b"event 0 key").unwrap())
+                );
+                assert_eq!(receipt.rejection_reason, None);
+            }
+        }
+
+        #[tokio::test]
+        async fn rejected() {
+            use crate::rpc::types::reply::TransactionStatus;
+            use crate::sequencer::reply::transaction::Failure;
+
+            let storage = setup_storage();
+            let txn_hashes = [b"txn 3".as_ref(), b"txn 4 ", b"txn 5"]
+                .map(|hash| StarknetTransactionHash(StarkHash::from_be_slice(hash).unwrap()));
+            let mut connection = storage.connection().unwrap();
+            let db_txn = connection.transaction().unwrap();
+            let mut transaction_data = txn_hashes
                 .iter()
-                .map(|set_chain| async {
-                    let storage = Storage::in_memory().unwrap();
-                    let sequencer = SeqClient::new(*set_chain).unwrap();
-                    let sync_state = Arc::new(SyncState::default());
-                    let api = RpcApi::new(storage, sequencer, *set_chain, sync_state);
-                    let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-                    let params = rpc_params!();
-                    client(addr)
-                        .request::<String>("starknet_chainId", params)
-                        .await
+                .map(|hash| {
+                    let txn = StarknetTransactionsTable::get_transaction(&db_txn, *hash)
+                        .unwrap()
+                        .unwrap();
+                    let (receipt, _) = StarknetTransactionsTable::get_receipt(&db_txn, *hash)
                         .unwrap()
+                        .unwrap();
+                    (txn, receipt)
                 })
-                .collect::<futures::stream::FuturesOrdered<_>>()
-                .collect::<Vec<_>>()
-                .await,
-            vec![
-                format!("0x{}", hex::encode("SN_GOERLI")),
-                format!("0x{}", hex::encode("SN_MAIN")),
-            ]
-        );
-    }
-
-    #[tokio::test]
-    #[should_panic]
-    async fn pending_transactions() {
-        let storage = Storage::in_memory().unwrap();
-        let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-        let sync_state = Arc::new(SyncState::default());
-        let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-        let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-        client(addr)
-            .request::<()>("starknet_pendingTransactions", rpc_params!())
-            .await
-            .unwrap();
-    }
-
-    #[tokio::test]
-    #[should_panic]
-    async fn protocol_version() {
-        let storage = Storage::in_memory().unwrap();
-        let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-        let sync_state = Arc::new(SyncState::default());
-        let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-        let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-        client(addr)
-            .request::<StarknetProtocolVersion>("starknet_protocolVersion", rpc_params!())
-            .await
+                .collect::<Vec<_>>();
+            transaction_data[2].1.transaction_failure_reason = Some(Failure {
+                code: "TRANSACTION_FAILED".to_owned(),
+                error_message: "Assertion failed".to_owned(),
+                tx_id: 0,
+            });
+            StarknetTransactionsTable::upsert(
+                &db_txn,
+                StarknetBlockHash(StarkHash::from_be_slice(b"latest").unwrap()),
+                StarknetBlockNumber(2),
+                &transaction_data,
+            )
             .unwrap();
-    }
-
-    mod syncing {
-        use crate::rpc::types::reply::{syncing, Syncing};
-        use pretty_assertions::assert_eq;
-
-        use super::*;
+            db_txn.commit().unwrap();
 
-        #[tokio::test]
-        async fn not_syncing() {
-            let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let syncing = client(addr)
-                .request::<Syncing>("starknet_syncing", rpc_params!())
+            let params = rpc_params!(txn_hashes[2]);
+            let receipt = client(addr)
+                .request::<TransactionReceipt>("starknet_getTransactionReceipt", params)
                 .await
                 .unwrap();
-
-            assert_eq!(syncing, Syncing::False(false));
+            assert_eq!(receipt.txn_hash, txn_hashes[2]);
+            assert_eq!(receipt.status, TransactionStatus::Rejected);
+            assert_eq!(receipt.status_data, "Assertion failed");
+            assert_eq!(
+                receipt.rejection_reason,
+                Some("Assertion failed".to_owned())
+            );
         }
 
         #[tokio::test]
-        async fn syncing() {
-            let expected = Syncing::Status(syncing::Status {
-                starting_block_hash: StarknetBlockHash(
-                    StarkHash::from_be_slice(b"starting").unwrap(),
-                ),
-                starting_block_num: StarknetBlockNumber(1),
-                current_block_hash: StarknetBlockHash(
-                    StarkHash::from_be_slice(b"current").unwrap(),
-                ),
-                current_block_num: StarknetBlockNumber(2),
-                highest_block_hash: StarknetBlockHash(
-                    StarkHash::from_be_slice(b"highest").unwrap(),
-                ),
-                highest_block_num: StarknetBlockNumber(3),
-            });
-
+        async fn invalid() {
             let storage = setup_storage();
             let sequencer = SeqClient::new(Chain::Goerli).unwrap();
             let sync_state = Arc::new(SyncState::default());
-            *sync_state.status.write().await = expected.clone();
             let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
             let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-            let syncing = client(addr)
-                .request::<Syncing>("starknet_syncing", rpc_params!())
+            let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"not found").unwrap());
+            let params = rpc_params!(txn_hash);
+            let error = client(addr)
+                .request::<TransactionReceipt>("starknet_getTransactionReceipt", params)
                 .await
-                .unwrap();
-
-            assert_eq!(syncing, expected);
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+            );
         }
     }
 
-    mod events {
+    mod get_transaction_index {
+        use super::*;
+        use crate::{core::StarknetTransactionIndex, rpc::types::reply::TransactionIndex};
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn accepted() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 3").unwrap());
+            let params = rpc_params!(txn_hash);
+            let index = client(addr)
+                .request::<TransactionIndex>("starknet_getTransactionIndex", params)
+                .await
+                .unwrap();
+            assert_eq!(
+                index,
+                TransactionIndex {
+                    block_hash: StarknetBlockHash(StarkHash::from_be_slice(b"latest").unwrap()),
+                    transaction_index: StarknetTransactionIndex(0),
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn named_args() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 2").unwrap());
+            let params = by_name([("transaction_hash", json!(txn_hash))]);
+            let index = client(addr)
+                .request::<TransactionIndex>("starknet_getTransactionIndex", params)
+                .await
+                .unwrap();
+            assert_eq!(
+                index,
+                TransactionIndex {
+                    block_hash: StarknetBlockHash(StarkHash::from_be_slice(b"block 1").unwrap()),
+                    transaction_index: StarknetTransactionIndex(1),
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn invalid() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"not found").unwrap());
+            let params = rpc_params!(txn_hash);
+            let error = client(addr)
+                .request::<TransactionIndex>("starknet_getTransactionIndex", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+            );
+        }
+    }
+
+    mod get_messages_by_transaction {
+        use super::*;
+        use crate::{
+            core::{EthereumAddress, L2ToL1MessagePayloadElem},
+            rpc::types::reply::{transaction_messages, TransactionMessages},
+            sequencer::reply::transaction::L2ToL1Message,
+        };
+        use pretty_assertions::assert_eq;
+
+        /// Adds an L2-to-L1 message to the receipt of `txn 1`.
+        fn setup_storage_with_message() -> (Storage, L2ToL1Message) {
+            let storage = setup_storage();
+            let connection = storage.connection().unwrap();
+
+            let block = StarknetBlocksTable::get(&connection, StarknetBlockNumber(1).into())
+                .unwrap()
+                .unwrap();
+            let mut transaction_data = StarknetTransactionsTable::get_transaction_data_for_block(
+                &connection,
+                block.number.into(),
+            )
+            .unwrap();
+            let message = L2ToL1Message {
+                from_address: transaction_data[0].0.contract_address,
+                payload: vec![L2ToL1MessagePayloadElem(
+                    StarkHash::from_be_slice(b"message payload").unwrap(),
+                )],
+                to_address: EthereumAddress(web3::types::H160::from_low_u64_be(0x1234)),
+            };
+            transaction_data[0].1.l2_to_l1_messages = vec![message.clone()];
+            StarknetTransactionsTable::upsert(
+                &connection,
+                block.hash,
+                block.number,
+                &transaction_data,
+            )
+            .unwrap();
+
+            (storage, message)
+        }
+
+        #[tokio::test]
+        async fn l2_to_l1_message() {
+            let (storage, message) = setup_storage_with_message();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 1").unwrap());
+            let params = rpc_params!(txn_hash);
+            let messages = client(addr)
+                .request::<TransactionMessages>("starknet_getMessagesByTransaction", params)
+                .await
+                .unwrap();
+            assert_eq!(
+                messages,
+                TransactionMessages {
+                    l1_to_l2_consumed_message: None,
+                    l2_to_l1_messages: vec![transaction_messages::L2ToL1Message::from(message)],
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn no_messages() {
+            let (storage, _) = setup_storage_with_message();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 2").unwrap());
+            let params = by_name([("transaction_hash", json!(txn_hash))]);
+            let messages = client(addr)
+                .request::<TransactionMessages>("starknet_getMessagesByTransaction", params)
+                .await
+                .unwrap();
+            assert_eq!(
+                messages,
+                TransactionMessages {
+                    l1_to_l2_consumed_message: None,
+                    l2_to_l1_messages: vec![],
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn invalid() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let txn_hash = StarknetTransactionHash(StarkHash::from_be_slice(b"not found").unwrap());
+            let params = rpc_params!(txn_hash);
+            let error = client(addr)
+                .request::<TransactionMessages>("starknet_getMessagesByTransaction", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+            );
+        }
+    }
+
+    mod get_code {
+        use super::*;
+        use crate::core::ContractCode;
+        use crate::rpc::types::reply::ErrorCode;
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn invalid_contract_address() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(*INVALID_CONTRACT_ADDR);
+            let error = client(addr)
+                .request::<ContractCode>("starknet_getCode", params)
+                .await
+                .unwrap_err();
+            assert_eq!(ErrorCode::ContractNotFound, error);
+        }
+
+        #[tokio::test]
+        async fn returns_not_found_if_we_dont_know_about_the_contract() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let not_found = client(addr)
+                .request::<ContractCode>(
+                    "starknet_getCode",
+                    rpc_params!(
+                        "0x4ae0618c330c59559a59a27d143dd1c07cd74cf4e5e5a7cd85d53c6bf0e89dc"
+                    ),
+                )
+                .await
+                .unwrap_err();
+
+            assert_eq!(ErrorCode::ContractNotFound, not_found);
+        }
+
+        #[tokio::test]
+        async fn zero_contract_address() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            // Not even the sequencer is asked for it.
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                .with_sequencer_code_fallback(true);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let error = client(addr)
+                .request::<ContractCode>("starknet_getCode", rpc_params!(ContractAddress::ZERO))
+                .await
+                .unwrap_err();
+            assert_eq!(ErrorCode::ContractNotFound, error);
+        }
+
+        #[tokio::test]
+        async fn returns_abi_and_code_for_known() {
+            use crate::core::ContractCode;
+            use anyhow::Context;
+            use bytes::Bytes;
+            use futures::stream::TryStreamExt;
+            use pedersen::StarkHash;
+
+            let storage = Storage::in_memory().unwrap();
+
+            let contract_definition = include_bytes!("../fixtures/contract_definition.json.zst");
+            let buffer = zstd::decode_all(std::io::Cursor::new(contract_definition)).unwrap();
+            let contract_definition = Bytes::from(buffer);
+
+            {
+                let mut conn = storage.connection().unwrap();
+                let tx = conn.transaction().unwrap();
+
+                let address = StarkHash::from_hex_str(
+                    "057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374",
+                )
+                .unwrap();
+                let expected_hash = StarkHash::from_hex_str(
+                    "050b2148c0d782914e0b12a1a32abe5e398930b7e914f82c65cb7afce0a0ab9b",
+                )
+                .unwrap();
+
+                let (abi, bytecode, hash) =
+                    crate::state::contract_hash::extract_abi_code_hash(&*contract_definition)
+                        .unwrap();
+
+                assert_eq!(hash.0, expected_hash);
+
+                crate::storage::ContractCodeTable::insert(
+                    &tx,
+                    hash,
+                    &abi,
+                    &bytecode,
+                    &contract_definition,
+                )
+                .context("Deploy testing contract")
+                .unwrap();
+
+                crate::storage::ContractsTable::upsert(
+                    &tx,
+                    crate::core::ContractAddress(address),
+                    hash,
+                )
+                .unwrap();
+
+                tx.commit().unwrap();
+            }
+
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let client = client(addr);
+
+            // both parameters, these used to be separate tests
+            let rets = [
+                rpc_params!("0x057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374"),
+                by_name([(
+                    "contract_address",
+                    json!("0x057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374"),
+                )]),
+            ]
+            .into_iter()
+            .map(|arg| client.request::<ContractCode>("starknet_getCode", arg))
+            .collect::<futures::stream::FuturesOrdered<_>>()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+            assert_eq!(rets.len(), 2);
+
+            assert_eq!(rets[0], rets[1]);
+            let abi = rets[0].abi.to_string();
+            assert_eq!(
+                abi,
+                // this should not have the quotes because that'd be in json:
+                // `"abi":"\"[{....}]\""`
+                r#"[{"inputs":[{"name":"address","type":"felt"},{"name":"value","type":"felt"}],"name":"increase_value","outputs":[],"type":"function"},{"inputs":[{"name":"contract_address","type":"felt"},{"name":"address","type":"felt"},{"name":"value","type":"felt"}],"name":"call_increase_value","outputs":[],"type":"function"},{"inputs":[{"name":"address","type":"felt"}],"name":"get_value","outputs":[{"name":"res","type":"felt"}],"type":"function"}]"#
+            );
+            assert_eq!(rets[0].bytecode.len(), 132);
+        }
+
+        #[tokio::test]
+        async fn include_events() {
+            use crate::rpc::types::reply::{
+                contract_abi::{Event, Member},
+                GetCodeResult,
+            };
+
+            let storage = Storage::in_memory().unwrap();
+
+            let fixture_address = ContractAddress(
+                StarkHash::from_hex_str(
+                    "057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374",
+                )
+                .unwrap(),
+            );
+            let address = ContractAddress(StarkHash::from_hex_str("abc").unwrap());
+            {
+                let mut conn = storage.connection().unwrap();
+                let tx = conn.transaction().unwrap();
+
+                let contract_definition =
+                    include_bytes!("../fixtures/contract_definition.json.zst");
+                let contract_definition =
+                    zstd::decode_all(std::io::Cursor::new(contract_definition)).unwrap();
+                let (abi, bytecode, hash) =
+                    crate::state::contract_hash::extract_abi_code_hash(&contract_definition)
+                        .unwrap();
+                crate::storage::ContractCodeTable::insert(
+                    &tx,
+                    hash,
+                    &abi,
+                    &bytecode,
+                    &contract_definition,
+                )
+                .unwrap();
+                crate::storage::ContractsTable::upsert(&tx, fixture_address, hash).unwrap();
+
+                let hash = ContractHash(StarkHash::from_hex_str("123").unwrap());
+                let abi = br#"[
+                    {"type":"struct","name":"Pair","size":2,"members":[]},
+                    {"type":"event","name":"Transfer","keys":[],"data":[{"name":"to","type":"felt"},{"name":"amount","type":"Pair"}]}
+                ]"#;
+                crate::storage::ContractCodeTable::insert(&tx, hash, abi, b"[]", b"{}").unwrap();
+                crate::storage::ContractsTable::upsert(&tx, address, hash).unwrap();
+
+                tx.commit().unwrap();
+            }
+
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let client = client(addr);
+
+            // The fixture contract does not declare any events.
+            let result = client
+                .request::<GetCodeResult>("starknet_getCode", rpc_params!(fixture_address, true))
+                .await
+                .unwrap();
+            assert_eq!(result.events, Some(vec![]));
+            assert_eq!(result.code.bytecode.len(), 132);
+
+            let result = client
+                .request::<GetCodeResult>("starknet_getCode", rpc_params!(address, true))
+                .await
+                .unwrap();
+            let member = |name: &str, r#type: &str| Member {
+                name: name.to_owned(),
+                r#type: r#type.to_owned(),
+            };
+            assert_eq!(
+                result.events,
+                Some(vec![Event {
+                    name: "Transfer".to_owned(),
+                    keys: vec![],
+                    data: vec![member("to", "felt"), member("amount", "Pair")],
+                }])
+            );
+
+            // Events are left out unless requested.
+            let result = client
+                .request::<GetCodeResult>("starknet_getCode", rpc_params!(address))
+                .await
+                .unwrap();
+            assert_eq!(result.events, None);
+        }
+
+        #[tokio::test]
+        async fn exceeding_max_decompressed_size() {
+            let storage = Storage::in_memory().unwrap();
+            let address = ContractAddress(StarkHash::from_hex_str("abc").unwrap());
+            {
+                let mut conn = storage.connection().unwrap();
+                let tx = conn.transaction().unwrap();
+
+                let hash = ContractHash(StarkHash::from_hex_str("123").unwrap());
+                // Compresses to a small fraction of its size.
+                let abi = vec![b' '; 4096];
+                crate::storage::ContractCodeTable::insert(&tx, hash, &abi, b"[]", b"{}").unwrap();
+                crate::storage::ContractsTable::upsert(&tx, address, hash).unwrap();
+
+                tx.commit().unwrap();
+            }
+
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                .with_max_decompressed_size(1024);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let error = client(addr)
+                .request::<ContractCode>("starknet_getCode", rpc_params!(address))
+                .await
+                .unwrap_err();
+            assert_matches!(error, Error::Request(_));
+        }
+
+        mod sequencer_fallback {
+            use super::*;
+            use pretty_assertions::assert_eq;
+
+            const ADDRESS: &str =
+                "0x057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374";
+
+            /// Spawns a mock sequencer which replies with the test contract's definition
+            /// to any request.
+            fn sequencer_with_contract() -> SeqClient {
+                use warp::Filter;
+
+                let contract_definition =
+                    include_bytes!("../fixtures/contract_definition.json.zst");
+                let contract_definition =
+                    zstd::decode_all(std::io::Cursor::new(contract_definition)).unwrap();
+
+                let any = warp::any().map(move || contract_definition.clone());
+                let (addr, run_srv) = warp::serve(any).bind_ephemeral(([127, 0, 0, 1], 0));
+                tokio::spawn(run_srv);
+
+                let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+                SeqClient::with_url(url).unwrap()
+            }
+
+            #[tokio::test]
+            async fn fetches_unknown_contract_without_storing_it() {
+                let storage = Storage::in_memory().unwrap();
+                let sequencer = sequencer_with_contract();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage.clone(), sequencer, Chain::Goerli, sync_state)
+                    .with_sequencer_code_fallback(true);
+                let fallbacks = api.sequencer_fallbacks();
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let code = client(addr)
+                    .request::<ContractCode>("starknet_getCode", rpc_params!(ADDRESS))
+                    .await
+                    .unwrap();
+                assert_eq!(code.bytecode.len(), 132);
+                assert_eq!(fallbacks.get("starknet_getCode"), 1);
+
+                // Only the sync process stores contracts, once their deployment is verified.
+                let mut conn = storage.connection().unwrap();
+                let tx = conn.transaction().unwrap();
+                let address = ContractAddress(StarkHash::from_hex_str(ADDRESS).unwrap());
+                let stored = crate::storage::ContractCodeTable::get_code(&tx, address).unwrap();
+                assert_eq!(stored, None);
+                let hash = crate::storage::ContractsTable::get_hash(&tx, address).unwrap();
+                assert_eq!(hash, None);
+
+                let again = client(addr)
+                    .request::<ContractCode>("starknet_getCode", rpc_params!(ADDRESS))
+                    .await
+                    .unwrap();
+                assert_eq!(again, code);
+                assert_eq!(fallbacks.get("starknet_getCode"), 2);
+            }
+
+            #[tokio::test]
+            async fn disabled_by_default() {
+                let storage = Storage::in_memory().unwrap();
+                let sequencer = sequencer_with_contract();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let error = client(addr)
+                    .request::<ContractCode>("starknet_getCode", rpc_params!(ADDRESS))
+                    .await
+                    .unwrap_err();
+                assert_eq!(ErrorCode::ContractNotFound, error);
+            }
+        }
+    }
+
+    mod get_abi {
+        use super::*;
+        use crate::rpc::types::reply::{
+            contract_abi::{Function, Member},
+            ContractAbi, ErrorCode,
+        };
+        use crate::rpc::types::{BlockHashOrTag, Tag};
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn returns_functions_for_known() {
+            use crate::{ethereum::state_update::ContractUpdate, state::update_contract_state};
+
+            let storage = Storage::in_memory().unwrap();
+
+            let contract_definition = include_bytes!("../fixtures/contract_definition.json.zst");
+            let contract_definition =
+                zstd::decode_all(std::io::Cursor::new(contract_definition)).unwrap();
+            let address = ContractAddress(
+                StarkHash::from_hex_str(
+                    "057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374",
+                )
+                .unwrap(),
+            );
+
+            {
+                let mut conn = storage.connection().unwrap();
+                let tx = conn.transaction().unwrap();
+
+                let (abi, bytecode, hash) =
+                    crate::state::contract_hash::extract_abi_code_hash(&contract_definition)
+                        .unwrap();
+                ContractCodeTable::insert(&tx, hash, &abi, &bytecode, &contract_definition)
+                    .unwrap();
+                ContractsTable::upsert(&tx, address, hash).unwrap();
+
+                let mut global_tree =
+                    GlobalStateTree::load(&tx, GlobalRoot(StarkHash::ZERO)).unwrap();
+                let update = ContractUpdate {
+                    address,
+                    storage_updates: vec![],
+                };
+                let contract_state_hash =
+                    update_contract_state(&update, &global_tree, &tx).unwrap();
+                global_tree.set(address, contract_state_hash).unwrap();
+                let root = global_tree.apply().unwrap();
+
+                let block = StarknetBlock {
+                    number: StarknetBlockNumber(0),
+                    hash: StarknetBlockHash(StarkHash::from_be_slice(b"genesis").unwrap()),
+                    root,
+                    timestamp: StarknetBlockTimestamp(0),
+                    gas_price: GasPrice::ZERO,
+                    sequencer_address: SequencerAddress(StarkHash::ZERO),
+                };
+                StarknetBlocksTable::insert(&tx, &block).unwrap();
+
+                tx.commit().unwrap();
+            }
+
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let params = rpc_params!(address, BlockHashOrTag::Tag(Tag::Latest));
+            let abi = client(addr)
+                .request::<ContractAbi>("starknet_getAbi", params)
+                .await
+                .unwrap();
+
+            let felt = |name: &str| Member {
+                name: name.to_owned(),
+                r#type: "felt".to_owned(),
+            };
+            assert_eq!(
+                abi,
+                ContractAbi {
+                    functions: vec![
+                        Function {
+                            name: "increase_value".to_owned(),
+                            inputs: vec![felt("address"), felt("value")],
+                            outputs: vec![],
+                        },
+                        Function {
+                            name: "call_increase_value".to_owned(),
+                            inputs: vec![felt("contract_address"), felt("address"), felt("value")],
+                            outputs: vec![],
+                        },
+                        Function {
+                            name: "get_value".to_owned(),
+                            inputs: vec![felt("address")],
+                            outputs: vec![felt("res")],
+                        },
+                    ],
+                    events: vec![],
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn contract_not_yet_deployed() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            // Contract 1 is only deployed in block 1.
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                StarknetBlockHash(StarkHash::from_be_slice(b"genesis").unwrap())
+            );
+            let error = client(addr)
+                .request::<ContractAbi>("starknet_getAbi", params)
+                .await
+                .unwrap_err();
+            assert_eq!(ErrorCode::ContractNotFound, error);
+        }
+    }
+
+    mod class_exists {
+        use super::*;
+
+        #[tokio::test]
+        async fn known_and_unknown() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let known = ContractHash(StarkHash::from_be_slice(b"contract 0 hash").unwrap());
+            let unknown = ContractHash(StarkHash::from_be_slice(b"no such class").unwrap());
+
+            let exists = client(addr)
+                .request::<bool>("starknet_classExists", rpc_params!(known))
+                .await
+                .unwrap();
+            assert!(exists);
+
+            let exists = client(addr)
+                .request::<bool>(
+                    "starknet_classExists",
+                    by_name([("class_hash", json!(unknown))]),
+                )
+                .await
+                .unwrap();
+            assert!(!exists);
+        }
+    }
+
+    mod get_block_transaction_count_by_hash {
+        use super::*;
+        use crate::rpc::types::{BlockHashOrTag, Tag};
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn genesis() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(StarknetBlockHash(
+                StarkHash::from_be_slice(b"genesis").unwrap()
+            ));
+            let count = client(addr)
+                .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                .await
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        #[tokio::test]
+        async fn genesis_by_number() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(StarknetBlockNumber(0));
+            let count = client(addr)
+                .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                .await
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        mod latest {
+            use super::*;
+            use pretty_assertions::assert_eq;
+
+            #[tokio::test]
+            async fn positional_args() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = rpc_params!(BlockHashOrTag::Tag(Tag::Latest));
+                let count = client(addr)
+                    .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                    .await
+                    .unwrap();
+                assert_eq!(count, 3);
+            }
+
+            #[tokio::test]
+            async fn named_args() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = by_name([("block_hash", json!("latest"))]);
+                let count = client(addr)
+                    .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                    .await
+                    .unwrap();
+                assert_eq!(count, 3);
+            }
+        }
+
+        #[tokio::test]
+        async fn pending() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(BlockHashOrTag::Tag(Tag::Pending));
+            client(addr)
+                .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                .await
+                .unwrap();
+        }
+
+        #[tokio::test]
+        async fn invalid() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(StarknetBlockHash(StarkHash::ZERO));
+            let error = client(addr)
+                .request::<u64>("starknet_getBlockTransactionCountByHash", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
+            );
+        }
+    }
+
+    mod get_block_transaction_count_by_number {
+        use super::*;
+        use crate::rpc::types::{BlockNumberOrTag, Tag};
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn genesis() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(0);
+            let count = client(addr)
+                .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                .await
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        #[tokio::test]
+        async fn genesis_by_hash() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(StarknetBlockHash(
+                StarkHash::from_be_slice(b"genesis").unwrap()
+            ));
+            let count = client(addr)
+                .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                .await
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+
+        mod latest {
+            use super::*;
+            use pretty_assertions::assert_eq;
+
+            #[tokio::test]
+            async fn positional_args() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Latest));
+                let count = client(addr)
+                    .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                    .await
+                    .unwrap();
+                assert_eq!(count, 3);
+            }
+
+            #[tokio::test]
+            async fn named_args() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = by_name([("block_number", json!("latest"))]);
+                let count = client(addr)
+                    .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                    .await
+                    .unwrap();
+                assert_eq!(count, 3);
+            }
+        }
+
+        #[tokio::test]
+        async fn pending() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Pending));
+            client(addr)
+                .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                .await
+                .unwrap();
+        }
+
+        #[tokio::test]
+        async fn invalid() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(123);
+            let error = client(addr)
+                .request::<u64>("starknet_getBlockTransactionCountByNumber", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
+            );
+        }
+    }
+
+    mod call {
+        use super::*;
+        use crate::{
+            core::{CallParam, CallResultValue},
+            rpc::types::{request::Call, BlockHashOrTag, Tag},
+        };
+        use pretty_assertions::assert_eq;
+
+        lazy_static::lazy_static! {
+            static ref CALL_DATA: Vec<CallParam> = vec![CallParam::from_hex_str("1234").unwrap()];
+        }
+
+        #[tokio::test]
+        async fn latest_invoked_block() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: CALL_DATA.clone(),
+                    contract_address: *VALID_CONTRACT_ADDR,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                *INVOKE_CONTRACT_BLOCK_HASH
+            );
+            client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap();
+        }
+
+        mod latest_block {
+            use super::*;
+
+            #[tokio::test]
+            async fn positional_args() {
+                let storage = Storage::in_memory().unwrap();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = rpc_params!(
+                    Call {
+                        calldata: CALL_DATA.clone(),
+                        contract_address: *VALID_CONTRACT_ADDR,
+                        entry_point_selector: *VALID_ENTRY_POINT,
+                        sender_address: None,
+                    },
+                    BlockHashOrTag::Tag(Tag::Latest)
+                );
+                client(addr)
+                    .request::<Vec<CallResultValue>>("starknet_call", params)
+                    .await
+                    .unwrap();
+            }
+
+            #[tokio::test]
+            async fn named_args() {
+                let storage = Storage::in_memory().unwrap();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let params = by_name([
+                    (
+                        "request",
+                        json!({
+                            "calldata": CALL_DATA.clone(),
+                            "contract_address": *VALID_CONTRACT_ADDR,
+                            "entry_point_selector": *VALID_ENTRY_POINT,
+                        }),
+                    ),
+                    ("block_hash", json!("latest")),
+                ]);
+                client(addr)
+                    .request::<Vec<CallResultValue>>("starknet_call", params)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        #[tokio::test]
+        async fn zero_contract_address() {
+            use crate::rpc::types::reply::ErrorCode;
+
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: CALL_DATA.clone(),
+                    contract_address: ContractAddress::ZERO,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_eq!(ErrorCode::ContractNotFound, error);
+        }
+
+        #[tokio::test]
+        async fn pending_block() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: CALL_DATA.clone(),
+                    contract_address: *VALID_CONTRACT_ADDR,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                BlockHashOrTag::Tag(Tag::Pending)
+            );
+            client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap();
+        }
+
+        #[tokio::test]
+        async fn invalid_entry_point() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: CALL_DATA.clone(),
+                    contract_address: *VALID_CONTRACT_ADDR,
+                    entry_point_selector: *INVALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_SELECTOR)
+            );
+        }
+
+        #[tokio::test]
+        async fn invalid_contract_address() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: CALL_DATA.clone(),
+                    contract_address: *INVALID_CONTRACT_ADDR,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::CONTRACT_NOT_FOUND)
+            );
+        }
+
+        #[tokio::test]
+        async fn invalid_call_data() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: vec![],
+                    contract_address: *VALID_CONTRACT_ADDR,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_CALL_DATA)
+            );
+        }
+
+        #[tokio::test]
+        async fn missing_call_data() {
+            use crate::core::{ContractAddress, EntryPoint};
+
+            let storage = Storage::in_memory().unwrap();
+
+            let contract_definition = include_bytes!("../fixtures/contract_definition.json.zst");
+            let contract_definition =
+                zstd::decode_all(std::io::Cursor::new(contract_definition)).unwrap();
+            let address = ContractAddress(
+                StarkHash::from_hex_str(
+                    "057dde83c18c0efe7123c36a52d704cf27d5c38cdf0b1e1edc3b0dae3ee4e374",
+                )
+                .unwrap(),
+            );
+
+            {
+                let mut conn = storage.connection().unwrap();
+                let tx = conn.transaction().unwrap();
+
+                let (abi, bytecode, hash) =
+                    crate::state::contract_hash::extract_abi_code_hash(&contract_definition)
+                        .unwrap();
+                ContractCodeTable::insert(&tx, hash, &abi, &bytecode, &contract_definition)
+                    .unwrap();
+                ContractsTable::upsert(&tx, address, hash).unwrap();
+
+                tx.commit().unwrap();
+            }
+
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            // `call_increase_value(contract_address, address, value)`
+            let params = rpc_params!(
+                Call {
+                    calldata: vec![],
+                    contract_address: address,
+                    entry_point_selector: EntryPoint::hashed(&b"call_increase_value"[..]),
+                    sender_address: None,
+                },
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => {
+                    assert_eq!(get_err(&s), *error::INVALID_CALL_DATA);
+
+                    let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+                    assert_eq!(v["error"]["data"]["expected_calldata_len"], 3);
+                    assert_eq!(
+                        v["error"]["data"]["reason"],
+                        "The entry point expects at least 3 calldata values, but none were given"
+                    );
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn revert_reason() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: vec![],
+                    contract_address: *VALID_CONTRACT_ADDR,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => {
+                    assert_eq!(get_err(&s), *error::INVALID_CALL_DATA);
+
+                    let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+                    let revert_reason = v["error"]["data"]["revert_reason"].as_str().unwrap();
+                    assert!(!revert_reason.is_empty());
+                }
+            );
+        }
+
+        #[tokio::test]
+        async fn uninitialized_contract() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: CALL_DATA.clone(),
+                    contract_address: *VALID_CONTRACT_ADDR,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                *PRE_DEPLOY_CONTRACT_BLOCK_HASH
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::CONTRACT_NOT_FOUND)
+            );
+        }
+
+        #[tokio::test]
+        async fn invalid_block_hash() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: CALL_DATA.clone(),
+                    contract_address: *VALID_CONTRACT_ADDR,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                *INVALID_BLOCK_HASH
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
+            );
+        }
+
+        #[tokio::test]
+        async fn invalid_block_number() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(
+                Call {
+                    calldata: CALL_DATA.clone(),
+                    contract_address: *VALID_CONTRACT_ADDR,
+                    entry_point_selector: *VALID_ENTRY_POINT,
+                    sender_address: None,
+                },
+                *INVALID_BLOCK_NUMBER
+            );
+            let error = client(addr)
+                .request::<Vec<CallResultValue>>("starknet_call", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
+            );
+        }
+    }
+
+    mod simulate_calls {
+        use super::*;
+        use crate::{
+            core::{CallParam, CallResultValue},
+            rpc::types::{request::Call, BlockId, Tag},
+        };
+        use pretty_assertions::assert_eq;
+
+        fn call() -> Call {
+            Call {
+                calldata: vec![CallParam::from_hex_str("1234").unwrap()],
+                contract_address: *VALID_CONTRACT_ADDR,
+                entry_point_selector: *VALID_ENTRY_POINT,
+                sender_address: None,
+            }
+        }
+
+        #[tokio::test]
+        async fn requires_call_executors() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(vec![call(), call()], BlockId::Tag(Tag::Latest));
+            let error = client(addr)
+                .request::<Vec<Vec<CallResultValue>>>("starknet_simulateCalls", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(
+                    get_err(&s),
+                    (-32000, "Simulating calls is not available on this node".to_owned())
+                )
+            );
+        }
+
+        #[tokio::test]
+        async fn empty_calls() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = by_name([("calls", json!([])), ("block_id", json!("latest"))]);
+            let error = client(addr)
+                .request::<Vec<Vec<CallResultValue>>>("starknet_simulateCalls", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s).0, -32602)
+            );
+        }
+    }
+
+    mod trace_transaction {
+        use super::*;
+        use crate::rpc::types::reply::CallTrace;
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn invalid_hash() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let params = rpc_params!(*INVALID_TX_HASH);
+            let error = client(addr)
+                .request::<CallTrace>("starknet_traceTransaction", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+            );
+        }
+
+        #[tokio::test]
+        async fn deploy_transaction() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 1").unwrap());
+            let params = by_name([("transaction_hash", json!(hash))]);
+            let error = client(addr)
+                .request::<CallTrace>("starknet_traceTransaction", params)
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(
+                    get_err(&s),
+                    (-32602, "Only invoke transactions can be traced".to_owned())
+                )
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn block_number() {
+        let storage = setup_storage();
+        let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+        let sync_state = Arc::new(SyncState::default());
+        let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+        let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+        let number = client(addr)
+            .request::<u64>("starknet_blockNumber", rpc_params!())
+            .await
+            .unwrap();
+        assert_eq!(number, 2);
+    }
+
+    #[tokio::test]
+    async fn latest_block_follows_sync() {
+        use crate::rpc::types::{
+            reply::{Block, BlockHeader, BlockStatus},
+            Tag,
+        };
+
+        let storage = setup_storage();
+        let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+        let sync_state = Arc::new(SyncState::default());
+        let api = RpcApi::new(
+            storage.clone(),
+            sequencer,
+            Chain::Goerli,
+            sync_state.clone(),
+        );
+        let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+        let latest_hash = move || async move {
+            client(addr)
+                .request::<Block>(
+                    "starknet_getBlockByNumber",
+                    rpc_params!(BlockNumberOrTag::Tag(Tag::Latest)),
+                )
+                .await
+                .unwrap()
+                .block_hash
+        };
+        let latest_number = move || async move {
+            client(addr)
+                .request::<u64>("starknet_blockNumber", rpc_params!())
+                .await
+                .unwrap()
+        };
+        assert_eq!(latest_number().await, 2);
+
+        // Sync inserts a new tip, announcing it once stored.
+        let block = StarknetBlock {
+            number: StarknetBlockNumber(3),
+            hash: StarknetBlockHash(StarkHash::from_be_slice(b"newest").unwrap()),
+            root: GlobalRoot(StarkHash::from_be_slice(b"newest root").unwrap()),
+            timestamp: StarknetBlockTimestamp(3),
+            gas_price: GasPrice::ZERO,
+            sequencer_address: SequencerAddress(StarkHash::ZERO),
+        };
+        let connection = storage.connection().unwrap();
+        StarknetBlocksTable::insert(&connection, &block).unwrap();
+        sync_state
+            .new_heads
+            .send(BlockHeader {
+                block_hash: block.hash,
+                parent_hash: StarknetBlockHash(StarkHash::from_be_slice(b"latest").unwrap()),
+                block_number: block.number,
+                status: BlockStatus::AcceptedOnL2,
+                sequencer: block.sequencer_address,
+                new_root: block.root,
+                accepted_time: block.timestamp,
+                gas_price: block.gas_price,
+            })
+            .unwrap();
+        assert_eq!(latest_number().await, 3);
+        assert_eq!(latest_hash().await, Some(block.hash));
+
+        // Sync rolls the new tip back again.
+        StarknetBlocksTable::reorg(&connection, StarknetBlockNumber(3)).unwrap();
+        sync_state.reorgs.send(StarknetBlockNumber(3)).unwrap();
+        assert_eq!(latest_number().await, 2);
+        assert_eq!(
+            latest_hash().await,
+            Some(StarknetBlockHash(
+                StarkHash::from_be_slice(b"latest").unwrap()
+            ))
+        );
+    }
+
+    mod prune_blocks {
+        use super::*;
+        use crate::{
+            core::StorageValue,
+            rpc::types::{BlockHashOrTag, Tag},
+        };
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn genesis() {
+            let storage = setup_storage();
+            {
+                let mut connection = storage.connection().unwrap();
+                let tx = connection.transaction().unwrap();
+                let genesis_root =
+                    StarknetBlocksTable::get_root(&tx, StarknetBlockNumber(0).into())
+                        .unwrap()
+                        .unwrap();
+
+                crate::state::prune_blocks(&tx, StarknetBlockNumber(1)).unwrap();
+
+                assert_eq!(
+                    StarknetBlocksTable::get(&tx, StarknetBlockNumber(0).into()).unwrap(),
+                    None
+                );
+                let txn0_hash =
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 0").unwrap());
+                let txn1_hash =
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"txn 1").unwrap());
+                assert!(StarknetTransactionsTable::get_transaction(&tx, txn0_hash)
+                    .unwrap()
+                    .is_none());
+                assert!(StarknetTransactionsTable::get_transaction(&tx, txn1_hash)
+                    .unwrap()
+                    .is_some());
+                // The genesis root is not shared with the remaining blocks.
+                assert!(GlobalStateTree::load(&tx, genesis_root).is_err());
+
+                tx.commit().unwrap();
+            }
+
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            for (block, expected) in [
+                (
+                    BlockHashOrTag::Hash(StarknetBlockHash(
+                        StarkHash::from_be_slice(b"block 1").unwrap(),
+                    )),
+                    b"storage value 1",
+                ),
+                (BlockHashOrTag::Tag(Tag::Latest), b"storage value 2"),
+            ] {
+                let params = rpc_params!(
+                    ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+                    StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                    block
+                );
+                let value = client(addr)
+                    .request::<StorageValue>("starknet_getStorageAt", params)
+                    .await
+                    .unwrap();
+                assert_eq!(value.0, StarkHash::from_be_slice(expected).unwrap());
+            }
+
+            // Contract 0 was deployed in genesis, its state must still be reachable.
+            let params = rpc_params!(
+                ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap()),
+                StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                BlockHashOrTag::Tag(Tag::Latest)
+            );
+            let value = client(addr)
+                .request::<StorageValue>("starknet_getStorageAt", params)
+                .await
+                .unwrap();
+            assert_eq!(value.0, StarkHash::ZERO);
+        }
+    }
+
+    mod contract_storage_entries {
+        use super::*;
+        use crate::core::StorageValue;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn latest() {
+            let storage = setup_storage();
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+
+            let entries = crate::state::contract_storage_entries(
+                &tx,
+                StarknetBlockNumber(2).into(),
+                ContractAddress(StarkHash::from_be_slice(b"contract 1").unwrap()),
+            )
+            .unwrap()
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+            assert_eq!(
+                entries,
+                vec![(
+                    StorageAddress(StarkHash::from_be_slice(b"storage addr 0").unwrap()),
+                    StorageValue(StarkHash::from_be_slice(b"storage value 2").unwrap())
+                )]
+            );
+        }
+
+        #[test]
+        fn contract_without_storage() {
+            let storage = setup_storage();
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+
+            let mut entries = crate::state::contract_storage_entries(
+                &tx,
+                StarknetBlockNumber(2).into(),
+                ContractAddress(StarkHash::from_be_slice(b"contract 0").unwrap()),
+            )
+            .unwrap()
+            .unwrap();
+
+            assert!(entries.next().is_none());
+        }
+
+        #[test]
+        fn unknown_contract() {
+            let storage = setup_storage();
+            let mut connection = storage.connection().unwrap();
+            let tx = connection.transaction().unwrap();
+
+            let entries = crate::state::contract_storage_entries(
+                &tx,
+                StarknetBlockNumber(2).into(),
+                ContractAddress(StarkHash::from_be_slice(b"unknown").unwrap()),
+            )
+            .unwrap();
+
+            assert!(entries.is_none());
+        }
+    }
+
+    mod get_recent_block_hashes {
+        use super::*;
+        use crate::rpc::types::reply::BlockHashAndNumber;
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn latest_first() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let latest = BlockHashAndNumber {
+                block_number: StarknetBlockNumber(2),
+                block_hash: StarknetBlockHash(StarkHash::from_be_slice(b"latest").unwrap()),
+            };
+            let block1 = BlockHashAndNumber {
+                block_number: StarknetBlockNumber(1),
+                block_hash: StarknetBlockHash(StarkHash::from_be_slice(b"block 1").unwrap()),
+            };
+            let genesis = BlockHashAndNumber {
+                block_number: StarknetBlockNumber::GENESIS,
+                block_hash: StarknetBlockHash(StarkHash::from_be_slice(b"genesis").unwrap()),
+            };
+
+            for params in [rpc_params!(2), by_name([("n", json!(2))])] {
+                let hashes = client(addr)
+                    .request::<Vec<BlockHashAndNumber>>("starknet_getRecentBlockHashes", params)
+                    .await
+                    .unwrap();
+                assert_eq!(hashes, vec![latest, block1]);
+            }
+
+            let hashes = client(addr)
+                .request::<Vec<BlockHashAndNumber>>(
+                    "starknet_getRecentBlockHashes",
+                    rpc_params!(10),
+                )
+                .await
+                .unwrap();
+            assert_eq!(hashes, vec![latest, block1, genesis]);
+        }
+
+        #[tokio::test]
+        async fn too_many() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                .with_max_recent_block_hashes(2);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let error = client(addr)
+                .request::<Vec<BlockHashAndNumber>>("starknet_getRecentBlockHashes", rpc_params!(3))
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s).0, -32602)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn chain_id() {
+        use futures::stream::StreamExt;
+
+        assert_eq!(
+            [Chain::Goerli, Chain::Mainnet]
+                .iter()
+                .map(|set_chain| async {
+                    let storage = Storage::in_memory().unwrap();
+                    let sequencer = SeqClient::new(*set_chain).unwrap();
+                    let sync_state = Arc::new(SyncState::default());
+                    let api = RpcApi::new(storage, sequencer, *set_chain, sync_state);
+                    let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                    let params = rpc_params!();
+                    client(addr)
+                        .request::<String>("starknet_chainId", params)
+                        .await
+                        .unwrap()
+                })
+                .collect::<futures::stream::FuturesOrdered<_>>()
+                .collect::<Vec<_>>()
+                .await,
+            vec![
+                format!("0x{}", hex::encode("SN_GOERLI")),
+                format!("0x{}", hex::encode("SN_MAIN")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_id_is_cached() {
+        // Hex encodings of "SN_GOERLI" and "SN_MAIN".
+        for (chain, expected) in [
+            (Chain::Goerli, "0x534e5f474f45524c49"),
+            (Chain::Mainnet, "0x534e5f4d41494e"),
+        ] {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(chain).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, chain, sync_state);
+
+            // Every call returns the value resolved on construction.
+            assert_eq!(api.chain_id().await.unwrap(), expected);
+            assert_eq!(api.chain_id().await.unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    #[should_panic]
+    async fn pending_transactions() {
+        let storage = Storage::in_memory().unwrap();
+        let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+        let sync_state = Arc::new(SyncState::default());
+        let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+        let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+        client(addr)
+            .request::<()>("starknet_pendingTransactions", rpc_params!())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[should_panic]
+    async fn protocol_version() {
+        let storage = Storage::in_memory().unwrap();
+        let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+        let sync_state = Arc::new(SyncState::default());
+        let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+        let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+        client(addr)
+            .request::<StarknetProtocolVersion>("starknet_protocolVersion", rpc_params!())
+            .await
+            .unwrap();
+    }
+
+    mod syncing {
+        use crate::rpc::types::reply::{syncing, Syncing};
+        use pretty_assertions::assert_eq;
+
+        use super::*;
+
+        #[tokio::test]
+        async fn not_syncing() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let syncing = client(addr)
+                .request::<Syncing>("starknet_syncing", rpc_params!())
+                .await
+                .unwrap();
+
+            assert_eq!(syncing, Syncing::False(false));
+        }
+
+        #[tokio::test]
+        async fn syncing() {
+            let expected = Syncing::Status(syncing::Status {
+                starting_block_hash: StarknetBlockHash(
+                    StarkHash::from_be_slice(b"starting").unwrap(),
+                ),
+                starting_block_num: StarknetBlockNumber(1),
+                current_block_hash: StarknetBlockHash(
+                    StarkHash::from_be_slice(b"current").unwrap(),
+                ),
+                current_block_num: StarknetBlockNumber(2),
+                highest_block_hash: StarknetBlockHash(
+                    StarkHash::from_be_slice(b"highest").unwrap(),
+                ),
+                highest_block_num: StarknetBlockNumber(3),
+            });
+
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            *sync_state.status.write().await = expected.clone();
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let syncing = client(addr)
+                .request::<Syncing>("starknet_syncing", rpc_params!())
+                .await
+                .unwrap();
+
+            assert_eq!(syncing, expected);
+        }
+
+        mod sync_stats {
+            use super::*;
+            use crate::rpc::types::reply::SyncStats;
+            use pretty_assertions::assert_eq;
+
+            #[tokio::test]
+            async fn not_syncing() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let stats = client(addr)
+                    .request::<SyncStats>("starknet_syncStats", rpc_params!())
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    stats,
+                    SyncStats {
+                        current_block_num: None,
+                        highest_block_num: None,
+                        blocks_behind: None,
+                        blocks_per_second: None,
+                        estimated_seconds_remaining: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn catching_up() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                *sync_state.status.write().await = Syncing::Status(syncing::Status {
+                    starting_block_hash: StarknetBlockHash(StarkHash::ZERO),
+                    starting_block_num: StarknetBlockNumber(0),
+                    current_block_hash: StarknetBlockHash(StarkHash::ZERO),
+                    current_block_num: StarknetBlockNumber(110),
+                    highest_block_hash: StarknetBlockHash(StarkHash::ZERO),
+                    highest_block_num: StarknetBlockNumber(130),
+                });
+                // 10 blocks stored in 2 seconds.
+                {
+                    let mut sync_rate = sync_state.sync_rate.write().await;
+                    let start = std::time::Instant::now();
+                    sync_rate.record(start, StarknetBlockNumber(100));
+                    sync_rate.record(
+                        start + std::time::Duration::from_secs(2),
+                        StarknetBlockNumber(110),
+                    );
+                }
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+                let stats = client(addr)
+                    .request::<SyncStats>("starknet_syncStats", rpc_params!())
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    stats,
+                    SyncStats {
+                        current_block_num: Some(StarknetBlockNumber(110)),
+                        highest_block_num: Some(StarknetBlockNumber(130)),
+                        blocks_behind: Some(20),
+                        blocks_per_second: Some(5.0),
+                        estimated_seconds_remaining: Some(4.0),
+                    }
+                );
+            }
+        }
+    }
+
+    mod l1_confirmed_block {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn none_confirmed() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let confirmed = client(addr)
+                .request::<Option<StarknetBlockNumber>>("starknet_l1ConfirmedBlock", rpc_params!())
+                .await
+                .unwrap();
+
+            assert_eq!(confirmed, None);
+        }
+
+        #[tokio::test]
+        async fn confirmed() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            *sync_state.l1_confirmed.write().await = Some(StarknetBlockNumber(1));
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            let confirmed = client(addr)
+                .request::<Option<StarknetBlockNumber>>("starknet_l1ConfirmedBlock", rpc_params!())
+                .await
+                .unwrap();
+
+            assert_eq!(confirmed, Some(StarknetBlockNumber(1)));
+        }
+    }
+
+    mod events {
         use super::*;
 
         use super::types::reply::{EmittedEvent, GetEventsResult};
         use crate::sequencer::reply::transaction;
 
-        const NUM_BLOCKS: usize = 4;
-        const TRANSACTIONS_PER_BLOCK: usize = 10;
-        const EVENTS_PER_BLOCK: usize = TRANSACTIONS_PER_BLOCK;
-        const NUM_TRANSACTIONS: usize = NUM_BLOCKS * TRANSACTIONS_PER_BLOCK;
-        const NUM_EVENTS: usize = NUM_BLOCKS * EVENTS_PER_BLOCK;
+        const NUM_BLOCKS: usize = 4;
+        const TRANSACTIONS_PER_BLOCK: usize = 10;
+        const EVENTS_PER_BLOCK: usize = TRANSACTIONS_PER_BLOCK;
+        const NUM_TRANSACTIONS: usize = NUM_BLOCKS * TRANSACTIONS_PER_BLOCK;
+        const NUM_EVENTS: usize = NUM_BLOCKS * EVENTS_PER_BLOCK;
+
+        fn create_transactions_and_receipts(
+        ) -> [(transaction::Transaction, transaction::Receipt); NUM_TRANSACTIONS] {
+            let transactions = (0..NUM_TRANSACTIONS).map(|i| transaction::Transaction {
+                calldata: None,
+                class_hash: None,
+                constructor_calldata: None,
+                contract_address: ContractAddress(
+                    StarkHash::from_hex_str(&"2".repeat(i + 3)).unwrap(),
+                ),
+                contract_address_salt: None,
+                entry_point_type: None,
+                entry_point_selector: None,
+                signature: None,
+                transaction_hash: StarknetTransactionHash(
+                    StarkHash::from_hex_str(&"f".repeat(i + 3)).unwrap(),
+                ),
+                r#type: transaction::Type::InvokeFunction,
+                max_fee: None,
+            });
+            let receipts = (0..NUM_TRANSACTIONS).map(|i| transaction::Receipt {
+                actual_fee: None,
+                events: vec![transaction::Event {
+                    from_address: ContractAddress(
+                        StarkHash::from_hex_str(&"2".repeat(i + 3)).unwrap(),
+                    ),
+                    data: vec![EventData(
+                        StarkHash::from_hex_str(&"c".repeat(i + 3)).unwrap(),
+                    )],
+                    keys: vec![
+                        EventKey(StarkHash::from_hex_str(&"d".repeat(i + 3)).unwrap()),
+                        EventKey(StarkHash::from_hex_str("deadbeef").unwrap()),
+                    ],
+                }],
+                execution_resources: transaction::ExecutionResources {
+                    builtin_instance_counter:
+                        transaction::execution_resources::BuiltinInstanceCounter::Empty(
+                            transaction::execution_resources::EmptyBuiltinInstanceCounter {},
+                        ),
+                    n_steps: i as u64 + 987,
+                    n_memory_holes: i as u64 + 1177,
+                },
+                l1_to_l2_consumed_message: None,
+                l2_to_l1_messages: Vec::new(),
+                transaction_hash: StarknetTransactionHash(
+                    StarkHash::from_hex_str(&"e".repeat(i + 3)).unwrap(),
+                ),
+                transaction_index: StarknetTransactionIndex(i as u64 + 2311),
+                transaction_failure_reason: None,
+            });
+
+            transactions
+                .into_iter()
+                .zip(receipts)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap()
+        }
+
+        fn setup() -> (Storage, Vec<EmittedEvent>) {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let blocks = crate::storage::test_utils::create_blocks::<NUM_BLOCKS>();
+            let transactions_and_receipts = create_transactions_and_receipts();
+
+            for (i, block) in blocks.iter().enumerate() {
+                StarknetBlocksTable::insert(&connection, block).unwrap();
+                StarknetTransactionsTable::upsert(
+                    &connection,
+                    block.hash,
+                    block.number,
+                    &transactions_and_receipts
+                        [i * TRANSACTIONS_PER_BLOCK..(i + 1) * TRANSACTIONS_PER_BLOCK],
+                )
+                .unwrap();
+            }
+
+            let events = transactions_and_receipts
+                .iter()
+                .enumerate()
+                .map(|(i, (txn, receipt))| {
+                    let event = &receipt.events[0];
+                    let block = &blocks[i / TRANSACTIONS_PER_BLOCK];
+
+                    EmittedEvent {
+                        data: event.data.clone(),
+                        from_address: event.from_address,
+                        keys: event.keys.clone(),
+                        block_hash: Some(block.hash),
+                        block_number: Some(block.number),
+                        transaction_hash: txn.transaction_hash,
+                    }
+                })
+                .collect();
+
+            (storage, events)
+        }
+
+        mod subscription {
+            use super::*;
+            use crate::rpc::types::reply::{BlockHeader, BlockStatus};
+            use jsonrpsee::{
+                types::traits::SubscriptionClient,
+                ws_client::{WsClient, WsClientBuilder},
+            };
+            use pretty_assertions::assert_eq;
+
+            /// Notifies the subscribers of `block`, as sync does once the block is stored.
+            fn notify(sync_state: &SyncState, block: &StarknetBlock) {
+                let header = BlockHeader {
+                    block_hash: block.hash,
+                    parent_hash: StarknetBlockHash(StarkHash::ZERO),
+                    block_number: block.number,
+                    status: BlockStatus::AcceptedOnL2,
+                    sequencer: block.sequencer_address,
+                    new_root: block.root,
+                    accepted_time: block.timestamp,
+                    gas_price: block.gas_price,
+                };
+                sync_state.new_heads.send(header).unwrap();
+            }
+
+            async fn setup_subscription() -> (Vec<EmittedEvent>, Arc<SyncState>, WsClient) {
+                setup_subscription_with_key_limit(StarknetEventsTable::KEY_COUNT_LIMIT).await
+            }
+
+            async fn setup_subscription_with_key_limit(
+                max_event_filter_keys: usize,
+            ) -> (Vec<EmittedEvent>, Arc<SyncState>, WsClient) {
+                let (storage, events) = setup();
+                let sync_state = Arc::new(SyncState::default());
+                let (_handle, addr) = run_ws_server(
+                    *LOCALHOST,
+                    storage,
+                    sync_state.clone(),
+                    max_event_filter_keys,
+                )
+                .await
+                .unwrap();
+                let client = WsClientBuilder::default()
+                    .build(&format!("ws://{}", addr))
+                    .await
+                    .unwrap();
+                (events, sync_state, client)
+            }
+
+            /// Waits until the server has set up the subscription.
+            async fn wait_for_subscriber(sync_state: &SyncState) {
+                tokio::time::timeout(Duration::from_secs(5), async {
+                    while sync_state.new_heads.receiver_count() == 0 {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                })
+                .await
+                .unwrap();
+            }
+
+            #[tokio::test]
+            async fn receives_matching_events_of_stored_blocks() {
+                let (events, sync_state, client) = setup_subscription().await;
+                let blocks = crate::storage::test_utils::create_blocks::<NUM_BLOCKS>();
+
+                // Matches the event of the second transaction of each block.
+                let keys = events
+                    .iter()
+                    .skip(1)
+                    .step_by(TRANSACTIONS_PER_BLOCK)
+                    .map(|event| event.keys[0])
+                    .collect::<Vec<_>>();
+                let filter = EventSubscriptionFilter {
+                    address: None,
+                    exclude_address: None,
+                    keys,
+                    key_match: KeyMatchMode::Exact,
+                };
+                let mut subscription = client
+                    .subscribe::<EmittedEvent>(
+                        "starknet_subscribeEvents",
+                        rpc_params!(filter),
+                        "starknet_unsubscribeEvents",
+                    )
+                    .await
+                    .unwrap();
+                wait_for_subscriber(&sync_state).await;
+
+                notify(&sync_state, &blocks[2]);
+                notify(&sync_state, &blocks[3]);
+                let expected = &events[2 * TRANSACTIONS_PER_BLOCK + 1];
+                assert_eq!(subscription.next().await.unwrap().as_ref(), Some(expected));
+                let expected = &events[3 * TRANSACTIONS_PER_BLOCK + 1];
+                assert_eq!(subscription.next().await.unwrap().as_ref(), Some(expected));
+            }
+
+            #[tokio::test]
+            async fn too_many_keys_closes_subscription() {
+                let (_, _, client) = setup_subscription_with_key_limit(1).await;
+
+                let filter = EventSubscriptionFilter {
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![
+                        EventKey(StarkHash::from_hex_str("deadbeef").unwrap()),
+                        EventKey(StarkHash::from_hex_str("cafebabe").unwrap()),
+                    ],
+                    key_match: KeyMatchMode::Exact,
+                };
+                let mut subscription = client
+                    .subscribe::<EmittedEvent>(
+                        "starknet_subscribeEvents",
+                        rpc_params!(filter),
+                        "starknet_unsubscribeEvents",
+                    )
+                    .await
+                    .unwrap();
+
+                let error = subscription.next().await.unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::SubscriptionClosed(closed) => {
+                        assert_eq!(
+                            closed.close_reason(),
+                            "Too many keys in filter, at most 1 are allowed"
+                        )
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn slow_subscriber_is_closed() {
+                let (_, sync_state, client) = setup_subscription().await;
+                let blocks = crate::storage::test_utils::create_blocks::<NUM_BLOCKS>();
+
+                let filter = EventSubscriptionFilter {
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![EventKey(StarkHash::from_hex_str("deadbeef").unwrap())],
+                    key_match: KeyMatchMode::Exact,
+                };
+                let mut subscription = client
+                    .subscribe::<EmittedEvent>(
+                        "starknet_subscribeEvents",
+                        rpc_params!(filter),
+                        "starknet_unsubscribeEvents",
+                    )
+                    .await
+                    .unwrap();
+                wait_for_subscriber(&sync_state).await;
+
+                // The subscription is not served in between, so its buffer overflows.
+                for i in 0..=SyncState::NEW_HEADS_CAPACITY {
+                    notify(&sync_state, &blocks[i % NUM_BLOCKS]);
+                }
+
+                let error = subscription.next().await.unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::SubscriptionClosed(closed) => {
+                        assert_eq!(
+                            closed.close_reason(),
+                            "Subscriber too slow, missed the events of 1 block(s)"
+                        )
+                    }
+                );
+                tokio::time::timeout(Duration::from_secs(5), async {
+                    while sync_state.new_heads.receiver_count() != 0 {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                })
+                .await
+                .unwrap();
+            }
+        }
+
+        mod positional_args {
+            use super::*;
+
+            use pretty_assertions::assert_eq;
+
+            #[tokio::test]
+            async fn get_events_with_empty_filter() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events,
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_with_fully_specified_filter() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let expected_event = &events[1];
+                let params = rpc_params!(EventFilter {
+                    from_block: expected_event.block_number,
+                    to_block: expected_event.block_number.map(Into::into),
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: Some(expected_event.from_address.into()),
+                    exclude_address: None,
+                    // we're using a key which is present in _all_ events
+                    keys: vec![EventKey(StarkHash::from_hex_str("deadbeef").unwrap())],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![expected_event.clone()],
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_from_multiple_addresses() {
+                use crate::rpc::types::request::EventAddressFilter;
+
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let addresses = vec![events[1].from_address, events[NUM_EVENTS - 1].from_address];
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: Some(EventAddressFilter::Multiple(addresses)),
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![events[1].clone(), events[NUM_EVENTS - 1].clone()],
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_by_timestamp_range() {
+                use crate::core::StarknetBlockTimestamp;
+
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                // Blocks are created with timestamps 500, 501 and so on.
+                let filter = |from_timestamp, to_timestamp| EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: Some(StarknetBlockTimestamp::from_unix_secs(from_timestamp)),
+                    to_timestamp: Some(StarknetBlockTimestamp::from_unix_secs(to_timestamp)),
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                };
+
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", rpc_params!(filter(501, 502)))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: events[EVENTS_PER_BLOCK..3 * EVENTS_PER_BLOCK].to_vec(),
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                // The same range in milliseconds is rejected instead of matching nothing.
+                let error = client(addr)
+                    .request::<GetEventsResult>(
+                        "starknet_getEvents",
+                        rpc_params!(filter(501_000, 502_000_000_000_000)),
+                    )
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(
+                        get_err(&s),
+                        (
+                            -32602,
+                            "Timestamp 502000000000000 is out of range, timestamps are in seconds since the Unix epoch"
+                                .to_owned()
+                        )
+                    )
+                );
+            }
+
+            #[tokio::test]
+            async fn total_pages_when_counting() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                // The last of the three pages is only partially filled.
+                const PAGE_SIZE: usize = 15;
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(PAGE_SIZE),
+                    page_number: 1,
+                    continuation_token: None,
+                    include_count: true,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: events[PAGE_SIZE..2 * PAGE_SIZE].to_vec(),
+                        page_number: 1,
+                        is_last_page: false,
+                        continuation_token: None,
+                        total_count: Some(NUM_EVENTS),
+                        total_pages: Some(3),
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_by_block() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                const BLOCK_NUMBER: usize = 2;
+                let params = rpc_params!(EventFilter {
+                    from_block: Some(StarknetBlockNumber(BLOCK_NUMBER as u64)),
+                    to_block: Some(StarknetBlockNumber(BLOCK_NUMBER as u64).into()),
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                let expected_events =
+                    &events[EVENTS_PER_BLOCK * BLOCK_NUMBER..EVENTS_PER_BLOCK * (BLOCK_NUMBER + 1)];
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: expected_events.to_vec(),
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_by_block_hash_range() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let blocks = crate::storage::test_utils::create_blocks::<NUM_BLOCKS>();
+                // Mixing a hash bound with a number bound is allowed.
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: Some(blocks[2].number.into()),
+                    from_block_hash: Some(blocks[1].hash),
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: events[EVENTS_PER_BLOCK..EVENTS_PER_BLOCK * 3].to_vec(),
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: Some(blocks[3].hash),
+                    to_block_hash: Some(blocks[3].hash),
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: events[EVENTS_PER_BLOCK * 3..].to_vec(),
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_with_reversed_block_range() {
+                let (storage, _events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let blocks = crate::storage::test_utils::create_blocks::<NUM_BLOCKS>();
+                let expected_error = (
+                    -32602,
+                    "Block range starts at block 2 after it ends at block 1".to_owned(),
+                );
+
+                let params = rpc_params!(EventFilter {
+                    from_block: Some(StarknetBlockNumber(2)),
+                    to_block: Some(StarknetBlockNumber(1).into()),
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let error = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s), expected_error)
+                );
+
+                // Bounds given by hash are checked once resolved.
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: Some(blocks[2].hash),
+                    to_block_hash: Some(blocks[1].hash),
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let error = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s), expected_error)
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_with_unknown_block_hash() {
+                let (storage, _events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: Some(StarknetBlockHash(
+                        StarkHash::from_hex_str("0xbad").unwrap()
+                    )),
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let error = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_HASH)
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_with_invalid_page_size() {
+                let (storage, _events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(crate::storage::StarknetEventsTable::PAGE_SIZE_LIMIT + 1),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let error = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(
+                        serde_json::from_str::<serde_json::Value>(&s).unwrap()["error"],
+                        json!({
+                            "code": 31,
+                            "message": "Requested page size is too big",
+                            "data": {
+                                "max_page_size": crate::storage::StarknetEventsTable::PAGE_SIZE_LIMIT
+                            }
+                        })
+                    )
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_with_too_many_keys() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                    .with_max_event_filter_keys(2);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: events[..3].iter().map(|e| e.keys[0]).collect(),
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(10),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let error = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(
+                        serde_json::from_str::<serde_json::Value>(&s).unwrap()["error"],
+                        json!({
+                            "code": 34,
+                            "message": "Too many keys provided in a filter",
+                            "data": {
+                                "max_keys": 2
+                            }
+                        })
+                    )
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_by_key_with_paging() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let expected_events = &events[27..32];
+                let keys_for_expected_events: Vec<_> =
+                    expected_events.iter().map(|e| e.keys[0]).collect();
+
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: keys_for_expected_events.clone(),
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(2),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: expected_events[..2].to_vec(),
+                        page_number: 0,
+                        is_last_page: false,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: keys_for_expected_events.clone(),
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(2),
+                    page_number: 1,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: expected_events[2..4].to_vec(),
+                        page_number: 1,
+                        is_last_page: false,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: keys_for_expected_events.clone(),
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(2),
+                    page_number: 2,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: expected_events[4..].to_vec(),
+                        page_number: 2,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                // nonexistent page
+                let params = rpc_params!(EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: keys_for_expected_events.clone(),
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(2),
+                    page_number: 3,
+                    continuation_token: None,
+                    include_count: false,
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![],
+                        page_number: 3,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn concurrent_queries_above_limit_are_queued() {
+                const MAX_CONCURRENT_QUERIES: usize = 2;
+
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                    .with_max_concurrent_event_queries(
+                        std::num::NonZeroUsize::new(MAX_CONCURRENT_QUERIES).unwrap(),
+                    );
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let queries = (0..=MAX_CONCURRENT_QUERIES).map(|page_number| async move {
+                    let params = rpc_params!(EventFilter {
+                        from_block: None,
+                        to_block: None,
+                        from_block_hash: None,
+                        to_block_hash: None,
+                        from_timestamp: None,
+                        to_timestamp: None,
+                        address: None,
+                        exclude_address: None,
+                        keys: vec![],
+                        key_match: KeyMatchMode::Exact,
+                        page_size: Some(1),
+                        page_number,
+                        continuation_token: None,
+                        include_count: false,
+                    });
+                    client(addr)
+                        .request::<GetEventsResult>("starknet_getEvents", params)
+                        .await
+                });
+                let results = futures::future::join_all(queries).await;
+
+                for (page_number, result) in results.into_iter().enumerate() {
+                    assert_eq!(
+                        result.unwrap(),
+                        GetEventsResult {
+                            events: vec![events[page_number].clone()],
+                            page_number,
+                            is_last_page: false,
+                            continuation_token: None,
+                            total_count: None,
+                            total_pages: None,
+                        }
+                    );
+                }
+            }
+
+            #[tokio::test]
+            async fn partial_page_past_soft_deadline() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                // Every query runs past a zero deadline, right after its first event.
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                    .with_event_soft_deadline(Some(std::time::Duration::ZERO));
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let mut filter = EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(5),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                };
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", rpc_params!(filter.clone()))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![events[0].clone()],
+                        page_number: 0,
+                        is_last_page: false,
+                        continuation_token: Some(1),
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                filter.continuation_token = rpc_result.continuation_token;
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", rpc_params!(filter))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![events[1].clone()],
+                        page_number: 0,
+                        is_last_page: false,
+                        continuation_token: Some(2),
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+        }
+
+        mod named_args {
+            use super::*;
+
+            use pretty_assertions::assert_eq;
+
+            #[tokio::test]
+            async fn get_events_with_empty_filter() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params =
+                    by_name([("filter", json!({"page_size": NUM_EVENTS, "page_number": 0}))]);
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events,
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_with_default_page_size() {
+                const DEFAULT_PAGE_SIZE: usize = 3;
+
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
+                    .with_default_event_page_size(DEFAULT_PAGE_SIZE);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = by_name([("filter", json!({"page_number": 1}))]);
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: events[DEFAULT_PAGE_SIZE..2 * DEFAULT_PAGE_SIZE].to_vec(),
+                        page_number: 1,
+                        is_last_page: false,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn get_events_with_fully_specified_filter() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let expected_event = &events[1];
+                let params = by_name([(
+                    "filter",
+                    json!({
+                        "fromBlock": expected_event.block_number.unwrap().0,
+                        "toBlock": expected_event.block_number.unwrap().0,
+                        "address": expected_event.from_address,
+                        "keys": [expected_event.keys[0]],
+                        "page_size": NUM_EVENTS,
+                        "page_number": 0,
+                    }),
+                )]);
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![expected_event.clone()],
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+        }
+
+        mod pending {
+            use super::*;
+            use crate::rpc::types::{BlockNumberOrTag, Tag};
+            use pretty_assertions::assert_eq;
+
+            /// Spawns a mock sequencer whose pending block contains `events`, each emitted
+            /// by a separate transaction.
+            fn sequencer_with_pending(events: Vec<transaction::Event>) -> SeqClient {
+                use crate::sequencer::reply::{Block, Status};
+                use warp::Filter;
+
+                let (_, receipt) = create_transactions_and_receipts()[0].clone();
+                let receipts = events
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, event)| transaction::Receipt {
+                        events: vec![event],
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str(&"a".repeat(i + 3)).unwrap(),
+                        ),
+                        ..receipt.clone()
+                    })
+                    .collect::<Vec<_>>();
+
+                let any = warp::any().map(move || {
+                    warp::reply::json(&Block {
+                        block_hash: None,
+                        block_number: None,
+                        gas_price: None,
+                        parent_block_hash: StarknetBlockHash(StarkHash::ZERO),
+                        sequencer_address: None,
+                        state_root: None,
+                        status: Status::Pending,
+                        timestamp: StarknetBlockTimestamp(0),
+                        transaction_receipts: receipts.clone(),
+                        transactions: vec![],
+                    })
+                });
+                let (addr, run_srv) = warp::serve(any).bind_ephemeral(([127, 0, 0, 1], 0));
+                tokio::spawn(run_srv);
+
+                let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+                SeqClient::with_url(url).unwrap()
+            }
+
+            fn pending_event(i: usize) -> (transaction::Event, EmittedEvent) {
+                let event = transaction::Event {
+                    from_address: ContractAddress(
+                        StarkHash::from_hex_str(&"b".repeat(i + 3)).unwrap(),
+                    ),
+                    data: vec![],
+                    keys: vec![EventKey(StarkHash::from_hex_str("deadbeef").unwrap())],
+                };
+                let emitted = EmittedEvent {
+                    data: vec![],
+                    keys: event.keys.clone(),
+                    from_address: event.from_address,
+                    block_hash: None,
+                    block_number: None,
+                    transaction_hash: StarknetTransactionHash(
+                        StarkHash::from_hex_str(&"a".repeat(i + 3)).unwrap(),
+                    ),
+                };
+                (event, emitted)
+            }
+
+            fn filter(page_size: usize, page_number: usize) -> EventFilter {
+                EventFilter {
+                    from_block: None,
+                    to_block: Some(BlockNumberOrTag::Tag(Tag::Pending)),
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(page_size),
+                    page_number,
+                    continuation_token: None,
+                    include_count: false,
+                }
+            }
+
+            #[tokio::test]
+            async fn appends_pending_events() {
+                let (storage, events) = setup();
+                let (pending0, expected0) = pending_event(0);
+                let (pending1, expected1) = pending_event(1);
+                let sequencer = sequencer_with_pending(vec![pending0, pending1]);
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(filter(NUM_EVENTS + 2, 0));
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+
+                let mut expected_events = events;
+                expected_events.extend([expected0, expected1]);
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: expected_events,
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn paging_continues_into_pending_events() {
+                let (storage, events) = setup();
+                let (pending0, expected0) = pending_event(0);
+                let (pending1, expected1) = pending_event(1);
+                let (pending2, expected2) = pending_event(2);
+                let sequencer = sequencer_with_pending(vec![pending0, pending1, pending2]);
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                const PAGE_SIZE: usize = NUM_EVENTS - 1;
+                let params = rpc_params!(filter(PAGE_SIZE, 0));
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: events[..PAGE_SIZE].to_vec(),
+                        page_number: 0,
+                        is_last_page: false,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                let params = rpc_params!(filter(2, PAGE_SIZE / 2));
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![events[PAGE_SIZE - 1].clone(), events[PAGE_SIZE].clone()],
+                        page_number: PAGE_SIZE / 2,
+                        is_last_page: false,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                let params = rpc_params!(filter(2, NUM_EVENTS / 2));
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![expected0, expected1],
+                        page_number: NUM_EVENTS / 2,
+                        is_last_page: false,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+
+                let params = rpc_params!(filter(2, NUM_EVENTS / 2 + 1));
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![expected2],
+                        page_number: NUM_EVENTS / 2 + 1,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn pending_events_are_filtered() {
+                let (storage, _) = setup();
+                let (pending0, _) = pending_event(0);
+                let (pending1, expected1) = pending_event(1);
+                let sequencer = sequencer_with_pending(vec![pending0, pending1]);
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(EventFilter {
+                    address: Some(expected1.from_address.into()),
+                    exclude_address: None,
+                    ..filter(NUM_EVENTS, 0)
+                });
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![expected1],
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+        }
+
+        mod cache {
+            use super::*;
+            use crate::rpc::types::reply::{BlockHeader, BlockStatus};
+            use pretty_assertions::assert_eq;
+
+            fn filter() -> EventFilter {
+                EventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_block_hash: None,
+                    to_block_hash: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    address: None,
+                    exclude_address: None,
+                    keys: vec![],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: Some(NUM_EVENTS),
+                    page_number: 0,
+                    continuation_token: None,
+                    include_count: false,
+                }
+            }
+
+            #[tokio::test]
+            async fn repeated_filter_does_not_query_database() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(
+                    storage.clone(),
+                    sequencer,
+                    Chain::Goerli,
+                    sync_state.clone(),
+                )
+                .with_events_cache(Duration::from_secs(60), 16);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let expected = GetEventsResult {
+                    events,
+                    page_number: 0,
+                    is_last_page: true,
+                    continuation_token: None,
+                    total_count: None,
+                    total_pages: None,
+                };
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", rpc_params!(filter()))
+                    .await
+                    .unwrap();
+                assert_eq!(rpc_result, expected);
+
+                // With the events gone from the database, they can only be served from the cache.
+                storage
+                    .connection()
+                    .unwrap()
+                    .execute("DELETE FROM starknet_events", [])
+                    .unwrap();
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", rpc_params!(filter()))
+                    .await
+                    .unwrap();
+                assert_eq!(rpc_result, expected);
+
+                // A new head drops the cached result, so the database is queried again.
+                let blocks = crate::storage::test_utils::create_blocks::<NUM_BLOCKS>();
+                let block = &blocks[NUM_BLOCKS - 1];
+                let header = BlockHeader {
+                    block_hash: block.hash,
+                    parent_hash: StarknetBlockHash(StarkHash::ZERO),
+                    block_number: block.number,
+                    status: BlockStatus::AcceptedOnL2,
+                    sequencer: block.sequencer_address,
+                    new_root: block.root,
+                    accepted_time: block.timestamp,
+                    gas_price: block.gas_price,
+                };
+                sync_state.new_heads.send(header).unwrap();
+                let rpc_result = client(addr)
+                    .request::<GetEventsResult>("starknet_getEvents", rpc_params!(filter()))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    rpc_result,
+                    GetEventsResult {
+                        events: vec![],
+                        page_number: 0,
+                        is_last_page: true,
+                        continuation_token: None,
+                        total_count: None,
+                        total_pages: None,
+                    }
+                );
+            }
+        }
+
+        mod event_key_stats {
+            use super::*;
+
+            use crate::core::EventKey;
+            use pretty_assertions::assert_eq;
+            use std::collections::BTreeMap;
+
+            #[tokio::test]
+            async fn block() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(BlockNumberOrTag::Number(StarknetBlockNumber(1)));
+                let rpc_result = client(addr)
+                    .request::<BTreeMap<EventKey, u64>>("starknet_getEventKeyStats", params)
+                    .await
+                    .unwrap();
+
+                let mut expected = BTreeMap::new();
+                events
+                    .iter()
+                    .filter(|event| event.block_number == Some(StarknetBlockNumber(1)))
+                    .for_each(|event| *expected.entry(event.keys[0]).or_default() += 1);
+                assert_eq!(expected.values().sum::<u64>(), EVENTS_PER_BLOCK as u64);
+                assert_eq!(rpc_result, expected);
+            }
+
+            #[tokio::test]
+            async fn invalid_block() {
+                let (storage, _) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(BlockNumberOrTag::Number(StarknetBlockNumber(
+                    NUM_BLOCKS as u64
+                )));
+                let error = client(addr)
+                    .request::<BTreeMap<EventKey, u64>>("starknet_getEventKeyStats", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_BLOCK_NUMBER)
+                );
+            }
+        }
+
+        mod transaction_events {
+            use super::*;
+
+            use pretty_assertions::assert_eq;
+
+            #[tokio::test]
+            async fn with_event() {
+                let (storage, events) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let expected_event = &events[NUM_EVENTS / 2];
+                let params = rpc_params!(expected_event.transaction_hash);
+                let rpc_result = client(addr)
+                    .request::<Vec<EmittedEvent>>("starknet_getTransactionEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(rpc_result, vec![expected_event.clone()]);
+            }
 
-        fn create_transactions_and_receipts(
-        ) -> [(transaction::Transaction, transaction::Receipt); NUM_TRANSACTIONS] {
-            let transactions = (0..NUM_TRANSACTIONS).map(|i| transaction::Transaction {
-                calldata: None,
-                class_hash: None,
-                constructor_calldata: None,
-                contract_address: ContractAddress(
-                    StarkHash::from_hex_str(&"2".repeat(i + 3)).unwrap(),
-                ),
-                contract_address_salt: None,
-                entry_point_type: None,
-                entry_point_selector: None,
-                signature: None,
-                transaction_hash: StarknetTransactionHash(
-                    StarkHash::from_hex_str(&"f".repeat(i + 3)).unwrap(),
-                ),
-                r#type: transaction::Type::InvokeFunction,
-                max_fee: None,
-            });
-            let receipts = (0..NUM_TRANSACTIONS).map(|i| transaction::Receipt {
-                actual_fee: None,
-                events: vec![transaction::Event {
-                    from_address: ContractAddress(
-                        StarkHash::from_hex_str(&"2".repeat(i + 3)).unwrap(),
+            #[tokio::test]
+            async fn without_events() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let hash = StarknetTransactionHash(StarkHash::from_be_slice(b"txn 1").unwrap());
+                let params = rpc_params!(hash);
+                let rpc_result = client(addr)
+                    .request::<Vec<EmittedEvent>>("starknet_getTransactionEvents", params)
+                    .await
+                    .unwrap();
+                assert_eq!(rpc_result, vec![]);
+            }
+
+            #[tokio::test]
+            async fn invalid_hash() {
+                let (storage, _) = setup();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(*INVALID_TX_HASH);
+                let error = client(addr)
+                    .request::<Vec<EmittedEvent>>("starknet_getTransactionEvents", params)
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s), *error::INVALID_TX_HASH)
+                );
+            }
+        }
+    }
+
+    mod add_transaction {
+        use super::*;
+        use crate::rpc::types::reply::{DeployTransactionResult, InvokeTransactionResult};
+
+        lazy_static::lazy_static! {
+            pub static ref CONTRACT_DEFINITION_JSON: serde_json::Value = {
+                let json = include_bytes!("../resources/deploy_transaction.json");
+                let mut json: serde_json::Value = serde_json::from_slice(json).unwrap();
+                json["contract_definition"].take()
+            };
+        }
+
+        mod positional_args {
+            use std::collections::HashMap;
+
+            use super::*;
+            use crate::{
+                core::{ByteCodeOffset, CallParam, EntryPoint},
+                sequencer::request::contract::{EntryPointType, SelectorAndOffset},
+            };
+
+            use pretty_assertions::assert_eq;
+            use web3::types::H256;
+
+            lazy_static::lazy_static! {
+                pub static ref CALL: Call = Call {
+                    contract_address: ContractAddress(
+                        StarkHash::from_hex_str(
+                            "0x23371b227eaecd8e8920cd429357edddd2cd0f3fee6abaacca08d3ab82a7cdd",
+                        )
+                        .unwrap(),
                     ),
-                    data: vec![EventData(
-                        StarkHash::from_hex_str(&"c".repeat(i + 3)).unwrap(),
-                    )],
-                    keys: vec![
-                        EventKey(StarkHash::from_hex_str(&"d".repeat(i + 3)).unwrap()),
-                        EventKey(StarkHash::from_hex_str("deadbeef").unwrap()),
-                    ],
-                }],
-                execution_resources: transaction::ExecutionResources {
-                    builtin_instance_counter:
-                        transaction::execution_resources::BuiltinInstanceCounter::Empty(
-                            transaction::execution_resources::EmptyBuiltinInstanceCounter {},
+                    calldata: vec![
+                        CallParam(StarkHash::from_hex_str("0x1").unwrap()),
+                        CallParam(
+                            StarkHash::from_hex_str(
+                                "0x677bb1cdc050e8d63855e8743ab6e09179138def390676cc03c484daf112ba1",
+                            )
+                            .unwrap(),
                         ),
-                    n_steps: i as u64 + 987,
-                    n_memory_holes: i as u64 + 1177,
-                },
-                l1_to_l2_consumed_message: None,
-                l2_to_l1_messages: Vec::new(),
-                transaction_hash: StarknetTransactionHash(
-                    StarkHash::from_hex_str(&"e".repeat(i + 3)).unwrap(),
-                ),
-                transaction_index: StarknetTransactionIndex(i as u64 + 2311),
-            });
+                        CallParam(
+                            StarkHash::from_hex_str(
+                                "0x362398bec32bc0ebb411203221a35a0301193a96f317ebe5e40be9f60d15320",
+                            )
+                            .unwrap(),
+                        ),
+                        CallParam(StarkHash::ZERO),
+                        CallParam(StarkHash::from_hex_str("0x1").unwrap()),
+                        CallParam(StarkHash::from_hex_str("0x1").unwrap()),
+                        CallParam(StarkHash::from_hex_str("0x2b").unwrap()),
+                        CallParam(StarkHash::ZERO),
+                    ],
+                    entry_point_selector: EntryPoint(
+                        StarkHash::from_hex_str(
+                            "0x15d40a3d6ca2ac30f4031e42be28da9b056fef9bb7357ac5e85627ee876e5ad",
+                        )
+                        .unwrap(),
+                    ),
+                    sender_address: None,
+                };
+                pub static ref SIGNATURE: Vec<CallSignatureElem> = vec![
+                    CallSignatureElem(
+                        StarkHash::from_hex_str(
+                            "0x7dd3a55d94a0de6f3d6c104d7e6c88ec719a82f4e2bbc12587c8c187584d3d5",
+                        )
+                        .unwrap(),
+                    ),
+                    CallSignatureElem(
+                        StarkHash::from_hex_str(
+                            "0x71456dded17015d1234779889d78f3e7c763ddcfd2662b19e7843c7542614f8",
+                        )
+                        .unwrap(),
+                    ),
+                ];
+                pub static ref MAX_FEE: Fee = Fee(5444010076217u128.to_be_bytes().into());
+                pub static ref TRANSACTION_VERSION: TransactionVersion = TransactionVersion(H256::zero());
+
+                pub static ref ENTRY_POINTS_BY_TYPE: HashMap<EntryPointType, Vec<SelectorAndOffset>> =
+                HashMap::from([
+                    (EntryPointType::Constructor, vec![]),
+                    (
+                        EntryPointType::External,
+                        vec![
+                            SelectorAndOffset {
+                                offset: ByteCodeOffset(StarkHash::from_hex_str("0x3a").unwrap()),
+                                selector: EntryPoint::hashed(&b"increase_balance"[..]),
+                            },
+                            SelectorAndOffset{
+                                offset: ByteCodeOffset(StarkHash::from_hex_str("0x5b").unwrap()),
+                                selector: EntryPoint::hashed(&b"get_balance"[..]),
+                            },
+                        ],
+                    ),
+                    (EntryPointType::L1Handler, vec![]),
+                ]);
+                pub static ref PROGRAM: String = CONTRACT_DEFINITION_JSON["program"]
+                    .as_str()
+                    .unwrap()
+                    .to_owned();
+                pub static ref CONTRACT_DEFINITION: ContractDefinition = ContractDefinition {
+                    abi: serde_json::json!([
+                        {
+                            "inputs": [
+                                {
+                                    "name": "amount",
+                                    "type": "felt"
+                                }
+                            ],
+                            "name": "increase_balance",
+                            "outputs": [],
+                            "type": "function"
+                        },
+                        {
+                            "inputs": [],
+                            "name": "get_balance",
+                            "outputs": [
+                                {
+                                    "name": "res",
+                                    "type": "felt"
+                                }
+                            ],
+                            "stateMutability": "view",
+                            "type": "function"
+                        }
+                    ]),
+                    program: PROGRAM.clone(),
+                    entry_points_by_type: ENTRY_POINTS_BY_TYPE.clone(),
+                };
+            }
+
+            #[tokio::test]
+            async fn invoke_transaction() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(
+                    CALL.clone(),
+                    SIGNATURE.clone(),
+                    *MAX_FEE,
+                    *TRANSACTION_VERSION
+                );
+                let rpc_result = client(addr)
+                    .request::<InvokeTransactionResult>("starknet_addInvokeTransaction", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    InvokeTransactionResult {
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str(
+                                "0x389dd0629f42176cc8b6c43acefc0713d0064ecdfc0470e0fc179f53421a38b"
+                            )
+                            .unwrap()
+                        )
+                    }
+                );
+            }
+
+            #[tokio::test]
+            async fn deploy_transaction() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let contract_definition = CONTRACT_DEFINITION.clone();
+                let contract_address_salt = ContractAddressSalt(
+                    StarkHash::from_hex_str(
+                        "0x5864b5e296c05028ac2bbc4a4c1378f56a3489d13e581f21d566bb94580f76d",
+                    )
+                    .unwrap(),
+                );
+                let constructor_calldata: Vec<ConstructorParam> = vec![];
 
-            transactions
-                .into_iter()
-                .zip(receipts)
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap()
+                let params = rpc_params!(
+                    contract_address_salt,
+                    constructor_calldata,
+                    contract_definition
+                );
+
+                let rpc_result = client(addr)
+                    .request::<DeployTransactionResult>("starknet_addDeployTransaction", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    DeployTransactionResult {
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str(
+                                "0x057ed4b4c76a1ca0ba044a654dd3ee2d0d3e550343d739350a22aacdd524110d"
+                            )
+                            .unwrap()
+                        ),
+                        contract_address: ContractAddress(
+                            StarkHash::from_hex_str(
+                                "0x03926aea98213ec34fe9783d803237d221c54c52344422e1f4942a5b340fa6ad"
+                            )
+                            .unwrap()
+                        ),
+                    }
+                );
+            }
         }
 
-        fn setup() -> (Storage, Vec<EmittedEvent>) {
-            let storage = Storage::in_memory().unwrap();
-            let connection = storage.connection().unwrap();
+        mod named_args {
+            use super::*;
 
-            let blocks = crate::storage::test_utils::create_blocks::<NUM_BLOCKS>();
-            let transactions_and_receipts = create_transactions_and_receipts();
+            use pretty_assertions::assert_eq;
 
-            for (i, block) in blocks.iter().enumerate() {
-                StarknetBlocksTable::insert(&connection, block).unwrap();
-                StarknetTransactionsTable::upsert(
-                    &connection,
-                    block.hash,
-                    block.number,
-                    &transactions_and_receipts
-                        [i * TRANSACTIONS_PER_BLOCK..(i + 1) * TRANSACTIONS_PER_BLOCK],
-                )
-                .unwrap();
-            }
+            #[tokio::test]
+            async fn invoke_transaction() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
 
-            let events = transactions_and_receipts
-                .iter()
-                .enumerate()
-                .map(|(i, (txn, receipt))| {
-                    let event = &receipt.events[0];
-                    let block = &blocks[i / TRANSACTIONS_PER_BLOCK];
+                let params = by_name([
+                    (
+                        "function_invocation",
+                        json!({
+                            "contract_address": "0x23371b227eaecd8e8920cd429357edddd2cd0f3fee6abaacca08d3ab82a7cdd",
+                            "calldata": [
+                                "1",
+                                "0x677BB1CDC050E8D63855E8743AB6E09179138DEF390676CC03C484DAF112BA1",
+                                "0x362398BEC32BC0EBB411203221A35A0301193A96F317EBE5E40BE9F60D15320",
+                                "0",
+                                "1",
+                                "1",
+                                "0x2B",
+                                "0"
+                            ],
+                            "entry_point_selector": "0x15d40a3d6ca2ac30f4031e42be28da9b056fef9bb7357ac5e85627ee876e5ad"
+                        }),
+                    ),
+                    (
+                        "signature",
+                        json!([
+                            "3557065757165699682249469970267166698995647077461960906176449260016084767701",
+                            "3202126414680946801789588986259466145787792017299869598314522555275920413944"
+                        ]),
+                    ),
+                    ("max_fee", json!("0x4f388496839")),
+                    ("version", json!("0x0")),
+                ]);
 
-                    EmittedEvent {
-                        data: event.data.clone(),
-                        from_address: event.from_address,
-                        keys: event.keys.clone(),
-                        block_hash: block.hash,
-                        block_number: block.number,
-                        transaction_hash: txn.transaction_hash,
+                let rpc_result = client(addr)
+                    .request::<InvokeTransactionResult>("starknet_addInvokeTransaction", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    InvokeTransactionResult {
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str(
+                                "0x389dd0629f42176cc8b6c43acefc0713d0064ecdfc0470e0fc179f53421a38b"
+                            )
+                            .unwrap()
+                        )
                     }
-                })
-                .collect();
+                );
+            }
 
-            (storage, events)
+            #[tokio::test]
+            async fn deploy_transaction() {
+                let storage = setup_storage();
+                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = by_name([
+                    (
+                        "contract_address_salt",
+                        json!("0x5864b5e296c05028ac2bbc4a4c1378f56a3489d13e581f21d566bb94580f76d"),
+                    ),
+                    ("constructor_calldata", json!([])),
+                    ("contract_definition", CONTRACT_DEFINITION_JSON.clone()),
+                ]);
+
+                let rpc_result = client(addr)
+                    .request::<DeployTransactionResult>("starknet_addDeployTransaction", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    DeployTransactionResult {
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str(
+                                "0x057ed4b4c76a1ca0ba044a654dd3ee2d0d3e550343d739350a22aacdd524110d"
+                            )
+                            .unwrap()
+                        ),
+                        contract_address: ContractAddress(
+                            StarkHash::from_hex_str(
+                                "0x03926aea98213ec34fe9783d803237d221c54c52344422e1f4942a5b340fa6ad"
+                            )
+                            .unwrap()
+                        ),
+                    }
+                );
+            }
         }
 
-        mod positional_args {
+        mod dry_run {
             use super::*;
+            use crate::rpc::types::reply::ErrorCode;
+            use http::{response::Builder, StatusCode};
+            use std::sync::atomic::{AtomicUsize, Ordering};
 
             use pretty_assertions::assert_eq;
 
+            const CALL_SUCCEEDED: (StatusCode, &str) = (StatusCode::OK, r#"{"result":[]}"#);
+
+            /// Spawns a mock sequencer which replies to calls with `call_reply`, and counts the
+            /// transactions submitted to it.
+            fn mock_sequencer(
+                call_reply: (StatusCode, &'static str),
+            ) -> (SeqClient, Arc<AtomicUsize>) {
+                use warp::Filter;
+
+                let count = Arc::new(AtomicUsize::new(0));
+                let count2 = count.clone();
+                let call = warp::path!("feeder_gateway" / "call_contract")
+                    .map(move || Builder::new().status(call_reply.0).body(call_reply.1));
+                let add_transaction = warp::path!("gateway" / "add_transaction").map(move || {
+                    count2.fetch_add(1, Ordering::Relaxed);
+                    Builder::new()
+                        .status(StatusCode::OK)
+                        .body(r#"{"code":"TRANSACTION_RECEIVED","transaction_hash":"0x1"}"#)
+                });
+                let (addr, run_srv) =
+                    warp::serve(call.or(add_transaction)).bind_ephemeral(([127, 0, 0, 1], 0));
+                tokio::spawn(run_srv);
+
+                let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+                (SeqClient::with_url(url).unwrap(), count)
+            }
+
             #[tokio::test]
-            async fn get_events_with_empty_filter() {
-                let (storage, events) = setup();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            async fn invoke_transaction() {
+                let storage = setup_storage();
+                let (sequencer, requests) = mock_sequencer(CALL_SUCCEEDED);
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
 
-                let params = rpc_params!(EventFilter {
-                    from_block: None,
-                    to_block: None,
-                    address: None,
-                    keys: vec![],
-                    page_size: NUM_EVENTS,
-                    page_number: 0,
+                let params = rpc_params!(
+                    positional_args::CALL.clone(),
+                    positional_args::SIGNATURE.clone(),
+                    *positional_args::MAX_FEE,
+                    *positional_args::TRANSACTION_VERSION,
+                    true
+                );
+                let rpc_result = client(addr)
+                    .request::<InvokeTransactionResult>("starknet_addInvokeTransaction", params)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    rpc_result,
+                    InvokeTransactionResult {
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str(
+                                "0x389dd0629f42176cc8b6c43acefc0713d0064ecdfc0470e0fc179f53421a38b"
+                            )
+                            .unwrap()
+                        )
+                    }
+                );
+                assert_eq!(requests.load(Ordering::Relaxed), 0);
+            }
+
+            #[tokio::test]
+            async fn invoke_transaction_failing_validation() {
+                let storage = setup_storage();
+                let (sequencer, requests) = mock_sequencer((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    r#"{"code":"StarknetErrorCode.TRANSACTION_FAILED","message":"Signature is invalid"}"#,
+                ));
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let params = rpc_params!(
+                    positional_args::CALL.clone(),
+                    positional_args::SIGNATURE.clone(),
+                    *positional_args::MAX_FEE,
+                    *positional_args::TRANSACTION_VERSION,
+                    true
+                );
+                let error = client(addr)
+                    .request::<InvokeTransactionResult>("starknet_addInvokeTransaction", params)
+                    .await
+                    .unwrap_err();
+
+                assert_matches!(error, Error::Request(s) => {
+                    let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+                    assert_eq!(v["error"]["code"], ErrorCode::InvalidCallData as i64);
+                    assert_eq!(v["error"]["data"]["revert_reason"], "Signature is invalid");
                 });
+                assert_eq!(requests.load(Ordering::Relaxed), 0);
+            }
+
+            /// The hash is only computed locally for dry runs, other transactions are forwarded
+            /// to the sequencer as they are, even if their hash cannot be computed.
+            #[tokio::test]
+            async fn version_out_of_range_is_forwarded() {
+                let storage = setup_storage();
+                let (sequencer, requests) = mock_sequencer(CALL_SUCCEEDED);
+                let sync_state = Arc::new(SyncState::default());
+                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+                let version = TransactionVersion(web3::types::H256::repeat_byte(0xff));
+                let params = rpc_params!(
+                    positional_args::CALL.clone(),
+                    positional_args::SIGNATURE.clone(),
+                    *positional_args::MAX_FEE,
+                    version
+                );
                 let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .request::<InvokeTransactionResult>(
+                        "starknet_addInvokeTransaction",
+                        params.clone(),
+                    )
                     .await
                     .unwrap();
 
                 assert_eq!(
                     rpc_result,
-                    GetEventsResult {
-                        events,
-                        page_number: 0,
-                        is_last_page: true,
+                    InvokeTransactionResult {
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str("0x1").unwrap()
+                        )
                     }
                 );
+                assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+                // A dry run of it computes the hash, which fails.
+                let params = rpc_params!(
+                    positional_args::CALL.clone(),
+                    positional_args::SIGNATURE.clone(),
+                    *positional_args::MAX_FEE,
+                    version,
+                    true
+                );
+                let error = client(addr)
+                    .request::<InvokeTransactionResult>("starknet_addInvokeTransaction", params)
+                    .await
+                    .unwrap_err();
+
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s).0, -32602)
+                );
+                assert_eq!(requests.load(Ordering::Relaxed), 1);
             }
 
             #[tokio::test]
-            async fn get_events_with_fully_specified_filter() {
-                let (storage, events) = setup();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            async fn deploy_transaction() {
+                let storage = setup_storage();
+                let (sequencer, requests) = mock_sequencer(CALL_SUCCEEDED);
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
 
-                let expected_event = &events[1];
-                let params = rpc_params!(EventFilter {
-                    from_block: Some(expected_event.block_number),
-                    to_block: Some(expected_event.block_number),
-                    address: Some(expected_event.from_address),
-                    // we're using a key which is present in _all_ events
-                    keys: vec![EventKey(StarkHash::from_hex_str("deadbeef").unwrap())],
-                    page_size: NUM_EVENTS,
-                    page_number: 0,
-                });
+                let params = by_name([
+                    (
+                        "contract_address_salt",
+                        json!("0x5864b5e296c05028ac2bbc4a4c1378f56a3489d13e581f21d566bb94580f76d"),
+                    ),
+                    ("constructor_calldata", json!([])),
+                    ("contract_definition", CONTRACT_DEFINITION_JSON.clone()),
+                    ("dry_run", json!(true)),
+                ]);
+
                 let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .request::<DeployTransactionResult>("starknet_addDeployTransaction", params)
                     .await
                     .unwrap();
 
                 assert_eq!(
                     rpc_result,
-                    GetEventsResult {
-                        events: vec![expected_event.clone()],
-                        page_number: 0,
-                        is_last_page: true,
+                    DeployTransactionResult {
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str(
+                                "0x057ed4b4c76a1ca0ba044a654dd3ee2d0d3e550343d739350a22aacdd524110d"
+                            )
+                            .unwrap()
+                        ),
+                        contract_address: ContractAddress(
+                            StarkHash::from_hex_str(
+                                "0x03926aea98213ec34fe9783d803237d221c54c52344422e1f4942a5b340fa6ad"
+                            )
+                            .unwrap()
+                        ),
                     }
                 );
+                assert_eq!(requests.load(Ordering::Relaxed), 0);
+            }
+        }
+
+        mod retry {
+            use super::*;
+            use http::{response::Builder, StatusCode};
+            use std::collections::VecDeque;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            use pretty_assertions::assert_eq;
+
+            const DUPLICATED: &str = r#"{"code":"StarknetErrorCode.DUPLICATED_TRANSACTION","message":"Transaction already exists"}"#;
+
+            /// Spawns a mock sequencer which replies to each request with the next status and
+            /// body of `replies`, and counts the requests it receives.
+            fn mock_sequencer(
+                replies: VecDeque<(StatusCode, &'static str)>,
+            ) -> (SeqClient, Arc<AtomicUsize>) {
+                use std::sync::Mutex;
+                use warp::Filter;
+
+                let count = Arc::new(AtomicUsize::new(0));
+                let count2 = count.clone();
+                let replies = Arc::new(Mutex::new(replies));
+                let any = warp::any().map(move || {
+                    count2.fetch_add(1, Ordering::Relaxed);
+                    let (status, body) = replies.lock().unwrap().pop_front().unwrap();
+                    Builder::new().status(status).body(body)
+                });
+                let (addr, run_srv) = warp::serve(any).bind_ephemeral(([127, 0, 0, 1], 0));
+                tokio::spawn(run_srv);
+
+                let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+                (SeqClient::with_url(url).unwrap(), count)
+            }
+
+            fn invoke_params() -> Option<ParamsSer<'static>> {
+                rpc_params!(
+                    positional_args::CALL.clone(),
+                    positional_args::SIGNATURE.clone(),
+                    *positional_args::MAX_FEE,
+                    *positional_args::TRANSACTION_VERSION
+                )
             }
 
             #[tokio::test]
-            async fn get_events_by_block() {
-                let (storage, events) = setup();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            async fn duplicate_after_server_error_is_success() {
+                let storage = setup_storage();
+                let (sequencer, requests) = mock_sequencer(VecDeque::from([
+                    (StatusCode::SERVICE_UNAVAILABLE, ""),
+                    (StatusCode::INTERNAL_SERVER_ERROR, DUPLICATED),
+                ]));
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
 
-                const BLOCK_NUMBER: usize = 2;
-                let params = rpc_params!(EventFilter {
-                    from_block: Some(StarknetBlockNumber(BLOCK_NUMBER as u64)),
-                    to_block: Some(StarknetBlockNumber(BLOCK_NUMBER as u64)),
-                    address: None,
-                    keys: vec![],
-                    page_size: NUM_EVENTS,
-                    page_number: 0,
-                });
                 let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
+                    .request::<InvokeTransactionResult>(
+                        "starknet_addInvokeTransaction",
+                        invoke_params(),
+                    )
                     .await
                     .unwrap();
 
-                let expected_events =
-                    &events[EVENTS_PER_BLOCK * BLOCK_NUMBER..EVENTS_PER_BLOCK * (BLOCK_NUMBER + 1)];
                 assert_eq!(
                     rpc_result,
-                    GetEventsResult {
-                        events: expected_events.to_vec(),
-                        page_number: 0,
-                        is_last_page: true,
+                    InvokeTransactionResult {
+                        transaction_hash: StarknetTransactionHash(
+                            StarkHash::from_hex_str(
+                                "0x389dd0629f42176cc8b6c43acefc0713d0064ecdfc0470e0fc179f53421a38b"
+                            )
+                            .unwrap()
+                        )
                     }
                 );
+                assert_eq!(requests.load(Ordering::Relaxed), 2);
             }
 
             #[tokio::test]
-            async fn get_events_with_invalid_page_size() {
-                let (storage, _events) = setup();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            async fn duplicate_on_first_attempt_is_an_error() {
+                let storage = setup_storage();
+                let (sequencer, requests) = mock_sequencer(VecDeque::from([(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    DUPLICATED,
+                )]));
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
 
-                let params = rpc_params!(EventFilter {
-                    from_block: None,
-                    to_block: None,
-                    address: None,
-                    keys: vec![],
-                    page_size: crate::storage::StarknetEventsTable::PAGE_SIZE_LIMIT + 1,
-                    page_number: 0,
-                });
-                let error = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
+                client(addr)
+                    .request::<InvokeTransactionResult>(
+                        "starknet_addInvokeTransaction",
+                        invoke_params(),
+                    )
                     .await
                     .unwrap_err();
-                assert_matches!(
-                    error,
-                    Error::Request(s) => assert_eq!(
-                        serde_json::from_str::<serde_json::Value>(&s).unwrap()["error"],
-                        json!({
-                            "code": 31,
-                            "message": "Requested page size is too big",
-                            "data": {
-                                "max_page_size": crate::storage::StarknetEventsTable::PAGE_SIZE_LIMIT
-                            }
-                        })
-                    )
-                );
+                assert_eq!(requests.load(Ordering::Relaxed), 1);
             }
 
             #[tokio::test]
-            async fn get_events_by_key_with_paging() {
-                let (storage, events) = setup();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            async fn retries_are_bounded() {
+                let storage = setup_storage();
+                let (sequencer, requests) = mock_sequencer(VecDeque::from([
+                    (StatusCode::SERVICE_UNAVAILABLE, ""),
+                    (StatusCode::SERVICE_UNAVAILABLE, ""),
+                    (StatusCode::SERVICE_UNAVAILABLE, ""),
+                    (StatusCode::SERVICE_UNAVAILABLE, ""),
+                    (StatusCode::OK, ""),
+                ]));
                 let sync_state = Arc::new(SyncState::default());
                 let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
                 let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
 
-                let expected_events = &events[27..32];
-                let keys_for_expected_events: Vec<_> =
-                    expected_events.iter().map(|e| e.keys[0]).collect();
-
-                let params = rpc_params!(EventFilter {
-                    from_block: None,
-                    to_block: None,
-                    address: None,
-                    keys: keys_for_expected_events.clone(),
-                    page_size: 2,
-                    page_number: 0,
-                });
-                let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
+                client(addr)
+                    .request::<InvokeTransactionResult>(
+                        "starknet_addInvokeTransaction",
+                        invoke_params(),
+                    )
                     .await
-                    .unwrap();
-                assert_eq!(
-                    rpc_result,
-                    GetEventsResult {
-                        events: expected_events[..2].to_vec(),
-                        page_number: 0,
-                        is_last_page: false,
-                    }
-                );
+                    .unwrap_err();
+                // The initial attempt and three retries.
+                assert_eq!(requests.load(Ordering::Relaxed), 4);
+            }
+        }
+    }
 
-                let params = rpc_params!(EventFilter {
-                    from_block: None,
-                    to_block: None,
-                    address: None,
-                    keys: keys_for_expected_events.clone(),
-                    page_size: 2,
-                    page_number: 1,
-                });
-                let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
-                    .await
+    mod compute_contract_address {
+        use super::add_transaction::CONTRACT_DEFINITION_JSON;
+        use super::*;
+        use crate::sequencer::request::add_transaction::ContractDefinition;
+        use pretty_assertions::assert_eq;
+
+        #[tokio::test]
+        async fn known_deployment() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let definition =
+                serde_json::from_value::<ContractDefinition>(CONTRACT_DEFINITION_JSON.clone())
                     .unwrap();
-                assert_eq!(
-                    rpc_result,
-                    GetEventsResult {
-                        events: expected_events[2..4].to_vec(),
-                        page_number: 1,
-                        is_last_page: false,
-                    }
-                );
+            let class_hash = definition.hash().unwrap();
 
-                let params = rpc_params!(EventFilter {
-                    from_block: None,
-                    to_block: None,
-                    address: None,
-                    keys: keys_for_expected_events.clone(),
-                    page_size: 2,
-                    page_number: 2,
+            let params = by_name([
+                (
+                    "salt",
+                    json!("0x5864b5e296c05028ac2bbc4a4c1378f56a3489d13e581f21d566bb94580f76d"),
+                ),
+                ("class_hash", json!(class_hash)),
+                ("constructor_calldata", json!([])),
+            ]);
+            let address = client(addr)
+                .request::<ContractAddress>("starknet_computeContractAddress", params)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                address,
+                ContractAddress(
+                    StarkHash::from_hex_str(
+                        "0x03926aea98213ec34fe9783d803237d221c54c52344422e1f4942a5b340fa6ad"
+                    )
+                    .unwrap()
+                )
+            );
+        }
+
+        #[tokio::test]
+        async fn deployer_address_changes_the_address() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let params = |deployer: &str| {
+                by_name([
+                    ("salt", json!("0x1")),
+                    ("class_hash", json!("0x2")),
+                    ("constructor_calldata", json!(["0x3", "0x4"])),
+                    ("deployer_address", json!(deployer)),
+                ])
+            };
+            let from_zero = client(addr)
+                .request::<ContractAddress>("starknet_computeContractAddress", params("0x0"))
+                .await
+                .unwrap();
+            let from_other = client(addr)
+                .request::<ContractAddress>("starknet_computeContractAddress", params("0x5"))
+                .await
+                .unwrap();
+
+            let default_deployer = by_name([
+                ("salt", json!("0x1")),
+                ("class_hash", json!("0x2")),
+                ("constructor_calldata", json!(["0x3", "0x4"])),
+            ]);
+            let from_default = client(addr)
+                .request::<ContractAddress>("starknet_computeContractAddress", default_deployer)
+                .await
+                .unwrap();
+
+            assert_eq!(from_default, from_zero);
+            assert_ne!(from_other, from_zero);
+        }
+    }
+
+    mod listener {
+        use super::*;
+        use crate::rpc::{listener::ListenerConfig, run_server_with};
+        use pretty_assertions::assert_eq;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpStream;
+
+        #[tokio::test]
+        async fn refuses_connections_above_limit() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let config = ListenerConfig {
+                max_connections: Some(1),
+                ..Default::default()
+            };
+            let (__handle, addr) =
+                run_server_with(*LOCALHOST, api, config, Default::default()).unwrap();
+
+            let mut first = TcpStream::connect(addr).await.unwrap();
+
+            // The connection exceeding the limit gets reset without a response, possibly before
+            // connecting even completes.
+            let second = async {
+                let mut second = TcpStream::connect(addr).await?;
+                let mut buf = [0u8; 1];
+                second.read(&mut buf).await
+            };
+            let refused = tokio::time::timeout(Duration::from_secs(5), second)
+                .await
+                .expect("Connection above the limit should be refused");
+            assert_matches!(refused, Ok(0) | Err(_));
+
+            // The connection within the limit is served.
+            let body = r#"{"jsonrpc":"2.0","id":0,"method":"starknet_chainId","params":[]}"#;
+            let request = format!(
+                "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            first.write_all(request.as_bytes()).await.unwrap();
+            let mut response = [0u8; 15];
+            first.read_exact(&mut response).await.unwrap();
+            assert_eq!(&response, b"HTTP/1.1 200 OK");
+        }
+
+        #[tokio::test]
+        async fn stops_accepting_when_handle_is_dropped() {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            drop(handle);
+
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while TcpStream::connect(addr).await.is_ok() {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("Listening socket should be closed");
+        }
+    }
+
+    mod concurrent_calls {
+        use super::*;
+        use crate::rpc::types::{reply::Block, BlockNumberOrTag, Tag};
+        use crate::rpc::{listener::ListenerConfig, run_server_with, SERVER_BUSY_CODE};
+        use pretty_assertions::assert_eq;
+        use tokio::sync::{mpsc, Semaphore};
+
+        #[tokio::test]
+        async fn rejects_calls_above_limit() {
+            use crate::sequencer::reply::{Block as SeqBlock, Status};
+            use warp::Filter;
+
+            // The sequencer only replies once released, keeping calls which query it in flight.
+            let release = Arc::new(Semaphore::new(0));
+            let (received_tx, mut received_rx) = mpsc::unbounded_channel();
+            let sequencer = {
+                let release = release.clone();
+                let held = warp::any().and_then(move || {
+                    let release = release.clone();
+                    let received_tx = received_tx.clone();
+                    async move {
+                        received_tx.send(()).unwrap();
+                        release.acquire().await.unwrap().forget();
+                        // Without a pending block, the latest block is served from storage.
+                        Ok::<_, warp::Rejection>(warp::reply::json(&SeqBlock {
+                            block_hash: Some(StarknetBlockHash(
+                                StarkHash::from_be_slice(b"sequencer latest").unwrap(),
+                            )),
+                            block_number: Some(StarknetBlockNumber(100)),
+                            gas_price: None,
+                            parent_block_hash: StarknetBlockHash(StarkHash::ZERO),
+                            sequencer_address: None,
+                            state_root: None,
+                            status: Status::AcceptedOnL2,
+                            timestamp: StarknetBlockTimestamp(0),
+                            transaction_receipts: vec![],
+                            transactions: vec![],
+                        }))
+                    }
                 });
-                let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
+                let (addr, run_srv) = warp::serve(held).bind_ephemeral(([127, 0, 0, 1], 0));
+                tokio::spawn(run_srv);
+                let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+                SeqClient::with_url(url).unwrap()
+            };
+
+            let storage = setup_storage();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let config = ListenerConfig {
+                max_concurrent_calls: Some(1),
+                ..Default::default()
+            };
+            let (__handle, addr) =
+                run_server_with(*LOCALHOST, api, config, Default::default()).unwrap();
+
+            let held = tokio::spawn(async move {
+                let params = rpc_params!(BlockNumberOrTag::Tag(Tag::Pending));
+                client(addr)
+                    .request::<Block>("starknet_getBlockByNumber", params)
                     .await
-                    .unwrap();
-                assert_eq!(
-                    rpc_result,
-                    GetEventsResult {
-                        events: expected_events[4..].to_vec(),
-                        page_number: 2,
-                        is_last_page: true,
-                    }
-                );
+            });
+            // The held call has reached the sequencer, so it holds the only permit.
+            received_rx.recv().await.unwrap();
+
+            let error = client(addr)
+                .request::<String>("starknet_chainId", rpc_params!())
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s).0, SERVER_BUSY_CODE as i64)
+            );
+
+            release.add_permits(1);
+            held.await.unwrap().unwrap();
+
+            // The permit is returned once the held call completes.
+            client(addr)
+                .request::<String>("starknet_chainId", rpc_params!())
+                .await
+                .unwrap();
+        }
+    }
 
-                // nonexistent page
-                let params = rpc_params!(EventFilter {
-                    from_block: None,
-                    to_block: None,
-                    address: None,
-                    keys: keys_for_expected_events.clone(),
-                    page_size: 2,
-                    page_number: 3,
-                });
-                let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
-                    .await
-                    .unwrap();
-                assert_eq!(
-                    rpc_result,
-                    GetEventsResult {
-                        events: vec![],
-                        page_number: 3,
-                        is_last_page: true,
-                    }
-                );
-            }
+    mod run_server_on {
+        use super::*;
+        use crate::rpc::run_server_on;
+        use pretty_assertions::assert_eq;
+
+        fn api() -> RpcApi {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            RpcApi::new(storage, sequencer, Chain::Goerli, sync_state)
         }
 
-        mod named_args {
-            use super::*;
+        #[tokio::test]
+        async fn resolves_localhost() {
+            let (__handle, addr) = run_server_on("localhost:0", api()).unwrap();
+            assert!(addr.ip().is_loopback());
+            assert_ne!(addr.port(), 0);
 
-            use pretty_assertions::assert_eq;
+            let chain_id = client(addr)
+                .request::<String>("starknet_chainId", rpc_params!())
+                .await
+                .unwrap();
+            assert_eq!(chain_id, format!("0x{}", hex::encode("SN_GOERLI")));
+        }
 
-            #[tokio::test]
-            async fn get_events_with_empty_filter() {
-                let (storage, events) = setup();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+        #[tokio::test]
+        async fn invalid_address() {
+            // The port is missing.
+            let error = run_server_on("localhost", api()).err().unwrap();
+            assert_matches!(
+                error,
+                Error::Custom(message) => assert!(message.contains("(localhost)"), "{}", message)
+            );
+        }
+    }
 
-                let params =
-                    by_name([("filter", json!({"page_size": NUM_EVENTS, "page_number": 0}))]);
-                let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
-                    .await
-                    .unwrap();
+    mod subscribe_new_heads {
+        use super::*;
+        use crate::rpc::types::reply::{BlockHeader, BlockStatus};
+        use jsonrpsee::{types::traits::SubscriptionClient, ws_client::WsClientBuilder};
+        use pretty_assertions::assert_eq;
 
-                assert_eq!(
-                    rpc_result,
-                    GetEventsResult {
-                        events,
-                        page_number: 0,
-                        is_last_page: true,
-                    }
-                );
+        fn header(number: u64) -> BlockHeader {
+            BlockHeader {
+                block_hash: StarknetBlockHash(
+                    StarkHash::from_be_slice(&[number as u8 + 1]).unwrap(),
+                ),
+                parent_hash: StarknetBlockHash(StarkHash::from_be_slice(&[number as u8]).unwrap()),
+                block_number: StarknetBlockNumber(number),
+                status: BlockStatus::AcceptedOnL2,
+                sequencer: SequencerAddress(StarkHash::ZERO),
+                new_root: GlobalRoot(StarkHash::ZERO),
+                accepted_time: StarknetBlockTimestamp(number),
+                gas_price: GasPrice::ZERO,
             }
+        }
 
-            #[tokio::test]
-            async fn get_events_with_fully_specified_filter() {
-                let (storage, events) = setup();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+        /// Waits until the number of new heads receivers is `count`, i.e. until the server has
+        /// set up or torn down a subscription.
+        async fn wait_for_receivers(sync_state: &SyncState, count: usize) {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while sync_state.new_heads.receiver_count() != count {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap();
+        }
 
-                let expected_event = &events[1];
-                let params = by_name([(
-                    "filter",
-                    json!({
-                        "fromBlock": expected_event.block_number.0,
-                        "toBlock": expected_event.block_number.0,
-                        "address": expected_event.from_address,
-                        "keys": [expected_event.keys[0]],
-                        "page_size": NUM_EVENTS,
-                        "page_number": 0,
-                    }),
-                )]);
-                let rpc_result = client(addr)
-                    .request::<GetEventsResult>("starknet_getEvents", params)
-                    .await
-                    .unwrap();
+        #[tokio::test]
+        async fn receives_stored_block_headers() {
+            let sync_state = Arc::new(SyncState::default());
+            let (_handle, addr) = run_ws_server(
+                *LOCALHOST,
+                Storage::in_memory().unwrap(),
+                sync_state.clone(),
+                StarknetEventsTable::KEY_COUNT_LIMIT,
+            )
+            .await
+            .unwrap();
+            let client = WsClientBuilder::default()
+                .build(&format!("ws://{}", addr))
+                .await
+                .unwrap();
 
-                assert_eq!(
-                    rpc_result,
-                    GetEventsResult {
-                        events: vec![expected_event.clone()],
-                        page_number: 0,
-                        is_last_page: true,
-                    }
-                );
+            let mut subscription = client
+                .subscribe::<BlockHeader>(
+                    "starknet_subscribeNewHeads",
+                    rpc_params!(),
+                    "starknet_unsubscribeNewHeads",
+                )
+                .await
+                .unwrap();
+            wait_for_receivers(&sync_state, 1).await;
+
+            for number in 0..2 {
+                sync_state.new_heads.send(header(number)).unwrap();
             }
+            assert_eq!(subscription.next().await.unwrap(), Some(header(0)));
+            assert_eq!(subscription.next().await.unwrap(), Some(header(1)));
         }
-    }
 
-    mod add_transaction {
-        use super::*;
-        use crate::rpc::types::reply::{DeployTransactionResult, InvokeTransactionResult};
+        #[tokio::test]
+        async fn unsubscribe() {
+            let sync_state = Arc::new(SyncState::default());
+            let (_handle, addr) = run_ws_server(
+                *LOCALHOST,
+                Storage::in_memory().unwrap(),
+                sync_state.clone(),
+                StarknetEventsTable::KEY_COUNT_LIMIT,
+            )
+            .await
+            .unwrap();
+            let client = WsClientBuilder::default()
+                .build(&format!("ws://{}", addr))
+                .await
+                .unwrap();
 
-        lazy_static::lazy_static! {
-            pub static ref CONTRACT_DEFINITION_JSON: serde_json::Value = {
-                let json = include_bytes!("../resources/deploy_transaction.json");
-                let mut json: serde_json::Value = serde_json::from_slice(json).unwrap();
-                json["contract_definition"].take()
-            };
+            let subscription = client
+                .subscribe::<BlockHeader>(
+                    "starknet_subscribeNewHeads",
+                    rpc_params!(),
+                    "starknet_unsubscribeNewHeads",
+                )
+                .await
+                .unwrap();
+            wait_for_receivers(&sync_state, 1).await;
+
+            // Dropping the subscription unsubscribes, after which the next header ends the
+            // forwarding on the server side.
+            drop(subscription);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let _ = sync_state.new_heads.send(header(0));
+            wait_for_receivers(&sync_state, 0).await;
         }
+    }
 
-        mod positional_args {
-            use std::collections::HashMap;
-
-            use super::*;
-            use crate::{
-                core::{ByteCodeOffset, CallParam, EntryPoint},
-                sequencer::request::contract::{EntryPointType, SelectorAndOffset},
-            };
+    mod method_filter {
+        use super::*;
+        use crate::rpc::{listener::ListenerConfig, run_server_with, MethodFilter};
+        use pretty_assertions::assert_eq;
 
-            use pretty_assertions::assert_eq;
-            use web3::types::H256;
+        fn run_server_filtered(methods: MethodFilter) -> (ServerHandle, SocketAddr) {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            run_server_with(*LOCALHOST, api, ListenerConfig::default(), methods).unwrap()
+        }
 
-            lazy_static::lazy_static! {
-                pub static ref CALL: Call = Call {
-                    contract_address: ContractAddress(
-                        StarkHash::from_hex_str(
-                            "0x23371b227eaecd8e8920cd429357edddd2cd0f3fee6abaacca08d3ab82a7cdd",
-                        )
-                        .unwrap(),
-                    ),
-                    calldata: vec![
-                        CallParam(StarkHash::from_hex_str("0x1").unwrap()),
-                        CallParam(
-                            StarkHash::from_hex_str(
-                                "0x677bb1cdc050e8d63855e8743ab6e09179138def390676cc03c484daf112ba1",
-                            )
-                            .unwrap(),
-                        ),
-                        CallParam(
-                            StarkHash::from_hex_str(
-                                "0x362398bec32bc0ebb411203221a35a0301193a96f317ebe5e40be9f60d15320",
-                            )
-                            .unwrap(),
-                        ),
-                        CallParam(StarkHash::ZERO),
-                        CallParam(StarkHash::from_hex_str("0x1").unwrap()),
-                        CallParam(StarkHash::from_hex_str("0x1").unwrap()),
-                        CallParam(StarkHash::from_hex_str("0x2b").unwrap()),
-                        CallParam(StarkHash::ZERO),
-                    ],
-                    entry_point_selector: EntryPoint(
-                        StarkHash::from_hex_str(
-                            "0x15d40a3d6ca2ac30f4031e42be28da9b056fef9bb7357ac5e85627ee876e5ad",
-                        )
-                        .unwrap(),
-                    ),
-                };
-                pub static ref SIGNATURE: Vec<CallSignatureElem> = vec![
-                    CallSignatureElem(
-                        StarkHash::from_hex_str(
-                            "0x7dd3a55d94a0de6f3d6c104d7e6c88ec719a82f4e2bbc12587c8c187584d3d5",
-                        )
-                        .unwrap(),
-                    ),
-                    CallSignatureElem(
-                        StarkHash::from_hex_str(
-                            "0x71456dded17015d1234779889d78f3e7c763ddcfd2662b19e7843c7542614f8",
-                        )
-                        .unwrap(),
-                    ),
-                ];
-                pub static ref MAX_FEE: Fee = Fee(5444010076217u128.to_be_bytes().into());
-                pub static ref TRANSACTION_VERSION: TransactionVersion = TransactionVersion(H256::zero());
+        #[tokio::test]
+        async fn denied_method_is_not_found() {
+            let (__handle, addr) = run_server_filtered(MethodFilter {
+                denied: vec!["starknet_call".to_owned()],
+                ..Default::default()
+            });
 
-                pub static ref ENTRY_POINTS_BY_TYPE: HashMap<EntryPointType, Vec<SelectorAndOffset>> =
-                HashMap::from([
-                    (EntryPointType::Constructor, vec![]),
-                    (
-                        EntryPointType::External,
-                        vec![
-                            SelectorAndOffset {
-                                offset: ByteCodeOffset(StarkHash::from_hex_str("0x3a").unwrap()),
-                                selector: EntryPoint::hashed(&b"increase_balance"[..]),
-                            },
-                            SelectorAndOffset{
-                                offset: ByteCodeOffset(StarkHash::from_hex_str("0x5b").unwrap()),
-                                selector: EntryPoint::hashed(&b"get_balance"[..]),
-                            },
-                        ],
-                    ),
-                    (EntryPointType::L1Handler, vec![]),
-                ]);
-                pub static ref PROGRAM: String = CONTRACT_DEFINITION_JSON["program"]
-                    .as_str()
-                    .unwrap()
-                    .to_owned();
-                pub static ref CONTRACT_DEFINITION: ContractDefinition = ContractDefinition {
-                    abi: serde_json::json!([
-                        {
-                            "inputs": [
-                                {
-                                    "name": "amount",
-                                    "type": "felt"
-                                }
-                            ],
-                            "name": "increase_balance",
-                            "outputs": [],
-                            "type": "function"
-                        },
-                        {
-                            "inputs": [],
-                            "name": "get_balance",
-                            "outputs": [
-                                {
-                                    "name": "res",
-                                    "type": "felt"
-                                }
-                            ],
-                            "stateMutability": "view",
-                            "type": "function"
-                        }
-                    ]),
-                    program: PROGRAM.clone(),
-                    entry_points_by_type: ENTRY_POINTS_BY_TYPE.clone(),
-                };
-            }
+            let error = client(addr)
+                .request::<serde_json::Value>("starknet_call", rpc_params!())
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                Error::Request(s) => assert_eq!(get_err(&s).0, -32601)
+            );
 
-            #[tokio::test]
-            async fn invoke_transaction() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+            // Nor is the method listed.
+            let methods = client(addr)
+                .request::<Vec<String>>("rpc_methods", rpc_params!())
+                .await
+                .unwrap();
+            assert!(!methods.iter().any(|m| m == "starknet_call"));
+            assert!(methods.iter().any(|m| m == "starknet_chainId"));
+        }
 
-                let params = rpc_params!(
-                    CALL.clone(),
-                    SIGNATURE.clone(),
-                    *MAX_FEE,
-                    *TRANSACTION_VERSION
-                );
-                let rpc_result = client(addr)
-                    .request::<InvokeTransactionResult>("starknet_addInvokeTransaction", params)
-                    .await
-                    .unwrap();
+        #[tokio::test]
+        async fn only_allowed_methods() {
+            let (__handle, addr) = run_server_filtered(MethodFilter {
+                allowed: Some(vec!["starknet_chainId".to_owned()]),
+                ..Default::default()
+            });
 
-                assert_eq!(
-                    rpc_result,
-                    InvokeTransactionResult {
-                        transaction_hash: StarknetTransactionHash(
-                            StarkHash::from_hex_str(
-                                "0x389dd0629f42176cc8b6c43acefc0713d0064ecdfc0470e0fc179f53421a38b"
-                            )
-                            .unwrap()
-                        )
-                    }
+            client(addr)
+                .request::<String>("starknet_chainId", rpc_params!())
+                .await
+                .unwrap();
+
+            for method in ["starknet_blockNumber", "rpc_methods"] {
+                let error = client(addr)
+                    .request::<serde_json::Value>(method, rpc_params!())
+                    .await
+                    .unwrap_err();
+                assert_matches!(
+                    error,
+                    Error::Request(s) => assert_eq!(get_err(&s).0, -32601, "{}", method)
                 );
             }
+        }
+    }
 
-            #[tokio::test]
-            async fn deploy_transaction() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+    mod compression {
+        use super::*;
+        use crate::rpc::{compression::GzipConfig, listener::ListenerConfig, run_server_with};
+        use pretty_assertions::assert_eq;
+        use std::io::Read;
+
+        async fn post(addr: SocketAddr, body: &'static str, gzip: bool) -> reqwest::Response {
+            let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+            let request = reqwest::Client::new()
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+            let request = match gzip {
+                true => request.header(reqwest::header::ACCEPT_ENCODING, "gzip"),
+                false => request,
+            };
+            request.send().await.unwrap()
+        }
 
-                let contract_definition = CONTRACT_DEFINITION.clone();
-                let contract_address_salt = ContractAddressSalt(
-                    StarkHash::from_hex_str(
-                        "0x5864b5e296c05028ac2bbc4a4c1378f56a3489d13e581f21d566bb94580f76d",
-                    )
-                    .unwrap(),
-                );
-                let constructor_calldata: Vec<ConstructorParam> = vec![];
+        fn run(gzip: GzipConfig) -> (ServerHandle, SocketAddr) {
+            let storage = setup_storage();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let config = ListenerConfig {
+                gzip: Some(gzip),
+                ..Default::default()
+            };
+            run_server_with(*LOCALHOST, api, config, Default::default()).unwrap()
+        }
 
-                let params = rpc_params!(
-                    contract_address_salt,
-                    constructor_calldata,
-                    contract_definition
-                );
+        const BLOCK_REQUEST: &str = r#"{"jsonrpc":"2.0","id":0,"method":"starknet_getBlockByNumber","params":["latest","FULL_TXNS"]}"#;
 
-                let rpc_result = client(addr)
-                    .request::<DeployTransactionResult>("starknet_addDeployTransaction", params)
-                    .await
-                    .unwrap();
+        #[tokio::test]
+        async fn compresses_response() {
+            let (__handle, addr) = run(GzipConfig { min_size: 256 });
 
-                assert_eq!(
-                    rpc_result,
-                    DeployTransactionResult {
-                        transaction_hash: StarknetTransactionHash(
-                            StarkHash::from_hex_str(
-                                "0x057ed4b4c76a1ca0ba044a654dd3ee2d0d3e550343d739350a22aacdd524110d"
-                            )
-                            .unwrap()
-                        ),
-                        contract_address: ContractAddress(
-                            StarkHash::from_hex_str(
-                                "0x03926aea98213ec34fe9783d803237d221c54c52344422e1f4942a5b340fa6ad"
-                            )
-                            .unwrap()
-                        ),
-                    }
-                );
-            }
+            let expected = post(addr, BLOCK_REQUEST, false).await;
+            assert_eq!(
+                expected.headers().get(reqwest::header::CONTENT_ENCODING),
+                None
+            );
+            let expected = expected.json::<serde_json::Value>().await.unwrap();
+
+            let response = post(addr, BLOCK_REQUEST, true).await;
+            assert_eq!(
+                response.headers()[reqwest::header::CONTENT_ENCODING],
+                "gzip"
+            );
+            let compressed = response.bytes().await.unwrap();
+
+            let mut decoded = Vec::new();
+            flate2::read::GzDecoder::new(compressed.as_ref())
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert!(compressed.len() < decoded.len());
+            let decoded = serde_json::from_slice::<serde_json::Value>(&decoded).unwrap();
+            assert_eq!(decoded, expected);
+            assert!(decoded["result"]["transactions"].is_array());
         }
 
-        mod named_args {
-            use super::*;
+        #[tokio::test]
+        async fn small_response_is_not_compressed() {
+            let (__handle, addr) = run(GzipConfig::default());
 
-            use pretty_assertions::assert_eq;
+            let response = post(
+                addr,
+                r#"{"jsonrpc":"2.0","id":0,"method":"starknet_chainId","params":[]}"#,
+                true,
+            )
+            .await;
+            assert_eq!(
+                response.headers().get(reqwest::header::CONTENT_ENCODING),
+                None
+            );
+            let response = response.json::<serde_json::Value>().await.unwrap();
+            assert_eq!(response["result"], "0x534e5f474f45524c49");
+        }
+    }
 
-            #[tokio::test]
-            async fn invoke_transaction() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+    mod envelope {
+        use super::*;
+        use pretty_assertions::assert_eq;
 
-                let params = by_name([
-                    (
-                        "function_invocation",
-                        json!({
-                            "contract_address": "0x23371b227eaecd8e8920cd429357edddd2cd0f3fee6abaacca08d3ab82a7cdd",
-                            "calldata": [
-                                "1",
-                                "0x677BB1CDC050E8D63855E8743AB6E09179138DEF390676CC03C484DAF112BA1",
-                                "0x362398BEC32BC0EBB411203221A35A0301193A96F317EBE5E40BE9F60D15320",
-                                "0",
-                                "1",
-                                "1",
-                                "0x2B",
-                                "0"
-                            ],
-                            "entry_point_selector": "0x15d40a3d6ca2ac30f4031e42be28da9b056fef9bb7357ac5e85627ee876e5ad"
-                        }),
-                    ),
-                    (
-                        "signature",
-                        json!([
-                            "3557065757165699682249469970267166698995647077461960906176449260016084767701",
-                            "3202126414680946801789588986259466145787792017299869598314522555275920413944"
-                        ]),
-                    ),
-                    ("max_fee", json!("0x4f388496839")),
-                    ("version", json!("0x0")),
-                ]);
+        async fn post(addr: SocketAddr, body: &'static str) -> serde_json::Value {
+            let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+            reqwest::Client::new()
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap()
+        }
 
-                let rpc_result = client(addr)
-                    .request::<InvokeTransactionResult>("starknet_addInvokeTransaction", params)
-                    .await
-                    .unwrap();
+        #[tokio::test]
+        async fn missing_version() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let response = post(addr, r#"{"id":1,"method":"starknet_chainId","params":[]}"#).await;
+            assert_eq!(response["error"]["code"], json!(-32600));
+            assert_eq!(response["id"], json!(1));
+        }
+
+        #[tokio::test]
+        async fn wrong_version() {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+
+            let response = post(
+                addr,
+                r#"{"jsonrpc":"1.0","id":1,"method":"starknet_chainId","params":[]}"#,
+            )
+            .await;
+            assert_eq!(response["error"]["code"], json!(-32600));
+            assert_eq!(response["id"], json!(1));
+        }
+    }
+
+    mod content_type {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        async fn post(addr: SocketAddr, content_type: &'static str) -> reqwest::Response {
+            let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+            reqwest::Client::new()
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(r#"{"jsonrpc":"2.0","id":1,"method":"starknet_chainId","params":[]}"#)
+                .send()
+                .await
+                .unwrap()
+        }
+
+        fn run() -> (ServerHandle, SocketAddr) {
+            let storage = Storage::in_memory().unwrap();
+            let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+            let sync_state = Arc::new(SyncState::default());
+            let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+            run_server(*LOCALHOST, api).unwrap()
+        }
 
+        #[tokio::test]
+        async fn json_with_charset() {
+            let (__handle, addr) = run();
+
+            for content_type in [
+                "application/json",
+                "application/json; charset=utf-8",
+                "application/json;charset=utf-8",
+                "Application/JSON; charset=UTF-8",
+            ] {
+                let response = post(addr, content_type).await;
                 assert_eq!(
-                    rpc_result,
-                    InvokeTransactionResult {
-                        transaction_hash: StarknetTransactionHash(
-                            StarkHash::from_hex_str(
-                                "0x389dd0629f42176cc8b6c43acefc0713d0064ecdfc0470e0fc179f53421a38b"
-                            )
-                            .unwrap()
-                        )
-                    }
+                    response.status(),
+                    reqwest::StatusCode::OK,
+                    "{}",
+                    content_type
                 );
+                let response = response.json::<serde_json::Value>().await.unwrap();
+                assert_eq!(response["result"], "0x534e5f474f45524c49");
             }
+        }
 
-            #[tokio::test]
-            async fn deploy_transaction() {
-                let storage = setup_storage();
-                let sequencer = SeqClient::new(Chain::Goerli).unwrap();
-                let sync_state = Arc::new(SyncState::default());
-                let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
-                let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
-
-                let params = by_name([
-                    (
-                        "contract_address_salt",
-                        json!("0x5864b5e296c05028ac2bbc4a4c1378f56a3489d13e581f21d566bb94580f76d"),
-                    ),
-                    ("constructor_calldata", json!([])),
-                    ("contract_definition", CONTRACT_DEFINITION_JSON.clone()),
-                ]);
-
-                let rpc_result = client(addr)
-                    .request::<DeployTransactionResult>("starknet_addDeployTransaction", params)
-                    .await
-                    .unwrap();
+        #[tokio::test]
+        async fn unsupported() {
+            let (__handle, addr) = run();
 
+            for content_type in ["text/plain", "application/x-www-form-urlencoded"] {
+                let response = post(addr, content_type).await;
                 assert_eq!(
-                    rpc_result,
-                    DeployTransactionResult {
-                        transaction_hash: StarknetTransactionHash(
-                            StarkHash::from_hex_str(
-                                "0x057ed4b4c76a1ca0ba044a654dd3ee2d0d3e550343d739350a22aacdd524110d"
-                            )
-                            .unwrap()
-                        ),
-                        contract_address: ContractAddress(
-                            StarkHash::from_hex_str(
-                                "0x03926aea98213ec34fe9783d803237d221c54c52344422e1f4942a5b340fa6ad"
-                            )
-                            .unwrap()
-                        ),
-                    }
+                    response.status(),
+                    reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "{}",
+                    content_type
                 );
             }
         }
     }
+
+    #[tokio::test]
+    async fn rpc_methods() {
+        let storage = Storage::in_memory().unwrap();
+        let sequencer = SeqClient::new(Chain::Goerli).unwrap();
+        let sync_state = Arc::new(SyncState::default());
+        let api = RpcApi::new(storage, sequencer, Chain::Goerli, sync_state);
+        let (__handle, addr) = run_server(*LOCALHOST, api).unwrap();
+        let methods = client(addr)
+            .request::<Vec<String>>("rpc_methods", rpc_params!())
+            .await
+            .unwrap();
+
+        for method in [
+            "rpc_methods",
+            "starknet_getBlockByHash",
+            "starknet_getBlockByNumber",
+            "starknet_getRawBlock",
+            "starknet_getStorageAtKeys",
+            "starknet_getStorageAtRoot",
+            "starknet_getStorageDiff",
+            "starknet_getNonce",
+            "starknet_getContractCount",
+            "starknet_getTransactionByHash",
+            "starknet_getTransactionsByHashes",
+            "starknet_getTransactionReceipt",
+            "starknet_classExists",
+            "starknet_call",
+            "starknet_simulateCalls",
+            "starknet_traceTransaction",
+            "starknet_blockNumber",
+            "starknet_getRecentBlockHashes",
+            "starknet_chainId",
+            "starknet_syncing",
+            "starknet_syncStats",
+            "starknet_l1ConfirmedBlock",
+            "starknet_getEvents",
+            "starknet_getEventKeyStats",
+            "starknet_getTransactionEvents",
+            "starknet_getMessagesByTransaction",
+            "starknet_getTransactionIndex",
+            "starknet_computeContractAddress",
+        ] {
+            assert!(methods.iter().any(|m| m == method), "{} is missing", method);
+        }
+        for method in [
+            "starknet_getStateUpdateByHash",
+            "starknet_pendingTransactions",
+            "starknet_protocolVersion",
+        ] {
+            assert!(!methods.iter().any(|m| m == method), "{} is listed", method);
+        }
+
+        let mut sorted = methods.clone();
+        sorted.sort();
+        assert_eq!(methods, sorted);
+    }
 }
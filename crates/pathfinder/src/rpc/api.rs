@@ -3,24 +3,32 @@ use crate::{
     cairo::ext_py,
     core::{
         CallResultValue, CallSignatureElem, ConstructorParam, ContractAddress, ContractAddressSalt,
-        ContractCode, Fee, GasPrice, GlobalRoot, SequencerAddress, StarknetBlockHash,
-        StarknetBlockNumber, StarknetBlockTimestamp, StarknetTransactionHash,
-        StarknetTransactionIndex, StorageValue, TransactionVersion,
+        ContractCode, ContractHash, ContractNonce, EntryPoint, EventKey, Fee, GasPrice, GlobalRoot,
+        SequencerAddress, StarknetBlockHash, StarknetBlockNumber, StarknetBlockTimestamp,
+        StarknetTransactionHash, StarknetTransactionIndex, StorageAddress, StorageValue,
+        TransactionSignatureElem, TransactionVersion,
     },
     ethereum::Chain,
     rpc::types::{
         reply::{
-            Block, BlockStatus, ErrorCode, GetEventsResult, Syncing, Transaction,
-            TransactionReceipt,
+            Block, BlockHashAndNumber, BlockStatus, CallTrace, ContractAbi, EmittedEvent,
+            GetCodeResult, GetEventsResult, InvalidStorageKey, RpcError, StorageAtKey,
+            StorageAtKeyResult, StorageDiffEntry, SyncStats, Syncing, Transaction,
+            TransactionIndex, TransactionMessages, TransactionReceipt, TransactionStatus,
         },
-        request::{BlockResponseScope, Call, EventFilter, OverflowingStorageAddress},
-        BlockHashOrTag, BlockNumberOrTag, Tag,
+        request::{BlockResponseScope, Call, EventFilter, KeyMatchMode, OverflowingStorageAddress},
+        BlockHashOrTag, BlockId, BlockNumberOrTag, BlockNumberTagOrOffset, LatestOffset, Tag,
     },
     sequencer::{self, request::add_transaction::ContractDefinition, ClientApi},
-    state::SyncState,
+    state::{
+        node_cache::NodeCache,
+        state_tree::{ContractsStateTree, GlobalStateTree},
+        SyncState,
+    },
     storage::{
-        EventFilterError, RefsTable, StarknetBlocksBlockId, StarknetBlocksTable,
-        StarknetEventsTable, StarknetTransactionsTable, Storage,
+        ContractCodeTable, ContractNoncesTable, ContractsStateTable, EventFilterError, RefsTable,
+        StarknetBlocksBlockId, StarknetBlocksTable, StarknetEventFilter, StarknetEventsTable,
+        StarknetMessagesTable, StarknetRawBlocksTable, StarknetTransactionsTable, Storage,
     },
 };
 use anyhow::Context;
@@ -29,18 +37,45 @@ use jsonrpsee::types::{
     RpcResult,
 };
 use pedersen::StarkHash;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::sync::Arc;
 
+use super::events_cache::EventsCache;
+use super::latest_block::{LatestBlock, LatestBlockCache};
+use super::metrics::SequencerFallbacks;
 use super::types::reply::{DeployTransactionResult, InvokeTransactionResult};
 
 /// Implements JSON-RPC endpoints.
 pub struct RpcApi {
     storage: Storage,
     sequencer: sequencer::Client,
+    /// The hex encoded chain id, resolved once on construction so that
+    /// `starknet_chainId` does not have to do any work per request.
     chain_id: &'static str,
     call_handle: Option<ext_py::Handle>,
     sync_state: Arc<SyncState>,
+    sequencer_fallbacks: Arc<SequencerFallbacks>,
+    /// State tree nodes shared by all reads of the state.
+    node_cache: Arc<NodeCache>,
+    /// Recent `starknet_getEvents` results, dropped whenever the tip advances.
+    events_cache: EventsCache,
+    /// Limits the number of `starknet_getEvents` queries executing at once.
+    event_queries: Arc<tokio::sync::Semaphore>,
+    /// The block the `latest` tag resolves to.
+    latest_block_cache: LatestBlockCache,
+    max_event_filter_keys: usize,
+    max_transaction_hashes: usize,
+    max_storage_keys: usize,
+    max_recent_block_hashes: usize,
+    max_decompressed_size: usize,
+    event_query_log_threshold: std::time::Duration,
+    default_event_page_size: usize,
+    event_soft_deadline: Option<std::time::Duration>,
+    sequencer_code_fallback: bool,
+    partial_blocks: bool,
+    pending_disabled: bool,
+    raw_blocks_retained: bool,
 }
 
 #[derive(Debug)]
@@ -58,6 +93,15 @@ pub struct RawBlock {
 
 /// Based on [the Starknet operator API spec](https://github.com/starkware-libs/starknet-specs/blob/master/api/starknet_api_openrpc.json).
 impl RpcApi {
+    /// The default maximum number of hashes accepted by `starknet_getTransactionsByHashes`.
+    pub const DEFAULT_MAX_TRANSACTION_HASHES: usize = 256;
+    /// The default maximum number of keys accepted by `starknet_getStorageAtKeys`.
+    pub const DEFAULT_MAX_STORAGE_KEYS: usize = 256;
+    /// The default maximum number of hashes returned by `starknet_getRecentBlockHashes`.
+    pub const DEFAULT_MAX_RECENT_BLOCK_HASHES: usize = 256;
+    /// The default maximum number of `starknet_getEvents` queries executing at once.
+    pub const DEFAULT_MAX_CONCURRENT_EVENT_QUERIES: usize = 8;
+
     pub fn new(
         storage: Storage,
         sequencer: sequencer::Client,
@@ -74,7 +118,30 @@ impl RpcApi {
                 Chain::Mainnet => "0x534e5f4d41494e",
             },
             call_handle: None,
+            sequencer_fallbacks: Arc::new(SequencerFallbacks::default()),
+            node_cache: Arc::new(NodeCache::default()),
+            events_cache: EventsCache::new(
+                EventsCache::DEFAULT_TTL,
+                EventsCache::DEFAULT_CAPACITY,
+                sync_state.new_heads.subscribe(),
+            ),
+            event_queries: Arc::new(tokio::sync::Semaphore::new(
+                Self::DEFAULT_MAX_CONCURRENT_EVENT_QUERIES,
+            )),
+            latest_block_cache: LatestBlockCache::new(&sync_state),
             sync_state,
+            max_event_filter_keys: StarknetEventsTable::KEY_COUNT_LIMIT,
+            max_transaction_hashes: Self::DEFAULT_MAX_TRANSACTION_HASHES,
+            max_storage_keys: Self::DEFAULT_MAX_STORAGE_KEYS,
+            max_recent_block_hashes: Self::DEFAULT_MAX_RECENT_BLOCK_HASHES,
+            max_decompressed_size: ContractCodeTable::DEFAULT_MAX_DECOMPRESSED_SIZE,
+            event_query_log_threshold: StarknetEventsTable::DEFAULT_SLOW_QUERY_THRESHOLD,
+            default_event_page_size: StarknetEventsTable::DEFAULT_PAGE_SIZE,
+            event_soft_deadline: None,
+            sequencer_code_fallback: false,
+            partial_blocks: false,
+            pending_disabled: false,
+            raw_blocks_retained: false,
         }
     }
 
@@ -85,9 +152,288 @@ impl RpcApi {
         }
     }
 
+    /// Sets the maximum number of keys accepted in a `starknet_getEvents` filter,
+    /// defaults to [StarknetEventsTable::KEY_COUNT_LIMIT].
+    pub fn with_max_event_filter_keys(self, max_event_filter_keys: usize) -> Self {
+        Self {
+            max_event_filter_keys,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of hashes accepted by `starknet_getTransactionsByHashes`,
+    /// defaults to [RpcApi::DEFAULT_MAX_TRANSACTION_HASHES].
+    pub fn with_max_transaction_hashes(self, max_transaction_hashes: usize) -> Self {
+        Self {
+            max_transaction_hashes,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of keys accepted by `starknet_getStorageAtKeys`,
+    /// defaults to [RpcApi::DEFAULT_MAX_STORAGE_KEYS].
+    pub fn with_max_storage_keys(self, max_storage_keys: usize) -> Self {
+        Self {
+            max_storage_keys,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of hashes returned by `starknet_getRecentBlockHashes`,
+    /// defaults to [RpcApi::DEFAULT_MAX_RECENT_BLOCK_HASHES].
+    pub fn with_max_recent_block_hashes(self, max_recent_block_hashes: usize) -> Self {
+        Self {
+            max_recent_block_hashes,
+            ..self
+        }
+    }
+
+    /// Sets the maximum size to which stored contract code is decompressed, defaults to
+    /// [ContractCodeTable::DEFAULT_MAX_DECOMPRESSED_SIZE]. Reading larger contract code fails.
+    pub fn with_max_decompressed_size(self, max_decompressed_size: usize) -> Self {
+        Self {
+            max_decompressed_size,
+            ..self
+        }
+    }
+
+    /// Sets the duration above which event queries of `starknet_getEvents` are logged as slow,
+    /// defaults to [StarknetEventsTable::DEFAULT_SLOW_QUERY_THRESHOLD].
+    pub fn with_event_query_log_threshold(
+        self,
+        event_query_log_threshold: std::time::Duration,
+    ) -> Self {
+        Self {
+            event_query_log_threshold,
+            ..self
+        }
+    }
+
+    /// Sets the page size of `starknet_getEvents` filters which do not specify one, defaults to
+    /// [StarknetEventsTable::DEFAULT_PAGE_SIZE]. Capped by the maximum page size.
+    pub fn with_default_event_page_size(self, default_event_page_size: usize) -> Self {
+        Self {
+            default_event_page_size: default_event_page_size
+                .min(StarknetEventsTable::PAGE_SIZE_LIMIT),
+            ..self
+        }
+    }
+
+    /// Sets the duration after which `starknet_getEvents` returns the events gathered so far,
+    /// together with a continuation token to resume the query. Disabled by default, or if
+    /// [None].
+    pub fn with_event_soft_deadline(
+        self,
+        event_soft_deadline: Option<std::time::Duration>,
+    ) -> Self {
+        Self {
+            event_soft_deadline,
+            ..self
+        }
+    }
+
+    /// Sets for how long and how many `starknet_getEvents` results are cached, defaults to
+    /// [EventsCache::DEFAULT_TTL] and [EventsCache::DEFAULT_CAPACITY]. A `capacity` of zero
+    /// disables the cache.
+    pub fn with_events_cache(self, ttl: std::time::Duration, capacity: usize) -> Self {
+        Self {
+            events_cache: EventsCache::new(ttl, capacity, self.sync_state.new_heads.subscribe()),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of `starknet_getEvents` queries executing at once, defaults to
+    /// [RpcApi::DEFAULT_MAX_CONCURRENT_EVENT_QUERIES]. Queries above the limit are queued until an
+    /// earlier query completes.
+    pub fn with_max_concurrent_event_queries(self, max: std::num::NonZeroUsize) -> Self {
+        Self {
+            event_queries: Arc::new(tokio::sync::Semaphore::new(max.get())),
+            ..self
+        }
+    }
+
+    /// Enables querying the sequencer in `starknet_getCode` for contracts which are missing
+    /// from local storage, i.e. because they have not been synced yet. Disabled by default.
+    pub fn with_sequencer_code_fallback(self, sequencer_code_fallback: bool) -> Self {
+        Self {
+            sequencer_code_fallback,
+            ..self
+        }
+    }
+
+    /// Enables returning blocks with the transactions whose stored data cannot be read left out,
+    /// instead of failing the entire request. The hashes of the left out transactions are listed
+    /// in [Block::skipped_transactions]. Disabled by default.
+    pub fn with_partial_blocks(self, partial_blocks: bool) -> Self {
+        Self {
+            partial_blocks,
+            ..self
+        }
+    }
+
+    /// Disables the [pending tag](Tag::Pending), so that requests referring to it are rejected
+    /// instead of being served with data from the sequencer. Disabled by default.
+    pub fn with_pending_disabled(self, pending_disabled: bool) -> Self {
+        Self {
+            pending_disabled,
+            ..self
+        }
+    }
+
+    /// Enables `starknet_getRawBlock`, which requires the sync process to retain the blocks as
+    /// received from the sequencer. Disabled by default.
+    pub fn with_raw_blocks_retained(self, raw_blocks_retained: bool) -> Self {
+        Self {
+            raw_blocks_retained,
+            ..self
+        }
+    }
+
+    /// The maximum number of keys accepted in an event filter, i.e. for applying the same limit
+    /// to the WebSocket-RPC subscriptions.
+    pub fn max_event_filter_keys(&self) -> usize {
+        self.max_event_filter_keys
+    }
+
+    /// Counts the methods which delegated to the sequencer, i.e. for serving on a metrics endpoint.
+    pub fn sequencer_fallbacks(&self) -> Arc<SequencerFallbacks> {
+        self.sequencer_fallbacks.clone()
+    }
+
+    /// The state tree node cache shared by all reads of the state, i.e. for serving its hit
+    /// rate on a metrics endpoint.
+    pub fn node_cache(&self) -> Arc<NodeCache> {
+        self.node_cache.clone()
+    }
+
+    /// Returns the sequencer client, counting the delegation of `method` to the sequencer.
+    ///
+    /// A request is counted once, however many sequencer queries it makes. Requests which already
+    /// went through [RpcApi::resolve_pending] make their remaining queries on `self.sequencer`.
+    fn fallback_to_sequencer(&self, method: &'static str) -> &sequencer::Client {
+        self.sequencer_fallbacks.increment(method);
+        &self.sequencer
+    }
+
+    /// Returns an error if the [pending tag](Tag::Pending) has been disabled using
+    /// [RpcApi::with_pending_disabled].
+    ///
+    /// Every request referring to the pending tag has to pass this check, which
+    /// [RpcApi::resolve_pending] and [RpcApi::resolve_block] do for their callers.
+    fn ensure_pending_enabled(&self) -> RpcResult<()> {
+        if self.pending_disabled {
+            return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                "The pending tag is disabled on this node"
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Resolves the [latest tag](Tag::Latest).
+    ///
+    /// Returns the latest block, or [None] if there is none yet.
+    ///
+    /// The latest block is cached until the tip changes, so that resolving the `latest` tag
+    /// does not read the database on every request.
+    async fn latest_block(&self) -> RpcResult<Option<LatestBlock>> {
+        let miss = match self.latest_block_cache.get() {
+            Ok(latest) => return Ok(Some(latest)),
+            Err(miss) => miss,
+        };
+
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let connection = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            StarknetBlocksTable::get(&connection, StarknetBlocksBlockId::Latest)
+                .context("Reading latest block from database")
+                .map_err(internal_server_error)
+                .map(|block| {
+                    block.map(|block| LatestBlock {
+                        number: block.number,
+                        hash: block.hash,
+                        root: block.root,
+                    })
+                })
+        });
+
+        let latest = jh
+            .await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)?;
+
+        if let Some(latest) = latest {
+            self.latest_block_cache.insert(miss, latest);
+        }
+        Ok(latest)
+    }
+
+    /// Resolves the `latest` tag to the number of the [latest block](RpcApi::latest_block).
+    ///
+    /// Without any blocks [StarknetBlocksBlockId::Latest] is returned, leaving it up to the
+    /// caller to report the missing block.
+    async fn resolve_latest(&self) -> RpcResult<StarknetBlocksBlockId> {
+        Ok(match self.latest_block().await? {
+            Some(latest) => latest.number.into(),
+            None => StarknetBlocksBlockId::Latest,
+        })
+    }
+
+    /// Returns the sequencer's pending block, or [None] if the sequencer currently has no
+    /// pending block. In the latter case callers should fall back to the latest accepted block.
+    ///
+    /// Fails if the pending tag is disabled, see [RpcApi::ensure_pending_enabled].
+    async fn resolve_pending(
+        &self,
+        method: &'static str,
+    ) -> RpcResult<Option<sequencer::reply::Block>> {
+        use sequencer::error::{SequencerError, StarknetErrorCode};
+
+        self.ensure_pending_enabled()?;
+
+        match self
+            .fallback_to_sequencer(method)
+            .block_by_hash(BlockHashOrTag::Tag(Tag::Pending))
+            .await
+        {
+            Ok(block) if block.status == sequencer::reply::Status::Pending => Ok(Some(block)),
+            // Without a pending block the sequencer replies with the latest accepted block instead.
+            Ok(_) => Ok(None),
+            Err(SequencerError::StarknetError(e)) if e.code == StarknetErrorCode::BlockNotFound => {
+                Ok(None)
+            }
+            Err(e) => Err(internal_server_error(e)),
+        }
+    }
+
+    /// Like [RpcApi::resolve_pending], but treats a failing sequencer like one without a pending
+    /// block.
+    ///
+    /// For lookups of hashes unknown to this node, which should fail as not found rather than
+    /// with an internal error if the sequencer cannot be reached.
+    async fn pending_or_none(&self, method: &'static str) -> Option<sequencer::reply::Block> {
+        match self.resolve_pending(method).await {
+            Ok(pending) => pending,
+            Err(e) => {
+                tracing::debug!(error=?e, %method, "Failed to fetch pending block");
+                None
+            }
+        }
+    }
+
     /// Get block information given the block hash.
     /// `block_hash` is the [Hash](crate::rpc::types::BlockHashOrTag::Hash) or [Tag](crate::rpc::types::BlockHashOrTag::Tag)
     /// of the requested block.
+    ///
+    /// The [pending tag](Tag::Pending) returns the sequencer's pending block. If the sequencer
+    /// has no pending block, the latest accepted block is returned instead, or
+    /// [RpcError::InvalidBlockHash] if there is none yet. If the pending tag is
+    /// [disabled](RpcApi::with_pending_disabled), the request is rejected.
     pub async fn get_block_by_hash(
         &self,
         block_hash: BlockHashOrTag,
@@ -95,27 +441,23 @@ impl RpcApi {
     ) -> RpcResult<Block> {
         let block_id = match block_hash {
             BlockHashOrTag::Tag(Tag::Pending) => {
-                let block = self
-                    .sequencer
-                    .block_by_hash(block_hash)
-                    .await
-                    .map_err(internal_server_error)?;
-
-                let scope = requested_scope.unwrap_or_default();
+                match self.resolve_pending("starknet_getBlockByHash").await? {
+                    Some(block) => {
+                        let scope = requested_scope.unwrap_or_default();
 
-                return Ok(Block::from_sequencer_scoped(block, scope));
+                        return Ok(Block::from_sequencer_scoped(block, scope));
+                    }
+                    None => self.resolve_latest().await?,
+                }
             }
             BlockHashOrTag::Hash(hash) => hash.into(),
-            BlockHashOrTag::Tag(Tag::Latest) => StarknetBlocksBlockId::Latest,
+            BlockHashOrTag::Tag(Tag::Latest) => self.resolve_latest().await?,
         };
 
-        // Need to get the block status. This also tests that the block hash is valid.
-        let block = self.get_raw_block_by_hash(block_id).await?;
         let scope = requested_scope.unwrap_or_default();
 
-        let transactions = self.get_block_transactions(block.number, scope).await?;
-
-        Ok(Block::from_raw(block, transactions))
+        self.get_block(block_id, scope, RpcError::InvalidBlockHash)
+            .await
     }
 
     /// This function assumes that the block ID is valid i.e. it won't check if the block hash or number exist.
@@ -127,7 +469,7 @@ impl RpcApi {
         let storage = self.storage.clone();
         let jh = tokio::task::spawn_blocking(move || -> RpcResult<_> {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
@@ -136,12 +478,7 @@ impl RpcApi {
                 .context("Creating database transaction")
                 .map_err(internal_server_error)?;
 
-            let transactions_receipts = StarknetTransactionsTable::get_transaction_data_for_block(
-                &db_tx,
-                block_number.into(),
-            )
-            .context("Reading transactions from database")
-            .map_err(internal_server_error)?;
+            let transactions_receipts = read_block_transactions(&db_tx, block_number)?;
 
             // All our data is L2 accepted, check our L1-L2 head to see if this block has been accepted on L1.
             let l1_l2_head = RefsTable::get_l1_l2_head(&db_tx)
@@ -152,125 +489,204 @@ impl RpcApi {
                 _ => BlockStatus::AcceptedOnL2,
             };
 
-            Ok((transactions_receipts, block_status))
+            Ok(scoped_transactions(
+                transactions_receipts,
+                block_status,
+                scope,
+            ))
         });
 
-        let (transactions_receipts, block_status) = jh
-            .await
+        jh.await
             .context("Database read panic or shutting down")
-            .map_err(internal_server_error)??;
+            .map_err(internal_server_error)
+            // flatten is unstable
+            .and_then(|x| x)
+    }
 
-        use super::types::reply;
-        let transactions = match scope {
-            BlockResponseScope::TransactionHashes => reply::Transactions::HashesOnly(
-                transactions_receipts
-                    .into_iter()
-                    .map(|(t, _)| t.transaction_hash)
-                    .collect(),
-            ),
-            BlockResponseScope::FullTransactions => reply::Transactions::Full(
-                transactions_receipts
-                    .into_iter()
-                    .map(|(t, _)| t.into())
-                    .collect(),
-            ),
-            BlockResponseScope::FullTransactionsAndReceipts => {
-                reply::Transactions::FullWithReceipts(
-                    transactions_receipts
-                        .into_iter()
-                        .map(|(t, r)| {
-                            let t: Transaction = t.into();
-                            let r = TransactionReceipt::with_status(r, block_status);
-
-                            reply::TransactionAndReceipt {
-                                txn_hash: t.txn_hash,
-                                contract_address: t.contract_address,
-                                entry_point_selector: t.entry_point_selector,
-                                calldata: t.calldata,
-                                status: r.status,
-                                status_data: r.status_data,
-                                messages_sent: r.messages_sent,
-                                l1_origin_message: r.l1_origin_message,
-                                events: r.events,
-                            }
-                        })
-                        .collect(),
-                )
-            }
-        };
+    /// Get a block exactly as it was received from the sequencer, given its hash.
+    ///
+    /// Only available if [raw blocks are retained](RpcApi::with_raw_blocks_retained), otherwise
+    /// the request is rejected. Blocks synced before retention was enabled are not found.
+    pub async fn get_raw_block(
+        &self,
+        block_hash: StarknetBlockHash,
+    ) -> RpcResult<Box<serde_json::value::RawValue>> {
+        if !self.raw_blocks_retained {
+            return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                "Raw block retention is disabled on this node"
+            ))));
+        }
 
-        Ok(transactions)
+        let storage = self.storage.clone();
+        let jh = tokio::task::spawn_blocking(move || -> RpcResult<_> {
+            let db = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            let raw_block = StarknetRawBlocksTable::get(&db, block_hash)
+                .context("Reading raw block from database")
+                .map_err(internal_server_error)?
+                .ok_or_else(|| Error::from(RpcError::InvalidBlockHash))?;
+
+            String::from_utf8(raw_block)
+                .context("Raw block is not UTF-8")
+                .and_then(|json| {
+                    serde_json::value::RawValue::from_string(json).context("Raw block is not JSON")
+                })
+                .map_err(internal_server_error)
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
     }
 
     /// Get block information given the block number (its height).
     /// `block_number` is the [Number](crate::rpc::types::BlockNumberOrTag::Number) (height) or [Tag](crate::rpc::types::BlockHashOrTag::Tag)
-    /// of the requested block.
+    /// of the requested block, or its [offset](crate::rpc::types::LatestOffset) from the latest block.
     pub async fn get_block_by_number(
         &self,
-        block_number: BlockNumberOrTag,
+        block_number: BlockNumberTagOrOffset,
         requested_scope: Option<BlockResponseScope>,
     ) -> RpcResult<Block> {
+        let block_number = match block_number {
+            BlockNumberTagOrOffset::NumberOrTag(block_number) => block_number,
+            BlockNumberTagOrOffset::LatestOffset(offset) => {
+                BlockNumberOrTag::Number(self.resolve_latest_offset(offset).await?)
+            }
+        };
+
         let block_id = match block_number {
             BlockNumberOrTag::Number(number) => number.into(),
-            BlockNumberOrTag::Tag(Tag::Latest) => StarknetBlocksBlockId::Latest,
+            BlockNumberOrTag::Tag(Tag::Latest) => self.resolve_latest().await?,
             BlockNumberOrTag::Tag(Tag::Pending) => {
-                let block = self
-                    .sequencer
-                    .block_by_number(block_number)
-                    .await
-                    .context("Fetch block from sequencer")
-                    .map_err(internal_server_error)?;
+                match self.resolve_pending("starknet_getBlockByNumber").await? {
+                    Some(block) => {
+                        let scope = requested_scope.unwrap_or_default();
 
-                let scope = requested_scope.unwrap_or_default();
-
-                return Ok(Block::from_sequencer_scoped(block, scope));
+                        return Ok(Block::from_sequencer_scoped(block, scope));
+                    }
+                    None => self.resolve_latest().await?,
+                }
             }
         };
 
-        // Need to get the block status. This also tests that the block hash is valid.
-        let block = self.get_raw_block_by_number(block_id).await?;
         let scope = requested_scope.unwrap_or_default();
 
-        let transactions = self.get_block_transactions(block.number, scope).await?;
-
-        Ok(Block::from_raw(block, transactions))
+        self.get_block(block_id, scope, RpcError::InvalidBlockNumber)
+            .await
     }
 
-    /// Fetches a [RawBlock] from storage.
+    /// Resolves a [LatestOffset] to the number of the block it refers to.
     ///
-    /// Returns [`jsonrpsee::types::Error::Call`] with code [`ErrorCode::InvalidBlockHash`]
-    /// when called with [`StarknetBlocksBlockId::Latest`] on an empty storage.
-    async fn get_raw_block_by_hash(&self, block_id: StarknetBlocksBlockId) -> RpcResult<RawBlock> {
-        self.get_raw_block(block_id, ErrorCode::InvalidBlockHash)
-            .await
+    /// Returns [RpcError::InvalidBlockNumber] if the offset exceeds the chain height.
+    async fn resolve_latest_offset(&self, offset: LatestOffset) -> RpcResult<StarknetBlockNumber> {
+        let latest = self
+            .latest_block()
+            .await?
+            .ok_or_else(|| Error::from(RpcError::InvalidBlockNumber))?;
+
+        latest
+            .number
+            .0
+            .checked_sub(offset.latest_offset)
+            .map(StarknetBlockNumber)
+            .ok_or_else(|| Error::from(RpcError::InvalidBlockNumber))
     }
 
-    /// Fetches a [RawBlock] from storage.
+    /// Resolves a [BlockId] to the number and global root of the block it refers to.
+    ///
+    /// [Tag::Pending] resolves to the latest block, which the pending block is built on, unless
+    /// the pending tag is [disabled](RpcApi::with_pending_disabled). Methods serving pending data
+    /// have to handle the pending tag before resolving the block.
     ///
-    /// Returns [`jsonrpsee::types::Error::Call`] with code [`ErrorCode::InvalidBlockNumber`]
-    /// when called with [`StarknetBlocksBlockId::Latest`] on an empty storage.
-    async fn get_raw_block_by_number(
+    /// Returns [RpcError::InvalidBlockNumber] for unknown block numbers and
+    /// [RpcError::InvalidBlockHash] otherwise.
+    ///
+    /// The resolved block is logged at debug level together with `method` and the requested
+    /// [BlockId], which helps explaining responses based on unexpectedly old state.
+    async fn resolve_block(
         &self,
-        block_id: StarknetBlocksBlockId,
-    ) -> RpcResult<RawBlock> {
-        self.get_raw_block(block_id, ErrorCode::InvalidBlockNumber)
-            .await
+        method: &'static str,
+        block: BlockId,
+    ) -> RpcResult<(StarknetBlockNumber, GlobalRoot)> {
+        let (number, root) = self.read_resolved_block(block).await?;
+
+        tracing::debug!(
+            method,
+            requested=?block,
+            block=%number.0,
+            root=%root.0,
+            "Resolved block"
+        );
+
+        Ok((number, root))
+    }
+
+    /// Looks up the block of [RpcApi::resolve_block].
+    async fn read_resolved_block(
+        &self,
+        block: BlockId,
+    ) -> RpcResult<(StarknetBlockNumber, GlobalRoot)> {
+        let (block_id, error) = match block {
+            BlockId::Hash(hash) => (hash.into(), RpcError::InvalidBlockHash),
+            BlockId::Number(number) => (number.into(), RpcError::InvalidBlockNumber),
+            BlockId::Tag(tag) => {
+                if tag == Tag::Pending {
+                    self.ensure_pending_enabled()?;
+                }
+
+                return self
+                    .latest_block()
+                    .await?
+                    .map(|latest| (latest.number, latest.root))
+                    .ok_or_else(|| Error::from(RpcError::InvalidBlockHash));
+            }
+        };
+
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let connection = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            StarknetBlocksTable::get(&connection, block_id)
+                .context("Reading block from database")
+                .map_err(internal_server_error)?
+                .map(|block| (block.number, block.root))
+                .ok_or_else(|| Error::from(error))
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
     }
 
-    /// Fetches a [RawBlock] from storage.
+    /// Fetches a [Block] and its transactions from storage.
+    ///
+    /// The block and its transactions are read within a single database transaction,
+    /// which pins [`StarknetBlocksBlockId::Latest`] to the latest block at the start of the read.
+    /// This prevents torn reads if the sync process concurrently inserts or reorgs blocks.
     ///
-    /// `error_code_for_latest` is the error code when the `latest` block is missing,
+    /// `error_for_latest` is the error when the `latest` block is missing,
     /// ie. when the storage is empty.
-    async fn get_raw_block(
+    async fn get_block(
         &self,
         block_id: StarknetBlocksBlockId,
-        error_code_for_latest: ErrorCode,
-    ) -> RpcResult<RawBlock> {
+        scope: BlockResponseScope,
+        error_for_latest: RpcError,
+    ) -> RpcResult<Block> {
         let storage = self.storage.clone();
+        let partial_blocks = self.partial_blocks;
 
         let handle = tokio::task::spawn_blocking(move || {
             let mut connection = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
@@ -279,48 +695,40 @@ impl RpcApi {
                 .context("Creating database transaction")
                 .map_err(internal_server_error)?;
 
-            let block = StarknetBlocksTable::get(&transaction, block_id)
-                .context("Read block from database")
-                .map_err(internal_server_error)?
-                .ok_or_else(|| Error::from(error_code_for_latest))?;
+            // Need to get the block status. This also tests that the block id is valid.
+            let block = read_raw_block(&transaction, block_id, error_for_latest)?;
 
-            // All our data is L2 accepted, check our L1-L2 head to see if this block has been accepted on L1.
-            let l1_l2_head = RefsTable::get_l1_l2_head(&transaction)
-                .context("Read latest L1 head from database")
-                .map_err(internal_server_error)?;
-            let block_status = match l1_l2_head {
-                Some(number) if number >= block.number => BlockStatus::AcceptedOnL1,
-                _ => BlockStatus::AcceptedOnL2,
-            };
+            if !partial_blocks {
+                let transactions_receipts = read_block_transactions(&transaction, block.number)?;
+                let transactions = scoped_transactions(transactions_receipts, block.status, scope);
 
-            let (parent_hash, parent_root) = match block.number {
-                StarknetBlockNumber::GENESIS => (
-                    StarknetBlockHash(StarkHash::ZERO),
-                    GlobalRoot(StarkHash::ZERO),
-                ),
-                other => {
-                    let parent_block = StarknetBlocksTable::get(&transaction, (other - 1).into())
-                        .context("Read parent block from database")
-                        .map_err(internal_server_error)?
-                        .context("Parent block missing")?;
+                return Ok(Block::from_raw(block, transactions));
+            }
 
-                    (parent_block.hash, parent_block.root)
-                }
-            };
+            let (transactions_receipts, unreadable) =
+                StarknetTransactionsTable::get_partial_transaction_data_for_block(
+                    &transaction,
+                    block.number.into(),
+                )
+                .context("Reading transactions from database")
+                .map_err(internal_server_error)?;
 
-            let block = RawBlock {
-                number: block.number,
-                hash: block.hash,
-                root: block.root,
-                parent_hash,
-                parent_root,
-                timestamp: block.timestamp,
-                status: block_status,
-                gas_price: block.gas_price,
-                sequencer: block.sequencer_address,
-            };
+            for skipped in &unreadable {
+                tracing::warn!(
+                    block=%block.number.0,
+                    transaction=%skipped.hash.0,
+                    index=%skipped.index,
+                    reason=?skipped.error,
+                    "Skipping unreadable transaction"
+                );
+            }
 
-            Ok(block)
+            let transactions = scoped_transactions(transactions_receipts, block.status, scope);
+
+            Ok(Block {
+                skipped_transactions: unreadable.into_iter().map(|t| t.hash).collect(),
+                ..Block::from_raw(block, transactions)
+            })
         });
 
         handle
@@ -350,8 +758,8 @@ impl RpcApi {
 
     /// Get the value of the storage at the given address and key.
     /// `contract_address` is the address of the contract to read from, `key` is the key to the storage value for the given contract,
-    /// `block_hash` is the [Hash](crate::rpc::types::BlockHashOrTag::Hash) or [Tag](crate::rpc::types::BlockHashOrTag::Tag)
-    /// of the requested block.
+    /// `block_id` is the [Hash](crate::rpc::types::BlockId::Hash), [Number](crate::rpc::types::BlockId::Number)
+    /// or [Tag](crate::rpc::types::BlockId::Tag) of the requested block.
     ///
     /// We are using overflowing type for `key` to be able to correctly report `INVALID_STORAGE_KEY` as per
     /// [StarkNet RPC spec](https://github.com/starkware-libs/starknet-specs/blob/master/api/starknet_api_openrpc.json),
@@ -360,42 +768,189 @@ impl RpcApi {
         &self,
         contract_address: ContractAddress,
         key: OverflowingStorageAddress,
-        block_hash: BlockHashOrTag,
+        block_id: BlockId,
     ) -> RpcResult<StorageValue> {
-        use crate::{
-            core::StorageAddress,
-            state::state_tree::{ContractsStateTree, GlobalStateTree},
-            storage::ContractsStateTable,
-        };
-        use pedersen::OverflowError;
-
-        let key = StorageAddress(StarkHash::from_be_bytes(key.0.to_fixed_bytes()).map_err(
-            // Report that the value is >= than the field modulus
-            // Use explicit typing in closure arg to force compiler error should error variants ever be expanded
-            |_e: OverflowError| Error::from(ErrorCode::InvalidStorageKey),
-        )?);
+        let key = parse_storage_key(key).map_err(|_| Error::from(RpcError::InvalidStorageKey))?;
+        reject_zero_address(contract_address)?;
+
+        if block_id == BlockId::Tag(Tag::Pending) {
+            // Like the pending call, the pending block's state diff is overlaid on the latest block.
+            if self
+                .resolve_pending("starknet_getStorageAt")
+                .await?
+                .is_some()
+            {
+                let pending_state_diff = self
+                    .sequencer
+                    .state_update_by_hash(BlockHashOrTag::Tag(Tag::Pending))
+                    .await
+                    .map_err(Error::from)?
+                    .state_diff;
+
+                let pending_value = pending_state_diff
+                    .storage_diffs
+                    .get(&contract_address)
+                    .and_then(|diffs| diffs.iter().rev().find(|diff| diff.key == key))
+                    .map(|diff| diff.value);
+                if let Some(value) = pending_value {
+                    return Ok(value);
+                }
 
-        if key.0.has_more_than_251_bits() {
-            // Report that the value is more than 251 bits
-            return Err(Error::from(ErrorCode::InvalidStorageKey));
+                // Contracts deployed in the pending block have no state in storage yet,
+                // so all of their unchanged slots are still zero.
+                if pending_state_diff
+                    .deployed_contracts
+                    .iter()
+                    .any(|contract| contract.address == contract_address)
+                {
+                    return Ok(StorageValue(StarkHash::ZERO));
+                }
+            }
         }
 
-        let block_id = match block_hash {
-            BlockHashOrTag::Hash(hash) => hash.into(),
-            BlockHashOrTag::Tag(Tag::Latest) => StarknetBlocksBlockId::Latest,
-            BlockHashOrTag::Tag(Tag::Pending) => {
-                return Ok(self
-                    .sequencer
-                    .storage(contract_address, key, block_hash)
-                    .await?);
-            }
-        };
+        let (_, global_root) = self
+            .resolve_block("starknet_getStorageAt", block_id)
+            .await?;
 
         let storage = self.storage.clone();
+        let node_cache = self.node_cache.clone();
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            let tx = db
+                .transaction()
+                .context("Creating database transaction")
+                .map_err(internal_server_error)?;
+
+            let contract_state_tree =
+                load_contract_state_tree(&tx, global_root, contract_address, &node_cache)?;
+
+            // ContractsStateTree::get() will return zero if the value is still not found (and we know the key is valid),
+            // which is consistent with the specification.
+            let storage_val = contract_state_tree
+                .get(key)
+                .context("Get value from contract state tree")
+                .map_err(internal_server_error)?;
+
+            Ok(storage_val)
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            // flatten is unstable
+            .and_then(|x| x)
+    }
+
+    /// Get the value of the storage at the given address and key, in the state with the given
+    /// global root.
+    ///
+    /// This is meant for tools which know a [GlobalRoot] but not the block it belongs to.
+    /// Fails if the state at `root` is not persisted by this node.
+    pub async fn get_storage_at_root(
+        &self,
+        contract_address: ContractAddress,
+        key: OverflowingStorageAddress,
+        root: GlobalRoot,
+    ) -> RpcResult<StorageValue> {
+        let key = parse_storage_key(key).map_err(|_| Error::from(RpcError::InvalidStorageKey))?;
+
+        let storage = self.storage.clone();
+        let node_cache = self.node_cache.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            let tx = db
+                .transaction()
+                .context("Creating database transaction")
+                .map_err(internal_server_error)?;
+
+            resolve_root(&tx, root)?;
+
+            let contract_state_tree =
+                load_contract_state_tree(&tx, root, contract_address, &node_cache)?;
+
+            contract_state_tree
+                .get(key)
+                .context("Get value from contract state tree")
+                .map_err(internal_server_error)
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
+    }
+
+    /// Get the values of multiple storage keys of a contract at once.
+    ///
+    /// Unlike [RpcApi::get_storage_at], invalid keys do not fail the entire request. Instead
+    /// the reason why a key is invalid is reported in place of its value.
+    ///
+    /// At most [RpcApi::with_max_storage_keys] keys are accepted. Like for
+    /// [RpcApi::get_storage_at], the pending block's state diff is overlaid on the latest block.
+    pub async fn get_storage_at_keys(
+        &self,
+        contract_address: ContractAddress,
+        keys: Vec<OverflowingStorageAddress>,
+        block_hash: BlockHashOrTag,
+    ) -> RpcResult<Vec<StorageAtKey>> {
+        if keys.len() > self.max_storage_keys {
+            return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                "Too many storage keys, supported maximum is {}",
+                self.max_storage_keys
+            ))));
+        }
+
+        let parsed_keys: Vec<_> = keys.iter().copied().map(parse_storage_key).collect();
+
+        let (block_id, pending_state_diff) = match block_hash {
+            BlockHashOrTag::Hash(hash) => (hash.into(), None),
+            BlockHashOrTag::Tag(Tag::Latest) => (self.resolve_latest().await?, None),
+            BlockHashOrTag::Tag(Tag::Pending) => {
+                let pending_state_diff =
+                    match self.resolve_pending("starknet_getStorageAtKeys").await? {
+                        Some(_) => Some(
+                            self.sequencer
+                                .state_update_by_hash(BlockHashOrTag::Tag(Tag::Pending))
+                                .await
+                                .map_err(Error::from)?
+                                .state_diff,
+                        ),
+                        None => None,
+                    };
+                (self.resolve_latest().await?, pending_state_diff)
+            }
+        };
+
+        let (pending_diffs, deployed_in_pending) = match pending_state_diff {
+            Some(mut state_diff) => (
+                state_diff
+                    .storage_diffs
+                    .remove(&contract_address)
+                    .unwrap_or_default(),
+                state_diff
+                    .deployed_contracts
+                    .iter()
+                    .any(|contract| contract.address == contract_address),
+            ),
+            None => (Vec::new(), false),
+        };
+
+        let storage = self.storage.clone();
+        let node_cache = self.node_cache.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
@@ -410,50 +965,215 @@ impl RpcApi {
                 .map_err(internal_server_error)?
                 // Since the db query succeeded in execution, we can now report if the block hash was indeed not found
                 // by using a dedicated error code from the RPC API spec
-                .ok_or_else(|| Error::from(ErrorCode::InvalidBlockHash))?;
+                .ok_or_else(|| Error::from(RpcError::InvalidBlockHash))?;
+
+            // Contracts deployed in the pending block have no state in storage yet,
+            // so all of their slots not in the pending diffs are still zero.
+            let contract_state_tree = match deployed_in_pending {
+                true => None,
+                false => Some(load_contract_state_tree(
+                    &tx,
+                    global_root,
+                    contract_address,
+                    &node_cache,
+                )?),
+            };
 
-            let global_state_tree = GlobalStateTree::load(&tx, global_root)
-                .context("Global state tree")
+            keys.into_iter()
+                .zip(parsed_keys)
+                .map(|(key, parsed)| {
+                    let parsed = match parsed {
+                        Ok(parsed) => parsed,
+                        Err(reason) => {
+                            return Ok(StorageAtKey {
+                                key,
+                                result: StorageAtKeyResult::InvalidKey(reason),
+                            })
+                        }
+                    };
+
+                    let pending_value = pending_diffs
+                        .iter()
+                        .rev()
+                        .find(|diff| diff.key == parsed)
+                        .map(|diff| diff.value);
+                    let value = match (pending_value, &contract_state_tree) {
+                        (Some(value), _) => value,
+                        (None, Some(tree)) => tree
+                            .get(parsed)
+                            .context("Get value from contract state tree")
+                            .map_err(internal_server_error)?,
+                        (None, None) => StorageValue(StarkHash::ZERO),
+                    };
+                    Ok(StorageAtKey {
+                        key,
+                        result: StorageAtKeyResult::Value(value),
+                    })
+                })
+                .collect::<RpcResult<Vec<_>>>()
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
+    }
+
+    /// Get the storage entries of a contract whose values differ between `from_block` and
+    /// `to_block`, in ascending key order.
+    ///
+    /// Returns [RpcError::ContractNotFound] if the contract does not exist at either of the blocks,
+    /// i.e. if it was deployed after `from_block`.
+    pub async fn get_storage_diff(
+        &self,
+        contract_address: ContractAddress,
+        from_block: BlockId,
+        to_block: BlockId,
+    ) -> RpcResult<Vec<StorageDiffEntry>> {
+        let (_, from_root) = self
+            .resolve_block("starknet_getStorageDiff", from_block)
+            .await?;
+        let (_, to_root) = self
+            .resolve_block("starknet_getStorageDiff", to_block)
+            .await?;
+
+        let storage = self.storage.clone();
+        let node_cache = self.node_cache.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
+                .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
-            let contract_state_hash = global_state_tree
-                .get(contract_address)
-                .context("Get contract state hash from global state tree")
+            let tx = db
+                .transaction()
+                .context("Creating database transaction")
                 .map_err(internal_server_error)?;
 
-            // There is a dedicated error code for a non-existent contract in the RPC API spec, so use it.
-            if contract_state_hash.0 == StarkHash::ZERO {
-                return Err(Error::from(ErrorCode::ContractNotFound));
+            let from_tree =
+                load_contract_state_tree(&tx, from_root, contract_address, &node_cache)?;
+            let to_tree = load_contract_state_tree(&tx, to_root, contract_address, &node_cache)?;
+
+            diff_storage_entries(from_tree.storage_entries(), to_tree.storage_entries())
+                .context("Comparing contract storage")
+                .map_err(internal_server_error)
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
+    }
+
+    /// Get the nonce of the contract at `contract_address`, in the state of the given block.
+    ///
+    /// Contracts whose nonce never changed have a nonce of zero. Returns
+    /// [RpcError::ContractNotFound] if the contract does not exist at the block.
+    pub async fn get_nonce(
+        &self,
+        contract_address: ContractAddress,
+        block_id: BlockId,
+    ) -> RpcResult<ContractNonce> {
+        if block_id == BlockId::Tag(Tag::Pending) {
+            // Like the pending storage, the pending block's state diff is overlaid on the latest block.
+            if self.resolve_pending("starknet_getNonce").await?.is_some() {
+                let pending_state_diff = self
+                    .sequencer
+                    .state_update_by_hash(BlockHashOrTag::Tag(Tag::Pending))
+                    .await
+                    .map_err(Error::from)?
+                    .state_diff;
+
+                if let Some(nonce) = pending_state_diff.nonces.get(&contract_address) {
+                    return Ok(*nonce);
+                }
+
+                if pending_state_diff
+                    .deployed_contracts
+                    .iter()
+                    .any(|contract| contract.address == contract_address)
+                {
+                    return Ok(ContractNonce(StarkHash::ZERO));
+                }
             }
+        }
+
+        let (block_number, global_root) = self.resolve_block("starknet_getNonce", block_id).await?;
+
+        let storage = self.storage.clone();
+        let node_cache = self.node_cache.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            let tx = db
+                .transaction()
+                .context("Creating database transaction")
+                .map_err(internal_server_error)?;
 
-            let contract_state_root = ContractsStateTable::get_root(&tx, contract_state_hash)
-                .context("Get contract state root")
+            // Only used to report a non-existent contract.
+            load_contract_state_tree(&tx, global_root, contract_address, &node_cache)?;
+
+            let nonce = ContractNoncesTable::get_at(&tx, contract_address, block_number)
+                .context("Reading contract nonce from database")
                 .map_err(internal_server_error)?
-                .ok_or_else(|| {
-                    internal_server_error(anyhow::anyhow!(
-                        "Contract state root not found for contract state hash {}",
-                        contract_state_hash.0
-                    ))
-                })?;
-
-            let contract_state_tree = ContractsStateTree::load(&tx, contract_state_root)
-                .context("Load contract state tree")
+                .unwrap_or(ContractNonce(StarkHash::ZERO));
+
+            Ok(nonce)
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
+    }
+
+    /// Get the number of contracts deployed in the state of the given block.
+    ///
+    /// The contracts are counted by walking the global state tree, so this takes time
+    /// proportional to the number of contracts. The [pending tag](Tag::Pending) resolves
+    /// to the latest block.
+    pub async fn get_contract_count(&self, block_id: BlockId) -> RpcResult<u64> {
+        let (_, global_root) = self
+            .resolve_block("starknet_getContractCount", block_id)
+            .await?;
+
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
+                .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
-            // ContractsStateTree::get() will return zero if the value is still not found (and we know the key is valid),
-            // which is consistent with the specification.
-            let storage_val = contract_state_tree
-                .get(key)
-                .context("Get value from contract state tree")
+            let tx = db
+                .transaction()
+                .context("Creating database transaction")
                 .map_err(internal_server_error)?;
 
-            Ok(storage_val)
+            // The walk would only fill the shared node cache with nodes which are rarely read.
+            let global_state_tree = GlobalStateTree::load(&tx, global_root)
+                .context("Global state tree")
+                .map_err(internal_server_error)?;
+
+            let mut count = 0u64;
+            for contract in global_state_tree.contracts() {
+                contract
+                    .context("Reading contract from global state tree")
+                    .map_err(internal_server_error)?;
+                count += 1;
+            }
+
+            Ok(count)
         });
 
         jh.await
             .context("Database read panic or shutting down")
             .map_err(internal_server_error)
-            // flatten is unstable
             .and_then(|x| x)
     }
 
@@ -467,7 +1187,7 @@ impl RpcApi {
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
@@ -478,15 +1198,115 @@ impl RpcApi {
 
             // Get the transaction from storage.
             StarknetTransactionsTable::get_transaction(&db_tx, transaction_hash)
-                .context("Reading transaction from database")?
-                .ok_or_else(|| ErrorCode::InvalidTransactionHash.into())
-                .map(|tx| tx.into())
+                .context("Reading transaction from database")
+                .map_err(internal_server_error)
         });
 
-        jh.await
+        let transaction = jh
+            .await
             .context("Database read panic or shutting down")
             .map_err(internal_server_error)
-            .and_then(|x| x)
+            .and_then(|x| x)?;
+
+        if let Some(transaction) = transaction {
+            return Ok(transaction.into());
+        }
+
+        if self.pending_disabled {
+            return Err(RpcError::InvalidTransactionHash.into());
+        }
+
+        // A recently submitted transaction may only be part of the pending block.
+        self.pending_or_none("starknet_getTransactionByHash")
+            .await
+            .and_then(|block| {
+                block
+                    .transactions
+                    .into_iter()
+                    .find(|tx| tx.transaction_hash == transaction_hash)
+            })
+            .map(|tx| Transaction {
+                status: Some(TransactionStatus::Pending),
+                ..tx.into()
+            })
+            .ok_or_else(|| RpcError::InvalidTransactionHash.into())
+    }
+
+    /// Get the details of multiple transactions by their hashes.
+    ///
+    /// The results are in the same order as `hashes`, with `null` in place of each
+    /// transaction which is unknown, instead of failing the entire request.
+    pub async fn get_transactions_by_hashes(
+        &self,
+        hashes: Vec<StarknetTransactionHash>,
+    ) -> RpcResult<Vec<Option<Transaction>>> {
+        if hashes.len() > self.max_transaction_hashes {
+            return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                "Too many transaction hashes, supported maximum is {}",
+                self.max_transaction_hashes
+            ))));
+        }
+
+        let storage = self.storage.clone();
+        let hashes_ = hashes.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            let db_tx = db
+                .transaction()
+                .context("Creating database transaction")
+                .map_err(internal_server_error)?;
+
+            hashes_
+                .into_iter()
+                .map(|hash| {
+                    StarknetTransactionsTable::get_transaction(&db_tx, hash)
+                        .context("Reading transaction from database")
+                        .map_err(internal_server_error)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        });
+
+        let transactions = jh
+            .await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)?;
+
+        let mut transactions: Vec<Option<Transaction>> = transactions
+            .into_iter()
+            .map(|tx| tx.map(Transaction::from))
+            .collect();
+
+        if self.pending_disabled || transactions.iter().all(Option::is_some) {
+            return Ok(transactions);
+        }
+
+        // Recently submitted transactions may only be part of the pending block.
+        let pending = self
+            .pending_or_none("starknet_getTransactionsByHashes")
+            .await;
+        if let Some(pending) = pending {
+            for (hash, transaction) in hashes.iter().zip(transactions.iter_mut()) {
+                if transaction.is_some() {
+                    continue;
+                }
+                *transaction = pending
+                    .transactions
+                    .iter()
+                    .find(|tx| tx.transaction_hash == *hash)
+                    .map(|tx| Transaction {
+                        status: Some(TransactionStatus::Pending),
+                        ..tx.clone().into()
+                    });
+            }
+        }
+
+        Ok(transactions)
     }
 
     /// Get the details of a transaction by a given block hash and index.
@@ -504,22 +1324,21 @@ impl RpcApi {
 
         let block_id = match block_hash {
             BlockHashOrTag::Hash(hash) => StarknetBlocksBlockId::Hash(hash),
-            BlockHashOrTag::Tag(Tag::Latest) => StarknetBlocksBlockId::Latest,
+            BlockHashOrTag::Tag(Tag::Latest) => self.resolve_latest().await?,
             BlockHashOrTag::Tag(Tag::Pending) => {
+                self.ensure_pending_enabled()?;
                 let block = self
-                    .sequencer
+                    .fallback_to_sequencer("starknet_getTransactionByBlockHashAndIndex")
                     .block_by_hash(block_hash)
                     .await
                     .context("Fetch block from sequencer")
                     .map_err(internal_server_error)?;
 
-                return block
-                    .transactions
-                    .into_iter()
-                    .nth(index)
-                    .map_or(Err(ErrorCode::InvalidTransactionIndex.into()), |txn| {
-                        Ok(txn.into())
-                    });
+                let transaction_count = block.transactions.len();
+                return block.transactions.into_iter().nth(index).map_or(
+                    Err(RpcError::InvalidTransactionIndex { transaction_count }.into()),
+                    |txn| Ok(txn.into()),
+                );
             }
         };
 
@@ -527,7 +1346,7 @@ impl RpcApi {
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
@@ -549,8 +1368,13 @@ impl RpcApi {
                     match StarknetBlocksTable::get_root(&db_tx, block_id)
                         .context("Reading block from database")?
                     {
-                        Some(_) => Err(ErrorCode::InvalidTransactionIndex.into()),
-                        None => Err(ErrorCode::InvalidBlockHash.into()),
+                        Some(_) => {
+                            let transaction_count =
+                                StarknetTransactionsTable::get_transaction_count(&db_tx, block_id)
+                                    .context("Counting transactions in database")?;
+                            Err(RpcError::InvalidTransactionIndex { transaction_count }.into())
+                        }
+                        None => Err(RpcError::InvalidBlockHash.into()),
                     }
                 }
             }
@@ -577,22 +1401,21 @@ impl RpcApi {
 
         let block_id = match block_number {
             BlockNumberOrTag::Number(number) => StarknetBlocksBlockId::Number(number),
-            BlockNumberOrTag::Tag(Tag::Latest) => StarknetBlocksBlockId::Latest,
+            BlockNumberOrTag::Tag(Tag::Latest) => self.resolve_latest().await?,
             BlockNumberOrTag::Tag(Tag::Pending) => {
+                self.ensure_pending_enabled()?;
                 let block = self
-                    .sequencer
+                    .fallback_to_sequencer("starknet_getTransactionByBlockNumberAndIndex")
                     .block_by_number(block_number)
                     .await
                     .context("Fetch block from sequencer")
                     .map_err(internal_server_error)?;
 
-                return block
-                    .transactions
-                    .into_iter()
-                    .nth(index)
-                    .map_or(Err(ErrorCode::InvalidTransactionIndex.into()), |txn| {
-                        Ok(txn.into())
-                    });
+                let transaction_count = block.transactions.len();
+                return block.transactions.into_iter().nth(index).map_or(
+                    Err(RpcError::InvalidTransactionIndex { transaction_count }.into()),
+                    |txn| Ok(txn.into()),
+                );
             }
         };
 
@@ -600,7 +1423,7 @@ impl RpcApi {
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
@@ -622,8 +1445,13 @@ impl RpcApi {
                     match StarknetBlocksTable::get_root(&db_tx, block_id)
                         .context("Reading block from database")?
                     {
-                        Some(_) => Err(ErrorCode::InvalidTransactionIndex.into()),
-                        None => Err(ErrorCode::InvalidBlockNumber.into()),
+                        Some(_) => {
+                            let transaction_count =
+                                StarknetTransactionsTable::get_transaction_count(&db_tx, block_id)
+                                    .context("Counting transactions in database")?;
+                            Err(RpcError::InvalidTransactionIndex { transaction_count }.into())
+                        }
+                        None => Err(RpcError::InvalidBlockNumber.into()),
                     }
                 }
             }
@@ -645,7 +1473,7 @@ impl RpcApi {
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
@@ -677,7 +1505,7 @@ impl RpcApi {
 
                     Ok(TransactionReceipt::with_status(receipt, block_status))
                 }
-                None => Err(ErrorCode::InvalidTransactionHash.into()),
+                None => Err(RpcError::InvalidTransactionHash.into()),
             }
         });
 
@@ -689,14 +1517,37 @@ impl RpcApi {
 
     /// Get the code of a specific contract.
     /// `contract_address` is the address of the contract to read from.
-    pub async fn get_code(&self, contract_address: ContractAddress) -> RpcResult<ContractCode> {
-        use crate::storage::ContractCodeTable;
+    /// `include_events` adds the events parsed from the contract's ABI to the result.
+    pub async fn get_code(
+        &self,
+        contract_address: ContractAddress,
+        include_events: bool,
+    ) -> RpcResult<GetCodeResult> {
+        // Also keeps the sequencer fallback from asking for it.
+        reject_zero_address(contract_address)?;
+        let code = self.get_stored_code(contract_address).await?;
+
+        let events = if include_events {
+            let abi = ContractAbi::from_json(&code.abi)
+                .context("Parsing contract ABI")
+                .map_err(internal_server_error)?;
+            Some(abi.events)
+        } else {
+            None
+        };
+
+        Ok(GetCodeResult { code, events })
+    }
 
+    /// Reads the code of a contract from local storage, falling back to the sequencer
+    /// if enabled.
+    async fn get_stored_code(&self, contract_address: ContractAddress) -> RpcResult<ContractCode> {
         let storage = self.storage.clone();
+        let max_decompressed_size = self.max_decompressed_size;
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
             let tx = db
@@ -704,54 +1555,113 @@ impl RpcApi {
                 .context("Creating database transaction")
                 .map_err(internal_server_error)?;
 
-            let code = ContractCodeTable::get_code(&tx, contract_address)
+            ContractCodeTable::get_code_with_limit(&tx, contract_address, max_decompressed_size)
                 .context("Fetching code from database")
-                .map_err(internal_server_error)?;
-
-            match code {
-                Some(code) => Ok(code),
-                None => Err(ErrorCode::ContractNotFound.into()),
-            }
+                .map_err(internal_server_error)
         });
 
-        jh.await
+        let code = jh
+            .await
             .context("Database read panic or shutting down")
             .map_err(internal_server_error)
-            .and_then(|x| x)
+            .and_then(|x| x)?;
+
+        match code {
+            Some(code) => Ok(code),
+            None if self.sequencer_code_fallback => {
+                self.get_code_from_sequencer(contract_address).await
+            }
+            None => Err(RpcError::ContractNotFound.into()),
+        }
     }
 
-    /// Get the number of transactions in a block given a block hash.
-    /// `block_hash` is the [Hash](crate::rpc::types::BlockHashOrTag::Hash) or [Tag](crate::rpc::types::BlockHashOrTag::Tag)
-    /// of the requested block.
-    pub async fn get_block_transaction_count_by_hash(
+    /// Fetches the code of a contract which is missing from local storage from the sequencer.
+    ///
+    /// The code is not stored: only the sync process writes contracts to the database, once it
+    /// has verified their deployment.
+    async fn get_code_from_sequencer(
         &self,
-        block_hash: BlockHashOrTag,
-    ) -> RpcResult<u64> {
+        contract_address: ContractAddress,
+    ) -> RpcResult<ContractCode> {
+        use crate::core::ByteCodeWord;
+        use crate::state::contract_hash::extract_abi_code_hash;
+
+        // Unknown contracts are mapped to RpcError::ContractNotFound.
+        let definition = self
+            .fallback_to_sequencer("starknet_getCode")
+            .full_contract(contract_address)
+            .await?;
+
+        let max_decompressed_size = self.max_decompressed_size;
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let (abi, bytecode, _) = extract_abi_code_hash(&definition)
+                .context("Parsing contract definition from sequencer")?;
+
+            // Same limit as for the stored code, which the sequencer's code would otherwise bypass.
+            anyhow::ensure!(
+                abi.len() <= max_decompressed_size && bytecode.len() <= max_decompressed_size,
+                "Contract code from sequencer exceeds {} bytes",
+                max_decompressed_size
+            );
+
+            let abi = String::from_utf8(abi).context("Parsing contract ABI from sequencer")?;
+            let bytecode = serde_json::from_slice::<Vec<ByteCodeWord>>(&bytecode)
+                .context("Parsing contract bytecode from sequencer")?;
+
+            Ok(ContractCode { abi, bytecode })
+        });
+
+        jh.await
+            .context("Parsing panic or shutting down")
+            .and_then(|x| x)
+            .map_err(internal_server_error)
+    }
+
+    /// Get the parsed ABI of a contract at a specific block.
+    /// `block_hash` is the [Hash](crate::rpc::types::BlockHashOrTag::Hash) or [Tag](crate::rpc::types::BlockHashOrTag::Tag)
+    /// of the requested block.
+    pub async fn get_abi(
+        &self,
+        contract_address: ContractAddress,
+        block_hash: BlockHashOrTag,
+    ) -> RpcResult<ContractAbi> {
+        use crate::state::state_tree::GlobalStateTree;
+
         let block_id = match block_hash {
             BlockHashOrTag::Hash(hash) => hash.into(),
-            BlockHashOrTag::Tag(Tag::Latest) => StarknetBlocksBlockId::Latest,
+            BlockHashOrTag::Tag(Tag::Latest) => self.resolve_latest().await?,
             BlockHashOrTag::Tag(Tag::Pending) => {
-                let block = self
-                    .sequencer
-                    .block_by_hash(block_hash)
+                self.ensure_pending_enabled()?;
+                let definition = self
+                    .fallback_to_sequencer("starknet_getAbi")
+                    .full_contract(contract_address)
                     .await
-                    .context("Fetch block from sequencer")
+                    .context("Fetch contract from sequencer")
                     .map_err(internal_server_error)?;
 
-                let len: u64 =
-                    block.transactions.len().try_into().map_err(|e| {
-                        Error::Call(CallError::InvalidParams(anyhow::Error::new(e)))
-                    })?;
+                #[derive(serde::Deserialize)]
+                struct Definition {
+                    abi: Box<serde_json::value::RawValue>,
+                }
+
+                let definition = serde_json::from_slice::<Definition>(&definition)
+                    .context("Parsing contract definition")
+                    .map_err(internal_server_error)?;
 
-                return Ok(len);
+                return ContractAbi::from_json(definition.abi.get())
+                    .context("Parsing contract ABI")
+                    .map_err(internal_server_error);
             }
         };
 
         let storage = self.storage.clone();
+        let node_cache = self.node_cache.clone();
+        let max_decompressed_size = self.max_decompressed_size;
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
             let tx = db
@@ -759,24 +1669,36 @@ impl RpcApi {
                 .context("Creating database transaction")
                 .map_err(internal_server_error)?;
 
-            match StarknetTransactionsTable::get_transaction_count(&tx, block_id)
-                .context("Reading transaction count from database")
+            let global_root = StarknetBlocksTable::get_root(&tx, block_id)
                 .map_err(internal_server_error)?
-            {
-                0 => {
-                    // We need to check if the value was 0 because there were no transactions, or because the block hash
-                    // is invalid.
-                    //
-                    // get_root is cheaper than querying the full block.
-                    match StarknetBlocksTable::get_root(&tx, block_id)
-                        .context("Reading block from database")?
-                    {
-                        Some(_) => Ok(0),
-                        None => Err(ErrorCode::InvalidBlockHash.into()),
-                    }
-                }
-                other => Ok(other as u64),
+                .ok_or_else(|| Error::from(RpcError::InvalidBlockHash))?;
+
+            let global_state_tree = GlobalStateTree::load_with_cache(&tx, global_root, &node_cache)
+                .context("Global state tree")
+                .map_err(internal_server_error)?;
+
+            let contract_state_hash = global_state_tree
+                .get(contract_address)
+                .context("Get contract state hash from global state tree")
+                .map_err(internal_server_error)?;
+
+            // The contract was not yet deployed at this block.
+            if contract_state_hash.0 == StarkHash::ZERO {
+                return Err(Error::from(RpcError::ContractNotFound));
             }
+
+            let code = ContractCodeTable::get_code_with_limit(
+                &tx,
+                contract_address,
+                max_decompressed_size,
+            )
+            .context("Fetching code from database")
+            .map_err(internal_server_error)?
+            .ok_or_else(|| Error::from(RpcError::ContractNotFound))?;
+
+            ContractAbi::from_json(&code.abi)
+                .context("Parsing contract ABI")
+                .map_err(internal_server_error)
         });
 
         jh.await
@@ -785,38 +1707,79 @@ impl RpcApi {
             .and_then(|x| x)
     }
 
+    /// Returns true if the contract class with the given hash is stored locally.
+    ///
+    /// This is cheaper than fetching the class, as its stored data is not decompressed.
+    pub async fn class_exists(&self, class_hash: ContractHash) -> RpcResult<bool> {
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let db = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            ContractCodeTable::exists(&db, &[class_hash])
+                .context("Querying contract existence")
+                .map_err(internal_server_error)
+        });
+
+        let exists = jh
+            .await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)?;
+
+        Ok(exists.first().copied().unwrap_or_default())
+    }
+
     /// Get the number of transactions in a block given a block hash.
-    /// `block_hash` is the [Hash](crate::rpc::types::BlockHashOrTag::Hash) or [Tag](crate::rpc::types::BlockHashOrTag::Tag)
-    /// of the requested block.
-    pub async fn get_block_transaction_count_by_number(
+    /// `block_id` is the [Hash](crate::rpc::types::BlockId::Hash), [Number](crate::rpc::types::BlockId::Number)
+    /// or [Tag](crate::rpc::types::BlockId::Tag) of the requested block.
+    pub async fn get_block_transaction_count_by_hash(&self, block_id: BlockId) -> RpcResult<u64> {
+        self.get_block_transaction_count(block_id, "starknet_getBlockTransactionCountByHash")
+            .await
+    }
+
+    /// Get the number of transactions in a block given a block number.
+    /// `block_id` is the [Hash](crate::rpc::types::BlockId::Hash), [Number](crate::rpc::types::BlockId::Number)
+    /// or [Tag](crate::rpc::types::BlockId::Tag) of the requested block.
+    pub async fn get_block_transaction_count_by_number(&self, block_id: BlockId) -> RpcResult<u64> {
+        self.get_block_transaction_count(block_id, "starknet_getBlockTransactionCountByNumber")
+            .await
+    }
+
+    /// Shared implementation of the block transaction count methods.
+    async fn get_block_transaction_count(
         &self,
-        block_number: BlockNumberOrTag,
+        block_id: BlockId,
+        method: &'static str,
     ) -> RpcResult<u64> {
-        let block_id = match block_number {
-            BlockNumberOrTag::Number(number) => number.into(),
-            BlockNumberOrTag::Tag(Tag::Latest) => StarknetBlocksBlockId::Latest,
-            BlockNumberOrTag::Tag(Tag::Pending) => {
-                let block = self
-                    .sequencer
-                    .block_by_number(block_number)
-                    .await
-                    .context("Fetch block from sequencer")
-                    .map_err(internal_server_error)?;
+        if block_id == BlockId::Tag(Tag::Pending) {
+            self.ensure_pending_enabled()?;
+            let block = self
+                .fallback_to_sequencer(method)
+                .block_by_hash(BlockHashOrTag::Tag(Tag::Pending))
+                .await
+                .context("Fetch block from sequencer")
+                .map_err(internal_server_error)?;
 
-                let len: u64 =
-                    block.transactions.len().try_into().map_err(|e| {
-                        Error::Call(CallError::InvalidParams(anyhow::Error::new(e)))
-                    })?;
+            let len: u64 = block
+                .transactions
+                .len()
+                .try_into()
+                .map_err(|e| Error::Call(CallError::InvalidParams(anyhow::Error::new(e))))?;
 
-                return Ok(len);
-            }
-        };
+            return Ok(len);
+        }
+
+        let (block_number, _) = self.resolve_block(method, block_id).await?;
 
         let storage = self.storage.clone();
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
             let tx = db
@@ -824,24 +1787,10 @@ impl RpcApi {
                 .context("Creating database transaction")
                 .map_err(internal_server_error)?;
 
-            match StarknetTransactionsTable::get_transaction_count(&tx, block_id)
+            StarknetTransactionsTable::get_transaction_count(&tx, block_number.into())
                 .context("Reading transaction count from database")
-                .map_err(internal_server_error)?
-            {
-                0 => {
-                    // We need to check if the value was 0 because there were no transactions, or because the block number
-                    // is invalid.
-                    //
-                    // get_root is cheaper than querying the full block.
-                    match StarknetBlocksTable::get_root(&tx, block_id)
-                        .context("Reading block from database")?
-                    {
-                        Some(_) => Ok(0),
-                        None => Err(ErrorCode::InvalidBlockNumber.into()),
-                    }
-                }
-                other => Ok(other as u64),
-            }
+                .map_err(internal_server_error)
+                .map(|count| count as u64)
         });
 
         jh.await
@@ -851,34 +1800,285 @@ impl RpcApi {
     }
 
     /// Call a starknet function without creating a StarkNet transaction.
-    /// `block_hash` is the [Hash](crate::rpc::types::BlockHashOrTag::Hash) or [Tag](crate::rpc::types::BlockHashOrTag::Tag)
-    /// of the requested block.
-    pub async fn call(
-        &self,
-        request: Call,
-        block_hash: BlockHashOrTag,
-    ) -> RpcResult<Vec<CallResultValue>> {
+    /// `block_id` is the [Hash](crate::rpc::types::BlockId::Hash), [Number](crate::rpc::types::BlockId::Number)
+    /// or [Tag](crate::rpc::types::BlockId::Tag) of the requested block.
+    pub async fn call(&self, request: Call, block_id: BlockId) -> RpcResult<Vec<CallResultValue>> {
         use futures::future::TryFutureExt;
 
+        reject_zero_address(request.contract_address)?;
+
+        // Without calldata the call can only fail as an arity mismatch, which we report more
+        // clearly than the executor does if the contract's abi is available.
+        if request.calldata.is_empty() {
+            let expected = self
+                .min_calldata_len(request.contract_address, request.entry_point_selector)
+                .await?;
+            if let Some(expected) = expected.filter(|&expected| expected > 0) {
+                return Err(missing_calldata(expected));
+            }
+        }
+
+        let block_id = match block_id {
+            BlockId::Tag(Tag::Pending) => match self.resolve_pending("starknet_call").await? {
+                Some(_) => block_id,
+                None => BlockId::Tag(Tag::Latest),
+            },
+            other => other,
+        };
+
         match self.call_handle.as_ref() {
-            Some(h) => h.call(request, block_hash).map_err(Error::from).await,
+            Some(h) if block_id == BlockId::Tag(Tag::Pending) => {
+                // The pending block is not stored locally, so the call is executed on the latest
+                // block with the pending state diff applied on top. The pending block builds on
+                // the sequencer's latest block, which we may not have synced yet.
+                let pending_state_diff = self
+                    .sequencer
+                    .state_update_by_hash(BlockHashOrTag::Tag(Tag::Pending))
+                    .await
+                    .map_err(Error::from)?
+                    .state_diff;
+
+                h.call(request, BlockId::Tag(Tag::Latest), Some(pending_state_diff))
+                    .map_err(Error::from)
+                    .await
+            }
+            Some(h) => {
+                // Resolving the block up front pins `latest`, and reports unknown blocks
+                // without involving the python process.
+                let (block_number, _) = self.resolve_block("starknet_call", block_id).await?;
+
+                h.call(request, BlockId::Number(block_number), None)
+                    .map_err(Error::from)
+                    .await
+            }
             None => {
-                self.sequencer
-                    .call(request.into(), block_hash)
+                self.fallback_to_sequencer("starknet_call")
+                    .call(request.into(), block_id)
                     .map_ok(|x| x.result)
-                    .map_err(Error::from)
+                    .map_err(sequencer_call_error)
                     .await
             }
         }
     }
 
+    /// The minimum number of calldata values the entry point of the contract expects, or [None]
+    /// if the contract's abi is not available locally or does not tell.
+    async fn min_calldata_len(
+        &self,
+        contract_address: ContractAddress,
+        entry_point: EntryPoint,
+    ) -> RpcResult<Option<usize>> {
+        let storage = self.storage.clone();
+        let max_decompressed_size = self.max_decompressed_size;
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+            let tx = db
+                .transaction()
+                .context("Creating database transaction")
+                .map_err(internal_server_error)?;
+
+            ContractCodeTable::get_code_with_limit(&tx, contract_address, max_decompressed_size)
+                .context("Fetching code from database")
+                .map_err(internal_server_error)
+        });
+
+        let code = jh
+            .await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)?;
+
+        Ok(code.and_then(|code| abi_min_calldata_len(&code.abi, entry_point)))
+    }
+
+    /// Executes the `calls` in order on the state at `block_id`, each call seeing the state
+    /// changes of the calls before it. Nothing is persisted.
+    ///
+    /// Returns the result of each call. A failing call aborts the simulation, and the error
+    /// carries the index of the call and the reason it failed.
+    ///
+    /// This requires the python call executors, the sequencer offers no equivalent.
+    pub async fn simulate_calls(
+        &self,
+        calls: Vec<Call>,
+        block_id: BlockId,
+    ) -> RpcResult<Vec<Vec<CallResultValue>>> {
+        use futures::future::TryFutureExt;
+
+        if calls.is_empty() {
+            return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                "At least one call is required"
+            ))));
+        }
+
+        let handle = self.call_handle.as_ref().ok_or_else(|| {
+            Error::Call(CallError::Failed(anyhow::anyhow!(
+                "Simulating calls is not available on this node"
+            )))
+        })?;
+
+        let (block_id, pending_state_diff) = match block_id {
+            BlockId::Tag(Tag::Pending) => {
+                match self.resolve_pending("starknet_simulateCalls").await? {
+                    Some(_) => {
+                        // Same as in `call`, the pending state diff is applied on top of latest.
+                        let pending_state_diff = self
+                            .sequencer
+                            .state_update_by_hash(BlockHashOrTag::Tag(Tag::Pending))
+                            .await
+                            .map_err(Error::from)?
+                            .state_diff;
+                        (BlockId::Tag(Tag::Latest), Some(pending_state_diff))
+                    }
+                    None => (BlockId::Tag(Tag::Latest), None),
+                }
+            }
+            other => (other, None),
+        };
+
+        let block_id = match pending_state_diff {
+            Some(_) => block_id,
+            None => BlockId::Number(
+                self.resolve_block("starknet_simulateCalls", block_id)
+                    .await?
+                    .0,
+            ),
+        };
+
+        handle
+            .simulate_calls(calls, block_id, pending_state_diff)
+            .map_err(|e| match e {
+                ext_py::CallFailure::ExecutionFailed {
+                    exception,
+                    revert_reason,
+                    call_index: Some(call_index),
+                } => simulated_call_failed(call_index, revert_reason.unwrap_or(exception)),
+                other => Error::from(other),
+            })
+            .await
+    }
+
+    /// Re-executes the invoke transaction with `transaction_hash` on the state of the block before
+    /// its own, and returns the trace of its calls.
+    ///
+    /// The transactions before it in its own block are not applied first, so a transaction which
+    /// depends on them may be traced differently than it was executed.
+    ///
+    /// This requires the python call executors, the sequencer offers no equivalent.
+    pub async fn trace_transaction(
+        &self,
+        transaction_hash: StarknetTransactionHash,
+    ) -> RpcResult<CallTrace> {
+        use crate::sequencer::reply::transaction::Type;
+        use futures::future::TryFutureExt;
+
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+            let tx = db
+                .transaction()
+                .context("Creating database transaction")
+                .map_err(internal_server_error)?;
+
+            let transaction = StarknetTransactionsTable::get_transaction(&tx, transaction_hash)
+                .context("Reading transaction from database")
+                .map_err(internal_server_error)?;
+            let receipt = StarknetTransactionsTable::get_receipt(&tx, transaction_hash)
+                .context("Reading transaction receipt from database")
+                .map_err(internal_server_error)?;
+            let (transaction, block_hash) = match (transaction, receipt) {
+                (Some(transaction), Some((_, block_hash))) => (transaction, block_hash),
+                _ => return Err(Error::from(RpcError::InvalidTransactionHash)),
+            };
+
+            let block = StarknetBlocksTable::get(&tx, StarknetBlocksBlockId::Hash(block_hash))
+                .context("Reading block from database")
+                .map_err(internal_server_error)?
+                .context("Block of the transaction is missing")
+                .map_err(internal_server_error)?;
+
+            Ok((transaction, block.number))
+        });
+
+        let (transaction, block_number) = jh
+            .await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)?;
+
+        if transaction.r#type != Type::InvokeFunction {
+            return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                "Only invoke transactions can be traced"
+            ))));
+        }
+
+        let entry_point_selector = transaction
+            .entry_point_selector
+            .context("Invoke transaction is missing the entry point selector")
+            .map_err(internal_server_error)?;
+        let call = Call {
+            contract_address: transaction.contract_address,
+            calldata: transaction.calldata.unwrap_or_default(),
+            entry_point_selector,
+            sender_address: None,
+        };
+        let signature = transaction.signature.unwrap_or_default();
+
+        let parent = block_number.0.checked_sub(1).ok_or_else(|| {
+            Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                "Transactions of the genesis block cannot be traced"
+            )))
+        })?;
+
+        let handle = self.call_handle.as_ref().ok_or_else(|| {
+            Error::Call(CallError::Failed(anyhow::anyhow!(
+                "Tracing transactions is not available on this node"
+            )))
+        })?;
+
+        handle
+            .trace_call(
+                call,
+                signature,
+                BlockId::Number(StarknetBlockNumber(parent)),
+            )
+            .map_err(Error::from)
+            .await
+    }
+
     /// Get the most recent accepted block number.
     pub async fn block_number(&self) -> RpcResult<u64> {
+        self.latest_block()
+            .await?
+            .map(|latest| latest.number.0)
+            .ok_or_else(|| internal_server_error("Database is empty"))
+    }
+
+    /// Get the numbers and hashes of the `n` most recent blocks, starting from the latest block.
+    ///
+    /// Intended for clients tracking the chain tip, which can compare these against previously
+    /// seen hashes to detect reorgs. `n` is capped by [RpcApi::with_max_recent_block_hashes].
+    pub async fn get_recent_block_hashes(&self, n: usize) -> RpcResult<Vec<BlockHashAndNumber>> {
+        if n > self.max_recent_block_hashes {
+            return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                "Too many block hashes requested, supported maximum is {}",
+                self.max_recent_block_hashes
+            ))));
+        }
+
         let storage = self.storage.clone();
 
         let jh = tokio::task::spawn_blocking(move || {
             let mut db = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
             let tx = db
@@ -886,12 +2086,18 @@ impl RpcApi {
                 .context("Creating database transaction")
                 .map_err(internal_server_error)?;
 
-            StarknetBlocksTable::get_latest_number(&tx)
-                .context("Reading latest block number from database")
-                .map_err(internal_server_error)?
-                .context("Database is empty")
+            StarknetBlocksTable::get_latest_hashes(&tx, n)
+                .context("Reading latest block hashes from database")
                 .map_err(internal_server_error)
-                .map(|number| number.0)
+                .map(|hashes| {
+                    hashes
+                        .into_iter()
+                        .map(|(block_number, block_hash)| BlockHashAndNumber {
+                            block_number,
+                            block_hash,
+                        })
+                        .collect()
+                })
         });
 
         jh.await
@@ -901,6 +2107,8 @@ impl RpcApi {
     }
 
     /// Return the currently configured StarkNet chain id.
+    ///
+    /// The value is resolved once on construction, so no work is done per request.
     pub async fn chain_id(&self) -> RpcResult<&'static str> {
         Ok(self.chain_id)
     }
@@ -922,96 +2130,601 @@ impl RpcApi {
         Ok(value)
     }
 
+    /// Returns how far the node is behind the latest block and how fast it is catching up.
+    pub async fn sync_stats(&self) -> RpcResult<SyncStats> {
+        let (current, highest) = match *self.sync_state.status.read().await {
+            Syncing::Status(status) => (
+                Some(status.current_block_num),
+                Some(status.highest_block_num),
+            ),
+            Syncing::False(_) => (None, None),
+        };
+        let blocks_per_second = self.sync_state.sync_rate.read().await.blocks_per_second();
+
+        let blocks_behind = current
+            .zip(highest)
+            .map(|(current, highest)| highest.0.saturating_sub(current.0));
+        let estimated_seconds_remaining = blocks_behind
+            .zip(blocks_per_second)
+            .filter(|(_, rate)| *rate > 0.0)
+            .map(|(blocks_behind, rate)| blocks_behind as f64 / rate);
+
+        Ok(SyncStats {
+            current_block_num: current,
+            highest_block_num: highest,
+            blocks_behind,
+            blocks_per_second,
+            estimated_seconds_remaining,
+        })
+    }
+
+    /// Returns the highest block number which has been verified on Ethereum,
+    /// or null if no block has been verified yet.
+    pub async fn l1_confirmed_block(&self) -> RpcResult<Option<StarknetBlockNumber>> {
+        let value = *self.sync_state.l1_confirmed.read().await;
+        Ok(value)
+    }
+
+    /// Returns the number of events in a block, grouped by the first key of the event.
+    ///
+    /// Events without any keys are not counted.
+    pub async fn get_event_key_stats(
+        &self,
+        block_number: BlockNumberOrTag,
+    ) -> RpcResult<BTreeMap<EventKey, u64>> {
+        let block_number = match block_number {
+            BlockNumberOrTag::Number(number) => Some(number),
+            BlockNumberOrTag::Tag(Tag::Latest) => None,
+            BlockNumberOrTag::Tag(Tag::Pending) => {
+                match self.resolve_pending("starknet_getEventKeyStats").await? {
+                    Some(block) => {
+                        let mut counts = BTreeMap::new();
+                        block
+                            .transaction_receipts
+                            .iter()
+                            .flat_map(|receipt| receipt.events.iter())
+                            .filter_map(|event| event.keys.first())
+                            .for_each(|key| *counts.entry(*key).or_default() += 1);
+
+                        return Ok(counts);
+                    }
+                    None => None,
+                }
+            }
+        };
+
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let mut db = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+            let tx = db
+                .transaction()
+                .context("Creating database transaction")
+                .map_err(internal_server_error)?;
+
+            let block_number = match block_number {
+                Some(number) => StarknetBlocksTable::get_root(&tx, number.into())
+                    .context("Reading block from database")
+                    .map_err(internal_server_error)?
+                    .map(|_| number),
+                None => StarknetBlocksTable::get_latest_number(&tx)
+                    .context("Reading latest block number from database")
+                    .map_err(internal_server_error)?,
+            }
+            .ok_or(RpcError::InvalidBlockNumber)?;
+
+            StarknetEventsTable::get_key0_counts(&tx, block_number)
+                .context("Reading event key counts from database")
+                .map_err(internal_server_error)
+                .map(|counts| counts.into_iter().collect())
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
+    }
+
     /// Returns events matching the specified filter
-    pub async fn get_events(&self, request: EventFilter) -> RpcResult<GetEventsResult> {
+    pub async fn get_events(&self, mut request: EventFilter) -> RpcResult<GetEventsResult> {
+        // Applied before the cache lookup, so that omitting the page size hits the same results
+        // as specifying the default.
+        request
+            .page_size
+            .get_or_insert(self.default_event_page_size);
+
+        if request.keys.len() > self.max_event_filter_keys {
+            return Err(EventFilterError::TooManyKeys(self.max_event_filter_keys).into());
+        }
+
+        // Would match no blocks at all, which most likely means that the timestamp is given in
+        // milliseconds rather than seconds.
+        for timestamp in [request.from_timestamp, request.to_timestamp]
+            .into_iter()
+            .flatten()
+        {
+            if timestamp > StarknetBlockTimestamp::MAX {
+                return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                    "Timestamp {} is out of range, timestamps are in seconds since the Unix epoch",
+                    timestamp.as_secs()
+                ))));
+            }
+        }
+
+        let cache_miss = match self.events_cache.get(&request) {
+            Ok(cached) => return Ok(cached),
+            Err(miss) => miss,
+        };
+
+        // Events of the pending block are not indexed, they are taken from the sequencer instead.
+        let pending_events = match request.to_block {
+            Some(BlockNumberOrTag::Tag(Tag::Pending)) => self
+                .resolve_pending("starknet_getEvents")
+                .await?
+                .map(|block| pending_events(block, &request))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
         let storage = self.storage.clone();
+        let threshold = self.event_query_log_threshold;
+        let soft_deadline = self.event_soft_deadline;
+        // Held until the query completes, even if this request is dropped in the meantime.
+        let permit = self
+            .event_queries
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Semaphore is never closed");
 
         let jh = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
             let connection = storage
-                .connection()
+                .read_connection()
                 .context("Opening database connection")
                 .map_err(internal_server_error)?;
 
-            let filter = request.into();
-            // We don't add context here, because [StarknetEventsTable::get_events] adds its
-            // own context to the errors. This way we get meaningful error information
-            // for errors related to query parameters.
-            let page = StarknetEventsTable::get_events(&connection, &filter).map_err(|e| {
+            request.from_block = resolve_event_filter_bound(
+                &connection,
+                request.from_block,
+                request.from_block_hash,
+            )?;
+            let to_block = match request.to_block {
+                Some(BlockNumberOrTag::Number(number)) => Some(number),
+                Some(BlockNumberOrTag::Tag(_)) | None => None,
+            };
+            let to_block =
+                resolve_event_filter_bound(&connection, to_block, request.to_block_hash)?;
+            if let (Some(from_block), Some(to_block)) = (request.from_block, to_block) {
+                if from_block > to_block {
+                    return Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+                        "Block range starts at block {} after it ends at block {}",
+                        from_block.0,
+                        to_block.0
+                    ))));
+                }
+            }
+            request.to_block = to_block.map(BlockNumberOrTag::Number);
+
+            let include_count = request.include_count;
+            let filter: StarknetEventFilter = request.into();
+            let deadline =
+                soft_deadline.map(|soft_deadline| std::time::Instant::now() + soft_deadline);
+            let map_filter_error = |e: anyhow::Error| {
                 if let Some(e) = e.downcast_ref::<EventFilterError>() {
                     Error::from(*e)
                 } else {
                     internal_server_error(e)
                 }
-            })?;
+            };
+            // We don't add context here, because [StarknetEventsTable::get_events] adds its
+            // own context to the errors. This way we get meaningful error information
+            // for errors related to query parameters.
+            let page = StarknetEventsTable::time_query("get_events", &filter, threshold, || {
+                StarknetEventsTable::get_events_until(&connection, &filter, deadline)
+            })
+            .map_err(map_filter_error)?;
+
+            let mut events: Vec<EmittedEvent> = page.events.into_iter().map(|e| e.into()).collect();
+            let mut is_last_page = page.is_last_page;
+
+            // Pending events follow all indexed events, so they only show up once the indexed
+            // events run out.
+            let merge_pending = is_last_page && !pending_events.is_empty();
+            let indexed_count = if merge_pending || include_count {
+                let count =
+                    StarknetEventsTable::time_query("event_count", &filter, threshold, || {
+                        StarknetEventsTable::event_count(&connection, &filter)
+                    })
+                    .map_err(map_filter_error)?;
+                Some(count)
+            } else {
+                None
+            };
+
+            if let (true, Some(indexed_count)) = (merge_pending, indexed_count) {
+                let page_start = filter.offset();
+                let page_end = page_start + filter.page_size;
+                let pending_start = page_start
+                    .saturating_sub(indexed_count)
+                    .min(pending_events.len());
+                let pending_end = page_end
+                    .saturating_sub(indexed_count)
+                    .min(pending_events.len());
+
+                events.extend_from_slice(&pending_events[pending_start..pending_end]);
+                is_last_page = indexed_count + pending_events.len() <= page_end;
+            }
+
+            let total_count = indexed_count
+                .filter(|_| include_count)
+                .map(|indexed_count| indexed_count + pending_events.len());
+            // The page size is validated to be non-zero by the events query.
+            let total_pages =
+                total_count.map(|count| (count + filter.page_size - 1) / filter.page_size);
 
             Ok(GetEventsResult {
-                events: page.events.into_iter().map(|e| e.into()).collect(),
+                events,
                 page_number: filter.page_number,
-                is_last_page: page.is_last_page,
+                is_last_page,
+                continuation_token: page.continuation_token,
+                total_count,
+                total_pages,
             })
         });
 
-        jh.await
+        let result = jh
+            .await
             .context("Database read panic or shutting down")
             .map_err(internal_server_error)
             // flatten is unstable
+            .and_then(|x| x)?;
+
+        // Partial pages depend on how long the query took, not only on the filter.
+        if let (Some(miss), None) = (cache_miss, result.continuation_token) {
+            self.events_cache.insert(miss, result.clone());
+        }
+        Ok(result)
+    }
+
+    /// Returns the events emitted by a transaction, in the order in which they were emitted.
+    pub async fn get_transaction_events(
+        &self,
+        transaction_hash: StarknetTransactionHash,
+    ) -> RpcResult<Vec<EmittedEvent>> {
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let connection = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            StarknetEventsTable::get_transaction_events(&connection, transaction_hash)
+                .context("Reading transaction events from database")
+                .map_err(internal_server_error)?
+                .map(|events| events.into_iter().map(|e| e.into()).collect())
+                .ok_or_else(|| RpcError::InvalidTransactionHash.into())
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
+    }
+
+    /// Returns the index of a transaction within its block, along with the block's hash.
+    pub async fn get_transaction_index(
+        &self,
+        transaction_hash: StarknetTransactionHash,
+    ) -> RpcResult<TransactionIndex> {
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let connection = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            StarknetTransactionsTable::get_transaction_index(&connection, transaction_hash)
+                .context("Reading transaction index from database")
+                .map_err(internal_server_error)?
+                .map(|(transaction_index, block_hash)| TransactionIndex {
+                    block_hash,
+                    transaction_index,
+                })
+                .ok_or_else(|| RpcError::InvalidTransactionHash.into())
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
+    }
+
+    /// Returns the L1-to-L2 message consumed by and the L2-to-L1 messages sent by a transaction.
+    pub async fn get_messages_by_transaction(
+        &self,
+        transaction_hash: StarknetTransactionHash,
+    ) -> RpcResult<TransactionMessages> {
+        let storage = self.storage.clone();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let connection = storage
+                .read_connection()
+                .context("Opening database connection")
+                .map_err(internal_server_error)?;
+
+            let (consumed, sent) =
+                StarknetMessagesTable::get_transaction_messages(&connection, transaction_hash)
+                    .context("Reading transaction messages from database")
+                    .map_err(internal_server_error)?
+                    .ok_or_else(|| Error::from(RpcError::InvalidTransactionHash))?;
+
+            Ok(TransactionMessages {
+                l1_to_l2_consumed_message: consumed.map(Into::into),
+                l2_to_l1_messages: sent.into_iter().map(Into::into).collect(),
+            })
+        });
+
+        jh.await
+            .context("Database read panic or shutting down")
+            .map_err(internal_server_error)
             .and_then(|x| x)
     }
 
     /// Submit a new transaction to be added to the chain.
     ///
     /// This method just forwards the request received over the JSON-RPC
-    /// interface to the sequencer.
+    /// interface to the sequencer. Failed requests are retried without adding the
+    /// transaction twice, see [sequencer::retry_add_transaction].
+    ///
+    /// If `dry_run` is set, the transaction is __not__ forwarded to the sequencer.
+    /// Instead, it is executed with its signature on the latest block without persisting
+    /// the result, and its hash is computed locally and returned.
     pub async fn add_invoke_transaction(
         &self,
         call: Call,
         signature: Vec<CallSignatureElem>,
         max_fee: Fee,
         version: TransactionVersion,
+        dry_run: bool,
     ) -> RpcResult<InvokeTransactionResult> {
+        use futures::future::TryFutureExt;
+
+        if dry_run {
+            return self
+                .dry_run_invoke_transaction(call, signature, max_fee, version)
+                .await;
+        }
+
         let mut call: sequencer::request::Call = call.into();
         call.signature = signature;
 
-        let result = self
-            .sequencer
-            .add_invoke_transaction(call, max_fee, version)
-            .await?;
-        Ok(InvokeTransactionResult {
-            transaction_hash: result.transaction_hash,
-        })
+        let sequencer = self.fallback_to_sequencer("starknet_addInvokeTransaction");
+        let forwarded = call.clone();
+        let result = sequencer::retry_add_transaction(
+            move || {
+                sequencer
+                    .add_invoke_transaction(forwarded.clone(), max_fee, version)
+                    .map_ok(|result| {
+                        Some(InvokeTransactionResult {
+                            transaction_hash: result.transaction_hash,
+                        })
+                    })
+            },
+            || None,
+        )
+        .await?;
+
+        match result {
+            Some(result) => Ok(result),
+            // An earlier attempt got through, the sequencer does not repeat its result.
+            None => Ok(InvokeTransactionResult {
+                transaction_hash: self.invoke_transaction_hash(call, max_fee, version)?,
+            }),
+        }
+    }
+
+    /// Executes the invoke transaction of `call` on the latest block without persisting the
+    /// result, and computes its hash locally.
+    ///
+    /// Executing the call with its signature runs the signature check of the called account
+    /// contract. This uses the python call executors if available, and the sequencer's
+    /// `call_contract` otherwise, neither of which adds the transaction.
+    async fn dry_run_invoke_transaction(
+        &self,
+        call: Call,
+        signature: Vec<CallSignatureElem>,
+        max_fee: Fee,
+        version: TransactionVersion,
+    ) -> RpcResult<InvokeTransactionResult> {
+        use futures::future::TryFutureExt;
+
+        reject_zero_address(call.contract_address)?;
+
+        let mut sequencer_call: sequencer::request::Call = call.clone().into();
+        sequencer_call.signature = signature;
+        let transaction_hash =
+            self.invoke_transaction_hash(sequencer_call.clone(), max_fee, version)?;
+
+        match self.call_handle.as_ref() {
+            Some(h) => {
+                let (block_number, _) = self
+                    .resolve_block("starknet_addInvokeTransaction", BlockId::Tag(Tag::Latest))
+                    .await?;
+                let signature = sequencer_call
+                    .signature
+                    .iter()
+                    .map(|elem| TransactionSignatureElem(elem.0))
+                    .collect();
+
+                // Executing with a signature traces the call as well, the trace is not needed.
+                h.trace_call(call, signature, BlockId::Number(block_number))
+                    .map_err(Error::from)
+                    .await?;
+            }
+            None => {
+                self.fallback_to_sequencer("starknet_addInvokeTransaction")
+                    .call(sequencer_call, BlockId::Tag(Tag::Latest))
+                    .map_err(sequencer_call_error)
+                    .await?;
+            }
+        }
+
+        Ok(InvokeTransactionResult { transaction_hash })
+    }
+
+    /// Computes the hash of the invoke transaction of `call` locally.
+    fn invoke_transaction_hash(
+        &self,
+        call: sequencer::request::Call,
+        max_fee: Fee,
+        version: TransactionVersion,
+    ) -> RpcResult<StarknetTransactionHash> {
+        let invoke = sequencer::request::add_transaction::InvokeFunction {
+            contract_address: call.contract_address,
+            entry_point_selector: call.entry_point_selector,
+            calldata: call.calldata,
+            max_fee,
+            version,
+            signature: call.signature,
+        };
+
+        invoke
+            .transaction_hash(self.chain_id_felt())
+            .map_err(|e| Error::Call(CallError::InvalidParams(e)))
     }
 
     /// Submit a new deploy contract transaction.
     ///
     /// This method just forwards the request received over the JSON-RPC
-    /// interface to the sequencer.
+    /// interface to the sequencer. Failed requests are retried without adding the
+    /// transaction twice, see [sequencer::retry_add_transaction].
+    ///
+    /// If `dry_run` is set, the transaction is __not__ forwarded to the sequencer.
+    /// Instead, only the contract address and transaction hash are computed locally
+    /// and returned. The transaction is not otherwise validated or executed.
     pub async fn add_deploy_transaction(
         &self,
         contract_address_salt: ContractAddressSalt,
         constructor_calldata: Vec<ConstructorParam>,
         contract_definition: ContractDefinition,
+        dry_run: bool,
     ) -> RpcResult<DeployTransactionResult> {
-        let result = self
-            .sequencer
-            .add_deploy_transaction(
-                contract_address_salt,
-                constructor_calldata,
-                contract_definition,
-            )
-            .await?;
-        Ok(DeployTransactionResult {
-            transaction_hash: result.transaction_hash,
-            contract_address: result.address,
-        })
+        use futures::future::TryFutureExt;
+
+        let deploy = sequencer::request::add_transaction::Deploy {
+            contract_address_salt,
+            contract_definition,
+            constructor_calldata,
+        };
+
+        if dry_run {
+            return self.compute_deploy_result(deploy).await;
+        }
+
+        let sequencer = self.fallback_to_sequencer("starknet_addDeployTransaction");
+        let forwarded = deploy.clone();
+        let result = sequencer::retry_add_transaction(
+            move || {
+                sequencer
+                    .add_deploy_transaction(
+                        forwarded.contract_address_salt,
+                        forwarded.constructor_calldata.clone(),
+                        forwarded.contract_definition.clone(),
+                    )
+                    .map_ok(|result| {
+                        Some(DeployTransactionResult {
+                            transaction_hash: result.transaction_hash,
+                            contract_address: result.address,
+                        })
+                    })
+            },
+            || None,
+        )
+        .await?;
+
+        match result {
+            Some(result) => Ok(result),
+            // An earlier attempt got through, the sequencer does not repeat its result.
+            None => self.compute_deploy_result(deploy).await,
+        }
+    }
+
+    /// Computes the contract address and transaction hash of `deploy` locally.
+    async fn compute_deploy_result(
+        &self,
+        deploy: sequencer::request::add_transaction::Deploy,
+    ) -> RpcResult<DeployTransactionResult> {
+        let chain_id = self.chain_id_felt();
+
+        let jh = tokio::task::spawn_blocking(move || {
+            let contract_address = deploy
+                .contract_address()
+                .map_err(|_| Error::from(RpcError::InvalidContractDefinition))?;
+            let transaction_hash = deploy.transaction_hash(contract_address, chain_id);
+
+            Ok(DeployTransactionResult {
+                transaction_hash,
+                contract_address,
+            })
+        });
+
+        jh.await
+            .context("Contract hash computation panic or shutting down")
+            .map_err(internal_server_error)
+            .and_then(|x| x)
+    }
+
+    /// Computes the address a contract of class `class_hash` would be deployed at.
+    ///
+    /// `deployer_address` defaults to zero, which is the deployer of deploy transactions.
+    pub async fn compute_contract_address(
+        &self,
+        salt: ContractAddressSalt,
+        class_hash: ContractHash,
+        constructor_calldata: Vec<ConstructorParam>,
+        deployer_address: Option<ContractAddress>,
+    ) -> RpcResult<ContractAddress> {
+        let deployer_address = deployer_address.unwrap_or(ContractAddress(StarkHash::ZERO));
+
+        Ok(crate::state::contract_hash::compute_contract_address(
+            salt,
+            class_hash,
+            &constructor_calldata,
+            deployer_address,
+        ))
+    }
+
+    /// The [chain id](RpcApi::chain_id) as a [StarkHash], as used in transaction hashes.
+    fn chain_id_felt(&self) -> StarkHash {
+        StarkHash::from_hex_str(self.chain_id).expect("Chain id is a valid StarkHash")
     }
 }
 
+/// The exception `cairo-lang` fails calls with which are reverted by the contract, as opposed
+/// to calls failing for other reasons, such as a missing entry point.
+const REVERTED_EXCEPTION: &str = "StarknetErrorCode.TRANSACTION_FAILED";
+
 impl From<ext_py::CallFailure> for jsonrpsee::types::Error {
     fn from(e: ext_py::CallFailure) -> Self {
         match e {
-            ext_py::CallFailure::NoSuchBlock => Error::from(ErrorCode::InvalidBlockHash),
-            ext_py::CallFailure::NoSuchContract => Error::from(ErrorCode::ContractNotFound),
-            ext_py::CallFailure::ExecutionFailed(e) => internal_server_error(e),
+            ext_py::CallFailure::NoSuchBlock => Error::from(RpcError::InvalidBlockHash),
+            ext_py::CallFailure::NoSuchContract => Error::from(RpcError::ContractNotFound),
+            ext_py::CallFailure::ExecutionFailed {
+                exception,
+                revert_reason: Some(revert_reason),
+                ..
+            } if exception == REVERTED_EXCEPTION => {
+                Error::from(RpcError::CallReverted { revert_reason })
+            }
+            ext_py::CallFailure::ExecutionFailed { exception, .. } => {
+                internal_server_error(exception)
+            }
             // Intentionally hide the message under Internal
             ext_py::CallFailure::Internal(_) | ext_py::CallFailure::Shutdown => {
                 static_internal_server_error()
@@ -1020,19 +2733,395 @@ impl From<ext_py::CallFailure> for jsonrpsee::types::Error {
     }
 }
 
+/// The minimum number of calldata values the function with the `entry_point` selector expects
+/// according to the contract `abi`.
+///
+/// Arrays are passed as their length followed by the elements, so they count for at least the
+/// length. Returns [None] if the function or the size of one of its inputs cannot be found.
+fn abi_min_calldata_len(abi: &str, entry_point: EntryPoint) -> Option<usize> {
+    let abi = serde_json::from_str::<Vec<serde_json::Value>>(abi).ok()?;
+
+    let function = abi.iter().find(|item| {
+        item["type"] == "function"
+            && item["name"]
+                .as_str()
+                .map(|name| EntryPoint::hashed(name.as_bytes()) == entry_point)
+                .unwrap_or(false)
+    })?;
+
+    function["inputs"]
+        .as_array()?
+        .iter()
+        .map(|input| match input["type"].as_str()? {
+            "felt" => Some(1),
+            // The elements follow the `_len` input, and there may be none of them.
+            pointer if pointer.ends_with('*') => Some(0),
+            name => abi
+                .iter()
+                .find(|item| item["type"] == "struct" && item["name"] == name)
+                .and_then(|item| item["size"].as_u64())
+                .map(|size| size as usize),
+        })
+        .sum()
+}
+
+/// Reads a [RawBlock] from storage.
+///
+/// `error_for_latest` is the error when the `latest` block is missing,
+/// ie. when the storage is empty.
+pub(super) fn read_raw_block(
+    transaction: &rusqlite::Transaction<'_>,
+    block_id: StarknetBlocksBlockId,
+    error_for_latest: RpcError,
+) -> RpcResult<RawBlock> {
+    let block = StarknetBlocksTable::get(transaction, block_id)
+        .context("Read block from database")
+        .map_err(internal_server_error)?
+        .ok_or_else(|| Error::from(error_for_latest))?;
+
+    // All our data is L2 accepted, check our L1-L2 head to see if this block has been accepted on L1.
+    let l1_l2_head = RefsTable::get_l1_l2_head(transaction)
+        .context("Read latest L1 head from database")
+        .map_err(internal_server_error)?;
+    let block_status = match l1_l2_head {
+        Some(number) if number >= block.number => BlockStatus::AcceptedOnL1,
+        _ => BlockStatus::AcceptedOnL2,
+    };
+
+    let (parent_hash, parent_root) = match block.number {
+        StarknetBlockNumber::GENESIS => (
+            StarknetBlockHash(StarkHash::ZERO),
+            GlobalRoot(StarkHash::ZERO),
+        ),
+        other => {
+            let parent_block = StarknetBlocksTable::get(transaction, (other - 1).into())
+                .context("Read parent block from database")
+                .map_err(internal_server_error)?
+                .context("Parent block missing")?;
+
+            (parent_block.hash, parent_block.root)
+        }
+    };
+
+    let block = RawBlock {
+        number: block.number,
+        hash: block.hash,
+        root: block.root,
+        parent_hash,
+        parent_root,
+        timestamp: block.timestamp,
+        status: block_status,
+        gas_price: block.gas_price,
+        sequencer: block.sequencer_address,
+    };
+
+    Ok(block)
+}
+
+/// Reads the transactions and their receipts of a block from storage.
+///
+/// This function assumes that the block number is valid i.e. it won't check if the block exists.
+fn read_block_transactions(
+    transaction: &rusqlite::Transaction<'_>,
+    block_number: StarknetBlockNumber,
+) -> RpcResult<
+    Vec<(
+        sequencer::reply::transaction::Transaction,
+        sequencer::reply::transaction::Receipt,
+    )>,
+> {
+    StarknetTransactionsTable::get_transaction_data_for_block(transaction, block_number.into())
+        .context("Reading transactions from database")
+        .map_err(internal_server_error)
+}
+
+/// Maps a block's transactions and receipts to the [requested scope](BlockResponseScope).
+fn scoped_transactions(
+    transactions_receipts: Vec<(
+        sequencer::reply::transaction::Transaction,
+        sequencer::reply::transaction::Receipt,
+    )>,
+    block_status: BlockStatus,
+    scope: BlockResponseScope,
+) -> super::types::reply::Transactions {
+    use super::types::reply;
+
+    match scope {
+        BlockResponseScope::TransactionHashes => reply::Transactions::HashesOnly(
+            transactions_receipts
+                .into_iter()
+                .map(|(t, _)| t.transaction_hash)
+                .collect(),
+        ),
+        BlockResponseScope::FullTransactions => reply::Transactions::Full(
+            transactions_receipts
+                .into_iter()
+                .map(|(t, _)| t.into())
+                .collect(),
+        ),
+        BlockResponseScope::FullTransactionsAndReceipts => reply::Transactions::FullWithReceipts(
+            transactions_receipts
+                .into_iter()
+                .map(|(t, r)| {
+                    let t: Transaction = t.into();
+                    let r = TransactionReceipt::with_status(r, block_status);
+
+                    reply::TransactionAndReceipt {
+                        txn_hash: t.txn_hash,
+                        contract_address: t.contract_address,
+                        entry_point_selector: t.entry_point_selector,
+                        calldata: t.calldata,
+                        status: r.status,
+                        status_data: r.status_data,
+                        messages_sent: r.messages_sent,
+                        l1_origin_message: r.l1_origin_message,
+                        events: r.events,
+                    }
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Checks that `key` is a valid [StorageAddress], that is it is less than the field modulus
+/// and has at most 251 bits.
+fn parse_storage_key(key: OverflowingStorageAddress) -> Result<StorageAddress, InvalidStorageKey> {
+    use pedersen::OverflowError;
+
+    let key = StarkHash::from_be_bytes(key.0.to_fixed_bytes())
+        // Use explicit typing in closure arg to force compiler error should error variants ever be expanded
+        .map_err(|_e: OverflowError| InvalidStorageKey::ExceedsFieldModulus)?;
+
+    if key.has_more_than_251_bits() {
+        return Err(InvalidStorageKey::MoreThan251Bits);
+    }
+
+    Ok(StorageAddress(key))
+}
+
+/// Checks that the [GlobalStateTree] with the given `root` is persisted, so the state at that
+/// root can be read.
+///
+/// There is no dedicated error code for an unknown root, so it is reported as invalid params.
+fn resolve_root(tx: &rusqlite::Transaction<'_>, root: GlobalRoot) -> RpcResult<()> {
+    let exists = GlobalStateTree::root_exists(tx, root)
+        .context("Checking global root")
+        .map_err(internal_server_error)?;
+
+    if exists {
+        Ok(())
+    } else {
+        Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+            "Global root {} is not persisted",
+            root.0
+        ))))
+    }
+}
+
+/// Maps the failure of a call on the sequencer, reporting a failed execution as reverted.
+fn sequencer_call_error(e: sequencer::error::SequencerError) -> Error {
+    use sequencer::error::{SequencerError, StarknetErrorCode};
+
+    match e {
+        SequencerError::StarknetError(e) if e.code == StarknetErrorCode::TransactionFailed => {
+            Error::from(RpcError::CallReverted {
+                revert_reason: e.message,
+            })
+        }
+        other => Error::from(other),
+    }
+}
+
+/// Returns [RpcError::ContractNotFound] for [ContractAddress::ZERO], without looking it up.
+///
+/// Whether it is found would otherwise depend on where it is looked up, i.e. storage, the
+/// sequencer or the call executor.
+fn reject_zero_address(contract_address: ContractAddress) -> RpcResult<()> {
+    if contract_address == ContractAddress::ZERO {
+        return Err(Error::from(RpcError::ContractNotFound));
+    }
+    Ok(())
+}
+
+/// Loads the storage tree of `contract_address` as of the block with the given global root.
+///
+/// Returns [RpcError::ContractNotFound] if the contract is not deployed at that block.
+fn load_contract_state_tree<'tx>(
+    tx: &'tx rusqlite::Transaction<'_>,
+    global_root: GlobalRoot,
+    contract_address: ContractAddress,
+    node_cache: &'tx NodeCache,
+) -> RpcResult<ContractsStateTree<'tx>> {
+    let global_state_tree = GlobalStateTree::load_with_cache(tx, global_root, node_cache)
+        .context("Global state tree")
+        .map_err(internal_server_error)?;
+
+    let contract_state_hash = global_state_tree
+        .get(contract_address)
+        .context("Get contract state hash from global state tree")
+        .map_err(internal_server_error)?;
+
+    // There is a dedicated error code for a non-existent contract in the RPC API spec, so use it.
+    if contract_state_hash.0 == StarkHash::ZERO {
+        return Err(Error::from(RpcError::ContractNotFound));
+    }
+
+    let contract_state_root = ContractsStateTable::get_root(tx, contract_state_hash)
+        .context("Get contract state root")
+        .map_err(internal_server_error)?
+        .ok_or_else(|| {
+            internal_server_error(anyhow::anyhow!(
+                "Contract state root not found for contract state hash {}",
+                contract_state_hash.0
+            ))
+        })?;
+
+    ContractsStateTree::load_with_cache(tx, contract_state_root, node_cache)
+        .context("Load contract state tree")
+        .map_err(internal_server_error)
+}
+
+/// Merges two sets of storage entries, both in ascending key order, into the entries whose
+/// values differ. Keys missing from either set have a zero value.
+fn diff_storage_entries(
+    mut old: impl Iterator<Item = anyhow::Result<(StorageAddress, StorageValue)>>,
+    mut new: impl Iterator<Item = anyhow::Result<(StorageAddress, StorageValue)>>,
+) -> anyhow::Result<Vec<StorageDiffEntry>> {
+    use std::cmp::Ordering;
+
+    const ZERO: StorageValue = StorageValue(StarkHash::ZERO);
+
+    let mut old_entry = old.next().transpose()?;
+    let mut new_entry = new.next().transpose()?;
+    let mut diff = Vec::new();
+
+    loop {
+        let (key, old_value, new_value) = match (old_entry, new_entry) {
+            (None, None) => break,
+            (Some((key, value)), None) => {
+                old_entry = old.next().transpose()?;
+                (key, value, ZERO)
+            }
+            (None, Some((key, value))) => {
+                new_entry = new.next().transpose()?;
+                (key, ZERO, value)
+            }
+            (Some((old_key, old_value)), Some((new_key, new_value))) => {
+                match old_key.cmp(&new_key) {
+                    Ordering::Less => {
+                        old_entry = old.next().transpose()?;
+                        (old_key, old_value, ZERO)
+                    }
+                    Ordering::Greater => {
+                        new_entry = new.next().transpose()?;
+                        (new_key, ZERO, new_value)
+                    }
+                    Ordering::Equal => {
+                        old_entry = old.next().transpose()?;
+                        new_entry = new.next().transpose()?;
+                        (old_key, old_value, new_value)
+                    }
+                }
+            }
+        };
+
+        if old_value != new_value {
+            diff.push(StorageDiffEntry {
+                key,
+                old_value,
+                new_value,
+            });
+        }
+    }
+
+    Ok(diff)
+}
+
+/// Returns the events of the pending block which match the filter's address and keys,
+/// in the order they were emitted.
+fn pending_events(block: sequencer::reply::Block, filter: &EventFilter) -> Vec<EmittedEvent> {
+    let in_time_range = filter
+        .from_timestamp
+        .map_or(true, |from| block.timestamp >= from)
+        && filter.to_timestamp.map_or(true, |to| block.timestamp <= to);
+    if !in_time_range {
+        return Vec::new();
+    }
+
+    block
+        .transaction_receipts
+        .into_iter()
+        .flat_map(|receipt| {
+            let transaction_hash = receipt.transaction_hash;
+            receipt.events.into_iter().map(move |event| EmittedEvent {
+                data: event.data,
+                keys: event.keys,
+                from_address: event.from_address,
+                block_hash: None,
+                block_number: None,
+                transaction_hash,
+            })
+        })
+        .filter(|event| match &filter.address {
+            Some(address) => address.addresses().contains(&event.from_address),
+            None => true,
+        })
+        .filter(|event| filter.exclude_address != Some(event.from_address))
+        .filter(|event| {
+            if filter.keys.is_empty() {
+                return true;
+            }
+            // Same as for indexed events, matching any of the keys is enough.
+            match filter.key_match {
+                KeyMatchMode::Exact => event.keys.iter().any(|key| filter.keys.contains(key)),
+                KeyMatchMode::Prefix => match event.keys.first() {
+                    Some(key0) => {
+                        let key0 = StarknetEventsTable::event_key_hex_digits(key0);
+                        filter.keys.iter().any(|prefix| {
+                            key0.starts_with(&StarknetEventsTable::event_key_hex_digits(prefix))
+                        })
+                    }
+                    None => false,
+                },
+            }
+        })
+        .collect()
+}
+
+/// Resolves one end of an [EventFilter]'s block range, which may be given either by number or
+/// by hash, to a block number.
+///
+/// Returns [RpcError::InvalidBlockHash] if the block hash is unknown.
+fn resolve_event_filter_bound(
+    connection: &rusqlite::Connection,
+    number: Option<StarknetBlockNumber>,
+    hash: Option<StarknetBlockHash>,
+) -> RpcResult<Option<StarknetBlockNumber>> {
+    match (number, hash) {
+        (Some(_), Some(_)) => Err(Error::Call(CallError::InvalidParams(anyhow::anyhow!(
+            "Block range bound given both by number and by hash"
+        )))),
+        (number, None) => Ok(number),
+        (None, Some(hash)) => {
+            let block = StarknetBlocksTable::get(connection, hash.into())
+                .context("Read block from database")
+                .map_err(internal_server_error)?
+                .ok_or_else(|| Error::from(RpcError::InvalidBlockHash))?;
+
+            Ok(Some(block.number))
+        }
+    }
+}
+
 impl From<EventFilterError> for jsonrpsee::types::Error {
     fn from(e: EventFilterError) -> Self {
         match e {
-            EventFilterError::PageSizeTooBig(max_size) => Error::Call(CallError::Custom {
-                code: ErrorCode::PageSizeTooBig as i32,
-                message: ErrorCode::PageSizeTooBig.to_string(),
-                data: Some(
-                    serde_json::value::RawValue::from_string(
-                        serde_json::json!({ "max_page_size": max_size }).to_string(),
-                    )
-                    .unwrap(),
-                ),
-            }),
+            EventFilterError::PageSizeTooBig(max_page_size) => {
+                RpcError::PageSizeTooBig { max_page_size }.into()
+            }
+            EventFilterError::TooManyKeys(max_keys) => {
+                RpcError::TooManyKeysInFilter { max_keys }.into()
+            }
         }
     }
 }
@@ -1052,6 +3141,35 @@ fn internal_server_error(e: impl std::fmt::Display) -> jsonrpsee::types::Error {
     })
 }
 
+/// [RpcError::InvalidCallData] with the index of the failed call and the reason it failed as
+/// `data.call_index` and `data.revert_reason`.
+fn simulated_call_failed(call_index: usize, revert_reason: String) -> jsonrpsee::types::Error {
+    let code = RpcError::InvalidCallData.code();
+    let data = serde_json::json!({ "call_index": call_index, "revert_reason": revert_reason });
+    Error::Call(CallError::Custom {
+        code: code as i32,
+        message: code.to_string(),
+        // Serializing a json value cannot fail.
+        data: Some(serde_json::value::RawValue::from_string(data.to_string()).unwrap()),
+    })
+}
+
+/// [RpcError::InvalidCallData] for a call without calldata to an entry point expecting at least
+/// `expected` calldata values, which is given as `data.expected_calldata_len`.
+fn missing_calldata(expected: usize) -> jsonrpsee::types::Error {
+    let code = RpcError::InvalidCallData.code();
+    let data = serde_json::json!({
+        "reason": format!("The entry point expects at least {expected} calldata values, but none were given"),
+        "expected_calldata_len": expected,
+    });
+    Error::Call(CallError::Custom {
+        code: code as i32,
+        message: code.to_string(),
+        // Serializing a json value cannot fail.
+        data: Some(serde_json::value::RawValue::from_string(data.to_string()).unwrap()),
+    })
+}
+
 fn static_internal_server_error() -> jsonrpsee::types::Error {
     Error::Call(CallError::Custom {
         code: jsonrpsee::types::v2::error::INTERNAL_ERROR_CODE,
@@ -0,0 +1,130 @@
+//! Gzip compression of HTTP-RPC responses.
+//!
+//! [jsonrpsee] does not compress responses itself, so compression is done by the
+//! [front server](super::listener) which forwards requests to the HTTP-RPC server.
+use std::io::Write;
+
+use hyper::header::{
+    HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY,
+};
+use hyper::{Body, Response, StatusCode};
+
+/// Settings of the gzip compression of HTTP-RPC responses.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GzipConfig {
+    /// Responses smaller than this many bytes are sent uncompressed, as compressing
+    /// these saves little bandwidth.
+    pub min_size: usize,
+}
+
+impl Default for GzipConfig {
+    fn default() -> Self {
+        Self { min_size: 1024 }
+    }
+}
+
+/// Compresses `response` for a client accepting gzip encoding, unless it is smaller than
+/// [GzipConfig::min_size] or already encoded.
+pub(super) async fn compress(response: Response<Body>, config: GzipConfig) -> Response<Body> {
+    if response.headers().contains_key(CONTENT_ENCODING) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!(reason=%e, "Failed to read HTTP-RPC server response");
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::BAD_GATEWAY;
+            return response;
+        }
+    };
+
+    parts
+        .headers
+        .insert(VARY, HeaderValue::from_static("accept-encoding"));
+    if body.len() < config.min_size {
+        return Response::from_parts(parts, Body::from(body));
+    }
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let compressed = encoder.write_all(&body).and_then(|_| encoder.finish());
+    match compressed {
+        Ok(compressed) => {
+            parts
+                .headers
+                .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            parts.headers.remove(CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(compressed))
+        }
+        Err(e) => {
+            tracing::warn!(reason=%e, "Failed to compress HTTP-RPC response");
+            Response::from_parts(parts, Body::from(body))
+        }
+    }
+}
+
+/// Returns true if the `Accept-Encoding` headers allow a gzip encoded response.
+pub(super) fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut params = coding.split(';').map(str::trim);
+            let name = params.next().unwrap_or_default();
+            // A quality value of zero means "not acceptable".
+            let rejected = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .map(|q| q == 0.0)
+                    .unwrap_or_default()
+            });
+
+            (name.eq_ignore_ascii_case("gzip") || name == "*") && !rejected
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(accept_encoding: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in accept_encoding {
+            headers.append(ACCEPT_ENCODING, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn accepts_gzip() {
+        for accept_encoding in [
+            &["gzip"][..],
+            &["GZIP"],
+            &["deflate, gzip;q=1.0, *;q=0.5"],
+            &["br", "gzip"],
+            &["*"],
+        ] {
+            assert!(
+                super::accepts_gzip(&headers(accept_encoding)),
+                "{:?}",
+                accept_encoding
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_gzip() {
+        for accept_encoding in [&[][..], &["identity"], &["br, deflate"], &["gzip;q=0"]] {
+            assert!(
+                !super::accepts_gzip(&headers(accept_encoding)),
+                "{:?}",
+                accept_encoding
+            );
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! A short lived cache of `starknet_getEvents` results, for clients polling the same filter.
+//!
+//! Results depend on the tip of the chain, i.e. on filters without an upper block bound, so all
+//! cached results are dropped whenever a [new head](crate::state::SyncState::new_heads) is
+//! announced. Results are additionally only served for a short while after being cached, which
+//! bounds their staleness should the tip change without an announcement, i.e. on a reorg.
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use crate::rpc::types::reply::{BlockHeader, GetEventsResult};
+use crate::rpc::types::request::EventFilter;
+
+/// A bounded cache of [GetEventsResult]s, keyed by their normalized [EventFilter].
+///
+/// Once full, the oldest cached result is evicted for every newly cached result.
+#[derive(Debug)]
+pub struct EventsCache {
+    ttl: Duration,
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    new_heads: broadcast::Receiver<BlockHeader>,
+    /// Incremented every time the cached results are dropped because of a new head.
+    generation: u64,
+    /// The cached results, oldest first.
+    entries: VecDeque<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    filter: EventFilter,
+    cached_at: Instant,
+    result: GetEventsResult,
+}
+
+/// A cache miss, to be passed on to [EventsCache::insert] together with the queried result.
+#[derive(Debug)]
+pub struct Miss {
+    // Boxed to keep the error variant of [EventsCache::get] small.
+    filter: Box<EventFilter>,
+    generation: u64,
+}
+
+impl EventsCache {
+    /// The default duration for which results are served from the cache.
+    pub const DEFAULT_TTL: Duration = Duration::from_millis(2000);
+    /// The default maximum number of cached results.
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    /// Creates a cache holding at most `capacity` results for up to `ttl` each, dropping all of
+    /// them on every header received from `new_heads`. A `capacity` of zero disables caching.
+    pub fn new(
+        ttl: Duration,
+        capacity: usize,
+        new_heads: broadcast::Receiver<BlockHeader>,
+    ) -> Self {
+        Self {
+            ttl,
+            capacity,
+            inner: Mutex::new(Inner {
+                new_heads,
+                generation: 0,
+                entries: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Returns the cached result of `filter`, or the [Miss] under which its result can be cached.
+    ///
+    /// Filters which include the [pending](crate::rpc::types::Tag::Pending) block are never cached,
+    /// as its events change without a new head being announced.
+    pub fn get(&self, filter: &EventFilter) -> Result<GetEventsResult, Option<Miss>> {
+        use crate::rpc::types::{BlockNumberOrTag, Tag};
+
+        if self.capacity == 0 || filter.to_block == Some(BlockNumberOrTag::Tag(Tag::Pending)) {
+            return Err(None);
+        }
+
+        let filter = normalize(filter.clone());
+        let mut inner = self.inner.lock().unwrap();
+        inner.drop_on_new_head();
+
+        let ttl = self.ttl;
+        inner
+            .entries
+            .retain(|entry| entry.cached_at.elapsed() < ttl);
+
+        match inner.entries.iter().find(|entry| entry.filter == filter) {
+            Some(entry) => Ok(entry.result.clone()),
+            None => Err(Some(Miss {
+                filter: Box::new(filter),
+                generation: inner.generation,
+            })),
+        }
+    }
+
+    /// Caches the `result` queried after the cache `miss`, unless a new head has been announced
+    /// since, in which case the result may already be stale.
+    pub fn insert(&self, miss: Miss, result: GetEventsResult) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.drop_on_new_head();
+        if inner.generation != miss.generation {
+            return;
+        }
+
+        // Concurrent requests may both miss and insert the same filter.
+        inner.entries.retain(|entry| entry.filter != *miss.filter);
+        inner.entries.push_back(Entry {
+            filter: *miss.filter,
+            cached_at: Instant::now(),
+            result,
+        });
+
+        if inner.entries.len() > self.capacity {
+            inner.entries.pop_front();
+        }
+    }
+}
+
+impl Inner {
+    /// Drops all cached results if a new head has been announced since the last call.
+    fn drop_on_new_head(&mut self) {
+        let mut new_head = false;
+        // Lagging behind means that heads were missed, which is just as much of a change.
+        while let Ok(_) | Err(TryRecvError::Lagged(_)) = self.new_heads.try_recv() {
+            new_head = true;
+        }
+
+        if new_head {
+            self.generation += 1;
+            self.entries.clear();
+        }
+    }
+}
+
+/// Orders and deduplicates the filter's keys and addresses, as events match if they match any
+/// of them.
+fn normalize(mut filter: EventFilter) -> EventFilter {
+    use crate::rpc::types::request::EventAddressFilter;
+
+    filter.keys.sort();
+    filter.keys.dedup();
+    if let Some(EventAddressFilter::Multiple(addresses)) = &mut filter.address {
+        addresses.sort();
+        addresses.dedup();
+    }
+    filter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        EventKey, GasPrice, GlobalRoot, SequencerAddress, StarknetBlockHash, StarknetBlockNumber,
+        StarknetBlockTimestamp,
+    };
+    use crate::rpc::types::reply::BlockStatus;
+    use crate::rpc::types::request::KeyMatchMode;
+    use crate::rpc::types::{BlockNumberOrTag, Tag};
+    use pedersen::StarkHash;
+
+    fn key(value: u8) -> EventKey {
+        EventKey(StarkHash::from_be_slice(&[value]).unwrap())
+    }
+
+    fn filter(keys: Vec<EventKey>, page_number: usize) -> EventFilter {
+        EventFilter {
+            from_block: None,
+            to_block: None,
+            from_block_hash: None,
+            to_block_hash: None,
+            from_timestamp: None,
+            to_timestamp: None,
+            address: None,
+            exclude_address: None,
+            keys,
+            key_match: KeyMatchMode::Exact,
+            page_size: Some(10),
+            page_number,
+            continuation_token: None,
+            include_count: false,
+        }
+    }
+
+    fn result(page_number: usize) -> GetEventsResult {
+        GetEventsResult {
+            events: vec![],
+            page_number,
+            is_last_page: false,
+            continuation_token: None,
+            total_count: None,
+            total_pages: None,
+        }
+    }
+
+    fn header() -> BlockHeader {
+        BlockHeader {
+            block_hash: StarknetBlockHash(StarkHash::ZERO),
+            parent_hash: StarknetBlockHash(StarkHash::ZERO),
+            block_number: StarknetBlockNumber(0),
+            status: BlockStatus::AcceptedOnL2,
+            sequencer: SequencerAddress(StarkHash::ZERO),
+            new_root: GlobalRoot(StarkHash::ZERO),
+            accepted_time: StarknetBlockTimestamp(0),
+            gas_price: GasPrice::ZERO,
+        }
+    }
+
+    fn new_cache(ttl: Duration, capacity: usize) -> (EventsCache, broadcast::Sender<BlockHeader>) {
+        let (new_heads, receiver) = broadcast::channel(1);
+        (EventsCache::new(ttl, capacity, receiver), new_heads)
+    }
+
+    #[test]
+    fn hit_and_miss() {
+        let (cache, _new_heads) = new_cache(Duration::from_secs(60), 10);
+
+        let miss = cache.get(&filter(vec![key(1), key(2)], 0)).unwrap_err();
+        cache.insert(miss.unwrap(), result(0));
+
+        // Keys are matched regardless of their order and duplicates.
+        assert_eq!(
+            cache.get(&filter(vec![key(2), key(1), key(2)], 0)).unwrap(),
+            result(0)
+        );
+        // Pages are cached separately.
+        assert!(cache.get(&filter(vec![key(1), key(2)], 1)).is_err());
+    }
+
+    #[test]
+    fn expires() {
+        let (cache, _new_heads) = new_cache(Duration::from_millis(10), 10);
+
+        let miss = cache.get(&filter(vec![], 0)).unwrap_err();
+        cache.insert(miss.unwrap(), result(0));
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(cache.get(&filter(vec![], 0)).is_err());
+    }
+
+    #[test]
+    fn new_head_drops_results() {
+        let (cache, new_heads) = new_cache(Duration::from_secs(60), 10);
+
+        let miss = cache.get(&filter(vec![], 0)).unwrap_err();
+        cache.insert(miss.unwrap(), result(0));
+        new_heads.send(header()).unwrap();
+        assert!(cache.get(&filter(vec![], 0)).is_err());
+
+        // Results queried before the new head are not cached.
+        let miss = cache.get(&filter(vec![], 0)).unwrap_err();
+        new_heads.send(header()).unwrap();
+        cache.insert(miss.unwrap(), result(0));
+        assert!(cache.get(&filter(vec![], 0)).is_err());
+    }
+
+    #[test]
+    fn evicts_oldest() {
+        let (cache, _new_heads) = new_cache(Duration::from_secs(60), 2);
+
+        for page_number in 0..3 {
+            let miss = cache.get(&filter(vec![], page_number)).unwrap_err();
+            cache.insert(miss.unwrap(), result(page_number));
+        }
+
+        assert!(cache.get(&filter(vec![], 0)).is_err());
+        assert_eq!(cache.get(&filter(vec![], 1)).unwrap(), result(1));
+        assert_eq!(cache.get(&filter(vec![], 2)).unwrap(), result(2));
+    }
+
+    #[test]
+    fn pending_and_disabled_are_not_cached() {
+        let (cache, _new_heads) = new_cache(Duration::from_secs(60), 10);
+        let mut pending = filter(vec![], 0);
+        pending.to_block = Some(BlockNumberOrTag::Tag(Tag::Pending));
+        assert!(cache.get(&pending).unwrap_err().is_none());
+
+        let (cache, _new_heads) = new_cache(Duration::from_secs(60), 0);
+        assert!(cache.get(&filter(vec![], 0)).unwrap_err().is_none());
+    }
+}
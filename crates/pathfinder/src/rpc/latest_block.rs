@@ -0,0 +1,181 @@
+//! A cache of the latest block, so that resolving the `latest` tag does not read the database
+//! on every request.
+//!
+//! The cached block is replaced by every [new head](SyncState::new_heads) and dropped on every
+//! [reorg](SyncState::reorgs), after which it is read from the database again.
+use std::sync::Mutex;
+
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use crate::core::{GlobalRoot, StarknetBlockHash, StarknetBlockNumber};
+use crate::rpc::types::reply::BlockHeader;
+use crate::state::SyncState;
+
+/// The identifying parts of the latest block.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LatestBlock {
+    pub number: StarknetBlockNumber,
+    pub hash: StarknetBlockHash,
+    pub root: GlobalRoot,
+}
+
+/// Caches the [LatestBlock], following the notifications of [SyncState].
+#[derive(Debug)]
+pub struct LatestBlockCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    new_heads: broadcast::Receiver<BlockHeader>,
+    reorgs: broadcast::Receiver<StarknetBlockNumber>,
+    /// Incremented every time the cached block changes because of a notification.
+    generation: u64,
+    latest: Option<LatestBlock>,
+}
+
+/// A cache miss, to be passed on to [LatestBlockCache::insert] together with the block read
+/// from the database.
+#[derive(Debug)]
+pub struct Miss {
+    generation: u64,
+}
+
+impl LatestBlockCache {
+    /// Creates an empty cache, subscribed to the notifications of `sync_state`.
+    pub fn new(sync_state: &SyncState) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                new_heads: sync_state.new_heads.subscribe(),
+                reorgs: sync_state.reorgs.subscribe(),
+                generation: 0,
+                latest: None,
+            }),
+        }
+    }
+
+    /// Returns the cached latest block, or the [Miss] under which the latest block can be cached.
+    pub fn get(&self) -> Result<LatestBlock, Miss> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.follow_notifications();
+
+        inner.latest.ok_or(Miss {
+            generation: inner.generation,
+        })
+    }
+
+    /// Caches the `latest` block read after the cache `miss`, unless the latest block has
+    /// changed since, in which case `latest` may already be outdated.
+    pub fn insert(&self, miss: Miss, latest: LatestBlock) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.follow_notifications();
+
+        if inner.generation == miss.generation {
+            inner.latest = Some(latest);
+        }
+    }
+}
+
+impl Inner {
+    /// Applies the notifications received since the last call.
+    ///
+    /// Reorgs are applied after new heads, as the order between both is lost. Dropping the cached
+    /// block is always correct, it merely has to be read from the database again.
+    fn follow_notifications(&mut self) {
+        let mut changed = false;
+
+        loop {
+            match self.new_heads.try_recv() {
+                Ok(header) => {
+                    self.latest = Some(LatestBlock {
+                        number: header.block_number,
+                        hash: header.block_hash,
+                        root: header.new_root,
+                    });
+                    changed = true;
+                }
+                // Missed heads could have been followed by a reorg.
+                Err(TryRecvError::Lagged(_)) => {
+                    self.latest = None;
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+            }
+        }
+
+        while let Ok(_) | Err(TryRecvError::Lagged(_)) = self.reorgs.try_recv() {
+            self.latest = None;
+            changed = true;
+        }
+
+        if changed {
+            self.generation += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{GasPrice, SequencerAddress, StarknetBlockTimestamp};
+    use crate::rpc::types::reply::BlockStatus;
+    use pedersen::StarkHash;
+
+    fn latest(number: u64) -> LatestBlock {
+        LatestBlock {
+            number: StarknetBlockNumber(number),
+            hash: StarknetBlockHash(StarkHash::from_be_slice(&[number as u8 + 1]).unwrap()),
+            root: GlobalRoot(StarkHash::from_be_slice(&[number as u8 + 2]).unwrap()),
+        }
+    }
+
+    fn header(number: u64) -> BlockHeader {
+        let latest = latest(number);
+        BlockHeader {
+            block_hash: latest.hash,
+            parent_hash: StarknetBlockHash(StarkHash::ZERO),
+            block_number: latest.number,
+            status: BlockStatus::AcceptedOnL2,
+            sequencer: SequencerAddress(StarkHash::ZERO),
+            new_root: latest.root,
+            accepted_time: StarknetBlockTimestamp(number),
+            gas_price: GasPrice::ZERO,
+        }
+    }
+
+    #[test]
+    fn follows_new_heads() {
+        let sync_state = SyncState::default();
+        let cache = LatestBlockCache::new(&sync_state);
+
+        let miss = cache.get().unwrap_err();
+        cache.insert(miss, latest(1));
+        assert_eq!(cache.get().unwrap(), latest(1));
+
+        sync_state.new_heads.send(header(2)).unwrap();
+        assert_eq!(cache.get().unwrap(), latest(2));
+    }
+
+    #[test]
+    fn reorg_drops_latest() {
+        let sync_state = SyncState::default();
+        let cache = LatestBlockCache::new(&sync_state);
+
+        sync_state.new_heads.send(header(2)).unwrap();
+        assert_eq!(cache.get().unwrap(), latest(2));
+
+        sync_state.reorgs.send(StarknetBlockNumber(2)).unwrap();
+        assert!(cache.get().is_err());
+    }
+
+    #[test]
+    fn outdated_miss_is_not_cached() {
+        let sync_state = SyncState::default();
+        let cache = LatestBlockCache::new(&sync_state);
+
+        let miss = cache.get().unwrap_err();
+        sync_state.reorgs.send(StarknetBlockNumber(1)).unwrap();
+        cache.insert(miss, latest(1));
+        assert!(cache.get().is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! Listening socket of the HTTP-RPC server.
+//!
+//! [jsonrpsee] 0.6 binds the listening socket itself, with a fixed accept backlog of 128, accepts
+//! connections without limit and does not compress responses. So the listening socket is bound
+//! here and served by a front server doing all of that, which forwards requests to the HTTP-RPC
+//! server listening on the loopback interface.
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use hyper::client::HttpConnector;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::server::accept;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Client, Method, Request, Response, Server, StatusCode, Uri};
+use socket2::{Domain, Socket, Type};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+
+use super::compression::{self, GzipConfig};
+
+/// Settings of the HTTP-RPC server's listening socket, and of the load it takes on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ListenerConfig {
+    /// Maximum number of concurrent connections, unlimited if [None].
+    ///
+    /// Connections exceeding this limit are refused right after being accepted.
+    pub max_connections: Option<usize>,
+    /// Maximum number of calls handled concurrently across all connections, unlimited if [None].
+    ///
+    /// Calls exceeding this limit fail right away with a [busy](super::SERVER_BUSY_CODE) error.
+    pub max_concurrent_calls: Option<usize>,
+    /// Accept backlog of the listening socket.
+    pub backlog: u32,
+    /// Gzip compression of responses for clients accepting it, disabled if [None].
+    pub gzip: Option<GzipConfig>,
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            max_concurrent_calls: None,
+            backlog: 1024,
+            gzip: None,
+        }
+    }
+}
+
+/// Binds a non-blocking TCP listener to `addr` with the given accept backlog.
+pub(super) fn bind(addr: SocketAddr, backlog: u32) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(backlog.try_into().unwrap_or(i32::MAX))?;
+    Ok(socket.into())
+}
+
+/// Returns an address on the loopback interface of the same IP version as `addr`, letting
+/// the operating system pick the port.
+pub(super) fn loopback(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(_) => (Ipv4Addr::LOCALHOST, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::LOCALHOST, 0).into(),
+    }
+}
+
+/// Serves HTTP on `listener`, forwarding all requests to the HTTP-RPC server at `upstream`, until
+/// the returned sender is dropped.
+///
+/// Connections exceeding [ListenerConfig::max_connections] are reset right after being
+/// accepted, and responses are compressed according to [ListenerConfig::gzip].
+pub(super) fn serve(
+    listener: TcpListener,
+    upstream: SocketAddr,
+    config: &ListenerConfig,
+) -> io::Result<oneshot::Sender<()>> {
+    let mut incoming = Incoming {
+        listener: tokio::net::TcpListener::from_std(listener)?,
+        permits: config
+            .max_connections
+            .map(|max_connections| Arc::new(Semaphore::new(max_connections))),
+        backoff: None,
+    };
+    let gzip = config.gzip;
+    let client = Client::new();
+
+    let make_service = make_service_fn(move |connection: &Connection| {
+        let client = client.clone();
+        let peer = connection.peer;
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                forward(client.clone(), upstream, peer, gzip, request)
+            }))
+        }
+    });
+
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server = Server::builder(accept::poll_fn(move |cx| incoming.poll_accept(cx)))
+        .serve(make_service)
+        .with_graceful_shutdown(async move {
+            let _ = stopped.await;
+        });
+
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            tracing::warn!(reason=%e, "HTTP-RPC front server stopped");
+        }
+    });
+
+    Ok(stop)
+}
+
+/// Accepts connections for the front server within the connection limit.
+struct Incoming {
+    listener: tokio::net::TcpListener,
+    /// One permit per connection, unlimited if [None].
+    permits: Option<Arc<Semaphore>>,
+    /// Delays accepting after an error which is not specific to a single connection.
+    backoff: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl Incoming {
+    fn poll_accept(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Connection>>> {
+        loop {
+            if let Some(backoff) = self.backoff.as_mut() {
+                futures::ready!(backoff.as_mut().poll(cx));
+                self.backoff = None;
+            }
+
+            let (stream, peer) = match futures::ready!(self.listener.poll_accept(cx)) {
+                Ok(connection) => connection,
+                Err(e) if is_connection_error(&e) => {
+                    tracing::debug!(reason=%e, "Failed to accept HTTP-RPC connection");
+                    continue;
+                }
+                Err(e) => {
+                    // Errors such as running out of file descriptors persist until other
+                    // connections get closed, so retrying right away would only spin.
+                    tracing::warn!(reason=%e, "Failed to accept HTTP-RPC connection, retrying in 1s");
+                    self.backoff = Some(Box::pin(tokio::time::sleep(Duration::from_secs(1))));
+                    continue;
+                }
+            };
+
+            let permit = match &self.permits {
+                Some(permits) => match permits.clone().try_acquire_owned() {
+                    Ok(permit) => Some(permit),
+                    Err(_) => {
+                        tracing::debug!(%peer, "HTTP-RPC connection limit reached, refusing connection");
+                        // A zero linger timeout resets the connection on close.
+                        let _ = stream.set_linger(Some(Duration::ZERO));
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            return Poll::Ready(Some(Ok(Connection {
+                stream,
+                peer,
+                _permit: permit,
+            })));
+        }
+    }
+}
+
+/// Errors which only concern the connection being accepted.
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    )
+}
+
+/// A connection accepted by the front server, which counts towards the connection limit
+/// until it is closed.
+struct Connection {
+    stream: TcpStream,
+    peer: SocketAddr,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+/// Returns true if `content_type` is one of the JSON content types accepted by [jsonrpsee].
+fn is_json(content_type: Option<&HeaderValue>) -> bool {
+    match content_type.and_then(|value| value.to_str().ok()) {
+        Some(content_type) => [
+            "application/json",
+            "application/json; charset=utf-8",
+            "application/json;charset=utf-8",
+        ]
+        .iter()
+        .any(|json| content_type.eq_ignore_ascii_case(json)),
+        None => false,
+    }
+}
+
+/// Forwards `request` from `peer` to the HTTP-RPC server.
+///
+/// Calls without a [JSON content type](is_json) are rejected with `415 Unsupported Media Type`,
+/// [jsonrpsee] itself would reject them with `405 Method Not Allowed`.
+async fn forward(
+    client: Client<HttpConnector>,
+    upstream: SocketAddr,
+    peer: SocketAddr,
+    gzip: Option<GzipConfig>,
+    mut request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    if request.method() == Method::POST && !is_json(request.headers().get(CONTENT_TYPE)) {
+        let mut response = Response::new(Body::from(
+            "Supplied content type is not allowed. Content-Type: application/json is required\n",
+        ));
+        *response.status_mut() = StatusCode::UNSUPPORTED_MEDIA_TYPE;
+        return Ok(response);
+    }
+
+    let gzip = gzip.filter(|_| compression::accepts_gzip(request.headers()));
+
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    let uri = Uri::builder()
+        .scheme("http")
+        .authority(upstream.to_string())
+        .path_and_query(path)
+        .build()
+        .expect("Socket address and request path form a valid URI");
+    *request.uri_mut() = uri;
+
+    let response = match client.request(request).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!(%peer, reason=%e, "Failed to forward request to HTTP-RPC server");
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::BAD_GATEWAY;
+            return Ok(response);
+        }
+    };
+
+    match gzip {
+        Some(config) => Ok(compression::compress(response, config).await),
+        None => Ok(response),
+    }
+}
@@ -0,0 +1,68 @@
+//! Metrics collected by the JSON-RPC API.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// Counts how often each [RpcApi](super::api::RpcApi) method could not be answered
+/// from local storage and delegated to the sequencer instead.
+#[derive(Debug, Default)]
+pub struct SequencerFallbacks(Mutex<BTreeMap<&'static str, u64>>);
+
+impl SequencerFallbacks {
+    /// Name of the counter when rendered in the Prometheus text format.
+    const METRIC_NAME: &'static str = "rpc_sequencer_fallbacks_total";
+
+    /// Increments the counter of the given JSON-RPC method.
+    pub fn increment(&self, method: &'static str) {
+        *self.0.lock().unwrap().entry(method).or_default() += 1;
+    }
+
+    /// Returns the counter of the given JSON-RPC method.
+    pub fn get(&self, method: &str) -> u64 {
+        self.0
+            .lock()
+            .unwrap()
+            .get(method)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Renders all counters in the [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/),
+    /// labeled by method.
+    pub fn render(&self) -> String {
+        let mut output = format!("# TYPE {} counter\n", Self::METRIC_NAME);
+        for (method, count) in self.0.lock().unwrap().iter() {
+            // Writing to a String cannot fail.
+            let _ = writeln!(
+                output,
+                "{}{{method=\"{}\"}} {}",
+                Self::METRIC_NAME,
+                method,
+                count
+            );
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SequencerFallbacks;
+
+    #[test]
+    fn render() {
+        let counter = SequencerFallbacks::default();
+        counter.increment("starknet_getBlockByHash");
+        counter.increment("starknet_call");
+        counter.increment("starknet_call");
+
+        assert_eq!(counter.get("starknet_call"), 2);
+        assert_eq!(counter.get("starknet_getBlockByNumber"), 0);
+        assert_eq!(
+            counter.render(),
+            "# TYPE rpc_sequencer_fallbacks_total counter\n\
+            rpc_sequencer_fallbacks_total{method=\"starknet_call\"} 2\n\
+            rpc_sequencer_fallbacks_total{method=\"starknet_getBlockByHash\"} 1\n"
+        );
+    }
+}
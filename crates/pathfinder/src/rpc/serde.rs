@@ -2,9 +2,10 @@
 
 use crate::core::{
     CallParam, CallSignatureElem, ConstructorParam, EthereumAddress, EventData, EventKey, Fee,
-    GasPrice, L1ToL2MessagePayloadElem, L2ToL1MessagePayloadElem, StarknetBlockNumber,
-    TransactionSignatureElem, TransactionVersion,
+    GasPrice, L1ToL2MessagePayloadElem, L2ToL1MessagePayloadElem, StarknetBlockHash,
+    StarknetBlockNumber, TransactionSignatureElem, TransactionVersion,
 };
+use crate::rpc::types::{reply::RpcError, BlockId, Tag};
 use num_bigint::BigUint;
 use pedersen::{HexParseError, OverflowError, StarkHash};
 use serde::de::Visitor;
@@ -282,6 +283,70 @@ impl<'de> DeserializeAs<'de, StarknetBlockNumber> for StarknetBlockNumberAsHexSt
     }
 }
 
+/// Deserializes a [BlockId] from either its plain hash, number or [tag](crate::rpc::types::Tag) form,
+/// or from an [EIP-1898](https://eips.ethereum.org/EIPS/eip-1898) style object:
+/// `{"block_hash": "0x..."}` or `{"block_number": "0x..."}`.
+///
+/// The object keys are also accepted in their EIP-1898 camel case spelling (`blockHash`, `blockNumber`),
+/// and the object block number may be either a hex string or a plain integer.
+pub struct BlockIdAsEip1898;
+
+impl<'de> DeserializeAs<'de, BlockId> for BlockIdAsEip1898 {
+    fn deserialize_as<D>(deserializer: D) -> Result<BlockId, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[serde_with::serde_as]
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum ObjectBlockNumber {
+            Hex(#[serde_as(as = "StarknetBlockNumberAsHexStr")] StarknetBlockNumber),
+            Integer(StarknetBlockNumber),
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct HashObject {
+            #[serde(alias = "blockHash")]
+            block_hash: StarknetBlockHash,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct NumberObject {
+            #[serde(alias = "blockNumber")]
+            block_number: ObjectBlockNumber,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Tag(Tag),
+            Hash(StarknetBlockHash),
+            Number(StarknetBlockNumber),
+            HashObject(HashObject),
+            NumberObject(NumberObject),
+        }
+
+        let repr = <Repr as serde::Deserialize>::deserialize(deserializer).map_err(|_| {
+            serde::de::Error::custom(
+                "expected a block hash, number or tag, or an object with either a block_hash or block_number field",
+            )
+        })?;
+
+        Ok(match repr {
+            Repr::Tag(tag) => BlockId::Tag(tag),
+            Repr::Hash(hash) | Repr::HashObject(HashObject { block_hash: hash }) => {
+                BlockId::Hash(hash)
+            }
+            Repr::Number(number)
+            | Repr::NumberObject(NumberObject {
+                block_number: ObjectBlockNumber::Hex(number) | ObjectBlockNumber::Integer(number),
+            }) => BlockId::Number(number),
+        })
+    }
+}
+
 serde_with::serde_conv!(
     pub TransactionVersionAsHexStr,
     TransactionVersion,
@@ -289,6 +354,52 @@ serde_with::serde_conv!(
     |s: &str| bytes_from_hex_str::<{ H256::len_bytes() }>(s).map(|b| TransactionVersion(H256::from(b)))
 );
 
+/// The reason a hex encoded felt could not be parsed, see [felt_from_hex_str].
+#[derive(Copy, Clone, Debug, thiserror::Error, PartialEq)]
+pub enum FeltParseError {
+    #[error("too many hex digits: {0}, a felt has at most 64")]
+    TooLong(usize),
+    #[error("invalid hex digit: {:?}", char::from(*.0))]
+    NonHex(u8),
+    #[error("value exceeds the field modulus")]
+    ExceedsModulus,
+}
+
+impl From<HexParseError> for FeltParseError {
+    fn from(e: HexParseError) -> Self {
+        match e {
+            HexParseError::InvalidLength(len) => FeltParseError::TooLong(len),
+            HexParseError::InvalidNibble(nibble) => FeltParseError::NonHex(nibble),
+            HexParseError::Overflow => FeltParseError::ExceedsModulus,
+        }
+    }
+}
+
+impl FeltParseError {
+    /// Converts the failure into `invalid`, the error the method reports for the parameter,
+    /// with the reason as `data.reason`.
+    pub fn into_rpc_error(self, invalid: RpcError) -> jsonrpsee::types::Error {
+        use jsonrpsee::types::{error::CallError, Error};
+
+        let code = invalid.code();
+        let data = serde_json::json!({ "reason": self.to_string() });
+        Error::Call(CallError::Custom {
+            code: code as i32,
+            message: code.to_string(),
+            // Serializing a json value cannot fail.
+            data: Some(serde_json::value::RawValue::from_string(data.to_string()).unwrap()),
+        })
+    }
+}
+
+/// Parses a felt from a hex string with an optional "0x" prefix.
+///
+/// Unlike deserializing a [StarkHash], which fails with a generic parse error, the
+/// [FeltParseError] tells why the string is not a felt.
+pub fn felt_from_hex_str(hex_str: &str) -> Result<StarkHash, FeltParseError> {
+    StarkHash::from_hex_str(hex_str).map_err(FeltParseError::from)
+}
+
 /// A helper conversion function. Only use with __sequencer API related types__.
 fn starkhash_from_biguint(b: BigUint) -> Result<StarkHash, OverflowError> {
     StarkHash::from_be_slice(&b.to_bytes_be())
@@ -428,6 +539,70 @@ mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    mod felt_from_hex_str {
+        use super::super::{felt_from_hex_str, FeltParseError};
+        use crate::rpc::types::reply::{ErrorCode, RpcError};
+        use pedersen::StarkHash;
+
+        #[test]
+        fn valid() {
+            assert_eq!(
+                felt_from_hex_str("0x1234").unwrap(),
+                StarkHash::from_hex_str("0x1234").unwrap()
+            );
+            assert_eq!(
+                felt_from_hex_str("1234").unwrap(),
+                StarkHash::from_hex_str("0x1234").unwrap()
+            );
+        }
+
+        #[test]
+        fn exceeds_modulus() {
+            // The field modulus itself.
+            let modulus = "0x800000000000011000000000000000000000000000000000000000000000001";
+            assert_eq!(
+                felt_from_hex_str(modulus).unwrap_err(),
+                FeltParseError::ExceedsModulus
+            );
+            assert_eq!(
+                felt_from_hex_str(&"f".repeat(64)).unwrap_err(),
+                FeltParseError::ExceedsModulus
+            );
+        }
+
+        #[test]
+        fn too_long() {
+            assert_eq!(
+                felt_from_hex_str(&format!("0x{}", "0".repeat(65))).unwrap_err(),
+                FeltParseError::TooLong(65)
+            );
+        }
+
+        #[test]
+        fn non_hex() {
+            assert_eq!(
+                felt_from_hex_str("0x12g4").unwrap_err(),
+                FeltParseError::NonHex(b'g')
+            );
+        }
+
+        #[test]
+        fn into_rpc_error() {
+            use jsonrpsee::types::{error::CallError, Error};
+
+            let error = FeltParseError::ExceedsModulus.into_rpc_error(RpcError::InvalidStorageKey);
+            let (code, data) = match error {
+                Error::Call(CallError::Custom { code, data, .. }) => (code, data.unwrap()),
+                other => panic!("Unexpected error: {:?}", other),
+            };
+            assert_eq!(code, ErrorCode::InvalidStorageKey as i32);
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(data.get()).unwrap(),
+                serde_json::json!({ "reason": "value exceeds the field modulus" })
+            );
+        }
+    }
+
     #[test]
     fn zero() {
         const ZERO_HEX_STR: &str = "0x0";
@@ -630,4 +805,56 @@ mod tests {
             });
         }
     }
+
+    mod block_id_as_eip1898 {
+        use crate::core::{StarknetBlockHash, StarknetBlockNumber};
+        use crate::rpc::types::{BlockId, Tag};
+        use pedersen::StarkHash;
+        use pretty_assertions::assert_eq;
+
+        fn hash() -> BlockId {
+            BlockId::Hash(StarknetBlockHash(
+                StarkHash::from_hex_str("0xabcdef").unwrap(),
+            ))
+        }
+
+        #[test]
+        fn hash_object() {
+            let id = serde_json::from_str::<BlockId>(r#"{"block_hash":"0xabcdef"}"#).unwrap();
+            assert_eq!(id, hash());
+            let id = serde_json::from_str::<BlockId>(r#"{"blockHash":"0xabcdef"}"#).unwrap();
+            assert_eq!(id, hash());
+        }
+
+        #[test]
+        fn number_object() {
+            let expected = BlockId::Number(StarknetBlockNumber(0x1a));
+            let id = serde_json::from_str::<BlockId>(r#"{"block_number":"0x1a"}"#).unwrap();
+            assert_eq!(id, expected);
+            let id = serde_json::from_str::<BlockId>(r#"{"blockNumber":"0x1a"}"#).unwrap();
+            assert_eq!(id, expected);
+            let id = serde_json::from_str::<BlockId>(r#"{"block_number":26}"#).unwrap();
+            assert_eq!(id, expected);
+        }
+
+        #[test]
+        fn shorthand() {
+            let id = serde_json::from_str::<BlockId>(r#""latest""#).unwrap();
+            assert_eq!(id, BlockId::Tag(Tag::Latest));
+            let id = serde_json::from_str::<BlockId>(r#""pending""#).unwrap();
+            assert_eq!(id, BlockId::Tag(Tag::Pending));
+            let id = serde_json::from_str::<BlockId>(r#""0xabcdef""#).unwrap();
+            assert_eq!(id, hash());
+            let id = serde_json::from_str::<BlockId>("26").unwrap();
+            assert_eq!(id, BlockId::Number(StarknetBlockNumber(26)));
+        }
+
+        #[test]
+        fn invalid() {
+            serde_json::from_str::<BlockId>(r#"{"block_hash":"0x1","block_number":"0x1"}"#)
+                .unwrap_err();
+            serde_json::from_str::<BlockId>(r#"{"block_tag":"latest"}"#).unwrap_err();
+            serde_json::from_str::<BlockId>(r#""earliest""#).unwrap_err();
+        }
+    }
 }
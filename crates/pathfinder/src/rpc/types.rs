@@ -48,12 +48,89 @@ pub enum BlockNumberOrTag {
     Tag(Tag),
 }
 
+/// Identifies a block by its distance to the latest block, i.e. `{"latest_offset": 3}`
+/// is the block with number `latest - 3`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LatestOffset {
+    pub latest_offset: u64,
+}
+
+/// A wrapper that contains either a [BlockNumberOrTag] or a [LatestOffset].
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum BlockNumberTagOrOffset {
+    /// Number (height) or [Tag](crate::rpc::types::Tag) of a block
+    NumberOrTag(BlockNumberOrTag),
+    /// Offset of a block from the latest block
+    LatestOffset(LatestOffset),
+}
+
+impl From<BlockNumberOrTag> for BlockNumberTagOrOffset {
+    fn from(block: BlockNumberOrTag) -> Self {
+        Self::NumberOrTag(block)
+    }
+}
+
+/// A block identifier which unifies [BlockHashOrTag] and [BlockNumberOrTag].
+///
+/// Apart from the plain hash, number and tag forms, this also accepts the
+/// [EIP-1898](https://eips.ethereum.org/EIPS/eip-1898) style objects
+/// `{"block_hash": "0x..."}` and `{"block_number": "0x..."}`,
+/// see [BlockIdAsEip1898](crate::rpc::serde::BlockIdAsEip1898).
+#[derive(Copy, Clone, Debug, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum BlockId {
+    /// Hash of a block
+    Hash(StarknetBlockHash),
+    /// Number (height) of a block
+    Number(StarknetBlockNumber),
+    /// Special [Tag](crate::rpc::types::Tag) describing a block
+    Tag(Tag),
+}
+
+impl<'de> Deserialize<'de> for BlockId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde_with::DeserializeAs;
+
+        crate::rpc::serde::BlockIdAsEip1898::deserialize_as(deserializer)
+    }
+}
+
+impl From<BlockHashOrTag> for BlockId {
+    fn from(block: BlockHashOrTag) -> Self {
+        match block {
+            BlockHashOrTag::Hash(hash) => BlockId::Hash(hash),
+            BlockHashOrTag::Tag(tag) => BlockId::Tag(tag),
+        }
+    }
+}
+
+impl From<BlockNumberOrTag> for BlockId {
+    fn from(block: BlockNumberOrTag) -> Self {
+        match block {
+            BlockNumberOrTag::Number(number) => BlockId::Number(number),
+            BlockNumberOrTag::Tag(tag) => BlockId::Tag(tag),
+        }
+    }
+}
+
 /// Groups all strictly input types of the RPC API.
 pub mod request {
     use crate::{
-        core::{CallParam, ContractAddress, EntryPoint, EventKey, StarknetBlockNumber},
-        rpc::serde::H256AsNoLeadingZerosHexStr,
+        core::{
+            CallParam, ContractAddress, EntryPoint, EventKey, StarknetBlockHash,
+            StarknetBlockNumber, StarknetBlockTimestamp, StarknetTransactionHash,
+        },
+        rpc::{
+            serde::H256AsNoLeadingZerosHexStr,
+            types::{reply::RpcError, BlockNumberOrTag},
+        },
     };
+    use pedersen::StarkHash;
     use serde::{Deserialize, Serialize};
     use serde_with::serde_as;
     use web3::types::H256;
@@ -66,6 +143,24 @@ pub mod request {
     #[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
     pub struct OverflowingStorageAddress(#[serde_as(as = "H256AsNoLeadingZerosHexStr")] pub H256);
 
+    /// A transaction hash as sent by the client.
+    ///
+    /// __This type is not checked to be a hex encoded field element__ in contrast to
+    /// [StarknetTransactionHash], so that malformed hashes are reported as
+    /// [invalid transaction hashes](RpcError::InvalidTransactionHash) instead of failing
+    /// to parse the request.
+    #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+    pub struct UncheckedTransactionHash(pub String);
+
+    impl UncheckedTransactionHash {
+        /// Parses the hash, failing with [RpcError::InvalidTransactionHash] if it is malformed.
+        pub fn parse(&self) -> Result<StarknetTransactionHash, RpcError> {
+            StarkHash::from_hex_str(&self.0)
+                .map(StarknetTransactionHash)
+                .map_err(|_| RpcError::InvalidTransactionHash)
+        }
+    }
+
     /// Contains parameters passed to `starknet_call`.
     #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
     #[serde(deny_unknown_fields)]
@@ -73,6 +168,10 @@ pub mod request {
         pub contract_address: ContractAddress,
         pub calldata: Vec<CallParam>,
         pub entry_point_selector: EntryPoint,
+        /// The caller seen by the called contract through `get_caller_address()`,
+        /// the zero address if not specified.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub sender_address: Option<ContractAddress>,
     }
 
     /// Determines the type of response to block related queries.
@@ -93,35 +192,132 @@ pub mod request {
         }
     }
 
+    /// Determines how the keys of an [EventFilter] are matched against event keys.
+    #[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub enum KeyMatchMode {
+        /// Matches events which have any of the filter's keys.
+        #[serde(rename = "EXACT")]
+        Exact,
+        /// Matches events whose first key starts with any of the filter's keys,
+        /// comparing their hex representations without leading zeros.
+        #[serde(rename = "PREFIX")]
+        Prefix,
+    }
+
+    impl Default for KeyMatchMode {
+        fn default() -> Self {
+            KeyMatchMode::Exact
+        }
+    }
+
+    /// The contract address, or list of addresses, of an [EventFilter].
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(untagged)]
+    pub enum EventAddressFilter {
+        /// Matches the events emitted by this contract.
+        Single(ContractAddress),
+        /// Matches the events emitted by any of these contracts.
+        Multiple(Vec<ContractAddress>),
+    }
+
+    impl EventAddressFilter {
+        pub fn addresses(&self) -> &[ContractAddress] {
+            match self {
+                EventAddressFilter::Single(address) => std::slice::from_ref(address),
+                EventAddressFilter::Multiple(addresses) => addresses,
+            }
+        }
+    }
+
+    impl From<ContractAddress> for EventAddressFilter {
+        fn from(address: ContractAddress) -> Self {
+            Self::Single(address)
+        }
+    }
+
     /// Contains event filter parameters passed to `starknet_getEvents`.
+    ///
+    /// Each end of the block range may be given either by number or by hash,
+    /// but not both.
     #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
     #[serde(deny_unknown_fields)]
     pub struct EventFilter {
         #[serde(default, rename = "fromBlock")]
         pub from_block: Option<StarknetBlockNumber>,
+        /// Both tags include all indexed blocks, [pending](crate::rpc::types::Tag::Pending)
+        /// additionally includes the events of the pending block.
         #[serde(default, rename = "toBlock")]
-        pub to_block: Option<StarknetBlockNumber>,
+        pub to_block: Option<BlockNumberOrTag>,
+        #[serde(default, rename = "fromBlockHash")]
+        pub from_block_hash: Option<StarknetBlockHash>,
+        /// Only the indexed chain is stored, so this also restricts the events to the ancestry
+        /// of the given block. Following another fork is not supported.
+        #[serde(default, rename = "toBlockHash")]
+        pub to_block_hash: Option<StarknetBlockHash>,
+        /// Restricts the events to blocks with at least this timestamp, in seconds since the
+        /// Unix epoch.
+        #[serde(default, rename = "fromTimestamp")]
+        pub from_timestamp: Option<StarknetBlockTimestamp>,
+        /// Restricts the events to blocks with at most this timestamp, in seconds since the
+        /// Unix epoch.
+        #[serde(default, rename = "toTimestamp")]
+        pub to_timestamp: Option<StarknetBlockTimestamp>,
         #[serde(default)]
-        pub address: Option<ContractAddress>,
+        pub address: Option<EventAddressFilter>,
+        /// Events emitted by this contract are left out.
+        #[serde(default, rename = "excludeAddress")]
+        pub exclude_address: Option<ContractAddress>,
         #[serde(default)]
         pub keys: Vec<EventKey>,
+        #[serde(default, rename = "keyMatch")]
+        pub key_match: KeyMatchMode,
 
         // These are inlined here because serde flatten and deny_unknown_fields
         // don't work together.
-        pub page_size: usize,
+        /// The node's default page size is used if [None].
+        #[serde(default)]
+        pub page_size: Option<usize>,
         pub page_number: usize,
+        /// Resumes a partial page, see [GetEventsResult::continuation_token]. Takes precedence
+        /// over the page number.
+        #[serde(default)]
+        pub continuation_token: Option<usize>,
+        /// Requests [GetEventsResult::total_count] and [GetEventsResult::total_pages], which
+        /// cost an additional query.
+        #[serde(default)]
+        pub include_count: bool,
+    }
+
+    /// Contains event filter parameters passed to `starknet_subscribeEvents`.
+    ///
+    /// Events are matched in the same way as by an [EventFilter], the block range
+    /// being each new block.
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct EventSubscriptionFilter {
+        #[serde(default)]
+        pub address: Option<ContractAddress>,
+        /// Events emitted by this contract are left out.
+        #[serde(default, rename = "excludeAddress")]
+        pub exclude_address: Option<ContractAddress>,
+        #[serde(default)]
+        pub keys: Vec<EventKey>,
+        #[serde(default, rename = "keyMatch")]
+        pub key_match: KeyMatchMode,
     }
 }
 
 /// Groups all strictly output types of the RPC API.
 pub mod reply {
     // At the moment both reply types are the same for get_code, hence the re-export
-    use super::request::BlockResponseScope;
+    use super::request::{BlockResponseScope, OverflowingStorageAddress};
     use crate::{
         core::{
-            CallParam, ContractAddress, EntryPoint, EventData, EventKey, GasPrice, GlobalRoot,
-            SequencerAddress, StarknetBlockHash, StarknetBlockNumber, StarknetBlockTimestamp,
-            StarknetTransactionHash,
+            CallParam, CallResultValue, ContractAddress, ContractCode, EntryPoint, EventData,
+            EventKey, GasPrice, GlobalRoot, SequencerAddress, StarknetBlockHash,
+            StarknetBlockNumber, StarknetBlockTimestamp, StarknetTransactionHash,
+            StarknetTransactionIndex, StorageAddress, StorageValue,
         },
         rpc::{api::RawBlock, serde::GasPriceAsHexStr},
         sequencer::reply as seq,
@@ -199,6 +395,10 @@ pub mod reply {
         #[serde_as(as = "GasPriceAsHexStr")]
         pub gas_price: GasPrice,
         pub transactions: Transactions,
+        /// Transactions of this block which were left out of `transactions` because
+        /// their stored data could not be read.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub skipped_transactions: Vec<StarknetTransactionHash>,
     }
 
     impl Block {
@@ -215,6 +415,7 @@ pub mod reply {
                 accepted_time: block.timestamp,
                 gas_price: block.gas_price,
                 transactions,
+                skipped_transactions: Vec::new(),
             }
         }
 
@@ -275,10 +476,51 @@ pub mod reply {
                         )
                     }
                 },
+                skipped_transactions: Vec::new(),
             }
         }
     }
 
+    /// Header of a newly accepted L2 block, as pushed to `starknet_subscribeNewHeads`
+    /// subscribers.
+    #[serde_as]
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct BlockHeader {
+        pub block_hash: StarknetBlockHash,
+        pub parent_hash: StarknetBlockHash,
+        pub block_number: StarknetBlockNumber,
+        pub status: BlockStatus,
+        pub sequencer: SequencerAddress,
+        pub new_root: GlobalRoot,
+        pub accepted_time: StarknetBlockTimestamp,
+        #[serde_as(as = "GasPriceAsHexStr")]
+        pub gas_price: GasPrice,
+    }
+
+    impl BlockHeader {
+        /// Constructs [BlockHeader] from [sequencer's block representation](crate::sequencer::reply::Block),
+        /// or [None] if the block is pending and so has no hash, number or root yet.
+        pub fn from_sequencer(block: &seq::Block) -> Option<Self> {
+            Some(Self {
+                block_hash: block.block_hash?,
+                parent_hash: block.parent_block_hash,
+                block_number: block.block_number?,
+                status: block.status.into(),
+                sequencer: block
+                    .sequencer_address
+                    // Default value for cairo <0.8.0 is 0
+                    .unwrap_or(SequencerAddress(StarkHash::ZERO)),
+                new_root: block.state_root?,
+                accepted_time: block.timestamp,
+                gas_price: block
+                    .gas_price
+                    // Default value for cairo <0.8.2 is 0
+                    .unwrap_or(GasPrice::ZERO),
+            })
+        }
+    }
+
     /// Starkware specific RPC error codes.
     // TODO verify with Starkware how `sequencer::reply::starknet::ErrorCode` should
     // map to the values below in all JSON-RPC API methods. Also verify if
@@ -295,6 +537,7 @@ pub mod reply {
         InvalidBlockNumber = 26,
         InvalidTransactionIndex = 27,
         PageSizeTooBig = 31,
+        TooManyKeysInFilter = 34,
         ContractError = 40,
         InvalidContractDefinition = 50,
     }
@@ -377,6 +620,7 @@ pub mod reply {
                 26 => InvalidBlockNumber,
                 27 => InvalidTransactionIndex,
                 31 => PageSizeTooBig,
+                34 => TooManyKeysInFilter,
                 40 => ContractError,
                 50 => InvalidContractDefinition,
                 x => return Err(x),
@@ -397,6 +641,7 @@ pub mod reply {
                 ErrorCode::InvalidBlockNumber => "Invalid block number",
                 ErrorCode::InvalidTransactionIndex => "Invalid transaction index in a block",
                 ErrorCode::PageSizeTooBig => "Requested page size is too big",
+                ErrorCode::TooManyKeysInFilter => "Too many keys provided in a filter",
                 ErrorCode::ContractError => "Contract error",
                 ErrorCode::InvalidContractDefinition => "Invalid contract definition",
             }
@@ -409,12 +654,78 @@ pub mod reply {
         }
     }
 
-    impl From<ErrorCode> for Error {
-        fn from(ecode: ErrorCode) -> Self {
+    /// Starkware specific RPC errors, along with any additional data which is returned
+    /// to the client.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum RpcError {
+        FailedToReceiveTransaction,
+        ContractNotFound,
+        InvalidMessageSelector,
+        InvalidCallData,
+        CallReverted { revert_reason: String },
+        InvalidStorageKey,
+        InvalidBlockHash,
+        InvalidTransactionHash,
+        InvalidBlockNumber,
+        InvalidTransactionIndex { transaction_count: usize },
+        PageSizeTooBig { max_page_size: usize },
+        TooManyKeysInFilter { max_keys: usize },
+        ContractError,
+        InvalidContractDefinition,
+    }
+
+    impl RpcError {
+        /// The [ErrorCode] of this error.
+        pub fn code(&self) -> ErrorCode {
+            match self {
+                RpcError::FailedToReceiveTransaction => ErrorCode::FailedToReceiveTransaction,
+                RpcError::ContractNotFound => ErrorCode::ContractNotFound,
+                RpcError::InvalidMessageSelector => ErrorCode::InvalidMessageSelector,
+                RpcError::InvalidCallData | RpcError::CallReverted { .. } => {
+                    ErrorCode::InvalidCallData
+                }
+                RpcError::InvalidStorageKey => ErrorCode::InvalidStorageKey,
+                RpcError::InvalidBlockHash => ErrorCode::InvalidBlockHash,
+                RpcError::InvalidTransactionHash => ErrorCode::InvalidTransactionHash,
+                RpcError::InvalidBlockNumber => ErrorCode::InvalidBlockNumber,
+                RpcError::InvalidTransactionIndex { .. } => ErrorCode::InvalidTransactionIndex,
+                RpcError::PageSizeTooBig { .. } => ErrorCode::PageSizeTooBig,
+                RpcError::TooManyKeysInFilter { .. } => ErrorCode::TooManyKeysInFilter,
+                RpcError::ContractError => ErrorCode::ContractError,
+                RpcError::InvalidContractDefinition => ErrorCode::InvalidContractDefinition,
+            }
+        }
+
+        /// Additional data of this error, if any.
+        pub fn data(&self) -> Option<serde_json::Value> {
+            match self {
+                RpcError::InvalidTransactionIndex { transaction_count } => {
+                    Some(serde_json::json!({ "transaction_count": transaction_count }))
+                }
+                RpcError::PageSizeTooBig { max_page_size } => {
+                    Some(serde_json::json!({ "max_page_size": max_page_size }))
+                }
+                RpcError::TooManyKeysInFilter { max_keys } => {
+                    Some(serde_json::json!({ "max_keys": max_keys }))
+                }
+                RpcError::CallReverted { revert_reason } => {
+                    Some(serde_json::json!({ "revert_reason": revert_reason }))
+                }
+                _ => None,
+            }
+        }
+    }
+
+    impl From<RpcError> for Error {
+        fn from(e: RpcError) -> Self {
+            let code = e.code();
             Error::Call(CallError::Custom {
-                code: ecode as i32,
-                message: ecode.to_string(),
-                data: None,
+                code: code as i32,
+                message: code.to_string(),
+                // Serializing a json value cannot fail.
+                data: e.data().map(|data| {
+                    serde_json::value::RawValue::from_string(data.to_string()).unwrap()
+                }),
             })
         }
     }
@@ -470,6 +781,9 @@ pub mod reply {
         pub entry_point_selector: Option<EntryPoint>,
         /// Absent for "deploy" transactions
         pub calldata: Option<Vec<CallParam>>,
+        /// Only present for transactions which are not part of an accepted block yet.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub status: Option<TransactionStatus>,
     }
 
     impl TryFrom<seq::Transaction> for Transaction {
@@ -484,6 +798,7 @@ pub mod reply {
                 contract_address: txn.contract_address,
                 entry_point_selector: txn.entry_point_selector,
                 calldata: txn.calldata,
+                status: None,
             })
         }
     }
@@ -495,6 +810,7 @@ pub mod reply {
                 contract_address: txn.contract_address,
                 entry_point_selector: txn.entry_point_selector,
                 calldata: txn.calldata,
+                status: None,
             }
         }
     }
@@ -508,15 +824,26 @@ pub mod reply {
         pub messages_sent: Vec<transaction_receipt::MessageToL1>,
         pub l1_origin_message: Option<transaction_receipt::MessageToL2>,
         pub events: Vec<transaction_receipt::Event>,
+        /// The reason why the sequencer rejected the transaction, `null` for accepted transactions.
+        pub rejection_reason: Option<String>,
     }
 
     impl TransactionReceipt {
+        /// Transactions the sequencer rejected are reported as [rejected](TransactionStatus::Rejected)
+        /// whatever the `status` of their block, with the reason as the status data.
         pub fn with_status(receipt: seq::transaction::Receipt, status: BlockStatus) -> Self {
+            let rejection_reason = receipt
+                .transaction_failure_reason
+                .map(|failure| failure.error_message);
+            let status = match rejection_reason {
+                Some(_) => TransactionStatus::Rejected,
+                None => status.into(),
+            };
+
             Self {
                 txn_hash: receipt.transaction_hash,
-                status: status.into(),
-                // TODO at the moment not available in sequencer replies
-                status_data: String::new(),
+                status,
+                status_data: rejection_reason.clone().unwrap_or_default(),
                 messages_sent: receipt
                     .l2_to_l1_messages
                     .into_iter()
@@ -530,6 +857,7 @@ pub mod reply {
                     .into_iter()
                     .map(transaction_receipt::Event::from)
                     .collect(),
+                rejection_reason,
             }
         }
     }
@@ -705,6 +1033,22 @@ pub mod reply {
         }
     }
 
+    /// The catch-up progress of the sync process, as returned by `starknet_syncStats`.
+    ///
+    /// The block numbers are [None] until the sync process has learned of the latest block.
+    #[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct SyncStats {
+        pub current_block_num: Option<StarknetBlockNumber>,
+        pub highest_block_num: Option<StarknetBlockNumber>,
+        pub blocks_behind: Option<u64>,
+        /// The rate at which the most recent blocks were stored, [None] until there are
+        /// enough of them.
+        pub blocks_per_second: Option<f64>,
+        /// How long it takes to store the blocks behind at the current rate.
+        pub estimated_seconds_remaining: Option<f64>,
+    }
+
     /// Describes an emitted event returned by starknet_getEvents
     #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
     #[serde(deny_unknown_fields)]
@@ -712,8 +1056,10 @@ pub mod reply {
         pub data: Vec<EventData>,
         pub keys: Vec<EventKey>,
         pub from_address: ContractAddress,
-        pub block_hash: StarknetBlockHash,
-        pub block_number: StarknetBlockNumber,
+        /// [None] for events of the pending block.
+        pub block_hash: Option<StarknetBlockHash>,
+        /// [None] for events of the pending block.
+        pub block_number: Option<StarknetBlockNumber>,
         pub transaction_hash: StarknetTransactionHash,
     }
 
@@ -723,8 +1069,8 @@ pub mod reply {
                 data: event.data,
                 keys: event.keys,
                 from_address: event.from_address,
-                block_hash: event.block_hash,
-                block_number: event.block_number,
+                block_hash: Some(event.block_hash),
+                block_number: Some(event.block_number),
                 transaction_hash: event.transaction_hash,
             }
         }
@@ -737,6 +1083,157 @@ pub mod reply {
         pub events: Vec<EmittedEvent>,
         pub page_number: usize,
         pub is_last_page: bool,
+        /// Set if the query ran past the node's soft deadline, in which case the page only
+        /// contains the events gathered until then. Passing the token on in an otherwise
+        /// identical filter returns the events following them.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub continuation_token: Option<usize>,
+        /// The number of events matching the filter across all pages, only set if
+        /// [requested](crate::rpc::types::request::EventFilter::include_count).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub total_count: Option<usize>,
+        /// The number of pages of the requested size needed for [Self::total_count] events.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub total_pages: Option<usize>,
+    }
+
+    /// The position of a transaction within its block, as returned by
+    /// `starknet_getTransactionIndex`.
+    #[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct TransactionIndex {
+        pub block_hash: StarknetBlockHash,
+        pub transaction_index: StarknetTransactionIndex,
+    }
+
+    /// The messages of a transaction, as returned by `starknet_getMessagesByTransaction`.
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct TransactionMessages {
+        /// The L1-to-L2 message consumed by the transaction, if any.
+        pub l1_to_l2_consumed_message: Option<transaction_messages::L1ToL2Message>,
+        /// The L2-to-L1 messages sent by the transaction, in the order in which they were sent.
+        pub l2_to_l1_messages: Vec<transaction_messages::L2ToL1Message>,
+    }
+
+    /// Transaction messages related substructures.
+    pub mod transaction_messages {
+        use crate::{
+            core::{
+                ContractAddress, EntryPoint, EthereumAddress, L1ToL2MessageNonce,
+                L1ToL2MessagePayloadElem, L2ToL1MessagePayloadElem,
+            },
+            rpc::serde::EthereumAddressAsHexStr,
+            sequencer::reply::transaction,
+        };
+        use serde::{Deserialize, Serialize};
+        use serde_with::serde_as;
+
+        /// Message sent from L1 to L2 and consumed by a transaction.
+        #[serde_as]
+        #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+        #[serde(deny_unknown_fields)]
+        pub struct L1ToL2Message {
+            #[serde_as(as = "EthereumAddressAsHexStr")]
+            pub from_address: EthereumAddress,
+            pub to_address: ContractAddress,
+            pub selector: EntryPoint,
+            pub payload: Vec<L1ToL2MessagePayloadElem>,
+            pub nonce: Option<L1ToL2MessageNonce>,
+        }
+
+        impl From<transaction::L1ToL2Message> for L1ToL2Message {
+            fn from(msg: transaction::L1ToL2Message) -> Self {
+                Self {
+                    from_address: msg.from_address,
+                    to_address: msg.to_address,
+                    selector: msg.selector,
+                    payload: msg.payload,
+                    nonce: msg.nonce,
+                }
+            }
+        }
+
+        /// Message sent from L2 to L1 by a transaction.
+        #[serde_as]
+        #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+        #[serde(deny_unknown_fields)]
+        pub struct L2ToL1Message {
+            pub from_address: ContractAddress,
+            #[serde_as(as = "EthereumAddressAsHexStr")]
+            pub to_address: EthereumAddress,
+            pub payload: Vec<L2ToL1MessagePayloadElem>,
+        }
+
+        impl From<transaction::L2ToL1Message> for L2ToL1Message {
+            fn from(msg: transaction::L2ToL1Message) -> Self {
+                Self {
+                    from_address: msg.from_address,
+                    to_address: msg.to_address,
+                    payload: msg.payload,
+                }
+            }
+        }
+    }
+
+    /// A block's number and hash, as returned by `starknet_getRecentBlockHashes`.
+    #[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct BlockHashAndNumber {
+        pub block_number: StarknetBlockNumber,
+        pub block_hash: StarknetBlockHash,
+    }
+
+    /// A storage entry whose value differs between two blocks, as returned by
+    /// `starknet_getStorageDiff`. Unset entries have a zero value.
+    #[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct StorageDiffEntry {
+        pub key: StorageAddress,
+        pub old_value: StorageValue,
+        pub new_value: StorageValue,
+    }
+
+    /// The result of reading a single key in `starknet_getStorageAtKeys`.
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    pub struct StorageAtKey {
+        pub key: OverflowingStorageAddress,
+        #[serde(flatten)]
+        pub result: StorageAtKeyResult,
+    }
+
+    /// Either the value stored at a key, or the reason why the key is invalid.
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    pub enum StorageAtKeyResult {
+        #[serde(rename = "value")]
+        Value(StorageValue),
+        #[serde(rename = "invalid_key")]
+        InvalidKey(InvalidStorageKey),
+    }
+
+    /// The reason why a storage key is invalid.
+    #[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+    pub enum InvalidStorageKey {
+        /// The key is not less than the field modulus.
+        #[serde(rename = "EXCEEDS_FIELD_MODULUS")]
+        ExceedsFieldModulus,
+        /// The key has more than 251 bits.
+        #[serde(rename = "MORE_THAN_251_BITS")]
+        MoreThan251Bits,
+    }
+
+    /// The trace of a call and of the calls it made in turn, as returned by
+    /// `starknet_traceTransaction`.
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct CallTrace {
+        pub contract_address: ContractAddress,
+        pub caller_address: ContractAddress,
+        /// Missing for calls to a default entry point.
+        pub entry_point_selector: Option<EntryPoint>,
+        pub calldata: Vec<CallParam>,
+        pub result: Vec<CallResultValue>,
+        pub internal_calls: Vec<CallTrace>,
     }
 
     // Result type for starknet_addInvokeTransaction
@@ -746,6 +1243,88 @@ pub mod reply {
         pub transaction_hash: StarknetTransactionHash,
     }
 
+    /// Describes a contract's ABI, parsed into its functions and events.
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct ContractAbi {
+        pub functions: Vec<contract_abi::Function>,
+        pub events: Vec<contract_abi::Event>,
+    }
+
+    impl ContractAbi {
+        /// Parses the ABI json as stored alongside the contract code.
+        ///
+        /// Entries other than functions and events (i.e. structs) are skipped.
+        pub fn from_json(abi: &str) -> serde_json::Result<Self> {
+            use contract_abi::Entry;
+
+            let entries = serde_json::from_str::<Vec<Entry>>(abi)?;
+
+            let mut functions = Vec::new();
+            let mut events = Vec::new();
+            for entry in entries {
+                match entry {
+                    Entry::Function(function) => functions.push(function),
+                    Entry::Event(event) => events.push(event),
+                    Entry::Other => {}
+                }
+            }
+
+            Ok(Self { functions, events })
+        }
+    }
+
+    /// Result type for `starknet_getCode`.
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    pub struct GetCodeResult {
+        #[serde(flatten)]
+        pub code: ContractCode,
+        /// The events of the contract's [ABI](ContractAbi), only present if requested.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub events: Option<Vec<contract_abi::Event>>,
+    }
+
+    /// [ContractAbi] substructures.
+    pub mod contract_abi {
+        use serde::{Deserialize, Serialize};
+
+        /// A single entry of the raw ABI json.
+        #[derive(Deserialize)]
+        #[serde(tag = "type")]
+        pub(super) enum Entry {
+            #[serde(rename = "function")]
+            Function(Function),
+            #[serde(rename = "event")]
+            Event(Event),
+            #[serde(other)]
+            Other,
+        }
+
+        /// An external function of a contract.
+        #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+        pub struct Function {
+            pub name: String,
+            pub inputs: Vec<Member>,
+            pub outputs: Vec<Member>,
+        }
+
+        /// An event a contract may emit.
+        #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+        pub struct Event {
+            pub name: String,
+            pub keys: Vec<Member>,
+            pub data: Vec<Member>,
+        }
+
+        /// A named and typed input, output or event member.
+        #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+        pub struct Member {
+            pub name: String,
+            #[serde(rename = "type")]
+            pub r#type: String,
+        }
+    }
+
     // Result type for starknet_addDeployTransaction
     #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
     #[serde(deny_unknown_fields)]
@@ -753,4 +1332,117 @@ pub mod reply {
         pub transaction_hash: StarknetTransactionHash,
         pub contract_address: ContractAddress,
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{ErrorCode, RpcError};
+        use jsonrpsee::types::{CallError, Error};
+
+        #[test]
+        fn rpc_error_codes() {
+            let cases = [
+                (RpcError::FailedToReceiveTransaction, 1, None),
+                (RpcError::ContractNotFound, 20, None),
+                (RpcError::InvalidMessageSelector, 21, None),
+                (RpcError::InvalidCallData, 22, None),
+                (
+                    RpcError::CallReverted {
+                        revert_reason: "Assertion failed".to_owned(),
+                    },
+                    22,
+                    Some(r#"{"revert_reason":"Assertion failed"}"#),
+                ),
+                (RpcError::InvalidStorageKey, 23, None),
+                (RpcError::InvalidBlockHash, 24, None),
+                (RpcError::InvalidTransactionHash, 25, None),
+                (RpcError::InvalidBlockNumber, 26, None),
+                (
+                    RpcError::InvalidTransactionIndex {
+                        transaction_count: 2,
+                    },
+                    27,
+                    Some(r#"{"transaction_count":2}"#),
+                ),
+                (
+                    RpcError::PageSizeTooBig { max_page_size: 10 },
+                    31,
+                    Some(r#"{"max_page_size":10}"#),
+                ),
+                (
+                    RpcError::TooManyKeysInFilter { max_keys: 3 },
+                    34,
+                    Some(r#"{"max_keys":3}"#),
+                ),
+                (RpcError::ContractError, 40, None),
+                (RpcError::InvalidContractDefinition, 50, None),
+            ];
+
+            for (error, expected_code, expected_data) in cases {
+                assert_eq!(ErrorCode::try_from(expected_code), Ok(error.code()));
+
+                match Error::from(error.clone()) {
+                    Error::Call(CallError::Custom {
+                        code,
+                        message,
+                        data,
+                    }) => {
+                        assert_eq!(code, expected_code, "{:?}", error);
+                        assert_eq!(message, error.code().to_string(), "{:?}", error);
+                        assert_eq!(
+                            data.as_ref().map(|data| data.get()),
+                            expected_data,
+                            "{:?}",
+                            error
+                        );
+                    }
+                    other => panic!("Unexpected error for {:?}: {:?}", error, other),
+                }
+            }
+        }
+
+        /// The field names of `starknet_getEvents` replies must match the specification's
+        /// `EMITTED_EVENT` and result object exactly.
+        #[test]
+        fn get_events_result_field_names() {
+            use super::{EmittedEvent, GetEventsResult};
+            use crate::core::{
+                ContractAddress, EventData, EventKey, StarknetBlockHash, StarknetBlockNumber,
+                StarknetTransactionHash,
+            };
+            use pedersen::StarkHash;
+
+            let result = GetEventsResult {
+                events: vec![EmittedEvent {
+                    data: vec![EventData(StarkHash::from_hex_str("0x1").unwrap())],
+                    keys: vec![EventKey(StarkHash::from_hex_str("0x2").unwrap())],
+                    from_address: ContractAddress(StarkHash::from_hex_str("0x3").unwrap()),
+                    block_hash: Some(StarknetBlockHash(StarkHash::from_hex_str("0x4").unwrap())),
+                    block_number: Some(StarknetBlockNumber(5)),
+                    transaction_hash: StarknetTransactionHash(
+                        StarkHash::from_hex_str("0x6").unwrap(),
+                    ),
+                }],
+                page_number: 0,
+                is_last_page: true,
+                continuation_token: None,
+                total_count: None,
+                total_pages: None,
+            };
+
+            let expected = serde_json::json!({
+                "events": [{
+                    "data": ["0x1"],
+                    "keys": ["0x2"],
+                    "from_address": "0x3",
+                    "block_hash": "0x4",
+                    "block_number": 5,
+                    "transaction_hash": "0x6",
+                }],
+                "page_number": 0,
+                "is_last_page": true,
+            });
+
+            assert_eq!(serde_json::to_value(&result).unwrap(), expected);
+        }
+    }
 }
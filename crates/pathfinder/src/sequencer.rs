@@ -13,11 +13,14 @@ use crate::{
         StorageAddress, StorageValue, TransactionVersion,
     },
     ethereum::Chain,
-    rpc::types::{BlockHashOrTag, BlockNumberOrTag, Tag},
+    rpc::types::{BlockHashOrTag, BlockId, BlockNumberOrTag, Tag},
     sequencer::error::SequencerError,
 };
 use reqwest::Url;
-use std::{borrow::Cow, fmt::Debug, future::Future, result::Result, time::Duration};
+use std::{
+    borrow::Cow, fmt::Debug, future::Future, num::NonZeroUsize, result::Result, sync::Arc,
+    time::Duration,
+};
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
@@ -32,10 +35,16 @@ pub trait ClientApi {
         block_hash: BlockHashOrTag,
     ) -> Result<reply::Block, SequencerError>;
 
+    /// The JSON of the block, exactly as returned by the sequencer.
+    async fn raw_block_by_number(
+        &self,
+        block_number: BlockNumberOrTag,
+    ) -> Result<bytes::Bytes, SequencerError>;
+
     async fn call(
         &self,
         payload: request::Call,
-        block_hash: BlockHashOrTag,
+        block_id: BlockId,
     ) -> Result<reply::Call, SequencerError>;
 
     async fn full_contract(
@@ -97,12 +106,17 @@ pub trait ClientApi {
 /// `backoff [secs] = min((2 ^ N) * 15, 3600) [secs]`
 ///
 /// where `N` is the consecutive retry iteration number `{1, 2, ...}`.
+///
+/// At most [max_in_flight](Client::with_max_in_flight) requests are sent concurrently, further
+/// requests wait for their turn. The limit is shared by all clones of a client.
 #[derive(Debug, Clone)]
 pub struct Client {
     /// This client is internally refcounted
     inner: reqwest::Client,
     /// StarkNet sequencer URL.
     sequencer_url: Url,
+    /// Bounds the number of concurrent requests to the sequencer.
+    in_flight: Arc<tokio::sync::Semaphore>,
 }
 
 /// Helper function which simplifies the handling of optional block hashes in queries.
@@ -114,6 +128,16 @@ fn block_hash_str(hash: BlockHashOrTag) -> (&'static str, Cow<'static, str>) {
     }
 }
 
+/// Helper function which simplifies the handling of optional block identifiers in queries.
+fn block_id_str(block: BlockId) -> (&'static str, Cow<'static, str>) {
+    match block {
+        BlockId::Hash(h) => ("blockHash", h.0.to_hex_str()),
+        BlockId::Number(n) => ("blockNumber", Cow::from(n.0.to_string())),
+        BlockId::Tag(Tag::Latest) => ("blockNumber", Cow::from("null")),
+        BlockId::Tag(Tag::Pending) => ("blockNumber", Cow::from("pending")),
+    }
+}
+
 /// Helper function which simplifies the handling of optional block numbers in queries.
 fn block_number_str(number: BlockNumberOrTag) -> Cow<'static, str> {
     match number {
@@ -196,28 +220,129 @@ where
         .await
 }
 
+/// Maximum number of retries of an add transaction request, see [retry_add_transaction].
+const ADD_TRANSACTION_MAX_RETRIES: usize = 3;
+
+/// Wrapper function to retry add transaction requests a bounded number of times, without
+/// failing if an earlier attempt did add the transaction.
+///
+/// A request which failed with a server error may still have been accepted by the sequencer,
+/// so only connection errors, timeouts and 5xx statuses are retried, at most
+/// [ADD_TRANSACTION_MAX_RETRIES] times. The transaction hash is computed locally by the caller:
+/// if a retry is rejected as a [duplicate](error::StarknetErrorCode::DuplicatedTransaction),
+/// a previous attempt has added the transaction, and `already_added` provides the result.
+pub(crate) async fn retry_add_transaction<T, Fut, FutureFactory>(
+    mut future_factory: FutureFactory,
+    already_added: impl FnOnce() -> T,
+) -> Result<T, SequencerError>
+where
+    Fut: Future<Output = Result<T, SequencerError>>,
+    FutureFactory: FnMut() -> Fut,
+{
+    use self::error::StarknetErrorCode;
+    use crate::retry::Retry;
+    use std::num::{NonZeroU64, NonZeroUsize};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tracing::warn;
+
+    let attempts = AtomicUsize::new(0);
+
+    let result = Retry::exponential(
+        || {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            future_factory()
+        },
+        NonZeroU64::new(2).unwrap(),
+    )
+    .max_num_retries(NonZeroUsize::new(ADD_TRANSACTION_MAX_RETRIES).unwrap())
+    .when(|e| match e {
+        SequencerError::ReqwestError(e) => {
+            let retry = e.is_connect()
+                || e.is_timeout()
+                || matches!(e.status(), Some(status) if status.is_server_error());
+            if retry {
+                warn!(reason=%e, "Adding transaction failed, retrying");
+            }
+            retry
+        }
+        SequencerError::StarknetError(_) => false,
+    })
+    .await;
+
+    match result {
+        Err(SequencerError::StarknetError(e))
+            if e.code == StarknetErrorCode::DuplicatedTransaction
+                && attempts.load(Ordering::Relaxed) > 1 =>
+        {
+            Ok(already_added())
+        }
+        other => other,
+    }
+}
+
 impl Client {
+    /// Timeout of requests to the sequencer unless configured otherwise.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+    /// Maximum number of concurrent requests to the sequencer unless configured otherwise.
+    pub const DEFAULT_MAX_IN_FLIGHT: usize = 64;
+
     /// Creates a new Sequencer client for the given chain.
     pub fn new(chain: Chain) -> reqwest::Result<Self> {
+        Self::with_timeout(chain, Self::DEFAULT_TIMEOUT)
+    }
+
+    /// Creates a new Sequencer client for the given chain, whose requests time out
+    /// after `timeout`.
+    ///
+    /// Timed out requests are retried.
+    pub fn with_timeout(chain: Chain, timeout: Duration) -> reqwest::Result<Self> {
         let url = match chain {
             Chain::Mainnet => Url::parse("https://alpha-mainnet.starknet.io/").unwrap(),
             Chain::Goerli => Url::parse("https://alpha4.starknet.io/").unwrap(),
         };
 
-        Self::with_url(url)
+        Self::with_url_and_timeout(url, timeout)
     }
 
     /// Create a Sequencer client for the given [Url].
-    fn with_url(url: Url) -> reqwest::Result<Self> {
+    #[cfg(test)]
+    pub(crate) fn with_url(url: Url) -> reqwest::Result<Self> {
+        Self::with_url_and_timeout(url, Self::DEFAULT_TIMEOUT)
+    }
+
+    /// Create a Sequencer client for the given [Url], whose requests time out after `timeout`.
+    pub(crate) fn with_url_and_timeout(url: Url, timeout: Duration) -> reqwest::Result<Self> {
         Ok(Self {
             inner: reqwest::Client::builder()
-                .timeout(Duration::from_secs(120))
+                .timeout(timeout)
                 .user_agent(crate::consts::USER_AGENT)
                 .build()?,
             sequencer_url: url,
+            in_flight: Arc::new(tokio::sync::Semaphore::new(Self::DEFAULT_MAX_IN_FLIGHT)),
         })
     }
 
+    /// Limits the number of concurrent requests to the sequencer to `max_in_flight`.
+    ///
+    /// Requests above the limit are queued until a previous request completes.
+    pub fn with_max_in_flight(mut self, max_in_flight: NonZeroUsize) -> Self {
+        self.in_flight = Arc::new(tokio::sync::Semaphore::new(max_in_flight.get()));
+        self
+    }
+
+    /// Waits until a request to the sequencer may be sent. The request should be sent
+    /// while holding the returned permit.
+    ///
+    /// Permits are taken per attempt, so that retries waiting for their backoff do not
+    /// hold up other requests.
+    async fn permit(&self) -> tokio::sync::SemaphorePermit<'_> {
+        self.in_flight
+            .acquire()
+            .await
+            .expect("Semaphore is never closed")
+    }
+
     /// Helper function that constructs a URL for particular query.
     fn build_query(&self, path_segments: &[&str], params: &[(&str, &str)]) -> Url {
         let mut query_url = self.sequencer_url.clone();
@@ -241,6 +366,7 @@ impl ClientApi for Client {
     ) -> Result<reply::Block, SequencerError> {
         let number = block_number_str(block_number);
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(
@@ -262,6 +388,7 @@ impl ClientApi for Client {
     ) -> Result<reply::Block, SequencerError> {
         let (tag, hash) = block_hash_str(block_hash);
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(&["feeder_gateway", "get_block"], &[(tag, &hash)]))
@@ -272,18 +399,43 @@ impl ClientApi for Client {
         .await
     }
 
+    /// Gets the JSON of a block by number, without deserializing it.
+    #[tracing::instrument(skip(self))]
+    async fn raw_block_by_number(
+        &self,
+        block_number: BlockNumberOrTag,
+    ) -> Result<bytes::Bytes, SequencerError> {
+        let number = block_number_str(block_number);
+        retry(|| async {
+            let _permit = self.permit().await;
+            let resp = self
+                .inner
+                .get(self.build_query(
+                    &["feeder_gateway", "get_block"],
+                    &[("blockNumber", &number)],
+                ))
+                .send()
+                .await?;
+            let resp = parse_raw(resp).await?;
+            let resp = resp.bytes().await?;
+            Ok(resp)
+        })
+        .await
+    }
+
     /// Performs a `call` on contract's function. Call result is not stored in L2, as opposed to `invoke`.
     #[tracing::instrument(skip(self))]
     async fn call(
         &self,
         payload: request::Call,
-        block_hash: BlockHashOrTag,
+        block_id: BlockId,
     ) -> Result<reply::Call, SequencerError> {
-        let (tag, hash) = block_hash_str(block_hash);
+        let (tag, id) = block_id_str(block_id);
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
-                .post(self.build_query(&["feeder_gateway", "call_contract"], &[(tag, &hash)]))
+                .post(self.build_query(&["feeder_gateway", "call_contract"], &[(tag, &id)]))
                 .json(&payload)
                 .send()
                 .await?;
@@ -299,6 +451,7 @@ impl ClientApi for Client {
         contract_addr: ContractAddress,
     ) -> Result<bytes::Bytes, SequencerError> {
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(
@@ -326,6 +479,7 @@ impl ClientApi for Client {
 
         let (tag, hash) = block_hash_str(block_hash);
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(
@@ -350,6 +504,7 @@ impl ClientApi for Client {
         transaction_hash: StarknetTransactionHash,
     ) -> Result<reply::Transaction, SequencerError> {
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(
@@ -370,6 +525,7 @@ impl ClientApi for Client {
         transaction_hash: StarknetTransactionHash,
     ) -> Result<reply::TransactionStatus, SequencerError> {
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(
@@ -391,6 +547,7 @@ impl ClientApi for Client {
     ) -> Result<reply::StateUpdate, SequencerError> {
         let (tag, hash) = block_hash_str(block_hash);
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(&["feeder_gateway", "get_state_update"], &[(tag, &hash)]))
@@ -408,6 +565,7 @@ impl ClientApi for Client {
         block_number: BlockNumberOrTag,
     ) -> Result<reply::StateUpdate, SequencerError> {
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(
@@ -425,6 +583,7 @@ impl ClientApi for Client {
     #[tracing::instrument(skip(self))]
     async fn eth_contract_addresses(&self) -> Result<reply::EthContractAddresses, SequencerError> {
         retry(|| async {
+            let _permit = self.permit().await;
             let resp = self
                 .inner
                 .get(self.build_query(&["feeder_gateway", "get_contract_addresses"], &[]))
@@ -456,8 +615,9 @@ impl ClientApi for Client {
 
         // Note that we don't do retries here.
         // This method is used to proxy an add transaction operation from the JSON-RPC
-        // API to the sequencer. Retries should be implemented in the JSON-RPC
-        // client instead.
+        // API to the sequencer, which retries using [retry_add_transaction] as only it
+        // knows the transaction hash.
+        let _permit = self.permit().await;
         let resp = self
             .inner
             .post(self.build_query(&["gateway", "add_transaction"], &[]))
@@ -483,8 +643,9 @@ impl ClientApi for Client {
             });
         // Note that we don't do retries here.
         // This method is used to proxy an add transaction operation from the JSON-RPC
-        // API to the sequencer. Retries should be implemented in the JSON-RPC
-        // client instead.
+        // API to the sequencer, which retries using [retry_add_transaction] as only it
+        // knows the transaction hash.
+        let _permit = self.permit().await;
         let resp = self
             .inner
             .post(self.build_query(&["gateway", "add_transaction"], &[]))
@@ -738,7 +899,7 @@ mod tests {
                         entry_point_selector: *INVALID_ENTRY_POINT,
                         signature: vec![],
                     },
-                    BlockHashOrTag::Tag(Tag::Latest),
+                    BlockId::Tag(Tag::Latest),
                 )
                 .await
                 .unwrap_err();
@@ -758,7 +919,7 @@ mod tests {
                         entry_point_selector: *VALID_ENTRY_POINT,
                         signature: vec![],
                     },
-                    BlockHashOrTag::Tag(Tag::Latest),
+                    BlockId::Tag(Tag::Latest),
                 )
                 .await
                 .unwrap_err();
@@ -778,7 +939,7 @@ mod tests {
                         entry_point_selector: *VALID_ENTRY_POINT,
                         signature: vec![],
                     },
-                    *INVOKE_CONTRACT_BLOCK_HASH,
+                    (*INVOKE_CONTRACT_BLOCK_HASH).into(),
                 )
                 .await
                 .unwrap_err();
@@ -798,7 +959,27 @@ mod tests {
                         entry_point_selector: *VALID_ENTRY_POINT,
                         signature: vec![],
                     },
-                    *GENESIS_BLOCK_HASH,
+                    (*GENESIS_BLOCK_HASH).into(),
+                )
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                SequencerError::StarknetError(e) => assert_eq!(e.code, StarknetErrorCode::UninitializedContract)
+            );
+        }
+
+        #[tokio::test]
+        async fn uninitialized_contract_by_block_number() {
+            let error = client()
+                .call(
+                    request::Call {
+                        calldata: vec![],
+                        contract_address: *VALID_CONTRACT_ADDR,
+                        entry_point_selector: *VALID_ENTRY_POINT,
+                        signature: vec![],
+                    },
+                    (*GENESIS_BLOCK_NUMBER).into(),
                 )
                 .await
                 .unwrap_err();
@@ -818,7 +999,27 @@ mod tests {
                         entry_point_selector: *VALID_ENTRY_POINT,
                         signature: vec![],
                     },
-                    *INVALID_BLOCK_HASH,
+                    (*INVALID_BLOCK_HASH).into(),
+                )
+                .await
+                .unwrap_err();
+            assert_matches!(
+                error,
+                SequencerError::StarknetError(e) => assert_eq!(e.code, StarknetErrorCode::BlockNotFound)
+            );
+        }
+
+        #[tokio::test]
+        async fn invalid_block_number() {
+            let error = client()
+                .call(
+                    request::Call {
+                        calldata: VALID_CALL_DATA.clone(),
+                        contract_address: *VALID_CONTRACT_ADDR,
+                        entry_point_selector: *VALID_ENTRY_POINT,
+                        signature: vec![],
+                    },
+                    (*INVALID_BLOCK_NUMBER).into(),
                 )
                 .await
                 .unwrap_err();
@@ -838,7 +1039,7 @@ mod tests {
                         entry_point_selector: *VALID_ENTRY_POINT,
                         signature: vec![],
                     },
-                    *INVOKE_CONTRACT_BLOCK_HASH,
+                    (*INVOKE_CONTRACT_BLOCK_HASH).into(),
                 )
                 .await
                 .unwrap();
@@ -854,7 +1055,7 @@ mod tests {
                         entry_point_selector: *VALID_ENTRY_POINT,
                         signature: vec![],
                     },
-                    BlockHashOrTag::Tag(Tag::Latest),
+                    BlockId::Tag(Tag::Latest),
                 )
                 .await
                 .unwrap();
@@ -870,7 +1071,7 @@ mod tests {
                         entry_point_selector: *VALID_ENTRY_POINT,
                         signature: vec![],
                     },
-                    BlockHashOrTag::Tag(Tag::Pending),
+                    BlockId::Tag(Tag::Pending),
                 )
                 .await
                 .unwrap();
@@ -1038,14 +1239,15 @@ mod tests {
             },
             *,
         };
-        use crate::core::{ContractAddress, GlobalRoot};
+        use crate::core::{ContractAddress, ContractNonce, GlobalRoot};
         use pretty_assertions::assert_eq;
-        use std::collections::{BTreeSet, HashMap};
+        use std::collections::{BTreeMap, BTreeSet};
 
         #[derive(Clone, Debug, PartialEq)]
         pub struct OrderedStateDiff {
-            pub storage_diffs: HashMap<ContractAddress, BTreeSet<StorageDiff>>,
+            pub storage_diffs: BTreeMap<ContractAddress, BTreeSet<StorageDiff>>,
             pub deployed_contracts: BTreeSet<Contract>,
+            pub nonces: BTreeMap<ContractAddress, ContractNonce>,
         }
 
         #[derive(Clone, Debug, PartialEq)]
@@ -1068,6 +1270,7 @@ mod tests {
                             .map(|(addr, diffs)| (addr, diffs.into_iter().collect()))
                             .collect(),
                         deployed_contracts: s.state_diff.deployed_contracts.into_iter().collect(),
+                        nonces: s.state_diff.nonces,
                     },
                 }
             }
@@ -1549,5 +1752,85 @@ mod tests {
             // 4th try should have timedout if this is really exponential backoff
             assert_eq!(CNT.load(Ordering::Relaxed), 4);
         }
+
+        #[test_log::test(tokio::test)]
+        async fn client_timeout() {
+            use crate::sequencer::ClientApi;
+
+            let (_jh, addr) = slow_server();
+            let mut url = reqwest::Url::parse("http://localhost/").unwrap();
+            url.set_port(Some(addr.port())).unwrap();
+
+            let client =
+                super::Client::with_url_and_timeout(url.clone(), Duration::from_millis(1)).unwrap();
+
+            let error = client.inner.get(url).send().await.unwrap_err();
+            assert!(error.is_timeout());
+
+            // Timeouts are retried, so the request never completes against the slow server.
+            tokio::time::timeout(Duration::from_millis(250), client.eth_contract_addresses())
+                .await
+                .unwrap_err();
+        }
+    }
+
+    mod max_in_flight {
+        use crate::core::ContractAddress;
+        use crate::sequencer::{Client, ClientApi};
+        use pedersen::StarkHash;
+        use std::num::NonZeroUsize;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+        use warp::Filter;
+
+        /// Spawns a slow mock sequencer which records the highest number of requests
+        /// it was serving at the same time.
+        fn instrumented_server() -> (reqwest::Url, Arc<AtomicUsize>) {
+            let current = Arc::new(AtomicUsize::new(0));
+            let highest = Arc::new(AtomicUsize::new(0));
+            let highest2 = highest.clone();
+
+            let any = warp::any().then(move || {
+                let current = current.clone();
+                let highest = highest2.clone();
+                async move {
+                    let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                    highest.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    current.fetch_sub(1, Ordering::SeqCst);
+                    "contract"
+                }
+            });
+            let (addr, run_srv) = warp::serve(any).bind_ephemeral(([127, 0, 0, 1], 0));
+            tokio::spawn(run_srv);
+
+            let url = reqwest::Url::parse(&format!("http://{}", addr)).unwrap();
+            (url, highest)
+        }
+
+        #[tokio::test]
+        async fn concurrency_never_exceeds_limit() {
+            let (url, highest) = instrumented_server();
+            let client = Client::with_url(url)
+                .unwrap()
+                .with_max_in_flight(NonZeroUsize::new(3).unwrap());
+
+            let requests = (0..20).map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    client
+                        .full_contract(ContractAddress(StarkHash::ZERO))
+                        .await
+                        .unwrap()
+                })
+            });
+            // Requests above the limit are queued, not failed.
+            for response in futures::future::join_all(requests).await {
+                assert_eq!(response.unwrap(), "contract");
+            }
+
+            assert_eq!(highest.load(Ordering::SeqCst), 3);
+        }
     }
 }
@@ -1,5 +1,5 @@
 //! Sequencer related error types.
-use crate::rpc::types::reply::ErrorCode as RpcErrorCode;
+use crate::rpc::types::reply::RpcError;
 use jsonrpsee::types as rpc;
 use serde::{Deserialize, Serialize};
 
@@ -22,28 +22,27 @@ impl From<SequencerError> for rpc::Error {
                 StarknetErrorCode::OutOfRangeBlockHash | StarknetErrorCode::BlockNotFound
                     if e.message.contains("Block hash") =>
                 {
-                    RpcErrorCode::InvalidBlockHash.into()
+                    RpcError::InvalidBlockHash.into()
                 }
                 StarknetErrorCode::OutOfRangeContractAddress
-                | StarknetErrorCode::UninitializedContract => RpcErrorCode::ContractNotFound.into(),
+                | StarknetErrorCode::UninitializedContract => RpcError::ContractNotFound.into(),
                 StarknetErrorCode::OutOfRangeTransactionHash => {
-                    RpcErrorCode::InvalidTransactionHash.into()
-                }
-                StarknetErrorCode::OutOfRangeStorageKey => RpcErrorCode::InvalidStorageKey.into(),
-                StarknetErrorCode::TransactionFailed => RpcErrorCode::InvalidCallData.into(),
-                StarknetErrorCode::EntryPointNotFound => {
-                    RpcErrorCode::InvalidMessageSelector.into()
+                    RpcError::InvalidTransactionHash.into()
                 }
+                StarknetErrorCode::OutOfRangeStorageKey => RpcError::InvalidStorageKey.into(),
+                StarknetErrorCode::TransactionFailed => RpcError::InvalidCallData.into(),
+                StarknetErrorCode::EntryPointNotFound => RpcError::InvalidMessageSelector.into(),
                 StarknetErrorCode::BlockNotFound if e.message.contains("Block number") => {
-                    RpcErrorCode::InvalidBlockNumber.into()
+                    RpcError::InvalidBlockNumber.into()
                 }
                 StarknetErrorCode::InvalidContractDefinition => {
-                    RpcErrorCode::InvalidContractDefinition.into()
+                    RpcError::InvalidContractDefinition.into()
                 }
                 StarknetErrorCode::BlockNotFound
                 | StarknetErrorCode::SchemaValidationError
                 | StarknetErrorCode::MalformedRequest
                 | StarknetErrorCode::UnsupportedSelectorForFee
+                | StarknetErrorCode::DuplicatedTransaction
                 | StarknetErrorCode::OutOfRangeBlockHash => {
                     rpc::Error::Call(rpc::CallError::Failed(e.into()))
                 }
@@ -97,4 +96,6 @@ pub enum StarknetErrorCode {
     UnsupportedSelectorForFee,
     #[serde(rename = "StarknetErrorCode.INVALID_CONTRACT_DEFINITION")]
     InvalidContractDefinition,
+    #[serde(rename = "StarknetErrorCode.DUPLICATED_TRANSACTION")]
+    DuplicatedTransaction,
 }
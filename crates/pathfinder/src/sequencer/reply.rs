@@ -213,6 +213,9 @@ pub mod transaction {
         pub l2_to_l1_messages: Vec<L2ToL1Message>,
         pub transaction_hash: StarknetTransactionHash,
         pub transaction_index: StarknetTransactionIndex,
+        /// Only present if the sequencer rejected the transaction.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub transaction_failure_reason: Option<Failure>,
     }
 
     /// Represents deserialized L2 transaction event data.
@@ -278,7 +281,7 @@ pub mod transaction {
     }
 
     /// Describes L2 transaction failure details.
-    #[derive(Clone, Debug, Deserialize, PartialEq)]
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
     #[serde(deny_unknown_fields)]
     pub struct Failure {
         pub code: String,
@@ -300,23 +303,28 @@ pub struct StateUpdate {
 
 /// Types used when deserializing state update related data.
 pub mod state_update {
-    use crate::core::{ContractAddress, ContractHash, StorageAddress, StorageValue};
-    use serde::Deserialize;
+    use crate::core::{ContractAddress, ContractHash, ContractNonce, StorageAddress, StorageValue};
+    use serde::{Deserialize, Serialize};
     use serde_with::serde_as;
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
     /// L2 state diff.
+    ///
+    /// The maps are ordered by contract address, so that the diff always serializes identically.
     #[serde_as]
-    #[derive(Clone, Debug, Deserialize, PartialEq)]
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
     #[serde(deny_unknown_fields)]
     pub struct StateDiff {
-        #[serde_as(as = "HashMap<_, Vec<_>>")]
-        pub storage_diffs: HashMap<ContractAddress, Vec<StorageDiff>>,
+        #[serde_as(as = "BTreeMap<_, Vec<_>>")]
+        pub storage_diffs: BTreeMap<ContractAddress, Vec<StorageDiff>>,
         pub deployed_contracts: Vec<Contract>,
+        /// The new nonces of the contracts whose nonce changed. Absent in older replies.
+        #[serde(default)]
+        pub nonces: BTreeMap<ContractAddress, ContractNonce>,
     }
 
     /// L2 storage diff.
-    #[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
     #[serde(deny_unknown_fields)]
     pub struct StorageDiff {
         pub key: StorageAddress,
@@ -324,12 +332,58 @@ pub mod state_update {
     }
 
     /// L2 contract data within state diff.
-    #[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
     #[serde(deny_unknown_fields)]
     pub struct Contract {
         pub address: ContractAddress,
         pub contract_hash: ContractHash,
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use pedersen::StarkHash;
+
+        #[test]
+        fn serializes_with_sorted_keys() {
+            let address = |hex| ContractAddress(StarkHash::from_hex_str(hex).unwrap());
+            let diff = |key, value| StorageDiff {
+                key: StorageAddress(StarkHash::from_hex_str(key).unwrap()),
+                value: StorageValue(StarkHash::from_hex_str(value).unwrap()),
+            };
+            let nonce = |hex| ContractNonce(StarkHash::from_hex_str(hex).unwrap());
+
+            // Inserted in reverse order of their addresses.
+            let state_diff = StateDiff {
+                storage_diffs: [
+                    (address("0x3"), vec![diff("0x1", "0x2")]),
+                    (address("0x2"), vec![diff("0x3", "0x4")]),
+                    (address("0x1"), vec![diff("0x5", "0x6")]),
+                ]
+                .into_iter()
+                .collect(),
+                deployed_contracts: vec![],
+                nonces: [
+                    (address("0x3"), nonce("0x1")),
+                    (address("0x1"), nonce("0x2")),
+                ]
+                .into_iter()
+                .collect(),
+            };
+
+            assert_eq!(
+                serde_json::to_string(&state_diff).unwrap(),
+                concat!(
+                    r#"{"storage_diffs":{"#,
+                    r#""0x1":[{"key":"0x5","value":"0x6"}],"#,
+                    r#""0x2":[{"key":"0x3","value":"0x4"}],"#,
+                    r#""0x3":[{"key":"0x1","value":"0x2"}]"#,
+                    r#"},"deployed_contracts":[],"#,
+                    r#""nonces":{"0x1":"0x2","0x3":"0x1"}}"#
+                )
+            );
+        }
+    }
 }
 
 /// Used to deserialize a reply from [ClientApi::eth_contract_addresses](crate::sequencer::ClientApi::eth_contract_addresses).
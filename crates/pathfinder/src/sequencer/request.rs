@@ -30,6 +30,8 @@ impl From<rpc::Call> for Call {
             // For the time being the RPC API does not use signatures here and we can pass
             // empty signature to the sequencer API safely
             signature: vec![],
+            // The sequencer API does not support specifying the caller, so `sender_address`
+            // only affects calls executed locally.
         }
     }
 }
@@ -72,16 +74,21 @@ pub mod contract {
 pub mod add_transaction {
     use std::collections::HashMap;
 
-    use crate::core::{ConstructorParam, ContractAddressSalt, TransactionVersion};
+    use crate::core::{
+        ConstructorParam, ContractAddressSalt, StarknetTransactionHash, TransactionVersion,
+    };
     use crate::rpc::serde::{
         CallParamAsDecimalStr, CallSignatureElemAsDecimalStr, FeeAsHexStr,
         TransactionVersionAsHexStr,
     };
 
+    use anyhow::Context;
+    use pedersen::StarkHash;
     use serde_with::serde_as;
 
     use super::contract::{EntryPointType, SelectorAndOffset};
     use super::{CallParam, CallSignatureElem, ContractAddress, EntryPoint, Fee};
+    use crate::state::contract_hash::{compute_contract_address, compute_contract_hash, HashChain};
 
     /// Definition of a contract.
     ///
@@ -97,7 +104,7 @@ pub mod add_transaction {
     }
 
     /// Contract deployment transaction details.
-    #[derive(serde::Deserialize, serde::Serialize)]
+    #[derive(Clone, serde::Deserialize, serde::Serialize)]
     pub struct Deploy {
         pub contract_address_salt: ContractAddressSalt,
         pub contract_definition: ContractDefinition,
@@ -124,6 +131,114 @@ pub mod add_transaction {
         pub signature: Vec<CallSignatureElem>,
     }
 
+    impl ContractDefinition {
+        /// Computes the [contract hash](crate::core::ContractHash) of this definition.
+        ///
+        /// The program is decoded from its gzip + base64 representation and re-assembled
+        /// into the full contract definition json blob for [compute_contract_hash].
+        pub fn hash(&self) -> anyhow::Result<crate::core::ContractHash> {
+            use std::io::Read;
+
+            let program = base64::decode(&self.program).context("Decoding program base64")?;
+            let mut decompressed = Vec::new();
+            flate2::read::GzDecoder::new(program.as_slice())
+                .read_to_end(&mut decompressed)
+                .context("Decompressing program")?;
+            let program = serde_json::from_slice::<serde_json::Value>(&decompressed)
+                .context("Parsing program")?;
+
+            let definition = serde_json::json!({
+                "abi": self.abi,
+                "entry_points_by_type": self.entry_points_by_type,
+                "program": program,
+            });
+            let definition =
+                serde_json::to_vec(&definition).context("Serializing contract definition")?;
+
+            compute_contract_hash(&definition)
+        }
+    }
+
+    impl Deploy {
+        /// Computes the address the contract will be deployed at.
+        ///
+        /// See: <https://starknet.io/documentation/contracts/#contract_address>
+        pub fn contract_address(&self) -> anyhow::Result<ContractAddress> {
+            // Deploy transactions have no caller.
+            const CALLER_ADDRESS: ContractAddress = ContractAddress(StarkHash::ZERO);
+
+            let contract_hash = self.contract_definition.hash()?;
+
+            Ok(compute_contract_address(
+                self.contract_address_salt,
+                contract_hash,
+                &self.constructor_calldata,
+                CALLER_ADDRESS,
+            ))
+        }
+
+        /// Computes the hash of this deploy transaction, which deploys to `contract_address`,
+        /// on the chain identified by `chain_id`.
+        pub fn transaction_hash(
+            &self,
+            contract_address: ContractAddress,
+            chain_id: StarkHash,
+        ) -> StarknetTransactionHash {
+            // Deploy transactions are always version 0 and free of charge.
+            const VERSION: StarkHash = StarkHash::ZERO;
+            const MAX_FEE: StarkHash = StarkHash::ZERO;
+
+            let mut hash = HashChain::default();
+            hash.update(StarkHash::from_be_slice(b"deploy").unwrap());
+            hash.update(VERSION);
+            hash.update(contract_address.0);
+            hash.update(EntryPoint::hashed(&b"constructor"[..]).0);
+            hash.update(hash_on_elements(
+                self.constructor_calldata.iter().map(|p| p.0),
+            ));
+            hash.update(MAX_FEE);
+            hash.update(chain_id);
+
+            StarknetTransactionHash(hash.finalize())
+        }
+    }
+
+    impl InvokeFunction {
+        /// Computes the hash of this invoke transaction on the chain identified by `chain_id`.
+        ///
+        /// Fails if the version does not fit into a [StarkHash].
+        pub fn transaction_hash(
+            &self,
+            chain_id: StarkHash,
+        ) -> anyhow::Result<StarknetTransactionHash> {
+            let version = StarkHash::from_be_slice(self.version.0.as_bytes())
+                .context("Transaction version out of range")?;
+            let max_fee = StarkHash::from_be_slice(self.max_fee.0.as_bytes())
+                .expect("Fee is smaller than 251-bits");
+
+            let mut hash = HashChain::default();
+            hash.update(StarkHash::from_be_slice(b"invoke").unwrap());
+            hash.update(version);
+            hash.update(self.contract_address.0);
+            hash.update(self.entry_point_selector.0);
+            hash.update(hash_on_elements(self.calldata.iter().map(|p| p.0)));
+            hash.update(max_fee);
+            hash.update(chain_id);
+
+            Ok(StarknetTransactionHash(hash.finalize()))
+        }
+    }
+
+    /// Hash chains the given elements, i.e. `compute_hash_on_elements` on the cairo side.
+    fn hash_on_elements(elements: impl Iterator<Item = StarkHash>) -> StarkHash {
+        elements
+            .fold(HashChain::default(), |mut hc, next| {
+                hc.update(next);
+                hc
+            })
+            .finalize()
+    }
+
     /// Add transaction API operation.
     ///
     /// This adds the "type" attribute to the JSON request according the type of
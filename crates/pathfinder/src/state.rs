@@ -3,15 +3,22 @@ use pedersen::{pedersen_hash, StarkHash};
 use rusqlite::Transaction;
 
 use crate::{
-    core::{ContractHash, ContractRoot, ContractStateHash},
-    ethereum::state_update::ContractUpdate,
+    core::{
+        ContractAddress, ContractHash, ContractRoot, ContractStateHash, GlobalRoot,
+        StarknetBlockNumber, StorageAddress, StorageValue,
+    },
+    ethereum::state_update::{ContractUpdate, StateUpdate},
     state::state_tree::{ContractsStateTree, GlobalStateTree},
-    storage::{ContractsStateTable, ContractsTable},
+    storage::{
+        ContractsStateTable, ContractsTable, StarknetBlocksBlockId, StarknetBlocksTable,
+        StarknetEventsTable, StarknetMessagesTable, StarknetTransactionsTable,
+    },
 };
 
 pub(crate) mod contract_hash;
 mod merkle_node;
 pub(crate) mod merkle_tree;
+pub mod node_cache;
 pub(crate) mod state_tree;
 mod sync;
 
@@ -24,6 +31,8 @@ pub struct CompressedContract {
     pub bytecode: Vec<u8>,
     pub definition: Vec<u8>,
     pub hash: ContractHash,
+    /// The id of the zstd dictionary the columns were compressed with, if any.
+    pub dictionary_id: Option<i64>,
 }
 
 impl std::fmt::Debug for CompressedContract {
@@ -78,6 +87,132 @@ pub(crate) fn update_contract_state(
     Ok(contract_state_hash)
 }
 
+/// Deletes all blocks below `below`, along with their transactions, events, messages and any
+/// state tree nodes which are no longer reachable from the remaining blocks.
+///
+/// The state of the blocks at and above `below` is left intact. Everything happens within
+/// `transaction`, which should be rolled back if this fails.
+pub fn prune_blocks(
+    transaction: &Transaction<'_>,
+    below: StarknetBlockNumber,
+) -> anyhow::Result<()> {
+    StarknetTransactionsTable::prune(transaction, below).context("Delete transactions")?;
+    StarknetEventsTable::prune(transaction, below).context("Delete events")?;
+    StarknetMessagesTable::prune(transaction, below).context("Delete messages")?;
+    let roots = StarknetBlocksTable::prune(transaction, below).context("Delete blocks")?;
+
+    if roots.is_empty() {
+        return Ok(());
+    }
+
+    // Each block committed its global root once, so deleting one instance per block
+    // only removes the nodes which are not shared with the remaining blocks.
+    for root in roots {
+        GlobalStateTree::load(transaction, root)
+            .context("Load global state tree")?
+            .delete()
+            .context("Delete global state tree")?;
+    }
+
+    // Contract states whose leaves got deleted from the global state tree can in turn be
+    // deleted, along with their storage.
+    let orphaned =
+        ContractsStateTable::get_orphaned(transaction).context("Read orphaned contract states")?;
+    for (state_hash, root) in orphaned {
+        ContractsStateTree::load(transaction, root)
+            .context("Load contract state tree")?
+            .delete()
+            .context("Delete contract state tree")?;
+        ContractsStateTable::delete(transaction, state_hash).context("Delete contract state")?;
+    }
+
+    Ok(())
+}
+
+/// The global root computed by [replay_block] differs from the root stored for the block.
+#[derive(Debug, Copy, Clone, PartialEq, thiserror::Error)]
+#[error("Computed global root {} does not match the stored root {}", .computed.0, .stored.0)]
+pub struct RootMismatch {
+    pub computed: GlobalRoot,
+    pub stored: GlobalRoot,
+}
+
+/// Re-applies `state_update`, the state update of `block`, on top of the state of its parent
+/// block and checks that the resulting global root equals the root stored for `block`.
+/// Returns the global root if they match.
+///
+/// State updates are not stored, so the caller has to provide it, i.e. as received from the
+/// sequencer. The state tree changes are made within a database transaction which is rolled
+/// back, leaving the database as it was. Fails if `block` or its parent is not stored.
+pub fn replay_block(
+    connection: &mut rusqlite::Connection,
+    block: StarknetBlockNumber,
+    state_update: StateUpdate,
+) -> anyhow::Result<Result<GlobalRoot, RootMismatch>> {
+    let transaction = connection
+        .transaction()
+        .context("Create database transaction")?;
+
+    let stored = StarknetBlocksTable::get_root(&transaction, block.into())
+        .context("Read global root")?
+        .with_context(|| format!("Block {} is not stored", block.0))?;
+    let parent_root = match block {
+        StarknetBlockNumber::GENESIS => GlobalRoot(StarkHash::ZERO),
+        block => StarknetBlocksTable::get_root(&transaction, (block - 1).into())
+            .context("Read parent global root")?
+            .with_context(|| format!("Parent of block {} is not stored", block.0))?,
+    };
+
+    let computed = sync::apply_state_update(&transaction, parent_root, state_update)
+        .context("Apply state update")?;
+
+    transaction
+        .rollback()
+        .context("Roll back database transaction")?;
+
+    if computed == stored {
+        Ok(Ok(computed))
+    } else {
+        Ok(Err(RootMismatch { computed, stored }))
+    }
+}
+
+/// Returns an iterator over all storage entries of the contract at `contract_address`, as of
+/// `block`. This is intended for export tooling, the entries are read lazily from the
+/// contract's state tree instead of being collected up front.
+///
+/// Returns `None` if either the block or the contract (at that block) does not exist.
+pub fn contract_storage_entries<'tx>(
+    transaction: &'tx Transaction<'tx>,
+    block: StarknetBlocksBlockId,
+    contract_address: ContractAddress,
+) -> anyhow::Result<
+    Option<impl Iterator<Item = anyhow::Result<(StorageAddress, StorageValue)>> + 'tx>,
+> {
+    let global_root =
+        match StarknetBlocksTable::get_root(transaction, block).context("Read global root")? {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+
+    let global_tree =
+        GlobalStateTree::load(transaction, global_root).context("Load global state tree")?;
+    let state_hash = global_tree
+        .get(contract_address)
+        .context("Get contract state hash from global state tree")?;
+    if state_hash.0 == StarkHash::ZERO {
+        return Ok(None);
+    }
+
+    let contract_root = ContractsStateTable::get_root(transaction, state_hash)
+        .context("Read contract root")?
+        .context("Contract state missing from contracts state table")?;
+    let contract_tree =
+        ContractsStateTree::load(transaction, contract_root).context("Load contract state tree")?;
+
+    Ok(Some(contract_tree.storage_entries()))
+}
+
 /// Calculates the contract state hash from its preimage.
 fn calculate_contract_state_hash(hash: ContractHash, root: ContractRoot) -> ContractStateHash {
     const RESERVED: StarkHash = StarkHash::ZERO;
@@ -93,11 +228,37 @@ fn calculate_contract_state_hash(hash: ContractHash, root: ContractRoot) -> Cont
     ContractStateHash(hash)
 }
 
+/// Calculates the transaction commitment of a block from the hashes of its transactions.
+///
+/// The commitment is the [HashChain](contract_hash::HashChain) of the transaction hashes in
+/// block order, i.e. `compute_hash_on_elements` in cairo-lang terms. Returns `None` if the
+/// block is not present in storage.
+pub fn calculate_transaction_commitment(
+    connection: &rusqlite::Connection,
+    block: StarknetBlocksBlockId,
+) -> anyhow::Result<Option<StarkHash>> {
+    let block = match StarknetBlocksTable::get(connection, block).context("Read block")? {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+
+    let transactions =
+        StarknetTransactionsTable::get_transaction_data_for_block(connection, block.hash.into())
+            .context("Read block transactions")?;
+
+    let mut chain = contract_hash::HashChain::default();
+    transactions
+        .iter()
+        .for_each(|(transaction, _)| chain.update(transaction.transaction_hash.0));
+
+    Ok(Some(chain.finalize()))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{calculate_contract_state_hash, sync};
+    use super::{calculate_contract_state_hash, calculate_transaction_commitment, sync};
     use crate::core::{ContractHash, ContractRoot, ContractStateHash};
-    use pedersen::StarkHash;
+    use pedersen::{pedersen_hash, StarkHash};
 
     #[test]
     fn hash() {
@@ -124,6 +285,212 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn transaction_commitment() {
+        use crate::core::{
+            ContractAddress, StarknetBlockHash, StarknetTransactionHash, StarknetTransactionIndex,
+        };
+        use crate::sequencer::reply::transaction;
+        use crate::storage::{
+            StarknetBlocksBlockId, StarknetBlocksTable, StarknetTransactionsTable, Storage,
+        };
+
+        let storage = Storage::in_memory().unwrap();
+        let mut connection = storage.connection().unwrap();
+        let tx = connection.transaction().unwrap();
+
+        let genesis = crate::storage::test_utils::create_blocks::<1>()
+            .into_iter()
+            .next()
+            .unwrap();
+        StarknetBlocksTable::insert(&tx, &genesis).unwrap();
+
+        let transaction_hash = StarknetTransactionHash(StarkHash::from_hex_str("fff").unwrap());
+        let transaction = transaction::Transaction {
+            calldata: None,
+            class_hash: None,
+            constructor_calldata: None,
+            contract_address: ContractAddress(StarkHash::from_hex_str("222").unwrap()),
+            contract_address_salt: None,
+            entry_point_type: None,
+            entry_point_selector: None,
+            signature: None,
+            transaction_hash,
+            r#type: transaction::Type::InvokeFunction,
+            max_fee: None,
+        };
+        let receipt = transaction::Receipt {
+            actual_fee: None,
+            events: Vec::new(),
+            execution_resources: transaction::ExecutionResources {
+                builtin_instance_counter:
+                    transaction::execution_resources::BuiltinInstanceCounter::Empty(
+                        transaction::execution_resources::EmptyBuiltinInstanceCounter {},
+                    ),
+                n_steps: 0,
+                n_memory_holes: 0,
+            },
+            l1_to_l2_consumed_message: None,
+            l2_to_l1_messages: Vec::new(),
+            transaction_hash,
+            transaction_index: StarknetTransactionIndex(0),
+            transaction_failure_reason: None,
+        };
+        StarknetTransactionsTable::upsert(
+            &tx,
+            genesis.hash,
+            genesis.number,
+            &[(transaction, receipt)],
+        )
+        .unwrap();
+
+        // H(H(0, tx_hash), 1)
+        let expected = pedersen_hash(
+            pedersen_hash(StarkHash::ZERO, transaction_hash.0),
+            StarkHash::from_be_slice(&[1]).unwrap(),
+        );
+
+        let commitment = calculate_transaction_commitment(&tx, genesis.number.into()).unwrap();
+        assert_eq!(commitment, Some(expected));
+
+        // The commitment is stable across reads and block id variants.
+        let commitment = calculate_transaction_commitment(&tx, genesis.hash.into()).unwrap();
+        assert_eq!(commitment, Some(expected));
+
+        let unknown = StarknetBlocksBlockId::Hash(StarknetBlockHash(
+            StarkHash::from_hex_str("deadbeef").unwrap(),
+        ));
+        let commitment = calculate_transaction_commitment(&tx, unknown).unwrap();
+        assert_eq!(commitment, None);
+    }
+
+    mod replay_block {
+        use super::super::{replay_block, sync::apply_state_update, CompressedContract};
+        use crate::core::{
+            ContractAddress, ContractHash, GlobalRoot, StarknetBlockNumber, StorageAddress,
+            StorageValue,
+        };
+        use crate::ethereum::state_update::{
+            ContractUpdate, DeployedContract, StateUpdate, StorageUpdate,
+        };
+        use crate::storage::{ContractCodeTable, StarknetBlocksTable, Storage};
+        use pedersen::StarkHash;
+
+        fn contract_update(value: &[u8]) -> ContractUpdate {
+            ContractUpdate {
+                address: ContractAddress(StarkHash::from_be_slice(b"contract").unwrap()),
+                storage_updates: vec![StorageUpdate {
+                    address: StorageAddress(StarkHash::from_be_slice(b"storage addr").unwrap()),
+                    value: StorageValue(StarkHash::from_be_slice(value).unwrap()),
+                }],
+            }
+        }
+
+        /// The state updates of blocks 0 and 1, the latter changing the storage written
+        /// by the former.
+        fn state_updates() -> [StateUpdate; 2] {
+            let deployed = DeployedContract {
+                address: ContractAddress(StarkHash::from_be_slice(b"contract").unwrap()),
+                hash: ContractHash(StarkHash::from_be_slice(b"contract hash").unwrap()),
+                call_data: vec![],
+            };
+
+            [
+                StateUpdate {
+                    deployed_contracts: vec![deployed],
+                    contract_updates: vec![contract_update(b"value 0")],
+                    nonce_updates: vec![],
+                },
+                StateUpdate {
+                    deployed_contracts: vec![],
+                    contract_updates: vec![contract_update(b"value 1")],
+                    nonce_updates: vec![],
+                },
+            ]
+        }
+
+        /// Stores blocks 0 and 1 with the roots of their [state_updates].
+        fn setup() -> Storage {
+            let storage = Storage::in_memory().unwrap();
+            let mut connection = storage.connection().unwrap();
+            let transaction = connection.transaction().unwrap();
+
+            // The zstd magic bytes, which are asserted for internally.
+            let zstd_magic = vec![0x28, 0xb5, 0x2f, 0xfd];
+            let code = CompressedContract {
+                abi: zstd_magic.clone(),
+                bytecode: zstd_magic.clone(),
+                definition: zstd_magic,
+                hash: ContractHash(StarkHash::from_be_slice(b"contract hash").unwrap()),
+                dictionary_id: None,
+            };
+            ContractCodeTable::insert_compressed(&transaction, &code).unwrap();
+
+            let mut root = GlobalRoot(StarkHash::ZERO);
+            let blocks = crate::storage::test_utils::create_blocks::<2>();
+            for (mut block, update) in blocks.into_iter().zip(state_updates()) {
+                root = apply_state_update(&transaction, root, update).unwrap();
+                block.root = root;
+                StarknetBlocksTable::insert(&transaction, &block).unwrap();
+            }
+
+            transaction.commit().unwrap();
+            storage
+        }
+
+        #[test]
+        fn roots_match() {
+            let storage = setup();
+            let mut connection = storage.connection().unwrap();
+            let [_, update] = state_updates();
+
+            let stored = StarknetBlocksTable::get_root(&connection, StarknetBlockNumber(1).into())
+                .unwrap()
+                .unwrap();
+            let computed = replay_block(&mut connection, StarknetBlockNumber(1), update)
+                .unwrap()
+                .unwrap();
+            assert_eq!(computed, stored);
+        }
+
+        #[test]
+        fn genesis() {
+            let storage = setup();
+            let mut connection = storage.connection().unwrap();
+            let [update, _] = state_updates();
+
+            replay_block(&mut connection, StarknetBlockNumber::GENESIS, update)
+                .unwrap()
+                .unwrap();
+        }
+
+        #[test]
+        fn roots_mismatch() {
+            let storage = setup();
+            let mut connection = storage.connection().unwrap();
+            let mut update = state_updates()[1].clone();
+            update.contract_updates = vec![contract_update(b"other value")];
+
+            let stored = StarknetBlocksTable::get_root(&connection, StarknetBlockNumber(1).into())
+                .unwrap()
+                .unwrap();
+            let mismatch = replay_block(&mut connection, StarknetBlockNumber(1), update)
+                .unwrap()
+                .unwrap_err();
+            assert_eq!(mismatch.stored, stored);
+            assert_ne!(mismatch.computed, stored);
+        }
+
+        #[test]
+        fn unknown_block() {
+            let storage = setup();
+            let mut connection = storage.connection().unwrap();
+            let [_, update] = state_updates();
+
+            replay_block(&mut connection, StarknetBlockNumber(2), update).unwrap_err();
+        }
+    }
+
     #[test]
     #[ignore]
     fn init_deployed_contracts_root_to_zero_regression() {
@@ -474,7 +841,9 @@ mod tests {
             sequencer,
             state,
             sync::l1::sync,
-            sync::l2::sync,
+            |tx_event, sequencer, head, chain| {
+                sync::l2::sync(tx_event, sequencer, head, chain, false)
+            },
         )
         .await
         .unwrap();
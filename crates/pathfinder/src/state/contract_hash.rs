@@ -3,7 +3,7 @@ use pedersen::{pedersen_hash, StarkHash};
 use serde::Serialize;
 use sha3::Digest;
 
-use crate::core::ContractHash;
+use crate::core::{ConstructorParam, ContractAddress, ContractAddressSalt, ContractHash};
 use crate::sequencer::request::contract::EntryPointType;
 
 /// Computes the starknet contract hash for given contract definition json blob.
@@ -183,6 +183,37 @@ fn compute_contract_hash0(
     Ok(ContractHash(outer.finalize()))
 }
 
+/// Computes the address a contract of class `class_hash` is deployed at by `deployer_address`.
+///
+/// Deploy transactions have no deployer, in which case `deployer_address` is zero.
+///
+/// See: <https://starknet.io/documentation/contracts/#contract_address>
+pub fn compute_contract_address(
+    salt: ContractAddressSalt,
+    class_hash: ContractHash,
+    constructor_calldata: &[ConstructorParam],
+    deployer_address: ContractAddress,
+) -> ContractAddress {
+    const CONTRACT_ADDRESS_PREFIX: &[u8] = b"STARKNET_CONTRACT_ADDRESS";
+
+    let calldata_hash = constructor_calldata
+        .iter()
+        .fold(HashChain::default(), |mut hc, next| {
+            hc.update(next.0);
+            hc
+        })
+        .finalize();
+
+    let mut address = HashChain::default();
+    address.update(StarkHash::from_be_slice(CONTRACT_ADDRESS_PREFIX).unwrap());
+    address.update(deployer_address.0);
+    address.update(salt.0);
+    address.update(class_hash.0);
+    address.update(calldata_hash);
+
+    ContractAddress(address.finalize())
+}
+
 /// HashChain is the structure used over at cairo side to represent the hash construction needed
 /// for computing the contract hash.
 ///
@@ -190,7 +221,10 @@ fn compute_contract_hash0(
 /// second value is the number of values hashed together in this chain. For other values, the
 /// accumulator is on each update replaced with the `H(hash, value)` and the number of count
 /// incremented by one.
-struct HashChain {
+///
+/// The same construction is known as `compute_hash_on_elements` on the cairo side, and is also used
+/// for transaction hashes and contract addresses.
+pub(crate) struct HashChain {
     hash: StarkHash,
     count: usize,
 }
@@ -205,7 +239,7 @@ impl Default for HashChain {
 }
 
 impl HashChain {
-    fn update(&mut self, value: StarkHash) {
+    pub(crate) fn update(&mut self, value: StarkHash) {
         self.hash = pedersen_hash(self.hash, value);
         self.count = self
             .count
@@ -213,7 +247,7 @@ impl HashChain {
             .expect("could not have deserialized larger than usize Vecs");
     }
 
-    fn finalize(self) -> StarkHash {
+    pub(crate) fn finalize(self) -> StarkHash {
         let count = StarkHash::from_be_slice(&self.count.to_be_bytes())
             .expect("usize is smaller than 251-bits");
         pedersen_hash(self.hash, count)
@@ -552,4 +586,36 @@ mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn contract_address_of_known_deployment() {
+        use super::compute_contract_address;
+        use crate::core::ContractAddress;
+        use crate::sequencer::request::add_transaction::AddTransaction;
+        use pedersen::StarkHash;
+
+        let json = include_bytes!("../../resources/deploy_transaction.json");
+        let deploy = match serde_json::from_slice::<AddTransaction>(json).unwrap() {
+            AddTransaction::Deploy(deploy) => deploy,
+            AddTransaction::Invoke(_) => unreachable!("Resource is a deploy transaction"),
+        };
+        let class_hash = deploy.contract_definition.hash().unwrap();
+
+        let address = compute_contract_address(
+            deploy.contract_address_salt,
+            class_hash,
+            &deploy.constructor_calldata,
+            ContractAddress(StarkHash::ZERO),
+        );
+
+        assert_eq!(
+            address,
+            ContractAddress(
+                StarkHash::from_hex_str(
+                    "0x03926aea98213ec34fe9783d803237d221c54c52344422e1f4942a5b340fa6ad"
+                )
+                .unwrap()
+            )
+        );
+    }
 }
@@ -45,11 +45,12 @@
 //! The in-memory tree is built using a graph of `Rc<RefCell<Node>>` which is a bit painful.
 
 use anyhow::Context;
-use bitvec::prelude::BitVec;
+use bitvec::{order::Msb0, prelude::BitVec};
 use rusqlite::Transaction;
 use std::{cell::RefCell, rc::Rc};
 
 use crate::state::merkle_node::{BinaryNode, Direction, EdgeNode, Node};
+use crate::state::node_cache::NodeCache;
 
 use crate::storage::merkle_tree::{
     PersistedBinaryNode, PersistedEdgeNode, PersistedNode, RcNodeStorage,
@@ -73,7 +74,6 @@ pub trait NodeStorage {
     fn upsert(&self, key: StarkHash, node: PersistedNode) -> anyhow::Result<()>;
 
     /// Decrement previously stored `key`'s reference count. This shouldn't fail for key not found.
-    #[cfg(test)]
     fn decrement_ref_count(&self, key: StarkHash) -> anyhow::Result<()>;
 
     /// Increment previously stored `key`'s reference count. This shouldn't fail for key not found.
@@ -114,6 +114,17 @@ impl<'a> MerkleTree<RcNodeStorage<'a>> {
         let storage = RcNodeStorage::open(table, transaction)?;
         Self::new(storage, root)
     }
+
+    /// Like [MerkleTree::load], but nodes are read through the shared `cache`.
+    pub fn load_with_cache(
+        table: String,
+        transaction: &'a Transaction,
+        root: StarkHash,
+        cache: &'a NodeCache,
+    ) -> anyhow::Result<Self> {
+        let storage = RcNodeStorage::open(table, transaction)?.with_cache(cache);
+        Self::new(storage, root)
+    }
 }
 
 impl<T: NodeStorage + Default> Default for MerkleTree<T> {
@@ -134,7 +145,6 @@ impl<T: NodeStorage> MerkleTree<T> {
     ///
     /// This allows for multiple instances of the same tree state to be committed,
     /// without deleting all of them in a single call.
-    #[cfg(test)]
     pub fn delete(self) -> anyhow::Result<()> {
         match self.root.borrow().hash() {
             Some(hash) if hash != StarkHash::ZERO => self
@@ -461,6 +471,31 @@ impl<T: NodeStorage> MerkleTree<T> {
         Ok(val)
     }
 
+    /// Returns an iterator over all `(key, value)` leaves of the tree, in ascending key order.
+    ///
+    /// Nodes are read from storage lazily as the iterator advances, so the tree is never
+    /// loaded into memory in its entirety. Only persisted trees can be iterated -- the
+    /// iterator yields an error if the tree contains uncommitted changes.
+    pub fn leaves(&self) -> Leaves<T>
+    where
+        T: Clone,
+    {
+        let (stack, error) = match self.root.borrow().hash() {
+            Some(hash) if hash == StarkHash::ZERO => (Vec::new(), None),
+            Some(hash) => (vec![(hash, BitVec::new())], None),
+            None => (
+                Vec::new(),
+                Some(anyhow::anyhow!("Tree contains uncommitted changes")),
+            ),
+        };
+
+        Leaves {
+            storage: self.storage.clone(),
+            stack,
+            error,
+        }
+    }
+
     /// Traverses from the current root towards the destination [Leaf](Node::Leaf) node.
     /// Returns the list of nodes along the path.
     ///
@@ -559,6 +594,65 @@ impl<T: NodeStorage> MerkleTree<T> {
     }
 }
 
+/// Iterator over the leaves of a persisted [MerkleTree], see [MerkleTree::leaves].
+///
+/// Performs a depth-first traversal, keeping only the pending subtree roots and their
+/// paths in memory.
+pub struct Leaves<T> {
+    storage: T,
+    /// Nodes still to be visited, along with the path leading to them from the root.
+    stack: Vec<(StarkHash, BitVec<Msb0, u8>)>,
+    error: Option<anyhow::Error>,
+}
+
+impl<T: NodeStorage> Iterator for Leaves<T> {
+    type Item = anyhow::Result<(StarkHash, StarkHash)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+
+        while let Some((hash, mut path)) = self.stack.pop() {
+            let node = match self.storage.get(hash) {
+                Ok(Some(node)) => node,
+                Ok(None) => {
+                    self.stack.clear();
+                    return Some(Err(anyhow::anyhow!("Node {} does not exist", hash)));
+                }
+                Err(e) => {
+                    self.stack.clear();
+                    return Some(Err(e.context("Reading node from storage")));
+                }
+            };
+
+            match node {
+                PersistedNode::Leaf => {
+                    let key = StarkHash::from_bits(&path).context("Leaf path exceeds key size");
+                    if key.is_err() {
+                        self.stack.clear();
+                    }
+                    return Some(key.map(|key| (key, hash)));
+                }
+                PersistedNode::Binary(binary) => {
+                    // Right is pushed first so that the left subtree is visited first.
+                    let mut right = path.clone();
+                    right.push(true);
+                    path.push(false);
+                    self.stack.push((binary.right, right));
+                    self.stack.push((binary.left, path));
+                }
+                PersistedNode::Edge(edge) => {
+                    path.extend_from_bitslice(&edge.path);
+                    self.stack.push((edge.child, path));
+                }
+            }
+        }
+
+        None
+    }
+}
+
 #[cfg(any(test, fuzzing))]
 impl NodeStorage for () {
     fn get(&self, _key: StarkHash) -> anyhow::Result<Option<PersistedNode>> {
@@ -571,7 +665,6 @@ impl NodeStorage for () {
         Ok(())
     }
 
-    #[cfg(test)]
     fn decrement_ref_count(&self, _key: StarkHash) -> anyhow::Result<()> {
         Ok(())
     }
@@ -962,6 +1055,60 @@ mod tests {
             assert_eq!(uut.get(key2).unwrap(), val2);
         }
 
+        #[test]
+        fn leaves() {
+            let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+            let transaction = conn.transaction().unwrap();
+            let mut uut =
+                MerkleTree::load("test".to_string(), &transaction, StarkHash::ZERO).unwrap();
+
+            let key0 = StarkHash::from_hex_str("8975").unwrap();
+            let key1 = StarkHash::from_hex_str("901823").unwrap();
+            let key2 = StarkHash::from_hex_str("99cadc82").unwrap();
+
+            let val0 = StarkHash::from_hex_str("1").unwrap();
+            let val1 = StarkHash::from_hex_str("2").unwrap();
+            let val2 = StarkHash::from_hex_str("3").unwrap();
+
+            // Insert out of order, iteration should still be sorted by key.
+            uut.set(key1, val1).unwrap();
+            uut.set(key2, val2).unwrap();
+            uut.set(key0, val0).unwrap();
+
+            let root = uut.commit().unwrap();
+
+            let uut = MerkleTree::load("test".to_string(), &transaction, root).unwrap();
+            let leaves = uut.leaves().collect::<anyhow::Result<Vec<_>>>().unwrap();
+
+            assert_eq!(leaves, vec![(key0, val0), (key1, val1), (key2, val2)]);
+        }
+
+        #[test]
+        fn leaves_of_empty_tree() {
+            let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+            let transaction = conn.transaction().unwrap();
+            let uut = MerkleTree::load("test".to_string(), &transaction, StarkHash::ZERO).unwrap();
+
+            assert_eq!(uut.leaves().count(), 0);
+        }
+
+        #[test]
+        fn leaves_with_uncommitted_changes() {
+            let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+            let transaction = conn.transaction().unwrap();
+            let mut uut =
+                MerkleTree::load("test".to_string(), &transaction, StarkHash::ZERO).unwrap();
+            uut.set(
+                StarkHash::from_hex_str("1").unwrap(),
+                StarkHash::from_hex_str("1").unwrap(),
+            )
+            .unwrap();
+
+            let mut leaves = uut.leaves();
+            assert!(leaves.next().unwrap().is_err());
+            assert!(leaves.next().is_none());
+        }
+
         #[test]
         fn delete_leaf_regression() {
             // This test exercises a bug in the merging of edge nodes. It was caused
@@ -0,0 +1,199 @@
+//! A bounded in-memory cache of persisted state tree nodes, shared by readers of the state.
+//!
+//! Tree nodes are content-addressed, i.e. a node is stored under its hash. The node stored under
+//! a hash therefore never changes, and cached nodes never have to be invalidated when new state
+//! is committed or old nodes are deleted.
+//!
+//! Leaves are stored under their value, which could equal the hash of another tree's inner node.
+//! Nodes are therefore cached per tree table, as they are in [storage](crate::storage::merkle_tree).
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use pedersen::StarkHash;
+
+use crate::storage::merkle_tree::PersistedNode;
+
+/// A bounded cache of [PersistedNode]s, keyed by their tree table and hash.
+///
+/// Once full, the oldest cached node is evicted for every newly cached node.
+#[derive(Debug)]
+pub struct NodeCache {
+    capacity: usize,
+    nodes: Mutex<Nodes>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Nodes {
+    by_table: HashMap<String, HashMap<StarkHash, PersistedNode>>,
+    /// The cached (table, hash) pairs, oldest first.
+    insertion_order: VecDeque<(String, StarkHash)>,
+}
+
+impl Default for NodeCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl NodeCache {
+    /// The default maximum number of cached nodes.
+    pub const DEFAULT_CAPACITY: usize = 65_536;
+
+    /// Names of the counters when rendered in the Prometheus text format.
+    const HITS_METRIC_NAME: &'static str = "state_tree_node_cache_hits_total";
+    const MISSES_METRIC_NAME: &'static str = "state_tree_node_cache_misses_total";
+
+    /// Creates a cache holding at most `capacity` nodes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            nodes: Mutex::new(Nodes::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the node stored under `hash` in the tree `table`, if cached.
+    pub fn get(&self, table: &str, hash: StarkHash) -> Option<PersistedNode> {
+        let node = self
+            .nodes
+            .lock()
+            .unwrap()
+            .by_table
+            .get(table)
+            .and_then(|nodes| nodes.get(&hash))
+            .cloned();
+
+        match node {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+
+        node
+    }
+
+    /// Caches the `node` stored under `hash` in the tree `table`, evicting the oldest
+    /// cached node if the cache is full.
+    pub fn insert(&self, table: &str, hash: StarkHash, node: PersistedNode) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut nodes = self.nodes.lock().unwrap();
+        let Nodes {
+            by_table,
+            insertion_order,
+        } = &mut *nodes;
+
+        let table_nodes = match by_table.get_mut(table) {
+            Some(table_nodes) => table_nodes,
+            None => by_table.entry(table.to_owned()).or_default(),
+        };
+        // Concurrent readers may both miss and insert the same node.
+        if table_nodes.insert(hash, node).is_some() {
+            return;
+        }
+        insertion_order.push_back((table.to_owned(), hash));
+
+        if insertion_order.len() > self.capacity {
+            if let Some((table, hash)) = insertion_order.pop_front() {
+                if let Some(table_nodes) = by_table.get_mut(&table) {
+                    table_nodes.remove(&hash);
+                }
+            }
+        }
+    }
+
+    /// The number of lookups served from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of lookups which had to be served from storage.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Renders the hit and miss counters in the [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for (name, count) in [
+            (Self::HITS_METRIC_NAME, self.hits()),
+            (Self::MISSES_METRIC_NAME, self.misses()),
+        ] {
+            // Writing to a String cannot fail.
+            let _ = writeln!(output, "# TYPE {} counter\n{} {}", name, name, count);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NodeCache;
+    use crate::storage::merkle_tree::{PersistedBinaryNode, PersistedNode};
+    use pedersen::StarkHash;
+
+    fn hash(value: u8) -> StarkHash {
+        StarkHash::from_be_slice(&[value]).unwrap()
+    }
+
+    fn binary(value: u8) -> PersistedNode {
+        PersistedNode::Binary(PersistedBinaryNode {
+            left: hash(value),
+            right: hash(value),
+        })
+    }
+
+    #[test]
+    fn hit_and_miss() {
+        let cache = NodeCache::new(10);
+
+        assert_eq!(cache.get("tree_global", hash(1)), None);
+        cache.insert("tree_global", hash(1), binary(2));
+        assert_eq!(cache.get("tree_global", hash(1)), Some(binary(2)));
+
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 1);
+        assert_eq!(
+            cache.render(),
+            "# TYPE state_tree_node_cache_hits_total counter\n\
+            state_tree_node_cache_hits_total 1\n\
+            # TYPE state_tree_node_cache_misses_total counter\n\
+            state_tree_node_cache_misses_total 1\n"
+        );
+    }
+
+    #[test]
+    fn tables_are_separate() {
+        let cache = NodeCache::new(10);
+
+        cache.insert("tree_global", hash(1), binary(2));
+        cache.insert("tree_contracts", hash(1), PersistedNode::Leaf);
+
+        assert_eq!(cache.get("tree_global", hash(1)), Some(binary(2)));
+        assert_eq!(
+            cache.get("tree_contracts", hash(1)),
+            Some(PersistedNode::Leaf)
+        );
+    }
+
+    #[test]
+    fn evicts_oldest() {
+        let cache = NodeCache::new(2);
+
+        cache.insert("tree_global", hash(1), binary(1));
+        cache.insert("tree_global", hash(2), binary(2));
+        // Caching an already cached node does not evict anything.
+        cache.insert("tree_global", hash(1), binary(1));
+        cache.insert("tree_global", hash(3), binary(3));
+
+        assert_eq!(cache.get("tree_global", hash(1)), None);
+        assert_eq!(cache.get("tree_global", hash(2)), Some(binary(2)));
+        assert_eq!(cache.get("tree_global", hash(3)), Some(binary(3)));
+    }
+}
@@ -9,7 +9,7 @@ use crate::{
     core::{
         ContractAddress, ContractRoot, ContractStateHash, GlobalRoot, StorageAddress, StorageValue,
     },
-    state::merkle_tree::MerkleTree,
+    state::{merkle_tree::MerkleTree, node_cache::NodeCache},
     storage::merkle_tree::RcNodeStorage,
 };
 
@@ -27,6 +27,19 @@ impl<'a> ContractsStateTree<'a> {
         Ok(Self { tree })
     }
 
+    /// Like [ContractsStateTree::load], but nodes are read through the shared `cache`.
+    pub fn load_with_cache(
+        transaction: &'a Transaction,
+        root: ContractRoot,
+        cache: &'a NodeCache,
+    ) -> anyhow::Result<Self> {
+        // TODO: move the string into storage.
+        let tree =
+            MerkleTree::load_with_cache("tree_contracts".to_string(), transaction, root.0, cache)?;
+
+        Ok(Self { tree })
+    }
+
     #[allow(dead_code)]
     pub fn get(&self, address: StorageAddress) -> anyhow::Result<StorageValue> {
         let value = self.tree.get(address.0)?;
@@ -37,11 +50,26 @@ impl<'a> ContractsStateTree<'a> {
         self.tree.set(address.0, value.0)
     }
 
+    /// Returns an iterator over all non-zero storage entries of this contract, in ascending
+    /// address order. Entries are read from storage lazily, see [MerkleTree::leaves].
+    pub fn storage_entries(
+        &self,
+    ) -> impl Iterator<Item = anyhow::Result<(StorageAddress, StorageValue)>> + 'a {
+        self.tree
+            .leaves()
+            .map(|leaf| leaf.map(|(address, value)| (StorageAddress(address), StorageValue(value))))
+    }
+
     /// Applies and persists any changes. Returns the new tree root.
     pub fn apply(self) -> anyhow::Result<ContractRoot> {
         let root = self.tree.commit()?;
         Ok(ContractRoot(root))
     }
+
+    /// Removes one instance of this tree's root from storage, see [MerkleTree::delete].
+    pub fn delete(self) -> anyhow::Result<()> {
+        self.tree.delete()
+    }
 }
 
 /// A Binary Merkle-Patricia Tree which contains
@@ -58,6 +86,31 @@ impl<'a> GlobalStateTree<'a> {
         Ok(Self { tree })
     }
 
+    /// Like [GlobalStateTree::load], but nodes are read through the shared `cache`.
+    pub fn load_with_cache(
+        transaction: &'a Transaction,
+        root: GlobalRoot,
+        cache: &'a NodeCache,
+    ) -> anyhow::Result<Self> {
+        // TODO: move the string into storage.
+        let tree =
+            MerkleTree::load_with_cache("tree_global".to_string(), transaction, root.0, cache)?;
+
+        Ok(Self { tree })
+    }
+
+    /// Returns true if the tree with the given root is persisted, i.e. it can be
+    /// [loaded](GlobalStateTree::load). The empty tree always exists.
+    pub fn root_exists(transaction: &Transaction, root: GlobalRoot) -> anyhow::Result<bool> {
+        if root.0 == pedersen::StarkHash::ZERO {
+            return Ok(true);
+        }
+
+        // TODO: move the string into storage.
+        let storage = RcNodeStorage::open("tree_global".to_string(), transaction)?;
+        Ok(storage.get(root.0)?.is_some())
+    }
+
     pub fn get(&self, address: ContractAddress) -> anyhow::Result<ContractStateHash> {
         let value = self.tree.get(address.0)?;
         Ok(ContractStateHash(value))
@@ -71,9 +124,26 @@ impl<'a> GlobalStateTree<'a> {
         self.tree.set(address.0, value.0)
     }
 
+    /// Returns an iterator over all deployed contracts and their state hashes, in ascending
+    /// address order. Entries are read from storage lazily, see [MerkleTree::leaves].
+    pub fn contracts(
+        &self,
+    ) -> impl Iterator<Item = anyhow::Result<(ContractAddress, ContractStateHash)>> + 'a {
+        self.tree.leaves().map(|leaf| {
+            leaf.map(|(address, state_hash)| {
+                (ContractAddress(address), ContractStateHash(state_hash))
+            })
+        })
+    }
+
     /// Applies and persists any changes. Returns the new global root.
     pub fn apply(self) -> anyhow::Result<GlobalRoot> {
         let root = self.tree.commit()?;
         Ok(GlobalRoot(root))
     }
+
+    /// Removes one instance of this tree's root from storage, see [MerkleTree::delete].
+    pub fn delete(self) -> anyhow::Result<()> {
+        self.tree.delete()
+    }
 }
@@ -15,31 +15,90 @@ use crate::{
         transport::EthereumTransport,
         Chain,
     },
-    rpc::types::reply::{syncing, Syncing as SyncStatus},
+    rpc::types::reply::{syncing, BlockHeader, Syncing as SyncStatus},
     sequencer::{self, reply::Block},
     state::{calculate_contract_state_hash, state_tree::GlobalStateTree, update_contract_state},
     storage::{
-        ContractCodeTable, ContractsStateTable, ContractsTable, L1StateTable, L1TableBlockId,
-        RefsTable, StarknetBlock, StarknetBlocksBlockId, StarknetBlocksTable,
-        StarknetTransactionsTable, Storage,
+        ContractCodeTable, ContractNoncesTable, ContractsStateTable, ContractsTable, L1StateTable,
+        L1TableBlockId, RefsTable, StarknetBlock, StarknetBlocksBlockId, StarknetBlocksTable,
+        StarknetRawBlocksTable, StarknetTransactionsTable, Storage,
     },
 };
 
 use anyhow::Context;
 use pedersen::StarkHash;
 use rusqlite::{Connection, Transaction};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock};
 
 pub struct State {
     pub status: RwLock<SyncStatus>,
+    /// The highest L2 block which has been verified on L1, if any.
+    pub l1_confirmed: RwLock<Option<StarknetBlockNumber>>,
+    /// Headers of the L2 blocks as they are stored, subscribe to receive them.
+    pub new_heads: broadcast::Sender<BlockHeader>,
+    /// The first block removed by each L2 reorg, subscribe to receive them.
+    pub reorgs: broadcast::Sender<StarknetBlockNumber>,
+    /// The rate at which L2 blocks have recently been stored.
+    pub sync_rate: RwLock<SyncRate>,
+}
+
+impl State {
+    /// The number of headers buffered for each [new heads](State::new_heads) receiver.
+    /// Receivers which fall further behind miss the oldest headers.
+    pub const NEW_HEADS_CAPACITY: usize = 64;
+    /// The number of reorgs buffered for each [reorgs](State::reorgs) receiver.
+    pub const REORGS_CAPACITY: usize = 16;
 }
 
 impl Default for State {
     fn default() -> Self {
         Self {
             status: RwLock::new(SyncStatus::False(false)),
+            l1_confirmed: RwLock::new(None),
+            new_heads: broadcast::channel(Self::NEW_HEADS_CAPACITY).0,
+            reorgs: broadcast::channel(Self::REORGS_CAPACITY).0,
+            sync_rate: RwLock::new(SyncRate::default()),
+        }
+    }
+}
+
+/// A rolling rate of stored L2 blocks, over the most recent [SyncRate::WINDOW] blocks.
+#[derive(Debug, Default)]
+pub struct SyncRate {
+    /// When each of the most recent blocks was stored, oldest first.
+    samples: std::collections::VecDeque<(std::time::Instant, StarknetBlockNumber)>,
+}
+
+impl SyncRate {
+    /// The number of most recent blocks the rate is computed over.
+    pub const WINDOW: usize = 32;
+
+    /// Records that `block` was stored at `stored_at`.
+    pub fn record(&mut self, stored_at: std::time::Instant, block: StarknetBlockNumber) {
+        self.samples.push_back((stored_at, block));
+        if self.samples.len() > Self::WINDOW {
+            self.samples.pop_front();
         }
     }
+
+    /// Forgets all recorded blocks, i.e. because a reorg makes the block numbers go backwards.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// The number of blocks stored per second, or [None] if fewer than two blocks have been
+    /// recorded or they were recorded at the same instant.
+    pub fn blocks_per_second(&self) -> Option<f64> {
+        let (first_at, first_block) = self.samples.front()?;
+        let (last_at, last_block) = self.samples.back()?;
+
+        let elapsed = last_at.duration_since(*first_at).as_secs_f64();
+        if elapsed == 0.0 {
+            return None;
+        }
+
+        Some(last_block.0.saturating_sub(first_block.0) as f64 / elapsed)
+    }
 }
 
 /// Implements the main sync loop, where L1 and L2 sync results are combined.
@@ -83,6 +142,8 @@ where
         Ok((l1_head, l2_head))
     })?;
 
+    update_l1_confirmed(&mut db_conn, &state).await?;
+
     // Start update sync-status process.
     let (starting_block_num, starting_block_hash) = l2_head.unwrap_or((
         // Seems a better choice for an invalid block number than 0
@@ -117,6 +178,7 @@ where
                     l1_update(&mut db_conn, &updates).await.with_context(|| {
                         format!("Update L1 state with blocks {:?}-{:?}", first, last)
                     })?;
+                    update_l1_confirmed(&mut db_conn, &state).await?;
 
                     match updates.as_slice() {
                         [single] => {
@@ -136,6 +198,7 @@ where
                     l1_reorg(&mut db_conn, reorg_tail)
                         .await
                         .with_context(|| format!("Reorg L1 state to block {}", reorg_tail.0))?;
+                    update_l1_confirmed(&mut db_conn, &state).await?;
 
                     let new_head = match reorg_tail {
                         StarknetBlockNumber::GENESIS => None,
@@ -181,22 +244,33 @@ where
                 },
             },
             l2_event = rx_l2.recv() => match l2_event {
-                Some(l2::Event::Update(block, diff, timings)) => {
+                Some(l2::Event::Update(block, diff, timings, raw_block)) => {
                     // unwrap is safe as only pending query blocks are None.
                     let block_num = block.block_number.unwrap().0;
                     let block_hash = block.block_hash.unwrap();
+                    let header = BlockHeader::from_sequencer(&block);
                     let storage_updates: usize = diff
                         .contract_updates
                         .iter()
                         .map(|u| u.storage_updates.len())
                         .sum();
                     let update_t = std::time::Instant::now();
-                    l2_update(&mut db_conn, *block, diff)
+                    l2_update(&mut db_conn, *block, diff, raw_block)
                         .await
                         .with_context(|| format!("Update L2 state to {}", block_num))?;
+                    update_l1_confirmed(&mut db_conn, &state).await?;
+                    // Sending only fails if there are no subscribers.
+                    if let Some(header) = header {
+                        let _ = state.new_heads.send(header);
+                    }
                     let block_time = last_block_start.elapsed();
                     let update_t = update_t.elapsed();
                     last_block_start = std::time::Instant::now();
+                    state
+                        .sync_rate
+                        .write()
+                        .await
+                        .record(last_block_start, StarknetBlockNumber(block_num));
 
                     block_time_avg = block_time_avg.mul_f32(1.0 - BLOCK_TIME_WEIGHT)
                         + block_time.mul_f32(BLOCK_TIME_WEIGHT);
@@ -240,6 +314,10 @@ where
                     l2_reorg(&mut db_conn, reorg_tail)
                         .await
                         .with_context(|| format!("Reorg L2 state to {:?}", reorg_tail))?;
+                    update_l1_confirmed(&mut db_conn, &state).await?;
+                    // Sending only fails if there are no subscribers.
+                    let _ = state.reorgs.send(reorg_tail);
+                    state.sync_rate.write().await.clear();
 
                     let new_head = match reorg_tail {
                         StarknetBlockNumber::GENESIS => None,
@@ -314,6 +392,17 @@ where
     }
 }
 
+/// Copies the L1-L2 head from the database into the sync state.
+///
+/// The connection is borrowed mutably only because [Connection] is not [Sync], so holding a
+/// shared reference to it across the `await` would make the sync future `!Send`.
+async fn update_l1_confirmed(connection: &mut Connection, state: &State) -> anyhow::Result<()> {
+    let head = tokio::task::block_in_place(|| RefsTable::get_l1_l2_head(connection))
+        .context("Query L1-L2 head from database")?;
+    *state.l1_confirmed.write().await = head;
+    Ok(())
+}
+
 /// Periodically updates sync state with the latest block height.
 async fn update_sync_status_latest(
     state: Arc<State>,
@@ -358,6 +447,7 @@ async fn update_sync_status_latest(
                     SyncStatus::Status(status) => {
                         if status.highest_block_hash != latest_hash {
                             status.highest_block_hash = latest_hash;
+                            status.highest_block_num = latest_num;
                             tracing::debug!(
                                 highest_hash=%latest_hash.0,
                                 highest_num=%latest_num.0,
@@ -452,13 +542,15 @@ async fn l1_reorg(
 async fn l2_update(
     connection: &mut Connection,
     block: Block,
-    state_diff: StateUpdate,
+    mut state_diff: StateUpdate,
+    raw_block: Option<bytes::Bytes>,
 ) -> anyhow::Result<()> {
     tokio::task::block_in_place(move || {
         let transaction = connection
             .transaction()
             .context("Create database transaction")?;
 
+        let nonce_updates = std::mem::take(&mut state_diff.nonce_updates);
         let new_root =
             update_starknet_state(&transaction, state_diff).context("Updating Starknet state")?;
 
@@ -482,6 +574,21 @@ async fn l2_update(
         StarknetBlocksTable::insert(&transaction, &starknet_block)
             .context("Insert block into database")?;
 
+        if let Some(raw_block) = raw_block {
+            StarknetRawBlocksTable::insert(&transaction, starknet_block.number, &raw_block)
+                .context("Insert raw block into database")?;
+        }
+
+        for update in nonce_updates {
+            ContractNoncesTable::insert(
+                &transaction,
+                update.address,
+                starknet_block.number,
+                update.nonce,
+            )
+            .context("Insert contract nonce into database")?;
+        }
+
         // Insert the transactions.
         anyhow::ensure!(
             block.transactions.len() == block.transaction_receipts.len(),
@@ -534,6 +641,8 @@ async fn l2_reorg(
 
         StarknetBlocksTable::reorg(&transaction, reorg_tail)
             .context("Delete L1 state from database")?;
+        ContractNoncesTable::reorg(&transaction, reorg_tail)
+            .context("Delete contract nonces from database")?;
 
         // Track combined L1 and L2 state.
         let l1_l2_head = RefsTable::get_l1_l2_head(&transaction).context("Query L1-L2 head")?;
@@ -560,6 +669,18 @@ fn update_starknet_state(
         .context("Query latest state root")?
         .map(|block| block.root)
         .unwrap_or(GlobalRoot(StarkHash::ZERO));
+
+    apply_state_update(transaction, global_root, diff)
+}
+
+/// Applies the contract deployments and storage updates of `diff` on top of the state at
+/// `global_root`, returning the new global root. Nonce updates are not part of the global state
+/// and are ignored.
+pub(super) fn apply_state_update(
+    transaction: &Transaction,
+    global_root: GlobalRoot,
+    diff: StateUpdate,
+) -> anyhow::Result<GlobalRoot> {
     let mut global_tree =
         GlobalStateTree::load(transaction, global_root).context("Loading global state tree")?;
 
@@ -630,7 +751,7 @@ mod tests {
             StarknetTransactionHash, StorageAddress, StorageValue, TransactionVersion,
         },
         ethereum,
-        rpc::types::{BlockHashOrTag, BlockNumberOrTag},
+        rpc::types::{reply::BlockHeader, BlockHashOrTag, BlockId, BlockNumberOrTag},
         sequencer::{
             self,
             error::SequencerError,
@@ -700,11 +821,14 @@ mod tests {
             unimplemented!()
         }
 
-        async fn call(
+        async fn raw_block_by_number(
             &self,
-            _: request::Call,
-            _: BlockHashOrTag,
-        ) -> Result<reply::Call, SequencerError> {
+            _: BlockNumberOrTag,
+        ) -> Result<bytes::Bytes, SequencerError> {
+            unimplemented!()
+        }
+
+        async fn call(&self, _: request::Call, _: BlockId) -> Result<reply::Call, SequencerError> {
             unimplemented!()
         }
 
@@ -865,6 +989,7 @@ mod tests {
         pub static ref STATE_UPDATE0: ethereum::state_update::StateUpdate = ethereum::state_update::StateUpdate {
             contract_updates: vec![],
             deployed_contracts: vec![],
+            nonce_updates: vec![],
         };
     }
 
@@ -1100,6 +1225,7 @@ mod tests {
                 Box::new(block()),
                 state_update(),
                 timings,
+                None,
             ))
             .await
             .unwrap();
@@ -1154,6 +1280,198 @@ mod tests {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn l2_update_notifies_new_heads() {
+        let storage = Storage::in_memory().unwrap();
+        let sync_state = Arc::new(state::SyncState::default());
+        let mut new_heads = sync_state.new_heads.subscribe();
+
+        let l2 = |tx: mpsc::Sender<l2::Event>, _, _, _| async move {
+            let timings = l2::Timings {
+                block_download: Duration::default(),
+                state_diff_download: Duration::default(),
+                contract_deployment: Duration::default(),
+            };
+            tx.send(l2::Event::Update(
+                Box::new(BLOCK0.clone()),
+                STATE_UPDATE0.clone(),
+                timings,
+                None,
+            ))
+            .await
+            .unwrap();
+            // Avoid being restarted all the time by the outer sync() loop
+            let () = std::future::pending().await;
+            Ok(())
+        };
+
+        // UUT
+        let _jh = tokio::spawn(state::sync(
+            storage,
+            FakeTransport,
+            ethereum::Chain::Goerli,
+            FakeSequencer,
+            sync_state.clone(),
+            l1_noop,
+            l2,
+        ));
+
+        let header = tokio::time::timeout(Duration::from_secs(1), new_heads.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(header, BlockHeader::from_sequencer(&BLOCK0).unwrap());
+        assert_eq!(header.block_number, StarknetBlockNumber(0));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn l2_update_stores_raw_block() {
+        let storage = Storage::in_memory().unwrap();
+        let connection = storage.connection().unwrap();
+        let sync_state = Arc::new(state::SyncState::default());
+
+        let raw_block = || serde_json::to_vec(&*BLOCK0).unwrap();
+
+        let l2 = move |tx: mpsc::Sender<l2::Event>, _, _, _| async move {
+            let timings = l2::Timings {
+                block_download: Duration::default(),
+                state_diff_download: Duration::default(),
+                contract_deployment: Duration::default(),
+            };
+            tx.send(l2::Event::Update(
+                Box::new(BLOCK0.clone()),
+                STATE_UPDATE0.clone(),
+                timings,
+                Some(raw_block().into()),
+            ))
+            .await
+            .unwrap();
+            // Avoid being restarted all the time by the outer sync() loop
+            let () = std::future::pending().await;
+            Ok(())
+        };
+
+        // UUT
+        let _jh = tokio::spawn(state::sync(
+            storage,
+            FakeTransport,
+            ethereum::Chain::Goerli,
+            FakeSequencer,
+            sync_state,
+            l1_noop,
+            l2,
+        ));
+
+        // TODO Find a better way to figure out that the DB update has already been performed
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let stored =
+            storage::StarknetRawBlocksTable::get(&connection, BLOCK0.block_hash.unwrap()).unwrap();
+        assert_eq!(stored, Some(raw_block()));
+    }
+
+    #[test]
+    fn sync_rate() {
+        let mut rate = super::SyncRate::default();
+        let start = std::time::Instant::now();
+
+        rate.record(start, StarknetBlockNumber(100));
+        assert_eq!(rate.blocks_per_second(), None);
+
+        rate.record(start + Duration::from_secs(2), StarknetBlockNumber(110));
+        assert_eq!(rate.blocks_per_second(), Some(5.0));
+
+        // Only the most recent blocks count.
+        for i in 1..=super::SyncRate::WINDOW as u64 {
+            rate.record(
+                start + Duration::from_secs(2 + i),
+                StarknetBlockNumber(110 + 2 * i),
+            );
+        }
+        assert_eq!(rate.blocks_per_second(), Some(2.0));
+
+        rate.clear();
+        assert_eq!(rate.blocks_per_second(), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn sync_status_updates_highest_block() {
+        use crate::rpc::types::reply::{syncing, Syncing};
+
+        let state = Arc::new(state::SyncState::default());
+        let stale = syncing::Status {
+            starting_block_hash: StarknetBlockHash(StarkHash::ZERO),
+            starting_block_num: StarknetBlockNumber(0),
+            current_block_hash: StarknetBlockHash(StarkHash::ZERO),
+            current_block_num: StarknetBlockNumber(0),
+            highest_block_hash: StarknetBlockHash(StarkHash::ZERO),
+            highest_block_num: StarknetBlockNumber(999),
+        };
+        *state.status.write().await = Syncing::Status(stale);
+
+        let handle = tokio::spawn(super::update_sync_status_latest(
+            Arc::clone(&state),
+            FakeSequencer,
+            StarknetBlockHash(StarkHash::ZERO),
+            StarknetBlockNumber(0),
+            ethereum::Chain::Goerli,
+        ));
+
+        // The first poll happens right away, the next one only after the poll interval.
+        let mut status = None;
+        for _ in 0..100 {
+            match &*state.status.read().await {
+                Syncing::Status(s) if s.highest_block_hash != stale.highest_block_hash => {
+                    status = Some(*s);
+                    break;
+                }
+                _ => {}
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        handle.abort();
+
+        let status = status.expect("Sync status was not updated");
+        assert_eq!(status.highest_block_hash, BLOCK0.block_hash.unwrap());
+        assert_eq!(status.highest_block_num, BLOCK0.block_number.unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn l2_reorg_notifies_reorgs() {
+        let storage = Storage::in_memory().unwrap();
+        let connection = storage.connection().unwrap();
+        StarknetBlocksTable::insert(&connection, &STORAGE_BLOCK0).unwrap();
+        StarknetBlocksTable::insert(&connection, &STORAGE_BLOCK1).unwrap();
+        let sync_state = Arc::new(state::SyncState::default());
+        let mut reorgs = sync_state.reorgs.subscribe();
+
+        let l2 = |tx: mpsc::Sender<l2::Event>, _, _, _| async move {
+            tx.send(l2::Event::Reorg(StarknetBlockNumber(1)))
+                .await
+                .unwrap();
+            // Avoid being restarted all the time by the outer sync() loop
+            let () = std::future::pending().await;
+            Ok(())
+        };
+
+        // UUT
+        let _jh = tokio::spawn(state::sync(
+            storage,
+            FakeTransport,
+            ethereum::Chain::Goerli,
+            FakeSequencer,
+            sync_state.clone(),
+            l1_noop,
+            l2,
+        ));
+
+        let reorg_tail = tokio::time::timeout(Duration::from_secs(1), reorgs.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reorg_tail, StarknetBlockNumber(1));
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn l2_reorg() {
         let results = [
@@ -1231,6 +1549,7 @@ mod tests {
                 bytecode: zstd_magic.clone(),
                 definition: zstd_magic,
                 hash: ContractHash(*A),
+                dictionary_id: None,
             }))
             .await
             .unwrap();
@@ -1308,6 +1627,7 @@ mod tests {
                 bytecode: zstd_magic.clone(),
                 definition: zstd_magic,
                 hash: ContractHash(*A),
+                dictionary_id: None,
             },
         )
         .unwrap();
@@ -5,7 +5,9 @@ use anyhow::Context;
 use tokio::sync::{mpsc, oneshot};
 
 use crate::core::{ContractHash, StarknetBlockHash, StarknetBlockNumber};
-use crate::ethereum::state_update::{ContractUpdate, DeployedContract, StateUpdate, StorageUpdate};
+use crate::ethereum::state_update::{
+    ContractUpdate, DeployedContract, NonceUpdate, StateUpdate, StorageUpdate,
+};
 use crate::rpc::types::{BlockNumberOrTag, Tag};
 use crate::sequencer::error::SequencerError;
 use crate::sequencer::reply::state_update::{Contract, StateDiff};
@@ -24,8 +26,9 @@ pub struct Timings {
 /// Events and queries emitted by L2 sync process.
 #[derive(Debug)]
 pub enum Event {
-    /// New L2 [block update](StateUpdate) found.
-    Update(Box<Block>, StateUpdate, Timings),
+    /// New L2 [block update](StateUpdate) found, with the block's JSON as received from the
+    /// sequencer if raw blocks are retained.
+    Update(Box<Block>, StateUpdate, Timings, Option<bytes::Bytes>),
     /// An L2 reorg was detected, contains the reorg-tail which
     /// indicates the oldest block which is now invalid
     /// i.e. reorg-tail + 1 should be the new head.
@@ -52,6 +55,7 @@ pub async fn sync(
     sequencer: impl sequencer::ClientApi,
     mut head: Option<(StarknetBlockNumber, StarknetBlockHash)>,
     chain: crate::ethereum::Chain,
+    retain_raw_blocks: bool,
 ) -> anyhow::Result<()> {
     use crate::state::sync::head_poll_interval;
 
@@ -63,9 +67,9 @@ pub async fn sync(
         };
 
         let t_block = std::time::Instant::now();
-        let block = loop {
-            match download_block(next, head_hash, &sequencer).await? {
-                DownloadBlock::Block(block) => break block,
+        let (block, raw_block) = loop {
+            match download_block(next, head_hash, &sequencer, retain_raw_blocks).await? {
+                DownloadBlock::Block(block, raw_block) => break (block, raw_block),
                 DownloadBlock::AtHead => {
                     let poll_interval = head_poll_interval(chain);
                     tracing::info!(poll_interval=?poll_interval, "At head of chain");
@@ -140,9 +144,17 @@ pub async fn sync(
             })
             .collect::<Vec<_>>();
 
+        let nonce_updates = state_update
+            .state_diff
+            .nonces
+            .into_iter()
+            .map(|(address, nonce)| NonceUpdate { address, nonce })
+            .collect::<Vec<_>>();
+
         let update = StateUpdate {
             deployed_contracts,
             contract_updates,
+            nonce_updates,
         };
 
         head = Some((next, block.block_hash.unwrap()));
@@ -154,14 +166,14 @@ pub async fn sync(
         };
 
         tx_event
-            .send(Event::Update(block, update, timings))
+            .send(Event::Update(block, update, timings, raw_block))
             .await
             .context("Event channel closed")?;
     }
 }
 
 enum DownloadBlock {
-    Block(Box<Block>),
+    Block(Box<Block>, Option<bytes::Bytes>),
     AtHead,
     Reorg,
 }
@@ -170,13 +182,27 @@ async fn download_block(
     block_number: StarknetBlockNumber,
     prev_block_hash: Option<StarknetBlockHash>,
     sequencer: &impl sequencer::ClientApi,
+    retain_raw_blocks: bool,
 ) -> anyhow::Result<DownloadBlock> {
     use sequencer::error::StarknetErrorCode::BlockNotFound;
 
-    let result = sequencer.block_by_number(block_number.into()).await;
+    let result = match retain_raw_blocks {
+        false => sequencer
+            .block_by_number(block_number.into())
+            .await
+            .map(|block| (block, None)),
+        true => match sequencer.raw_block_by_number(block_number.into()).await {
+            Ok(raw_block) => {
+                let block = serde_json::from_slice::<Block>(&raw_block)
+                    .context("Deserializing raw block")?;
+                Ok((block, Some(raw_block)))
+            }
+            Err(e) => Err(e),
+        },
+    };
 
     match result {
-        Ok(block) => Ok(DownloadBlock::Block(Box::new(block))),
+        Ok((block, raw_block)) => Ok(DownloadBlock::Block(Box::new(block), raw_block)),
         Err(SequencerError::StarknetError(err)) if err.code == BlockNotFound => {
             // This would occur if we queried past the head of the chain. We now need to check that
             // a reorg hasn't put us too far in the future. This does run into race conditions with
@@ -235,11 +261,11 @@ async fn reorg(
             None => break None,
         };
 
-        match download_block(previous_block_number, Some(previous_hash), sequencer)
+        match download_block(previous_block_number, Some(previous_hash), sequencer, false)
             .await
             .with_context(|| format!("Download block {} from sequencer", previous_block_number.0))?
         {
-            DownloadBlock::Block(block) if block.block_hash.unwrap() == previous_hash => {
+            DownloadBlock::Block(block, _) if block.block_hash.unwrap() == previous_hash => {
                 break Some((previous_block_number, previous_hash));
             }
             _ => {}
@@ -371,6 +397,7 @@ async fn download_and_compress_contract(
         bytecode,
         definition,
         hash,
+        dictionary_id: None,
     })
 }
 
@@ -394,7 +421,7 @@ mod tests {
         };
         use assert_matches::assert_matches;
         use pedersen::StarkHash;
-        use std::collections::HashMap;
+        use std::collections::BTreeMap;
 
         const DEF0: &str = r#"{
             "abi": [],
@@ -526,13 +553,14 @@ mod tests {
                         address: *CONTRACT0_ADDR,
                         contract_hash: *CONTRACT0_HASH,
                     }],
-                    storage_diffs: HashMap::from([(
+                    storage_diffs: BTreeMap::from([(
                      *CONTRACT0_ADDR,
                         vec![reply::state_update::StorageDiff {
                             key: *STORAGE_KEY0,
                             value: *STORAGE_VAL0,
                         }],
                     )]),
+                    nonces: BTreeMap::new(),
                 },
             };
             static ref STATE_UPDATE0_V2: reply::StateUpdate = reply::StateUpdate {
@@ -543,7 +571,8 @@ mod tests {
                         address: *CONTRACT0_ADDR_V2,
                         contract_hash: *CONTRACT0_HASH_V2,
                     }],
-                    storage_diffs: HashMap::new(),
+                    storage_diffs: BTreeMap::new(),
+                    nonces: BTreeMap::new(),
                 },
             };
             static ref STATE_UPDATE1: reply::StateUpdate = reply::StateUpdate {
@@ -554,7 +583,7 @@ mod tests {
                         address: *CONTRACT1_ADDR,
                         contract_hash: *CONTRACT1_HASH,
                     }],
-                    storage_diffs: HashMap::from([
+                    storage_diffs: BTreeMap::from([
                         (
                             *CONTRACT0_ADDR,
                             vec![reply::state_update::StorageDiff {
@@ -570,6 +599,7 @@ mod tests {
                             }],
                         ),
                     ]),
+                    nonces: BTreeMap::new(),
                 },
             };
             static ref STATE_UPDATE1_V2: reply::StateUpdate = reply::StateUpdate {
@@ -577,7 +607,8 @@ mod tests {
                 old_root: *GLOBAL_ROOT0_V2,
                 state_diff: reply::state_update::StateDiff {
                     deployed_contracts: vec![],
-                    storage_diffs: HashMap::new(),
+                    storage_diffs: BTreeMap::new(),
+                    nonces: BTreeMap::new(),
                 },
             };
             static ref STATE_UPDATE2: reply::StateUpdate = reply::StateUpdate {
@@ -585,7 +616,8 @@ mod tests {
                 old_root: *GLOBAL_ROOT1,
                 state_diff: reply::state_update::StateDiff {
                     deployed_contracts: vec![],
-                    storage_diffs: HashMap::new(),
+                    storage_diffs: BTreeMap::new(),
+                    nonces: BTreeMap::new(),
                 },
             };
             static ref STATE_UPDATE2_V2: reply::StateUpdate = reply::StateUpdate {
@@ -593,7 +625,8 @@ mod tests {
                 old_root: *GLOBAL_ROOT1_V2,
                 state_diff: reply::state_update::StateDiff {
                     deployed_contracts: vec![],
-                    storage_diffs: HashMap::new(),
+                    storage_diffs: BTreeMap::new(),
+                    nonces: BTreeMap::new(),
                 },
             };
             static ref STATE_UPDATE3: reply::StateUpdate = reply::StateUpdate {
@@ -601,7 +634,8 @@ mod tests {
                 old_root: *GLOBAL_ROOT2,
                 state_diff: reply::state_update::StateDiff {
                     deployed_contracts: vec![],
-                    storage_diffs: HashMap::new(),
+                    storage_diffs: BTreeMap::new(),
+                    nonces: BTreeMap::new(),
                 },
             };
 
@@ -621,6 +655,7 @@ mod tests {
                         hash: *CONTRACT0_HASH,
                         call_data: vec![],
                 }],
+                nonce_updates: vec![],
             };
             static ref EXPECTED_STATE_UPDATE1: state_update::StateUpdate = state_update::StateUpdate {
                 contract_updates: vec![
@@ -649,6 +684,7 @@ mod tests {
                         hash: *CONTRACT1_HASH,
                         call_data: vec![],
                 }],
+                nonce_updates: vec![],
             };
         }
 
@@ -749,7 +785,7 @@ mod tests {
                 expect_latest_block(&mut mock, &mut seq, Ok(BLOCK1.clone()));
 
                 // Let's run the UUT
-                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli));
+                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli, false));
 
                 let zstd_magic = vec![0x28, 0xb5, 0x2f, 0xfd];
 
@@ -765,7 +801,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT0_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block,state_update,_) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block,state_update,_, _) => {
                     assert_eq!(*block, *BLOCK0);
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE0);
                 });
@@ -781,13 +817,56 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT1_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block,mut state_update,_) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block,mut state_update,_, _) => {
                     assert_eq!(*block, *BLOCK1);
                     state_update.contract_updates.sort();
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE1);
                 });
             }
 
+            #[tokio::test]
+            async fn raw_blocks_retained() {
+                let (tx_event, mut rx_event) = tokio::sync::mpsc::channel(1);
+                let mut mock = MockClientApi::new();
+                let mut seq = mockall::Sequence::new();
+
+                let raw_block0 = bytes::Bytes::from(serde_json::to_vec(&*BLOCK0).unwrap());
+
+                // Downlad the genesis block as raw JSON with respective state update and contracts
+                let returned_block = raw_block0.clone();
+                mock.expect_raw_block_by_number()
+                    .withf(move |x| x == &BlockNumberOrTag::Number(BLOCK0_NUMBER))
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_once(move |_| Ok(returned_block));
+                expect_state_update(&mut mock, &mut seq, *BLOCK0_HASH, Ok(STATE_UPDATE0.clone()));
+                expect_full_contract(
+                    &mut mock,
+                    &mut seq,
+                    *CONTRACT0_ADDR,
+                    Ok(CONTRACT0_DEF.clone()),
+                );
+                // Stay at head, no more blocks available
+                mock.expect_raw_block_by_number()
+                    .withf(move |x| x == &BlockNumberOrTag::Number(BLOCK1_NUMBER))
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_once(|_| Err(block_not_found()));
+                expect_latest_block(&mut mock, &mut seq, Ok(BLOCK0.clone()));
+
+                // Let's run the UUT
+                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli, true));
+
+                assert_matches!(rx_event.recv().await.unwrap(), Event::QueryContractExistance(_, sender) => {
+                    sender.send(vec![false]).unwrap();
+                });
+                assert_matches!(rx_event.recv().await.unwrap(), Event::NewContract(_));
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, _, _, raw_block) => {
+                    assert_eq!(*block, *BLOCK0);
+                    assert_eq!(raw_block, Some(raw_block0));
+                });
+            }
+
             #[tokio::test]
             async fn resumed_after_genesis() {
                 let (tx_event, mut rx_event) = tokio::sync::mpsc::channel(1);
@@ -814,6 +893,7 @@ mod tests {
                     mock,
                     Some((BLOCK0_NUMBER, *BLOCK0_HASH)),
                     Chain::Goerli,
+                    false,
                 ));
 
                 let zstd_magic = vec![0x28, 0xb5, 0x2f, 0xfd];
@@ -830,7 +910,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT1_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block,mut state_update,_) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block,mut state_update,_, _) => {
                     assert_eq!(*block, *BLOCK1);
                     assert_eq!(state_update.deployed_contracts, vec![
                         state::sync::DeployedContract {
@@ -903,7 +983,7 @@ mod tests {
                 expect_latest_block(&mut mock, &mut seq, Ok(BLOCK0_V2.clone()));
 
                 // Let's run the UUT
-                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli));
+                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli, false));
 
                 let zstd_magic = vec![0x28, 0xb5, 0x2f, 0xfd];
 
@@ -919,7 +999,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT0_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK0);
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE0);
                 });
@@ -939,7 +1019,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT0_HASH_V2);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK0_V2);
                     assert_eq!(state_update.deployed_contracts, vec![
                         state::sync::DeployedContract {
@@ -1043,7 +1123,7 @@ mod tests {
                 expect_latest_block(&mut mock, &mut seq, Ok(block1_v2.clone()));
 
                 // Run the UUT
-                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli));
+                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli, false));
 
                 let zstd_magic = vec![0x28, 0xb5, 0x2f, 0xfd];
 
@@ -1059,7 +1139,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT0_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK0);
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE0);
                 });
@@ -1075,7 +1155,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT1_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, mut state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, mut state_update, _, _) => {
                     assert_eq!(*block, *BLOCK1);
                     assert_eq!(state_update.deployed_contracts, vec![
                         state::sync::DeployedContract {
@@ -1086,7 +1166,7 @@ mod tests {
                     state_update.contract_updates.sort();
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE1);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
@@ -1115,7 +1195,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT0_HASH_V2);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK0_V2);
                     assert_eq!(state_update.deployed_contracts, vec![
                         state::sync::DeployedContract {
@@ -1125,7 +1205,7 @@ mod tests {
                     }]);
                     assert!(state_update.contract_updates.is_empty());
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, block1_v2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
@@ -1249,7 +1329,7 @@ mod tests {
                 expect_latest_block(&mut mock, &mut seq, Ok(block2_v2.clone()));
 
                 // Run the UUT
-                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli));
+                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli, false));
 
                 let zstd_magic = vec![0x28, 0xb5, 0x2f, 0xfd];
 
@@ -1265,7 +1345,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT0_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK0);
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE0);
                 });
@@ -1281,7 +1361,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT1_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, mut state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, mut state_update, _, _) => {
                     assert_eq!(*block, *BLOCK1);
                     assert_eq!(state_update.deployed_contracts, vec![
                         state::sync::DeployedContract {
@@ -1292,12 +1372,12 @@ mod tests {
                     state_update.contract_updates.sort();
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE1);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, block3);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
@@ -1318,12 +1398,12 @@ mod tests {
                 assert_matches!(rx_event.recv().await.unwrap(), Event::Reorg(tail) => {
                     assert_eq!(tail, BLOCK1_NUMBER);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, block1_v2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, block2_v2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
@@ -1406,7 +1486,7 @@ mod tests {
                 expect_latest_block(&mut mock, &mut seq, Ok(block2_v2.clone()));
 
                 // Run the UUT
-                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli));
+                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli, false));
 
                 let zstd_magic = vec![0x28, 0xb5, 0x2f, 0xfd];
 
@@ -1422,7 +1502,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT0_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK0);
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE0);
                 });
@@ -1438,7 +1518,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT1_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, mut state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, mut state_update, _, _) => {
                     assert_eq!(*block, *BLOCK1);
                     assert_eq!(state_update.deployed_contracts, vec![
                         state::sync::DeployedContract {
@@ -1449,7 +1529,7 @@ mod tests {
                     state_update.contract_updates.sort();
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE1);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
@@ -1462,7 +1542,7 @@ mod tests {
                 assert_matches!(rx_event.recv().await.unwrap(), Event::Reorg(tail) => {
                     assert_eq!(tail, BLOCK2_NUMBER);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, block2_v2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
@@ -1554,7 +1634,7 @@ mod tests {
                 expect_latest_block(&mut mock, &mut seq, Ok(block2.clone()));
 
                 // Run the UUT
-                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli));
+                let _jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli, false));
 
                 let zstd_magic = vec![0x28, 0xb5, 0x2f, 0xfd];
 
@@ -1570,7 +1650,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT0_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, *BLOCK0);
                     assert_eq!(state_update, *EXPECTED_STATE_UPDATE0);
                 });
@@ -1586,7 +1666,7 @@ mod tests {
                         assert_eq!(compressed_contract.definition[..4], zstd_magic);
                         assert_eq!(compressed_contract.hash, *CONTRACT1_HASH);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, mut state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, mut state_update, _, _) => {
                     assert_eq!(*block, *BLOCK1);
                     assert_eq!(state_update.deployed_contracts, vec![
                         state::sync::DeployedContract {
@@ -1605,12 +1685,12 @@ mod tests {
                 assert_matches!(rx_event.recv().await.unwrap(), Event::Reorg(tail) => {
                     assert_eq!(tail, BLOCK1_NUMBER);
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, block1_v2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
                 });
-                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _) => {
+                assert_matches!(rx_event.recv().await.unwrap(), Event::Update(block, state_update, _, _) => {
                     assert_eq!(*block, block2);
                     assert!(state_update.deployed_contracts.is_empty());
                     assert!(state_update.contract_updates.is_empty());
@@ -1630,7 +1710,7 @@ mod tests {
                 expect_state_update(&mut mock, &mut seq, *BLOCK0_HASH, Ok(STATE_UPDATE0.clone()));
 
                 // Run the UUT
-                let jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli));
+                let jh = tokio::spawn(sync(tx_event, mock, None, Chain::Goerli, false));
 
                 // Wrap this in a timeout so we don't wait forever in case of test failure.
                 // Right now closing the channel causes an error.
@@ -15,9 +15,10 @@ use std::sync::Mutex;
 pub use contract::{ContractCodeTable, ContractsTable};
 pub use ethereum::{EthereumBlocksTable, EthereumTransactionsTable};
 pub use state::{
-    ContractsStateTable, EventFilterError, L1StateTable, L1TableBlockId, RefsTable, StarknetBlock,
-    StarknetBlocksBlockId, StarknetBlocksTable, StarknetEmittedEvent, StarknetEventFilter,
-    StarknetEventsTable, StarknetTransactionsTable,
+    ContractNoncesTable, ContractsStateTable, EventFilterError, L1StateTable, L1TableBlockId,
+    RefsTable, StarknetBlock, StarknetBlocksBlockId, StarknetBlocksTable, StarknetEmittedEvent,
+    StarknetEventFilter, StarknetEventsTable, StarknetMessagesTable, StarknetRawBlocksTable,
+    StarknetTransactionsTable, UnreadableTransaction,
 };
 
 use anyhow::Context;
@@ -27,7 +28,7 @@ use tracing::info;
 /// Indicates database is non-existant.
 const DB_VERSION_EMPTY: u32 = 0;
 /// Current database version.
-const DB_VERSION_CURRENT: u32 = 9;
+const DB_VERSION_CURRENT: u32 = 14;
 /// Sqlite key used for the PRAGMA user version.
 const VERSION_KEY: &str = "user_version";
 
@@ -38,11 +39,13 @@ const VERSION_KEY: &str = "user_version";
 /// - Pass the [Storage] (or clones thereof) to components which require database access.
 /// - Use [Storage::connection] to create connection's to the database, which can in turn
 ///   be used to interact with the various [tables](self).
+/// - Use [Storage::read_connection] instead for connections which should only read.
 #[derive(Clone)]
 pub struct Storage(std::sync::Arc<Inner>);
 
 struct Inner {
     database_path: PathBuf,
+    options: StorageOptions,
     /// Required to keep the in-memory variant alive. Sqlite drops in-memory databases
     /// as soon as all living connections are dropped, so we prevent this by storing
     /// a keep-alive connection.
@@ -53,21 +56,40 @@ struct Inner {
 }
 
 impl Storage {
-    /// Performs database schema migration and returns a new [Storage].
+    /// Performs database schema migration and returns a new [Storage], using the default
+    /// [StorageOptions].
     ///
     /// This should be called __once__ at the start of the application,
     /// and passed to the various components which require access to the database.
     ///
     /// May be cloned safely.
     pub fn migrate(database_path: PathBuf) -> anyhow::Result<Self> {
-        let mut conn = Self::open_connection(&database_path)?;
+        Self::open(database_path, StorageOptions::default())
+    }
+
+    /// Same as [Storage::migrate], but with the journal mode and synchronous level
+    /// selected by `options`.
+    ///
+    /// The journal mode is persisted in the database file, whereas the synchronous level is
+    /// applied to every [Connection] created by the returned [Storage].
+    ///
+    /// Fails without modifying the database if its schema is newer than this application
+    /// supports, i.e. after a downgrade of pathfinder. Older schemas are migrated.
+    pub fn open(database_path: PathBuf, options: StorageOptions) -> anyhow::Result<Self> {
+        let mut conn = Self::open_connection(&database_path, options)?;
+        check_schema_version(&conn)?;
+        set_journal_mode(&conn, options.journal_mode).context("Set database journal mode")?;
         migrate_database(&mut conn).context("Migrate database")?;
 
         #[cfg(not(test))]
-        let inner = Inner { database_path };
+        let inner = Inner {
+            database_path,
+            options,
+        };
         #[cfg(test)]
         let inner = Inner {
             database_path,
+            options,
             _keep_alive: Mutex::new(conn),
         };
 
@@ -78,13 +100,30 @@ impl Storage {
 
     /// Returns a new Sqlite [Connection] to the database.
     pub fn connection(&self) -> anyhow::Result<Connection> {
-        Self::open_connection(&self.0.database_path)
+        Self::open_connection(&self.0.database_path, self.0.options)
+    }
+
+    /// Returns a new read-only Sqlite [Connection] to the database.
+    ///
+    /// The connection has `PRAGMA query_only` enabled, so any attempt to write through it fails.
+    /// Use this for components which only serve data, i.e. the RPC API, leaving [Storage::connection]
+    /// to the writers.
+    pub fn read_connection(&self) -> anyhow::Result<Connection> {
+        let conn = self.connection()?;
+        conn.pragma_update(None, "query_only", true)
+            .context("Set database connection to query only")?;
+        Ok(conn)
     }
 
     /// Opens a connection the given database path.
-    fn open_connection(database_path: &Path) -> anyhow::Result<Connection> {
+    fn open_connection(
+        database_path: &Path,
+        options: StorageOptions,
+    ) -> anyhow::Result<Connection> {
         // TODO: think about flags?
         let conn = Connection::open(database_path)?;
+        conn.pragma_update(None, "synchronous", options.synchronous.as_str())
+            .context("Set database synchronous level")?;
         Ok(conn)
     }
 
@@ -106,12 +145,167 @@ impl Storage {
 
         let database_path = PathBuf::from(unique_mem_db);
 
-        Self::migrate(database_path)
+        // In-memory databases do not support WAL.
+        let options = StorageOptions {
+            journal_mode: JournalMode::Rollback,
+            ..Default::default()
+        };
+
+        Self::open(database_path, options)
     }
 
     pub fn path(&self) -> &Path {
         &self.0.database_path
     }
+
+    /// Performs database maintenance on a new [Connection].
+    ///
+    /// Always runs `PRAGMA optimize`, and additionally `ANALYZE` and `VACUUM` if requested
+    /// by [OptimizeOptions]. Note that `VACUUM` rewrites the entire database file and can
+    /// therefore take a long time on large databases.
+    pub fn optimize(&self, options: OptimizeOptions) -> anyhow::Result<()> {
+        let connection = self.connection()?;
+        optimize_database(&connection, options)
+    }
+
+    /// Checks the database for corruption using `PRAGMA integrity_check` on a new read-only
+    /// [Connection], returning the problems found. An empty list means the database is intact.
+    ///
+    /// This reads the entire database and can therefore take a long time on large databases.
+    pub fn integrity_check(&self) -> anyhow::Result<Vec<String>> {
+        let connection = self.read_connection()?;
+        check_database_integrity(&connection)
+    }
+}
+
+/// Selects how [Storage::open] configures the database.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct StorageOptions {
+    pub journal_mode: JournalMode,
+    pub synchronous: Synchronous,
+}
+
+/// Sqlite's [journal mode](https://www.sqlite.org/pragma.html#pragma_journal_mode).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum JournalMode {
+    /// Write-ahead log, which lets readers proceed concurrently with a writer. This greatly
+    /// improves RPC read throughput while syncing.
+    Wal,
+    /// The classic rollback journal, which is deleted at the end of each transaction.
+    Rollback,
+}
+
+impl Default for JournalMode {
+    fn default() -> Self {
+        JournalMode::Wal
+    }
+}
+
+/// Sqlite's [synchronous level](https://www.sqlite.org/pragma.html#pragma_synchronous).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Synchronous {
+    Off,
+    /// Safe from corruption in [JournalMode::Wal], but a power loss may roll back
+    /// the most recent transactions.
+    Normal,
+    Full,
+}
+
+impl Default for Synchronous {
+    fn default() -> Self {
+        Synchronous::Full
+    }
+}
+
+impl Synchronous {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Synchronous::Off => "OFF",
+            Synchronous::Normal => "NORMAL",
+            Synchronous::Full => "FULL",
+        }
+    }
+}
+
+/// Selects the optional maintenance steps of [Storage::optimize].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct OptimizeOptions {
+    /// Refreshes the query planner statistics of all tables and indexes using `ANALYZE`.
+    pub analyze: bool,
+    /// Rebuilds the database file using `VACUUM`, reclaiming unused pages.
+    pub vacuum: bool,
+}
+
+/// Runs `PRAGMA integrity_check` on the connection, which reports a single `ok` row if no
+/// problems were found, or a row per problem otherwise.
+fn check_database_integrity(connection: &Connection) -> anyhow::Result<Vec<String>> {
+    let mut statement = connection
+        .prepare("PRAGMA integrity_check")
+        .context("Preparing integrity check")?;
+    let problems = statement
+        .query_map([], |row| row.get::<_, String>(0))
+        .context("Running integrity check")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("Reading integrity check results")?;
+
+    match problems.as_slice() {
+        [ok] if ok == "ok" => Ok(Vec::new()),
+        _ => Ok(problems),
+    }
+}
+
+/// Runs the maintenance steps selected by `options` on the connection.
+///
+/// Fails if the connection has an open transaction, since `VACUUM` cannot run within one
+/// and `ANALYZE` should not hold up other writers for longer than necessary.
+fn optimize_database(connection: &Connection, options: OptimizeOptions) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        connection.is_autocommit(),
+        "Database optimization cannot run inside a transaction"
+    );
+
+    if options.analyze {
+        connection
+            .execute_batch("ANALYZE")
+            .context("Analyze database")?;
+    }
+
+    if options.vacuum {
+        info!("Vacuuming database, this may take a while.");
+        connection
+            .execute_batch("VACUUM")
+            .context("Vacuum database")?;
+    }
+
+    // Runs after ANALYZE, so that it can make use of the up-to-date statistics.
+    connection
+        .execute_batch("PRAGMA optimize")
+        .context("Optimize database")?;
+
+    Ok(())
+}
+
+/// Sets the journal mode of the database.
+///
+/// Fails if sqlite does not switch to the requested mode, e.g. if WAL is not supported
+/// by the file system. In-memory databases are the exception: they ignore this and
+/// always report `memory`.
+fn set_journal_mode(connection: &Connection, mode: JournalMode) -> anyhow::Result<()> {
+    let mode = match mode {
+        JournalMode::Wal => "WAL",
+        JournalMode::Rollback => "DELETE",
+    };
+    // Setting the journal mode returns the resulting mode as a row, which
+    // rules out `execute`.
+    let actual = connection
+        .pragma_update_and_check(None, "journal_mode", mode, |row| row.get::<_, String>(0))?;
+    anyhow::ensure!(
+        actual.eq_ignore_ascii_case(mode) || actual.eq_ignore_ascii_case("memory"),
+        "Database journal mode is {} instead of the requested {}",
+        actual,
+        mode
+    );
+    Ok(())
 }
 
 /// Migrates the database to the latest version. This __MUST__ be called
@@ -120,15 +314,7 @@ fn migrate_database(connection: &mut Connection) -> anyhow::Result<()> {
     use schema::PostMigrationAction;
 
     enable_foreign_keys(connection).context("Failed to enable foreign key support")?;
-    let version = schema_version(connection)?;
-
-    // Check that the database is not newer than this application knows of.
-    anyhow::ensure!(
-        version <= DB_VERSION_CURRENT,
-        "Database version is newer than this application ({} > {})",
-        version,
-        DB_VERSION_CURRENT
-    );
+    let version = check_schema_version(connection)?;
 
     let mut post_action = PostMigrationAction::None;
 
@@ -147,6 +333,11 @@ fn migrate_database(connection: &mut Connection) -> anyhow::Result<()> {
             6 => schema::revision_0007::migrate(&transaction)?,
             7 => schema::revision_0008::migrate(&transaction)?,
             8 => schema::revision_0009::migrate(&transaction)?,
+            9 => schema::revision_0010::migrate(&transaction)?,
+            10 => schema::revision_0011::migrate(&transaction)?,
+            11 => schema::revision_0012::migrate(&transaction)?,
+            12 => schema::revision_0013::migrate(&transaction)?,
+            13 => schema::revision_0014::migrate(&transaction)?,
             _ => unreachable!("Database version constraint was already checked!"),
         };
         // If any migration action requires vacuuming, we should vacuum.
@@ -174,6 +365,20 @@ fn migrate_database(connection: &mut Connection) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Returns the schema version of the database, failing if it is newer than this application
+/// knows of. Reading such a database could return garbage.
+fn check_schema_version(connection: &Connection) -> anyhow::Result<u32> {
+    let version = schema_version(connection).context("Read database schema version")?;
+    anyhow::ensure!(
+        version <= DB_VERSION_CURRENT,
+        "Database schema version {} is newer than the latest version {} supported by this \
+        application. Upgrade pathfinder, or use a different data directory.",
+        version,
+        DB_VERSION_CURRENT
+    );
+    Ok(version)
+}
+
 /// Returns the current schema version of the existing database,
 /// or [DB_VERSION_EMPTY] if database does not yet exist.
 fn schema_version(connection: &Connection) -> anyhow::Result<u32> {
@@ -256,6 +461,165 @@ mod tests {
         migrate_database(&mut conn).unwrap_err();
     }
 
+    #[test]
+    fn open_refuses_newer_database() {
+        let db_file = tempfile::NamedTempFile::new().unwrap();
+        {
+            let connection = rusqlite::Connection::open(db_file.path()).unwrap();
+            connection
+                .pragma_update(None, VERSION_KEY, DB_VERSION_CURRENT + 1)
+                .unwrap();
+        }
+
+        let error = Storage::open(db_file.path().to_path_buf(), StorageOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Database schema version {} is newer than the latest version {} supported by this \
+                application. Upgrade pathfinder, or use a different data directory.",
+                DB_VERSION_CURRENT + 1,
+                DB_VERSION_CURRENT
+            )
+        );
+
+        // The database is left as it was.
+        let connection = rusqlite::Connection::open(db_file.path()).unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), DB_VERSION_CURRENT + 1);
+        let mode = connection
+            .pragma_query_value(None, "journal_mode", |row| row.get::<_, String>(0))
+            .unwrap();
+        assert_eq!(mode, "delete");
+    }
+
+    #[test]
+    fn open_migrates_older_database() {
+        let db_file = tempfile::NamedTempFile::new().unwrap();
+        {
+            let mut connection = rusqlite::Connection::open(db_file.path()).unwrap();
+            let transaction = connection.transaction().unwrap();
+            schema::revision_0001::migrate(&transaction).unwrap();
+            transaction.pragma_update(None, VERSION_KEY, 1).unwrap();
+            transaction.commit().unwrap();
+        }
+
+        let storage =
+            Storage::open(db_file.path().to_path_buf(), StorageOptions::default()).unwrap();
+        let connection = storage.connection().unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), DB_VERSION_CURRENT);
+    }
+
+    #[test]
+    fn optimize() {
+        let storage = Storage::in_memory().unwrap();
+
+        let options = OptimizeOptions {
+            analyze: true,
+            vacuum: true,
+        };
+        storage.optimize(options).unwrap();
+        storage.optimize(OptimizeOptions::default()).unwrap();
+
+        // The database must still be usable afterwards.
+        let connection = storage.connection().unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), DB_VERSION_CURRENT);
+        StarknetBlocksTable::get_latest_number(&connection).unwrap();
+    }
+
+    #[test]
+    fn integrity_check() {
+        let storage = Storage::in_memory().unwrap();
+        assert_eq!(storage.integrity_check().unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn optimize_refuses_open_transaction() {
+        let mut connection = rusqlite::Connection::open_in_memory().unwrap();
+        migrate_database(&mut connection).unwrap();
+
+        let transaction = connection.transaction().unwrap();
+        optimize_database(&transaction, OptimizeOptions::default()).unwrap_err();
+    }
+
+    #[test]
+    fn open_file_in_wal_mode() {
+        let db_file = tempfile::NamedTempFile::new().unwrap();
+        let storage =
+            Storage::open(db_file.path().to_path_buf(), StorageOptions::default()).unwrap();
+
+        let connection = storage.connection().unwrap();
+        let mode = connection
+            .pragma_query_value(None, "journal_mode", |row| row.get::<_, String>(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        assert_eq!(schema_version(&connection).unwrap(), DB_VERSION_CURRENT);
+    }
+
+    #[test]
+    fn journal_mode_mismatch_is_an_error() {
+        // Temporary databases do not support WAL and stay in rollback mode.
+        let connection = rusqlite::Connection::open("").unwrap();
+        let error = set_journal_mode(&connection, JournalMode::Wal).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Database journal mode is delete instead of the requested WAL"
+        );
+
+        set_journal_mode(&connection, JournalMode::Rollback).unwrap();
+    }
+
+    #[test]
+    fn open_file_with_rollback_journal() {
+        let db_file = tempfile::NamedTempFile::new().unwrap();
+        let options = StorageOptions {
+            journal_mode: JournalMode::Rollback,
+            synchronous: Synchronous::Normal,
+        };
+        let storage = Storage::open(db_file.path().to_path_buf(), options).unwrap();
+
+        let connection = storage.connection().unwrap();
+        let mode = connection
+            .pragma_query_value(None, "journal_mode", |row| row.get::<_, String>(0))
+            .unwrap();
+        assert_eq!(mode, "delete");
+        // NORMAL is reported as 1.
+        let synchronous = connection
+            .pragma_query_value(None, "synchronous", |row| row.get::<_, u32>(0))
+            .unwrap();
+        assert_eq!(synchronous, 1);
+    }
+
+    #[test]
+    fn read_connection_rejects_writes() {
+        let storage = Storage::in_memory().unwrap();
+
+        let connection = storage.read_connection().unwrap();
+        let count: i64 = connection
+            .query_row("SELECT count(1) FROM starknet_blocks", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let error = connection
+            .execute("DELETE FROM starknet_blocks", [])
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("readonly"),
+            "unexpected error: {}",
+            error
+        );
+        connection
+            .execute_batch("CREATE TABLE read_only_test (id INTEGER)")
+            .unwrap_err();
+
+        // Regular connections are unaffected.
+        storage
+            .connection()
+            .unwrap()
+            .execute("DELETE FROM starknet_blocks", [])
+            .unwrap();
+    }
+
     #[test]
     fn foreign_keys_are_enforced() {
         let conn = rusqlite::Connection::open_in_memory().unwrap();
@@ -16,6 +16,10 @@ use rusqlite::{named_params, Connection, OptionalExtension, Transaction};
 pub struct ContractCodeTable {}
 
 impl ContractCodeTable {
+    /// The default maximum size of a decompressed column, which bounds the memory a corrupt or
+    /// maliciously crafted column can consume when read.
+    pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
     /// Insert a contract into the table.
     ///
     /// Does nothing if the contract [hash](ContractHash) is already populated.
@@ -26,8 +30,30 @@ impl ContractCodeTable {
         bytecode: &[u8],
         definition: &[u8],
     ) -> anyhow::Result<()> {
-        let mut compressor = zstd::bulk::Compressor::new(10)
-            .context("Couldn't create zstd compressor for ContractCodeTable")?;
+        Self::insert_with_dictionary(transaction, hash, abi, bytecode, definition, None)
+    }
+
+    /// Insert a contract into the table, compressing it with the given dictionary.
+    ///
+    /// The dictionary must have been stored using [ContractCodeTable::insert_dictionary].
+    pub fn insert_with_dictionary(
+        transaction: &Transaction,
+        hash: ContractHash,
+        abi: &[u8],
+        bytecode: &[u8],
+        definition: &[u8],
+        dictionary_id: Option<i64>,
+    ) -> anyhow::Result<()> {
+        let compressor = match dictionary_id {
+            Some(id) => {
+                let dictionary = Self::get_dictionary(transaction, id)?
+                    .with_context(|| format!("Unknown compression dictionary {}", id))?;
+                zstd::bulk::Compressor::with_dictionary(10, &dictionary)
+            }
+            None => zstd::bulk::Compressor::new(10),
+        };
+        let mut compressor =
+            compressor.context("Couldn't create zstd compressor for ContractCodeTable")?;
         let abi = compressor.compress(abi).context("Failed to compress ABI")?;
         let bytecode = compressor
             .compress(bytecode)
@@ -41,11 +67,16 @@ impl ContractCodeTable {
             bytecode,
             definition,
             hash,
+            dictionary_id,
         };
 
         Self::insert_compressed(transaction, &contract)
     }
 
+    /// Insert an already compressed contract into the table.
+    ///
+    /// Does nothing if the contract [hash](ContractHash) is already populated, so that a class
+    /// deployed at many addresses is only stored once.
     pub fn insert_compressed(
         connection: &Connection,
         contract: &CompressedContract,
@@ -57,28 +88,63 @@ impl ContractCodeTable {
         assert_eq!(&contract.definition[..4], magic);
 
         connection.execute(
-            r"INSERT INTO contract_code ( hash,  bytecode,  abi,  definition)
-                             VALUES (:hash, :bytecode, :abi, :definition)",
+            r"INSERT OR IGNORE INTO contract_code ( hash,  bytecode,  abi,  definition,  dictionary_id)
+                                       VALUES (:hash, :bytecode, :abi, :definition, :dictionary_id)",
             named_params! {
                 ":hash": &contract.hash.0.to_be_bytes()[..],
                 ":bytecode": &contract.bytecode[..],
                 ":abi": &contract.abi[..],
                 ":definition": &contract.definition[..],
+                ":dictionary_id": &contract.dictionary_id,
             },
         )?;
         Ok(())
     }
 
+    /// Stores a zstd compression dictionary, returning its id.
+    pub fn insert_dictionary(connection: &Connection, dictionary: &[u8]) -> anyhow::Result<i64> {
+        connection.execute(
+            "INSERT INTO contract_code_dictionaries (dictionary) VALUES (?)",
+            [dictionary],
+        )?;
+        Ok(connection.last_insert_rowid())
+    }
+
+    /// Gets the zstd compression dictionary with the given id.
+    pub fn get_dictionary(connection: &Connection, id: i64) -> anyhow::Result<Option<Vec<u8>>> {
+        let dictionary = connection
+            .query_row(
+                "SELECT dictionary FROM contract_code_dictionaries WHERE id = ?",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(dictionary)
+    }
+
     /// Gets the specified contract's [code](ContractCode).
+    ///
+    /// Columns are decompressed up to [ContractCodeTable::DEFAULT_MAX_DECOMPRESSED_SIZE].
     pub fn get_code(
         transaction: &Transaction,
         address: ContractAddress,
+    ) -> anyhow::Result<Option<ContractCode>> {
+        Self::get_code_with_limit(transaction, address, Self::DEFAULT_MAX_DECOMPRESSED_SIZE)
+    }
+
+    /// Gets the specified contract's [code](ContractCode), failing if any of its columns
+    /// decompresses to more than `max_decompressed_size` bytes.
+    pub fn get_code_with_limit(
+        transaction: &Transaction,
+        address: ContractAddress,
+        max_decompressed_size: usize,
     ) -> anyhow::Result<Option<ContractCode>> {
         let row = transaction
             .query_row(
-                "SELECT contract_code.bytecode, contract_code.abi
+                "SELECT contract_code.bytecode, contract_code.abi, contract_code_dictionaries.dictionary
                 FROM contracts
                 JOIN contract_code ON contracts.hash = contract_code.hash
+                LEFT JOIN contract_code_dictionaries ON contract_code.dictionary_id = contract_code_dictionaries.id
                 WHERE contracts.address = :address
                 LIMIT 1",
                 named_params! {
@@ -87,26 +153,23 @@ impl ContractCodeTable {
                 |row| {
                     let bytecode: Vec<u8> = row.get("bytecode")?;
                     let abi: Vec<u8> = row.get("abi")?;
+                    let dictionary: Option<Vec<u8>> = row.get("dictionary")?;
 
-                    Ok((bytecode, abi))
+                    Ok((bytecode, abi, dictionary))
                 },
             )
             .optional()?;
 
-        let (bytecode, abi) = match row {
+        let (bytecode, abi, dictionary) = match row {
             None => return Ok(None),
-            Some((bytecode, abi)) => (bytecode, abi),
+            Some((bytecode, abi, dictionary)) => (bytecode, abi, dictionary),
         };
 
-        // It might be dangerious to not have some upper bound on the compressed size.
-        // someone could put a very tight bomb to our database, and then have it OOM during
-        // runtime, but if you can already modify our database at will, maybe there's more useful
-        // things to do.
-
-        let bytecode = zstd::decode_all(&*bytecode)
+        let bytecode = decompress(&bytecode, dictionary.as_deref(), max_decompressed_size)
             .context("Corruption: invalid compressed column (bytecode)")?;
 
-        let abi = zstd::decode_all(&*abi).context("Corruption: invalid compressed column (abi)")?;
+        let abi = decompress(&abi, dictionary.as_deref(), max_decompressed_size)
+            .context("Corruption: invalid compressed column (abi)")?;
 
         let abi =
             String::from_utf8(abi).context("Corruption: invalid uncompressed column (abi)")?;
@@ -117,6 +180,52 @@ impl ContractCodeTable {
         Ok(Some(ContractCode { bytecode, abi }))
     }
 
+    /// Gets the specified contract's uncompressed definition.
+    ///
+    /// The definition is decompressed up to [ContractCodeTable::DEFAULT_MAX_DECOMPRESSED_SIZE].
+    pub fn get_definition(
+        connection: &Connection,
+        hash: ContractHash,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        Self::get_definition_with_limit(connection, hash, Self::DEFAULT_MAX_DECOMPRESSED_SIZE)
+    }
+
+    /// Gets the specified contract's uncompressed definition, failing if it decompresses to
+    /// more than `max_decompressed_size` bytes.
+    pub fn get_definition_with_limit(
+        connection: &Connection,
+        hash: ContractHash,
+        max_decompressed_size: usize,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let row = connection
+            .query_row(
+                "SELECT contract_code.definition, contract_code_dictionaries.dictionary
+                FROM contract_code
+                LEFT JOIN contract_code_dictionaries ON contract_code.dictionary_id = contract_code_dictionaries.id
+                WHERE contract_code.hash = :hash",
+                named_params! {
+                    ":hash": &hash.0.to_be_bytes()[..]
+                },
+                |row| {
+                    let definition: Vec<u8> = row.get("definition")?;
+                    let dictionary: Option<Vec<u8>> = row.get("dictionary")?;
+
+                    Ok((definition, dictionary))
+                },
+            )
+            .optional()?;
+
+        let (definition, dictionary) = match row {
+            None => return Ok(None),
+            Some(row) => row,
+        };
+
+        let definition = decompress(&definition, dictionary.as_deref(), max_decompressed_size)
+            .context("Corruption: invalid compressed column (definition)")?;
+
+        Ok(Some(definition))
+    }
+
     /// Returns true for each [ContractHash] if the contract definition already exists in the table.
     pub fn exists(
         connection: &Connection,
@@ -131,6 +240,35 @@ impl ContractCodeTable {
     }
 }
 
+/// Decompresses a [ContractCodeTable] column, using the dictionary it was compressed with, if any.
+///
+/// Fails without decompressing any further once the output exceeds `max_size` bytes.
+fn decompress(data: &[u8], dictionary: Option<&[u8]>, max_size: usize) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let decoder: Box<dyn Read + '_> = match dictionary {
+        Some(dictionary) => Box::new(zstd::stream::read::Decoder::with_dictionary(
+            data, dictionary,
+        )?),
+        None => Box::new(zstd::stream::read::Decoder::new(data)?),
+    };
+
+    // Reading a single byte past the limit tells apart output of exactly the maximum size.
+    let mut decompressed = Vec::new();
+    decoder
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+
+    if decompressed.len() > max_size {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Decompressed size exceeds the limit of {} bytes", max_size),
+        ));
+    }
+
+    Ok(decompressed)
+}
+
 /// Stores the mapping from StarkNet contract [address](ContractAddress) to [hash](ContractHash).
 pub struct ContractsTable {}
 
@@ -254,6 +392,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn same_definition_stored_once() {
+        let storage = Storage::in_memory().unwrap();
+        let mut conn = storage.connection().unwrap();
+        let transaction = conn.transaction().unwrap();
+
+        let address0 = ContractAddress(StarkHash::from_hex_str("abc").unwrap());
+        let address1 = ContractAddress(StarkHash::from_hex_str("def").unwrap());
+        let hash = ContractHash(StarkHash::from_hex_str("123").unwrap());
+
+        let abi = br#"[{"this":"looks"},{"like": "this"}]"#;
+        let code = br#"["0x40780017fff7fff","0x1","0x208b7fff7fff7ffe"]"#;
+        let definition = br#"{"abi":{"see":"above"},"program":{"huge":"hash"},"entry_points_by_type":{"this might be a":"hash"}}"#;
+
+        // The same class deployed at two addresses.
+        for address in [address0, address1] {
+            ContractCodeTable::insert(&transaction, hash, &abi[..], &code[..], &definition[..])
+                .unwrap();
+            ContractsTable::upsert(&transaction, address, hash).unwrap();
+        }
+
+        let count: i64 = transaction
+            .query_row("SELECT COUNT(*) FROM contract_code", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let code0 = ContractCodeTable::get_code(&transaction, address0).unwrap();
+        let code1 = ContractCodeTable::get_code(&transaction, address1).unwrap();
+        assert!(code0.is_some());
+        assert_eq!(code0, code1);
+        assert_eq!(
+            ContractsTable::get_hash(&transaction, address0).unwrap(),
+            Some(hash)
+        );
+        assert_eq!(
+            ContractsTable::get_hash(&transaction, address1).unwrap(),
+            Some(hash)
+        );
+    }
+
     #[test]
     fn contracts_exist() {
         let storage = Storage::in_memory().unwrap();
@@ -277,4 +455,108 @@ mod tests {
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn decompressed_size_limit() {
+        let storage = Storage::in_memory().unwrap();
+        let mut conn = storage.connection().unwrap();
+        let transaction = conn.transaction().unwrap();
+
+        let address = ContractAddress(StarkHash::from_hex_str("abc").unwrap());
+        let hash = ContractHash(StarkHash::from_hex_str("123").unwrap());
+
+        // Compresses to a small fraction of its size.
+        let abi = vec![b' '; 4096];
+        let code = br#"["0x40780017fff7fff","0x1","0x208b7fff7fff7ffe"]"#;
+        let definition = vec![b' '; 4096];
+
+        ContractCodeTable::insert(&transaction, hash, &abi, &code[..], &definition).unwrap();
+        ContractsTable::upsert(&transaction, address, hash).unwrap();
+
+        ContractCodeTable::get_code_with_limit(&transaction, address, 1024).unwrap_err();
+        ContractCodeTable::get_definition_with_limit(&transaction, hash, 1024).unwrap_err();
+
+        // The limit is inclusive.
+        let code = ContractCodeTable::get_code_with_limit(&transaction, address, 4096).unwrap();
+        assert_eq!(code.unwrap().abi.len(), 4096);
+        let stored =
+            ContractCodeTable::get_definition_with_limit(&transaction, hash, 4096).unwrap();
+        assert_eq!(stored, Some(definition));
+    }
+
+    mod dictionary {
+        use super::*;
+
+        const ABI: &[u8] = br#"[{"this":"looks"},{"like": "this"}]"#;
+        const CODE: &[u8] = br#"["0x40780017fff7fff","0x1","0x208b7fff7fff7ffe"]"#;
+        const DEFINITION: &[u8] = br#"{"abi":{"see":"above"},"program":{"huge":"hash"},"entry_points_by_type":{"this might be a":"hash"}}"#;
+
+        #[test]
+        fn without_dictionary() {
+            let storage = Storage::in_memory().unwrap();
+            let mut conn = storage.connection().unwrap();
+            let transaction = conn.transaction().unwrap();
+
+            let hash = ContractHash(StarkHash::from_hex_str("123").unwrap());
+            ContractCodeTable::insert(&transaction, hash, ABI, CODE, DEFINITION).unwrap();
+
+            let definition = ContractCodeTable::get_definition(&transaction, hash).unwrap();
+            assert_eq!(definition.as_deref(), Some(DEFINITION));
+        }
+
+        #[test]
+        fn with_dictionary() {
+            let storage = Storage::in_memory().unwrap();
+            let mut conn = storage.connection().unwrap();
+            let transaction = conn.transaction().unwrap();
+
+            let address = ContractAddress(StarkHash::from_hex_str("abc").unwrap());
+            let hash = ContractHash(StarkHash::from_hex_str("123").unwrap());
+
+            // Any content can serve as a raw zstd dictionary.
+            let dictionary = br#"{"abi":[{"this":"looks"}],"program":{"huge":"hash"},"entry_points_by_type":{}}"#;
+            let id = ContractCodeTable::insert_dictionary(&transaction, dictionary).unwrap();
+
+            ContractCodeTable::insert_with_dictionary(
+                &transaction,
+                hash,
+                ABI,
+                CODE,
+                DEFINITION,
+                Some(id),
+            )
+            .unwrap();
+            ContractsTable::upsert(&transaction, address, hash).unwrap();
+
+            let definition = ContractCodeTable::get_definition(&transaction, hash).unwrap();
+            assert_eq!(definition.as_deref(), Some(DEFINITION));
+
+            let code = ContractCodeTable::get_code(&transaction, address).unwrap();
+            assert_eq!(
+                code,
+                Some(ContractCode {
+                    abi: String::from_utf8(ABI.to_vec()).unwrap(),
+                    bytecode: serde_json::from_slice::<Vec<ByteCodeWord>>(CODE).unwrap(),
+                })
+            );
+        }
+
+        #[test]
+        fn unknown_dictionary() {
+            let storage = Storage::in_memory().unwrap();
+            let mut conn = storage.connection().unwrap();
+            let transaction = conn.transaction().unwrap();
+
+            let hash = ContractHash(StarkHash::from_hex_str("123").unwrap());
+            ContractCodeTable::insert_with_dictionary(
+                &transaction,
+                hash,
+                ABI,
+                CODE,
+                DEFINITION,
+                Some(1),
+            )
+            .unwrap_err();
+        }
+    }
 }
@@ -45,6 +45,8 @@ use rusqlite::{named_params, OptionalExtension, Transaction};
 
 use pedersen::StarkHash;
 
+use crate::state::node_cache::NodeCache;
+
 /// Provides a reference counted storage backend for the
 /// nodes of a Starknet Binary Merkle Patricia Tree.
 ///
@@ -59,6 +61,7 @@ use pedersen::StarkHash;
 pub struct RcNodeStorage<'a> {
     transaction: &'a Transaction<'a>,
     table: String,
+    cache: Option<&'a NodeCache>,
 }
 
 impl<'a> crate::state::merkle_tree::NodeStorage for RcNodeStorage<'a> {
@@ -70,7 +73,6 @@ impl<'a> crate::state::merkle_tree::NodeStorage for RcNodeStorage<'a> {
         self.upsert(key, node)
     }
 
-    #[cfg(test)]
     fn decrement_ref_count(&self, key: StarkHash) -> anyhow::Result<()> {
         RcNodeStorage::decrement_ref_count(self, key)
     }
@@ -197,7 +199,20 @@ impl<'a> RcNodeStorage<'a> {
             [],
         )?;
 
-        Ok(Self { transaction, table })
+        Ok(Self {
+            transaction,
+            table,
+            cache: None,
+        })
+    }
+
+    /// Reads nodes through the shared `cache`, falling back to the database for nodes which
+    /// are not cached yet.
+    pub fn with_cache(self, cache: &'a NodeCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
     }
 
     /// Inserts the node into storage, and increments the reference count of the node's
@@ -253,6 +268,10 @@ impl<'a> RcNodeStorage<'a> {
 
     /// Returns the node given by `key`, or [None] if it doesn't exist.
     pub fn get(&self, key: StarkHash) -> anyhow::Result<Option<PersistedNode>> {
+        if let Some(node) = self.cache.and_then(|cache| cache.get(&self.table, key)) {
+            return Ok(Some(node));
+        }
+
         let hash = key.to_be_bytes();
 
         let node = self
@@ -268,8 +287,13 @@ impl<'a> RcNodeStorage<'a> {
                 },
             )
             .optional()?;
+        let node = node.transpose()?;
+
+        if let (Some(cache), Some(node)) = (self.cache, &node) {
+            cache.insert(&self.table, key, node.clone());
+        }
 
-        node.transpose()
+        Ok(node)
     }
 
     /// Deletes the given node from storage, and decrements the reference count of the node's
@@ -283,7 +307,6 @@ impl<'a> RcNodeStorage<'a> {
     ///
     /// Does not perform rollback on failure. This implies that you should rollback the [RcNodeStorage's](RcNodeStorage) transaction
     /// if this call returns an error to prevent database corruption.
-    #[cfg(test)]
     fn delete_node(&self, key: StarkHash) -> anyhow::Result<()> {
         let hash = key.to_be_bytes();
 
@@ -313,7 +336,6 @@ impl<'a> RcNodeStorage<'a> {
 
     /// Decrements the reference count of the node and automatically deletes it
     /// if the count becomes zero.
-    #[cfg(test)]
     pub fn decrement_ref_count(&self, key: StarkHash) -> anyhow::Result<()> {
         let hash = key.to_be_bytes();
 
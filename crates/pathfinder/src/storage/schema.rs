@@ -7,6 +7,11 @@ pub(crate) mod revision_0006;
 pub(crate) mod revision_0007;
 pub(crate) mod revision_0008;
 pub(crate) mod revision_0009;
+pub(crate) mod revision_0010;
+pub(crate) mod revision_0011;
+pub(crate) mod revision_0012;
+pub(crate) mod revision_0013;
+pub(crate) mod revision_0014;
 
 /// Used to indicate which action the caller should perform after a schema migration.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -0,0 +1,148 @@
+use anyhow::Context;
+use rusqlite::{named_params, Transaction};
+
+use crate::storage::schema::PostMigrationAction;
+
+/// This schema migration adds the first key of each event as a separate `key0` column,
+/// and replaces the `from_address` index with a compound (from_address, key0) index.
+///
+/// This lets event queries filtering on both contract address and keys narrow down
+/// the candidate events using a single index. The compound index also covers
+/// queries on `from_address` alone, so the old index is no longer required.
+pub(crate) fn migrate(transaction: &Transaction) -> anyhow::Result<PostMigrationAction> {
+    transaction
+        .execute("ALTER TABLE starknet_events ADD COLUMN key0 BLOB", [])
+        .context("Add key0 column to starknet_events table")?;
+
+    let todo: usize = transaction
+        .query_row("SELECT count(1) FROM starknet_events", [], |r| r.get(0))
+        .context("Count rows in starknet events table")?;
+
+    if todo > 0 {
+        tracing::info!(
+            num_events=%todo,
+            "Extracting the first key of events, this may take a while.",
+        );
+
+        let mut stmt = transaction
+            .prepare("SELECT rowid, keys FROM starknet_events")
+            .context("Prepare event query")?;
+        let mut rows = stmt.query([])?;
+
+        while let Some(r) = rows.next()? {
+            let rowid: i64 = r.get("rowid")?;
+            let keys = r.get_ref_unwrap("keys").as_str()?;
+
+            // Keys are stored as space-separated base64 strings, an event without keys is stored as
+            // an empty string.
+            let key0 = match keys.split(' ').next().filter(|key| !key.is_empty()) {
+                Some(key) => Some(base64::decode(key).context("Decode first event key")?),
+                None => None,
+            };
+
+            transaction
+                .execute(
+                    "UPDATE starknet_events SET key0 = :key0 WHERE rowid = :rowid",
+                    named_params![":key0": key0, ":rowid": rowid],
+                )
+                .context("Update key0 of event")?;
+        }
+    }
+
+    transaction
+        .execute_batch(
+            r"DROP INDEX starknet_events_from_address;
+            CREATE INDEX starknet_events_from_address_key0 ON starknet_events(from_address, key0);",
+        )
+        .context("Replace from_address index with compound from_address and key0 index")?;
+
+    Ok(PostMigrationAction::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostMigrationAction;
+    use crate::storage::schema;
+    use rusqlite::{named_params, Connection};
+
+    fn migrate_to_previous(transaction: &rusqlite::Transaction<'_>) {
+        schema::revision_0001::migrate(transaction).unwrap();
+        schema::revision_0002::migrate(transaction).unwrap();
+        schema::revision_0003::migrate(transaction).unwrap();
+        schema::revision_0004::migrate(transaction).unwrap();
+        schema::revision_0005::migrate(transaction).unwrap();
+        schema::revision_0006::migrate(transaction).unwrap();
+        schema::revision_0007::migrate(transaction).unwrap();
+        schema::revision_0008::migrate(transaction).unwrap();
+        schema::revision_0009::migrate(transaction).unwrap();
+    }
+
+    #[test]
+    fn empty() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let transaction = conn.transaction().unwrap();
+
+        migrate_to_previous(&transaction);
+
+        let action = super::migrate(&transaction).unwrap();
+        assert_eq!(action, PostMigrationAction::None);
+    }
+
+    #[test]
+    fn stateful() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let transaction = conn.transaction().unwrap();
+
+        migrate_to_previous(&transaction);
+
+        // The events reference their block.
+        transaction
+            .execute(
+                r"INSERT INTO starknet_blocks ( number,  hash,  root,  timestamp)
+                                       VALUES (:number, :hash, :root, :timestamp)",
+                named_params![
+                    ":number": 0,
+                    ":hash": &[0u8; 32][..],
+                    ":root": &[0u8; 32][..],
+                    ":timestamp": 0,
+                ],
+            )
+            .unwrap();
+
+        let key0 = [0x11u8; 32];
+        let key1 = [0x22u8; 32];
+        let keys = [
+            format!("{} {}", base64::encode(key0), base64::encode(key1)),
+            String::new(),
+        ];
+        for (idx, keys) in keys.iter().enumerate() {
+            transaction
+                .execute(
+                    r"INSERT INTO starknet_events ( block_number,  idx,  transaction_hash,  from_address,  keys,  data)
+                                           VALUES (:block_number, :idx, :transaction_hash, :from_address, :keys, :data)",
+                    named_params![
+                        ":block_number": 0,
+                        ":idx": idx,
+                        ":transaction_hash": &[0u8; 32][..],
+                        ":from_address": &[0u8; 32][..],
+                        ":keys": keys,
+                        ":data": &[0u8; 32][..],
+                    ],
+                )
+                .unwrap();
+        }
+
+        let action = super::migrate(&transaction).unwrap();
+        assert_eq!(action, PostMigrationAction::None);
+
+        let mut stmt = transaction
+            .prepare("SELECT key0 FROM starknet_events ORDER BY idx")
+            .unwrap();
+        let key0s = stmt
+            .query_map([], |r| r.get::<_, Option<Vec<u8>>>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(key0s, vec![Some(key0.to_vec()), None]);
+    }
+}
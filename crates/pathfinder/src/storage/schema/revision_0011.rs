@@ -0,0 +1,57 @@
+use anyhow::Context;
+use rusqlite::Transaction;
+
+use crate::storage::schema::PostMigrationAction;
+
+/// This schema migration adds support for dictionary based compression of contract code.
+///
+/// Dictionaries are stored in their own table and referenced by `contract_code.dictionary_id`.
+/// The column is `NULL` for contracts compressed without a dictionary, which includes all
+/// contracts stored prior to this migration.
+pub(crate) fn migrate(transaction: &Transaction) -> anyhow::Result<PostMigrationAction> {
+    transaction
+        .execute(
+            r"CREATE TABLE contract_code_dictionaries (
+    id         INTEGER PRIMARY KEY,
+    dictionary BLOB NOT NULL
+)",
+            [],
+        )
+        .context("Create contract_code_dictionaries table")?;
+
+    transaction
+        .execute(
+            "ALTER TABLE contract_code ADD COLUMN dictionary_id INTEGER REFERENCES contract_code_dictionaries(id)",
+            [],
+        )
+        .context("Add dictionary_id column to contract_code table")?;
+
+    Ok(PostMigrationAction::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostMigrationAction;
+    use crate::storage::schema;
+    use rusqlite::Connection;
+
+    #[test]
+    fn empty() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let transaction = conn.transaction().unwrap();
+
+        schema::revision_0001::migrate(&transaction).unwrap();
+        schema::revision_0002::migrate(&transaction).unwrap();
+        schema::revision_0003::migrate(&transaction).unwrap();
+        schema::revision_0004::migrate(&transaction).unwrap();
+        schema::revision_0005::migrate(&transaction).unwrap();
+        schema::revision_0006::migrate(&transaction).unwrap();
+        schema::revision_0007::migrate(&transaction).unwrap();
+        schema::revision_0008::migrate(&transaction).unwrap();
+        schema::revision_0009::migrate(&transaction).unwrap();
+        schema::revision_0010::migrate(&transaction).unwrap();
+
+        let action = super::migrate(&transaction).unwrap();
+        assert_eq!(action, PostMigrationAction::None);
+    }
+}
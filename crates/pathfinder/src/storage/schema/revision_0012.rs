@@ -0,0 +1,54 @@
+use anyhow::Context;
+use rusqlite::Transaction;
+
+use crate::storage::schema::PostMigrationAction;
+
+/// This schema migration adds the `contract_nonces` table, which tracks the nonce of each
+/// contract per block in which it changed.
+///
+/// The nonce of a contract at a given block is the one of its latest row at or before that block.
+/// There is deliberately no foreign key on `starknet_blocks`: pruning old blocks must not delete
+/// the rows which still define the nonces of the remaining blocks. Reorgs delete rows explicitly.
+pub(crate) fn migrate(transaction: &Transaction) -> anyhow::Result<PostMigrationAction> {
+    transaction
+        .execute(
+            r"CREATE TABLE contract_nonces (
+    contract_address BLOB NOT NULL,
+    block_number     INTEGER NOT NULL,
+    nonce            BLOB NOT NULL,
+    PRIMARY KEY(contract_address, block_number)
+)",
+            [],
+        )
+        .context("Create contract_nonces table")?;
+
+    Ok(PostMigrationAction::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostMigrationAction;
+    use crate::storage::schema;
+    use rusqlite::Connection;
+
+    #[test]
+    fn empty() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let transaction = conn.transaction().unwrap();
+
+        schema::revision_0001::migrate(&transaction).unwrap();
+        schema::revision_0002::migrate(&transaction).unwrap();
+        schema::revision_0003::migrate(&transaction).unwrap();
+        schema::revision_0004::migrate(&transaction).unwrap();
+        schema::revision_0005::migrate(&transaction).unwrap();
+        schema::revision_0006::migrate(&transaction).unwrap();
+        schema::revision_0007::migrate(&transaction).unwrap();
+        schema::revision_0008::migrate(&transaction).unwrap();
+        schema::revision_0009::migrate(&transaction).unwrap();
+        schema::revision_0010::migrate(&transaction).unwrap();
+        schema::revision_0011::migrate(&transaction).unwrap();
+
+        let action = super::migrate(&transaction).unwrap();
+        assert_eq!(action, PostMigrationAction::None);
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::Context;
+use rusqlite::{named_params, Transaction};
+
+use crate::storage::schema::PostMigrationAction;
+
+// This is a copy of the message data structures and their serialization specification as of
+// revision 12. We have to keep these intact so that future changes to these types
+// do not break database upgrades.
+mod transaction {
+    use crate::{
+        core::{
+            ContractAddress, EntryPoint, EthereumAddress, L1ToL2MessageNonce,
+            L1ToL2MessagePayloadElem, L2ToL1MessagePayloadElem,
+        },
+        rpc::serde::{
+            EthereumAddressAsHexStr, L1ToL2MessagePayloadElemAsDecimalStr,
+            L2ToL1MessagePayloadElemAsDecimalStr,
+        },
+    };
+    use serde::Deserialize;
+    use serde_with::serde_as;
+
+    /// Represents deserialized L1 to L2 message.
+    #[serde_as]
+    #[derive(Clone, Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct L1ToL2Message {
+        #[serde_as(as = "EthereumAddressAsHexStr")]
+        pub from_address: EthereumAddress,
+        #[serde_as(as = "Vec<L1ToL2MessagePayloadElemAsDecimalStr>")]
+        pub payload: Vec<L1ToL2MessagePayloadElem>,
+        pub selector: EntryPoint,
+        pub to_address: ContractAddress,
+        #[serde(default)]
+        pub nonce: Option<L1ToL2MessageNonce>,
+    }
+
+    /// Represents deserialized L2 to L1 message.
+    #[serde_as]
+    #[derive(Clone, Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct L2ToL1Message {
+        pub from_address: ContractAddress,
+        #[serde_as(as = "Vec<L2ToL1MessagePayloadElemAsDecimalStr>")]
+        pub payload: Vec<L2ToL1MessagePayloadElem>,
+        #[serde_as(as = "EthereumAddressAsHexStr")]
+        pub to_address: EthereumAddress,
+    }
+
+    /// The message fields of a deserialized L2 transaction receipt, all other fields are ignored.
+    #[derive(Clone, Debug, Deserialize, PartialEq)]
+    pub struct Receipt {
+        pub l1_to_l2_consumed_message: Option<L1ToL2Message>,
+        pub l2_to_l1_messages: Vec<L2ToL1Message>,
+    }
+}
+
+/// This schema migration adds the `starknet_messages` table, which stores the L1-to-L2 message
+/// consumed by and the L2-to-L1 messages sent by each transaction, and fills it from the
+/// receipts of the existing transactions.
+///
+/// Payloads are stored as the concatenation of their 32 byte elements, as is done for event data.
+pub(crate) fn migrate(transaction: &Transaction) -> anyhow::Result<PostMigrationAction> {
+    transaction
+        .execute_batch(
+            r"CREATE TABLE starknet_messages (
+    block_number     INTEGER NOT NULL,
+    transaction_hash BLOB NOT NULL,
+    -- Either 'L1_TO_L2' or 'L2_TO_L1'
+    direction        TEXT NOT NULL,
+    -- Index of the message amongst the transaction's messages of the same direction
+    idx              INTEGER NOT NULL,
+    from_address     BLOB NOT NULL,
+    to_address       BLOB NOT NULL,
+    payload          BLOB NOT NULL,
+    -- Only set for L1-to-L2 messages
+    selector         BLOB,
+    nonce            BLOB,
+    FOREIGN KEY(block_number) REFERENCES starknet_blocks(number)
+    ON DELETE CASCADE
+);
+
+CREATE INDEX starknet_messages_transaction_hash ON starknet_messages(transaction_hash);",
+        )
+        .context("Create starknet_messages table")?;
+
+    let todo: usize = transaction
+        .query_row("SELECT count(1) FROM starknet_transactions", [], |r| {
+            r.get(0)
+        })
+        .context("Count rows in starknet transactions table")?;
+
+    if todo == 0 {
+        return Ok(PostMigrationAction::None);
+    }
+
+    tracing::info!(
+        num_transactions=%todo,
+        "Decompressing and migrating messages, this may take a while.",
+    );
+
+    let mut stmt = transaction
+        .prepare(
+            "SELECT starknet_transactions.hash as hash, starknet_blocks.number as block_number, receipt
+            FROM starknet_transactions
+            JOIN starknet_blocks ON starknet_transactions.block_hash = starknet_blocks.hash",
+        )
+        .context("Prepare transaction query")?;
+    let mut rows = stmt.query([])?;
+
+    while let Some(r) = rows.next()? {
+        let transaction_hash = r.get_ref_unwrap("hash").as_blob()?;
+        let block_number = r.get_ref_unwrap("block_number").as_i64()?;
+        let receipt = r.get_ref_unwrap("receipt").as_blob()?;
+
+        let receipt = zstd::decode_all(receipt).context("Decompress receipt")?;
+        let receipt: transaction::Receipt =
+            serde_json::de::from_slice(&receipt).context("Deserializing transaction receipt")?;
+
+        if let Some(message) = receipt.l1_to_l2_consumed_message {
+            let payload = message
+                .payload
+                .iter()
+                .flat_map(|e| (*e.0.as_be_bytes()).into_iter())
+                .collect::<Vec<_>>();
+
+            transaction.execute(
+                r"INSERT INTO starknet_messages ( block_number,  transaction_hash,  direction,  idx,  from_address,  to_address,  payload,  selector,  nonce)
+                                         VALUES (:block_number, :transaction_hash, 'L1_TO_L2', 0,   :from_address, :to_address, :payload, :selector, :nonce)",
+                named_params![
+                    ":block_number": block_number,
+                    ":transaction_hash": transaction_hash,
+                    ":from_address": message.from_address.0.as_bytes(),
+                    ":to_address": &message.to_address.0.as_be_bytes()[..],
+                    ":payload": &payload,
+                    ":selector": &message.selector.0.as_be_bytes()[..],
+                    ":nonce": message.nonce.as_ref().map(|nonce| &nonce.0.as_be_bytes()[..]),
+                ],
+            )
+            .context("Insert L1-to-L2 message into messages table")?;
+        }
+
+        for (idx, message) in receipt.l2_to_l1_messages.into_iter().enumerate() {
+            let payload = message
+                .payload
+                .iter()
+                .flat_map(|e| (*e.0.as_be_bytes()).into_iter())
+                .collect::<Vec<_>>();
+
+            transaction.execute(
+                r"INSERT INTO starknet_messages ( block_number,  transaction_hash,  direction,  idx,  from_address,  to_address,  payload)
+                                         VALUES (:block_number, :transaction_hash, 'L2_TO_L1', :idx, :from_address, :to_address, :payload)",
+                named_params![
+                    ":block_number": block_number,
+                    ":transaction_hash": transaction_hash,
+                    ":idx": idx,
+                    ":from_address": &message.from_address.0.as_be_bytes()[..],
+                    ":to_address": message.to_address.0.as_bytes(),
+                    ":payload": &payload,
+                ],
+            )
+            .context("Insert L2-to-L1 message into messages table")?;
+        }
+    }
+
+    Ok(PostMigrationAction::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostMigrationAction;
+    use crate::storage::schema;
+    use rusqlite::Connection;
+
+    #[test]
+    fn empty() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let transaction = conn.transaction().unwrap();
+
+        schema::revision_0001::migrate(&transaction).unwrap();
+        schema::revision_0002::migrate(&transaction).unwrap();
+        schema::revision_0003::migrate(&transaction).unwrap();
+        schema::revision_0004::migrate(&transaction).unwrap();
+        schema::revision_0005::migrate(&transaction).unwrap();
+        schema::revision_0006::migrate(&transaction).unwrap();
+        schema::revision_0007::migrate(&transaction).unwrap();
+        schema::revision_0008::migrate(&transaction).unwrap();
+        schema::revision_0009::migrate(&transaction).unwrap();
+        schema::revision_0010::migrate(&transaction).unwrap();
+        schema::revision_0011::migrate(&transaction).unwrap();
+        schema::revision_0012::migrate(&transaction).unwrap();
+
+        let action = super::migrate(&transaction).unwrap();
+        assert_eq!(action, PostMigrationAction::None);
+    }
+}
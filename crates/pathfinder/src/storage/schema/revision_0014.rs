@@ -0,0 +1,55 @@
+use anyhow::Context;
+use rusqlite::Transaction;
+
+use crate::storage::schema::PostMigrationAction;
+
+/// This schema migration adds the `starknet_raw_blocks` table, which stores the JSON of blocks
+/// exactly as received from the sequencer, if their retention is enabled.
+///
+/// Only blocks synced after this migration are stored, the existing ones are not refetched.
+pub(crate) fn migrate(transaction: &Transaction) -> anyhow::Result<PostMigrationAction> {
+    transaction
+        .execute(
+            r"CREATE TABLE starknet_raw_blocks (
+    block_number INTEGER PRIMARY KEY,
+    -- zstd compressed JSON
+    data         BLOB NOT NULL,
+    FOREIGN KEY(block_number) REFERENCES starknet_blocks(number)
+    ON DELETE CASCADE
+)",
+            [],
+        )
+        .context("Create starknet_raw_blocks table")?;
+
+    Ok(PostMigrationAction::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostMigrationAction;
+    use crate::storage::schema;
+    use rusqlite::Connection;
+
+    #[test]
+    fn empty() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let transaction = conn.transaction().unwrap();
+
+        schema::revision_0001::migrate(&transaction).unwrap();
+        schema::revision_0002::migrate(&transaction).unwrap();
+        schema::revision_0003::migrate(&transaction).unwrap();
+        schema::revision_0004::migrate(&transaction).unwrap();
+        schema::revision_0005::migrate(&transaction).unwrap();
+        schema::revision_0006::migrate(&transaction).unwrap();
+        schema::revision_0007::migrate(&transaction).unwrap();
+        schema::revision_0008::migrate(&transaction).unwrap();
+        schema::revision_0009::migrate(&transaction).unwrap();
+        schema::revision_0010::migrate(&transaction).unwrap();
+        schema::revision_0011::migrate(&transaction).unwrap();
+        schema::revision_0012::migrate(&transaction).unwrap();
+        schema::revision_0013::migrate(&transaction).unwrap();
+
+        let action = super::migrate(&transaction).unwrap();
+        assert_eq!(action, PostMigrationAction::None);
+    }
+}
@@ -1,16 +1,19 @@
 use anyhow::Context;
 use pedersen::StarkHash;
 use rusqlite::{named_params, params, Connection, OptionalExtension, Transaction};
-use web3::types::H256;
+use web3::types::{H160, H256};
 
 use crate::{
     core::{
-        ContractAddress, ContractHash, ContractRoot, ContractStateHash, EthereumBlockHash,
-        EthereumBlockNumber, EthereumLogIndex, EthereumTransactionHash, EthereumTransactionIndex,
-        EventData, EventKey, GasPrice, GlobalRoot, SequencerAddress, StarknetBlockHash,
-        StarknetBlockNumber, StarknetBlockTimestamp, StarknetTransactionHash,
+        ContractAddress, ContractHash, ContractNonce, ContractRoot, ContractStateHash, EntryPoint,
+        EthereumAddress, EthereumBlockHash, EthereumBlockNumber, EthereumLogIndex,
+        EthereumTransactionHash, EthereumTransactionIndex, EventData, EventKey, GasPrice,
+        GlobalRoot, L1ToL2MessageNonce, L1ToL2MessagePayloadElem, L2ToL1MessagePayloadElem,
+        SequencerAddress, StarknetBlockHash, StarknetBlockNumber, StarknetBlockTimestamp,
+        StarknetTransactionHash, StarknetTransactionIndex,
     },
     ethereum::{log::StateUpdateLog, BlockOrigin, EthOrigin, TransactionOrigin},
+    rpc::types::request::KeyMatchMode,
     sequencer::reply::transaction,
 };
 
@@ -251,7 +254,7 @@ impl StarknetBlocksTable {
                 ":number": block.number.0,
                 ":hash": block.hash.0.as_be_bytes(),
                 ":root": block.root.0.as_be_bytes(),
-                ":timestamp": block.timestamp.0,
+                ":timestamp": block.timestamp.as_secs(),
                 ":gas_price": &block.gas_price.to_be_bytes(),
                 ":sequencer_address": block.sequencer_address.0.as_be_bytes(),
             },
@@ -302,7 +305,7 @@ impl StarknetBlocksTable {
                 let root = GlobalRoot(root);
 
                 let timestamp = row.get_ref_unwrap("timestamp").as_i64().unwrap() as u64;
-                let timestamp = StarknetBlockTimestamp(timestamp);
+                let timestamp = StarknetBlockTimestamp::from_unix_secs(timestamp);
 
                 let gas_price = row.get_ref_unwrap("gas_price").as_blob().unwrap();
                 let gas_price = GasPrice::from_be_slice(gas_price).unwrap();
@@ -371,6 +374,34 @@ impl StarknetBlocksTable {
         Ok(())
     }
 
+    /// Deletes all blocks with `number < below` and returns their [roots](GlobalRoot),
+    /// in ascending block order.
+    ///
+    /// Note that this does not delete the blocks' transactions, see
+    /// [StarknetTransactionsTable::prune].
+    pub fn prune(
+        connection: &Connection,
+        below: StarknetBlockNumber,
+    ) -> anyhow::Result<Vec<GlobalRoot>> {
+        let mut statement = connection
+            .prepare("SELECT root FROM starknet_blocks WHERE number < ? ORDER BY number ASC")?;
+        let mut rows = statement.query(params![below.0])?;
+
+        let mut roots = Vec::new();
+        while let Some(row) = rows.next().context("Iterate rows")? {
+            let root = row.get_ref_unwrap("root").as_blob().unwrap();
+            let root = StarkHash::from_be_slice(root).unwrap();
+            roots.push(GlobalRoot(root));
+        }
+
+        connection.execute(
+            "DELETE FROM starknet_blocks WHERE number < ?",
+            params![below.0],
+        )?;
+
+        Ok(roots)
+    }
+
     /// Returns the [number](StarknetBlockNumber) of the latest block.
     pub fn get_latest_number(
         connection: &Connection,
@@ -388,6 +419,30 @@ impl StarknetBlocksTable {
             None => Ok(None),
         }
     }
+
+    /// Returns the numbers and hashes of the `count` most recent blocks, latest first.
+    pub fn get_latest_hashes(
+        connection: &Connection,
+        count: usize,
+    ) -> anyhow::Result<Vec<(StarknetBlockNumber, StarknetBlockHash)>> {
+        let mut statement = connection
+            .prepare("SELECT number, hash FROM starknet_blocks ORDER BY number DESC LIMIT ?")?;
+        let mut rows = statement.query(params![count])?;
+
+        let mut hashes = Vec::new();
+        while let Some(row) = rows.next().context("Iterate rows")? {
+            let number = row.get_ref_unwrap("number").as_i64().unwrap() as u64;
+            let number = StarknetBlockNumber(number);
+
+            let hash = row.get_ref_unwrap("hash").as_blob().unwrap();
+            let hash = StarkHash::from_be_slice(hash).unwrap();
+            let hash = StarknetBlockHash(hash);
+
+            hashes.push((number, hash));
+        }
+
+        Ok(hashes)
+    }
 }
 
 /// Identifies block in some [StarknetBlocksTable] queries.
@@ -410,12 +465,28 @@ impl From<StarknetBlockHash> for StarknetBlocksBlockId {
     }
 }
 
+/// A transaction whose stored data could not be read, i.e. because it no longer
+/// deserializes after a change to the transaction types.
+#[derive(Debug)]
+pub struct UnreadableTransaction {
+    pub hash: StarknetTransactionHash,
+    /// Index of the transaction within its block.
+    pub index: u64,
+    pub error: anyhow::Error,
+}
+
 /// Stores all known starknet transactions
 pub struct StarknetTransactionsTable {}
 impl StarknetTransactionsTable {
     /// Inserts a Starknet block's transactions and transaction receipts into the [StarknetTransactionsTable].
     ///
-    /// overwrites existing data if the transaction hash already exists.
+    /// If a transaction hash already exists (e.g. the transaction got re-included in a different
+    /// block after a reorg), its block association, index and receipt are replaced by the new ones
+    /// and the events and messages of its previous inclusion are removed. The transaction is therefore
+    /// only ever associated with the latest block it was inserted for.
+    ///
+    /// This should be called within a database transaction, so that the transactions and their
+    /// events are replaced atomically.
     pub fn upsert(
         connection: &Connection,
         block_hash: StarknetBlockHash,
@@ -441,6 +512,30 @@ impl StarknetTransactionsTable {
                 .compress(&serialized_receipt)
                 .context("Compress Starknet transaction receipt")?;
 
+            // Drop the events of a previous inclusion of this transaction, these would
+            // otherwise remain associated with the old block.
+            let exists = connection
+                .query_row(
+                    "SELECT 1 FROM starknet_transactions WHERE hash = ?",
+                    params![transaction.transaction_hash.0.as_be_bytes()],
+                    |_| Ok(()),
+                )
+                .optional()
+                .context("Check for existing transaction")?
+                .is_some();
+            if exists {
+                connection
+                    .execute(
+                        "DELETE FROM starknet_events WHERE transaction_hash = ?",
+                        params![&transaction.transaction_hash.0.as_be_bytes()[..]],
+                    )
+                    .context("Delete events of existing transaction")?;
+                StarknetMessagesTable::delete_transaction_messages(
+                    connection,
+                    transaction.transaction_hash,
+                )?;
+            }
+
             connection.execute(r"INSERT OR REPLACE INTO starknet_transactions (hash, idx, block_hash, tx, receipt) VALUES (:hash, :idx, :block_hash, :tx, :receipt)",
         named_params![
                     ":hash": transaction.transaction_hash.0.as_be_bytes(),
@@ -457,35 +552,68 @@ impl StarknetTransactionsTable {
                 transaction,
                 &receipt.events,
             )?;
+
+            StarknetMessagesTable::insert_messages(connection, block_number, transaction, receipt)?;
         }
 
         Ok(())
     }
 
+    /// Deletes the transactions and receipts of all blocks with `number < below`.
+    ///
+    /// Transactions are only linked to their block by hash, so this must be called
+    /// before the blocks themselves are deleted.
+    pub fn prune(connection: &Connection, below: StarknetBlockNumber) -> anyhow::Result<()> {
+        connection.execute(
+            "DELETE FROM starknet_transactions WHERE block_hash IN (SELECT hash FROM starknet_blocks WHERE number < ?)",
+            params![below.0],
+        )?;
+        Ok(())
+    }
+
     pub fn get_transaction_data_for_block(
         connection: &Connection,
         block: StarknetBlocksBlockId,
     ) -> anyhow::Result<Vec<(transaction::Transaction, transaction::Receipt)>> {
+        let (data, unreadable) = Self::get_partial_transaction_data_for_block(connection, block)?;
+
+        match unreadable.into_iter().next() {
+            Some(unreadable) => Err(unreadable.error),
+            None => Ok(data),
+        }
+    }
+
+    /// Same as [StarknetTransactionsTable::get_transaction_data_for_block], except that
+    /// transactions whose stored data cannot be read are skipped instead of failing the
+    /// entire block. The skipped transactions are returned separately.
+    #[allow(clippy::type_complexity)]
+    pub fn get_partial_transaction_data_for_block(
+        connection: &Connection,
+        block: StarknetBlocksBlockId,
+    ) -> anyhow::Result<(
+        Vec<(transaction::Transaction, transaction::Receipt)>,
+        Vec<UnreadableTransaction>,
+    )> {
         // Identify block hash
         let block_hash = match block {
             StarknetBlocksBlockId::Number(number) => {
                 match StarknetBlocksTable::get(connection, number.into())? {
                     Some(block) => block.hash,
-                    None => return Ok(Vec::new()),
+                    None => return Ok((Vec::new(), Vec::new())),
                 }
             }
             StarknetBlocksBlockId::Hash(hash) => hash,
             StarknetBlocksBlockId::Latest => {
                 match StarknetBlocksTable::get(connection, StarknetBlocksBlockId::Latest)? {
                     Some(block) => block.hash,
-                    None => return Ok(Vec::new()),
+                    None => return Ok((Vec::new(), Vec::new())),
                 }
             }
         };
 
         let mut stmt = connection
             .prepare(
-                "SELECT tx, receipt FROM starknet_transactions WHERE block_hash = ? ORDER BY idx ASC",
+                "SELECT hash, idx, tx, receipt FROM starknet_transactions WHERE block_hash = ? ORDER BY idx ASC",
             )
             .context("Preparing statement")?;
 
@@ -494,27 +622,48 @@ impl StarknetTransactionsTable {
             .context("Executing query")?;
 
         let mut data = Vec::new();
+        let mut unreadable = Vec::new();
         while let Some(row) = rows.next()? {
-            let receipt = row
-                .get_ref_unwrap("receipt")
-                .as_blob_or_null()?
-                .context("Receipt data missing")?;
-            let receipt = zstd::decode_all(receipt).context("Decompressing transaction receipt")?;
-            let receipt = serde_json::de::from_slice(&receipt)
-                .context("Deserializing transaction receipt")?;
-
-            let transaction = row
-                .get_ref_unwrap("tx")
-                .as_blob_or_null()?
-                .context("Transaction data missing")?;
-            let transaction = zstd::decode_all(transaction).context("Decompressing transaction")?;
-            let transaction =
-                serde_json::de::from_slice(&transaction).context("Deserializing transaction")?;
+            let read_row = || -> anyhow::Result<_> {
+                let receipt = row
+                    .get_ref_unwrap("receipt")
+                    .as_blob_or_null()?
+                    .context("Receipt data missing")?;
+                let receipt =
+                    zstd::decode_all(receipt).context("Decompressing transaction receipt")?;
+                let receipt = serde_json::de::from_slice(&receipt)
+                    .context("Deserializing transaction receipt")?;
+
+                let transaction = row
+                    .get_ref_unwrap("tx")
+                    .as_blob_or_null()?
+                    .context("Transaction data missing")?;
+                let transaction =
+                    zstd::decode_all(transaction).context("Decompressing transaction")?;
+                let transaction = serde_json::de::from_slice(&transaction)
+                    .context("Deserializing transaction")?;
+
+                Ok((transaction, receipt))
+            };
 
-            data.push((transaction, receipt));
+            match read_row() {
+                Ok(transaction_and_receipt) => data.push(transaction_and_receipt),
+                Err(error) => {
+                    let hash = row.get_ref_unwrap("hash").as_blob()?;
+                    let hash =
+                        StarkHash::from_be_slice(hash).context("Parsing transaction hash")?;
+                    let index = row.get_ref_unwrap("idx").as_i64()?;
+
+                    unreadable.push(UnreadableTransaction {
+                        hash: StarknetTransactionHash(hash),
+                        index: index as u64,
+                        error,
+                    });
+                }
+            }
         }
 
-        Ok(data)
+        Ok((data, unreadable))
     }
 
     pub fn get_transaction_at_block(
@@ -596,6 +745,33 @@ impl StarknetTransactionsTable {
         Ok(Some((receipt, block_hash)))
     }
 
+    /// Returns the index of a transaction within its block and the hash of that block.
+    pub fn get_transaction_index(
+        connection: &Connection,
+        transaction: StarknetTransactionHash,
+    ) -> anyhow::Result<Option<(StarknetTransactionIndex, StarknetBlockHash)>> {
+        let row = connection
+            .query_row(
+                "SELECT idx, block_hash FROM starknet_transactions WHERE hash = ?1",
+                params![transaction.0.as_be_bytes()],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)),
+            )
+            .optional()
+            .context("Reading transaction index")?;
+
+        let (index, block_hash) = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let block_hash =
+            StarkHash::from_be_slice(&block_hash).context("Deserializing block hash")?;
+
+        Ok(Some((
+            StarknetTransactionIndex(index as u64),
+            StarknetBlockHash(block_hash),
+        )))
+    }
+
     pub fn get_transaction(
         connection: &Connection,
         transaction: StarknetTransactionHash,
@@ -663,21 +839,51 @@ impl StarknetTransactionsTable {
 pub struct StarknetEventFilter {
     pub from_block: Option<StarknetBlockNumber>,
     pub to_block: Option<StarknetBlockNumber>,
-    pub contract_address: Option<ContractAddress>,
+    /// Only events of blocks with at least this timestamp match.
+    pub from_timestamp: Option<StarknetBlockTimestamp>,
+    /// Only events of blocks with at most this timestamp match.
+    pub to_timestamp: Option<StarknetBlockTimestamp>,
+    /// Events emitted by any of these contracts match, all events match if [None].
+    pub contract_addresses: Option<Vec<ContractAddress>>,
+    /// Events emitted by this contract are left out.
+    pub exclude_address: Option<ContractAddress>,
     pub keys: Vec<EventKey>,
+    pub key_match: KeyMatchMode,
     pub page_size: usize,
     pub page_number: usize,
+    /// The offset of the first event of the page, from a [PageOfEvents::continuation_token].
+    /// Takes precedence over the page number.
+    pub continuation_token: Option<usize>,
+}
+
+impl StarknetEventFilter {
+    /// The offset of the first event of the page within all matching events.
+    pub fn offset(&self) -> usize {
+        self.continuation_token
+            .unwrap_or(self.page_number * self.page_size)
+    }
 }
 
 impl From<crate::rpc::types::request::EventFilter> for StarknetEventFilter {
     fn from(filter: crate::rpc::types::request::EventFilter) -> Self {
         Self {
             from_block: filter.from_block,
-            to_block: filter.to_block,
-            contract_address: filter.address,
+            to_block: match filter.to_block {
+                Some(crate::rpc::types::BlockNumberOrTag::Number(number)) => Some(number),
+                // Tags refer to the most recent blocks, which leaves the range open-ended.
+                Some(crate::rpc::types::BlockNumberOrTag::Tag(_)) | None => None,
+            },
+            from_timestamp: filter.from_timestamp,
+            to_timestamp: filter.to_timestamp,
+            contract_addresses: filter.address.map(|address| address.addresses().to_vec()),
+            exclude_address: filter.exclude_address,
             keys: filter.keys,
-            page_size: filter.page_size,
+            key_match: filter.key_match,
+            page_size: filter
+                .page_size
+                .unwrap_or(StarknetEventsTable::DEFAULT_PAGE_SIZE),
             page_number: filter.page_number,
+            continuation_token: filter.continuation_token,
         }
     }
 }
@@ -696,16 +902,30 @@ pub struct StarknetEmittedEvent {
 pub enum EventFilterError {
     #[error("requested page size is too big, supported maximum is {0}")]
     PageSizeTooBig(usize),
+    #[error("too many keys in filter, supported maximum is {0}")]
+    TooManyKeys(usize),
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct PageOfEvents {
     pub events: Vec<StarknetEmittedEvent>,
     pub is_last_page: bool,
+    /// Set if the query was cut short by its deadline, in which case the page contains fewer
+    /// events than its size. Continues the page when passed on in the filter.
+    pub continuation_token: Option<usize>,
 }
 
 pub struct StarknetEventsTable {}
 impl StarknetEventsTable {
+    /// Deletes the events of all blocks with `number < below`.
+    pub fn prune(connection: &Connection, below: StarknetBlockNumber) -> anyhow::Result<()> {
+        connection.execute(
+            "DELETE FROM starknet_events WHERE block_number < ?",
+            params![below.0],
+        )?;
+        Ok(())
+    }
+
     pub fn event_data_to_bytes(data: &[EventData]) -> Vec<u8> {
         data.iter()
             .flat_map(|e| (*e.0.as_be_bytes()).into_iter())
@@ -716,6 +936,16 @@ impl StarknetEventsTable {
         base64::encode(key.0.as_be_bytes())
     }
 
+    /// Returns the upper case hex digits of the key without leading zeros, which is
+    /// what prefixes are compared against in [KeyMatchMode::Prefix] mode.
+    pub fn event_key_hex_digits(key: &EventKey) -> String {
+        key.0
+            .to_hex_str()
+            .trim_start_matches("0x")
+            .trim_start_matches('0')
+            .to_uppercase()
+    }
+
     pub fn event_keys_to_base64_strings(keys: &[EventKey]) -> String {
         // TODO: we really should be using Iterator::intersperse() here once it's stabilized.
         let keys: Vec<String> = keys.iter().map(Self::event_key_to_base64_string).collect();
@@ -731,14 +961,15 @@ impl StarknetEventsTable {
         for (idx, event) in events.iter().enumerate() {
             connection
                 .execute(
-                    r"INSERT INTO starknet_events ( block_number,  idx,  transaction_hash,  from_address,  keys,  data)
-                                           VALUES (:block_number, :idx, :transaction_hash, :from_address, :keys, :data)",
+                    r"INSERT INTO starknet_events ( block_number,  idx,  transaction_hash,  from_address,  keys,  key0,  data)
+                                           VALUES (:block_number, :idx, :transaction_hash, :from_address, :keys, :key0, :data)",
                     named_params![
                         ":block_number": block_number.0,
                         ":idx": idx,
                         ":transaction_hash": &transaction.transaction_hash.0.as_be_bytes()[..],
                         ":from_address": &transaction.contract_address.0.as_be_bytes()[..],
                         ":keys": Self::event_keys_to_base64_strings(&event.keys),
+                        ":key0": event.keys.first().map(|key| &key.0.as_be_bytes()[..]),
                         ":data": Self::event_data_to_bytes(&event.data),
                     ],
                 )
@@ -747,12 +978,96 @@ impl StarknetEventsTable {
         Ok(())
     }
 
+    /// Counts the events of a block grouped by their first key, ordered by key.
+    ///
+    /// Events without any keys are not counted.
+    pub fn get_key0_counts(
+        connection: &Connection,
+        block_number: StarknetBlockNumber,
+    ) -> anyhow::Result<Vec<(EventKey, u64)>> {
+        let mut statement = connection
+            .prepare(
+                "SELECT key0, count(1) FROM starknet_events
+                WHERE block_number = ? AND key0 IS NOT NULL
+                GROUP BY key0
+                ORDER BY key0",
+            )
+            .context("Preparing statement")?;
+
+        let mut rows = statement
+            .query(params![block_number.0])
+            .context("Executing query")?;
+
+        let mut counts = Vec::new();
+        while let Some(row) = rows.next().context("Iterating over rows")? {
+            let key = row.get_ref_unwrap(0).as_blob()?;
+            let key = StarkHash::from_be_slice(key).context("Deserializing event key")?;
+            let count = row.get_ref_unwrap(1).as_i64()? as u64;
+
+            counts.push((EventKey(key), count));
+        }
+
+        Ok(counts)
+    }
+
     pub(crate) const PAGE_SIZE_LIMIT: usize = 1024;
+    /// The default page size of event filters which do not specify one.
+    pub const DEFAULT_PAGE_SIZE: usize = 64;
+    /// The default maximum number of keys in an event filter.
+    ///
+    /// Each key is bound as a separate SQL parameter, so this keeps queries well below
+    /// SQLite's parameter limit.
+    pub const KEY_COUNT_LIMIT: usize = 256;
+    /// The default duration above which event queries are logged as slow,
+    /// see [Self::time_query].
+    pub const DEFAULT_SLOW_QUERY_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(1);
+
+    /// Runs `query` for `filter`, logging a warning if it takes `threshold` or longer.
+    ///
+    /// The warning describes the shape of the filter only. Keys are logged by their count, as
+    /// their values would mostly be noise.
+    pub fn time_query<T>(
+        name: &'static str,
+        filter: &StarknetEventFilter,
+        threshold: std::time::Duration,
+        query: impl FnOnce() -> T,
+    ) -> T {
+        let started = std::time::Instant::now();
+        let result = query();
+        let elapsed = started.elapsed();
+
+        if elapsed >= threshold {
+            tracing::warn!(
+                query = name,
+                from_block = ?filter.from_block.map(|block| block.0),
+                to_block = ?filter.to_block.map(|block| block.0),
+                from_timestamp = ?filter.from_timestamp.map(|timestamp| timestamp.as_secs()),
+                to_timestamp = ?filter.to_timestamp.map(|timestamp| timestamp.as_secs()),
+                address_count = ?filter.contract_addresses.as_ref().map(Vec::len),
+                has_exclude_address = filter.exclude_address.is_some(),
+                key_count = filter.keys.len(),
+                key_match = ?filter.key_match,
+                page_size = filter.page_size,
+                page_number = filter.page_number,
+                elapsed_ms = elapsed.as_millis() as u64,
+                "Slow event query"
+            );
+        }
 
-    pub fn get_events(
-        connection: &Connection,
+        result
+    }
+
+    /// Builds the SQL query and its named parameters for the given filter.
+    ///
+    /// Only the page size and page number are not bound, see [Self::get_events].
+    ///
+    /// Every value taken from the filter is bound as a parameter, never interpolated into the
+    /// query. The FTS5 match expression is bound too, and consists solely of quoted base64 strings,
+    /// whose alphabet cannot terminate the quotes.
+    #[allow(clippy::type_complexity)]
+    fn event_query(
         filter: &StarknetEventFilter,
-    ) -> anyhow::Result<PageOfEvents> {
+    ) -> (String, Vec<(String, Box<dyn rusqlite::ToSql>)>) {
         let mut base_query =
             r#"SELECT
                   block_number,
@@ -762,53 +1077,186 @@ impl StarknetEventsTable {
                   data,
                   starknet_events.keys as keys
                FROM starknet_events
-               INNER JOIN starknet_blocks ON starknet_blocks.number = starknet_events.block_number "#
+               INNER JOIN starknet_blocks ON starknet_blocks.number = starknet_events.block_number
+               LEFT JOIN starknet_transactions ON starknet_transactions.hash = starknet_events.transaction_hash "#
                 .to_string();
-        let mut where_statement_parts: Vec<&'static str> = Vec::new();
-        let mut params: Vec<(&str, &dyn rusqlite::ToSql)> = Vec::new();
+        let mut where_statement_parts: Vec<String> = Vec::new();
+        let mut params: Vec<(String, Box<dyn rusqlite::ToSql>)> = Vec::new();
 
         // filter on block range
         match (&filter.from_block, &filter.to_block) {
             (Some(from_block), Some(to_block)) => {
-                where_statement_parts.push("block_number BETWEEN :from_block AND :to_block");
-                params.push((":from_block", &from_block.0));
-                params.push((":to_block", &to_block.0));
+                where_statement_parts.push("block_number BETWEEN :from_block AND :to_block".into());
+                params.push((":from_block".into(), Box::new(from_block.0)));
+                params.push((":to_block".into(), Box::new(to_block.0)));
             }
             (Some(from_block), None) => {
-                where_statement_parts.push("block_number >= :from_block");
-                params.push((":from_block", &from_block.0));
+                where_statement_parts.push("block_number >= :from_block".into());
+                params.push((":from_block".into(), Box::new(from_block.0)));
             }
             (None, Some(to_block)) => {
-                where_statement_parts.push("block_number <= :to_block");
-                params.push((":to_block", &to_block.0));
+                where_statement_parts.push("block_number <= :to_block".into());
+                params.push((":to_block".into(), Box::new(to_block.0)));
             }
             (None, None) => {}
         }
 
-        // filter on contract address
-        if let Some(contract_address) = &filter.contract_address {
-            where_statement_parts.push("from_address = :contract_address");
-            params.push((":contract_address", contract_address.0.as_be_bytes()))
+        // filter on the timestamps of the blocks, which are stored in seconds
+        if let Some(from_timestamp) = filter.from_timestamp {
+            where_statement_parts.push("starknet_blocks.timestamp >= :from_timestamp".into());
+            params.push((":from_timestamp".into(), Box::new(from_timestamp.as_secs())));
+        }
+        if let Some(to_timestamp) = filter.to_timestamp {
+            where_statement_parts.push("starknet_blocks.timestamp <= :to_timestamp".into());
+            params.push((":to_timestamp".into(), Box::new(to_timestamp.as_secs())));
+        }
+
+        // filter on contract addresses
+        let mut address_params: Vec<String> = Vec::new();
+        if let Some(contract_addresses) = &filter.contract_addresses {
+            // Duplicate addresses don't match any additional events, they would only add
+            // parameters.
+            let mut contract_addresses = contract_addresses.clone();
+            contract_addresses.sort();
+            contract_addresses.dedup();
+
+            address_params = (0..contract_addresses.len())
+                .map(|i| format!(":contract_address_{}", i))
+                .collect();
+            where_statement_parts.push(format!("from_address IN ({})", address_params.join(", ")));
+            for (name, address) in address_params.iter().zip(&contract_addresses) {
+                params.push((name.clone(), Box::new(address.0.as_be_bytes().to_vec())));
+            }
+        }
+
+        // filter out an excluded contract address
+        if let Some(exclude_address) = &filter.exclude_address {
+            where_statement_parts.push("from_address <> :exclude_address".into());
+            params.push((
+                ":exclude_address".into(),
+                Box::new(exclude_address.0.as_be_bytes().to_vec()),
+            ))
         }
 
-        // Filter on keys: this is using an FTS5 full-text index (virtual table) on the keys.
-        // The idea is that we convert keys to a space-separated list of Bas64 encoded string
-        // representation and then use the full-text index to find events matching the events.
-        // HACK: make sure key_fts_expression lives long enough
-        let key_fts_expression;
-        if !filter.keys.is_empty() {
-            let base64_keys: Vec<String> = filter
-                .keys
+        // Duplicate keys don't match any additional events, they would only add parameters.
+        let mut keys = filter.keys.clone();
+        keys.sort();
+        keys.dedup();
+
+        if !keys.is_empty() && filter.key_match == KeyMatchMode::Prefix {
+            // Prefixes only apply to the first key. The hex digits only consist of `0-9A-F`,
+            // so the GLOB pattern contains no special characters other than the trailing `*`.
+            let prefix_params: Vec<String> = (0..keys.len())
+                .map(|i| format!(":key0_prefix_{}", i))
+                .collect();
+            where_statement_parts.push(format!(
+                "key0 IS NOT NULL AND ({})",
+                prefix_params
+                    .iter()
+                    .map(|name| format!("ltrim(hex(key0), '0') GLOB {}", name))
+                    .collect::<Vec<_>>()
+                    .join(" OR ")
+            ));
+            for (name, key) in prefix_params.into_iter().zip(&keys) {
+                let pattern = format!("{}*", Self::event_key_hex_digits(key));
+                params.push((name, Box::new(pattern)));
+            }
+        } else if !keys.is_empty() {
+            // Filter on keys: this is using an FTS5 full-text index (virtual table) on the keys.
+            // The idea is that we convert keys to a space-separated list of Bas64 encoded string
+            // representation and then use the full-text index to find events matching the events.
+            let base64_keys: Vec<String> = keys
                 .iter()
                 .map(|key| format!("\"{}\"", Self::event_key_to_base64_string(key)))
                 .collect();
-            key_fts_expression = base64_keys.join(" OR ");
-
-            base_query.push_str("INNER JOIN starknet_events_keys ON starknet_events.rowid = starknet_events_keys.rowid");
-            where_statement_parts.push("starknet_events_keys.keys MATCH :events_match");
-            params.push((":events_match", &key_fts_expression));
+            let key_fts_expression = base64_keys.join(" OR ");
+            params.push((":events_match".into(), Box::new(key_fts_expression)));
+
+            if !address_params.is_empty() {
+                // Events are most commonly filtered on their first key, which lets us use the
+                // compound (from_address, key0) index. Matches on any of the other keys still
+                // have to be looked up in the full-text index, so that the results stay the same.
+                // The two lookups are combined with a UNION, as an OR across them would keep
+                // SQLite from using the compound index.
+                let key0_params: Vec<String> =
+                    (0..keys.len()).map(|i| format!(":key0_{}", i)).collect();
+                where_statement_parts.push(format!(
+                    "starknet_events.rowid IN (
+                        SELECT rowid FROM starknet_events WHERE from_address IN ({}) AND key0 IN ({})
+                        UNION
+                        SELECT rowid FROM starknet_events_keys WHERE keys MATCH :events_match
+                    )",
+                    address_params.join(", "),
+                    key0_params.join(", ")
+                ));
+                for (name, key) in key0_params.into_iter().zip(&keys) {
+                    params.push((name, Box::new(key.0.as_be_bytes().to_vec())));
+                }
+            } else {
+                base_query.push_str("INNER JOIN starknet_events_keys ON starknet_events.rowid = starknet_events_keys.rowid");
+                where_statement_parts.push("starknet_events_keys.keys MATCH :events_match".into());
+            }
         }
 
+        // Paging relies on a total order, events are ordered the way they were emitted. The
+        // transaction hash only breaks ties for events whose transaction has been pruned.
+        let order_by = "ORDER BY block_number, starknet_transactions.idx, transaction_hash, starknet_events.idx";
+
+        let query = if where_statement_parts.is_empty() {
+            format!("{} {} LIMIT :limit OFFSET :offset", base_query, order_by)
+        } else {
+            format!(
+                "{} WHERE {} {} LIMIT :limit OFFSET :offset",
+                base_query,
+                where_statement_parts.join(" AND "),
+                order_by,
+            )
+        };
+
+        (query, params)
+    }
+
+    /// Returns the number of events matching the filter across all pages.
+    pub fn event_count(
+        connection: &Connection,
+        filter: &StarknetEventFilter,
+    ) -> anyhow::Result<usize> {
+        let (query, owned_params) = Self::event_query(filter);
+        let query = format!("SELECT count(1) FROM ({})", query);
+        // A negative limit means there is no limit.
+        let limit = -1i64;
+        let offset = 0i64;
+        let mut params: Vec<(&str, &dyn rusqlite::ToSql)> = owned_params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_ref()))
+            .collect();
+        params.push((":limit", &limit));
+        params.push((":offset", &offset));
+
+        let count: i64 = connection
+            .query_row(&query, params.as_slice(), |row| row.get(0))
+            .context("Counting events")?;
+
+        Ok(count as usize)
+    }
+
+    pub fn get_events(
+        connection: &Connection,
+        filter: &StarknetEventFilter,
+    ) -> anyhow::Result<PageOfEvents> {
+        Self::get_events_until(connection, filter, None)
+    }
+
+    /// Same as [Self::get_events], except that once the `deadline` has passed, the events
+    /// fetched so far are returned as a partial page with a
+    /// [continuation token](PageOfEvents::continuation_token).
+    ///
+    /// At least one event is always returned, so that continuing the query makes progress.
+    pub fn get_events_until(
+        connection: &Connection,
+        filter: &StarknetEventFilter,
+        deadline: Option<std::time::Instant>,
+    ) -> anyhow::Result<PageOfEvents> {
         // Paging
         if filter.page_size > Self::PAGE_SIZE_LIMIT {
             return Err(EventFilterError::PageSizeTooBig(Self::PAGE_SIZE_LIMIT).into());
@@ -816,182 +1264,561 @@ impl StarknetEventsTable {
         if filter.page_size < 1 {
             anyhow::bail!("Invalid page size");
         }
-        let offset = filter.page_number * filter.page_size;
+        let offset = filter.offset();
         // We have to be able to decide if there are more events. We request one extra event
         // above the requested page size, so that we can decide.
         let limit = filter.page_size + 1;
+
+        let (query, owned_params) = Self::event_query(filter);
+        let mut params: Vec<(&str, &dyn rusqlite::ToSql)> = owned_params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_ref()))
+            .collect();
         params.push((":limit", &limit));
         params.push((":offset", &offset));
 
-        let query = if where_statement_parts.is_empty() {
-            format!(
-                "{} ORDER BY block_number, transaction_hash, idx LIMIT :limit OFFSET :offset",
-                base_query
-            )
-        } else {
-            format!(
-                "{} WHERE {} ORDER BY block_number, transaction_hash, idx LIMIT :limit OFFSET :offset",
-                base_query,
-                where_statement_parts.join(" AND "),
-            )
-        };
-
         let mut statement = connection.prepare(&query).context("Preparing SQL query")?;
         let mut rows = statement
             .query(params.as_slice())
             .context("Executing SQL query")?;
 
         let mut is_last_page = true;
+        let mut continuation_token = None;
         let mut emitted_events = Vec::new();
         while let Some(row) = rows.next().context("Fetching next event")? {
-            let block_number = row.get_ref_unwrap("block_number").as_i64().unwrap() as u64;
-            let block_number = StarknetBlockNumber(block_number);
-
-            let block_hash = row.get_ref_unwrap("block_hash").as_blob().unwrap();
-            let block_hash = StarkHash::from_be_slice(block_hash).unwrap();
-            let block_hash = StarknetBlockHash(block_hash);
-
-            let transaction_hash = row.get_ref_unwrap("transaction_hash").as_blob().unwrap();
-            let transaction_hash = StarkHash::from_be_slice(transaction_hash).unwrap();
-            let transaction_hash = StarknetTransactionHash(transaction_hash);
-
-            let from_address = row.get_ref_unwrap("from_address").as_blob().unwrap();
-            let from_address = StarkHash::from_be_slice(from_address).unwrap();
-            let from_address = ContractAddress(from_address);
-
-            let data = row.get_ref_unwrap("data").as_blob().unwrap();
-            let data: Vec<_> = data
-                .chunks_exact(32)
-                .map(|data| {
-                    let data = StarkHash::from_be_slice(data).unwrap();
-                    EventData(data)
-                })
-                .collect();
-
-            let keys = row.get_ref_unwrap("keys").as_str().unwrap();
-            let keys: Vec<_> = keys
-                .split(' ')
-                .map(|key| {
-                    let key = StarkHash::from_be_slice(&base64::decode(key).unwrap()).unwrap();
-                    EventKey(key)
-                })
-                .collect();
-
             if emitted_events.len() == filter.page_size {
                 // We already have a full page, and are just fetching the extra event
                 // This means that there are more pages.
                 is_last_page = false;
             } else {
-                let event = StarknetEmittedEvent {
-                    data,
-                    from_address,
-                    keys,
-                    block_hash,
-                    block_number,
-                    transaction_hash,
-                };
-                emitted_events.push(event);
+                emitted_events.push(Self::emitted_event_from_row(row));
+
+                let past_deadline = deadline
+                    .map(|deadline| std::time::Instant::now() >= deadline)
+                    .unwrap_or(false);
+                if past_deadline && emitted_events.len() < filter.page_size {
+                    // Whether any events follow is unknown, the continuation finds out.
+                    is_last_page = false;
+                    continuation_token = Some(offset + emitted_events.len());
+                    break;
+                }
             }
         }
 
         Ok(PageOfEvents {
             events: emitted_events,
             is_last_page,
+            continuation_token,
         })
     }
-}
-
-/// Describes a Starknet block.
-#[derive(Debug, Clone, PartialEq)]
-pub struct StarknetBlock {
-    pub number: StarknetBlockNumber,
-    pub hash: StarknetBlockHash,
-    pub root: GlobalRoot,
-    pub timestamp: StarknetBlockTimestamp,
-    pub gas_price: GasPrice,
-    pub sequencer_address: SequencerAddress,
-}
-
-/// Stores the contract state hash along with its preimage. This is useful to
-/// map between the global state tree and the contracts tree.
-///
-/// Specifically it stores
-///
-/// - [contract state hash](ContractStateHash)
-/// - [contract hash](ContractHash)
-/// - [contract root](ContractRoot)
-pub struct ContractsStateTable {}
-
-impl ContractsStateTable {
-    /// Insert a state hash into the table, overwrites the data if the hash already exists.
-    pub fn upsert(
-        transaction: &Transaction,
-        state_hash: ContractStateHash,
-        hash: ContractHash,
-        root: ContractRoot,
-    ) -> anyhow::Result<()> {
-        transaction.execute(
-            "INSERT OR IGNORE INTO contract_states (state_hash, hash, root) VALUES (:state_hash, :hash, :root)",
-            named_params! {
-                ":state_hash": state_hash.0.to_be_bytes(),
-                ":hash": hash.0.to_be_bytes(),
-                ":root": root.0.to_be_bytes(),
-            },
-        )?;
-        Ok(())
-    }
 
-    /// Gets the root associated with the given state hash, or [None]
-    /// if it does not exist.
-    pub fn get_root(
-        transaction: &Transaction,
-        state_hash: ContractStateHash,
-    ) -> anyhow::Result<Option<ContractRoot>> {
-        let bytes: Option<Vec<u8>> = transaction
+    /// Returns the events emitted by a transaction, ordered by their index within the
+    /// transaction, or [None] if the transaction is unknown.
+    pub fn get_transaction_events(
+        connection: &Connection,
+        transaction_hash: StarknetTransactionHash,
+    ) -> anyhow::Result<Option<Vec<StarknetEmittedEvent>>> {
+        // Events are only indexed by block, so the transaction's block is looked up first.
+        let block_number = connection
             .query_row(
-                "SELECT root FROM contract_states WHERE state_hash = :state_hash",
-                named_params! {
-                    ":state_hash": state_hash.0.to_be_bytes()
-                },
-                |row| row.get("root"),
+                "SELECT starknet_blocks.number FROM starknet_transactions
+                JOIN starknet_blocks ON starknet_transactions.block_hash = starknet_blocks.hash
+                WHERE starknet_transactions.hash = ?",
+                params![transaction_hash.0.as_be_bytes()],
+                |row| row.get::<_, i64>(0),
             )
-            .optional()?;
-
-        let bytes = match bytes {
-            Some(bytes) => bytes,
+            .optional()
+            .context("Reading transaction's block number")?;
+        let block_number = match block_number {
+            Some(number) => number,
             None => return Ok(None),
         };
 
-        let bytes: [u8; 32] = match bytes.try_into() {
-            Ok(bytes) => bytes,
-            Err(bytes) => anyhow::bail!("Bad contract root length: {}", bytes.len()),
-        };
+        let mut statement = connection
+            .prepare(
+                r"SELECT
+                  block_number,
+                  starknet_blocks.hash as block_hash,
+                  transaction_hash,
+                  from_address,
+                  data,
+                  starknet_events.keys as keys
+               FROM starknet_events
+               INNER JOIN starknet_blocks ON starknet_blocks.number = starknet_events.block_number
+               WHERE block_number = ? AND transaction_hash = ?
+               ORDER BY idx",
+            )
+            .context("Preparing SQL query")?;
+        let mut rows = statement
+            .query(params![block_number, &transaction_hash.0.as_be_bytes()[..]])
+            .context("Executing SQL query")?;
 
-        let root = StarkHash::from_be_bytes(bytes)?;
-        let root = ContractRoot(root);
+        let mut events = Vec::new();
+        while let Some(row) = rows.next().context("Fetching next event")? {
+            events.push(Self::emitted_event_from_row(row));
+        }
 
-        Ok(Some(root))
+        Ok(Some(events))
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        sequencer::reply::transaction::{Event, Transaction},
-        storage::Storage,
-    };
 
-    mod contracts {
-        use super::*;
+    /// Decodes a row selecting the columns of a [StarknetEmittedEvent].
+    fn emitted_event_from_row(row: &rusqlite::Row<'_>) -> StarknetEmittedEvent {
+        let block_number = row.get_ref_unwrap("block_number").as_i64().unwrap() as u64;
+        let block_number = StarknetBlockNumber(block_number);
 
-        #[test]
-        fn get_root() {
-            let storage = Storage::in_memory().unwrap();
-            let mut connection = storage.connection().unwrap();
-            let transaction = connection.transaction().unwrap();
+        let block_hash = row.get_ref_unwrap("block_hash").as_blob().unwrap();
+        let block_hash = StarkHash::from_be_slice(block_hash).unwrap();
+        let block_hash = StarknetBlockHash(block_hash);
 
-            let state_hash = ContractStateHash(StarkHash::from_hex_str("abc").unwrap());
+        let transaction_hash = row.get_ref_unwrap("transaction_hash").as_blob().unwrap();
+        let transaction_hash = StarkHash::from_be_slice(transaction_hash).unwrap();
+        let transaction_hash = StarknetTransactionHash(transaction_hash);
+
+        let from_address = row.get_ref_unwrap("from_address").as_blob().unwrap();
+        let from_address = StarkHash::from_be_slice(from_address).unwrap();
+        let from_address = ContractAddress(from_address);
+
+        let data = row.get_ref_unwrap("data").as_blob().unwrap();
+        let data: Vec<_> = data
+            .chunks_exact(32)
+            .map(|data| {
+                let data = StarkHash::from_be_slice(data).unwrap();
+                EventData(data)
+            })
+            .collect();
+
+        let keys = row.get_ref_unwrap("keys").as_str().unwrap();
+        let keys: Vec<_> = keys
+            .split(' ')
+            .map(|key| {
+                let key = StarkHash::from_be_slice(&base64::decode(key).unwrap()).unwrap();
+                EventKey(key)
+            })
+            .collect();
+
+        StarknetEmittedEvent {
+            data,
+            from_address,
+            keys,
+            block_hash,
+            block_number,
+            transaction_hash,
+        }
+    }
+}
+
+/// Stores the L1-to-L2 message consumed by and the L2-to-L1 messages sent by each transaction.
+pub struct StarknetMessagesTable {}
+impl StarknetMessagesTable {
+    const L1_TO_L2: &'static str = "L1_TO_L2";
+    const L2_TO_L1: &'static str = "L2_TO_L1";
+
+    /// Deletes the messages of all blocks with `number < below`.
+    pub fn prune(connection: &Connection, below: StarknetBlockNumber) -> anyhow::Result<()> {
+        connection.execute(
+            "DELETE FROM starknet_messages WHERE block_number < ?",
+            params![below.0],
+        )?;
+        Ok(())
+    }
+
+    fn payload_to_bytes(payload: impl Iterator<Item = StarkHash>) -> Vec<u8> {
+        payload
+            .flat_map(|e| (*e.as_be_bytes()).into_iter())
+            .collect()
+    }
+
+    fn payload_from_bytes(bytes: &[u8]) -> anyhow::Result<Vec<StarkHash>> {
+        bytes
+            .chunks_exact(32)
+            .map(|e| StarkHash::from_be_slice(e).context("Parsing payload element"))
+            .collect()
+    }
+
+    /// Inserts the messages of a transaction receipt.
+    pub fn insert_messages(
+        connection: &Connection,
+        block_number: StarknetBlockNumber,
+        transaction: &transaction::Transaction,
+        receipt: &transaction::Receipt,
+    ) -> anyhow::Result<()> {
+        if let Some(message) = &receipt.l1_to_l2_consumed_message {
+            connection
+                .execute(
+                    r"INSERT INTO starknet_messages ( block_number,  transaction_hash,  direction,  idx,  from_address,  to_address,  payload,  selector,  nonce)
+                                             VALUES (:block_number, :transaction_hash, :direction, 0,    :from_address, :to_address, :payload, :selector, :nonce)",
+                    named_params![
+                        ":block_number": block_number.0,
+                        ":transaction_hash": &transaction.transaction_hash.0.as_be_bytes()[..],
+                        ":direction": Self::L1_TO_L2,
+                        ":from_address": message.from_address.0.as_bytes(),
+                        ":to_address": &message.to_address.0.as_be_bytes()[..],
+                        ":payload": Self::payload_to_bytes(message.payload.iter().map(|e| e.0)),
+                        ":selector": &message.selector.0.as_be_bytes()[..],
+                        ":nonce": message.nonce.as_ref().map(|nonce| &nonce.0.as_be_bytes()[..]),
+                    ],
+                )
+                .context("Insert L1-to-L2 message into messages table")?;
+        }
+
+        for (idx, message) in receipt.l2_to_l1_messages.iter().enumerate() {
+            connection
+                .execute(
+                    r"INSERT INTO starknet_messages ( block_number,  transaction_hash,  direction,  idx,  from_address,  to_address,  payload)
+                                             VALUES (:block_number, :transaction_hash, :direction, :idx, :from_address, :to_address, :payload)",
+                    named_params![
+                        ":block_number": block_number.0,
+                        ":transaction_hash": &transaction.transaction_hash.0.as_be_bytes()[..],
+                        ":direction": Self::L2_TO_L1,
+                        ":idx": idx,
+                        ":from_address": &message.from_address.0.as_be_bytes()[..],
+                        ":to_address": message.to_address.0.as_bytes(),
+                        ":payload": Self::payload_to_bytes(message.payload.iter().map(|e| e.0)),
+                    ],
+                )
+                .context("Insert L2-to-L1 message into messages table")?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the messages of a transaction.
+    pub fn delete_transaction_messages(
+        connection: &Connection,
+        transaction_hash: StarknetTransactionHash,
+    ) -> anyhow::Result<()> {
+        connection
+            .execute(
+                "DELETE FROM starknet_messages WHERE transaction_hash = ?",
+                params![&transaction_hash.0.as_be_bytes()[..]],
+            )
+            .context("Delete messages of transaction")?;
+        Ok(())
+    }
+
+    /// Returns the L1-to-L2 message consumed by and the L2-to-L1 messages sent by a transaction,
+    /// the latter in the order in which they were sent, or [None] if the transaction is unknown.
+    #[allow(clippy::type_complexity)]
+    pub fn get_transaction_messages(
+        connection: &Connection,
+        transaction_hash: StarknetTransactionHash,
+    ) -> anyhow::Result<
+        Option<(
+            Option<transaction::L1ToL2Message>,
+            Vec<transaction::L2ToL1Message>,
+        )>,
+    > {
+        let exists = connection
+            .query_row(
+                "SELECT 1 FROM starknet_transactions WHERE hash = ?",
+                params![transaction_hash.0.as_be_bytes()],
+                |_| Ok(()),
+            )
+            .optional()
+            .context("Check for transaction")?
+            .is_some();
+        if !exists {
+            return Ok(None);
+        }
+
+        let mut statement = connection
+            .prepare(
+                r"SELECT direction, from_address, to_address, payload, selector, nonce
+                FROM starknet_messages
+                WHERE transaction_hash = ?
+                ORDER BY direction, idx",
+            )
+            .context("Preparing SQL query")?;
+        let mut rows = statement
+            .query(params![&transaction_hash.0.as_be_bytes()[..]])
+            .context("Executing SQL query")?;
+
+        let mut consumed = None;
+        let mut sent = Vec::new();
+        while let Some(row) = rows.next().context("Fetching next message")? {
+            let direction = row.get_ref_unwrap("direction").as_str()?;
+            let from_address = row.get_ref_unwrap("from_address").as_blob()?;
+            let to_address = row.get_ref_unwrap("to_address").as_blob()?;
+            let payload = row.get_ref_unwrap("payload").as_blob()?;
+            let payload = Self::payload_from_bytes(payload)?;
+
+            match direction {
+                Self::L1_TO_L2 => {
+                    let selector = row
+                        .get_ref_unwrap("selector")
+                        .as_blob_or_null()?
+                        .context("Selector missing")?;
+                    let selector =
+                        StarkHash::from_be_slice(selector).context("Parsing selector")?;
+                    let nonce = row
+                        .get_ref_unwrap("nonce")
+                        .as_blob_or_null()?
+                        .map(|nonce| StarkHash::from_be_slice(nonce).context("Parsing nonce"))
+                        .transpose()?;
+                    let to_address =
+                        StarkHash::from_be_slice(to_address).context("Parsing to address")?;
+
+                    consumed = Some(transaction::L1ToL2Message {
+                        from_address: EthereumAddress(H160::from_slice(from_address)),
+                        payload: payload.into_iter().map(L1ToL2MessagePayloadElem).collect(),
+                        selector: EntryPoint(selector),
+                        to_address: ContractAddress(to_address),
+                        nonce: nonce.map(L1ToL2MessageNonce),
+                    });
+                }
+                Self::L2_TO_L1 => {
+                    let from_address =
+                        StarkHash::from_be_slice(from_address).context("Parsing from address")?;
+
+                    sent.push(transaction::L2ToL1Message {
+                        from_address: ContractAddress(from_address),
+                        payload: payload.into_iter().map(L2ToL1MessagePayloadElem).collect(),
+                        to_address: EthereumAddress(H160::from_slice(to_address)),
+                    });
+                }
+                other => anyhow::bail!("Unknown message direction {}", other),
+            }
+        }
+
+        Ok(Some((consumed, sent)))
+    }
+}
+
+/// Stores the JSON of blocks exactly as received from the sequencer, for debugging purposes.
+///
+/// Rows are deleted together with their block.
+pub struct StarknetRawBlocksTable {}
+impl StarknetRawBlocksTable {
+    /// Stores the raw JSON of the block with the given number, which must already be stored.
+    pub fn insert(
+        connection: &Connection,
+        block_number: StarknetBlockNumber,
+        raw: &[u8],
+    ) -> anyhow::Result<()> {
+        let data = zstd::bulk::compress(raw, 10).context("Compressing raw block")?;
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO starknet_raw_blocks (block_number, data) VALUES (?, ?)",
+                params![block_number.0, &data],
+            )
+            .context("Insert raw block")?;
+        Ok(())
+    }
+
+    /// Returns the raw JSON of the block with the given hash, or [None] if the block is unknown
+    /// or its JSON was not retained.
+    pub fn get(
+        connection: &Connection,
+        block_hash: StarknetBlockHash,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let data: Option<Vec<u8>> = connection
+            .query_row(
+                "SELECT data FROM starknet_raw_blocks
+                JOIN starknet_blocks ON starknet_raw_blocks.block_number = starknet_blocks.number
+                WHERE starknet_blocks.hash = ?",
+                params![block_hash.0.as_be_bytes()],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Query raw block")?;
+
+        data.map(|data| zstd::decode_all(&*data).context("Decompressing raw block"))
+            .transpose()
+    }
+}
+
+/// Describes a Starknet block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StarknetBlock {
+    pub number: StarknetBlockNumber,
+    pub hash: StarknetBlockHash,
+    pub root: GlobalRoot,
+    pub timestamp: StarknetBlockTimestamp,
+    pub gas_price: GasPrice,
+    pub sequencer_address: SequencerAddress,
+}
+
+/// Stores the contract state hash along with its preimage. This is useful to
+/// map between the global state tree and the contracts tree.
+///
+/// Specifically it stores
+///
+/// - [contract state hash](ContractStateHash)
+/// - [contract hash](ContractHash)
+/// - [contract root](ContractRoot)
+pub struct ContractsStateTable {}
+
+impl ContractsStateTable {
+    /// Insert a state hash into the table, overwrites the data if the hash already exists.
+    pub fn upsert(
+        transaction: &Transaction,
+        state_hash: ContractStateHash,
+        hash: ContractHash,
+        root: ContractRoot,
+    ) -> anyhow::Result<()> {
+        transaction.execute(
+            "INSERT OR IGNORE INTO contract_states (state_hash, hash, root) VALUES (:state_hash, :hash, :root)",
+            named_params! {
+                ":state_hash": state_hash.0.to_be_bytes(),
+                ":hash": hash.0.to_be_bytes(),
+                ":root": root.0.to_be_bytes(),
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Gets the root associated with the given state hash, or [None]
+    /// if it does not exist.
+    pub fn get_root(
+        transaction: &Transaction,
+        state_hash: ContractStateHash,
+    ) -> anyhow::Result<Option<ContractRoot>> {
+        let bytes: Option<Vec<u8>> = transaction
+            .query_row(
+                "SELECT root FROM contract_states WHERE state_hash = :state_hash",
+                named_params! {
+                    ":state_hash": state_hash.0.to_be_bytes()
+                },
+                |row| row.get("root"),
+            )
+            .optional()?;
+
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+
+        let bytes: [u8; 32] = match bytes.try_into() {
+            Ok(bytes) => bytes,
+            Err(bytes) => anyhow::bail!("Bad contract root length: {}", bytes.len()),
+        };
+
+        let root = StarkHash::from_be_bytes(bytes)?;
+        let root = ContractRoot(root);
+
+        Ok(Some(root))
+    }
+
+    /// Returns the state hashes and roots of all contract states which are no longer
+    /// a leaf of the global state tree, i.e. because all blocks referencing them have
+    /// been pruned.
+    pub fn get_orphaned(
+        transaction: &Transaction,
+    ) -> anyhow::Result<Vec<(ContractStateHash, ContractRoot)>> {
+        // The global state tree stores its leaves by value, which is the contract state hash.
+        let mut statement = transaction.prepare(
+            "SELECT state_hash, root FROM contract_states WHERE state_hash NOT IN (SELECT hash FROM tree_global)",
+        )?;
+        let mut rows = statement.query([])?;
+
+        let mut orphaned = Vec::new();
+        while let Some(row) = rows.next().context("Iterate rows")? {
+            let state_hash = row.get_ref_unwrap("state_hash").as_blob().unwrap();
+            let state_hash = StarkHash::from_be_slice(state_hash).unwrap();
+            let state_hash = ContractStateHash(state_hash);
+
+            let root = row.get_ref_unwrap("root").as_blob().unwrap();
+            let root = StarkHash::from_be_slice(root).unwrap();
+            let root = ContractRoot(root);
+
+            orphaned.push((state_hash, root));
+        }
+
+        Ok(orphaned)
+    }
+
+    /// Deletes the given contract state.
+    pub fn delete(transaction: &Transaction, state_hash: ContractStateHash) -> anyhow::Result<()> {
+        transaction.execute(
+            "DELETE FROM contract_states WHERE state_hash = ?",
+            params![state_hash.0.as_be_bytes()],
+        )?;
+        Ok(())
+    }
+}
+
+/// Stores the nonces of contracts, one row per contract and block in which its nonce changed.
+///
+/// The nonce of a contract in the state of a block is the one of its latest row at or
+/// before that block. Rows are not removed when blocks are pruned, as they still define the
+/// nonces of the remaining blocks.
+pub struct ContractNoncesTable {}
+
+impl ContractNoncesTable {
+    /// Sets the nonce of the contract as of the given block.
+    pub fn insert(
+        connection: &Connection,
+        contract_address: ContractAddress,
+        block_number: StarknetBlockNumber,
+        nonce: ContractNonce,
+    ) -> anyhow::Result<()> {
+        connection.execute(
+            "INSERT OR REPLACE INTO contract_nonces (contract_address, block_number, nonce) VALUES (:contract_address, :block_number, :nonce)",
+            named_params! {
+                ":contract_address": contract_address.0.as_be_bytes(),
+                ":block_number": block_number.0,
+                ":nonce": nonce.0.as_be_bytes(),
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Gets the nonce of the contract in the state of the given block, or [None] if
+    /// its nonce has not changed at or before that block.
+    pub fn get_at(
+        connection: &Connection,
+        contract_address: ContractAddress,
+        block_number: StarknetBlockNumber,
+    ) -> anyhow::Result<Option<ContractNonce>> {
+        let bytes: Option<Vec<u8>> = connection
+            .query_row(
+                "SELECT nonce FROM contract_nonces WHERE contract_address = :contract_address AND block_number <= :block_number ORDER BY block_number DESC LIMIT 1",
+                named_params! {
+                    ":contract_address": contract_address.0.as_be_bytes(),
+                    ":block_number": block_number.0,
+                },
+                |row| row.get("nonce"),
+            )
+            .optional()?;
+
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+
+        let nonce = StarkHash::from_be_slice(&bytes).context("Parse contract nonce")?;
+
+        Ok(Some(ContractNonce(nonce)))
+    }
+
+    /// Deletes all rows where `block_number >= reorg_tail`.
+    pub fn reorg(connection: &Connection, reorg_tail: StarknetBlockNumber) -> anyhow::Result<()> {
+        connection.execute(
+            "DELETE FROM contract_nonces WHERE block_number >= ?",
+            params![reorg_tail.0],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sequencer::reply::transaction::{Event, Transaction},
+        storage::Storage,
+    };
+
+    mod contracts {
+        use super::*;
+
+        #[test]
+        fn get_root() {
+            let storage = Storage::in_memory().unwrap();
+            let mut connection = storage.connection().unwrap();
+            let transaction = connection.transaction().unwrap();
+
+            let state_hash = ContractStateHash(StarkHash::from_hex_str("abc").unwrap());
             let hash = ContractHash(StarkHash::from_hex_str("123").unwrap());
             let root = ContractRoot(StarkHash::from_hex_str("def").unwrap());
 
@@ -1003,6 +1830,62 @@ mod tests {
         }
     }
 
+    mod contract_nonces {
+        use super::*;
+
+        #[test]
+        fn get_at() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let blocks = crate::storage::test_utils::create_blocks::<3>();
+            for block in &blocks {
+                StarknetBlocksTable::insert(&connection, block).unwrap();
+            }
+
+            let address = ContractAddress(StarkHash::from_hex_str("abc").unwrap());
+            let nonce1 = ContractNonce(StarkHash::from_hex_str("1").unwrap());
+            let nonce2 = ContractNonce(StarkHash::from_hex_str("2").unwrap());
+            ContractNoncesTable::insert(&connection, address, blocks[1].number, nonce1).unwrap();
+            ContractNoncesTable::insert(&connection, address, blocks[2].number, nonce2).unwrap();
+
+            let nonces = blocks
+                .iter()
+                .map(|block| ContractNoncesTable::get_at(&connection, address, block.number))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(nonces, vec![None, Some(nonce1), Some(nonce2)]);
+
+            let other = ContractAddress(StarkHash::from_hex_str("def").unwrap());
+            assert_eq!(
+                ContractNoncesTable::get_at(&connection, other, blocks[2].number).unwrap(),
+                None
+            );
+        }
+
+        #[test]
+        fn reorg() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let blocks = crate::storage::test_utils::create_blocks::<2>();
+            for block in &blocks {
+                StarknetBlocksTable::insert(&connection, block).unwrap();
+            }
+
+            let address = ContractAddress(StarkHash::from_hex_str("abc").unwrap());
+            let nonce1 = ContractNonce(StarkHash::from_hex_str("1").unwrap());
+            ContractNoncesTable::insert(&connection, address, blocks[1].number, nonce1).unwrap();
+
+            ContractNoncesTable::reorg(&connection, blocks[1].number).unwrap();
+
+            assert_eq!(
+                ContractNoncesTable::get_at(&connection, address, blocks[1].number).unwrap(),
+                None
+            );
+        }
+    }
+
     mod refs {
         use super::*;
 
@@ -1552,6 +2435,40 @@ mod tests {
                 );
             }
         }
+
+        #[test]
+        fn get_latest_hashes() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            assert_eq!(
+                StarknetBlocksTable::get_latest_hashes(&connection, 2).unwrap(),
+                Vec::new()
+            );
+
+            let blocks = create_blocks();
+            for block in &blocks {
+                StarknetBlocksTable::insert(&connection, block).unwrap();
+            }
+
+            assert_eq!(
+                StarknetBlocksTable::get_latest_hashes(&connection, 2).unwrap(),
+                vec![
+                    (blocks[2].number, blocks[2].hash),
+                    (blocks[1].number, blocks[1].hash)
+                ]
+            );
+
+            let expected = blocks
+                .iter()
+                .rev()
+                .map(|block| (block.number, block.hash))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                StarknetBlocksTable::get_latest_hashes(&connection, 10).unwrap(),
+                expected
+            );
+        }
     }
 
     mod starknet_events {
@@ -1609,7 +2526,7 @@ mod tests {
         const NUM_TRANSACTIONS: usize = NUM_BLOCKS * TRANSACTIONS_PER_BLOCK;
         const NUM_EVENTS: usize = NUM_BLOCKS * EVENTS_PER_BLOCK;
 
-        fn create_transactions_and_receipts(
+        pub(super) fn create_transactions_and_receipts(
         ) -> [(transaction::Transaction, transaction::Receipt); NUM_TRANSACTIONS] {
             let transactions = (0..NUM_TRANSACTIONS).map(|i| transaction::Transaction {
                 calldata: None,
@@ -1656,6 +2573,7 @@ mod tests {
                     StarkHash::from_hex_str(&"e".repeat(i + 3)).unwrap(),
                 ),
                 transaction_index: StarknetTransactionIndex(i as u64 + 2311),
+                transaction_failure_reason: None,
             });
 
             transactions
@@ -1702,381 +2620,1421 @@ mod tests {
         }
 
         #[test]
-        fn get_events_with_fully_specified_filter() {
+        fn get_events_with_fully_specified_filter() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            let expected_event = &emitted_events[1];
+            let filter = StarknetEventFilter {
+                from_block: Some(expected_event.block_number),
+                to_block: Some(expected_event.block_number),
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: Some(vec![expected_event.from_address]),
+                exclude_address: None,
+                // we're using a key which is present in _all_ events
+                keys: vec![EventKey(StarkHash::from_hex_str("deadbeef").unwrap())],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: vec![expected_event.clone()],
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+        }
+
+        #[test]
+        fn get_transaction_events() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+            let expected = &emitted_events[3];
+            assert_eq!(
+                StarknetEventsTable::get_transaction_events(&connection, expected.transaction_hash)
+                    .unwrap(),
+                Some(vec![expected.clone()])
+            );
+
+            // A block with a transaction emitting two events and one emitting none.
+            let block = StarknetBlock {
+                number: StarknetBlockNumber(NUM_BLOCKS as u64),
+                hash: StarknetBlockHash(StarkHash::from_be_slice(b"events block").unwrap()),
+                root: GlobalRoot(StarkHash::ZERO),
+                timestamp: StarknetBlockTimestamp(0),
+                gas_price: GasPrice::ZERO,
+                sequencer_address: SequencerAddress(StarkHash::ZERO),
+            };
+            StarknetBlocksTable::insert(&connection, &block).unwrap();
+            let (transaction, receipt) = create_transactions_and_receipts()[0].clone();
+            let event = |key: &[u8]| transaction::Event {
+                keys: vec![EventKey(StarkHash::from_be_slice(key).unwrap())],
+                ..receipt.events[0].clone()
+            };
+            let two_events = (
+                transaction::Transaction {
+                    transaction_hash: StarknetTransactionHash(
+                        StarkHash::from_be_slice(b"two events").unwrap(),
+                    ),
+                    ..transaction.clone()
+                },
+                transaction::Receipt {
+                    events: vec![event(b"first"), event(b"second")],
+                    ..receipt.clone()
+                },
+            );
+            let no_events = (
+                transaction::Transaction {
+                    transaction_hash: StarknetTransactionHash(
+                        StarkHash::from_be_slice(b"no events").unwrap(),
+                    ),
+                    ..transaction
+                },
+                transaction::Receipt {
+                    events: vec![],
+                    ..receipt
+                },
+            );
+            StarknetTransactionsTable::upsert(
+                &connection,
+                block.hash,
+                block.number,
+                &[two_events.clone(), no_events.clone()],
+            )
+            .unwrap();
+
+            let events = StarknetEventsTable::get_transaction_events(
+                &connection,
+                two_events.0.transaction_hash,
+            )
+            .unwrap()
+            .unwrap();
+            let keys = events.into_iter().map(|e| e.keys).collect::<Vec<_>>();
+            assert_eq!(keys, vec![event(b"first").keys, event(b"second").keys]);
+
+            assert_eq!(
+                StarknetEventsTable::get_transaction_events(
+                    &connection,
+                    no_events.0.transaction_hash
+                )
+                .unwrap(),
+                Some(vec![])
+            );
+            assert_eq!(
+                StarknetEventsTable::get_transaction_events(
+                    &connection,
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"unknown").unwrap())
+                )
+                .unwrap(),
+                None
+            );
+        }
+
+        #[test]
+        fn get_events_by_block() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            const BLOCK_NUMBER: usize = 2;
+            let filter = StarknetEventFilter {
+                from_block: Some(StarknetBlockNumber(BLOCK_NUMBER as u64)),
+                to_block: Some(StarknetBlockNumber(BLOCK_NUMBER as u64)),
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+
+            let expected_events = &emitted_events
+                [EVENTS_PER_BLOCK * BLOCK_NUMBER..EVENTS_PER_BLOCK * (BLOCK_NUMBER + 1)];
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: expected_events.to_vec(),
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+        }
+
+        #[test]
+        fn get_events_up_to_block() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            const UNTIL_BLOCK_NUMBER: usize = 2;
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: Some(StarknetBlockNumber(UNTIL_BLOCK_NUMBER as u64)),
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+
+            let expected_events =
+                &emitted_events[..TRANSACTIONS_PER_BLOCK * (UNTIL_BLOCK_NUMBER + 1)];
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: expected_events.to_vec(),
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+        }
+
+        #[test]
+        fn get_events_from_block_onwards() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            const FROM_BLOCK_NUMBER: usize = 2;
+            let filter = StarknetEventFilter {
+                from_block: Some(StarknetBlockNumber(FROM_BLOCK_NUMBER as u64)),
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+
+            let expected_events = &emitted_events[TRANSACTIONS_PER_BLOCK * FROM_BLOCK_NUMBER..];
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: expected_events.to_vec(),
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+        }
+
+        #[test]
+        fn get_events_from_contract() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            let expected_event = &emitted_events[33];
+
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: Some(vec![expected_event.from_address]),
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: vec![expected_event.clone()],
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+        }
+
+        #[test]
+        fn get_events_excluding_contract() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            let excluded_event = &emitted_events[33];
+            let expected_events = emitted_events
+                .iter()
+                .filter(|event| event.from_address != excluded_event.from_address)
+                .cloned()
+                .collect::<Vec<_>>();
+            assert_eq!(expected_events.len(), NUM_EVENTS - 1);
+
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: Some(excluded_event.from_address),
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: expected_events,
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+
+            // Composes with the positive filters.
+            let filter = StarknetEventFilter {
+                from_block: Some(excluded_event.block_number),
+                to_block: Some(excluded_event.block_number),
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: Some(excluded_event.from_address),
+                keys: vec![excluded_event.keys[0], emitted_events[32].keys[0]],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: vec![emitted_events[32].clone()],
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+
+            // Excluding the only included contract leaves nothing.
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: Some(vec![excluded_event.from_address]),
+                exclude_address: Some(excluded_event.from_address),
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: vec![],
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+        }
+
+        #[test]
+        fn get_events_by_key() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            let expected_event = &emitted_events[27];
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![expected_event.keys[0]],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: vec![expected_event.clone()],
+                    is_last_page: true,
+                    continuation_token: None,
+                }
+            );
+        }
+
+        #[test]
+        fn get_events_with_no_filter() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
             let emitted_events = setup(&connection);
 
-            let expected_event = &emitted_events[1];
             let filter = StarknetEventFilter {
-                from_block: Some(expected_event.block_number),
-                to_block: Some(expected_event.block_number),
-                contract_address: Some(expected_event.from_address),
-                // we're using a key which is present in _all_ events
-                keys: vec![EventKey(StarkHash::from_hex_str("deadbeef").unwrap())],
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
                 page_size: NUM_EVENTS,
                 page_number: 0,
+                continuation_token: None,
             };
 
             let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
             assert_eq!(
                 events,
                 PageOfEvents {
-                    events: vec![expected_event.clone()],
-                    is_last_page: true
+                    events: emitted_events,
+                    is_last_page: true,
+                    continuation_token: None,
                 }
             );
         }
 
         #[test]
-        fn get_events_by_block() {
+        fn get_events_with_no_filter_and_paging() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
             let emitted_events = setup(&connection);
 
-            const BLOCK_NUMBER: usize = 2;
             let filter = StarknetEventFilter {
-                from_block: Some(StarknetBlockNumber(BLOCK_NUMBER as u64)),
-                to_block: Some(StarknetBlockNumber(BLOCK_NUMBER as u64)),
-                contract_address: None,
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
                 keys: vec![],
-                page_size: NUM_EVENTS,
+                key_match: KeyMatchMode::Exact,
+                page_size: 10,
                 page_number: 0,
+                continuation_token: None,
+            };
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: emitted_events[..10].to_vec(),
+                    is_last_page: false,
+                    continuation_token: None,
+                }
+            );
+
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: 10,
+                page_number: 1,
+                continuation_token: None,
             };
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: emitted_events[10..20].to_vec(),
+                    is_last_page: false,
+                    continuation_token: None,
+                }
+            );
 
-            let expected_events = &emitted_events
-                [EVENTS_PER_BLOCK * BLOCK_NUMBER..EVENTS_PER_BLOCK * (BLOCK_NUMBER + 1)];
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: 10,
+                page_number: 3,
+                continuation_token: None,
+            };
             let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
             assert_eq!(
                 events,
                 PageOfEvents {
-                    events: expected_events.to_vec(),
-                    is_last_page: true
+                    events: emitted_events[30..40].to_vec(),
+                    is_last_page: true,
+                    continuation_token: None,
                 }
             );
         }
 
         #[test]
-        fn get_events_up_to_block() {
+        fn get_events_past_deadline() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
             let emitted_events = setup(&connection);
 
-            const UNTIL_BLOCK_NUMBER: usize = 2;
-            let filter = StarknetEventFilter {
+            let mut filter = StarknetEventFilter {
                 from_block: None,
-                to_block: Some(StarknetBlockNumber(UNTIL_BLOCK_NUMBER as u64)),
-                contract_address: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
                 keys: vec![],
-                page_size: NUM_EVENTS,
-                page_number: 0,
+                key_match: KeyMatchMode::Exact,
+                page_size: 10,
+                page_number: 1,
+                continuation_token: None,
             };
+            let deadline = Some(std::time::Instant::now());
+            let events =
+                StarknetEventsTable::get_events_until(&connection, &filter, deadline).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: emitted_events[10..11].to_vec(),
+                    is_last_page: false,
+                    continuation_token: Some(11),
+                }
+            );
 
-            let expected_events =
-                &emitted_events[..TRANSACTIONS_PER_BLOCK * (UNTIL_BLOCK_NUMBER + 1)];
+            // The continuation resumes the page where the deadline cut it short.
+            filter.continuation_token = events.continuation_token;
+            let events = StarknetEventsTable::get_events_until(&connection, &filter, None).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: emitted_events[11..21].to_vec(),
+                    is_last_page: false,
+                    continuation_token: None,
+                }
+            );
+        }
+
+        #[test]
+        fn get_events_with_no_filter_and_nonexistent_page() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let _emitted_events = setup(&connection);
+
+            const PAGE_SIZE: usize = 10;
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: PAGE_SIZE,
+                // one page _after_ the last one
+                page_number: NUM_BLOCKS * EVENTS_PER_BLOCK / PAGE_SIZE,
+                continuation_token: None,
+            };
             let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
             assert_eq!(
                 events,
                 PageOfEvents {
-                    events: expected_events.to_vec(),
-                    is_last_page: true
+                    events: vec![],
+                    is_last_page: true,
+                    continuation_token: None,
                 }
             );
         }
 
         #[test]
-        fn get_events_from_block_onwards() {
+        fn get_events_with_invalid_page_size() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
-            let emitted_events = setup(&connection);
+            let _emitted_events = setup(&connection);
 
-            const FROM_BLOCK_NUMBER: usize = 2;
             let filter = StarknetEventFilter {
-                from_block: Some(StarknetBlockNumber(FROM_BLOCK_NUMBER as u64)),
+                from_block: None,
                 to_block: None,
-                contract_address: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
                 keys: vec![],
-                page_size: NUM_EVENTS,
+                key_match: KeyMatchMode::Exact,
+                page_size: 0,
+                page_number: 0,
+                continuation_token: None,
+            };
+            let result = StarknetEventsTable::get_events(&connection, &filter);
+            assert!(result.is_err());
+            assert_eq!(result.unwrap_err().to_string(), "Invalid page size");
+
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: StarknetEventsTable::PAGE_SIZE_LIMIT + 1,
                 page_number: 0,
+                continuation_token: None,
             };
+            let result = StarknetEventsTable::get_events(&connection, &filter);
+            assert!(result.is_err());
+            assert_eq!(
+                result.unwrap_err().downcast::<EventFilterError>().unwrap(),
+                EventFilterError::PageSizeTooBig(StarknetEventsTable::PAGE_SIZE_LIMIT)
+            );
+        }
 
-            let expected_events = &emitted_events[TRANSACTIONS_PER_BLOCK * FROM_BLOCK_NUMBER..];
+        #[test]
+        fn get_events_by_key_with_paging() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            let expected_events = &emitted_events[27..32];
+            let keys_for_expected_events: Vec<_> =
+                expected_events.iter().map(|e| e.keys[0]).collect();
+
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: keys_for_expected_events.clone(),
+                key_match: KeyMatchMode::Exact,
+                page_size: 2,
+                page_number: 0,
+                continuation_token: None,
+            };
             let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
             assert_eq!(
                 events,
                 PageOfEvents {
-                    events: expected_events.to_vec(),
-                    is_last_page: true
+                    events: expected_events[..2].to_vec(),
+                    is_last_page: false,
+                    continuation_token: None,
+                }
+            );
+
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: keys_for_expected_events.clone(),
+                key_match: KeyMatchMode::Exact,
+                page_size: 2,
+                page_number: 1,
+                continuation_token: None,
+            };
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: expected_events[2..4].to_vec(),
+                    is_last_page: false,
+                    continuation_token: None,
+                }
+            );
+
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: keys_for_expected_events,
+                key_match: KeyMatchMode::Exact,
+                page_size: 2,
+                page_number: 2,
+                continuation_token: None,
+            };
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            assert_eq!(
+                events,
+                PageOfEvents {
+                    events: expected_events[4..].to_vec(),
+                    is_last_page: true,
+                    continuation_token: None,
                 }
             );
         }
 
         #[test]
-        fn get_events_from_contract() {
+        fn get_events_with_equal_keys_is_paging_stable() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let blocks = create_blocks();
+            let (template, template_receipt) = &create_transactions_and_receipts()[0];
+            let key = EventKey(StarkHash::from_hex_str("0xabc").unwrap());
+
+            const TRANSACTIONS: usize = 4;
+            const EVENTS_PER_TRANSACTION: usize = 3;
+
+            let mut expected_events = Vec::new();
+            for (b, block) in blocks.iter().enumerate() {
+                StarknetBlocksTable::insert(&connection, block).unwrap();
+
+                let transactions_and_receipts = (0..TRANSACTIONS)
+                    .map(|t| {
+                        // Hashes decrease with the transaction index, so that ordering by hash
+                        // would differ from the order of emission.
+                        let hash = StarknetTransactionHash(
+                            StarkHash::from_be_slice(
+                                &(0x1000u64 - (b * TRANSACTIONS + t) as u64).to_be_bytes(),
+                            )
+                            .unwrap(),
+                        );
+                        let transaction = transaction::Transaction {
+                            transaction_hash: hash,
+                            ..template.clone()
+                        };
+                        let events = (0..EVENTS_PER_TRANSACTION)
+                            .map(|e| transaction::Event {
+                                from_address: template.contract_address,
+                                data: vec![EventData(
+                                    StarkHash::from_be_slice(
+                                        &((b * 100 + t * 10 + e) as u64).to_be_bytes(),
+                                    )
+                                    .unwrap(),
+                                )],
+                                keys: vec![key],
+                            })
+                            .collect::<Vec<_>>();
+                        let receipt = transaction::Receipt {
+                            transaction_hash: hash,
+                            events,
+                            ..template_receipt.clone()
+                        };
+                        (transaction, receipt)
+                    })
+                    .collect::<Vec<_>>();
+
+                StarknetTransactionsTable::upsert(
+                    &connection,
+                    block.hash,
+                    block.number,
+                    &transactions_and_receipts,
+                )
+                .unwrap();
+
+                for (transaction, receipt) in &transactions_and_receipts {
+                    expected_events.extend(receipt.events.iter().map(|event| {
+                        StarknetEmittedEvent {
+                            data: event.data.clone(),
+                            from_address: event.from_address,
+                            keys: event.keys.clone(),
+                            block_hash: block.hash,
+                            block_number: block.number,
+                            transaction_hash: transaction.transaction_hash,
+                        }
+                    }));
+                }
+            }
+
+            // A page size which does not divide the number of events of a transaction or block.
+            let mut events = Vec::new();
+            for page_number in 0.. {
+                let filter = StarknetEventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    contract_addresses: None,
+                    exclude_address: None,
+                    keys: vec![key],
+                    key_match: KeyMatchMode::Exact,
+                    page_size: 5,
+                    page_number,
+                    continuation_token: None,
+                };
+                let page = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+                events.extend(page.events);
+                if page.is_last_page {
+                    break;
+                }
+            }
+
+            assert_eq!(events, expected_events);
+        }
+
+        #[test]
+        fn event_count_ignores_paging() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let emitted_events = setup(&connection);
+
+            let expected_events = &emitted_events[27..32];
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: expected_events.iter().map(|e| e.keys[0]).collect(),
+                key_match: KeyMatchMode::Exact,
+                page_size: 2,
+                page_number: 1,
+                continuation_token: None,
+            };
+            let count = StarknetEventsTable::event_count(&connection, &filter).unwrap();
+            assert_eq!(count, expected_events.len());
+
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![],
+                key_match: KeyMatchMode::Exact,
+                page_size: 1,
+                page_number: 0,
+                continuation_token: None,
+            };
+            let count = StarknetEventsTable::event_count(&connection, &filter).unwrap();
+            assert_eq!(count, emitted_events.len());
+        }
+
+        #[test]
+        fn get_events_by_key_prefix() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            setup(&connection);
+
+            let block = &create_blocks()[0];
+            let (transaction, _) = &create_transactions_and_receipts()[0];
+            let key = |hex: &str| EventKey(StarkHash::from_hex_str(hex).unwrap());
+            let events = [
+                vec![key("0xabc123")],
+                vec![key("0xabc4"), key("0x1")],
+                vec![key("0xabd123")],
+                // Prefixes are only matched against the first key.
+                vec![key("0x1"), key("0xabc123")],
+                // A key matches itself as a prefix.
+                vec![key("0xabc")],
+            ]
+            .into_iter()
+            .map(|keys| transaction::Event {
+                from_address: transaction.contract_address,
+                data: vec![],
+                keys,
+            })
+            .collect::<Vec<_>>();
+            StarknetEventsTable::insert_events(&connection, block.number, transaction, &events)
+                .unwrap();
+
+            let expected_keys = vec![
+                vec![key("0xabc123")],
+                vec![key("0xabc4"), key("0x1")],
+                vec![key("0xabc")],
+            ];
+
+            for contract_address in [None, Some(transaction.contract_address)] {
+                let filter = StarknetEventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    contract_addresses: contract_address.map(|address| vec![address]),
+                    exclude_address: None,
+                    keys: vec![key("0xabc")],
+                    key_match: KeyMatchMode::Prefix,
+                    page_size: NUM_EVENTS,
+                    page_number: 0,
+                    continuation_token: None,
+                };
+
+                let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+                let keys = events
+                    .events
+                    .into_iter()
+                    .map(|event| event.keys)
+                    .collect::<Vec<_>>();
+                assert_eq!(keys, expected_keys);
+            }
+
+            // Any of the prefixes may match.
+            let filter = StarknetEventFilter {
+                from_block: None,
+                to_block: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![key("0xabc1"), key("0xabd")],
+                key_match: KeyMatchMode::Prefix,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
+            };
+            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            let keys = events
+                .events
+                .into_iter()
+                .map(|event| event.keys)
+                .collect::<Vec<_>>();
+            assert_eq!(keys, vec![vec![key("0xabc123")], vec![key("0xabd123")]]);
+        }
+
+        #[test]
+        fn get_events_with_sql_metacharacters_in_keys() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
-            let emitted_events = setup(&connection);
+            let emitted_events = setup(&connection);
+
+            // Keys whose bytes are SQL and FTS5 metacharacters, the latter's base64 encoding
+            // also contains the non-alphanumeric `+` and `/` characters.
+            let evil_keys = [
+                EventKey(StarkHash::from_be_slice(b"'\"; DROP TABLE x; --").unwrap()),
+                EventKey(StarkHash::from_be_slice(b"\xfb\xff' OR 1=1 OR \"").unwrap()),
+            ];
+            let block = &create_blocks()[0];
+            let (transaction, _) = &create_transactions_and_receipts()[0];
+            let event = transaction::Event {
+                from_address: transaction.contract_address,
+                data: vec![],
+                keys: evil_keys.to_vec(),
+            };
+            StarknetEventsTable::insert_events(
+                &connection,
+                block.number,
+                transaction,
+                std::slice::from_ref(&event),
+            )
+            .unwrap();
+            let expected_event = StarknetEmittedEvent {
+                from_address: transaction.contract_address,
+                data: vec![],
+                keys: evil_keys.to_vec(),
+                block_hash: block.hash,
+                block_number: block.number,
+                transaction_hash: transaction.transaction_hash,
+            };
+
+            for key in evil_keys {
+                for contract_address in [None, Some(transaction.contract_address)] {
+                    let filter = StarknetEventFilter {
+                        from_block: None,
+                        to_block: None,
+                        from_timestamp: None,
+                        to_timestamp: None,
+                        contract_addresses: contract_address.map(|address| vec![address]),
+                        exclude_address: None,
+                        keys: vec![key],
+                        key_match: KeyMatchMode::Exact,
+                        page_size: NUM_EVENTS,
+                        page_number: 0,
+                        continuation_token: None,
+                    };
 
-            let expected_event = &emitted_events[33];
+                    let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+                    assert_eq!(
+                        events,
+                        PageOfEvents {
+                            events: vec![expected_event.clone()],
+                            is_last_page: true,
+                            continuation_token: None,
+                        }
+                    );
+                }
+            }
 
+            // All other events are still intact.
             let filter = StarknetEventFilter {
                 from_block: None,
                 to_block: None,
-                contract_address: Some(expected_event.from_address),
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
                 keys: vec![],
-                page_size: NUM_EVENTS,
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS + 1,
                 page_number: 0,
+                continuation_token: None,
             };
-
             let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
-            assert_eq!(
-                events,
-                PageOfEvents {
-                    events: vec![expected_event.clone()],
-                    is_last_page: true
-                }
-            );
+            assert_eq!(events.events.len(), emitted_events.len() + 1);
         }
 
         #[test]
-        fn get_events_by_key() {
+        fn get_events_by_address_and_key_uses_compound_index() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
             let emitted_events = setup(&connection);
 
-            let expected_event = &emitted_events[27];
             let filter = StarknetEventFilter {
                 from_block: None,
                 to_block: None,
-                contract_address: None,
-                keys: vec![expected_event.keys[0]],
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: Some(vec![emitted_events[3].from_address]),
+                exclude_address: None,
+                keys: vec![emitted_events[3].keys[0]],
+                key_match: KeyMatchMode::Exact,
                 page_size: NUM_EVENTS,
                 page_number: 0,
+                continuation_token: None,
             };
 
-            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
-            assert_eq!(
-                events,
-                PageOfEvents {
-                    events: vec![expected_event.clone()],
-                    is_last_page: true
-                }
+            let (query, params) = StarknetEventsTable::event_query(&filter);
+            let mut params: Vec<(&str, &dyn rusqlite::ToSql)> = params
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_ref()))
+                .collect();
+            params.push((":limit", &1));
+            params.push((":offset", &0));
+
+            let mut statement = connection
+                .prepare(&format!("EXPLAIN QUERY PLAN {}", query))
+                .unwrap();
+            let plan = statement
+                .query_map(params.as_slice(), |row| row.get::<_, String>("detail"))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+
+            // Both columns of the index have to be used, not just the address.
+            assert!(
+                plan.iter().any(|detail| detail
+                    .contains("starknet_events_from_address_key0 (from_address=? AND key0=?)")),
+                "compound index not used: {:?}",
+                plan
             );
         }
 
         #[test]
-        fn get_events_with_no_filter() {
+        fn get_events_by_address_and_key_matches_full_scan() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
             let emitted_events = setup(&connection);
 
-            let filter = StarknetEventFilter {
-                from_block: None,
-                to_block: None,
-                contract_address: None,
-                keys: vec![],
-                page_size: NUM_EVENTS,
-                page_number: 0,
-            };
+            // The first key is unique to each event, the second key is present in all events.
+            let deadbeef = EventKey(StarkHash::from_hex_str("deadbeef").unwrap());
+            for event in &emitted_events {
+                for keys in [
+                    vec![event.keys[0]],
+                    vec![deadbeef],
+                    vec![emitted_events[0].keys[0], event.keys[0]],
+                    vec![emitted_events[0].keys[0]],
+                ] {
+                    let filter = StarknetEventFilter {
+                        from_block: None,
+                        to_block: None,
+                        from_timestamp: None,
+                        to_timestamp: None,
+                        contract_addresses: Some(vec![event.from_address]),
+                        exclude_address: None,
+                        keys: keys.clone(),
+                        key_match: KeyMatchMode::Exact,
+                        page_size: NUM_EVENTS,
+                        page_number: 0,
+                        continuation_token: None,
+                    };
 
-            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
-            assert_eq!(
-                events,
-                PageOfEvents {
-                    events: emitted_events,
-                    is_last_page: true
+                    let expected_events = emitted_events
+                        .iter()
+                        .filter(|e| e.from_address == event.from_address)
+                        .filter(|e| e.keys.iter().any(|key| keys.contains(key)))
+                        .cloned()
+                        .collect::<Vec<_>>();
+
+                    let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+                    assert_eq!(
+                        events,
+                        PageOfEvents {
+                            events: expected_events,
+                            is_last_page: true,
+                            continuation_token: None,
+                        }
+                    );
                 }
-            );
+            }
         }
 
         #[test]
-        fn get_events_with_no_filter_and_paging() {
+        fn get_events_with_duplicate_keys() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
             let emitted_events = setup(&connection);
 
+            // The first key is unique to each event, the second key is present in all events.
+            let deadbeef = EventKey(StarkHash::from_hex_str("deadbeef").unwrap());
+            let first_key = emitted_events[0].keys[0];
+            let keys = vec![deadbeef, first_key, deadbeef, first_key, deadbeef];
+
+            for contract_address in [None, Some(emitted_events[0].from_address)] {
+                let filter = StarknetEventFilter {
+                    from_block: None,
+                    to_block: None,
+                    from_timestamp: None,
+                    to_timestamp: None,
+                    contract_addresses: contract_address.map(|address| vec![address]),
+                    exclude_address: None,
+                    keys: keys.clone(),
+                    key_match: KeyMatchMode::Exact,
+                    page_size: NUM_EVENTS,
+                    page_number: 0,
+                    continuation_token: None,
+                };
+
+                let expected_events = emitted_events
+                    .iter()
+                    .filter(|e| contract_address.map_or(true, |a| a == e.from_address))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+                assert_eq!(
+                    events,
+                    PageOfEvents {
+                        events: expected_events.clone(),
+                        is_last_page: true,
+                        continuation_token: None,
+                    }
+                );
+                assert_eq!(
+                    StarknetEventsTable::event_count(&connection, &filter).unwrap(),
+                    expected_events.len()
+                );
+            }
+
+            // Repeated prefixes match each event once too. The first keys of all events
+            // start with the first key of the first event.
             let filter = StarknetEventFilter {
                 from_block: None,
                 to_block: None,
-                contract_address: None,
-                keys: vec![],
-                page_size: 10,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![first_key, first_key],
+                key_match: KeyMatchMode::Prefix,
+                page_size: NUM_EVENTS,
                 page_number: 0,
+                continuation_token: None,
             };
             let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
             assert_eq!(
                 events,
                 PageOfEvents {
-                    events: emitted_events[..10].to_vec(),
-                    is_last_page: false
+                    events: emitted_events,
+                    is_last_page: true,
+                    continuation_token: None,
                 }
             );
+        }
 
-            let filter = StarknetEventFilter {
-                from_block: None,
-                to_block: None,
-                contract_address: None,
-                keys: vec![],
-                page_size: 10,
-                page_number: 1,
-            };
-            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
-            assert_eq!(
-                events,
-                PageOfEvents {
-                    events: emitted_events[10..20].to_vec(),
-                    is_last_page: false
+        #[test]
+        fn slow_query_is_logged() {
+            /// Collects the formatted log output.
+            #[derive(Clone, Default)]
+            struct LogBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+            impl std::io::Write for LogBuffer {
+                fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                    self.0.lock().unwrap().extend_from_slice(buf);
+                    Ok(buf.len())
                 }
-            );
 
-            let filter = StarknetEventFilter {
-                from_block: None,
-                to_block: None,
-                contract_address: None,
-                keys: vec![],
-                page_size: 10,
-                page_number: 3,
-            };
-            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
-            assert_eq!(
-                events,
-                PageOfEvents {
-                    events: emitted_events[30..40].to_vec(),
-                    is_last_page: true
+                fn flush(&mut self) -> std::io::Result<()> {
+                    Ok(())
                 }
-            );
-        }
+            }
 
-        #[test]
-        fn get_events_with_no_filter_and_nonexistent_page() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
-            let _emitted_events = setup(&connection);
+            setup(&connection);
 
-            const PAGE_SIZE: usize = 10;
             let filter = StarknetEventFilter {
-                from_block: None,
+                from_block: Some(StarknetBlockNumber(1)),
                 to_block: None,
-                contract_address: None,
-                keys: vec![],
-                page_size: PAGE_SIZE,
-                // one page _after_ the last one
-                page_number: NUM_BLOCKS * EVENTS_PER_BLOCK / PAGE_SIZE,
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
+                keys: vec![EventKey(StarkHash::from_hex_str("deadbeef").unwrap())],
+                key_match: KeyMatchMode::Exact,
+                page_size: NUM_EVENTS,
+                page_number: 0,
+                continuation_token: None,
             };
-            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
-            assert_eq!(
-                events,
-                PageOfEvents {
-                    events: vec![],
-                    is_last_page: true
-                }
-            );
+
+            let logs = |threshold| {
+                let buffer = LogBuffer::default();
+                let writer = buffer.clone();
+                let subscriber = tracing_subscriber::fmt()
+                    .with_writer(move || writer.clone())
+                    .with_ansi(false)
+                    .finish();
+
+                tracing::subscriber::with_default(subscriber, || {
+                    StarknetEventsTable::time_query("get_events", &filter, threshold, || {
+                        StarknetEventsTable::get_events(&connection, &filter)
+                    })
+                    .unwrap();
+                });
+
+                let logs = buffer.0.lock().unwrap().clone();
+                String::from_utf8(logs).unwrap()
+            };
+
+            let slow = logs(std::time::Duration::ZERO);
+            assert!(slow.contains("Slow event query"), "{}", slow);
+            assert!(slow.contains("query=\"get_events\""), "{}", slow);
+            assert!(slow.contains("from_block=Some(1)"), "{}", slow);
+            assert!(slow.contains("key_count=1"), "{}", slow);
+            // Only the number of keys is logged, not their values.
+            assert!(!slow.contains("deadbeef"), "{}", slow);
+
+            let fast = logs(std::time::Duration::from_secs(3600));
+            assert!(fast.is_empty(), "{}", fast);
         }
+    }
+
+    mod starknet_transactions {
+        use super::*;
+
+        use crate::sequencer::reply::transaction;
 
         #[test]
-        fn get_events_with_invalid_page_size() {
+        fn upsert_moves_transaction_to_latest_block() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
-            let _emitted_events = setup(&connection);
+            let blocks = crate::storage::test_utils::create_blocks::<3>();
+            for block in &blocks {
+                StarknetBlocksTable::insert(&connection, block).unwrap();
+            }
 
-            let filter = StarknetEventFilter {
-                from_block: None,
-                to_block: None,
-                contract_address: None,
-                keys: vec![],
-                page_size: 0,
-                page_number: 0,
+            let (transaction, receipt) = super::starknet_events::create_transactions_and_receipts()
+                .into_iter()
+                .next()
+                .unwrap();
+            let hash = transaction.transaction_hash;
+
+            StarknetTransactionsTable::upsert(
+                &connection,
+                blocks[1].hash,
+                blocks[1].number,
+                &[(transaction.clone(), receipt.clone())],
+            )
+            .unwrap();
+
+            let new_receipt = transaction::Receipt {
+                actual_fee: Some(crate::core::Fee(web3::types::H128::from_low_u64_be(10))),
+                ..receipt
             };
-            let result = StarknetEventsTable::get_events(&connection, &filter);
-            assert!(result.is_err());
-            assert_eq!(result.unwrap_err().to_string(), "Invalid page size");
+            StarknetTransactionsTable::upsert(
+                &connection,
+                blocks[2].hash,
+                blocks[2].number,
+                &[(transaction, new_receipt.clone())],
+            )
+            .unwrap();
+
+            let (stored_receipt, block_hash) =
+                StarknetTransactionsTable::get_receipt(&connection, hash)
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(block_hash, blocks[2].hash);
+            assert_eq!(stored_receipt.actual_fee, new_receipt.actual_fee);
+
+            assert!(StarknetTransactionsTable::get_transaction_data_for_block(
+                &connection,
+                blocks[1].number.into()
+            )
+            .unwrap()
+            .is_empty());
+            assert_eq!(
+                StarknetTransactionsTable::get_transaction_data_for_block(
+                    &connection,
+                    blocks[2].number.into()
+                )
+                .unwrap()
+                .len(),
+                1
+            );
 
+            // Only the events of the latest inclusion remain.
             let filter = StarknetEventFilter {
+                from_timestamp: None,
+                to_timestamp: None,
+                contract_addresses: None,
+                exclude_address: None,
                 from_block: None,
                 to_block: None,
-                contract_address: None,
                 keys: vec![],
-                page_size: StarknetEventsTable::PAGE_SIZE_LIMIT + 1,
+                key_match: KeyMatchMode::Exact,
+                page_size: 10,
                 page_number: 0,
+                continuation_token: None,
             };
-            let result = StarknetEventsTable::get_events(&connection, &filter);
-            assert!(result.is_err());
-            assert_eq!(
-                result.unwrap_err().downcast::<EventFilterError>().unwrap(),
-                EventFilterError::PageSizeTooBig(StarknetEventsTable::PAGE_SIZE_LIMIT)
-            );
+            let events = StarknetEventsTable::get_events(&connection, &filter)
+                .unwrap()
+                .events;
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].block_number, blocks[2].number);
+            assert_eq!(events[0].transaction_hash, hash);
         }
+    }
+
+    mod starknet_messages {
+        use super::*;
+
+        use crate::core::{
+            EntryPoint, EthereumAddress, L1ToL2MessageNonce, L1ToL2MessagePayloadElem,
+            L2ToL1MessagePayloadElem,
+        };
+        use crate::sequencer::reply::transaction;
 
         #[test]
-        fn get_events_by_key_with_paging() {
+        fn get_transaction_messages() {
             let storage = Storage::in_memory().unwrap();
             let connection = storage.connection().unwrap();
 
-            let emitted_events = setup(&connection);
-
-            let expected_events = &emitted_events[27..32];
-            let keys_for_expected_events: Vec<_> =
-                expected_events.iter().map(|e| e.keys[0]).collect();
+            let blocks = crate::storage::test_utils::create_blocks::<2>();
+            for block in &blocks {
+                StarknetBlocksTable::insert(&connection, block).unwrap();
+            }
 
-            let filter = StarknetEventFilter {
-                from_block: None,
-                to_block: None,
-                contract_address: None,
-                keys: keys_for_expected_events.clone(),
-                page_size: 2,
-                page_number: 0,
+            let mut transactions = super::starknet_events::create_transactions_and_receipts()
+                .into_iter()
+                .take(2);
+            let (with_messages, receipt) = transactions.next().unwrap();
+            let (without_messages, receipt_without_messages) = transactions.next().unwrap();
+
+            let consumed = transaction::L1ToL2Message {
+                from_address: EthereumAddress(H160::from_low_u64_be(0x1234)),
+                payload: vec![L1ToL2MessagePayloadElem(
+                    StarkHash::from_hex_str("0xa").unwrap(),
+                )],
+                selector: EntryPoint(StarkHash::from_hex_str("0xb").unwrap()),
+                to_address: with_messages.contract_address,
+                nonce: Some(L1ToL2MessageNonce(StarkHash::from_hex_str("0xc").unwrap())),
             };
-            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            let sent = (0..2u8)
+                .map(|i| transaction::L2ToL1Message {
+                    from_address: with_messages.contract_address,
+                    payload: vec![
+                        L2ToL1MessagePayloadElem(StarkHash::from_be_slice(&[i]).unwrap()),
+                        L2ToL1MessagePayloadElem(StarkHash::from_hex_str("0xdead").unwrap()),
+                    ],
+                    to_address: EthereumAddress(H160::from_low_u64_be(0x5678 + i as u64)),
+                })
+                .collect::<Vec<_>>();
+            let receipt = transaction::Receipt {
+                l1_to_l2_consumed_message: Some(consumed.clone()),
+                l2_to_l1_messages: sent.clone(),
+                ..receipt
+            };
+
+            StarknetTransactionsTable::upsert(
+                &connection,
+                blocks[0].hash,
+                blocks[0].number,
+                &[
+                    (with_messages.clone(), receipt),
+                    (without_messages.clone(), receipt_without_messages),
+                ],
+            )
+            .unwrap();
+
             assert_eq!(
-                events,
-                PageOfEvents {
-                    events: expected_events[..2].to_vec(),
-                    is_last_page: false
-                }
+                StarknetMessagesTable::get_transaction_messages(
+                    &connection,
+                    with_messages.transaction_hash
+                )
+                .unwrap(),
+                Some((Some(consumed), sent))
             );
-
-            let filter = StarknetEventFilter {
-                from_block: None,
-                to_block: None,
-                contract_address: None,
-                keys: keys_for_expected_events.clone(),
-                page_size: 2,
-                page_number: 1,
-            };
-            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
             assert_eq!(
-                events,
-                PageOfEvents {
-                    events: expected_events[2..4].to_vec(),
-                    is_last_page: false
-                }
+                StarknetMessagesTable::get_transaction_messages(
+                    &connection,
+                    without_messages.transaction_hash
+                )
+                .unwrap(),
+                Some((None, vec![]))
+            );
+            assert_eq!(
+                StarknetMessagesTable::get_transaction_messages(
+                    &connection,
+                    StarknetTransactionHash(StarkHash::from_be_slice(b"unknown").unwrap())
+                )
+                .unwrap(),
+                None
             );
 
-            let filter = StarknetEventFilter {
-                from_block: None,
-                to_block: None,
-                contract_address: None,
-                keys: keys_for_expected_events,
-                page_size: 2,
-                page_number: 2,
-            };
-            let events = StarknetEventsTable::get_events(&connection, &filter).unwrap();
+            StarknetMessagesTable::prune(&connection, blocks[1].number).unwrap();
             assert_eq!(
-                events,
-                PageOfEvents {
-                    events: expected_events[4..].to_vec(),
-                    is_last_page: true
-                }
+                StarknetMessagesTable::get_transaction_messages(
+                    &connection,
+                    with_messages.transaction_hash
+                )
+                .unwrap(),
+                Some((None, vec![]))
             );
         }
     }
@@ -2164,20 +4122,30 @@ mod tests {
             block0_number
         );
         let filter0 = StarknetEventFilter {
-            contract_address: None,
+            from_timestamp: None,
+            to_timestamp: None,
+            contract_addresses: None,
+            exclude_address: None,
             from_block: None,
             to_block: None,
             keys: vec![event0_key],
+            key_match: KeyMatchMode::Exact,
             page_size: 10,
             page_number: 0,
+            continuation_token: None,
         };
         let filter1 = StarknetEventFilter {
-            contract_address: None,
+            from_timestamp: None,
+            to_timestamp: None,
+            contract_addresses: None,
+            exclude_address: None,
             from_block: None,
             to_block: None,
             keys: vec![event1_key],
+            key_match: KeyMatchMode::Exact,
             page_size: 10,
             page_number: 0,
+            continuation_token: None,
         };
         assert_eq!(
             StarknetEventsTable::get_events(&connection, &filter0).unwrap(),
@@ -2190,7 +4158,8 @@ mod tests {
                     keys: vec![event0_key],
                     transaction_hash: transaction0_hash,
                 }],
-                is_last_page: true
+                is_last_page: true,
+                continuation_token: None,
             }
         );
         assert!(StarknetEventsTable::get_events(&connection, &filter1)
@@ -2198,4 +4167,47 @@ mod tests {
             .events
             .is_empty());
     }
+
+    mod starknet_raw_blocks {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let blocks = crate::storage::test_utils::create_blocks::<2>();
+            for block in &blocks {
+                StarknetBlocksTable::insert(&connection, block).unwrap();
+            }
+
+            let raw = br#"{"block_number": 0, "unknown_field": [1, 2]}"#;
+            StarknetRawBlocksTable::insert(&connection, blocks[0].number, raw).unwrap();
+
+            let stored = StarknetRawBlocksTable::get(&connection, blocks[0].hash).unwrap();
+            assert_eq!(stored.as_deref(), Some(&raw[..]));
+            // Not retained.
+            let stored = StarknetRawBlocksTable::get(&connection, blocks[1].hash).unwrap();
+            assert_eq!(stored, None);
+        }
+
+        #[test]
+        fn deleted_with_block() {
+            let storage = Storage::in_memory().unwrap();
+            let connection = storage.connection().unwrap();
+
+            let blocks = crate::storage::test_utils::create_blocks::<1>();
+            StarknetBlocksTable::insert(&connection, &blocks[0]).unwrap();
+            StarknetRawBlocksTable::insert(&connection, blocks[0].number, b"{}").unwrap();
+
+            StarknetBlocksTable::reorg(&connection, blocks[0].number).unwrap();
+
+            let count: i64 = connection
+                .query_row("SELECT count(1) FROM starknet_raw_blocks", [], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            assert_eq!(count, 0);
+        }
+    }
 }
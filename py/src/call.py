@@ -7,7 +7,7 @@ from starkware.starkware_utils.error_handling import WebFriendlyException
 from starkware.storage.storage import Storage
 
 # used from tests, and the query which asserts that the schema is of expected version.
-EXPECTED_SCHEMA_REVISION = 9
+EXPECTED_SCHEMA_REVISION = 14
 EXPECTED_CAIRO_VERSION = "0.8.2"
 
 
@@ -58,12 +58,25 @@ def do_loop(connection, input_gen, output_file):
     required = {
         # FIXME: this should be hash_or_latest
         "at_block": int_hash_or_latest,
+    }
+
+    optional = {
+        "pending_state_diff": pending_state_diff,
+        "trace": boolean,
+    }
+
+    # a command either has a single call at the top level, or a non-empty list
+    # of calls under "calls", which are executed in order on the same state.
+    call_required = {
         "contract_address": hash_or_int,
         "entry_point_selector": string_or_int,
         "calldata": list_of_hash_or_int,
     }
 
-    optional = {"caller_address": hash_or_int, "signature": hash_or_int}
+    call_optional = {
+        "caller_address": hash_or_int,
+        "signature": list_of_hash_or_int,
+    }
 
     for line in input_gen:
         if line == "" or line.startswith("#"):
@@ -75,7 +88,9 @@ def do_loop(connection, input_gen, output_file):
         parsed_at = None
 
         try:
-            command = parse_command(json.loads(line), required, optional)
+            command = parse_command(
+                json.loads(line), required, optional, call_required, call_optional
+            )
 
             parsed_at = time.time()
 
@@ -83,10 +98,16 @@ def do_loop(connection, input_gen, output_file):
 
             output = loop_inner(connection, command)
 
+            if command.get("trace", False):
+                # the traces carry the retdata of each call, which is the output as well
+                out["traces"] = list(map(render_call_info, output))
+                output = [call_info.retdata for call_info in output]
+
             # we need to render the retdata as hex strings, so we can just deserialize it easily
-            out["output"] = list(
-                map(lambda x: "0x" + x.to_bytes(32, "big").hex(), output)
-            )
+            if "calls" in command:
+                out["output"] = list(map(render_retdata, output))
+            else:
+                out["output"] = render_retdata(output)
         except NoSuchBlock:
             out = {"status": "error", "kind": "NO_SUCH_BLOCK"}
         except NoSuchContract:
@@ -98,11 +119,17 @@ def do_loop(connection, input_gen, output_file):
         except WebFriendlyException as e:
             # this is hopefully something we can give to the user
             out = {"status": "failed", "exception": str(e.code)}
+            if e.message is not None:
+                out["revert_reason"] = e.message
+            if hasattr(e, "failed_call_index"):
+                out["failed_call_index"] = e.failed_call_index
         except Exception as e:
             stringified = str(e)
             if len(stringified) > 200:
                 stringified = stringified[:197] + "..."
             out = {"status": "failed", "exception": stringified}
+            if hasattr(e, "failed_call_index"):
+                out["failed_call_index"] = e.failed_call_index
         finally:
             connection.rollback()
 
@@ -126,21 +153,81 @@ def loop_inner(connection, command):
 
     (block_info, global_root) = resolve_block(connection, command["at_block"])
 
-    return asyncio.run(
+    calls = command.get("calls", [command])
+
+    trace = command.get("trace", False)
+    assert not trace or "calls" in command, "only commands with calls can be traced"
+
+    outputs = asyncio.run(
         do_call(
             SqliteAdapter(connection),
             global_root,
-            command["contract_address"],
-            command["entry_point_selector"],
-            command["calldata"],
-            command.get("caller_address", 0),
-            command.get("signature", None),
+            [
+                (
+                    call["contract_address"],
+                    call["entry_point_selector"],
+                    call["calldata"],
+                    call.get("caller_address", 0),
+                    call.get("signature", None),
+                )
+                for call in calls
+            ],
             block_info,
+            command.get("pending_state_diff", None),
+            trace,
         )
     )
 
+    if "calls" in command:
+        return outputs
+
+    [output] = outputs
+    return output
+
+
+def render_retdata(retdata):
+    return list(map(render_felt, retdata))
+
+
+def render_felt(x):
+    return "0x" + x.to_bytes(32, "big").hex()
+
+
+def render_call_info(call_info):
+    """
+    Renders the cairo-lang CallInfo of a call and of the calls it made in turn.
+    """
+    selector = call_info.entry_point_selector
+    return {
+        "contract_address": render_felt(call_info.contract_address),
+        "caller_address": render_felt(call_info.caller_address),
+        "entry_point_selector": None if selector is None else render_felt(selector),
+        "calldata": render_retdata(call_info.calldata),
+        "result": render_retdata(call_info.retdata),
+        "internal_calls": list(map(render_call_info, call_info.internal_calls)),
+    }
+
+
+def parse_command(command, required, optional, call_required, call_optional):
+    if "calls" not in command:
+        return parse_keys(
+            command,
+            {**required, **call_required},
+            {**optional, **call_optional},
+        )
+
+    command = dict(command)
+    calls = command.pop("calls")
+    assert type(calls) == list and len(calls) > 0, "calls must be a non-empty list"
+
+    converted = parse_keys(command, required, optional)
+    converted["calls"] = [
+        parse_keys(call, call_required, call_optional) for call in calls
+    ]
+    return converted
+
 
-def parse_command(command, required, optional):
+def parse_keys(command, required, optional):
     # it would be nice to use marshmallow but before we can lock with
     # cairo-lang we cannot really add common dependencies
     missing = required.keys() - command.keys()
@@ -217,11 +304,39 @@ def string_or_int(s):
     raise TypeError(f"expected string or int, not {type(s)}")
 
 
+def boolean(s):
+    assert type(s) == bool, f"Expected bool, got {type(s)}"
+    return s
+
+
 def list_of_hash_or_int(s):
     assert type(s) == list, f"Expected list, got {type(s)}"
     return list(map(hash_or_int, s))
 
 
+def pending_state_diff(s):
+    """
+    Converts the pending block's state diff into a tuple of storage updates by
+    contract address and deployed contract hashes by contract address.
+    """
+    assert type(s) == dict, f"Expected dict, got {type(s)}"
+
+    storage_updates = dict()
+    for address, diffs in s["storage_diffs"].items():
+        storage_updates[hash_or_int(address)] = {
+            hash_or_int(diff["key"]): hash_or_int(diff["value"]) for diff in diffs
+        }
+
+    deployed_contracts = dict()
+    for contract in s["deployed_contracts"]:
+        contract_hash = len_safe_hex(contract["contract_hash"])
+        # contract hashes are used as keys into the contract_code table
+        contract_hash = b"\x00" * (32 - len(contract_hash)) + contract_hash
+        deployed_contracts[hash_or_int(contract["address"])] = contract_hash
+
+    return (storage_updates, deployed_contracts)
+
+
 def check_schema(connection):
     global first
     assert connection.in_transaction
@@ -382,15 +497,28 @@ class SqliteAdapter(Storage):
 
         # assert False, "we must rebuild the full json out of our columns"
         cursor = self.connection.execute(
-            "select definition from contract_code where hash = ?", [suffix]
+            """
+            select definition, dictionary
+            from contract_code
+            left join contract_code_dictionaries
+                on contract_code.dictionary_id = contract_code_dictionaries.id
+            where contract_code.hash = ?
+            """,
+            [suffix],
         )
-        [only] = next(cursor, [None])
+        [only, dictionary] = next(cursor, [None, None])
 
         if only is None:
             return None
 
-        # pathfinder stores zstd compressed json blobs
-        decompressor = zstandard.ZstdDecompressor()
+        # pathfinder stores zstd compressed json blobs, optionally compressed
+        # with a shared dictionary
+        if dictionary is None:
+            decompressor = zstandard.ZstdDecompressor()
+        else:
+            decompressor = zstandard.ZstdDecompressor(
+                dict_data=zstandard.ZstdCompressionDict(dictionary)
+            )
         only = decompressor.decompress(only)
 
         # cairo-lang expects a ContractDefinitionFact, however we store just
@@ -412,24 +540,35 @@ class SqliteAdapter(Storage):
 async def do_call(
     adapter,
     root,
-    contract_address,
-    selector,
-    calldata,
-    caller_address,
-    signature,
+    calls,
     block_info,
+    pending_state_diff=None,
+    trace=False,
 ):
     """
-    Loads all of the cairo-lang parts needed for the call. Dirties the internal
+    Loads all of the cairo-lang parts needed for the calls. Dirties the internal
     cairo-lang state which does not matter, because the state will be thrown
     out.
 
-    Returns the retdata from the call, which is the only property needed by the RPC api.
+    The calls are tuples of (contract_address, selector, calldata,
+    caller_address, signature) and are executed in order, each one seeing the
+    state changes of the previous ones. The optional pending_state_diff is
+    applied on top of the state at root before the first call is made.
+
+    Returns the retdata from each call, which is the only property needed by
+    the RPC api, or the whole CallInfo of each call if trace is set. An
+    exception raised by a call gets the index of the call as
+    `failed_call_index`.
     """
     from starkware.starknet.business_logic.state.state import (
         SharedState,
         StateSelector,
     )
+    from starkware.starknet.business_logic.state.objects import (
+        ContractCarriedState,
+        ContractState,
+    )
+    from starkware.starknet.storage.starknet_storage import StorageLeaf
     from starkware.starknet.definitions.general_config import StarknetGeneralConfig
     from starkware.storage.storage import FactFetchingContext
     from starkware.starkware_utils.commitment_tree.patricia_tree.patricia_tree import (
@@ -444,21 +583,58 @@ async def do_call(
     ffc = FactFetchingContext(storage=adapter, hash_func=pedersen_hash_func)
 
     # the root tree has to always be height=251
+    (storage_updates, deployed_contracts) = pending_state_diff or (dict(), dict())
+
     shared_state = SharedState(PatriciaTree(root=root, height=251), block_info)
-    state_selector = StateSelector(contract_addresses={contract_address})
+    # contracts deployed in the pending block cannot be found from the tree
+    state_selector = StateSelector(
+        contract_addresses=(
+            {contract_address for (contract_address, *_) in calls}
+            | storage_updates.keys()
+        )
+        - deployed_contracts.keys()
+    )
     carried_state = await shared_state.get_filled_carried_state(
         ffc, state_selector=state_selector
     )
 
+    for address, contract_hash in deployed_contracts.items():
+        # the storage of a newly deployed contract is empty, apart from the updates below
+        carried_state.contract_states[address] = ContractCarriedState(
+            state=ContractState(
+                contract_hash=contract_hash,
+                storage_commitment_tree=PatriciaTree(root=b"\x00" * 32, height=251),
+            ),
+            storage_updates=dict(),
+        )
+
+    for address, updates in storage_updates.items():
+        carried_state.contract_states[address].storage_updates.update(
+            {key: StorageLeaf(value) for key, value in updates.items()}
+        )
+
     state = StarknetState(state=carried_state, general_config=general_config)
     max_fee = 0
 
-    output = await state.invoke_raw(
-        contract_address, selector, calldata, caller_address, max_fee, signature
-    )
+    outputs = []
+
+    for (index, call) in enumerate(calls):
+        (contract_address, selector, calldata, caller_address, signature) = call
+        try:
+            output = await state.invoke_raw(
+                contract_address, selector, calldata, caller_address, max_fee, signature
+            )
+        except Exception as e:
+            e.failed_call_index = index
+            raise
+
+        if trace:
+            outputs.append(output.call_info)
+        else:
+            # this is everything we need, at least so far for the "call".
+            outputs.append(output.call_info.retdata)
 
-    # this is everything we need, at least so far for the "call".
-    return output.call_info.retdata
+    return outputs
 
 
 if __name__ == "__main__":
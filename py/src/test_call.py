@@ -68,11 +68,17 @@ def inmemory_with_tables():
             hash       BLOB NOT NULL
         );
 
+        CREATE TABLE contract_code_dictionaries (
+            id         INTEGER PRIMARY KEY,
+            dictionary BLOB NOT NULL
+        );
+
         CREATE TABLE contract_code (
-            hash       BLOB PRIMARY KEY,
-            bytecode   BLOB,
-            abi        BLOB,
-            definition BLOB
+            hash          BLOB PRIMARY KEY,
+            bytecode      BLOB,
+            abi           BLOB,
+            definition    BLOB,
+            dictionary_id INTEGER REFERENCES contract_code_dictionaries(id)
         );
 
         -- This is missing the foreign key definition
@@ -314,7 +320,9 @@ def test_nested_called_contract_not_found():
         ],
     )
 
-    # the original exception message is too long
+    # the original exception message is too long to compare in full
+    revert_reason = output.pop("revert_reason")
+    assert "is not deployed" in revert_reason
 
     assert output == {
         "status": "failed",
@@ -365,6 +373,200 @@ def test_no_such_block():
     assert latest == expected
 
 
+def test_caller_address(monkeypatch):
+    import call
+
+    con = inmemory_with_tables()
+    contract_address = populate_test_contract_with_132_on_3(con)
+
+    callers = []
+
+    async def capture_caller_address(
+        adapter,
+        root,
+        calls,
+        block_info,
+        pending_state_diff,
+    ):
+        callers.extend(caller_address for (_, _, _, caller_address, _) in calls)
+        return [[] for _ in calls]
+
+    monkeypatch.setattr(call, "do_call", capture_caller_address)
+
+    output = default_132_on_3_scenario(
+        con,
+        [
+            f'{{ "at_block": 1, "contract_address": {contract_address}, "entry_point_selector": "get_value", "calldata": [132], "caller_address": "0x1234" }}',
+            f'{{ "at_block": 1, "contract_address": {contract_address}, "entry_point_selector": "get_value", "calldata": [132] }}',
+        ],
+    )
+
+    assert output == [{"status": "ok", "output": []}] * 2
+    # the zero address is used when no caller is given
+    assert callers == [0x1234, 0]
+
+
+def test_pending_state_diff():
+    con = inmemory_with_tables()
+    contract_address = populate_test_contract_with_132_on_3(con)
+
+    pending_state_diff = {
+        "storage_diffs": {
+            hex(contract_address): [{"key": hex(133), "value": hex(5)}],
+        },
+        "deployed_contracts": [],
+    }
+
+    output = default_132_on_3_scenario(
+        con,
+        [
+            f'{{ "at_block": "latest", "contract_address": {contract_address}, "entry_point_selector": "get_value", "calldata": [133], "pending_state_diff": {json.dumps(pending_state_diff)} }}',
+            f'{{ "at_block": "latest", "contract_address": {contract_address}, "entry_point_selector": "get_value", "calldata": [132], "pending_state_diff": {json.dumps(pending_state_diff)} }}',
+            f'{{ "at_block": "latest", "contract_address": {contract_address}, "entry_point_selector": "get_value", "calldata": [133] }}',
+        ],
+    )
+
+    [pending, untouched, latest] = output
+
+    def expected(value):
+        return {"status": "ok", "output": ["0x" + value.to_bytes(32, "big").hex()]}
+
+    # the value of 133 is only set in the pending state diff
+    assert pending == expected(5)
+    assert untouched == expected(3)
+    assert latest == expected(0)
+
+
+def test_calls():
+    con = inmemory_with_tables()
+    contract_address = populate_test_contract_with_132_on_3(con)
+
+    increase = {
+        "contract_address": contract_address,
+        "entry_point_selector": "increase_value",
+        "calldata": [132, 4],
+    }
+    get = {
+        "contract_address": contract_address,
+        "entry_point_selector": "get_value",
+        "calldata": [132],
+    }
+
+    output = default_132_on_3_scenario(
+        con,
+        [
+            json.dumps({"at_block": "latest", "calls": [increase, get]}),
+            json.dumps({"at_block": "latest", "calls": [get]}),
+        ],
+    )
+
+    [increased, untouched] = output
+
+    def hexed(value):
+        return "0x" + value.to_bytes(32, "big").hex()
+
+    # the second call sees the storage write of the first one
+    assert increased == {"status": "ok", "output": [[], [hexed(7)]]}
+    # but the changes do not carry over to the next command
+    assert untouched == {"status": "ok", "output": [[hexed(3)]]}
+
+
+def test_calls_failing_call_index():
+    con = inmemory_with_tables()
+    contract_address = populate_test_contract_with_132_on_3(con)
+
+    get = {
+        "contract_address": contract_address,
+        "entry_point_selector": "get_value",
+        "calldata": [132],
+    }
+    missing_calldata = {
+        "contract_address": contract_address,
+        "entry_point_selector": "get_value",
+        "calldata": [],
+    }
+
+    output = default_132_on_3_scenario(
+        con,
+        [json.dumps({"at_block": "latest", "calls": [get, missing_calldata]})],
+    )
+
+    assert output["status"] == "failed"
+    assert output["failed_call_index"] == 1
+
+
+def test_trace():
+    con = inmemory_with_tables()
+    contract_address = populate_test_contract_with_132_on_3(con)
+
+    call_increase = {
+        "contract_address": contract_address,
+        "entry_point_selector": "call_increase_value",
+        "calldata": [contract_address, 132, 4],
+        "signature": [],
+    }
+
+    output = default_132_on_3_scenario(
+        con,
+        [json.dumps({"at_block": "latest", "calls": [call_increase], "trace": True})],
+    )
+
+    def hexed(value):
+        return "0x" + value.to_bytes(32, "big").hex()
+
+    from starkware.starknet.public.abi import get_selector_from_name
+
+    assert output == {
+        "status": "ok",
+        "output": [[]],
+        "traces": [
+            {
+                "contract_address": hexed(contract_address),
+                "caller_address": hexed(0),
+                "entry_point_selector": hexed(
+                    get_selector_from_name("call_increase_value")
+                ),
+                "calldata": [hexed(contract_address), hexed(132), hexed(4)],
+                "result": [],
+                "internal_calls": [
+                    {
+                        "contract_address": hexed(contract_address),
+                        "caller_address": hexed(contract_address),
+                        "entry_point_selector": hexed(
+                            get_selector_from_name("increase_value")
+                        ),
+                        "calldata": [hexed(132), hexed(4)],
+                        "result": [],
+                        "internal_calls": [],
+                    }
+                ],
+            }
+        ],
+    }
+
+
+def test_trace_requires_calls():
+    con = inmemory_with_tables()
+    contract_address = populate_test_contract_with_132_on_3(con)
+
+    output = default_132_on_3_scenario(
+        con,
+        [
+            json.dumps(
+                {
+                    "at_block": "latest",
+                    "contract_address": contract_address,
+                    "entry_point_selector": "get_value",
+                    "calldata": [132],
+                    "trace": True,
+                }
+            )
+        ],
+    )
+
+    assert output["status"] == "failed"
+
+
 def test_check_cairolang_version():
     # run this here as well so that we get earlier than CI feedback
     # of another constant that needs to be upgraded